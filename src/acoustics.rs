@@ -0,0 +1,68 @@
+use crate::tuning::{Tunable, Tuning};
+use crate::world::EntityKind;
+
+// #############################
+// #         ACOUSTICS         #
+// #############################
+
+// Levels are in decibels (dB re 1 uPa at 1 m), ranges in meters.
+
+/// Broadband level radiated by the propulsion plant: a base level for the
+/// kind of ship growing by one dB per meter per second of speed
+pub fn propulsion_level(kind: EntityKind, speed: f32) -> f32 {
+    let base = match kind {
+        EntityKind::Merchant => 140.0,
+        EntityKind::Warship => 135.0,
+        EntityKind::Submarine => 115.0,
+        EntityKind::Torpedo => 150.0,
+    };
+    base + speed
+}
+
+/// Spherical spreading loss in dB, ignoring absorption
+pub fn spreading_loss(range: f32) -> f32 {
+    20.0 * range.max(1.0).log10()
+}
+
+/// Spreading plus absorption loss in dB, absorbed as `tuning` sets
+pub fn transmission_loss(range: f32, tuning: &Tuning) -> f32 {
+    spreading_loss(range) + range.max(0.0) * Tunable::Absorption.get(tuning)
+}
+
+/// Background noise of the sea in dB, growing with the sea state
+pub fn ambient_noise(sea_state: u8) -> f32 {
+    30.0 + 3.0 * sea_state as f32
+}
+
+/// Adds incoherent sound levels given in dB
+pub fn db_sum(levels: &[f32]) -> f32 {
+    let power: f32 = levels.iter().map(|l| 10f32.powf(l / 10.0)).sum();
+    if power <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    10.0 * power.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreading_loss1() {
+        assert_eq!(spreading_loss(1.0), 0.0);
+        assert_eq!(spreading_loss(1000.0), 60.0);
+        assert_eq!(spreading_loss(0.1), 0.0);
+    }
+
+    #[test]
+    fn transmission_loss1() {
+        assert_eq!(transmission_loss(1000.0, &Tuning::default()), 60.5);
+        assert!(ambient_noise(6) > ambient_noise(2));
+    }
+
+    #[test]
+    fn db_sum1() {
+        assert!((db_sum(&[100.0, 100.0]) - 103.0103).abs() < 0.001);
+        assert_eq!(db_sum(&[]), f32::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,1171 @@
+pub mod behavior;
+
+use self::behavior::{Agent, Leaf, Status};
+use crate::approach::{can_reach, pursue, Pursuit, Solution, Weapon};
+use crate::autopilot::{self, approach, SprintDrift, DRIFT_SPEED, SPRINT_FACTOR};
+use crate::coverage::{self, Signature};
+use crate::datum::Datum;
+use crate::decoy;
+use crate::environment::Environment;
+use crate::events::Event;
+use crate::faction::Stance;
+use crate::identification::apparent_stance;
+use crate::intercept::{self, EmissionKind};
+use crate::physics::{normalize_angle, turn_towards, Point, KNOT};
+use crate::route;
+use crate::sensors::{passive_excess, SensorKind};
+use crate::torpedo;
+use crate::trace::{self, Level};
+use crate::tracking::{Track, Tracker};
+use crate::tuning::{Tunable, Tuning};
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #       SUBMARINE AI        #
+// #############################
+
+// What a boat does is decided by the behavior tree of its role (see
+// behavior.rs); this module perceives, runs the leaves and steers.
+//
+// The default submarine tree patrols sprinting and drifting: a sprint
+// covers ground but its own noise leaves the boat deaf, a drift is slow
+// enough to listen. A boat given a waypoint by the scenario first sprints
+// and drifts there, as the autopilot does (see autopilot.rs). A contact
+// heard is stalked and engaged once its track has been held long enough
+// for a solution: surface ships from under the layer, where their hull
+// sonars cannot reach, submarines from their side of the layer so as not
+// to lose them. Shots are led on the motion of the contact between the
+// last two times it was heard, or on a Kalman track of its bearings for a
+// boat the scenario gives that tracker (see tracking.rs), and only taken
+// from inside the danger zone of the torpedo loaded (see approach.rs), so
+// no fish is wasted on a target it cannot catch. A torpedo is heard like
+// any other noise, from the farther the faster it runs and the louder its
+// seeker pings (see noise.rs); once one is heard closing, or its seeker
+// caught on the intercept receiver, the boat runs away from it and across
+// the layer as soon as the crew has reacted; so it does from an escort or
+// its helicopter pinging close by, or depth charges going off around it
+// (see asw.rs and helicopter.rs). A
+// contact lost is not given up at once: the boat searches the circle it
+// can have got to since, around where it would be on its last course, for
+// a while before patrolling again (see datum.rs). Decoys
+// the crew cannot tell from the boat they mimic are stalked in its place,
+// see decoy.rs.
+//
+// The hull sonar is deaf astern (see baffles.rs), so every so often the
+// patrolling boat turns away from its course for a few minutes to listen
+// there. The other way round, a submarine heard moving is stalked from
+// right astern of it, in its baffles, rather than straight on.
+//
+// Only hostile vessels are hunted, see faction.rs; without sides in the
+// scenario, every other vessel is an enemy. Whatever
+// the tree orders, the boat turns away from land and zones forbidden to it.
+
+const SPRINT_TIME: f32 = 600.0;
+const DRIFT_TIME: f32 = 300.0;
+const STALK_SPEED: f32 = 5.0 * KNOT;
+/// Seconds a contact must be tracked before shooting at it
+const SOLUTION_TIME: f32 = 120.0;
+const FIRING_RANGE: f32 = 4_000.0;
+/// Seconds between two shots
+const RELOAD_TIME: f32 = 60.0;
+/// Seconds without hearing a contact before giving up on it
+const CONTACT_TIMEOUT: f32 = 300.0;
+/// Meters per second a lost contact is taken to make at most, unless it
+/// was heard making more
+const DATUM_SPEED: f32 = 10.0 * KNOT;
+/// Seconds since a contact was lost before its search is given up
+const SEARCH_TIME: f32 = 1_800.0;
+/// Meters from a search point at which the boat turns for the next
+const SEARCH_REACHED: f32 = 300.0;
+/// Meters a torpedo seeker caught on the intercept receiver only is guessed
+/// to be off
+const SEEKER_RANGE: f32 = 2_500.0;
+const EVASION_TIME: f32 = 240.0;
+/// Meters off a charge going off is taken for an attack on the boat
+const CHARGE_HEARD: f32 = 5_000.0;
+/// Meters off a hostile pulse is taken for a hunt for the boat
+const PING_HEARD: f32 = 4_000.0;
+/// Seconds an attack heard is remembered, for pulses and charges are
+/// heard a moment at a time
+const ATTACK_MEMORY: f32 = 30.0;
+/// Seconds between two baffle clearing turns
+const BAFFLE_INTERVAL: f32 = 1_200.0;
+/// Seconds a baffle clearing turn lasts
+const CLEARING_TIME: f32 = 180.0;
+/// Game angle a baffle clearing turn turns away from the patrol course by
+const CLEARING_TURN: f32 = 2.0 * std::f32::consts::FRAC_PI_3;
+/// Meters astern of a moving submarine it is stalked from
+const TRAIL_DISTANCE: f32 = 2_000.0;
+/// Meters per second a submarine must make to be stalked from astern
+const TRAIL_SPEED: f32 = 0.5;
+/// Meters kept between the boat and the layer when hiding across it
+const LAYER_MARGIN: f32 = 30.0;
+/// Shallowest depth the AI hides at above the layer
+const MIN_DEPTH: f32 = 20.0;
+/// Contacts shallower than this are surface ships
+const SURFACE_DEPTH: f32 = 5.0;
+/// Meters ahead the boat looks for forbidden zones
+const ZONE_LOOKAHEAD: f32 = 1_500.0;
+/// Meters kept clear of the edge of forbidden zones
+const ZONE_MARGIN: f32 = 300.0;
+/// Meters from a waypoint at which the boat steers for the next one
+const WAYPOINT_REACHED: f32 = 200.0;
+/// Meters the destination may move before the route is planned again
+const REPLAN_DISTANCE: f32 = 1_000.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Phase {
+    Sprint,
+    Drift,
+    Attack,
+    Evade,
+    Search,
+}
+
+/// What the boat knows of the vessel it is stalking
+#[derive(Debug, PartialEq, Clone)]
+pub struct Contact {
+    pub target: EntityId,
+    pub position: Point,
+    pub depth: f32,
+    /// Estimated from the last two positions heard
+    pub velocity: Point,
+    pub first_heard: f32,
+    pub last_heard: f32,
+    /// Kept on the bearings heard by the Kalman tracker, see tracking.rs
+    pub track: Option<Track>,
+}
+
+/// A lost contact being searched for
+#[derive(Debug, PartialEq, Clone)]
+pub struct Search {
+    pub datum: Datum,
+    /// Depth it was last heard at
+    pub depth: f32,
+    /// Leg of the search under way, see Datum::search_point
+    pub leg: usize,
+}
+
+impl Contact {
+    /// Where the contact is at `time` and how it moves, by its track when
+    /// it has one
+    pub fn solution(&self, time: f32) -> Solution {
+        match &self.track {
+            Some(track) => Solution {
+                position: track.projected(time - track.time),
+                velocity: track.velocity(),
+            },
+            None => Solution {
+                position: self.position.clone(),
+                velocity: self.velocity.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SubmarineAi {
+    /// Behavior tree the boat follows, see behavior.rs
+    pub role: String,
+    /// What the boat did last tick
+    pub phase: Phase,
+    /// Seconds left of the current sprint or drift
+    pub timer: f32,
+    /// Meters per second
+    pub max_speed: f32,
+    /// Game angle the patrol follows
+    pub patrol_heading: f32,
+    pub patrol_depth: f32,
+    pub contact: Option<Contact>,
+    pub search: Option<Search>,
+    /// Where the last torpedo threat was heard
+    pub threat: Option<Point>,
+    /// When the current threat was first heard
+    pub threat_since: Option<f32>,
+    /// Seconds left running from the threat
+    pub evasion: f32,
+    /// Where and when an escort attacking the boat was last heard
+    pub attacked: Option<(Point, f32)>,
+    /// Seconds before the next shot
+    pub reload: f32,
+    /// Waypoints around zones towards the contact, next first
+    pub route: Vec<Point>,
+    /// Where the boat sprints and drifts to when told to, see autopilot.rs
+    pub sprint_to: Option<SprintDrift>,
+    /// How the fire control follows the contact
+    pub tracker: Tracker,
+    /// Seconds into the scenario the baffles were last cleared
+    pub baffles_cleared: f32,
+    /// Seconds left of the baffle clearing turn under way
+    pub clearing: f32,
+}
+
+/// A vessel heard this tick, where it is taken to be: at a decoy of it
+/// when the crew is fooled
+struct Heard {
+    target: EntityId,
+    position: Point,
+    depth: f32,
+    excess: f32,
+}
+
+/// Heading, speed and depth the AI wants the boat at
+struct Orders {
+    heading: f32,
+    speed: f32,
+    depth: f32,
+}
+
+impl SubmarineAi {
+    pub fn new(max_speed: f32, patrol_heading: f32, patrol_depth: f32) -> SubmarineAi {
+        SubmarineAi {
+            role: "submarine".to_string(),
+            phase: Phase::Drift,
+            timer: DRIFT_TIME,
+            max_speed,
+            patrol_heading,
+            patrol_depth,
+            contact: None,
+            search: None,
+            threat: None,
+            threat_since: None,
+            evasion: 0.0,
+            attacked: None,
+            reload: 0.0,
+            route: Vec::new(),
+            sprint_to: None,
+            tracker: Tracker::default(),
+            baffles_cleared: 0.0,
+            clearing: 0.0,
+        }
+    }
+
+    /// Has the boat sprint and drift to `waypoint` before patrolling
+    pub fn send_to(&mut self, waypoint: Point) {
+        self.sprint_to = Some(SprintDrift::new(waypoint, SPRINT_TIME));
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.timer = match phase {
+            Phase::Sprint => SPRINT_TIME,
+            Phase::Drift => DRIFT_TIME,
+            Phase::Attack | Phase::Evade | Phase::Search => 0.0,
+        };
+    }
+
+    /// Updates the contact with what was heard this tick from `from`
+    fn track(&mut self, time: f32, from: &Point, heard: &Heard, tuning: &Tuning) {
+        let bearing = from.angle_to(&heard.position);
+        let bearing_error = SensorKind::HullSonar.bearing_error(heard.excess, tuning);
+        let kalman = self.tracker == Tracker::Kalman;
+        match self.contact.as_mut() {
+            Some(contact) if contact.target == heard.target => {
+                let elapsed = time - contact.last_heard;
+                if elapsed > 0.0 {
+                    let moved = heard.position.sub(&contact.position);
+                    contact.velocity = Point {
+                        x: moved.x / elapsed,
+                        y: moved.y / elapsed,
+                    };
+                }
+                contact.position = heard.position.clone();
+                contact.depth = heard.depth;
+                contact.last_heard = time;
+                if let Some(track) = contact.track.as_mut() {
+                    track.predict(time);
+                    track.bearing(from, bearing, bearing_error);
+                }
+            }
+            _ => {
+                trace::event(
+                    Level::Debug,
+                    "ai",
+                    "new contact",
+                    &[("contact", &heard.target)],
+                );
+                self.contact = Some(Contact {
+                    target: heard.target,
+                    position: heard.position.clone(),
+                    depth: heard.depth,
+                    velocity: Point { x: 0.0, y: 0.0 },
+                    first_heard: time,
+                    last_heard: time,
+                    track: if kalman {
+                        Some(Track::new(from, bearing, bearing_error, None, time))
+                    } else {
+                        None
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// Depth on the other side of the layer from `depth`
+pub fn across_layer(environment: &Environment, depth: f32, fallback: f32) -> f32 {
+    match environment.sound_speed.layer_depth() {
+        Some(layer) if depth < layer => layer + LAYER_MARGIN,
+        Some(layer) => (layer - LAYER_MARGIN).max(MIN_DEPTH),
+        None => fallback,
+    }
+}
+
+/// Depth to stalk a contact at `contact_depth` from
+pub fn stalking_depth(environment: &Environment, contact_depth: f32, patrol_depth: f32) -> f32 {
+    let layer = match environment.sound_speed.layer_depth() {
+        Some(layer) => layer,
+        None => return patrol_depth,
+    };
+    if contact_depth < SURFACE_DEPTH {
+        layer + LAYER_MARGIN
+    } else if (contact_depth < layer) == (patrol_depth < layer) {
+        patrol_depth
+    } else {
+        across_layer(environment, patrol_depth, patrol_depth)
+    }
+}
+
+/// Where to stalk `contact` from at `time`: right astern of a submarine
+/// on the move, in its baffles, or straight at anything else
+fn stalking_point(contact: &Contact, time: f32) -> Point {
+    let solution = contact.solution(time);
+    if contact.depth < SURFACE_DEPTH || solution.velocity.abs() < TRAIL_SPEED {
+        return contact.position.clone();
+    }
+    let ahead = solution.velocity.unit();
+    Point {
+        x: solution.position.x - TRAIL_DISTANCE * ahead.x,
+        y: solution.position.y - TRAIL_DISTANCE * ahead.y,
+    }
+}
+
+/// Whether `torpedo` is running closer to `boat`
+fn closing(torpedo: &Entity, boat: &Entity) -> bool {
+    let velocity = torpedo.velocity();
+    let (dx, dy) = (
+        boat.position.x - torpedo.position.x,
+        boat.position.y - torpedo.position.y,
+    );
+    velocity.x * dx + velocity.y * dy > 0.0
+}
+
+/// Strongest vessel and closest hostile torpedo closing that `boat` hears
+fn listen<'a>(world: &'a World, boat: &Entity) -> (Option<Heard>, Option<&'a Entity>) {
+    let mut vessel: Option<Heard> = None;
+    let mut torpedo: Option<(&Entity, f32)> = None;
+    for other in world.entities.iter() {
+        if other.id == boat.id || other.is_destroyed() {
+            continue;
+        }
+        let hostile = apparent_stance(world, boat, other) == Stance::Hostile;
+        let excess = match passive_excess(&world.environment, boat, other) {
+            Some(excess) if excess > 0.0 => excess,
+            _ => continue,
+        };
+        if other.kind == EntityKind::Torpedo {
+            let friendly = other
+                .torpedo
+                .as_ref()
+                .and_then(|t| world.entity(t.shooter))
+                .is_some_and(|s| {
+                    s.id == boat.id || world.diplomacy.stance(boat, s) != Stance::Hostile
+                });
+            let range = boat.position.distance_to(&other.position);
+            if !friendly && closing(other, boat) && torpedo.is_none_or(|(_, r)| range < r) {
+                torpedo = Some((other, range));
+            }
+        } else if hostile && vessel.as_ref().is_none_or(|v| excess > v.excess) {
+            vessel = Some(Heard {
+                target: other.id,
+                position: other.position.clone(),
+                depth: other.depth,
+                excess,
+            });
+        }
+    }
+    for d in world.decoys.iter() {
+        let excess = match decoy::apparent_excess(world, boat, d) {
+            Some(excess) if excess > 0.0 => excess,
+            _ => continue,
+        };
+        let hostile = world
+            .entity(d.owner)
+            .is_some_and(|owner| apparent_stance(world, boat, owner) == Stance::Hostile);
+        if hostile && vessel.as_ref().is_none_or(|v| excess > v.excess) {
+            vessel = Some(Heard {
+                target: d.owner,
+                position: d.position.clone(),
+                depth: d.depth,
+                excess,
+            });
+        }
+    }
+    (vessel, torpedo.map(|(e, _)| e))
+}
+
+/// Loaded tube and the speed its torpedo will run at
+/// Seconds the crew of `boat` takes to act on what it hears, slower in
+/// foul air or short of hands
+fn reaction_time(boat: &Entity) -> f32 {
+    boat.crew.reaction_time() / boat.crew_performance()
+}
+
+fn ready_tube(boat: &Entity) -> Option<(usize, Weapon)> {
+    let station = boat.weapons.as_ref()?;
+    station
+        .tubes
+        .tubes
+        .iter()
+        .find(|t| t.loaded)
+        .map(|t| (t.number, Weapon::torpedo(t.settings.speed)))
+}
+
+/// One tick of the AI of a boat, run by its behavior tree
+struct Tick<'a> {
+    ai: &'a mut SubmarineAi,
+    world: &'a World,
+    boat: &'a Entity,
+    /// Seconds since the last tick
+    dt: f32,
+    orders: Orders,
+    shot: Option<(usize, f32)>,
+}
+
+impl<'a> Tick<'a> {
+    fn solution_ready(&self) -> bool {
+        let (contact, (_, weapon)) = match (&self.ai.contact, ready_tube(self.boat)) {
+            (Some(contact), Some(tube)) => (contact, tube),
+            _ => return false,
+        };
+        self.boat.position.distance_to(&contact.position) < FIRING_RANGE
+            && contact.last_heard - contact.first_heard >= SOLUTION_TIME
+            && self.ai.reload <= 0.0
+            && can_reach(
+                &self.boat.position,
+                &contact.solution(self.world.time),
+                &weapon,
+            )
+    }
+}
+
+impl<'a> Agent for Tick<'a> {
+    fn run(&mut self, leaf: Leaf) -> Status {
+        let boat = self.boat;
+        let environment = &self.world.environment;
+        let ai = &mut *self.ai;
+        match leaf {
+            Leaf::Threatened => Status::from_bool(ai.evasion > 0.0),
+            Leaf::HasContact => Status::from_bool(
+                ai.contact
+                    .as_ref()
+                    .is_some_and(|c| c.last_heard - c.first_heard >= reaction_time(boat)),
+            ),
+            Leaf::SolutionReady => Status::from_bool(self.solution_ready()),
+            Leaf::Evade => {
+                ai.phase = Phase::Evade;
+                let from = ai.threat.clone().unwrap_or_else(|| boat.position.clone());
+                self.orders = Orders {
+                    heading: from.angle_to(&boat.position),
+                    speed: ai.max_speed,
+                    depth: if boat.crew.uses_layer() {
+                        across_layer(environment, boat.depth, ai.patrol_depth)
+                    } else {
+                        boat.depth
+                    },
+                };
+                Status::Running
+            }
+            Leaf::Fire => {
+                let (contact, (tube, weapon)) = match (&ai.contact, ready_tube(boat)) {
+                    (Some(contact), Some(tube)) => (contact, tube),
+                    _ => return Status::Failure,
+                };
+                let course = match pursue(
+                    &boat.position,
+                    &contact.solution(self.world.time),
+                    weapon.speed,
+                ) {
+                    Pursuit::Intercept { course, .. } => course,
+                    Pursuit::Unreachable { course, .. } => course,
+                };
+                self.shot = Some((tube, course));
+                ai.reload = RELOAD_TIME;
+                ai.phase = Phase::Attack;
+                Status::Success
+            }
+            Leaf::Stalk => {
+                let (position, depth) = match &ai.contact {
+                    Some(contact) => (stalking_point(contact, self.world.time), contact.depth),
+                    None => return Status::Failure,
+                };
+                ai.phase = Phase::Attack;
+                self.orders = Orders {
+                    heading: head_for(ai, self.world, boat, &position),
+                    speed: STALK_SPEED,
+                    depth: stalking_depth(environment, depth, ai.patrol_depth),
+                };
+                Status::Running
+            }
+            Leaf::SprintDrift => {
+                match ai.phase {
+                    Phase::Sprint if ai.timer <= 0.0 => ai.enter(Phase::Drift),
+                    Phase::Drift if ai.timer <= 0.0 => ai.enter(Phase::Sprint),
+                    Phase::Sprint | Phase::Drift => {}
+                    _ => ai.enter(Phase::Drift),
+                }
+                self.orders = Orders {
+                    heading: ai.patrol_heading,
+                    speed: match ai.phase {
+                        Phase::Sprint => ai.max_speed * SPRINT_FACTOR,
+                        _ => DRIFT_SPEED,
+                    },
+                    depth: ai.patrol_depth,
+                };
+                Status::Running
+            }
+            Leaf::ClearBaffles => {
+                let time = self.world.time;
+                if ai.clearing <= 0.0 {
+                    if time - ai.baffles_cleared < BAFFLE_INTERVAL {
+                        return Status::Failure;
+                    }
+                    ai.clearing = CLEARING_TIME;
+                    ai.baffles_cleared = time;
+                }
+                ai.phase = Phase::Drift;
+                self.orders = Orders {
+                    heading: normalize_angle(ai.patrol_heading + CLEARING_TURN),
+                    speed: DRIFT_SPEED,
+                    depth: ai.patrol_depth,
+                };
+                Status::Running
+            }
+            Leaf::BelowLayer => {
+                ai.patrol_depth = autopilot::below_layer(environment, ai.patrol_depth);
+                Status::Success
+            }
+            Leaf::Search => {
+                let time = self.world.time;
+                let search = match ai.search.as_mut() {
+                    Some(search) => search,
+                    None => return Status::Failure,
+                };
+                let mut point = search.datum.search_point(search.leg, time);
+                if point.distance_to(&boat.position) < SEARCH_REACHED {
+                    search.leg += 1;
+                    point = search.datum.search_point(search.leg, time);
+                }
+                // sprints until the circle is within what the boat hears
+                // slowed down, then listens its way across it
+                let mut listening = boat.clone();
+                listening.speed = STALK_SPEED;
+                let target = if search.depth < SURFACE_DEPTH {
+                    Signature::MERCHANT
+                } else {
+                    Signature {
+                        depth: search.depth,
+                        ..Signature::SUBMARINE
+                    }
+                };
+                let hearing = coverage::hearing_range(
+                    environment,
+                    &listening,
+                    &target,
+                    boat.position.angle_to(&search.datum.position),
+                );
+                let edge =
+                    boat.position.distance_to(&search.datum.position) - search.datum.radius(time);
+                let speed = if edge < hearing {
+                    STALK_SPEED
+                } else {
+                    ai.max_speed * SPRINT_FACTOR
+                };
+                let depth = stalking_depth(environment, search.depth, ai.patrol_depth);
+                ai.phase = Phase::Search;
+                self.orders = Orders {
+                    heading: head_for(ai, self.world, boat, &point),
+                    speed,
+                    depth,
+                };
+                Status::Running
+            }
+            Leaf::SprintTo => {
+                let (waypoint, listening) = match &mut ai.sprint_to {
+                    Some(sprint) if !sprint.arrived(&boat.position) => {
+                        sprint.tick(self.dt);
+                        (sprint.waypoint.clone(), sprint.listening)
+                    }
+                    _ => return Status::Failure,
+                };
+                let speed = ai.sprint_to.as_ref().unwrap().speed(ai.max_speed);
+                ai.phase = if listening {
+                    Phase::Drift
+                } else {
+                    Phase::Sprint
+                };
+                self.orders = Orders {
+                    heading: head_for(ai, self.world, boat, &waypoint),
+                    speed,
+                    depth: ai.patrol_depth,
+                };
+                Status::Running
+            }
+        }
+    }
+}
+
+/// Where a torpedo seeker the intercept receiver of `boat` classifies is
+/// guessed to be: range is unknown, so a guess down its bearing
+fn seeker_heard(world: &World, boat: &Entity) -> Option<Point> {
+    let seeker = intercept::intercepts(world, boat)
+        .into_iter()
+        .find(|i| i.kind == Some(EmissionKind::TorpedoSeeker))?;
+    Some(Point {
+        x: boat.position.x + SEEKER_RANGE * seeker.bearing.cos(),
+        y: boat.position.y + SEEKER_RANGE * seeker.bearing.sin(),
+    })
+}
+
+/// Where an escort attacking `boat` is heard: a charge gone off in the
+/// last `dt` seconds, or a hostile pulse sent close by
+fn attack_heard(world: &World, boat: &Entity, dt: f32) -> Option<Point> {
+    let charge = world
+        .events
+        .iter()
+        .rev()
+        .take_while(|t| t.time > world.time - dt)
+        .filter_map(|t| match &t.event {
+            Event::ChargeExploded { position, .. } => Some(position),
+            _ => None,
+        })
+        .find(|p| p.distance_to(&boat.position) < CHARGE_HEARD);
+    if let Some(position) = charge {
+        return Some(position.clone());
+    }
+    world
+        .emissions
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.kind,
+                EmissionKind::ActiveSonar | EmissionKind::DippingSonar
+            )
+        })
+        .filter(|e| e.position.distance_to(&boat.position) < PING_HEARD)
+        .find(|e| {
+            world
+                .entity(e.source)
+                .is_some_and(|s| apparent_stance(world, boat, s) == Stance::Hostile)
+        })
+        .map(|e| e.position.clone())
+}
+
+/// Updates what `ai` knows from what `boat` hears this tick
+fn perceive(ai: &mut SubmarineAi, world: &World, boat: &Entity, dt: f32) {
+    let (heard, threat) = listen(world, boat);
+    ai.reload = (ai.reload - dt).max(0.0);
+    ai.timer -= dt;
+    ai.clearing = (ai.clearing - dt).max(0.0);
+    ai.evasion = (ai.evasion - dt).max(0.0);
+    if let Some(position) = attack_heard(world, boat, dt) {
+        ai.attacked = Some((position, world.time));
+    }
+    if ai
+        .attacked
+        .as_ref()
+        .is_some_and(|(_, time)| world.time - time > ATTACK_MEMORY)
+    {
+        ai.attacked = None;
+    }
+    let threat = threat
+        .map(|t| t.position.clone())
+        .or_else(|| seeker_heard(world, boat))
+        .or_else(|| ai.attacked.as_ref().map(|(position, _)| position.clone()));
+    if let Some(position) = threat {
+        if ai.threat.is_none() {
+            trace::event(Level::Debug, "ai", "torpedo threat", &[]);
+        }
+        ai.threat = Some(position);
+        let since = *ai.threat_since.get_or_insert(world.time);
+        if world.time - since >= reaction_time(boat) {
+            ai.evasion = EVASION_TIME;
+        }
+    } else if ai.evasion <= 0.0 {
+        ai.threat = None;
+        ai.threat_since = None;
+    }
+    if let Some(vessel) = heard {
+        ai.track(
+            world.time,
+            &boat.position,
+            &vessel,
+            &world.environment.tuning,
+        );
+        ai.search = None;
+    }
+    if let Some(contact) = &ai.contact {
+        if world.time - contact.last_heard > CONTACT_TIMEOUT {
+            trace::event(
+                Level::Debug,
+                "ai",
+                "contact lost",
+                &[("contact", &contact.target)],
+            );
+            let solution = contact.solution(contact.last_heard);
+            ai.search = Some(Search {
+                datum: Datum {
+                    speed: solution.velocity.abs().max(DATUM_SPEED),
+                    position: solution.position,
+                    velocity: solution.velocity,
+                    time: contact.last_heard,
+                },
+                depth: contact.depth,
+                leg: 0,
+            });
+            ai.contact = None;
+        }
+    }
+    if ai
+        .search
+        .as_ref()
+        .is_some_and(|s| world.time - s.datum.time > SEARCH_TIME)
+    {
+        ai.search = None;
+    }
+}
+
+/// Runs the behavior tree of `ai`, returning its orders and the shot to
+/// take if any, as (tube, bearing)
+fn think(
+    ai: &mut SubmarineAi,
+    world: &World,
+    boat: &Entity,
+    dt: f32,
+) -> (Orders, Option<(usize, f32)>) {
+    perceive(ai, world, boat, dt);
+    let tree = world.behaviors.get(&ai.role);
+    let mut tick = Tick {
+        ai,
+        world,
+        boat,
+        dt,
+        orders: Orders {
+            heading: boat.heading,
+            speed: boat.speed,
+            depth: boat.depth,
+        },
+        shot: None,
+    };
+    if let Some(tree) = tree {
+        tree.tick(&mut tick);
+    }
+    (tick.orders, tick.shot)
+}
+
+/// Heading to steer for `to`, along a planned route when the zones the
+/// boat must keep out of are in the way
+fn head_for(ai: &mut SubmarineAi, world: &World, boat: &Entity, to: &Point) -> f32 {
+    let blocked = |p: &Point| world.forbidden(boat, p);
+    if route::is_clear(&boat.position, to, ZONE_MARGIN / 2.0, &blocked) {
+        ai.route.clear();
+        return boat.position.angle_to(to);
+    }
+    let stale = ai
+        .route
+        .last()
+        .is_none_or(|end| end.distance_to(to) > REPLAN_DISTANCE);
+    if stale {
+        ai.route = world.route(boat, to).unwrap_or_default();
+    }
+    while ai
+        .route
+        .first()
+        .is_some_and(|w| w.distance_to(&boat.position) < WAYPOINT_REACHED)
+    {
+        ai.route.remove(0);
+    }
+    let next = ai.route.first().unwrap_or(to);
+    boat.position.angle_to(next)
+}
+
+/// Heading closest to `heading` whose way ahead stays out of the zones
+/// forbidden to the boat
+fn steer_clear(world: &World, boat: &Entity, heading: f32) -> f32 {
+    let blocked = |heading: f32| {
+        (1..=3).any(|i| {
+            let distance = ZONE_LOOKAHEAD * i as f32 / 3.0;
+            let ahead = Point {
+                x: boat.position.x + distance * heading.cos(),
+                y: boat.position.y + distance * heading.sin(),
+            };
+            let near_zone = world.zones.iter().any(|z| {
+                z.forbids(boat)
+                    && !z.contains(&boat.position)
+                    && (z.contains(&ahead) || z.closest_edge(&ahead).1 < ZONE_MARGIN)
+            });
+            near_zone || world.forbidden(boat, &ahead)
+        })
+    };
+    for i in 0..=6 {
+        let offset = i as f32 * std::f32::consts::PI / 6.0;
+        for candidate in [heading + offset, heading - offset] {
+            if !blocked(candidate) {
+                return normalize_angle(candidate);
+            }
+        }
+    }
+    heading
+}
+
+/// Runs the AI of every submarine that has one
+pub fn update(world: &mut World, dt: f32) {
+    let boats: Vec<EntityId> = world
+        .entities
+        .iter()
+        .filter(|e| e.ai.is_some() && !e.is_destroyed())
+        .map(|e| e.id)
+        .collect();
+    for id in boats {
+        let _span = trace::span("boat", &[("entity", &id)]);
+        let boat = world.entity(id).unwrap();
+        let mut ai = boat.ai.clone().unwrap();
+        let (mut orders, shot) = think(&mut ai, world, boat, dt);
+        orders.heading = steer_clear(world, boat, orders.heading);
+        if let Some(safe) = world.safe_depth(boat) {
+            orders.depth = orders.depth.min(safe);
+        }
+        let tuning = &world.environment.tuning;
+        let turn = Tunable::TurnRate.get(tuning) * dt;
+        let acceleration = Tunable::Acceleration.get(tuning) * dt;
+        let depth_change = Tunable::DepthRate.get(tuning) * dt;
+        let boat = world.entity_mut(id).unwrap();
+        boat.ai = Some(ai);
+        boat.heading = turn_towards(boat.heading, orders.heading, turn);
+        boat.speed = approach(boat.speed, orders.speed, acceleration);
+        boat.depth = approach(boat.depth, orders.depth, depth_change);
+        let aim_error = boat.crew.aim_error() / boat.crew_performance();
+        if let Some((tube, bearing)) = shot {
+            let bearing = world.rng.gaussian(bearing, aim_error);
+            // a refused shot is simply tried again after the reload
+            let _ = torpedo::fire(world, id, tube, bearing);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asw::{self, AswStation};
+    use crate::command::AswCommand;
+    use crate::config::Config;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::weapons::{Guidance, PresetLibrary, SpeedSetting, WeaponsStation};
+    use crate::zone::{Zone, ZoneKind};
+
+    fn submarine(name: &str, x: f32, y: f32) -> Entity {
+        let mut boat = Entity::new(name, EntityKind::Submarine, Point { x, y });
+        boat.depth = 100.0;
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        boat.weapons = Some(WeaponsStation::new(4, PresetLibrary::new()));
+        boat
+    }
+
+    fn hunter(world: &mut World) -> EntityId {
+        let mut boat = submarine("hunter", 0.0, 0.0);
+        boat.ai = Some(SubmarineAi::new(10.0, 0.0, 100.0));
+        world.spawn(boat)
+    }
+
+    fn phase(world: &World, id: EntityId) -> Phase {
+        world.entity(id).unwrap().ai.as_ref().unwrap().phase
+    }
+
+    #[test]
+    fn keeps_out_of_minefields() {
+        let mut world = World::new();
+        world.zones.push(Zone {
+            name: "field".to_string(),
+            kind: ZoneKind::Minefield,
+            points: vec![
+                Point {
+                    x: -2000.0,
+                    y: 3000.0,
+                },
+                Point {
+                    x: 2000.0,
+                    y: 3000.0,
+                },
+                Point {
+                    x: 2000.0,
+                    y: 4000.0,
+                },
+                Point {
+                    x: -2000.0,
+                    y: 4000.0,
+                },
+            ],
+            depth: None,
+            bottom: None,
+        });
+        let id = world.spawn({
+            let mut boat = submarine("hunter", 0.0, 0.0);
+            boat.ai = Some(SubmarineAi::new(10.0, std::f32::consts::FRAC_PI_2, 100.0));
+            boat.heading = std::f32::consts::FRAC_PI_2;
+            boat
+        });
+        for _ in 0..1200 {
+            world.step(1.0);
+            let boat = world.entity(id).unwrap();
+            assert!(world.zones_at(&boat.position).next().is_none());
+        }
+    }
+
+    #[test]
+    fn sprint_and_drift() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        for _ in 0..310 {
+            world.step(1.0);
+        }
+        assert_eq!(phase(&world, id), Phase::Sprint);
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        assert!(world.entity(id).unwrap().speed > 5.0);
+        for _ in 0..600 {
+            world.step(1.0);
+        }
+        assert_eq!(phase(&world, id), Phase::Drift);
+    }
+
+    #[test]
+    fn clears_its_baffles() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        let heading = |world: &World| world.entity(id).unwrap().heading.to_degrees();
+        for _ in 0..1000 {
+            world.step(1.0);
+        }
+        assert!(heading(&world).abs() < 1.0, "{}", heading(&world));
+        for _ in 0..300 {
+            world.step(1.0);
+        }
+        assert!((heading(&world) - 120.0).abs() < 1.0, "{}", heading(&world));
+        for _ in 0..300 {
+            world.step(1.0);
+        }
+        assert!(heading(&world).abs() < 1.0, "{}", heading(&world));
+    }
+
+    #[test]
+    fn stalks_submarines_from_astern() {
+        let mut contact = Contact {
+            target: 2,
+            position: Point { x: 5000.0, y: 0.0 },
+            depth: 100.0,
+            velocity: Point { x: 2.0, y: 0.0 },
+            first_heard: 0.0,
+            last_heard: 10.0,
+            track: None,
+        };
+        let astern = stalking_point(&contact, 10.0);
+        assert!(astern.distance_to(&Point { x: 3000.0, y: 0.0 }) < 0.01);
+        contact.depth = 0.0;
+        assert_eq!(stalking_point(&contact, 10.0), contact.position);
+    }
+
+    #[test]
+    fn sprints_to_the_waypoint_under_the_layer() {
+        let mut world = World::new();
+        let tree =
+            "[tree.submarine]\nroot = sequence(below_layer, selector(sprint_to, sprint_drift))";
+        world.behaviors.read(&Config::parse(tree).unwrap()).unwrap();
+        let id = hunter(&mut world);
+        let ai = world.entity_mut(id).unwrap().ai.as_mut().unwrap();
+        ai.send_to(Point { x: 6000.0, y: 0.0 });
+        for _ in 0..1500 {
+            world.step(1.0);
+        }
+        let boat = world.entity(id).unwrap();
+        assert!(boat.position.x > 5800.0, "{:?}", boat.position);
+        assert!(boat.position.y.abs() < 200.0, "{:?}", boat.position);
+        assert_eq!(boat.depth, 75.0);
+    }
+
+    #[test]
+    fn duel() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        let mut target = submarine("target", 0.0, 2500.0);
+        target.speed = 6.0 * KNOT;
+        let target = world.spawn(target);
+        for _ in 0..200 {
+            world.step(1.0);
+        }
+        assert_eq!(phase(&world, id), Phase::Attack);
+        let contact = world
+            .entity(id)
+            .unwrap()
+            .ai
+            .as_ref()
+            .unwrap()
+            .contact
+            .clone();
+        assert_eq!(contact.unwrap().target, target);
+        assert!(world
+            .events
+            .iter()
+            .any(|e| matches!(e.event, Event::TorpedoFired { shooter, .. } if shooter == id)));
+    }
+
+    #[test]
+    fn kalman_fire_control() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        world.entity_mut(id).unwrap().ai.as_mut().unwrap().tracker = Tracker::Kalman;
+        let mut target = submarine("target", 0.0, 2500.0);
+        target.speed = 6.0 * KNOT;
+        let target = world.spawn(target);
+        for _ in 0..200 {
+            world.step(1.0);
+        }
+        let hunter = world.entity(id).unwrap();
+        let contact = hunter.ai.as_ref().unwrap().contact.clone().unwrap();
+        let track = contact.track.unwrap();
+        let truth = &world.entity(target).unwrap().position;
+        let off = normalize_angle(
+            hunter.position.angle_to(&track.position()) - hunter.position.angle_to(truth),
+        );
+        assert!(off.abs() < 2_f32.to_radians(), "{}", off.to_degrees());
+        assert!(world
+            .events
+            .iter()
+            .any(|e| matches!(e.event, Event::TorpedoFired { shooter, .. } if shooter == id)));
+    }
+
+    #[test]
+    fn searches_for_a_lost_contact() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        world.entity_mut(id).unwrap().ai.as_mut().unwrap().contact = Some(Contact {
+            target: 99,
+            position: Point { x: 0.0, y: 3000.0 },
+            depth: 100.0,
+            velocity: Point { x: 3.0, y: 0.0 },
+            first_heard: -600.0,
+            last_heard: -400.0,
+            track: None,
+        });
+        world.step(1.0);
+        let ai = world.entity(id).unwrap().ai.as_ref().unwrap();
+        assert_eq!(ai.contact, None);
+        let datum = ai.search.as_ref().unwrap().datum.clone();
+        assert_eq!(datum.speed, DATUM_SPEED);
+        assert_eq!(phase(&world, id), Phase::Search);
+        for _ in 0..600 {
+            world.step(1.0);
+        }
+        // off after where the contact was going, east of the datum, and
+        // slowed down to listen with the circle within hearing
+        let boat = world.entity(id).unwrap();
+        assert!(boat.position.x > 500.0, "{:?}", boat.position);
+        assert!(boat.position.y > 1000.0, "{:?}", boat.position);
+        assert!(boat.speed <= STALK_SPEED + 0.01, "{}", boat.speed);
+        for _ in 0..1500 {
+            world.step(1.0);
+        }
+        assert_eq!(world.entity(id).unwrap().ai.as_ref().unwrap().search, None);
+        assert_ne!(phase(&world, id), Phase::Search);
+    }
+
+    #[test]
+    fn evade() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        let shooter = world.spawn(submarine("shooter", 0.0, -3000.0));
+        torpedo::fire(&mut world, shooter, 1, std::f32::consts::FRAC_PI_2).unwrap();
+        world.step(1.0);
+        assert_ne!(phase(&world, id), Phase::Evade);
+        for _ in 0..15 {
+            world.step(1.0);
+        }
+        assert_eq!(phase(&world, id), Phase::Evade);
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        let boat = world.entity(id).unwrap();
+        assert!(boat.speed > 9.0);
+        assert!(boat.depth < 100.0);
+    }
+
+    #[test]
+    fn runs_from_an_escort_pinging() {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        let mut escort = Entity::new("escort", EntityKind::Warship, Point { x: 2000.0, y: 0.0 });
+        escort.asw = Some(AswStation::default());
+        let escort = world.spawn(escort);
+        asw::execute(&mut world, escort, &AswCommand::Ping(Some(true))).unwrap();
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        assert_eq!(phase(&world, id), Phase::Evade);
+        assert!(world.entity(id).unwrap().heading.cos() < 0.0);
+    }
+
+    /// Meters off a torpedo fired at the hunter from `range` with `speed`
+    /// and `guidance` is when the hunter first hears it coming
+    fn heard_at(range: f32, speed: SpeedSetting, guidance: Guidance) -> Option<f32> {
+        let mut world = World::new();
+        let id = hunter(&mut world);
+        world
+            .entity_mut(id)
+            .unwrap()
+            .ai
+            .as_mut()
+            .unwrap()
+            .patrol_heading = 0.0;
+        let mut shooter = submarine("shooter", 0.0, -range);
+        let station = shooter.weapons.as_mut().unwrap();
+        station.guidance = guidance;
+        station.tubes.tubes[0].settings.speed = speed;
+        station.tubes.tubes[0].settings.enable_run = 0.0;
+        let shooter = world.spawn(shooter);
+        let torpedo = torpedo::fire(&mut world, shooter, 1, std::f32::consts::FRAC_PI_2).unwrap();
+        for _ in 0..2_000 {
+            world.step(1.0);
+            let boat = world.entity(id).unwrap();
+            if boat.ai.as_ref().unwrap().threat_since.is_some() {
+                let torpedo = world.entity(torpedo)?;
+                return Some(torpedo.position.distance_to(&boat.position));
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn hears_loud_torpedoes_sooner() {
+        let pinging = Guidance::Acoustic(crate::seeker::SeekerGeneration::Modern);
+        // a slow straight runner never gets close enough to be heard
+        assert_eq!(
+            heard_at(30_000.0, SpeedSetting::Slow, Guidance::Unguided),
+            None
+        );
+        assert!(heard_at(30_000.0, SpeedSetting::Fast, Guidance::Unguided).is_some());
+        assert!(heard_at(30_000.0, SpeedSetting::Slow, pinging).is_some());
+        let quiet = heard_at(20_000.0, SpeedSetting::Slow, Guidance::Unguided).unwrap();
+        let loud = heard_at(20_000.0, SpeedSetting::Slow, pinging).unwrap();
+        assert!(loud > quiet);
+    }
+
+    #[test]
+    fn across_layer1() {
+        let environment = Environment::default();
+        assert_eq!(across_layer(&environment, 10.0, 50.0), 90.0);
+        assert_eq!(across_layer(&environment, 200.0, 50.0), 30.0);
+        assert_eq!(stalking_depth(&environment, 0.0, 100.0), 90.0);
+        assert_eq!(stalking_depth(&environment, 150.0, 100.0), 100.0);
+        assert_eq!(stalking_depth(&environment, 40.0, 100.0), 30.0);
+    }
+}
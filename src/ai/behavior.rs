@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError, Section};
+
+// Behavior trees are read from "[tree.<role>]" sections. Every key names a
+// node, "root" is where the tree starts, and a node is either a leaf or a
+// composite of other nodes, named or written inline:
+//
+// [tree.submarine]
+// root = selector(defend, attack, search, patrol)
+// defend = sequence(threatened, evade)
+// attack = sequence(has_contact, selector(sequence(solution_ready, fire), stalk))
+// patrol = selector(sprint_to, clear_baffles, sequence(below_layer, sprint_drift))
+//
+// selector(a, b, ...)   runs its children until one does not fail
+// sequence(a, b, ...)   runs its children until one does not succeed
+// invert(a)             swaps success and failure
+//
+// The whole tree is run again from the root on every tick.
+
+/// Trees used when the scenario does not bring its own
+const DEFAULT_TREES: &str = "
+[tree.submarine]
+root = selector(defend, attack, search, patrol)
+defend = sequence(threatened, evade)
+attack = sequence(has_contact, selector(shoot, stalk))
+shoot = sequence(solution_ready, fire)
+patrol = selector(sprint_to, clear_baffles, sprint_drift)
+";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+impl Status {
+    pub fn from_bool(value: bool) -> Status {
+        if value {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+/// Conditions and actions the AI knows how to carry out
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Leaf {
+    /// A hostile torpedo is (or was recently) heard
+    Threatened,
+    HasContact,
+    /// In range of a contact tracked long enough, with a tube ready
+    SolutionReady,
+    /// Run away from the threat and across the layer
+    Evade,
+    Fire,
+    /// Close on the contact
+    Stalk,
+    /// Patrol alternating sprints and listening drifts
+    SprintDrift,
+    /// Patrol just under the layer; succeeds at once, for the movement
+    /// that follows
+    BelowLayer,
+    /// Sprint and drift to the waypoint of the boat, failing without one
+    /// or once there
+    SprintTo,
+    /// Search the furthest-on circle of the contact last lost, failing
+    /// when none is being searched for
+    Search,
+    /// Turn away from the patrol course to listen astern, failing when the
+    /// baffles were cleared not long ago
+    ClearBaffles,
+}
+
+impl FromStr for Leaf {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threatened" => Ok(Leaf::Threatened),
+            "has_contact" => Ok(Leaf::HasContact),
+            "solution_ready" => Ok(Leaf::SolutionReady),
+            "evade" => Ok(Leaf::Evade),
+            "fire" => Ok(Leaf::Fire),
+            "stalk" => Ok(Leaf::Stalk),
+            "sprint_drift" => Ok(Leaf::SprintDrift),
+            "below_layer" => Ok(Leaf::BelowLayer),
+            "sprint_to" => Ok(Leaf::SprintTo),
+            "search" => Ok(Leaf::Search),
+            "clear_baffles" => Ok(Leaf::ClearBaffles),
+            _ => Err(format!("unknown node '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Leaf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Leaf::Threatened => "threatened",
+            Leaf::HasContact => "has_contact",
+            Leaf::SolutionReady => "solution_ready",
+            Leaf::Evade => "evade",
+            Leaf::Fire => "fire",
+            Leaf::Stalk => "stalk",
+            Leaf::SprintDrift => "sprint_drift",
+            Leaf::BelowLayer => "below_layer",
+            Leaf::SprintTo => "sprint_to",
+            Leaf::Search => "search",
+            Leaf::ClearBaffles => "clear_baffles",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Whatever runs the leaves of a tree
+pub trait Agent {
+    fn run(&mut self, leaf: Leaf) -> Status;
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Node {
+    Selector(Vec<Node>),
+    Sequence(Vec<Node>),
+    Invert(Box<Node>),
+    Leaf(Leaf),
+}
+
+impl Node {
+    pub fn tick<A: Agent>(&self, agent: &mut A) -> Status {
+        match self {
+            Node::Selector(children) => {
+                for child in children {
+                    let status = child.tick(agent);
+                    if status != Status::Failure {
+                        return status;
+                    }
+                }
+                Status::Failure
+            }
+            Node::Sequence(children) => {
+                for child in children {
+                    let status = child.tick(agent);
+                    if status != Status::Success {
+                        return status;
+                    }
+                }
+                Status::Success
+            }
+            Node::Invert(child) => match child.tick(agent) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            Node::Leaf(leaf) => agent.run(*leaf),
+        }
+    }
+}
+
+/// Reads the node definitions of one section
+struct Parser<'a> {
+    section: &'a Section,
+    /// Named nodes being expanded, to catch definitions referring to
+    /// themselves
+    expanding: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn invalid(&self, key: &str, value: &str) -> ConfigError {
+        ConfigError::Invalid {
+            section: self.section.name.clone(),
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn named(&mut self, key: &str) -> Result<Node, ConfigError> {
+        let text = match self.section.get(key) {
+            Some(text) => text.to_string(),
+            None => {
+                return key
+                    .parse()
+                    .map(Node::Leaf)
+                    .map_err(|_| ConfigError::Missing {
+                        section: self.section.name.clone(),
+                        key: key.to_string(),
+                    })
+            }
+        };
+        if self.expanding.iter().any(|k| k == key) {
+            return Err(self.invalid(key, &text));
+        }
+        self.expanding.push(key.to_string());
+        let mut rest = text.as_str();
+        let node = self.expression(&mut rest);
+        self.expanding.pop();
+        match node {
+            Some(Ok(node)) if rest.trim().is_empty() => Ok(node),
+            Some(Err(e)) => Err(e),
+            _ => Err(self.invalid(key, &text)),
+        }
+    }
+
+    /// Parses one expression at the start of `rest`, leaving what follows
+    fn expression(&mut self, rest: &mut &str) -> Option<Result<Node, ConfigError>> {
+        let text = (*rest).trim_start();
+        let end = text
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(text.len());
+        let name = &text[..end];
+        if name.is_empty() {
+            return None;
+        }
+        let after = text[end..].trim_start();
+        if !after.starts_with('(') {
+            *rest = after;
+            return Some(self.named(name));
+        }
+        *rest = &after[1..];
+        let mut children = Vec::new();
+        loop {
+            match self.expression(rest)? {
+                Ok(child) => children.push(child),
+                Err(e) => return Some(Err(e)),
+            }
+            let text = (*rest).trim_start();
+            if let Some(after) = text.strip_prefix(',') {
+                *rest = after;
+            } else if let Some(after) = text.strip_prefix(')') {
+                *rest = after;
+                break;
+            } else {
+                return None;
+            }
+        }
+        Some(match name {
+            "selector" => Ok(Node::Selector(children)),
+            "sequence" => Ok(Node::Sequence(children)),
+            "invert" if children.len() == 1 => Ok(Node::Invert(Box::new(children.remove(0)))),
+            _ => Err(self.invalid(name, "")),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BehaviorTree {
+    pub root: Node,
+}
+
+impl BehaviorTree {
+    pub fn read(section: &Section) -> Result<BehaviorTree, ConfigError> {
+        let mut parser = Parser {
+            section,
+            expanding: Vec::new(),
+        };
+        if section.get("root").is_none() {
+            return Err(ConfigError::Missing {
+                section: section.name.clone(),
+                key: "root".to_string(),
+            });
+        }
+        Ok(BehaviorTree {
+            root: parser.named("root")?,
+        })
+    }
+
+    pub fn tick<A: Agent>(&self, agent: &mut A) -> Status {
+        self.root.tick(agent)
+    }
+}
+
+/// Behavior trees by vessel role
+#[derive(Debug, PartialEq, Clone)]
+pub struct Behaviors {
+    trees: HashMap<String, BehaviorTree>,
+}
+
+impl Default for Behaviors {
+    fn default() -> Self {
+        let mut behaviors = Behaviors {
+            trees: HashMap::new(),
+        };
+        behaviors
+            .read(&Config::parse(DEFAULT_TREES).unwrap())
+            .unwrap();
+        behaviors
+    }
+}
+
+impl Behaviors {
+    /// Reads every "[tree.<role>]" section, replacing the trees already
+    /// known for those roles
+    pub fn read(&mut self, config: &Config) -> Result<(), ConfigError> {
+        for (role, section) in config.sections_with_prefix("tree") {
+            self.trees
+                .insert(role.to_string(), BehaviorTree::read(section)?);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, role: &str) -> Option<&BehaviorTree> {
+        self.trees.get(role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the leaves run, answering conditions from a fixed list
+    struct Script {
+        true_conditions: Vec<Leaf>,
+        ran: Vec<Leaf>,
+    }
+
+    impl Agent for Script {
+        fn run(&mut self, leaf: Leaf) -> Status {
+            self.ran.push(leaf);
+            match leaf {
+                Leaf::Threatened | Leaf::HasContact | Leaf::SolutionReady => {
+                    Status::from_bool(self.true_conditions.contains(&leaf))
+                }
+                // run only when the boat has a waypoint, a lost contact or
+                // baffles to clear
+                Leaf::SprintTo | Leaf::Search | Leaf::ClearBaffles
+                    if !self.true_conditions.contains(&leaf) =>
+                {
+                    Status::Failure
+                }
+                Leaf::Fire | Leaf::BelowLayer => Status::Success,
+                _ => Status::Running,
+            }
+        }
+    }
+
+    fn run(conditions: Vec<Leaf>) -> Vec<Leaf> {
+        let behaviors = Behaviors::default();
+        let mut script = Script {
+            true_conditions: conditions,
+            ran: Vec::new(),
+        };
+        behaviors.get("submarine").unwrap().tick(&mut script);
+        script.ran
+    }
+
+    #[test]
+    fn default_tree() {
+        assert_eq!(
+            run(vec![]),
+            vec![
+                Leaf::Threatened,
+                Leaf::HasContact,
+                Leaf::Search,
+                Leaf::SprintTo,
+                Leaf::ClearBaffles,
+                Leaf::SprintDrift
+            ]
+        );
+        assert_eq!(
+            run(vec![Leaf::ClearBaffles]),
+            vec![
+                Leaf::Threatened,
+                Leaf::HasContact,
+                Leaf::Search,
+                Leaf::SprintTo,
+                Leaf::ClearBaffles
+            ]
+        );
+        assert_eq!(
+            run(vec![Leaf::SprintTo]),
+            vec![
+                Leaf::Threatened,
+                Leaf::HasContact,
+                Leaf::Search,
+                Leaf::SprintTo
+            ]
+        );
+        assert_eq!(
+            run(vec![Leaf::Search]),
+            vec![Leaf::Threatened, Leaf::HasContact, Leaf::Search]
+        );
+        assert_eq!(
+            run(vec![Leaf::HasContact, Leaf::SolutionReady]),
+            vec![
+                Leaf::Threatened,
+                Leaf::HasContact,
+                Leaf::SolutionReady,
+                Leaf::Fire
+            ]
+        );
+        assert_eq!(
+            run(vec![Leaf::Threatened, Leaf::HasContact]),
+            vec![Leaf::Threatened, Leaf::Evade]
+        );
+    }
+
+    #[test]
+    fn invert() {
+        let config =
+            Config::parse("[tree.t]\nroot = sequence(invert(has_contact), evade)").unwrap();
+        let tree = BehaviorTree::read(config.section("tree.t").unwrap()).unwrap();
+        let mut script = Script {
+            true_conditions: vec![],
+            ran: Vec::new(),
+        };
+        assert_eq!(tree.tick(&mut script), Status::Running);
+        assert_eq!(script.ran, vec![Leaf::HasContact, Leaf::Evade]);
+    }
+
+    #[test]
+    fn bad_trees() {
+        for text in [
+            "root = selector(evade, dance)",
+            "root = loop\nloop = sequence(fire, loop)",
+            "root = selector(evade",
+            "root = repeat(evade)",
+            "patrol = sprint_drift",
+        ] {
+            let config = Config::parse(&format!("[tree.t]\n{}", text)).unwrap();
+            assert!(BehaviorTree::read(config.section("tree.t").unwrap()).is_err());
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use crate::physics::{normalize_angle, Point};
+use crate::torpedo::max_run;
+use crate::weapons::SpeedSetting;
+
+// #############################
+// #  FIRE CONTROL GEOMETRY    #
+// #############################
+
+// Where a shot can come from, given a target solution: its position and
+// velocity now. A torpedo running at u for at most a time T hits a target
+// moving at v from wherever a circle of radius u t around where the target
+// will be at t, for some t up to T, reaches; the union of those circles is
+// the danger zone of the target, convex, a plain circle when the torpedo is
+// the faster. A boat slower than its target can only close it from ahead,
+// inside the limiting lines of approach: the lines from the target at
+// asin(boat speed / target speed) either side of its course. The plot draws
+// both around the contacts the own ship tracks (see plot.rs), and the AI
+// only shoots from inside the danger zone (see ai.rs).
+//
+// The same triangle gives the course to steer: pursue() answers with the
+// intercept course, when it is met and the lead angle off the bearing of
+// the target, or, for a target that cannot be caught, with the course that
+// passes it closest and by how much.
+
+/// Circles sampled along the run for the outline of a danger zone
+const ZONE_STEPS: usize = 16;
+/// Points on each of them
+const CIRCLE_POINTS: usize = 32;
+
+/// Where a target is and how it moves, meters and meters per second
+#[derive(Debug, PartialEq, Clone)]
+pub struct Solution {
+    pub position: Point,
+    pub velocity: Point,
+}
+
+impl Solution {
+    pub fn at(&self, seconds: f32) -> Point {
+        Point {
+            x: self.position.x + self.velocity.x * seconds,
+            y: self.position.y + self.velocity.y * seconds,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        self.velocity.x.hypot(self.velocity.y)
+    }
+}
+
+/// How fast and how far a weapon runs
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Weapon {
+    /// Meters per second
+    pub speed: f32,
+    /// Meters
+    pub range: f32,
+}
+
+impl Weapon {
+    /// A torpedo set to run at `setting`
+    pub fn torpedo(setting: SpeedSetting) -> Weapon {
+        Weapon {
+            speed: setting.meters_per_second(),
+            range: max_run(setting),
+        }
+    }
+
+    /// Seconds the weapon runs at most
+    pub fn endurance(&self) -> f32 {
+        self.range / self.speed
+    }
+}
+
+/// Seconds a weapon running at `speed` from `launcher` takes to meet
+/// `target`, None when it never does
+pub fn intercept_time(launcher: &Point, target: &Solution, speed: f32) -> Option<f32> {
+    let d = target.position.sub(launcher);
+    let v = &target.velocity;
+    let a = v.x * v.x + v.y * v.y - speed * speed;
+    let b = 2.0 * (d.x * v.x + d.y * v.y);
+    let c = d.x * d.x + d.y * d.y;
+    if a.abs() < 1e-6 {
+        if b >= 0.0 {
+            return None;
+        }
+        return Some(-c / b);
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        .iter()
+        .cloned()
+        .filter(|t| *t >= 0.0)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// How a pursuer making a given speed goes after a target
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pursuit {
+    Intercept {
+        /// Game angle to steer
+        course: f32,
+        /// Seconds to the meeting
+        time: f32,
+        point: Point,
+        /// Radians the course leads the bearing of the target by,
+        /// counterclockwise positive
+        lead: f32,
+    },
+    /// The target cannot be caught
+    Unreachable {
+        /// Game angle passing the target closest
+        course: f32,
+        /// Meters the target is passed by at best
+        closest: f32,
+    },
+}
+
+/// Course to steer from `pursuer` at `speed` to meet `target`, or to pass
+/// it as close as can be
+pub fn pursue(pursuer: &Point, target: &Solution, speed: f32) -> Pursuit {
+    let bearing = pursuer.angle_to(&target.position);
+    if let Some(time) = intercept_time(pursuer, target, speed) {
+        let point = target.at(time);
+        // a target already reached is steered at
+        let course = if time > 0.0 {
+            pursuer.angle_to(&point)
+        } else {
+            bearing
+        };
+        return Pursuit::Intercept {
+            course,
+            time,
+            point,
+            lead: normalize_angle(course - bearing),
+        };
+    }
+    // The closing velocities make a circle of radius `speed` around the
+    // target's own, reversed; the best of them touches it along a tangent
+    let d = target.position.sub(pursuer);
+    let reversed = Point {
+        x: -target.velocity.x,
+        y: -target.velocity.y,
+    };
+    let target_speed = target.speed();
+    let spread = (speed / target_speed).min(1.0).asin();
+    let tangent = (target_speed * target_speed - speed * speed)
+        .max(0.0)
+        .sqrt();
+    let mut best: Option<(f32, f32)> = None;
+    for side in [-1.0, 1.0] {
+        let angle = reversed.angle() + side * spread;
+        let closing = Point {
+            x: tangent * angle.cos(),
+            y: tangent * angle.sin(),
+        };
+        let own = closing.sub(&reversed);
+        let course = if speed > 0.0 { own.angle() } else { bearing };
+        // off the line of sight by the angle between it and the closing
+        let off = normalize_angle(closing.angle() - d.angle()).abs();
+        let closest = if off < std::f32::consts::FRAC_PI_2 {
+            d.abs() * off.sin()
+        } else {
+            d.abs()
+        };
+        if best.is_none_or(|(_, c)| closest < c) {
+            best = Some((course, closest));
+        }
+    }
+    let (course, closest) = best.unwrap();
+    Pursuit::Unreachable { course, closest }
+}
+
+/// Radians to lead the bearing of `target` by, shooting from `launcher` at
+/// `speed`; None when it cannot be caught
+pub fn lead_angle(launcher: &Point, target: &Solution, speed: f32) -> Option<f32> {
+    match pursue(launcher, target, speed) {
+        Pursuit::Intercept { lead, .. } => Some(lead),
+        Pursuit::Unreachable { .. } => None,
+    }
+}
+
+/// Whether a weapon launched from `launcher` reaches `target` before its
+/// run is over
+pub fn can_reach(launcher: &Point, target: &Solution, weapon: &Weapon) -> bool {
+    intercept_time(launcher, target, weapon.speed).is_some_and(|t| t <= weapon.endurance())
+}
+
+/// Outline of the points `weapon` reaches `target` from, convex
+pub fn danger_zone(target: &Solution, weapon: &Weapon) -> Vec<Point> {
+    let endurance = weapon.endurance();
+    let mut points = Vec::new();
+    for step in 1..=ZONE_STEPS {
+        let t = endurance * step as f32 / ZONE_STEPS as f32;
+        let center = target.at(t);
+        let radius = weapon.speed * t;
+        for i in 0..CIRCLE_POINTS {
+            let angle = std::f32::consts::TAU * i as f32 / CIRCLE_POINTS as f32;
+            points.push(Point {
+                x: center.x + radius * angle.cos(),
+                y: center.y + radius * angle.sin(),
+            });
+        }
+    }
+    convex_hull(points)
+}
+
+/// Ends, `length` meters out, of the limiting lines of approach of a boat
+/// making `speed` on `target`; None when the boat is the faster
+pub fn limiting_lines(target: &Solution, speed: f32, length: f32) -> Option<[Point; 2]> {
+    let target_speed = target.speed();
+    if speed >= target_speed {
+        return None;
+    }
+    let course = target.velocity.y.atan2(target.velocity.x);
+    let spread = (speed / target_speed).asin();
+    let end = |angle: f32| Point {
+        x: target.position.x + length * angle.cos(),
+        y: target.position.y + length * angle.sin(),
+    };
+    Some([end(course - spread), end(course + spread)])
+}
+
+/// Whether a boat at `position` making `speed` can close `target`
+pub fn within_limiting_lines(position: &Point, target: &Solution, speed: f32) -> bool {
+    intercept_time(position, target, speed).is_some()
+}
+
+fn cross(o: &Point, a: &Point, b: &Point) -> f32 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+/// Counterclockwise hull of `points` (Andrew's monotone chain)
+fn convex_hull(mut points: Vec<Point>) -> Vec<Point> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Point> = Vec::new();
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Vec<&Point> = if pass == 0 {
+            points.iter().collect()
+        } else {
+            points.iter().rev().collect()
+        };
+        for p in ordered {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p.clone());
+        }
+        hull.pop();
+    }
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merchant() -> Solution {
+        // 5 m/s north from the origin
+        Solution {
+            position: Point { x: 0.0, y: 0.0 },
+            velocity: Point { x: 0.0, y: 5.0 },
+        }
+    }
+
+    #[test]
+    fn reaches_from_inside_the_danger_zone() {
+        let weapon = Weapon {
+            speed: 20.0,
+            range: 4000.0,
+        };
+        let target = merchant();
+        // ahead the torpedo meets the target coming, astern it must catch up
+        assert!(can_reach(&Point { x: 0.0, y: 4900.0 }, &target, &weapon));
+        assert!(!can_reach(&Point { x: 0.0, y: -3100.0 }, &target, &weapon));
+        assert!(can_reach(&Point { x: 0.0, y: -2900.0 }, &target, &weapon));
+        let t = intercept_time(&Point { x: 3000.0, y: 0.0 }, &target, 20.0).unwrap();
+        let hit = target.at(t);
+        assert!((hit.distance_to(&Point { x: 3000.0, y: 0.0 }) - 20.0 * t).abs() < 1.0);
+
+        // the zone is the circle of the run around the target at the end
+        let zone = danger_zone(&target, &weapon);
+        let center = target.at(weapon.endurance());
+        for p in &zone {
+            assert!((p.distance_to(&center) - 4000.0).abs() < 1.0, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn slow_boats_close_from_ahead_only() {
+        let target = merchant();
+        let lines = limiting_lines(&target, 2.5, 1000.0).unwrap();
+        // asin(0.5), 30 degrees either side of north
+        let spread = (lines[0].x - lines[1].x).abs() / 2.0;
+        assert!((spread - 500.0).abs() < 1.0);
+        assert!(within_limiting_lines(
+            &Point {
+                x: 500.0,
+                y: 3000.0
+            },
+            &target,
+            2.5
+        ));
+        assert!(!within_limiting_lines(
+            &Point {
+                x: 3000.0,
+                y: 500.0
+            },
+            &target,
+            2.5
+        ));
+        assert_eq!(limiting_lines(&target, 6.0, 1000.0), None);
+        assert!(within_limiting_lines(
+            &Point { x: 0.0, y: -3000.0 },
+            &target,
+            6.0
+        ));
+    }
+
+    #[test]
+    fn pursues_with_a_lead() {
+        let target = merchant();
+        // abeam at 20 m/s: sin(lead) = 5 / 20, ahead of the target
+        match pursue(&Point { x: 3000.0, y: 0.0 }, &target, 20.0) {
+            Pursuit::Intercept {
+                course,
+                time,
+                point,
+                lead,
+            } => {
+                assert!((lead + (0.25f32).asin()).abs() < 1e-3, "{}", lead);
+                assert!((course - (std::f32::consts::PI - (0.25f32).asin())).abs() < 1e-3);
+                assert!((point.y - 5.0 * time).abs() < 1.0);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(lead_angle(&Point { x: 3000.0, y: 0.0 }, &target, 20.0).is_some());
+
+        // astern and slower it never catches up, but steers to pass closest
+        let astern = Point {
+            x: 100.0,
+            y: -3000.0,
+        };
+        assert_eq!(lead_angle(&astern, &target, 2.5), None);
+        match pursue(&astern, &target, 2.5) {
+            Pursuit::Unreachable { closest, .. } => {
+                assert!(closest <= astern.distance_to(&target.position))
+            }
+            other => panic!("{:?}", other),
+        }
+        // abeam and slower it closes on the tangent, passing closer
+        let abeam = Point {
+            x: 3000.0,
+            y: 3000.0,
+        };
+        match pursue(&abeam, &target, 2.5) {
+            Pursuit::Unreachable { course, closest } => {
+                assert!(closest < 3000.0 * 2.0f32.sqrt(), "{}", closest);
+                // heading west, towards the track ahead of it
+                assert!(course.cos() < 0.0, "{}", course);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
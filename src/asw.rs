@@ -0,0 +1,467 @@
+use std::fmt;
+
+use crate::command::AswCommand;
+use crate::events::Event;
+use crate::helicopter::Helicopter;
+use crate::messages::Catalog;
+use crate::physics::Point;
+use crate::preferences::Preferences;
+use crate::pulse::Pulse;
+use crate::units::{Meters, MetersPerSecond};
+use crate::vds::{self, Vds};
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #  ANTI-SUBMARINE WEAPONS   #
+// #############################
+
+// An escort hunts a submerged boat with its hull sonar pinging, and attacks
+// it with what it drops and throws, as much as its class carries:
+//
+// [class.flower]
+// kind = warship
+// depth_charges = 70      # dropped five to a pattern
+// mortar = 10             # salvos of an ahead-throwing mortar
+//
+// A pattern of depth charges rolls off the stern rails and is thrown
+// abeam, so the escort must run over the boat to attack it, losing it on
+// its sonar on the way in. The charges sink and go off at the depth they
+// were set to: one close enough breaks the hull, those farther off shake
+// it and hurt the men inside. A mortar salvo lands a ring of small bombs
+// ahead while the boat is still held; they only go off on striking the
+// hull, so a miss does no harm but does not warn the boat either. The
+// player of an escort orders
+//
+// ping [on | off]         # one pulse, or pulse every few seconds
+// pulse long low sector   # what the pulses are, see pulse.rs
+// vds <depth> | recover   # the variable depth sonar, see vds.rs
+// charges <depth>         # drop a pattern set to go off at that depth
+// mortar                  # throw a salvo ahead
+//
+// A hunted boat hears the pulses and the charges going off, and runs away
+// from them and across the layer, see ai.rs.
+
+/// Seconds between two pulses when pinging
+pub const PING_INTERVAL: f32 = 10.0;
+/// Shallowest and deepest depths a charge can be set to, meters
+pub const MIN_SETTING: f32 = 15.0;
+pub const MAX_SETTING: f32 = 250.0;
+/// Where the charges of a pattern go in, meters ahead and to port of the
+/// escort: three off the rails, two thrown abeam
+const PATTERN: [(f32, f32); 5] = [
+    (-30.0, 0.0),
+    (-60.0, 0.0),
+    (-90.0, 0.0),
+    (-60.0, 60.0),
+    (-60.0, -60.0),
+];
+/// Meters per second a depth charge sinks
+const CHARGE_SINK_RATE: f32 = 3.0;
+/// Meters from a charge going off within which a hull is broken
+const LETHAL_RADIUS: f32 = 8.0;
+/// Meters beyond which a charge going off does no harm
+const SHOCK_RADIUS: f32 = 60.0;
+/// Seconds to ready the rails and throwers for the next pattern
+const CHARGE_RELOAD: f32 = 20.0;
+/// Meters ahead of the escort a mortar salvo lands
+const MORTAR_RANGE: f32 = 230.0;
+const MORTAR_BOMBS: usize = 24;
+/// Meters across the ring of bombs of a salvo
+const RING_DIAMETER: f32 = 40.0;
+const BOMB_SINK_RATE: f32 = 7.0;
+/// Meters from a bomb the hull must pass within to be struck
+const HULL_RADIUS: f32 = 4.0;
+/// Depth past which a bomb that struck nothing is forgotten
+const BOMB_MAX_DEPTH: f32 = 300.0;
+/// Hull lost to one bomb going off against it
+const BOMB_DAMAGE: f32 = 0.4;
+const MORTAR_RELOAD: f32 = 90.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AswError {
+    NoActiveSonar,
+    NoCharges,
+    NoMortar,
+    /// Seconds still to go
+    Reloading(f32),
+    /// The depth asked for a pattern, meters
+    BadSetting(f32),
+    NoVds,
+    /// Streaming or recovering the body of the variable depth sonar
+    /// above its speed
+    TooFastForVds,
+    /// The depth asked for the body, meters
+    BadVdsDepth(f32),
+}
+
+impl AswError {
+    /// The error as written for the player, in the units of `preferences`
+    pub fn describe(&self, messages: &Catalog, preferences: &Preferences) -> String {
+        match self {
+            AswError::NoActiveSonar => messages.get("error-no-active-sonar").to_string(),
+            AswError::NoCharges => messages.get("error-no-charges").to_string(),
+            AswError::NoMortar => messages.get("error-no-mortar").to_string(),
+            AswError::Reloading(seconds) => {
+                let seconds = format!("{:.0}", seconds.ceil());
+                messages.format("error-reloading", &[("seconds", &seconds)])
+            }
+            AswError::BadSetting(_) => {
+                let units = preferences.units;
+                let (min, max) = (
+                    units.depth(Meters(MIN_SETTING)),
+                    units.depth(Meters(MAX_SETTING)),
+                );
+                messages.format("error-charge-setting", &[("min", &min), ("max", &max)])
+            }
+            AswError::NoVds => messages.get("error-no-vds").to_string(),
+            AswError::TooFastForVds => {
+                let speed = preferences.units.speed(MetersPerSecond(vds::STREAM_SPEED));
+                messages.format("error-vds-too-fast", &[("speed", &speed)])
+            }
+            AswError::BadVdsDepth(_) => {
+                let units = preferences.units;
+                let (min, max) = (
+                    units.depth(Meters(vds::MIN_DEPTH)),
+                    units.depth(Meters(vds::MAX_DEPTH)),
+                );
+                messages.format("error-vds-depth", &[("min", &min), ("max", &max)])
+            }
+        }
+    }
+}
+
+impl fmt::Display for AswError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.describe(&Catalog::default(), &Preferences::default())
+        )
+    }
+}
+
+impl std::error::Error for AswError {}
+
+/// Active sonar and weapons of an escort
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct AswStation {
+    pub depth_charges: u32,
+    /// Mortar salvos left
+    pub mortar: u32,
+    /// Seconds before the next pattern can be dropped
+    pub charge_reload: f32,
+    /// Seconds before the next salvo can be thrown
+    pub mortar_reload: f32,
+    /// Pulsing every PING_INTERVAL
+    pub pinging: bool,
+    /// A single pulse ordered for the next tick
+    pub ping_due: bool,
+    /// Seconds since the last pulse
+    pub since_ping: f32,
+    /// What the pulses sent are
+    pub pulse: Pulse,
+    /// See helicopter.rs
+    pub helicopter: Option<Helicopter>,
+    /// See vds.rs
+    pub vds: Option<Vds>,
+}
+
+impl AswStation {
+    pub fn new(depth_charges: u32, mortar: u32) -> AswStation {
+        AswStation {
+            depth_charges,
+            mortar,
+            ..AswStation::default()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChargeKind {
+    DepthCharge,
+    MortarBomb,
+}
+
+/// A depth charge or mortar bomb sinking
+#[derive(Debug, PartialEq, Clone)]
+pub struct Charge {
+    /// Escort that dropped or threw it
+    pub owner: EntityId,
+    pub kind: ChargeKind,
+    pub position: Point,
+    pub depth: f32,
+    /// Depth a depth charge goes off at
+    pub setting: f32,
+}
+
+/// The point `ahead` meters ahead of and `port` meters to port of `ship`
+fn relative(ship: &Entity, ahead: f32, port: f32) -> Point {
+    let (sin, cos) = ship.heading.sin_cos();
+    Point {
+        x: ship.position.x + ahead * cos - port * sin,
+        y: ship.position.y + ahead * sin + port * cos,
+    }
+}
+
+/// Carries out an anti-submarine order for `escort`
+pub fn execute(world: &mut World, escort: EntityId, command: &AswCommand) -> Result<(), AswError> {
+    let ship = world
+        .entity(escort)
+        .filter(|e| e.asw.is_some())
+        .ok_or(AswError::NoActiveSonar)?;
+    let mut charges = Vec::new();
+    match command {
+        AswCommand::Ping(_) | AswCommand::Pulse(_) | AswCommand::Vds(_) => {}
+        AswCommand::Charges(depth) => {
+            let setting = depth.0;
+            if !(MIN_SETTING..=MAX_SETTING).contains(&setting) {
+                return Err(AswError::BadSetting(setting));
+            }
+            for (ahead, port) in PATTERN {
+                charges.push(Charge {
+                    owner: escort,
+                    kind: ChargeKind::DepthCharge,
+                    position: relative(ship, ahead, port),
+                    depth: 0.0,
+                    setting,
+                });
+            }
+        }
+        AswCommand::Mortar => {
+            for i in 0..MORTAR_BOMBS {
+                let angle = i as f32 * std::f32::consts::TAU / MORTAR_BOMBS as f32;
+                let radius = RING_DIAMETER / 2.0;
+                charges.push(Charge {
+                    owner: escort,
+                    kind: ChargeKind::MortarBomb,
+                    position: relative(
+                        ship,
+                        MORTAR_RANGE + radius * angle.cos(),
+                        radius * angle.sin(),
+                    ),
+                    depth: 0.0,
+                    setting: BOMB_MAX_DEPTH,
+                });
+            }
+        }
+    }
+    let speed = ship.speed;
+    let station = world.entity_mut(escort).unwrap().asw.as_mut().unwrap();
+    match command {
+        AswCommand::Ping(None) => station.ping_due = true,
+        AswCommand::Ping(Some(on)) => station.pinging = *on,
+        AswCommand::Pulse(pulse) => station.pulse = *pulse,
+        AswCommand::Vds(depth) => vds::order(station, speed, depth.map(|d| d.0))?,
+        AswCommand::Charges(_) => {
+            if station.depth_charges < PATTERN.len() as u32 {
+                return Err(AswError::NoCharges);
+            }
+            if station.charge_reload > 0.0 {
+                return Err(AswError::Reloading(station.charge_reload));
+            }
+            station.depth_charges -= PATTERN.len() as u32;
+            station.charge_reload = CHARGE_RELOAD;
+        }
+        AswCommand::Mortar => {
+            if station.mortar == 0 {
+                return Err(AswError::NoMortar);
+            }
+            if station.mortar_reload > 0.0 {
+                return Err(AswError::Reloading(station.mortar_reload));
+            }
+            station.mortar -= 1;
+            station.mortar_reload = MORTAR_RELOAD;
+        }
+    }
+    world.charges.extend(charges);
+    Ok(())
+}
+
+/// Hull `boat` loses to a depth charge going off at `position` and `depth`
+fn shock(boat: &Entity, position: &Point, depth: f32) -> f32 {
+    let across = boat.position.distance_to(position);
+    let distance = (across * across + (boat.depth - depth).powi(2)).sqrt();
+    if distance >= SHOCK_RADIUS {
+        return 0.0;
+    }
+    (LETHAL_RADIUS / distance.max(LETHAL_RADIUS)).powi(2)
+}
+
+/// Sends the pulses due, reloads, sinks the charges in the water and sets
+/// off those at their depth or against a hull
+pub fn update(world: &mut World, dt: f32) {
+    let mut pings = Vec::new();
+    for ship in world.entities.iter_mut() {
+        let destroyed = ship.is_destroyed();
+        let station = match ship.asw.as_mut() {
+            Some(station) => station,
+            None => continue,
+        };
+        station.charge_reload = (station.charge_reload - dt).max(0.0);
+        station.mortar_reload = (station.mortar_reload - dt).max(0.0);
+        station.since_ping += dt;
+        let due = station.ping_due || (station.pinging && station.since_ping >= PING_INTERVAL);
+        if due && !destroyed {
+            pings.push(ship.id);
+            station.since_ping = 0.0;
+        }
+        station.ping_due = false;
+    }
+    for id in pings {
+        world.ping(id);
+    }
+    let mut blasts = Vec::new();
+    let mut struck = Vec::new();
+    let entities = &world.entities;
+    world.charges.retain_mut(|charge| match charge.kind {
+        ChargeKind::DepthCharge => {
+            charge.depth = (charge.depth + CHARGE_SINK_RATE * dt).min(charge.setting);
+            if charge.depth < charge.setting {
+                return true;
+            }
+            blasts.push(charge.clone());
+            false
+        }
+        ChargeKind::MortarBomb => {
+            let above = charge.depth;
+            charge.depth += BOMB_SINK_RATE * dt;
+            let hit = entities.iter().find(|e| {
+                e.kind == EntityKind::Submarine
+                    && !e.is_destroyed()
+                    && e.position.distance_to(&charge.position) < HULL_RADIUS
+                    && e.depth + HULL_RADIUS >= above
+                    && e.depth - HULL_RADIUS <= charge.depth
+            });
+            if let Some(boat) = hit {
+                struck.push((charge.clone(), boat.id));
+                return false;
+            }
+            charge.depth < BOMB_MAX_DEPTH
+        }
+    });
+    for charge in blasts {
+        world.emit(Event::ChargeExploded {
+            owner: charge.owner,
+            position: charge.position.clone(),
+            depth: charge.depth,
+        });
+        let shaken: Vec<(EntityId, f32)> = world
+            .entities
+            .iter()
+            .filter(|e| e.kind != EntityKind::Torpedo && !e.is_destroyed())
+            .map(|e| (e.id, shock(e, &charge.position, charge.depth)))
+            .filter(|(_, damage)| *damage > 0.0)
+            .collect();
+        for (id, damage) in shaken {
+            world.apply_damage(id, damage);
+        }
+    }
+    for (bomb, boat) in struck {
+        world.emit(Event::ChargeExploded {
+            owner: bomb.owner,
+            position: bomb.position,
+            depth: bomb.depth,
+        });
+        world.apply_damage(boat, BOMB_DAMAGE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::UnitSystem;
+
+    fn hunt(boat_depth: f32) -> (World, EntityId, EntityId) {
+        let mut world = World::new();
+        let mut escort = Entity::new("escort", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.asw = Some(AswStation::new(10, 1));
+        let escort = world.spawn(escort);
+        let mut boat = Entity::new("boat", EntityKind::Submarine, Point { x: -60.0, y: 0.0 });
+        boat.depth = boat_depth;
+        let boat = world.spawn(boat);
+        (world, escort, boat)
+    }
+
+    #[test]
+    fn charges_set_to_the_boat_break_it() {
+        let (mut world, escort, boat) = hunt(60.0);
+        execute(&mut world, escort, &AswCommand::Charges(Meters(60.0))).unwrap();
+        assert_eq!(world.charges.len(), 5);
+        assert_eq!(
+            execute(&mut world, escort, &AswCommand::Charges(Meters(60.0))),
+            Err(AswError::Reloading(CHARGE_RELOAD))
+        );
+        for _ in 0..25 {
+            world.step(1.0);
+        }
+        assert!(world.charges.is_empty());
+        assert!(world.entity(boat).unwrap().is_destroyed());
+        // the escort is well clear of charges set that deep
+        assert_eq!(world.entity(escort).unwrap().hull, 1.0);
+        assert_eq!(
+            execute(&mut world, escort, &AswCommand::Charges(Meters(500.0))),
+            Err(AswError::BadSetting(500.0))
+        );
+        let imperial = Preferences {
+            units: UnitSystem::Imperial,
+            ..Preferences::default()
+        };
+        assert_eq!(
+            AswError::BadSetting(500.0).describe(&Catalog::default(), &imperial),
+            "charges can be set from 49 ft to 820 ft"
+        );
+        assert_eq!(
+            AswError::BadVdsDepth(900.0).describe(&Catalog::default(), &imperial),
+            "the body can be set from 66 ft to 984 ft"
+        );
+        assert_eq!(
+            AswError::TooFastForVds.to_string(),
+            "slow to 12.0 kn to stream or recover the body"
+        );
+    }
+
+    #[test]
+    fn charges_set_too_shallow_only_shake_it() {
+        let (mut world, escort, boat) = hunt(120.0);
+        execute(&mut world, escort, &AswCommand::Charges(Meters(80.0))).unwrap();
+        for _ in 0..30 {
+            world.step(1.0);
+        }
+        let hull = world.entity(boat).unwrap().hull;
+        assert!(hull > 0.5 && hull < 1.0);
+        let blasts = world
+            .events
+            .iter()
+            .filter(|t| matches!(t.event, Event::ChargeExploded { .. }))
+            .count();
+        assert_eq!(blasts, 5);
+    }
+
+    #[test]
+    fn mortar_bombs_only_go_off_on_the_hull() {
+        let (mut world, escort, boat) = hunt(100.0);
+        world.entity_mut(boat).unwrap().position = Point { x: 230.0, y: 20.0 };
+        execute(&mut world, escort, &AswCommand::Mortar).unwrap();
+        for _ in 0..50 {
+            world.step(1.0);
+        }
+        assert!(world.charges.is_empty());
+        assert!(world.entity(boat).unwrap().hull < 1.0);
+        assert_eq!(
+            execute(&mut world, escort, &AswCommand::Mortar),
+            Err(AswError::NoMortar)
+        );
+    }
+
+    #[test]
+    fn pinging() {
+        let (mut world, escort, _) = hunt(60.0);
+        execute(&mut world, escort, &AswCommand::Ping(Some(true))).unwrap();
+        let mut pulses = 0;
+        for _ in 0..30 {
+            world.step(1.0);
+            pulses += world.emissions.len();
+        }
+        assert_eq!(pulses, 3);
+    }
+}
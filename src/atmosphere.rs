@@ -0,0 +1,223 @@
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::world::{Entity, World};
+
+// #############################
+// #     AIR IN THE BOAT       #
+// #############################
+
+// A submerged boat lives on the air it dived with. The crew breathes the
+// oxygen down and the carbon dioxide up; the scrubber (potash cartridges)
+// takes the CO2 out for as many hours as its absorbent lasts, and oxygen
+// candles are lit one at a time when the oxygen runs low. As the air goes
+// foul the crew gets slow and dull: it reacts late, hears less and aims
+// wide. Surfacing, or running the snorkel at periscope depth, airs the
+// boat out. Classes give what they carry:
+//
+// [class.type_xxi]
+// scrubber = 72           # hours of CO2 absorbent
+// candles = 20            # oxygen candles
+// snorkel = true
+
+/// Fraction of oxygen in fresh air
+const FRESH_OXYGEN: f32 = 0.21;
+/// Fraction of carbon dioxide in fresh air
+const FRESH_CO2: f32 = 0.0004;
+/// Fraction of the air the crew turns from oxygen into CO2 in an hour
+const BREATHING: f32 = 0.004;
+/// Fraction of oxygen below which a candle is lit
+const CANDLE_AT: f32 = 0.19;
+/// Seconds an oxygen candle burns, giving the oxygen breathed meanwhile
+const CANDLE_TIME: f32 = 3600.0;
+/// Seconds for the air to be changed through an open hatch or the snorkel
+const VENTILATION_TIME: f32 = 600.0;
+/// Fractions of CO2 at which the crew starts to suffer and is done for
+const FOUL_CO2: (f32, f32) = (0.02, 0.06);
+/// Fractions of oxygen at which the crew starts to suffer and is done for
+const FOUL_OXYGEN: (f32, f32) = (0.17, 0.12);
+/// Least performance of a crew in the worst air
+const MIN_PERFORMANCE: f32 = 0.1;
+/// Hours of CO2 absorbent when the class does not say
+pub const DEFAULT_SCRUBBER: f32 = 72.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Atmosphere {
+    /// Fraction of the air
+    pub oxygen: f32,
+    /// Fraction of the air
+    pub co2: f32,
+    /// Hours of CO2 absorbent left
+    pub scrubber: f32,
+    /// Oxygen candles left, not counting the one burning
+    pub candles: u32,
+    /// Seconds the burning candle has left
+    pub candle_burning: f32,
+    /// Whether the boat can breathe through a snorkel at periscope depth
+    pub snorkel: bool,
+}
+
+impl Atmosphere {
+    /// Fresh air, with `scrubber` hours of absorbent and `candles`
+    pub fn new(scrubber: f32, candles: u32, snorkel: bool) -> Atmosphere {
+        Atmosphere {
+            oxygen: FRESH_OXYGEN,
+            co2: FRESH_CO2,
+            scrubber,
+            candles,
+            candle_burning: 0.0,
+            snorkel,
+        }
+    }
+
+    /// 1 in fresh air, down to 0.1 as the air gets unbreathable
+    pub fn performance(&self) -> f32 {
+        let co2 = (self.co2 - FOUL_CO2.0) / (FOUL_CO2.1 - FOUL_CO2.0);
+        let oxygen = (FOUL_OXYGEN.0 - self.oxygen) / (FOUL_OXYGEN.0 - FOUL_OXYGEN.1);
+        let severity = co2.max(oxygen).clamp(0.0, 1.0);
+        1.0 - severity * (1.0 - MIN_PERFORMANCE)
+    }
+
+    /// Whether the crew has started to suffer
+    pub fn is_foul(&self) -> bool {
+        self.performance() < 1.0
+    }
+
+    /// Hours the boat can stay shut in before the air turns foul
+    pub fn hours_left(&self) -> f32 {
+        let candles = (self.candles as f32 * CANDLE_TIME + self.candle_burning) / 3600.0;
+        let oxygen_hours = (self.oxygen - FOUL_OXYGEN.0) / BREATHING + candles;
+        let co2_hours = (FOUL_CO2.0 - self.co2) / BREATHING + self.scrubber;
+        oxygen_hours.min(co2_hours).max(0.0)
+    }
+
+    /// Airs the boat out over `dt` seconds
+    fn ventilate(&mut self, dt: f32) {
+        let change = (dt / VENTILATION_TIME).min(1.0);
+        self.oxygen += (FRESH_OXYGEN - self.oxygen) * change;
+        self.co2 += (FRESH_CO2 - self.co2) * change;
+    }
+
+    /// The crew breathes the air for `dt` seconds shut in
+    fn breathe(&mut self, dt: f32) {
+        let hours = dt / 3600.0;
+        let breathed = BREATHING * hours;
+        if self.candle_burning <= 0.0 && self.oxygen < CANDLE_AT && self.candles > 0 {
+            self.candles -= 1;
+            self.candle_burning = CANDLE_TIME;
+        }
+        if self.candle_burning > 0.0 {
+            self.candle_burning = (self.candle_burning - dt).max(0.0);
+        } else {
+            self.oxygen = (self.oxygen - breathed).max(0.0);
+        }
+        if self.scrubber > 0.0 {
+            self.scrubber = (self.scrubber - hours).max(0.0);
+        } else {
+            self.co2 += breathed;
+        }
+    }
+}
+
+/// Whether `entity` is drawing fresh air, on the surface or snorkeling
+fn airing(entity: &Entity, atmosphere: &Atmosphere) -> bool {
+    entity.is_surfaced() || (atmosphere.snorkel && entity.depth <= PERISCOPE_DEPTH)
+}
+
+/// Whether `entity` is running its snorkel, submerged at periscope depth
+pub fn snorkeling(entity: &Entity) -> bool {
+    let snorkel = entity.atmosphere.as_ref().is_some_and(|a| a.snorkel);
+    snorkel && !entity.is_surfaced() && entity.depth <= PERISCOPE_DEPTH
+}
+
+/// How well the crew of `entity` works in its air, 1 at best
+pub fn performance(entity: &Entity) -> f32 {
+    entity.atmosphere.as_ref().map_or(1.0, |a| a.performance())
+}
+
+/// Carries the air of every boat on by `dt` seconds
+pub fn update(world: &mut World, dt: f32) {
+    for entity in world.entities.iter_mut() {
+        if entity.is_destroyed() {
+            continue;
+        }
+        let airing = match entity.atmosphere.as_ref() {
+            Some(atmosphere) => airing(entity, atmosphere),
+            None => continue,
+        };
+        let atmosphere = entity.atmosphere.as_mut().unwrap();
+        if airing {
+            atmosphere.ventilate(dt);
+        } else {
+            atmosphere.breathe(dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::world::EntityKind;
+
+    fn boat(atmosphere: Atmosphere) -> World {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 60.0;
+        boat.atmosphere = Some(atmosphere);
+        world.spawn(boat);
+        world
+    }
+
+    fn hours(world: &mut World, hours: u32) {
+        for _ in 0..hours * 60 {
+            world.step(60.0);
+        }
+    }
+
+    fn air(world: &World) -> &Atmosphere {
+        world.entity(1).unwrap().atmosphere.as_ref().unwrap()
+    }
+
+    #[test]
+    fn air_goes_foul_once_the_scrubber_is_spent() {
+        let mut world = boat(Atmosphere::new(6.0, 20, false));
+        hours(&mut world, 5);
+        assert_eq!(air(&world).co2, FRESH_CO2);
+        assert_eq!(air(&world).hours_left().round(), 6.0);
+        hours(&mut world, 7);
+        assert!(air(&world).co2 > FOUL_CO2.0);
+        let foul = performance(world.entity(1).unwrap());
+        assert!((MIN_PERFORMANCE..1.0).contains(&foul));
+    }
+
+    #[test]
+    fn candles_keep_the_oxygen_up() {
+        let mut without = boat(Atmosphere::new(100.0, 0, false));
+        let mut with = boat(Atmosphere::new(100.0, 4, false));
+        hours(&mut without, 11);
+        hours(&mut with, 11);
+        assert!(air(&without).oxygen < FOUL_OXYGEN.0);
+        assert!(air(&with).oxygen > FOUL_OXYGEN.0);
+        assert_eq!(air(&with).candles, 0);
+    }
+
+    #[test]
+    fn snorkel_airs_the_boat() {
+        let mut foul = Atmosphere::new(0.0, 0, true);
+        foul.co2 = 0.04;
+        foul.oxygen = 0.16;
+        let mut world = boat(foul.clone());
+        hours(&mut world, 1);
+        assert!(air(&world).co2 > 0.04);
+
+        world.entity_mut(1).unwrap().depth = PERISCOPE_DEPTH;
+        hours(&mut world, 1);
+        assert!(!air(&world).is_foul());
+        assert_eq!(performance(world.entity(1).unwrap()), 1.0);
+
+        foul.snorkel = false;
+        let mut world = boat(foul);
+        world.entity_mut(1).unwrap().depth = PERISCOPE_DEPTH;
+        hours(&mut world, 1);
+        assert!(air(&world).is_foul());
+    }
+}
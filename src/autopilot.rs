@@ -0,0 +1,237 @@
+use crate::environment::Environment;
+use crate::physics::{turn_towards, Point, KNOT};
+use crate::tuning::{Tunable, Tuning};
+use crate::world::Entity;
+
+// #############################
+// #    TACTICAL AUTOPILOT     #
+// #############################
+
+// Rather than steering, trimming and ringing the engines by hand, a captain
+// can hand the boat to the autopilot with a standing order:
+//
+// autopilot layer                  hug the layer from just below it
+// autopilot sprint 12000 4000 10   sprint and drift to a point, stopping
+//                                  to listen every 10 minutes
+// autopilot off
+//
+// The two orders combine: a boat can sprint and drift to its waypoint
+// while keeping under the layer. Hugging the layer hides the boat from
+// ships above it while leaving it as close as can be to hear them through
+// it; without a layer the boat holds its depth. Sprinting covers ground
+// but deafens the boat with its own flow noise, so the sprints are broken
+// by slow listening stops; at the waypoint the boat slows and listens. The
+// AI uses the same orders (see ai.rs), the player's boat carries them out
+// at the rates of its helm, engines and planes.
+
+/// Meters under the layer a boat hugging it keeps at
+const HUG_MARGIN: f32 = 15.0;
+/// Fraction of the maximum speed used to sprint
+pub const SPRINT_FACTOR: f32 = 0.7;
+/// Meters per second of a boat stopped to listen
+pub const DRIFT_SPEED: f32 = 3.0 * KNOT;
+/// Seconds of each listening stop
+const LISTEN_TIME: f32 = 180.0;
+/// Meters from the waypoint at which a sprint is over
+const WAYPOINT_REACHED: f32 = 200.0;
+
+/// Depth just under the layer, `fallback` when the water has none
+pub fn below_layer(environment: &Environment, fallback: f32) -> f32 {
+    environment
+        .sound_speed
+        .layer_depth()
+        .map_or(fallback, |layer| layer + HUG_MARGIN)
+}
+
+/// Sprints toward a waypoint broken by listening stops
+#[derive(Debug, PartialEq, Clone)]
+pub struct SprintDrift {
+    pub waypoint: Point,
+    /// Seconds sprinted between two listening stops
+    pub interval: f32,
+    pub listening: bool,
+    /// Seconds left of the current sprint or stop
+    pub timer: f32,
+}
+
+impl SprintDrift {
+    pub fn new(waypoint: Point, interval: f32) -> SprintDrift {
+        SprintDrift {
+            waypoint,
+            interval,
+            listening: false,
+            timer: interval,
+        }
+    }
+
+    pub fn arrived(&self, position: &Point) -> bool {
+        position.distance_to(&self.waypoint) < WAYPOINT_REACHED
+    }
+
+    /// Carries the cycle of sprints and stops on by `dt` seconds
+    pub fn tick(&mut self, dt: f32) {
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            self.listening = !self.listening;
+            self.timer = if self.listening {
+                LISTEN_TIME
+            } else {
+                self.interval
+            };
+        }
+    }
+
+    /// Meters per second to make for a boat able of `max_speed`
+    pub fn speed(&self, max_speed: f32) -> f32 {
+        if self.listening {
+            DRIFT_SPEED
+        } else {
+            max_speed * SPRINT_FACTOR
+        }
+    }
+}
+
+/// Heading, speed and depth the autopilot wants; None where it leaves the
+/// boat alone
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Orders {
+    pub heading: Option<f32>,
+    pub speed: Option<f32>,
+    pub depth: Option<f32>,
+}
+
+/// The standing orders the boat is under
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Autopilot {
+    pub below_layer: bool,
+    pub sprint_drift: Option<SprintDrift>,
+}
+
+impl Autopilot {
+    pub fn is_engaged(&self) -> bool {
+        self.below_layer || self.sprint_drift.is_some()
+    }
+
+    /// What the autopilot wants of `boat` after `dt` more seconds
+    pub fn orders(
+        &mut self,
+        environment: &Environment,
+        boat: &Entity,
+        max_speed: f32,
+        dt: f32,
+    ) -> Orders {
+        let mut orders = Orders::default();
+        if self.below_layer && !boat.is_surfaced() && boat.transition.is_none() {
+            orders.depth = Some(below_layer(environment, boat.depth));
+        }
+        if let Some(sprint) = self.sprint_drift.as_mut() {
+            if sprint.arrived(&boat.position) {
+                orders.speed = Some(DRIFT_SPEED);
+            } else {
+                sprint.tick(dt);
+                orders.heading = Some(boat.position.angle_to(&sprint.waypoint));
+                orders.speed = Some(sprint.speed(max_speed));
+            }
+        }
+        orders
+    }
+}
+
+/// `current` moved by at most `step` towards `desired`
+pub fn approach(current: f32, desired: f32, step: f32) -> f32 {
+    if (desired - current).abs() <= step {
+        desired
+    } else {
+        current + step * (desired - current).signum()
+    }
+}
+
+/// Moves the helm, engines and planes of `boat` towards `orders` for `dt`
+/// seconds, never deeper than `safe_depth`, as fast as `tuning` lets it
+pub fn drive(
+    boat: &mut Entity,
+    orders: &Orders,
+    safe_depth: Option<f32>,
+    tuning: &Tuning,
+    dt: f32,
+) {
+    if let Some(heading) = orders.heading {
+        boat.heading = turn_towards(boat.heading, heading, Tunable::TurnRate.get(tuning) * dt);
+    }
+    if let Some(speed) = orders.speed {
+        boat.speed = approach(boat.speed, speed, Tunable::Acceleration.get(tuning) * dt);
+    }
+    if let Some(depth) = orders.depth {
+        let depth = safe_depth.map_or(depth, |safe| depth.min(safe));
+        boat.depth = approach(boat.depth, depth, Tunable::DepthRate.get(tuning) * dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::SoundSpeedProfile;
+    use crate::world::EntityKind;
+
+    fn boat() -> Entity {
+        let mut boat = Entity::new("U-48", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 50.0;
+        boat
+    }
+
+    #[test]
+    fn hugs_the_layer() {
+        let mut environment = Environment::default();
+        let mut boat = boat();
+        let mut autopilot = Autopilot {
+            below_layer: true,
+            sprint_drift: None,
+        };
+        for _ in 0..60 {
+            let orders = autopilot.orders(&environment, &boat, 8.0, 1.0);
+            drive(&mut boat, &orders, None, &Tuning::default(), 1.0);
+        }
+        assert_eq!(boat.depth, 60.0 + HUG_MARGIN);
+        assert_eq!(boat.speed, 0.0);
+
+        // without a layer the boat holds its depth
+        environment.sound_speed = SoundSpeedProfile {
+            points: vec![(0.0, 1500.0), (400.0, 1485.0)],
+        };
+        let orders = autopilot.orders(&environment, &boat, 8.0, 1.0);
+        assert_eq!(orders.depth, Some(60.0 + HUG_MARGIN));
+    }
+
+    #[test]
+    fn sprints_and_listens_to_the_waypoint() {
+        let environment = Environment::default();
+        let mut boat = boat();
+        let mut autopilot = Autopilot {
+            below_layer: false,
+            sprint_drift: Some(SprintDrift::new(
+                Point {
+                    x: 0.0,
+                    y: 12_000.0,
+                },
+                600.0,
+            )),
+        };
+        let mut stops = 0;
+        let mut listening = false;
+        for _ in 0..6000 {
+            let orders = autopilot.orders(&environment, &boat, 8.0, 1.0);
+            drive(&mut boat, &orders, None, &Tuning::default(), 1.0);
+            boat.position.x += boat.heading.cos() * boat.speed;
+            boat.position.y += boat.heading.sin() * boat.speed;
+            let sprint = autopilot.sprint_drift.as_ref().unwrap();
+            if sprint.listening && !listening {
+                stops += 1;
+            }
+            listening = sprint.listening;
+        }
+        assert!(stops >= 3, "{}", stops);
+        let sprint = autopilot.sprint_drift.as_ref().unwrap();
+        assert!(sprint.arrived(&boat.position), "{:?}", boat.position);
+        assert!((boat.speed - DRIFT_SPEED).abs() < 0.01);
+    }
+}
@@ -0,0 +1,141 @@
+use std::f32::consts::PI;
+
+use crate::physics::normalize_angle;
+use crate::sensors::SensorKind;
+use crate::world::Entity;
+
+// #############################
+// #          BAFFLES          #
+// #############################
+
+// The hull sonar is deaf astern, where the hull and the screw stand
+// between it and the sea (see sensors.rs). Whatever follows right in the
+// wake is not heard until the boat turns to look: a baffle clearing turn,
+// far enough for the arc that was astern to come out of the baffles. The
+// watch notes when the own ship last cleared them, advises the player once
+// it has gone too long without, and flags a contact gained in the arc
+// just cleared, while the turn is fresh: a boat trailing in the baffles.
+//
+// Any turn counts, whether ordered to clear the baffles or not: a boat
+// zigzagging along its route clears them as it goes.
+
+/// Seconds without clearing the baffles before the player is advised to
+const CLEAR_INTERVAL: f32 = 900.0;
+/// Seconds after a clearing turn began that a contact gained in the arc
+/// it clears is taken for a trailer
+const TRAILER_WINDOW: f32 = 180.0;
+
+/// Half the width of the baffles of the hull sonar
+fn half_width() -> f32 {
+    SensorKind::HullSonar.baffles().unwrap_or(0.0)
+}
+
+/// When the own ship last looked into its baffles
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct BaffleWatch {
+    /// Heading the baffles were last cleared on, None before the first
+    /// look
+    heading: Option<f32>,
+    /// Seconds into the scenario they were last cleared
+    cleared_at: f32,
+    /// Heading the arc being cleared lies astern of, and when the turn
+    /// began to clear it
+    turn: Option<(f32, f32)>,
+    /// Whether the player was advised since they were last cleared
+    advised: bool,
+}
+
+impl BaffleWatch {
+    /// Follows the turns of `own` at `time`; true when the player should
+    /// be advised to clear the baffles
+    pub fn update(&mut self, own: &Entity, time: f32) -> bool {
+        if !own.sensors.iter().any(|s| s.kind.baffles().is_some()) {
+            return false;
+        }
+        let heading = match self.heading {
+            Some(heading) => heading,
+            None => {
+                self.heading = Some(own.heading);
+                self.cleared_at = time;
+                return false;
+            }
+        };
+        let turned = normalize_angle(own.heading - heading).abs();
+        if turned >= half_width() && self.turn.is_none_or(|(from, _)| from != heading) {
+            self.turn = Some((heading, time));
+        }
+        if turned >= 2.0 * half_width() {
+            self.heading = Some(own.heading);
+            self.cleared_at = time;
+            self.advised = false;
+        }
+        if !self.advised && time - self.cleared_at >= CLEAR_INTERVAL {
+            self.advised = true;
+            return true;
+        }
+        false
+    }
+
+    /// Minutes since the baffles were last cleared at `time`
+    pub fn minutes_since(&self, time: f32) -> f32 {
+        (time - self.cleared_at) / 60.0
+    }
+
+    /// Whether a contact gained at `time` on `bearing` (game angle) is in
+    /// the arc a turn just cleared
+    pub fn trailing(&self, bearing: f32, time: f32) -> bool {
+        self.turn.is_some_and(|(heading, since)| {
+            time - since <= TRAILER_WINDOW
+                && normalize_angle(bearing - heading - PI).abs() < half_width()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::sensors::Sensor;
+    use crate::world::EntityKind;
+
+    fn boat() -> Entity {
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        boat
+    }
+
+    #[test]
+    fn advised_once_until_cleared() {
+        let mut boat = boat();
+        let mut watch = BaffleWatch::default();
+        assert!(!watch.update(&boat, 0.0));
+        assert!(!watch.update(&boat, 600.0));
+        assert!(watch.update(&boat, 900.0));
+        assert!(!watch.update(&boat, 1000.0));
+        assert_eq!(watch.minutes_since(1200.0), 20.0);
+        // a small turn does not clear them, a wide one does
+        boat.heading = 20_f32.to_radians();
+        watch.update(&boat, 1010.0);
+        assert_eq!(watch.minutes_since(1200.0), 20.0);
+        boat.heading = 70_f32.to_radians();
+        assert!(!watch.update(&boat, 1020.0));
+        assert_eq!(watch.minutes_since(1020.0), 0.0);
+        assert!(watch.update(&boat, 1920.0));
+        let deaf = Entity::new("SS Deaf", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        assert!(!BaffleWatch::default().update(&deaf, 0.0));
+    }
+
+    #[test]
+    fn trailers_gained_in_the_arc_cleared() {
+        let mut boat = boat();
+        let mut watch = BaffleWatch::default();
+        watch.update(&boat, 0.0);
+        assert!(!watch.trailing(PI, 10.0));
+        boat.heading = 40_f32.to_radians();
+        watch.update(&boat, 100.0);
+        // what was right astern is heard off the quarter
+        assert!(watch.trailing(PI, 110.0));
+        assert!(!watch.trailing(PI / 2.0, 110.0));
+        assert!(!watch.trailing(PI, 100.0 + TRAILER_WINDOW + 1.0));
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::editor::EditError;
+use crate::events::Event;
+use crate::random::Rng;
+use crate::scenario::Scenario;
+
+// #############################
+// #    MONTE CARLO SWEEPS     #
+// #############################
+
+// For tuning by numbers rather than by feel: a scenario is run headlessly
+// many times, each run with its own seed, for every value of one key of
+// the scenario file, and how each run came out is written as CSV, one row
+// a run, ready for a spreadsheet:
+//
+// subsim sweep <file> <seconds> <runs> <section.key> <values>
+// subsim sweep duel.cfg 1800 50 reliability.dud 0:0.3:0.05
+// subsim sweep duel.cfg 1800 50 class.type_viic.max_speed 15,17.7,20
+//
+// The key is named by its section and itself, the last dot between them;
+// the values are listed with commas or given as "from:to:step". With
+// "summary" at the end, a row a value instead gives the mean and spread
+// of every outcome.
+
+/// A key of the scenario file
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parameter {
+    pub section: String,
+    pub key: String,
+}
+
+impl FromStr for Parameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once('.') {
+            Some((section, key)) if !section.is_empty() && !key.is_empty() => Ok(Parameter {
+                section: section.to_string(),
+                key: key.to_string(),
+            }),
+            _ => Err(format!("expected <section>.<key>, found '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.section, self.key)
+    }
+}
+
+/// Reads "a,b,c" or "from:to:step"
+pub fn values(s: &str) -> Result<Vec<String>, String> {
+    let bounds: Vec<&str> = s.split(':').collect();
+    if let [from, to, step] = bounds.as_slice() {
+        let number = |w: &str| {
+            w.parse::<f64>()
+                .map_err(|_| format!("expected a number, found '{}'", w))
+        };
+        let (from, to, step) = (number(from)?, number(to)?, number(step)?);
+        if step <= 0.0 {
+            return Err(format!("step must be positive, found {}", step));
+        }
+        let count = ((to - from) / step + 1e-9).floor() as usize + 1;
+        // rounded so that 0.1 steps do not show as 0.30000000000000004
+        return Ok((0..count)
+            .map(|i| ((from + step * i as f64) * 1e6).round() / 1e6)
+            .map(|v| v.to_string())
+            .collect());
+    }
+    let list: Vec<String> = s
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if list.is_empty() {
+        return Err("no values to sweep".to_string());
+    }
+    Ok(list)
+}
+
+/// How one run came out
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct RunOutcome {
+    pub torpedoes_fired: u32,
+    pub torpedo_hits: u32,
+    pub torpedo_failures: u32,
+    /// Vessels other than the own ship destroyed
+    pub sunk: u32,
+    /// Own ship hull left, 0 when lost
+    pub own_hull: f32,
+}
+
+/// Names of the outcomes, in the order of `RunOutcome::values`
+const OUTCOMES: [&str; 5] = [
+    "torpedoes_fired",
+    "torpedo_hits",
+    "torpedo_failures",
+    "sunk",
+    "own_hull",
+];
+
+impl RunOutcome {
+    fn values(&self) -> [f32; 5] {
+        [
+            self.torpedoes_fired as f32,
+            self.torpedo_hits as f32,
+            self.torpedo_failures as f32,
+            self.sunk as f32,
+            self.own_hull,
+        ]
+    }
+}
+
+/// Runs `scenario` for `seconds` on `seed`
+pub fn run_once(scenario: &Config, seconds: u32, seed: u64) -> Result<RunOutcome, EditError> {
+    let mut sim = Scenario::from_config(scenario)?
+        .build()
+        .map_err(EditError::Invalid)?;
+    sim.world.rng = Rng::new(seed);
+    for _ in 0..seconds {
+        sim.step(1.0);
+    }
+    let mut outcome = RunOutcome {
+        own_hull: sim.own_ship().map_or(0.0, |s| s.hull.max(0.0)),
+        ..RunOutcome::default()
+    };
+    for timed in &sim.world.events {
+        match &timed.event {
+            Event::TorpedoFired { .. } => outcome.torpedoes_fired += 1,
+            Event::TorpedoHit { .. } => outcome.torpedo_hits += 1,
+            Event::TorpedoFailed { .. } => outcome.torpedo_failures += 1,
+            Event::Destroyed { entity } if *entity != sim.player => outcome.sunk += 1,
+            _ => {}
+        }
+    }
+    Ok(outcome)
+}
+
+/// Every run of a sweep
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sweep {
+    pub parameter: Parameter,
+    /// Value, seed and outcome of every run
+    pub runs: Vec<(String, u64, RunOutcome)>,
+}
+
+impl Sweep {
+    /// Runs `scenario` `runs` times, seeds 1 up, for each of `values`
+    pub fn run(
+        scenario: &Config,
+        seconds: u32,
+        runs: u64,
+        parameter: Parameter,
+        values: &[String],
+    ) -> Result<Sweep, EditError> {
+        let mut sweep = Sweep {
+            parameter,
+            runs: Vec::new(),
+        };
+        for value in values {
+            let mut config = scenario.clone();
+            config
+                .section_mut(&sweep.parameter.section)
+                .set(&sweep.parameter.key, value);
+            for seed in 1..=runs {
+                let outcome = run_once(&config, seconds, seed)?;
+                sweep.runs.push((value.clone(), seed, outcome));
+            }
+        }
+        Ok(sweep)
+    }
+
+    /// A row a run
+    pub fn csv(&self) -> String {
+        let mut csv = format!("{},seed,{}\n", self.parameter, OUTCOMES.join(","));
+        for (value, seed, outcome) in &self.runs {
+            let cells: Vec<String> = outcome.values().iter().map(|v| v.to_string()).collect();
+            csv.push_str(&format!("{},{},{}\n", value, seed, cells.join(",")));
+        }
+        csv
+    }
+
+    /// A row a value: runs, then the mean, standard deviation, least and
+    /// most of every outcome
+    pub fn summary(&self) -> String {
+        let mut header = vec![self.parameter.to_string(), "runs".to_string()];
+        for name in OUTCOMES {
+            for statistic in ["mean", "sd", "min", "max"] {
+                header.push(format!("{}_{}", name, statistic));
+            }
+        }
+        let mut csv = header.join(",") + "\n";
+        let mut values: Vec<&String> = Vec::new();
+        for (value, _, _) in &self.runs {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        for value in values {
+            let outcomes: Vec<[f32; 5]> = self
+                .runs
+                .iter()
+                .filter(|(v, _, _)| v == value)
+                .map(|(_, _, o)| o.values())
+                .collect();
+            let n = outcomes.len() as f32;
+            let mut row = vec![value.clone(), outcomes.len().to_string()];
+            for i in 0..OUTCOMES.len() {
+                let column: Vec<f32> = outcomes.iter().map(|o| o[i]).collect();
+                let mean = column.iter().sum::<f32>() / n;
+                let variance = column.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / n;
+                let min = column.iter().cloned().fold(f32::INFINITY, f32::min);
+                let max = column.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                row.push(format!("{:.3}", mean));
+                row.push(format!("{:.3}", variance.sqrt()));
+                row.push(min.to_string());
+                row.push(max.to_string());
+            }
+            csv.push_str(&(row.join(",") + "\n"));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_parameters_and_values() {
+        assert_eq!(
+            "class.type_viic.max_speed".parse(),
+            Ok(Parameter {
+                section: "class.type_viic".to_string(),
+                key: "max_speed".to_string(),
+            })
+        );
+        assert!("speed".parse::<Parameter>().is_err());
+        assert_eq!(values("0:0.3:0.1").unwrap(), vec!["0", "0.1", "0.2", "0.3"]);
+        assert_eq!(values("15, 17.7,20").unwrap(), vec!["15", "17.7", "20"]);
+        assert!(values("1:2:0").is_err());
+        assert!(values(",").is_err());
+    }
+
+    #[test]
+    fn sweeps_a_key() {
+        let scenario = Config::parse(
+            "
+[scenario]
+name = Sweep
+player = U-99
+
+[class.type_viic]
+kind = submarine
+max_speed = 17.7
+
+[entity.U-99]
+class = type_viic
+x = 0
+y = 0
+speed = 5
+",
+        )
+        .unwrap();
+        let parameter: Parameter = "entity.U-99.hull".parse().unwrap();
+        let values = values("0.5,1").unwrap();
+        let sweep = Sweep::run(&scenario, 10, 3, parameter, &values).unwrap();
+        assert_eq!(sweep.runs.len(), 6);
+        let csv = sweep.csv();
+        assert_eq!(csv.lines().count(), 7);
+        assert!(csv.starts_with("entity.U-99.hull,seed,torpedoes_fired"));
+        assert!(csv.lines().nth(1).unwrap().ends_with(",0.5"));
+        let summary = sweep.summary();
+        assert_eq!(summary.lines().count(), 3);
+        assert!(summary.lines().nth(2).unwrap().starts_with("1,3,"));
+    }
+}
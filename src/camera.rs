@@ -0,0 +1,199 @@
+use crate::physics::{normalize_angle, Point};
+use crate::seakeeping;
+use crate::world::{EntityId, EntityKind, World};
+
+// #############################
+// #        CAMERA FEED        #
+// #############################
+
+// The external view: where every entity is and how it sits in the water,
+// for a graphical client drawing at its own frame rate. The simulation
+// ticks at a fixed step; after each tick a Frame of the poses is pushed to
+// the CameraFeed, and the client asks for the poses at any time between
+// the last two ticks, interpolated. Drawing one tick behind the simulation
+// keeps the motion smooth at the cost of one tick of latency.
+//
+// Angles are game angles, in radians. Pitch and roll are the motion in a
+// seaway (see seakeeping.rs) plus what is made up from how the entity
+// moved since the previous frame: the bow goes down when diving, and a
+// ship heels out of a turn.
+
+/// Meters per second squared
+const GRAVITY: f32 = 9.81;
+/// Fraction of the turning acceleration a hull heels to
+const HEEL_RESPONSE: f32 = 0.5;
+/// Radians, heel of the tightest turn
+const MAX_HEEL: f32 = 0.35;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pose {
+    pub id: EntityId,
+    pub kind: EntityKind,
+    pub position: Point,
+    /// Meters, positive down
+    pub depth: f32,
+    pub heading: f32,
+    /// Bow up positive
+    pub pitch: f32,
+    /// Starboard down positive
+    pub roll: f32,
+}
+
+/// Poses of every entity at one tick
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Frame {
+    /// Seconds into the scenario
+    pub time: f32,
+    pub poses: Vec<Pose>,
+}
+
+impl Frame {
+    /// Poses of the entities of `world`, pitch and roll taken from their
+    /// motion since `previous`
+    pub fn capture(world: &World, previous: &Frame) -> Frame {
+        let dt = world.time - previous.time;
+        let poses = world
+            .entities
+            .iter()
+            .filter(|e| !e.is_destroyed())
+            .map(|e| {
+                let before = previous.poses.iter().find(|p| p.id == e.id);
+                let (pitch, roll) = match before {
+                    Some(before) if dt > 0.0 => {
+                        let run = before.position.distance_to(&e.position);
+                        let pitch = (before.depth - e.depth).atan2(run);
+                        let turn_rate = normalize_angle(e.heading - before.heading) / dt;
+                        let heel = (turn_rate * e.speed / GRAVITY).atan() * HEEL_RESPONSE;
+                        (pitch, heel.clamp(-MAX_HEEL, MAX_HEEL))
+                    }
+                    _ => (0.0, 0.0),
+                };
+                let (sea_pitch, sea_roll) = seakeeping::attitude(e, &world.environment, world.time);
+                Pose {
+                    id: e.id,
+                    kind: e.kind,
+                    position: e.position.clone(),
+                    depth: e.depth,
+                    heading: e.heading,
+                    pitch: pitch + sea_pitch,
+                    roll: roll + sea_roll,
+                }
+            })
+            .collect();
+        Frame {
+            time: world.time,
+            poses,
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// The pose a fraction `t` of the way from `from` to `to`
+fn between(from: &Pose, to: &Pose, t: f32) -> Pose {
+    Pose {
+        id: to.id,
+        kind: to.kind,
+        position: Point {
+            x: lerp(from.position.x, to.position.x, t),
+            y: lerp(from.position.y, to.position.y, t),
+        },
+        depth: lerp(from.depth, to.depth, t),
+        heading: normalize_angle(from.heading + normalize_angle(to.heading - from.heading) * t),
+        pitch: lerp(from.pitch, to.pitch, t),
+        roll: lerp(from.roll, to.roll, t),
+    }
+}
+
+/// The last two frames, to draw between
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct CameraFeed {
+    pub previous: Frame,
+    pub current: Frame,
+}
+
+impl CameraFeed {
+    /// Records the world as it is after a tick
+    pub fn push(&mut self, world: &World) {
+        let frame = Frame::capture(world, &self.current);
+        self.previous = std::mem::replace(&mut self.current, frame);
+    }
+
+    /// Poses at `time`, interpolated between the last two frames; times
+    /// outside them give the nearest frame. Entities that appeared in the
+    /// last frame are where they appeared, and those gone are left out.
+    pub fn poses(&self, time: f32) -> Vec<Pose> {
+        let span = self.current.time - self.previous.time;
+        let t = if span > 0.0 {
+            ((time - self.previous.time) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        self.current
+            .poses
+            .iter()
+            .map(
+                |to| match self.previous.poses.iter().find(|p| p.id == to.id) {
+                    Some(from) => between(from, to, t),
+                    None => to.clone(),
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Entity;
+    use std::f32::consts::PI;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.environment.sea_state = 0;
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.speed = 5.0;
+        boat.heading = PI;
+        world.spawn(boat);
+        world
+    }
+
+    #[test]
+    fn interpolates_between_ticks() {
+        let mut world = world();
+        let mut feed = CameraFeed::default();
+        feed.push(&world);
+        world.step(1.0);
+        {
+            let boat = world.entity_mut(1).unwrap();
+            boat.depth = 5.0;
+            boat.heading = -PI + 0.1;
+        }
+        feed.push(&world);
+        let halfway = feed.poses(0.5);
+        assert_eq!(halfway.len(), 1);
+        assert!(halfway[0].position.distance_to(&Point { x: -2.5, y: 0.0 }) < 0.001);
+        assert_eq!(halfway[0].depth, 2.5);
+        // on through west, not all the way round
+        assert!((halfway[0].heading - (-PI + 0.05)).abs() < 0.001);
+        assert!(halfway[0].pitch < 0.0);
+        assert_eq!(feed.poses(7.0)[0].depth, 5.0);
+    }
+
+    #[test]
+    fn heels_out_of_a_turn() {
+        let mut world = world();
+        let mut feed = CameraFeed::default();
+        feed.push(&world);
+        world.step(1.0);
+        world.entity_mut(1).unwrap().heading += 0.1;
+        feed.push(&world);
+        let roll = feed.current.poses[0].roll;
+        assert!(roll > 0.0 && roll <= MAX_HEEL);
+        world.step(1.0);
+        feed.push(&world);
+        assert_eq!(feed.current.poses[0].roll, 0.0);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::command::Command;
+use crate::contacts::{Contact, ContactUpdate};
+use crate::helm::Helm;
+use crate::physics::{game_to_user_angle, Point};
+use crate::simulation::Simulation;
+use crate::units::{Knots, MetersPerSecond};
+use crate::world::EntityId;
+
+// #############################
+// #       BOT CAPTAINS        #
+// #############################
+
+// A bot captain commands a boat the way the player does: every so often it
+// is shown what the player would see, its own ship, the contacts it holds
+// and the messages since it last looked, and answers with commands, run
+// as if typed (see command.rs). Captains are written in Rust:
+//
+// struct Rusher;
+//
+// impl BotCaptain for Rusher {
+//     fn name(&self) -> &str { "rusher" }
+//     fn orders(&mut self, view: &CaptainView) -> Vec<Command> {
+//         vec![Command::parse("rig quiet").unwrap()]
+//     }
+// }
+//
+// and seated in an arena, one to a boat of a scenario, to fight it out
+// headlessly. Boats with no captain keep their AI; a seated boat loses
+// its own. What the simulation keeps for the own ship is kept for each
+// seated boat on its helm, see helm.rs.
+
+/// Seconds between the orders of the captains, unless set otherwise
+const ORDERS_INTERVAL: f32 = 10.0;
+
+/// The own ship as its captain sees it
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnShip {
+    pub position: Point,
+    pub depth: f32,
+    /// User angle, degrees
+    pub heading: f32,
+    pub speed: Knots,
+    /// 1 (intact) to 0 (destroyed)
+    pub hull: f32,
+}
+
+/// What a captain is shown before giving orders
+#[derive(Debug, PartialEq, Clone)]
+pub struct CaptainView {
+    /// Seconds into the scenario
+    pub time: f32,
+    /// None once the boat is lost
+    pub own: Option<OwnShip>,
+    pub contacts: Vec<Contact>,
+    /// Contacts gained, lost and so on, since last shown
+    pub changes: Vec<ContactUpdate>,
+    /// Reports, alerts and errors of the last orders, since last shown
+    pub messages: Vec<String>,
+}
+
+pub trait BotCaptain {
+    fn name(&self) -> &str;
+
+    /// The commands to give, having seen `view`
+    fn orders(&mut self, view: &CaptainView) -> Vec<Command>;
+}
+
+/// A captain and what the simulation keeps for the boat it commands
+struct Seat {
+    captain: Box<dyn BotCaptain>,
+    helm: Helm,
+    /// Alerts already shown
+    alerts_seen: usize,
+    /// Contact changes already shown
+    changes_seen: usize,
+    /// Errors of the last orders, not yet shown
+    errors: Vec<String>,
+}
+
+impl Seat {
+    /// What the captain is shown, `sim` working for the seat
+    fn view(&mut self, sim: &Simulation, reports: &[String]) -> CaptainView {
+        let own = sim
+            .own_ship()
+            .filter(|ship| !ship.is_destroyed())
+            .map(|ship| OwnShip {
+                position: ship.position.clone(),
+                depth: ship.depth,
+                heading: game_to_user_angle(ship.heading),
+                speed: Knots::from(MetersPerSecond(ship.speed)),
+                hull: ship.hull,
+            });
+        let mut messages = reports.to_vec();
+        messages.extend(
+            sim.alerts[self.alerts_seen..]
+                .iter()
+                .map(|a| a.describe(&sim.messages, &sim.preferences, &sim.world.environment)),
+        );
+        self.alerts_seen = sim.alerts.len();
+        messages.append(&mut self.errors);
+        let changes = sim.contacts.changes[self.changes_seen..].to_vec();
+        self.changes_seen = sim.contacts.changes.len();
+        CaptainView {
+            time: sim.world.time,
+            own,
+            contacts: sim.contacts.contacts.clone(),
+            changes,
+            messages,
+        }
+    }
+}
+
+/// How a captain's boat came out of a bout
+#[derive(Debug, PartialEq, Clone)]
+pub struct Standing {
+    pub captain: String,
+    pub entity: EntityId,
+    /// 1 (intact) to 0 (destroyed)
+    pub hull: f32,
+}
+
+/// Captains pitted against each other in one simulation
+pub struct Arena {
+    pub sim: Simulation,
+    /// Seconds between the orders of the captains
+    pub interval: f32,
+    seats: Vec<Seat>,
+    /// Reports of the simulation already shown
+    reports_seen: usize,
+    /// Seconds until the captains give orders again
+    until_orders: f32,
+}
+
+impl Arena {
+    pub fn new(sim: Simulation) -> Arena {
+        Arena {
+            sim,
+            interval: ORDERS_INTERVAL,
+            seats: Vec::new(),
+            reports_seen: 0,
+            until_orders: 0.0,
+        }
+    }
+
+    /// Gives the entity named `entity` to `captain`
+    pub fn seat(&mut self, entity: &str, captain: Box<dyn BotCaptain>) -> Result<(), String> {
+        let id = match self.sim.world.entities.by_name(entity) {
+            Some(ship) => ship.id,
+            None => return Err(format!("no entity '{}' in the scenario", entity)),
+        };
+        if self.seats.iter().any(|s| s.helm.entity == id) {
+            return Err(format!("'{}' already has a captain", entity));
+        }
+        if let Some(ship) = self.sim.world.entity_mut(id) {
+            ship.ai = None;
+        }
+        self.seats.push(Seat {
+            captain,
+            helm: Helm::new(id, &self.sim.contacts),
+            alerts_seen: 0,
+            changes_seen: 0,
+            errors: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Runs one tick: every boat commanded is steered and listens, the
+    /// captains give orders when due, then the world moves on
+    pub fn step(&mut self, dt: f32) {
+        let due = self.until_orders <= 0.0;
+        if due {
+            self.until_orders += self.interval;
+        }
+        self.until_orders -= dt;
+        let reports = self.sim.reports[self.reports_seen..].to_vec();
+        if !self.seats.iter().any(|s| s.helm.entity == self.sim.player) {
+            self.sim.sense(dt);
+        }
+        for seat in &mut self.seats {
+            seat.helm.swap(&mut self.sim);
+            self.sim.sense(dt);
+            if due {
+                let view = seat.view(&self.sim, &reports);
+                for command in seat.captain.orders(&view) {
+                    if let Err(e) = self.sim.execute(&command) {
+                        seat.errors.push(format!(
+                            "{}: {}",
+                            command,
+                            e.describe(&self.sim.messages, &self.sim.preferences)
+                        ));
+                    }
+                }
+            }
+            seat.helm.swap(&mut self.sim);
+        }
+        if due {
+            self.reports_seen = self.sim.reports.len();
+        }
+        self.sim.advance(dt);
+    }
+
+    /// Runs `seconds` of one second ticks, stopping early when at most one
+    /// captain still has a boat
+    pub fn run(&mut self, seconds: u32) {
+        for _ in 0..seconds {
+            self.step(1.0);
+            let afloat = self.standings().iter().filter(|s| s.hull > 0.0).count();
+            if afloat <= 1 && self.seats.len() > 1 {
+                break;
+            }
+        }
+    }
+
+    /// The captains, with what is left of their boats
+    pub fn standings(&self) -> Vec<Standing> {
+        self.seats
+            .iter()
+            .map(|seat| Standing {
+                captain: seat.captain.name().to_string(),
+                entity: seat.helm.entity,
+                hull: self
+                    .sim
+                    .world
+                    .entity(seat.helm.entity)
+                    .map_or(0.0, |ship| ship.hull.max(0.0)),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::user_to_game_angle;
+    use crate::world::{Entity, EntityKind, World};
+
+    /// Steers for a point, and asks for a tube it does not have
+    struct Helmsman {
+        to: Point,
+        views: Vec<CaptainView>,
+    }
+
+    impl BotCaptain for Helmsman {
+        fn name(&self) -> &str {
+            "helmsman"
+        }
+
+        fn orders(&mut self, view: &CaptainView) -> Vec<Command> {
+            self.views.push(view.clone());
+            vec![
+                Command::parse(&format!("course {} {}", self.to.x, self.to.y)).unwrap(),
+                Command::parse("door open 9").unwrap(),
+            ]
+        }
+    }
+
+    fn arena() -> Arena {
+        let mut world = World::new();
+        for (name, x) in [("U-47", -3000.0), ("U-99", 3000.0)] {
+            let mut sub = Entity::new(name, EntityKind::Submarine, Point { x, y: 0.0 });
+            sub.speed = 4.0;
+            sub.heading = user_to_game_angle(90.0);
+            world.spawn(sub);
+        }
+        let player = world.entities.by_name("U-47").unwrap().id;
+        Arena::new(Simulation::new(world, player))
+    }
+
+    #[test]
+    fn captains_command_their_own_boats() {
+        let mut arena = arena();
+        for (name, y) in [("U-47", 5000.0), ("U-99", -5000.0)] {
+            let captain = Helmsman {
+                to: Point { x: 0.0, y },
+                views: Vec::new(),
+            };
+            arena.seat(name, Box::new(captain)).unwrap();
+        }
+        assert!(arena
+            .seat(
+                "U-99",
+                Box::new(Helmsman {
+                    to: Point { x: 0.0, y: 0.0 },
+                    views: Vec::new(),
+                })
+            )
+            .is_err());
+        arena.run(300);
+        let north = arena.sim.world.entities.by_name("U-47").unwrap();
+        let south = arena.sim.world.entities.by_name("U-99").unwrap();
+        assert!(north.position.y > 500.0, "{:?}", north.position);
+        assert!(south.position.y < -500.0, "{:?}", south.position);
+        let standings = arena.standings();
+        assert_eq!(standings.len(), 2);
+        assert!(standings.iter().all(|s| s.captain == "helmsman"));
+    }
+
+    #[test]
+    fn captains_hear_of_their_errors() {
+        let mut arena = arena();
+        arena
+            .seat(
+                "U-99",
+                Box::new(Helmsman {
+                    to: Point {
+                        x: 3000.0,
+                        y: 3000.0,
+                    },
+                    views: Vec::new(),
+                }),
+            )
+            .unwrap();
+        arena.interval = 5.0;
+        arena.run(6);
+        // the errors of the last orders wait for the next view
+        let seat = &arena.seats[0];
+        assert_eq!(seat.errors.len(), 1);
+        assert!(seat.errors[0].starts_with("door open 9"));
+        assert_eq!(
+            arena.sim.player,
+            arena.sim.world.entities.by_name("U-47").unwrap().id
+        );
+    }
+}
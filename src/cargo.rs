@@ -0,0 +1,315 @@
+use crate::events::Event;
+use crate::torpedo::TORPEDO_DAMAGE;
+use crate::transient::{self, TransientKind};
+use crate::world::{EntityId, World};
+
+// #############################
+// #    TONNAGE AND CARGO      #
+// #############################
+
+// A merchant is sunk by flooding, not by a number: its hull is divided into
+// watertight compartments, and it stays afloat until enough of them are
+// open to the sea. How many depends on what it carries: an ammunition
+// ship goes up with the first hit, ore takes a ship down fast, timber
+// keeps one afloat long after it should have gone. Classes give it:
+//
+// [class.liberty]
+// kind = merchant
+// tonnage = 7176          # gross register tons
+// cargo = general         # general, ammunition, fuel, ore, timber or ballast
+// compartments = 5        # watertight compartments of the hull
+//
+// Each torpedo floods a compartment. One that leaves the ship afloat
+// cripples it: it lists over and slows down, stopping and drifting once
+// half of what it can take is flooded, and with a cargo that burns it is
+// on fire, losing hull until it goes down by itself, or is finished off
+// with the deck gun or a second fish.
+//
+// A fire in an ammunition ship or a tanker sets off secondary explosions
+// from time to time, and every hull that goes down breaks up on its way;
+// both are loud transients, so sonar can tell a ship crippled from one
+// sunk without putting up the periscope.
+
+/// Compartments of a hull, unless the class says otherwise
+pub const DEFAULT_COMPARTMENTS: u32 = 5;
+/// Hull a fire aboard burns through in a second: a hulk burns out in two
+/// hours
+const BURN_RATE: f32 = 1.0 / 7200.0;
+/// Degrees a ship lists over with as much flooded as it can take
+const MAX_LIST: f32 = 25.0;
+/// Hull a secondary explosion takes off
+const SECONDARY_DAMAGE: f32 = 0.1;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CargoKind {
+    General,
+    Ammunition,
+    Fuel,
+    Ore,
+    Timber,
+    Ballast,
+}
+
+impl std::str::FromStr for CargoKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CargoKind, String> {
+        match s {
+            "general" => Ok(CargoKind::General),
+            "ammunition" => Ok(CargoKind::Ammunition),
+            "fuel" => Ok(CargoKind::Fuel),
+            "ore" => Ok(CargoKind::Ore),
+            "timber" => Ok(CargoKind::Timber),
+            "ballast" => Ok(CargoKind::Ballast),
+            other => Err(format!("unknown cargo '{}'", other)),
+        }
+    }
+}
+
+impl CargoKind {
+    /// Fraction of the compartments that can be flooded with the ship
+    /// staying afloat
+    fn reserve(&self) -> f32 {
+        match self {
+            CargoKind::Ammunition => 0.0,
+            CargoKind::Ore => 0.1,
+            CargoKind::General | CargoKind::Fuel => 0.3,
+            CargoKind::Ballast => 0.5,
+            CargoKind::Timber => 0.6,
+        }
+    }
+
+    /// Whether a ship hit with it aboard catches fire
+    pub fn burns(&self) -> bool {
+        matches!(
+            self,
+            CargoKind::General | CargoKind::Ammunition | CargoKind::Fuel
+        )
+    }
+
+    /// Secondary explosions an hour while it burns
+    fn explosions_per_hour(&self) -> f32 {
+        match self {
+            CargoKind::Ammunition => 6.0,
+            CargoKind::Fuel => 3.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// What a merchant carries and how much of it is flooded
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hold {
+    /// Gross register tons
+    pub tonnage: u32,
+    pub cargo: CargoKind,
+    pub compartments: u32,
+    pub flooded: u32,
+    pub burning: bool,
+    /// Meters per second it can still make, None while undamaged
+    pub speed_limit: Option<f32>,
+    /// Heard going down, see `update`
+    pub broken_up: bool,
+}
+
+impl Hold {
+    pub fn new(tonnage: u32, cargo: CargoKind, compartments: u32) -> Hold {
+        Hold {
+            tonnage,
+            cargo,
+            compartments: compartments.max(1),
+            flooded: 0,
+            burning: false,
+            speed_limit: None,
+            broken_up: false,
+        }
+    }
+
+    /// Compartments flooded that sink the ship
+    pub fn to_sink(&self) -> u32 {
+        let reserve = (self.compartments as f32 * self.cargo.reserve()).floor() as u32;
+        (reserve + 1).min(self.compartments)
+    }
+
+    /// Hit but afloat
+    pub fn is_crippled(&self) -> bool {
+        self.flooded > 0 && self.flooded < self.to_sink()
+    }
+
+    /// Fraction of the speed it had it can still make: less for each
+    /// compartment flooded, none once half of what it can take is
+    pub fn speed_factor(&self) -> f32 {
+        let to_sink = self.to_sink();
+        if self.flooded * 2 >= to_sink {
+            return 0.0;
+        }
+        1.0 - self.flooded as f32 / to_sink as f32
+    }
+
+    /// Degrees it lists over
+    pub fn list(&self) -> f32 {
+        MAX_LIST * (self.flooded as f32 / self.to_sink() as f32).min(1.0)
+    }
+}
+
+/// A torpedo strikes `target` with `factor` times the damage of a hit on
+/// the side (see fuse.rs): a ship with a hold floods a compartment, two
+/// with its back broken, losing hull in proportion to the compartments it
+/// has left to flood, and is crippled or sunk; others take the warhead on
+/// the hull
+pub fn torpedo_hit(world: &mut World, target: EntityId, factor: f32) {
+    let ship = match world.entity_mut(target) {
+        Some(ship) if !ship.is_destroyed() => ship,
+        _ => return,
+    };
+    let hull = ship.hull;
+    let hold = match ship.hold.as_mut() {
+        Some(hold) => hold,
+        None => return world.apply_damage(target, TORPEDO_DAMAGE * factor),
+    };
+    let left = hold.to_sink().saturating_sub(hold.flooded).max(1);
+    let compartments = (factor.round() as u32).max(1);
+    hold.flooded += compartments;
+    hold.burning |= hold.cargo.burns();
+    let crippled = hold.is_crippled();
+    if crippled {
+        let limit = ship.speed * hold.speed_factor();
+        hold.speed_limit = Some(limit);
+        ship.speed = limit;
+    }
+    world.apply_damage(target, hull * (compartments as f32 / left as f32).min(1.0));
+    if crippled {
+        world.emit(Event::Crippled { entity: target });
+    }
+}
+
+/// Slows the crippled, burns the fires aboard, sets off their cargo and
+/// breaks up the ships gone down
+pub fn update(world: &mut World, dt: f32) {
+    let mut burning = Vec::new();
+    let mut breaking = Vec::new();
+    for ship in world.entities.iter_mut() {
+        let destroyed = ship.is_destroyed();
+        let hold = match ship.hold.as_mut() {
+            Some(hold) => hold,
+            None => continue,
+        };
+        if destroyed {
+            if !hold.broken_up {
+                hold.broken_up = true;
+                breaking.push(ship.id);
+            }
+            continue;
+        }
+        if let Some(limit) = hold.speed_limit {
+            ship.speed = ship.speed.min(limit);
+        }
+        if hold.burning {
+            burning.push((ship.id, hold.cargo.explosions_per_hour()));
+        }
+    }
+    for id in breaking {
+        transient::make(world, id, TransientKind::BreakingUp);
+    }
+    for (id, per_hour) in burning {
+        world.apply_damage(id, BURN_RATE * dt);
+        if world.rng.chance(per_hour * dt / 3600.0) {
+            transient::make(world, id, TransientKind::SecondaryExplosion);
+            world.apply_damage(id, SECONDARY_DAMAGE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::world::{Entity, EntityKind};
+
+    fn merchant(world: &mut World, cargo: CargoKind) -> EntityId {
+        let mut ship = Entity::new("SS Test", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        ship.speed = 4.0;
+        ship.hold = Some(Hold::new(7176, cargo, DEFAULT_COMPARTMENTS));
+        world.spawn(ship)
+    }
+
+    #[test]
+    fn cargo_sets_the_hits_to_sink() {
+        let to_sink = |cargo| Hold::new(7176, cargo, 5).to_sink();
+        assert_eq!(to_sink(CargoKind::Ammunition), 1);
+        assert_eq!(to_sink(CargoKind::Ore), 1);
+        assert_eq!(to_sink(CargoKind::General), 2);
+        assert_eq!(to_sink(CargoKind::Timber), 4);
+        assert_eq!(Hold::new(100, CargoKind::Timber, 1).to_sink(), 1);
+
+        let mut world = World::new();
+        let ship = merchant(&mut world, CargoKind::Ammunition);
+        torpedo_hit(&mut world, ship, 1.0);
+        assert!(world.entity(ship).unwrap().is_destroyed());
+        world.step(1.0);
+        world.step(1.0);
+        let breaking = Event::Transient {
+            entity: ship,
+            kind: TransientKind::BreakingUp,
+        };
+        assert_eq!(
+            world.events.iter().filter(|e| e.event == breaking).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn flooding_slows_and_lists() {
+        let mut world = World::new();
+        let ship = merchant(&mut world, CargoKind::Timber);
+        torpedo_hit(&mut world, ship, 1.0);
+        world.entity_mut(ship).unwrap().speed = 10.0;
+        world.step(1.0);
+        let hit = world.entity(ship).unwrap();
+        let hold = hit.hold.as_ref().unwrap();
+        assert_eq!(hit.speed, 3.0);
+        assert_eq!(hold.list(), 6.25);
+        assert!(!hold.burning);
+
+        let tanker = merchant(&mut world, CargoKind::Fuel);
+        torpedo_hit(&mut world, tanker, 1.0);
+        for _ in 0..600 {
+            world.step(1.0);
+        }
+        let explosions = world
+            .events
+            .iter()
+            .filter(|e| {
+                e.event
+                    == Event::Transient {
+                        entity: tanker,
+                        kind: TransientKind::SecondaryExplosion,
+                    }
+            })
+            .count();
+        assert!(explosions <= 3);
+        assert!(world.entity(tanker).unwrap().hull < 0.5 - 600.0 * BURN_RATE + 0.001);
+    }
+
+    #[test]
+    fn one_fish_cripples_and_a_second_sinks() {
+        let mut world = World::new();
+        let ship = merchant(&mut world, CargoKind::General);
+        torpedo_hit(&mut world, ship, 1.0);
+        let hulk = world.entity(ship).unwrap();
+        assert_eq!(hulk.hull, 0.5);
+        assert_eq!(hulk.speed, 0.0);
+        assert!(hulk.hold.as_ref().unwrap().burning);
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        let hull = world.entity(ship).unwrap().hull;
+        assert!(hull < 0.5 && hull > 0.0);
+        torpedo_hit(&mut world, ship, 1.0);
+        assert!(world.entity(ship).unwrap().is_destroyed());
+        assert!(world
+            .events
+            .iter()
+            .any(|e| e.event == Event::Crippled { entity: ship }));
+    }
+}
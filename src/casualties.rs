@@ -0,0 +1,338 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::events::{Event, TimedEvent};
+use crate::messages::Catalog;
+use crate::morale;
+use crate::random::Rng;
+use crate::sensors::SensorKind;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #   CASUALTIES AND MEDBAY   #
+// #############################
+
+// Every blow to the hull, whether the shock of a depth charge or a hit
+// letting the sea in, hurts some of the men aboard and kills a few. The
+// wounded are out of the fight until they recover, sooner with a corpsman
+// and sooner still with a doctor; untreated, some of them die of their
+// wounds; a crew in good spirits recovers faster (see morale.rs). A crew
+// short of hands works worse, and the men lost are counted
+// after the mission (see crew_losses). Classes give their complement:
+//
+// [class.type_viic]
+// complement = 44
+// medic = corpsman        # none, corpsman or doctor
+
+/// Chance of a man being hurt per unit of hull integrity lost
+const CASUALTY_RATE: f32 = 0.5;
+/// Fraction of the men hurt who are killed outright
+const KILLED_FRACTION: f32 = 0.3;
+/// Fraction of its complement a crew can lose before it works worse
+const SPARE_HANDS: f32 = 0.2;
+/// Least performance of a crew with hardly a man standing
+const MIN_PERFORMANCE: f32 = 0.1;
+
+/// Who looks after the wounded
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Medic {
+    #[default]
+    None,
+    Corpsman,
+    Doctor,
+}
+
+impl Medic {
+    /// Hours a wounded man takes to recover, on average
+    fn recovery_hours(&self) -> f32 {
+        match self {
+            Medic::None => 96.0,
+            Medic::Corpsman => 48.0,
+            Medic::Doctor => 24.0,
+        }
+    }
+
+    /// Hours until a wounded man dies of his wounds, on average
+    fn death_hours(&self) -> f32 {
+        match self {
+            Medic::None => 200.0,
+            Medic::Corpsman => 1_000.0,
+            Medic::Doctor => 5_000.0,
+        }
+    }
+}
+
+impl FromStr for Medic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Medic::None),
+            "corpsman" => Ok(Medic::Corpsman),
+            "doctor" => Ok(Medic::Doctor),
+            _ => Err(format!("unknown medic '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Medic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Medic::None => "none",
+            Medic::Corpsman => "corpsman",
+            Medic::Doctor => "doctor",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The state of the men aboard
+#[derive(Debug, PartialEq, Clone)]
+pub struct Casualties {
+    pub complement: u32,
+    pub wounded: u32,
+    pub killed: u32,
+    pub medic: Medic,
+}
+
+impl Casualties {
+    pub fn new(complement: u32, medic: Medic) -> Casualties {
+        Casualties {
+            complement,
+            wounded: 0,
+            killed: 0,
+            medic,
+        }
+    }
+
+    /// Men fit for duty
+    pub fn fit(&self) -> u32 {
+        self.complement - self.wounded - self.killed
+    }
+
+    /// 1 while the crew has hands to spare, down to 0.1 as it runs short
+    pub fn performance(&self) -> f32 {
+        if self.complement == 0 {
+            return 1.0;
+        }
+        let manning = self.fit() as f32 / self.complement as f32;
+        (manning / (1.0 - SPARE_HANDS)).clamp(MIN_PERFORMANCE, 1.0)
+    }
+
+    /// Hurts the men fit for duty for `damage` lost of the hull; returns
+    /// the men killed
+    fn wound(&mut self, damage: f32, rng: &mut Rng) -> u32 {
+        let mut killed = 0;
+        for _ in 0..self.fit() {
+            if rng.chance(damage * CASUALTY_RATE) {
+                if rng.chance(KILLED_FRACTION) {
+                    killed += 1;
+                } else {
+                    self.wounded += 1;
+                }
+            }
+        }
+        self.killed += killed;
+        killed
+    }
+
+    /// Looks after the wounded for `dt` seconds, recovering `spirits` times
+    /// as fast as usual; returns the men who died of their wounds
+    fn treat(&mut self, dt: f32, spirits: f32, rng: &mut Rng) -> u32 {
+        let hours = dt / 3600.0;
+        let mut died = 0;
+        let mut recovered = 0;
+        for _ in 0..self.wounded {
+            if rng.chance(spirits * hours / self.medic.recovery_hours()) {
+                recovered += 1;
+            } else if rng.chance(hours / self.medic.death_hours()) {
+                died += 1;
+            }
+        }
+        self.wounded -= recovered + died;
+        self.killed += died;
+        died
+    }
+}
+
+/// How well the crew of `entity` works with the hands it has, 1 at best
+pub fn performance(entity: &Entity) -> f32 {
+    entity.casualties.as_ref().map_or(1.0, |c| c.performance())
+}
+
+/// Hurts the crew of `id` for `damage` lost of the hull
+pub fn wound(world: &mut World, id: EntityId, damage: f32) {
+    let killed = match world.entities.get_mut(id) {
+        Some(entity) => match entity.casualties.as_mut() {
+            Some(casualties) => casualties.wound(damage, &mut world.rng),
+            None => return,
+        },
+        None => return,
+    };
+    if killed > 0 {
+        world.emit(Event::CrewKilled { entity: id, killed });
+    }
+}
+
+/// Looks after the wounded of every ship for `dt` seconds
+pub fn update(world: &mut World, dt: f32) {
+    let mut deaths = Vec::new();
+    for entity in world.entities.iter_mut() {
+        if entity.is_destroyed() {
+            continue;
+        }
+        let spirits = morale::recovery(entity);
+        if let Some(casualties) = entity.casualties.as_mut() {
+            let died = casualties.treat(dt, spirits, &mut world.rng);
+            if died > 0 {
+                deaths.push((entity.id, died));
+            }
+        }
+    }
+    for (entity, killed) in deaths {
+        world.emit(Event::CrewKilled { entity, killed });
+    }
+}
+
+/// Men of `entity` killed during the mission, outright or of their wounds
+pub fn crew_losses(events: &[TimedEvent], entity: EntityId) -> u32 {
+    events
+        .iter()
+        .map(|e| match e.event {
+            Event::CrewKilled { entity: id, killed } if id == entity => killed,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// What the damage control station reports
+#[derive(Debug, PartialEq, Clone)]
+pub struct DamageReport {
+    /// Hull integrity, 1 when intact
+    pub hull: f32,
+    /// Sensors that took damage, with their health
+    pub sensors: Vec<(SensorKind, f32)>,
+    pub casualties: Option<Casualties>,
+}
+
+impl DamageReport {
+    pub fn new(entity: &Entity) -> DamageReport {
+        DamageReport {
+            hull: entity.hull,
+            sensors: entity
+                .sensors
+                .iter()
+                .filter(|s| s.health < 1.0)
+                .map(|s| (s.kind, s.health))
+                .collect(),
+            casualties: entity.casualties.clone(),
+        }
+    }
+
+    /// The report as written for the player, a line for the hull, the
+    /// crew and each damaged sensor
+    pub fn describe(&self, messages: &Catalog) -> String {
+        let percent = |fraction: f32| format!("{:.0}", fraction * 100.0);
+        let mut lines = vec![messages.format("damage-hull", &[("hull", &percent(self.hull))])];
+        if let Some(c) = &self.casualties {
+            lines.push(messages.format(
+                "damage-crew",
+                &[
+                    ("fit", &c.fit()),
+                    ("wounded", &c.wounded),
+                    ("killed", &c.killed),
+                ],
+            ));
+        }
+        for (kind, health) in &self.sensors {
+            lines.push(messages.format(
+                "damage-sensor",
+                &[("sensor", kind), ("health", &percent(*health))],
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for DamageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::sensors::Sensor;
+    use crate::world::EntityKind;
+
+    fn boat(medic: Medic) -> World {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.casualties = Some(Casualties::new(50, medic));
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        world.spawn(boat);
+        world
+    }
+
+    fn crew(world: &World) -> &Casualties {
+        world.entity(1).unwrap().casualties.as_ref().unwrap()
+    }
+
+    #[test]
+    fn damage_hurts_the_crew() {
+        let mut world = boat(Medic::None);
+        world.apply_damage(1, 0.4);
+        let hurt = crew(&world).wounded + crew(&world).killed;
+        assert!(hurt > 2 && hurt < 20, "{}", hurt);
+        assert_eq!(crew(&world).fit() + hurt, 50);
+        assert_eq!(crew_losses(&world.events, 1), crew(&world).killed);
+
+        let mut short = Casualties::new(50, Medic::None);
+        assert_eq!(short.performance(), 1.0);
+        short.wounded = 20;
+        assert!(short.performance() < 1.0);
+    }
+
+    #[test]
+    fn a_doctor_saves_lives() {
+        let outcome = |medic| {
+            let mut world = boat(medic);
+            world
+                .entity_mut(1)
+                .unwrap()
+                .casualties
+                .as_mut()
+                .unwrap()
+                .wounded = 30;
+            for _ in 0..7 * 24 {
+                world.step(3600.0);
+            }
+            (crew(&world).fit(), crew_losses(&world.events, 1))
+        };
+        let (untreated_fit, untreated_dead) = outcome(Medic::None);
+        let (doctor_fit, doctor_dead) = outcome(Medic::Doctor);
+        assert!(doctor_fit > untreated_fit);
+        assert!(doctor_dead < untreated_dead);
+        assert_eq!(doctor_fit + doctor_dead, 50);
+    }
+
+    #[test]
+    fn damage_control_report() {
+        let mut world = boat(Medic::Corpsman);
+        {
+            let boat = world.entity_mut(1).unwrap();
+            boat.hull = 0.7;
+            boat.sensors[0].health = 0.5;
+            let crew = boat.casualties.as_mut().unwrap();
+            crew.wounded = 3;
+            crew.killed = 1;
+        }
+        let report = DamageReport::new(world.entity(1).unwrap());
+        assert_eq!(
+            report.to_string(),
+            "hull 70%\n46 fit, 3 wounded, 1 killed\nhull sonar at 50%"
+        );
+    }
+}
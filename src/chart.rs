@@ -0,0 +1,318 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError};
+use crate::messages::Catalog;
+use crate::physics::{user_to_game_angle, Point};
+use crate::plot::{bearing_line, Item, Layer, Shape};
+use crate::units::Meters;
+
+// #############################
+// #      CHART ANNOTATION     #
+// #############################
+
+// The player plots by hand on the nav plot: a datum where a contact was
+// lost, a bearing line from a sinking, the box an ambush is laid in. Marks
+// are named, put down and rubbed out with commands:
+//
+// mark datum point 12000 4000
+// mark sinking bearing 0 0 045
+// mark ambush area 0 0 2000 0 2000 2nm
+// unmark datum
+//
+// Coordinates are meters east and north (units allowed as for "course"),
+// bearings are degrees. A chart is saved to and loaded from a file of its
+// own, and a scenario can come with marks already plotted, in the same
+// form:
+//
+// [chart]
+// datum = point 12000 4000
+// ambush = area 0 0 2000 0 2000 3704
+//
+// Loading a chart adds its marks to the ones plotted, so charts can be
+// passed from one player to another.
+
+/// Meters of the circle drawn around a point mark
+const POINT_RADIUS: f32 = 100.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum MarkShape {
+    Point(Point),
+    /// From a point on a bearing (user angle, degrees)
+    Bearing {
+        origin: Point,
+        bearing: f32,
+    },
+    /// Three corners or more
+    Area(Vec<Point>),
+}
+
+fn coordinate(word: &str) -> Result<f32, String> {
+    word.parse::<Meters>().map(|m| m.0)
+}
+
+/// Reads "x y" pairs of coordinates
+fn points(words: &[&str]) -> Result<Vec<Point>, String> {
+    if !words.len().is_multiple_of(2) {
+        return Err("expected x y pairs of coordinates".to_string());
+    }
+    words
+        .chunks(2)
+        .map(|pair| {
+            Ok(Point {
+                x: coordinate(pair[0])?,
+                y: coordinate(pair[1])?,
+            })
+        })
+        .collect()
+}
+
+/// Reads "point <x> <y>", "bearing <x> <y> <bearing>" or
+/// "area <x> <y> <x> <y> <x> <y> ..."
+impl FromStr for MarkShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["point", x, y] => Ok(MarkShape::Point(points(&[x, y])?.remove(0))),
+            ["bearing", x, y, bearing] => Ok(MarkShape::Bearing {
+                origin: points(&[x, y])?.remove(0),
+                bearing: bearing
+                    .parse()
+                    .map_err(|_| format!("expected a bearing, found '{}'", bearing))?,
+            }),
+            ["area", rest @ ..] if rest.len() >= 6 => Ok(MarkShape::Area(points(rest)?)),
+            ["area", ..] => Err("an area needs three corners or more".to_string()),
+            _ => Err(format!("unknown mark '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for MarkShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkShape::Point(p) => write!(f, "point {} {}", p.x, p.y),
+            MarkShape::Bearing { origin, bearing } => {
+                write!(f, "bearing {} {} {}", origin.x, origin.y, bearing)
+            }
+            MarkShape::Area(corners) => {
+                write!(f, "area")?;
+                for corner in corners {
+                    write!(f, " {} {}", corner.x, corner.y)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mark {
+    pub name: String,
+    pub shape: MarkShape,
+}
+
+impl Mark {
+    /// Where the name of the mark is written
+    pub fn anchor(&self) -> Point {
+        match &self.shape {
+            MarkShape::Point(p) => p.clone(),
+            MarkShape::Bearing { origin, .. } => origin.clone(),
+            MarkShape::Area(corners) => Point {
+                x: corners.iter().map(|c| c.x).sum::<f32>() / corners.len() as f32,
+                y: corners.iter().map(|c| c.y).sum::<f32>() / corners.len() as f32,
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ChartError {
+    NoSuchMark(String),
+    /// The chart file could not be read or written, with why
+    File(String),
+}
+
+impl ChartError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            ChartError::NoSuchMark(name) => {
+                messages.format("error-no-such-mark", &[("name", name)])
+            }
+            ChartError::File(error) => messages.format("error-chart-file", &[("error", error)]),
+        }
+    }
+}
+
+impl fmt::Display for ChartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for ChartError {}
+
+impl From<ConfigError> for ChartError {
+    fn from(e: ConfigError) -> Self {
+        ChartError::File(e.to_string())
+    }
+}
+
+/// The marks plotted by hand
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Chart {
+    pub marks: Vec<Mark>,
+}
+
+impl Chart {
+    /// Reads the "[chart]" section, an empty chart without one
+    pub fn read(config: &Config) -> Result<Chart, ConfigError> {
+        let mut chart = Chart::default();
+        let section = match config.section("chart") {
+            Some(section) => section,
+            None => return Ok(chart),
+        };
+        for (name, _) in section.entries() {
+            chart.place(Mark {
+                name: name.to_string(),
+                shape: section.parse(name)?,
+            });
+        }
+        Ok(chart)
+    }
+
+    pub fn write(&self, config: &mut Config) {
+        let section = config.section_mut("chart");
+        for mark in &self.marks {
+            section.set(&mark.name, &mark.shape);
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Chart, ConfigError> {
+        Chart::read(&Config::load(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let mut config = Config::new();
+        self.write(&mut config);
+        config.save(path)
+    }
+
+    /// Puts a mark down, in place of any mark of the same name
+    pub fn place(&mut self, mark: Mark) {
+        match self.marks.iter_mut().find(|m| m.name == mark.name) {
+            Some(old) => *old = mark,
+            None => self.marks.push(mark),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), ChartError> {
+        let index = self
+            .marks
+            .iter()
+            .position(|m| m.name == name)
+            .ok_or_else(|| ChartError::NoSuchMark(name.to_string()))?;
+        self.marks.remove(index);
+        Ok(())
+    }
+
+    /// Adds the marks of `other`, which win over marks of the same name
+    pub fn merge(&mut self, other: Chart) {
+        for mark in other.marks {
+            self.place(mark);
+        }
+    }
+
+    /// The marks as drawn on the plot, bearing lines `length` meters long
+    pub fn items(&self, length: f32) -> Vec<Item> {
+        let mut items = Vec::new();
+        for mark in &self.marks {
+            let shape = match &mark.shape {
+                MarkShape::Point(p) => Shape::Circle {
+                    center: p.clone(),
+                    radius: POINT_RADIUS,
+                },
+                MarkShape::Bearing { origin, bearing } => {
+                    bearing_line(origin, user_to_game_angle(*bearing), length).shape
+                }
+                MarkShape::Area(corners) => Shape::Polygon(corners.clone()),
+            };
+            items.push(Item {
+                layer: Layer::Mark,
+                shape,
+            });
+            items.push(Item {
+                layer: Layer::Mark,
+                shape: Shape::Label {
+                    at: mark.anchor(),
+                    text: mark.name.clone(),
+                },
+            });
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHART: &str = "
+[chart]
+datum = point 12000 4000
+sinking = bearing 0 0 45
+ambush = area 0 0 2000 0 2000 1nm
+";
+
+    #[test]
+    fn reads_and_writes_marks() {
+        let chart = Chart::read(&Config::parse(CHART).unwrap()).unwrap();
+        assert_eq!(chart.marks.len(), 3);
+        assert_eq!(
+            chart.marks[2].shape,
+            MarkShape::Area(vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 2000.0, y: 0.0 },
+                Point {
+                    x: 2000.0,
+                    y: 1852.0
+                },
+            ])
+        );
+        let mut config = Config::new();
+        chart.write(&mut config);
+        assert_eq!(Chart::read(&config).unwrap(), chart);
+
+        for bad in ["x = point 1", "x = area 0 0 1 1", "x = circle 0 0 5"] {
+            let config = Config::parse(&format!("[chart]\n{}", bad)).unwrap();
+            assert!(Chart::read(&config).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn places_and_draws_marks() {
+        let mut chart = Chart::read(&Config::parse(CHART).unwrap()).unwrap();
+        chart.place(Mark {
+            name: "datum".to_string(),
+            shape: "point 0 500".parse().unwrap(),
+        });
+        assert_eq!(chart.marks.len(), 3);
+        assert!(chart.remove("sinking").is_ok());
+        assert_eq!(
+            chart.remove("sinking"),
+            Err(ChartError::NoSuchMark("sinking".to_string()))
+        );
+        let items = chart.items(5000.0);
+        assert_eq!(items.len(), 4);
+        assert_eq!(
+            items[1].shape,
+            Shape::Label {
+                at: Point { x: 0.0, y: 500.0 },
+                text: "datum".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use crate::geo::LatLon;
+use crate::physics::Point;
+use crate::zone;
+
+// #############################
+// #        COASTLINES         #
+// #############################
+
+// Land from the GSHHG shoreline database (binary "gshhs_*.b" files), so a
+// scenario can be set in a real strait: the header key
+//
+// coastline = data/gshhs_i.b
+//
+// loads the shorelines within COASTLINE_RANGE of the scenario origin,
+// projected onto the local plane. GSHHG nests its polygons (land, lake,
+// island in a lake, pond), so a point is land when an odd number of them
+// contain it. Polygons are indexed by the grid cells their bounding boxes
+// cover, so a query only tests the few polygons around the point.
+
+/// Meters around the scenario origin shorelines are loaded for
+pub const COASTLINE_RANGE: f32 = 200_000.0;
+/// Meters on a side of the cells of the index
+const INDEX_CELL: f32 = 5_000.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Shore {
+    pub points: Vec<Point>,
+    min: Point,
+    max: Point,
+}
+
+impl Shore {
+    /// The shore round `points`, None without any
+    pub fn new(points: Vec<Point>) -> Option<Shore> {
+        let mut min = points.first()?.clone();
+        let mut max = min.clone();
+        for p in &points {
+            min.x = min.x.min(p.x);
+            min.y = min.y.min(p.y);
+            max.x = max.x.max(p.x);
+            max.y = max.y.max(p.y);
+        }
+        Some(Shore { points, min, max })
+    }
+
+    fn contains(&self, p: &Point) -> bool {
+        p.x >= self.min.x
+            && p.x <= self.max.x
+            && p.y >= self.min.y
+            && p.y <= self.max.y
+            && zone::polygon_contains(&self.points, p)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Coastline {
+    pub shores: Vec<Shore>,
+    index: HashMap<(i32, i32), Vec<usize>>,
+}
+
+fn cell_of(value: f32) -> i32 {
+    (value / INDEX_CELL).floor() as i32
+}
+
+/// GSHHG polygon header: 11 big-endian 32 bit integers
+struct Header {
+    points: usize,
+    flag: i32,
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> io::Result<i32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(i32::from_be_bytes(bytes))
+}
+
+fn read_header<R: Read>(reader: &mut R) -> io::Result<Option<Header>> {
+    let mut bytes = [0; 4];
+    // the end of the file may only come between two polygons
+    let read = reader.read(&mut bytes)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut bytes[read..])?;
+    let mut fields = [0; 10];
+    for field in fields.iter_mut() {
+        *field = read_i32(reader)?;
+    }
+    let points = usize::try_from(fields[0]).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("negative point count {}", fields[0]),
+        )
+    })?;
+    Ok(Some(Header {
+        points,
+        flag: fields[1],
+    }))
+}
+
+/// Longitude in degrees, taken round the globe to be nearest `origin`
+fn unwrap_lon(lon: f32, origin: f32) -> f32 {
+    let mut lon = lon;
+    while lon - origin > 180.0 {
+        lon -= 360.0;
+    }
+    while lon - origin < -180.0 {
+        lon += 360.0;
+    }
+    lon
+}
+
+impl Coastline {
+    pub fn new(shores: Vec<Shore>) -> Coastline {
+        let mut coastline = Coastline {
+            shores: Vec::new(),
+            index: HashMap::new(),
+        };
+        for shore in shores {
+            coastline.add(shore);
+        }
+        coastline
+    }
+
+    pub fn add(&mut self, shore: Shore) {
+        let number = self.shores.len();
+        for x in cell_of(shore.min.x)..=cell_of(shore.max.x) {
+            for y in cell_of(shore.min.y)..=cell_of(shore.max.y) {
+                self.index.entry((x, y)).or_default().push(number);
+            }
+        }
+        self.shores.push(shore);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shores.is_empty()
+    }
+
+    pub fn is_land(&self, p: &Point) -> bool {
+        let candidates = match self.index.get(&(cell_of(p.x), cell_of(p.y))) {
+            Some(candidates) => candidates,
+            None => return false,
+        };
+        let containing = candidates
+            .iter()
+            .filter(|&&i| self.shores[i].contains(p))
+            .count();
+        containing % 2 == 1
+    }
+
+    /// Reads the GSHHG shorelines within `range` meters of `origin`,
+    /// leaving out rivers
+    pub fn read_gshhg<R: Read>(
+        reader: &mut R,
+        origin: &LatLon,
+        range: f32,
+    ) -> io::Result<Coastline> {
+        let mut coastline = Coastline::default();
+        while let Some(header) = read_header(reader)? {
+            // read as far as the file goes, never trusting the count to
+            // size anything before the points are there
+            let size = header.points.saturating_mul(8);
+            let mut block = Vec::new();
+            reader.take(size as u64).read_to_end(&mut block)?;
+            if block.len() != size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} points past the end of the file", header.points),
+                ));
+            }
+            let mut points = Vec::with_capacity(header.points);
+            for mut point in block.chunks_exact(8) {
+                let lon = read_i32(&mut point)? as f32 * 1e-6;
+                let lat = read_i32(&mut point)? as f32 * 1e-6;
+                let position = LatLon {
+                    lat,
+                    lon: unwrap_lon(lon, origin.lon),
+                };
+                points.push(position.to_local(origin));
+            }
+            let river = (header.flag >> 25) & 1 == 1;
+            if river || points.len() < 3 {
+                continue;
+            }
+            let shore = match Shore::new(points) {
+                Some(shore) => shore,
+                None => continue,
+            };
+            let near = shore.max.x >= -range
+                && shore.min.x <= range
+                && shore.max.y >= -range
+                && shore.min.y <= range;
+            if near {
+                coastline.add(shore);
+            }
+        }
+        Ok(coastline)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, origin: &LatLon) -> io::Result<Coastline> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Coastline::read_gshhg(&mut reader, origin, COASTLINE_RANGE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A GSHHG polygon record around the given (lon, lat) corners
+    fn record(level: i32, corners: &[(f32, f32)]) -> Vec<u8> {
+        let micro = |degrees: f32| (degrees * 1e6).round() as i32;
+        let mut fields = vec![0, corners.len() as i32, level, 0, 0, 0, 0, 0, 0, 0, 0];
+        for (lon, lat) in corners {
+            fields.push(micro(*lon));
+            fields.push(micro(*lat));
+        }
+        fields.iter().flat_map(|f| f.to_be_bytes()).collect()
+    }
+
+    fn square(lon: f32, lat: f32, size: f32) -> Vec<(f32, f32)> {
+        vec![
+            (lon, lat),
+            (lon + size, lat),
+            (lon + size, lat + size),
+            (lon, lat + size),
+        ]
+    }
+
+    #[test]
+    fn island_with_lake() {
+        let origin = LatLon {
+            lat: 36.0,
+            lon: -5.5,
+        };
+        let mut data = record(1, &square(354.6, 36.1, 0.2));
+        data.extend(record(2, &square(354.65, 36.15, 0.1)));
+        // far away, left out
+        data.extend(record(1, &square(10.0, 50.0, 1.0)));
+        let coastline =
+            Coastline::read_gshhg(&mut data.as_slice(), &origin, COASTLINE_RANGE).unwrap();
+        assert_eq!(coastline.shores.len(), 2);
+        let at = |lat: f32, lon: f32| LatLon { lat, lon }.to_local(&origin);
+        assert!(coastline.is_land(&at(36.12, -5.38)));
+        assert!(!coastline.is_land(&at(36.2, -5.3)));
+        assert!(!coastline.is_land(&at(36.0, -5.5)));
+    }
+
+    #[test]
+    fn truncated_file() {
+        let data = record(1, &square(0.0, 0.0, 1.0));
+        let origin = LatLon::default();
+        let cut = &mut &data[..data.len() - 3];
+        assert!(Coastline::read_gshhg(cut, &origin, COASTLINE_RANGE).is_err());
+
+        // counts the file cannot hold
+        for count in [-1, i32::MAX] {
+            let mut bad = data.clone();
+            bad[4..8].copy_from_slice(&count.to_be_bytes());
+            let error = Coastline::read_gshhg(&mut bad.as_slice(), &origin, COASTLINE_RANGE);
+            assert_eq!(error.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        assert_eq!(Shore::new(Vec::new()), None);
+    }
+}
@@ -0,0 +1,832 @@
+use std::fmt;
+
+use crate::chart::Mark;
+use crate::dive::DiveKind;
+use crate::narration::Readout;
+use crate::noise::Rig;
+use crate::preferences::Setting;
+use crate::pulse::Pulse;
+use crate::tracking::Tracker;
+use crate::units::Meters;
+use crate::world::EntityId;
+
+// #############################
+// #      PLAYER COMMANDS      #
+// #############################
+
+/// Syntax and purpose of every command, as shown by "help commands"
+pub const REFERENCE: &[(&str, &str)] = &[
+    (
+        "preset save <name> <tube>",
+        "store the tube settings as a preset",
+    ),
+    (
+        "preset apply <name> [tube ...]",
+        "wire a preset into tubes (all if none)",
+    ),
+    ("preset delete <name>", "forget a preset"),
+    ("gun man", "man the deck gun (surfaced only)"),
+    ("gun secure", "secure the deck gun"),
+    ("gun target <entity id | nearest>", "pick the gun target"),
+    (
+        "fire <tube> <bearing>",
+        "launch a torpedo, bearing in degrees",
+    ),
+    ("xbt", "drop a bathythermograph"),
+    (
+        "ping [on | off]",
+        "send an active sonar pulse, or pulse every few seconds (escorts)",
+    ),
+    (
+        "pulse <short | medium | long> <low | medium | high> [omni | sector]",
+        "choose the active sonar pulse: range, resolution and who hears it",
+    ),
+    (
+        "vds <depth> | vds recover",
+        "stream the variable depth sonar to a depth, or heave it in",
+    ),
+    (
+        "charges <depth>",
+        "drop a pattern of depth charges set to go off at a depth",
+    ),
+    ("mortar", "throw a salvo of mortar bombs ahead"),
+    (
+        "helo goto <x> <y>",
+        "send the helicopter to hover over a point",
+    ),
+    (
+        "helo dip <x> <y> [depth]",
+        "dip the helicopter sonar at a point, to 90 m unless set",
+    ),
+    (
+        "helo attack [<x> <y>]",
+        "drop a torpedo on the datum of the last dip, or on a point",
+    ),
+    ("helo recover", "land the helicopter back on board"),
+    (
+        "report",
+        "radio a contact report, at periscope depth: shore stations may fix you",
+    ),
+    (
+        "fix",
+        "take a sun or star sight, surfaced or at periscope depth",
+    ),
+    ("decoy stream", "stream the towed decoy astern"),
+    ("decoy recover", "reel the towed decoy back in"),
+    (
+        "decoy launch <bearing> [bearing ...]",
+        "launch a mobile decoy, each leg run for two minutes",
+    ),
+    (
+        "dive [crash]",
+        "dive to periscope depth, crash dives are faster and louder",
+    ),
+    ("surface", "blow ballast and surface"),
+    (
+        "planes <auto | manual>",
+        "hold depth with the trim pumps helping, or quietly on the planes",
+    ),
+    ("refit", "take on stores, stopped in port or by a tender"),
+    ("identify <entity id>", "classify a contact, with how sure"),
+    (
+        "shadow <contact>",
+        "radio the position of a contact to the pack, at periscope depth",
+    ),
+    ("helm <vessel>", "take command of another of your vessels"),
+    ("door <open | close> <tube>", "work a tube outer door"),
+    (
+        "rig <normal | quiet | ultra>",
+        "rig the boat for quiet running",
+    ),
+    (
+        "course <x> <y>",
+        "steer to a point east, north (1500, 1600yd, 2nm) around zones",
+    ),
+    ("autopilot layer", "keep just under the layer"),
+    (
+        "autopilot sprint <x> <y> <minutes>",
+        "sprint to a point, stopping to listen every so many minutes",
+    ),
+    ("autopilot off", "drop the autopilot orders"),
+    (
+        "mark <name> point <x> <y>",
+        "plot a named point on the chart",
+    ),
+    (
+        "mark <name> bearing <x> <y> <bearing>",
+        "plot a named bearing line from a point",
+    ),
+    (
+        "mark <name> area <x> <y> <x> <y> <x> <y> ...",
+        "outline a named area, three corners or more",
+    ),
+    ("unmark <name>", "rub a mark out"),
+    ("chart save <file>", "write the marks to a file"),
+    ("chart load <file>", "add the marks of a file to the chart"),
+    (
+        "log save <file>",
+        "write the patrol log, as JSON for a .json file, else markdown",
+    ),
+    (
+        "tracker <least-squares | kalman>",
+        "fit each tick's bearings, or follow contacts with a Kalman filter",
+    ),
+    ("profile on", "time every subsystem, tick by tick"),
+    (
+        "profile",
+        "report the time per tick of each subsystem, in microseconds",
+    ),
+    (
+        "set <units | bearings | clock | dates | palette> <value>",
+        "change how reports are written and displays colored (see preferences)",
+    ),
+    ("signals", "list the radio signals received"),
+    (
+        "describe <status | sonar | plot>",
+        "a display in short sentences, for a screen reader",
+    ),
+    (
+        "timer <start | stop | clear> <name>",
+        "start a stopwatch, over again if it ran, stop it or throw it away",
+    ),
+    ("timers", "show every stopwatch"),
+    (
+        "compress <steps>",
+        "run that many steps a tick, back to 1 when the captain is needed",
+    ),
+    ("continue", "go on with the tutorial"),
+    ("help [commands | boat | weapons | <command>]", "this help"),
+    (
+        "alias <name> <command ...>",
+        "name the start of a command, see console.rs",
+    ),
+    (
+        "macro <name> <command>; <command> ...",
+        "name several commands run one after the other",
+    ),
+    ("unalias <name>", "forget an alias or macro"),
+    (
+        "bind [station] <key> <command ...>",
+        "put a command on a key, at one station or all, see keys.rs",
+    ),
+    ("unbind [station] <key>", "take a command off a key"),
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    Preset(PresetCommand),
+    Gun(GunCommand),
+    /// Launch the torpedo in `tube` on a bearing (user angle, degrees)
+    Fire {
+        tube: usize,
+        bearing: f32,
+    },
+    LaunchXbt,
+    Asw(AswCommand),
+    Helo(HeloCommand),
+    /// Radio the contacts held
+    Report,
+    /// Take a celestial fix
+    Fix,
+    Decoy(DecoyCommand),
+    Dive(DiveKind),
+    Surface,
+    /// Automatic depth keeping on or off
+    Planes(bool),
+    Refit,
+    /// Report what the contact is taken for
+    Identify(EntityId),
+    /// Radio a shadowing report on a contact to the pack, see wolfpack.rs
+    Shadow(u32),
+    /// Take command of another vessel of the player, see helm.rs
+    Helm(String),
+    Door {
+        tube: usize,
+        open: bool,
+    },
+    Rig(Rig),
+    /// Plot a route to a point (east and north of the origin) and follow it
+    Course {
+        x: Meters,
+        y: Meters,
+    },
+    Autopilot(AutopilotCommand),
+    Mark(Mark),
+    Unmark(String),
+    Chart(ChartCommand),
+    /// Write the patrol log to a file
+    SaveLog(String),
+    Tracker(Tracker),
+    Profile(ProfileCommand),
+    /// Change a preference
+    Set(Setting),
+    /// List the radio signals received
+    Signals,
+    Timer(TimerCommand),
+    /// Show every stopwatch
+    Timers,
+    /// A display in words, see narration.rs
+    Describe(Readout),
+    /// Time compression, in world steps per tick
+    Compress(u32),
+    Continue,
+    Help(HelpTopic),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HelpTopic {
+    Index,
+    /// The command reference, or only the commands starting with a word
+    Commands(Option<String>),
+    /// Capabilities of the own ship
+    Boat,
+    /// Envelopes of the own ship's weapons
+    Weapons,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PresetCommand {
+    Save { name: String, tube: usize },
+    Apply { name: String, tubes: Vec<usize> },
+    Delete { name: String },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum GunCommand {
+    Man,
+    Secure,
+    /// Engage an entity, or the nearest surface ship when `None`
+    Target(Option<EntityId>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecoyCommand {
+    Stream,
+    Recover,
+    /// Launch a mobile decoy running legs on these bearings (user angles,
+    /// degrees), see decoy.rs
+    Launch(Vec<f32>),
+}
+
+/// Orders to the sonar and weapons of an escort, see asw.rs
+#[derive(Debug, PartialEq, Clone)]
+pub enum AswCommand {
+    /// A single pulse, or pulsing switched on or off
+    Ping(Option<bool>),
+    /// What the pulses are, see pulse.rs
+    Pulse(Pulse),
+    /// Stream the variable depth sonar to a depth, or recover it, see
+    /// vds.rs
+    Vds(Option<Meters>),
+    /// Drop a pattern of depth charges set to go off at a depth
+    Charges(Meters),
+    Mortar,
+}
+
+/// Tasking of the helicopter of an escort, see helicopter.rs
+#[derive(Debug, PartialEq, Clone)]
+pub enum HeloCommand {
+    GoTo {
+        x: Meters,
+        y: Meters,
+    },
+    Dip {
+        x: Meters,
+        y: Meters,
+        depth: Option<Meters>,
+    },
+    /// Drop a torpedo on a point, or on the datum of the last dip
+    Attack(Option<(Meters, Meters)>),
+    Recover,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ChartCommand {
+    Save(String),
+    Load(String),
+}
+
+/// Stopwatches of the attack team, see stopwatch.rs
+#[derive(Debug, PartialEq, Clone)]
+pub enum TimerCommand {
+    Start(String),
+    Stop(String),
+    Clear(String),
+}
+
+/// Timing of the subsystems, see trace.rs
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProfileCommand {
+    Start,
+    Report,
+}
+
+/// Standing orders, see autopilot.rs
+#[derive(Debug, PartialEq, Clone)]
+pub enum AutopilotCommand {
+    BelowLayer,
+    /// Sprint to a point, stopping to listen every `minutes`
+    Sprint {
+        x: Meters,
+        y: Meters,
+        minutes: f32,
+    },
+    Off,
+}
+
+/// Writes the command back the way `Command::parse` reads it
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Preset(PresetCommand::Save { name, tube }) => {
+                write!(f, "preset save {} {}", name, tube)
+            }
+            Command::Preset(PresetCommand::Apply { name, tubes }) => {
+                write!(f, "preset apply {}", name)?;
+                for tube in tubes {
+                    write!(f, " {}", tube)?;
+                }
+                Ok(())
+            }
+            Command::Preset(PresetCommand::Delete { name }) => write!(f, "preset delete {}", name),
+            Command::Gun(GunCommand::Man) => write!(f, "gun man"),
+            Command::Gun(GunCommand::Secure) => write!(f, "gun secure"),
+            Command::Gun(GunCommand::Target(Some(id))) => write!(f, "gun target {}", id),
+            Command::Gun(GunCommand::Target(None)) => write!(f, "gun target nearest"),
+            Command::Fire { tube, bearing } => write!(f, "fire {} {}", tube, bearing),
+            Command::LaunchXbt => write!(f, "xbt"),
+            Command::Asw(AswCommand::Ping(None)) => write!(f, "ping"),
+            Command::Asw(AswCommand::Ping(Some(true))) => write!(f, "ping on"),
+            Command::Asw(AswCommand::Ping(Some(false))) => write!(f, "ping off"),
+            Command::Asw(AswCommand::Pulse(pulse)) => write!(f, "pulse {}", pulse),
+            Command::Asw(AswCommand::Vds(Some(depth))) => write!(f, "vds {}", depth.0),
+            Command::Asw(AswCommand::Vds(None)) => write!(f, "vds recover"),
+            Command::Asw(AswCommand::Charges(depth)) => write!(f, "charges {}", depth.0),
+            Command::Asw(AswCommand::Mortar) => write!(f, "mortar"),
+            Command::Helo(HeloCommand::GoTo { x, y }) => write!(f, "helo goto {} {}", x.0, y.0),
+            Command::Helo(HeloCommand::Dip { x, y, depth }) => {
+                write!(f, "helo dip {} {}", x.0, y.0)?;
+                match depth {
+                    Some(depth) => write!(f, " {}", depth.0),
+                    None => Ok(()),
+                }
+            }
+            Command::Helo(HeloCommand::Attack(Some((x, y)))) => {
+                write!(f, "helo attack {} {}", x.0, y.0)
+            }
+            Command::Helo(HeloCommand::Attack(None)) => write!(f, "helo attack"),
+            Command::Helo(HeloCommand::Recover) => write!(f, "helo recover"),
+            Command::Report => write!(f, "report"),
+            Command::Fix => write!(f, "fix"),
+            Command::Decoy(DecoyCommand::Stream) => write!(f, "decoy stream"),
+            Command::Decoy(DecoyCommand::Recover) => write!(f, "decoy recover"),
+            Command::Decoy(DecoyCommand::Launch(bearings)) => {
+                write!(f, "decoy launch")?;
+                for bearing in bearings {
+                    write!(f, " {}", bearing)?;
+                }
+                Ok(())
+            }
+            Command::Dive(DiveKind::Normal) => write!(f, "dive"),
+            Command::Dive(DiveKind::Crash) => write!(f, "dive crash"),
+            Command::Surface => write!(f, "surface"),
+            Command::Planes(true) => write!(f, "planes auto"),
+            Command::Planes(false) => write!(f, "planes manual"),
+            Command::Refit => write!(f, "refit"),
+            Command::Identify(id) => write!(f, "identify {}", id),
+            Command::Shadow(number) => write!(f, "shadow {}", number),
+            Command::Helm(name) => write!(f, "helm {}", name),
+            Command::Door { tube, open } => {
+                let action = if *open { "open" } else { "close" };
+                write!(f, "door {} {}", action, tube)
+            }
+            Command::Rig(rig) => write!(f, "rig {}", rig),
+            Command::Course { x, y } => write!(f, "course {} {}", x.0, y.0),
+            Command::Autopilot(AutopilotCommand::BelowLayer) => write!(f, "autopilot layer"),
+            Command::Autopilot(AutopilotCommand::Sprint { x, y, minutes }) => {
+                write!(f, "autopilot sprint {} {} {}", x.0, y.0, minutes)
+            }
+            Command::Autopilot(AutopilotCommand::Off) => write!(f, "autopilot off"),
+            Command::Mark(mark) => write!(f, "mark {} {}", mark.name, mark.shape),
+            Command::Unmark(name) => write!(f, "unmark {}", name),
+            Command::Chart(ChartCommand::Save(path)) => write!(f, "chart save {}", path),
+            Command::Chart(ChartCommand::Load(path)) => write!(f, "chart load {}", path),
+            Command::SaveLog(path) => write!(f, "log save {}", path),
+            Command::Tracker(tracker) => write!(f, "tracker {}", tracker),
+            Command::Profile(ProfileCommand::Start) => write!(f, "profile on"),
+            Command::Profile(ProfileCommand::Report) => write!(f, "profile"),
+            Command::Set(setting) => write!(f, "set {}", setting),
+            Command::Signals => write!(f, "signals"),
+            Command::Timer(TimerCommand::Start(name)) => write!(f, "timer start {}", name),
+            Command::Timer(TimerCommand::Stop(name)) => write!(f, "timer stop {}", name),
+            Command::Timer(TimerCommand::Clear(name)) => write!(f, "timer clear {}", name),
+            Command::Timers => write!(f, "timers"),
+            Command::Describe(readout) => write!(f, "describe {}", readout),
+            Command::Compress(steps) => write!(f, "compress {}", steps),
+            Command::Continue => write!(f, "continue"),
+            Command::Help(HelpTopic::Index) => write!(f, "help"),
+            Command::Help(HelpTopic::Commands(None)) => write!(f, "help commands"),
+            Command::Help(HelpTopic::Commands(Some(word))) => write!(f, "help {}", word),
+            Command::Help(HelpTopic::Boat) => write!(f, "help boat"),
+            Command::Help(HelpTopic::Weapons) => write!(f, "help weapons"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_number(word: &str) -> Result<usize, ParseError> {
+    word.parse()
+        .map_err(|_| ParseError(format!("expected a number, found '{}'", word)))
+}
+
+fn expect<'a>(words: &[&'a str], index: usize, what: &str) -> Result<&'a str, ParseError> {
+    words
+        .get(index)
+        .copied()
+        .ok_or_else(|| ParseError(format!("missing {}", what)))
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, ParseError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Err(ParseError("empty command".to_string())),
+            ["preset", rest @ ..] => Command::parse_preset(rest).map(Command::Preset),
+            ["gun", rest @ ..] => Command::parse_gun(rest).map(Command::Gun),
+            ["fire", rest @ ..] => {
+                let tube = parse_number(expect(rest, 0, "tube number")?)?;
+                let bearing = expect(rest, 1, "bearing")?;
+                let bearing = bearing
+                    .parse()
+                    .map_err(|_| ParseError(format!("expected a bearing, found '{}'", bearing)))?;
+                Ok(Command::Fire { tube, bearing })
+            }
+            ["xbt"] => Ok(Command::LaunchXbt),
+            ["ping"] => Ok(Command::Asw(AswCommand::Ping(None))),
+            ["ping", "on"] => Ok(Command::Asw(AswCommand::Ping(Some(true)))),
+            ["ping", "off"] => Ok(Command::Asw(AswCommand::Ping(Some(false)))),
+            ["pulse", rest @ ..] => rest
+                .join(" ")
+                .parse()
+                .map(|pulse| Command::Asw(AswCommand::Pulse(pulse)))
+                .map_err(ParseError),
+            ["vds", "recover"] => Ok(Command::Asw(AswCommand::Vds(None))),
+            ["vds", rest @ ..] => expect(rest, 0, "depth")?
+                .parse()
+                .map(|depth| Command::Asw(AswCommand::Vds(Some(depth))))
+                .map_err(ParseError),
+            ["charges", rest @ ..] => expect(rest, 0, "depth")?
+                .parse()
+                .map(|depth| Command::Asw(AswCommand::Charges(depth)))
+                .map_err(ParseError),
+            ["mortar"] => Ok(Command::Asw(AswCommand::Mortar)),
+            ["helo", rest @ ..] => Command::parse_helo(rest).map(Command::Helo),
+            ["report"] => Ok(Command::Report),
+            ["fix"] => Ok(Command::Fix),
+            ["decoy", rest @ ..] => Command::parse_decoy(rest).map(Command::Decoy),
+            ["dive"] => Ok(Command::Dive(DiveKind::Normal)),
+            ["dive", "crash"] => Ok(Command::Dive(DiveKind::Crash)),
+            ["surface"] => Ok(Command::Surface),
+            ["planes", rest @ ..] => match expect(rest, 0, "planes mode")? {
+                "auto" => Ok(Command::Planes(true)),
+                "manual" => Ok(Command::Planes(false)),
+                other => Err(ParseError(format!("unknown planes mode '{}'", other))),
+            },
+            ["refit"] => Ok(Command::Refit),
+            ["identify", id] => Ok(Command::Identify(parse_number(id)?)),
+            ["shadow", number] => Ok(Command::Shadow(parse_number(number)? as u32)),
+            ["helm", name @ ..] if !name.is_empty() => Ok(Command::Helm(name.join(" "))),
+            ["door", rest @ ..] => {
+                let open = match expect(rest, 0, "door action")? {
+                    "open" => true,
+                    "close" => false,
+                    other => return Err(ParseError(format!("unknown door action '{}'", other))),
+                };
+                let tube = parse_number(expect(rest, 1, "tube number")?)?;
+                Ok(Command::Door { tube, open })
+            }
+            ["course", rest @ ..] => {
+                let mut coordinates = [Meters(0.0); 2];
+                for (i, what) in ["x", "y"].iter().enumerate() {
+                    coordinates[i] = expect(rest, i, what)?.parse().map_err(ParseError)?;
+                }
+                Ok(Command::Course {
+                    x: coordinates[0],
+                    y: coordinates[1],
+                })
+            }
+            ["autopilot", rest @ ..] => Command::parse_autopilot(rest).map(Command::Autopilot),
+            ["mark", name, shape @ ..] => Ok(Command::Mark(Mark {
+                name: name.to_string(),
+                shape: shape.join(" ").parse().map_err(ParseError)?,
+            })),
+            ["unmark", name] => Ok(Command::Unmark(name.to_string())),
+            ["chart", "save", path] => Ok(Command::Chart(ChartCommand::Save(path.to_string()))),
+            ["chart", "load", path] => Ok(Command::Chart(ChartCommand::Load(path.to_string()))),
+            ["log", "save", path] => Ok(Command::SaveLog(path.to_string())),
+            ["profile"] => Ok(Command::Profile(ProfileCommand::Report)),
+            ["profile", "on"] => Ok(Command::Profile(ProfileCommand::Start)),
+            ["tracker", rest @ ..] => expect(rest, 0, "tracker")?
+                .parse()
+                .map(Command::Tracker)
+                .map_err(ParseError),
+            ["signals"] => Ok(Command::Signals),
+            ["timer", "start", name] => Ok(Command::Timer(TimerCommand::Start(name.to_string()))),
+            ["timer", "stop", name] => Ok(Command::Timer(TimerCommand::Stop(name.to_string()))),
+            ["timer", "clear", name] => Ok(Command::Timer(TimerCommand::Clear(name.to_string()))),
+            ["timers"] => Ok(Command::Timers),
+            ["describe", rest @ ..] => expect(rest, 0, "display")?
+                .parse()
+                .map(Command::Describe)
+                .map_err(ParseError),
+            ["compress", steps] => match steps.parse() {
+                Ok(steps) if steps > 0 => Ok(Command::Compress(steps)),
+                _ => Err(ParseError(format!(
+                    "expected a number of steps, found '{}'",
+                    steps
+                ))),
+            },
+            ["continue"] => Ok(Command::Continue),
+            ["help"] => Ok(Command::Help(HelpTopic::Index)),
+            ["help", "commands"] => Ok(Command::Help(HelpTopic::Commands(None))),
+            ["help", "boat"] => Ok(Command::Help(HelpTopic::Boat)),
+            ["help", "weapons"] => Ok(Command::Help(HelpTopic::Weapons)),
+            ["help", word] => Ok(Command::Help(HelpTopic::Commands(Some(word.to_string())))),
+            ["set", rest @ ..] => {
+                let key = expect(rest, 0, "preference")?;
+                let value = expect(rest, 1, "value")?;
+                Setting::parse(key, value)
+                    .map(Command::Set)
+                    .map_err(ParseError)
+            }
+            ["rig", rest @ ..] => expect(rest, 0, "rig")?
+                .parse()
+                .map(Command::Rig)
+                .map_err(ParseError),
+            [other, ..] => Err(ParseError(format!("unknown command '{}'", other))),
+        }
+    }
+
+    fn parse_preset(words: &[&str]) -> Result<PresetCommand, ParseError> {
+        let action = expect(words, 0, "preset action")?;
+        let name = expect(words, 1, "preset name")?.to_string();
+        match action {
+            "save" => {
+                let tube = parse_number(expect(words, 2, "tube number")?)?;
+                Ok(PresetCommand::Save { name, tube })
+            }
+            "apply" => {
+                let tubes = words[2..]
+                    .iter()
+                    .map(|w| parse_number(w))
+                    .collect::<Result<Vec<usize>, ParseError>>()?;
+                Ok(PresetCommand::Apply { name, tubes })
+            }
+            "delete" => Ok(PresetCommand::Delete { name }),
+            other => Err(ParseError(format!("unknown preset action '{}'", other))),
+        }
+    }
+
+    fn parse_decoy(words: &[&str]) -> Result<DecoyCommand, ParseError> {
+        match expect(words, 0, "decoy action")? {
+            "stream" => Ok(DecoyCommand::Stream),
+            "recover" => Ok(DecoyCommand::Recover),
+            "launch" => {
+                expect(words, 1, "bearing")?;
+                let bearings = words[1..]
+                    .iter()
+                    .map(|w| {
+                        w.parse()
+                            .map_err(|_| ParseError(format!("expected a bearing, found '{}'", w)))
+                    })
+                    .collect::<Result<Vec<f32>, ParseError>>()?;
+                Ok(DecoyCommand::Launch(bearings))
+            }
+            other => Err(ParseError(format!("unknown decoy action '{}'", other))),
+        }
+    }
+
+    fn parse_autopilot(words: &[&str]) -> Result<AutopilotCommand, ParseError> {
+        match expect(words, 0, "autopilot order")? {
+            "layer" => Ok(AutopilotCommand::BelowLayer),
+            "off" => Ok(AutopilotCommand::Off),
+            "sprint" => {
+                let x = expect(words, 1, "x")?.parse().map_err(ParseError)?;
+                let y = expect(words, 2, "y")?.parse().map_err(ParseError)?;
+                let minutes = expect(words, 3, "minutes")?;
+                let minutes = minutes
+                    .parse()
+                    .ok()
+                    .filter(|m: &f32| *m > 0.0)
+                    .ok_or_else(|| ParseError(format!("expected minutes, found '{}'", minutes)))?;
+                Ok(AutopilotCommand::Sprint { x, y, minutes })
+            }
+            other => Err(ParseError(format!("unknown autopilot order '{}'", other))),
+        }
+    }
+
+    fn parse_helo(words: &[&str]) -> Result<HeloCommand, ParseError> {
+        let length = |i: usize, what: &str| -> Result<Meters, ParseError> {
+            expect(words, i, what)?.parse().map_err(ParseError)
+        };
+        match expect(words, 0, "helicopter task")? {
+            "goto" => Ok(HeloCommand::GoTo {
+                x: length(1, "x")?,
+                y: length(2, "y")?,
+            }),
+            "dip" => Ok(HeloCommand::Dip {
+                x: length(1, "x")?,
+                y: length(2, "y")?,
+                depth: match words.get(3) {
+                    Some(_) => Some(length(3, "depth")?),
+                    None => None,
+                },
+            }),
+            "attack" if words.len() == 1 => Ok(HeloCommand::Attack(None)),
+            "attack" => Ok(HeloCommand::Attack(Some((
+                length(1, "x")?,
+                length(2, "y")?,
+            )))),
+            "recover" => Ok(HeloCommand::Recover),
+            other => Err(ParseError(format!("unknown helicopter task '{}'", other))),
+        }
+    }
+
+    fn parse_gun(words: &[&str]) -> Result<GunCommand, ParseError> {
+        match expect(words, 0, "gun action")? {
+            "man" => Ok(GunCommand::Man),
+            "secure" => Ok(GunCommand::Secure),
+            "target" => match expect(words, 1, "target")? {
+                "nearest" => Ok(GunCommand::Target(None)),
+                id => Ok(GunCommand::Target(Some(parse_number(id)?))),
+            },
+            other => Err(ParseError(format!("unknown gun action '{}'", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_preset1() {
+        assert_eq!(
+            Command::parse("preset apply deep 1 3"),
+            Ok(Command::Preset(PresetCommand::Apply {
+                name: "deep".to_string(),
+                tubes: vec![1, 3]
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_preset2() {
+        assert_eq!(
+            Command::parse("preset save deep 2"),
+            Ok(Command::Preset(PresetCommand::Save {
+                name: "deep".to_string(),
+                tube: 2
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_gun1() {
+        assert_eq!(Command::parse("gun man"), Ok(Command::Gun(GunCommand::Man)));
+        assert_eq!(
+            Command::parse("gun target 4"),
+            Ok(Command::Gun(GunCommand::Target(Some(4))))
+        );
+        assert_eq!(
+            Command::parse("gun target nearest"),
+            Ok(Command::Gun(GunCommand::Target(None)))
+        );
+        assert!(Command::parse("gun target").is_err());
+        assert!(Command::parse("gun fire").is_err());
+    }
+
+    #[test]
+    fn parse_fire() {
+        assert_eq!(
+            Command::parse("fire 2 045.5"),
+            Ok(Command::Fire {
+                tube: 2,
+                bearing: 45.5
+            })
+        );
+        assert!(Command::parse("fire 2").is_err());
+        assert!(Command::parse("fire 2 north").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("fire").is_err());
+        assert!(Command::parse("preset save deep").is_err());
+        assert!(Command::parse("preset apply deep one").is_err());
+    }
+
+    #[test]
+    fn parse_quieting() {
+        assert_eq!(
+            Command::parse("door close 2"),
+            Ok(Command::Door {
+                tube: 2,
+                open: false
+            })
+        );
+        assert_eq!(
+            Command::parse("rig ultra"),
+            Ok(Command::Rig(Rig::UltraQuiet))
+        );
+        assert!(Command::parse("rig silent").is_err());
+    }
+
+    #[test]
+    fn parse_course() {
+        assert_eq!(
+            Command::parse("course 2nm -1nm"),
+            Ok(Command::Course {
+                x: Meters(3704.0),
+                y: Meters(-1852.0),
+            })
+        );
+        assert!(Command::parse("course 2nm").is_err());
+        assert!(Command::parse("course 2 leagues").is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for line in [
+            "preset apply deep 1 3",
+            "preset delete deep",
+            "gun target nearest",
+            "fire 2 45.5",
+            "ping",
+            "ping off",
+            "pulse long low sector",
+            "vds 150",
+            "vds recover",
+            "charges 75",
+            "mortar",
+            "helo goto 5000 -2000",
+            "helo dip 5000 -2000",
+            "helo dip 5000 -2000 150",
+            "helo attack",
+            "helo attack 5100 -1900",
+            "helo recover",
+            "decoy launch 90 180.5",
+            "decoy recover",
+            "door open 1",
+            "rig ultra",
+            "dive crash",
+            "surface",
+            "planes manual",
+            "refit",
+            "report",
+            "fix",
+            "identify 4",
+            "shadow 2",
+            "helm SS Test",
+            "course -1500 3000",
+            "autopilot sprint 12000 -4000 10",
+            "autopilot layer",
+            "mark datum point 12000 -400.5",
+            "mark box area 0 0 2000 0 2000 2000",
+            "mark sinking bearing 10 20 45",
+            "unmark datum",
+            "chart save patrol.chart",
+            "log save patrol.md",
+            "tracker kalman",
+            "profile on",
+            "profile",
+            "set units imperial",
+            "set palette deuteranopia",
+            "set clock 12",
+            "signals",
+            "timer start run",
+            "timer stop run",
+            "timer clear run",
+            "timers",
+            "describe sonar",
+            "compress 32",
+            "continue",
+            "help gun",
+            "help boat",
+        ] {
+            let command = Command::parse(line).unwrap();
+            assert_eq!(command.to_string(), line);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// #############################
+// #        FILE FORMAT        #
+// #############################
+
+// Settings and save files share a small sectioned key/value format:
+//
+// # comment
+// [section name]
+// key = value
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    Missing {
+        section: String,
+        key: String,
+    },
+    Invalid {
+        section: String,
+        key: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "i/o error: {}", e),
+            ConfigError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigError::Missing { section, key } => {
+                write!(f, "[{}] missing key '{}'", section, key)
+            }
+            ConfigError::Invalid {
+                section,
+                key,
+                value,
+            } => write!(f, "[{}] invalid value '{}' for '{}'", section, value, key),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Section {
+    pub name: String,
+    entries: Vec<(String, String)>,
+}
+
+impl Section {
+    pub fn new(name: &str) -> Section {
+        Section {
+            name: name.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets a key, replacing any previous value
+    pub fn set<T: ToString>(&mut self, key: &str, value: T) {
+        let value = value.to_string();
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    /// Returns a required key parsed into `T`
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<T, ConfigError> {
+        let value = self.get(key).ok_or_else(|| ConfigError::Missing {
+            section: self.name.clone(),
+            key: key.to_string(),
+        })?;
+        value.parse().map_err(|_| ConfigError::Invalid {
+            section: self.name.clone(),
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Returns an optional key parsed into `T`, or `default` when absent
+    pub fn parse_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, ConfigError> {
+        match self.get(key) {
+            Some(_) => self.parse(key),
+            None => Ok(default),
+        }
+    }
+
+    /// Returns an optional key parsed into `T`, None when absent
+    pub fn parse_optional<T: FromStr>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        match self.get(key) {
+            Some(_) => self.parse(key).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Config {
+    sections: Vec<Section>,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::new();
+        let mut current: Option<usize> = None;
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                if !line.ends_with(']') || line.len() < 3 {
+                    return Err(ConfigError::Syntax {
+                        line: index + 1,
+                        message: format!("bad section header '{}'", line),
+                    });
+                }
+                let name = line[1..line.len() - 1].trim();
+                config.sections.push(Section::new(name));
+                current = Some(config.sections.len() - 1);
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+                None => {
+                    return Err(ConfigError::Syntax {
+                        line: index + 1,
+                        message: format!("expected 'key = value', found '{}'", line),
+                    })
+                }
+            };
+            match current {
+                Some(i) => config.sections[i].set(key, value),
+                None => {
+                    return Err(ConfigError::Syntax {
+                        line: index + 1,
+                        message: "key outside of a section".to_string(),
+                    })
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path)?;
+        Config::parse(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Returns the named section, creating it if needed
+    pub fn section_mut(&mut self, name: &str) -> &mut Section {
+        match self.sections.iter().position(|s| s.name == name) {
+            Some(i) => &mut self.sections[i],
+            None => {
+                self.sections.push(Section::new(name));
+                self.sections.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn remove_section(&mut self, name: &str) -> Option<Section> {
+        let index = self.sections.iter().position(|s| s.name == name)?;
+        Some(self.sections.remove(index))
+    }
+
+    /// Iterates the sections named "<prefix>.<something>", yielding the suffix
+    pub fn sections_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a Section)> + 'a {
+        self.sections.iter().filter_map(move |s| {
+            if s.name.len() > prefix.len()
+                && s.name.starts_with(prefix)
+                && s.name[prefix.len()..].starts_with('.')
+            {
+                Some((&s.name[prefix.len() + 1..], s))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{}]", section.name)?;
+            for (key, value) in section.entries() {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse1() {
+        let config = Config::parse("# comment\n[a]\nx = 1\ny=two\n\n[b]\nz = 3.5\n").unwrap();
+        assert_eq!(config.section("a").unwrap().get("y"), Some("two"));
+        assert_eq!(config.section("b").unwrap().parse::<f32>("z").unwrap(), 3.5);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Config::parse("x = 1").is_err());
+        assert!(Config::parse("[a]\nnonsense").is_err());
+        assert!(Config::parse("[a").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut config = Config::new();
+        config.section_mut("preset.alpha").set("depth", 30.0);
+        config.section_mut("preset.beta").set("depth", 60);
+        config.section_mut("other").set("k", "v");
+        let parsed = Config::parse(&config.to_string()).unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn prefix() {
+        let config = Config::parse("[preset.a]\n[presets]\n[preset.b]\n[preset]\n").unwrap();
+        let names: Vec<&str> = config
+            .sections_with_prefix("preset")
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn missing_and_invalid() {
+        let config = Config::parse("[a]\nx = abc\n").unwrap();
+        let section = config.section("a").unwrap();
+        assert!(matches!(
+            section.parse::<f32>("x"),
+            Err(ConfigError::Invalid { .. })
+        ));
+        assert!(matches!(
+            section.parse::<f32>("y"),
+            Err(ConfigError::Missing { .. })
+        ));
+        assert_eq!(section.parse_or("y", 2.0).unwrap(), 2.0);
+    }
+}
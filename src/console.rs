@@ -0,0 +1,343 @@
+use std::path::Path;
+
+use crate::command::{Command, ParseError};
+use crate::config::{Config, ConfigError};
+use crate::keys::{Chord, Keymap, Station};
+
+// #############################
+// #      COMMAND CONSOLE      #
+// #############################
+
+// What the player types goes through the console before it becomes
+// commands (see command.rs). Lines entered are kept for recall, newest
+// last, and the player may name their own words for commands:
+//
+// alias tubes door open 1
+// macro attack_setup dive; mark datum point 12000 4000; tubes
+// unalias tubes
+//
+// An alias stands for the start of a command, the rest of the line being
+// added after it; a macro stands for several commands, separated by ";",
+// and may use aliases and other macros. Both are kept with the
+// preferences in the player's profile, along with the latest lines:
+//
+// [aliases]
+// tubes = door open 1
+//
+// [macros]
+// attack_setup = dive; mark datum point 12000 4000; tubes
+//
+// [recall]
+// 1 = rig quiet
+// 2 = attack_setup
+//
+// Key bindings are typed and kept here too, see keys.rs.
+
+/// Lines kept for recall
+const HISTORY_LIMIT: usize = 200;
+/// Of them, lines kept in the profile
+const SAVED_HISTORY: usize = 50;
+/// Aliases and macros deep within one another before giving up
+const MAX_NESTING: usize = 8;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Console {
+    /// Lines entered, oldest first
+    pub history: Vec<String>,
+    /// Index in `history` of the line recalled, None when at the prompt
+    recalled: Option<usize>,
+    /// Name and the words it stands for
+    pub aliases: Vec<(String, String)>,
+    /// Name and the lines it runs
+    pub macros: Vec<(String, Vec<String>)>,
+    pub keys: Keymap,
+}
+
+fn define<T>(list: &mut Vec<(String, T)>, name: &str, value: T) {
+    match list.iter_mut().find(|(n, _)| n == name) {
+        Some(entry) => entry.1 = value,
+        None => list.push((name.to_string(), value)),
+    }
+}
+
+fn steps(text: &str) -> Vec<String> {
+    text.split(';')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+impl Console {
+    /// Reads the aliases, macros and recall of a profile
+    pub fn read(config: &Config) -> Console {
+        let mut console = Console::default();
+        if let Some(section) = config.section("aliases") {
+            for (name, words) in section.entries() {
+                define(&mut console.aliases, name, words.to_string());
+            }
+        }
+        if let Some(section) = config.section("macros") {
+            for (name, text) in section.entries() {
+                define(&mut console.macros, name, steps(text));
+            }
+        }
+        if let Some(section) = config.section("recall") {
+            console.history = section.entries().map(|(_, l)| l.to_string()).collect();
+        }
+        console.keys = Keymap::read(config);
+        console
+    }
+
+    pub fn write(&self, config: &mut Config) {
+        for name in ["aliases", "macros", "recall"] {
+            config.remove_section(name);
+        }
+        if !self.aliases.is_empty() {
+            let section = config.section_mut("aliases");
+            for (name, words) in &self.aliases {
+                section.set(name, words);
+            }
+        }
+        if !self.macros.is_empty() {
+            let section = config.section_mut("macros");
+            for (name, lines) in &self.macros {
+                section.set(name, lines.join("; "));
+            }
+        }
+        let kept = self.history.len().saturating_sub(SAVED_HISTORY);
+        if kept < self.history.len() {
+            let section = config.section_mut("recall");
+            for (i, line) in self.history[kept..].iter().enumerate() {
+                section.set(&(i + 1).to_string(), line);
+            }
+        }
+        self.keys.write(config);
+    }
+
+    /// Reads the console of a profile, an empty one when there is no file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Console, ConfigError> {
+        if !path.as_ref().exists() {
+            return Ok(Console::default());
+        }
+        Ok(Console::read(&Config::load(path)?))
+    }
+
+    /// Writes the console into a profile, keeping what else it holds
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let mut config = if path.as_ref().exists() {
+            Config::load(&path)?
+        } else {
+            Config::new()
+        };
+        self.write(&mut config);
+        config.save(path)
+    }
+
+    /// Takes a line typed by the player: keeps it for recall, and either
+    /// defines an alias or macro or turns it into the commands it stands
+    /// for
+    pub fn enter(&mut self, line: &str) -> Result<Vec<Command>, ParseError> {
+        let line = line.trim();
+        self.recalled = None;
+        if !line.is_empty() && self.history.last().map(|l| l.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > HISTORY_LIMIT {
+                self.history.remove(0);
+            }
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["alias", name, rest @ ..] if !rest.is_empty() => {
+                define(&mut self.aliases, name, rest.join(" "));
+                Ok(Vec::new())
+            }
+            ["macro", name, ..] => {
+                let text = line.splitn(3, char::is_whitespace).nth(2).unwrap_or("");
+                let lines = steps(text);
+                if lines.is_empty() {
+                    return Err(ParseError(format!("macro '{}' runs nothing", name)));
+                }
+                define(&mut self.macros, name, lines);
+                Ok(Vec::new())
+            }
+            ["unalias", name] => {
+                let before = self.aliases.len() + self.macros.len();
+                self.aliases.retain(|(n, _)| n != name);
+                self.macros.retain(|(n, _)| n != name);
+                if self.aliases.len() + self.macros.len() == before {
+                    return Err(ParseError(format!("no alias or macro '{}'", name)));
+                }
+                Ok(Vec::new())
+            }
+            ["bind", first, rest @ ..] if !rest.is_empty() => {
+                let (station, chord, line) = match (first.parse::<Station>(), rest) {
+                    (Ok(station), [chord, line @ ..]) if !line.is_empty() => {
+                        (Some(station), chord, line)
+                    }
+                    _ => (None, first, rest),
+                };
+                let chord: Chord = chord.parse().map_err(ParseError)?;
+                self.keys.bind(station, chord, &line.join(" "));
+                Ok(Vec::new())
+            }
+            ["unbind", words @ ..] if !words.is_empty() && words.len() <= 2 => {
+                let (station, chord) = match words {
+                    [station, chord] => (Some(station.parse().map_err(ParseError)?), chord),
+                    _ => (None, &words[0]),
+                };
+                let chord: Chord = chord.parse().map_err(ParseError)?;
+                if !self.keys.unbind(station, &chord) {
+                    return Err(ParseError(format!("nothing bound to '{}'", chord)));
+                }
+                Ok(Vec::new())
+            }
+            ["bind" | "unbind", ..] => Err(ParseError(
+                "usage: bind [station] <key> <command ...> | unbind [station] <key>".to_string(),
+            )),
+            ["alias" | "unalias", ..] => Err(ParseError(
+                "usage: alias <name> <command ...> | macro <name> <command>; ... \
+                 | unalias <name>"
+                    .to_string(),
+            )),
+            _ => self.expand(line, 0),
+        }
+    }
+
+    /// The commands `line` stands for, through aliases and macros
+    pub fn expand(&self, line: &str, depth: usize) -> Result<Vec<Command>, ParseError> {
+        if depth > MAX_NESTING {
+            return Err(ParseError(format!("'{}' expands without end", line)));
+        }
+        let (first, rest) = match line.split_once(char::is_whitespace) {
+            Some((first, rest)) => (first, rest.trim()),
+            None => (line, ""),
+        };
+        if let Some((_, words)) = self.aliases.iter().find(|(n, _)| n == first) {
+            return self.expand(format!("{} {}", words, rest).trim(), depth + 1);
+        }
+        if let Some((_, lines)) = self.macros.iter().find(|(n, _)| n == first) {
+            if !rest.is_empty() {
+                return Err(ParseError(format!("macro '{}' takes nothing more", first)));
+            }
+            let mut commands = Vec::new();
+            for line in lines {
+                commands.extend(self.expand(line, depth + 1)?);
+            }
+            return Ok(commands);
+        }
+        Command::parse(line).map(|command| vec![command])
+    }
+
+    /// The commands a key pressed at `station` runs, none when nothing is
+    /// bound to it
+    pub fn press(&self, station: Station, chord: &Chord) -> Result<Vec<Command>, ParseError> {
+        match self.keys.lookup(station, chord) {
+            Some(line) => self.expand(line, 0),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The line entered before the one recalled, going back in history
+    pub fn recall_previous(&mut self) -> Option<&str> {
+        let index = match self.recalled {
+            Some(0) => 0,
+            Some(i) => i - 1,
+            None => self.history.len().checked_sub(1)?,
+        };
+        self.recalled = Some(index);
+        self.history.get(index).map(|l| l.as_str())
+    }
+
+    /// The line entered after the one recalled, None back at the prompt
+    pub fn recall_next(&mut self) -> Option<&str> {
+        let index = self.recalled? + 1;
+        if index >= self.history.len() {
+            self.recalled = None;
+            return None;
+        }
+        self.recalled = Some(index);
+        self.history.get(index).map(|l| l.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_aliases_and_macros() {
+        let mut console = Console::default();
+        assert_eq!(console.enter("alias open door open"), Ok(Vec::new()));
+        console
+            .enter("macro attack_setup dive; open 1; open 2")
+            .unwrap();
+        let commands = console.enter("attack_setup").unwrap();
+        let lines: Vec<String> = commands.iter().map(|c| c.to_string()).collect();
+        assert_eq!(lines, vec!["dive", "door open 1", "door open 2"]);
+        assert_eq!(
+            console.enter("open 3").unwrap(),
+            vec![Command::parse("door open 3").unwrap()]
+        );
+        console.enter("alias loop loop").unwrap();
+        assert!(console.enter("loop").is_err());
+        console.enter("unalias open").unwrap();
+        assert!(console.enter("attack_setup").is_err());
+        assert!(console.enter("unalias open").is_err());
+    }
+
+    #[test]
+    fn recalls_lines() {
+        let mut console = Console::default();
+        assert_eq!(console.recall_previous(), None);
+        for line in ["dive", "rig quiet", "rig quiet", "xbt"] {
+            let _ = console.enter(line);
+        }
+        assert_eq!(console.history, vec!["dive", "rig quiet", "xbt"]);
+        assert_eq!(console.recall_previous(), Some("xbt"));
+        assert_eq!(console.recall_previous(), Some("rig quiet"));
+        assert_eq!(console.recall_previous(), Some("dive"));
+        assert_eq!(console.recall_previous(), Some("dive"));
+        assert_eq!(console.recall_next(), Some("rig quiet"));
+        assert_eq!(console.recall_next(), Some("xbt"));
+        assert_eq!(console.recall_next(), None);
+    }
+
+    #[test]
+    fn keeps_its_profile() {
+        let mut console = Console::default();
+        console.enter("alias quiet rig ultra").unwrap();
+        console.enter("macro go dive; quiet").unwrap();
+        let mut config = Config::parse("[preferences]\nunits = imperial").unwrap();
+        console.write(&mut config);
+        assert!(config.section("preferences").is_some());
+        let mut read = Console::read(&config);
+        assert_eq!(read, console);
+        assert_eq!(read.enter("go").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn binds_keys_per_station() {
+        let mut console = Console::default();
+        console.enter("macro go dive; rig ultra").unwrap();
+        console.enter("bind ctrl+g go").unwrap();
+        console.enter("bind sonar ctrl+g signals").unwrap();
+        let ctrl_g: Chord = "ctrl+g".parse().unwrap();
+        assert_eq!(console.press(Station::Conn, &ctrl_g).unwrap().len(), 2);
+        assert_eq!(
+            console.press(Station::Sonar, &ctrl_g),
+            Ok(vec![Command::Signals])
+        );
+        assert_eq!(
+            console.press(Station::Conn, &"x".parse().unwrap()),
+            Ok(Vec::new())
+        );
+        assert!(console.enter("bind hyper+x dive").is_err());
+        console.enter("unbind sonar ctrl+g").unwrap();
+        assert!(console.enter("unbind sonar ctrl+g").is_err());
+        assert_eq!(console.press(Station::Sonar, &ctrl_g).unwrap().len(), 2);
+
+        let mut config = Config::new();
+        console.write(&mut config);
+        assert_eq!(Console::read(&config).keys, console.keys);
+    }
+}
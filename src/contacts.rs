@@ -0,0 +1,872 @@
+use std::f32::consts::PI;
+use std::fmt;
+
+use crate::config::{Config, ConfigError};
+use crate::gunnery::{self, PERISCOPE_DEPTH};
+use crate::history::{History, Retention};
+use crate::intercept::{self, EmissionKind};
+use crate::noise;
+use crate::physics::{game_to_user_angle, normalize_angle, Point, KNOT};
+use crate::random::Rng;
+use crate::seakeeping;
+use crate::sensors::{echo_excess, excesses_at, SensorContext, SensorKind};
+use crate::tracking::{Track, Tracker};
+use crate::tuning::{Tunable, Tuning};
+use crate::vds;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #     CONTACT MANAGEMENT    #
+// #############################
+
+// Every sensor of the own ship reports what it holds as an observation: a
+// bearing with how accurate the sensor is, and a range from the sensors
+// that measure one (the periscope stadimeter, the radar, the hull sonar
+// when it pinged). Both are off by the error of the sensor, the wider the
+// weaker the signal (see sensors.rs). The same ship held on the towed
+// array, the hull sonar, the intercept receiver and the periscope must
+// still be one contact, not four. Observations are first gathered into
+// groups that could be the same ship, never two from the same sensor, then
+// each group is matched against the contacts already held. Two
+// observations, or a group and a contact, can be the same ship when their
+// bearings (and ranges, when both have one) agree within three times their
+// combined error. A group is fused into one solution by weighting each
+// observation by the inverse of its variance, so the solution is better
+// than its best sensor, and contacts keep their number for as long as they
+// are held, with the history of their bearings (see history.rs). With the
+// Kalman tracker every contact also carries a track, see tracking.rs,
+// corrected by each observation. Where each observation placed a contact
+// that was ranged or tracked is kept too, for the plot to draw its track.
+//
+// A sensor holds ships within its beam as one, unless it ranges them (see
+// sensors.rs): the hull sonar may count a tight column of merchants as a
+// single contact the towed array splits in two.
+//
+// A contact no sensor holds any more grows stale: where it may be spreads
+// out as it could have gone on at up to ten knots, its bearing and range
+// drawn wider and wider. After a while it is lost, and after a longer one
+// dropped from the plot for good, as a "[contacts]" section of the
+// scenario sets:
+//
+// [contacts]
+// lost = 60               # seconds without an observation before lost
+// archive = 600           # seconds before dropped
+//
+// Every contact gained, held again or on other sensors, lost and dropped
+// is logged for the reports of the own ship and for the bot captains (see
+// captain.rs).
+
+/// Standard deviations of the bearing errors that may still be the same
+/// ship
+const GATE: f32 = 3.0;
+/// Seconds without an observation before a contact is lost, unless set
+const LOST: f32 = 60.0;
+/// Seconds without an observation before a contact is dropped, unless set
+const ARCHIVE: f32 = 600.0;
+/// Meters per second a contact no longer held is taken to make at most
+const STALE_SPEED: f32 = 10.0 * KNOT;
+/// Meters off a contact held on a bearing only is taken to be, for how
+/// fast its bearing may drift
+const UNRANGED: f32 = 5_000.0;
+
+/// How long contacts are kept without an observation
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Aging {
+    /// Seconds before a contact is lost
+    pub lost: f32,
+    /// Seconds before it is dropped
+    pub archive: f32,
+}
+
+impl Default for Aging {
+    fn default() -> Self {
+        Aging {
+            lost: LOST,
+            archive: ARCHIVE,
+        }
+    }
+}
+
+impl Aging {
+    /// Reads the "[contacts]" section, the defaults without one
+    pub fn read(config: &Config) -> Result<Aging, ConfigError> {
+        let section = match config.section("contacts") {
+            Some(section) => section,
+            None => return Ok(Aging::default()),
+        };
+        let lost = section.parse_or("lost", LOST)?;
+        Ok(Aging {
+            lost,
+            archive: section.parse_or("archive", ARCHIVE)?.max(lost),
+        })
+    }
+}
+
+/// What became of a contact
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ContactChange {
+    Gained,
+    /// Held again after being lost, or on other sensors
+    Updated,
+    Lost,
+    /// Dropped from the plot
+    Archived,
+}
+
+impl fmt::Display for ContactChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ContactChange::Gained => "gained",
+            ContactChange::Updated => "updated",
+            ContactChange::Lost => "lost",
+            ContactChange::Archived => "archived",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A contact changing, as logged
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContactUpdate {
+    /// Seconds into the scenario
+    pub time: f32,
+    pub number: u32,
+    pub change: ContactChange,
+}
+
+/// What one sensor holds of one ship this tick
+#[derive(Debug, PartialEq, Clone)]
+pub struct Observation {
+    pub sensor: SensorKind,
+    /// Game angle from the observer
+    pub bearing: f32,
+    /// Standard deviation of the bearing, radians
+    pub bearing_error: f32,
+    /// Meters with its standard deviation, for the sensors measuring it
+    pub range: Option<(f32, f32)>,
+}
+
+/// What one sensor holds of one ship, or of several within its beam, before
+/// the error of the sensor
+#[derive(Debug, PartialEq, Clone)]
+struct Detection {
+    sensor: SensorKind,
+    /// Game angle from the observer
+    bearing: f32,
+    range: f32,
+    /// dB of signal excess
+    excess: f32,
+    /// dB of signal excess the range is measured at, when it is
+    ranged: Option<f32>,
+    /// Meters within which the pulse the range is measured with knows it
+    resolution: f32,
+}
+
+impl Detection {
+    /// What `sensor` holds of `target` at `excess` dB of signal excess,
+    /// with a range when it measured one at `ranged` dB
+    fn new(
+        sensor: SensorKind,
+        observer: &Entity,
+        target: &Entity,
+        excess: f32,
+        ranged: Option<f32>,
+    ) -> Detection {
+        Detection {
+            sensor,
+            bearing: observer.position.angle_to(&target.position),
+            range: observer.position.distance_to(&target.position),
+            excess,
+            ranged,
+            resolution: 0.0,
+        }
+    }
+}
+
+/// Merges the detections no range tells apart that fall within the beam
+/// of their sensor into one, on the bearing between them weighted by how
+/// loud each is; the loudest are taken first
+fn resolve(mut detections: Vec<Detection>) -> Vec<Detection> {
+    detections.sort_by(|a, b| b.excess.total_cmp(&a.excess));
+    let mut resolved: Vec<(Detection, f32)> = Vec::new();
+    for detection in detections {
+        let power = 10_f32.powf(detection.excess / 10.0);
+        let blur = resolved.iter_mut().find(|(held, _)| {
+            held.sensor == detection.sensor
+                && held.ranged.is_none()
+                && detection.ranged.is_none()
+                && normalize_angle(detection.bearing - held.bearing).abs()
+                    < detection.sensor.beam_width()
+        });
+        match blur {
+            Some((held, weight)) => {
+                let offset = normalize_angle(detection.bearing - held.bearing);
+                held.bearing = normalize_angle(held.bearing + offset * power / (*weight + power));
+                *weight += power;
+                held.excess = 10.0 * weight.log10();
+            }
+            None => resolved.push((detection, power)),
+        }
+    }
+    resolved.into_iter().map(|(d, _)| d).collect()
+}
+
+impl Observation {
+    /// What the sensor of `detection` reports of it, off by its error; a
+    /// range no finer than the pulse it was measured with resolves
+    fn new(detection: &Detection, tuning: &Tuning, rng: &mut Rng) -> Observation {
+        let sensor = detection.sensor;
+        let bearing_error = sensor.bearing_error(detection.excess, tuning);
+        let range = detection
+            .ranged
+            .and_then(|excess| sensor.range_error(excess, tuning))
+            .map(|error| {
+                let error = (detection.range * error).hypot(detection.resolution / 2.0);
+                (rng.gaussian(detection.range, error).max(0.0), error)
+            });
+        Observation {
+            sensor,
+            bearing: normalize_angle(rng.gaussian(detection.bearing, bearing_error)),
+            bearing_error,
+            range,
+        }
+    }
+}
+
+/// Whether `observer` has its periscope up and sees `target` through it
+fn in_periscope(world: &World, observer: &Entity, target: &Entity) -> bool {
+    let context = SensorContext::new(observer, &world.environment);
+    let working = observer
+        .sensors
+        .iter()
+        .any(|s| s.kind == SensorKind::Periscope && s.is_operational(&context));
+    let raised =
+        observer.is_surfaced() || (observer.depth <= PERISCOPE_DEPTH && observer.mast_raised);
+    working
+        && raised
+        && gunnery::exposure(target) > 0.0
+        && observer.position.distance_to(&target.position)
+            <= seakeeping::sighting_range(observer, &world.environment)
+}
+
+/// Everything the sensors of `observer` hold this tick, the echoes of its
+/// own pulse included, ships within a beam as one
+pub fn observe(world: &World, observer: &Entity, rng: &mut Rng) -> Vec<Observation> {
+    let mut detections = Vec::new();
+    let intercepts = intercept::intercepts(world, observer);
+    let pinged = world
+        .emissions
+        .iter()
+        .find(|e| e.source == observer.id && e.kind == EmissionKind::ActiveSonar)
+        .map(|e| e.pulse);
+    // a towed body listens and pings from where it is, see vds.rs
+    let body = vds::body(observer);
+    let listeners: Vec<&Entity> = std::iter::once(observer).chain(body.as_ref()).collect();
+    for target in world.entities.iter() {
+        if target.id == observer.id || target.is_destroyed() {
+            continue;
+        }
+        let echo = pinged.as_ref().and_then(|pulse| {
+            let level = EmissionKind::ActiveSonar.source_level();
+            listeners
+                .iter()
+                .filter_map(|l| echo_excess(&world.environment, l, target, level, pulse))
+                .filter(|e| *e > 0.0)
+                .reduce(f32::max)
+        });
+        let mut heard: Vec<(SensorKind, f32)> = Vec::new();
+        for listener in listeners.iter() {
+            for (sensor, excess) in excesses_at(
+                &world.environment,
+                listener,
+                &target.position,
+                target.depth,
+                noise::radiated_level(target),
+            ) {
+                match heard.iter_mut().find(|(s, _)| *s == sensor) {
+                    Some((_, best)) => *best = best.max(excess),
+                    None => heard.push((sensor, excess)),
+                }
+            }
+        }
+        if echo.is_some() && !heard.iter().any(|(s, _)| *s == SensorKind::HullSonar) {
+            heard.push((SensorKind::HullSonar, f32::NEG_INFINITY));
+        }
+        for (sensor, excess) in heard {
+            let ranged = echo.filter(|_| sensor == SensorKind::HullSonar);
+            let excess = ranged.map_or(excess, |echo| echo.max(excess));
+            if excess > 0.0 {
+                let mut detection = Detection::new(sensor, observer, target, excess, ranged);
+                if let (Some(_), Some(pulse)) = (ranged, pinged) {
+                    detection.resolution = pulse.range_resolution();
+                }
+                detections.push(detection);
+            }
+        }
+        let mut sighted = Vec::new();
+        if in_periscope(world, observer, target) {
+            sighted.push(SensorKind::Periscope);
+        }
+        if observer.radar_contacts.contains(&target.id) {
+            sighted.push(SensorKind::Radar);
+        }
+        for sensor in sighted {
+            detections.push(Detection::new(
+                sensor,
+                observer,
+                target,
+                Tunable::ClearSignal.get(&world.environment.tuning),
+                Some(Tunable::ClearSignal.get(&world.environment.tuning)),
+            ));
+        }
+        if let Some(heard) = intercepts.iter().find(|i| i.source == target.id) {
+            detections.push(Detection::new(
+                SensorKind::InterceptReceiver,
+                observer,
+                target,
+                heard.excess,
+                None,
+            ));
+        }
+    }
+    resolve(detections)
+        .iter()
+        .map(|d| Observation::new(d, &world.environment.tuning, rng))
+        .collect()
+}
+
+/// One ship as the own ship holds it, from all its sensors together
+#[derive(Debug, PartialEq, Clone)]
+pub struct Contact {
+    /// Number the contact keeps for as long as it is held
+    pub number: u32,
+    /// Game angle from the own ship
+    pub bearing: f32,
+    /// Standard deviation of the bearing, radians
+    pub bearing_error: f32,
+    /// Meters with its standard deviation, when a sensor measures it
+    pub range: Option<(f32, f32)>,
+    /// Sensors holding the contact at the last observation
+    pub sensors: Vec<SensorKind>,
+    /// Seconds into the scenario of the last observation
+    pub last_seen: f32,
+    /// No longer held for too long
+    pub lost: bool,
+    /// Position and velocity estimated by the Kalman tracker
+    pub track: Option<Track>,
+    /// Seconds into the scenario and bearings in degrees from north, kept
+    /// continuous across north
+    pub bearings: History,
+    /// Where the observations placed it, whenever it was ranged or tracked
+    pub positions: History,
+}
+
+impl Contact {
+    /// Fuses `observations` of one ship
+    fn fuse(number: u32, time: f32, observations: &[Observation]) -> Contact {
+        let reference = observations[0].bearing;
+        let mut weight = 0.0;
+        let mut offset = 0.0;
+        for o in observations {
+            let w = 1.0 / o.bearing_error.powi(2);
+            weight += w;
+            offset += w * normalize_angle(o.bearing - reference);
+        }
+        let mut range_weight = 0.0;
+        let mut range = 0.0;
+        for (r, error) in observations.iter().filter_map(|o| o.range) {
+            let w = 1.0 / error.powi(2);
+            range_weight += w;
+            range += w * r;
+        }
+        Contact {
+            number,
+            bearing: normalize_angle(reference + offset / weight),
+            bearing_error: weight.powf(-0.5),
+            range: (range_weight > 0.0).then(|| (range / range_weight, range_weight.powf(-0.5))),
+            sensors: observations.iter().map(|o| o.sensor).collect(),
+            last_seen: time,
+            lost: false,
+            track: None,
+            bearings: History::default(),
+            positions: History::default(),
+        }
+    }
+
+    /// Widens the bearing and range of a contact not observed for another
+    /// `dt` seconds by how far it may have gone meanwhile
+    fn grow_stale(&mut self, dt: f32) {
+        let spread = STALE_SPEED * dt.max(0.0);
+        let range = self.range.map_or(UNRANGED, |(range, _)| range.max(spread));
+        self.bearing_error = (self.bearing_error + spread / range).min(PI);
+        if let Some((_, error)) = self.range.as_mut() {
+            *error += spread;
+        }
+    }
+
+    /// Where the contact is reckoned to be, seen from `observer`, and how
+    /// many meters that may be off by: on its track, or along its bearing
+    /// at the range measured. None for a bare bearing
+    pub fn estimate(&self, observer: &Point) -> Option<(Point, f32)> {
+        if let Some(track) = &self.track {
+            return Some((track.position(), track.position_error()));
+        }
+        let (range, error) = self.range?;
+        let position = Point {
+            x: observer.x + range * self.bearing.cos(),
+            y: observer.y + range * self.bearing.sin(),
+        };
+        Some((position, error.max(range * self.bearing_error)))
+    }
+
+    /// Records where the last observation from `observer` placed it
+    fn place(&mut self, observer: &Point) {
+        if let Some((position, _)) = self.estimate(observer) {
+            self.positions.push(position);
+        }
+    }
+
+    /// Whether a ship on `bearing` (game angle) may be this contact
+    pub fn covers(&self, bearing: f32) -> bool {
+        normalize_angle(bearing - self.bearing).abs() <= GATE * self.bearing_error
+    }
+
+    /// Normalized distance from `other`, None when outside the gate
+    fn distance(&self, other: &Contact) -> Option<f32> {
+        let error = self.bearing_error.hypot(other.bearing_error);
+        let mut distance = (normalize_angle(self.bearing - other.bearing) / error).powi(2);
+        if let (Some((a, ea)), Some((b, eb))) = (self.range, other.range) {
+            distance += ((a - b) / ea.hypot(eb)).powi(2);
+        }
+        (distance.sqrt() <= GATE).then_some(distance)
+    }
+}
+
+impl fmt::Display for Contact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "S{} {:03.0} ±{:.1}°",
+            self.number,
+            game_to_user_angle(self.bearing),
+            self.bearing_error.to_degrees()
+        )?;
+        if let Some((range, error)) = self.range {
+            write!(f, " {:.0} ±{:.0} m", range, error)?;
+        }
+        let sensors: Vec<String> = self.sensors.iter().map(|s| s.to_string()).collect();
+        write!(f, " ({})", sensors.join(", "))?;
+        if self.lost {
+            write!(f, " lost")?;
+        }
+        Ok(())
+    }
+}
+
+/// Gathers `observations` into groups that may be the same ship, the most
+/// accurate first
+fn associate(mut observations: Vec<Observation>, time: f32) -> Vec<Vec<Observation>> {
+    observations.sort_by(|a, b| a.bearing_error.total_cmp(&b.bearing_error));
+    let mut groups: Vec<Vec<Observation>> = Vec::new();
+    for observation in observations {
+        let alone = Contact::fuse(0, time, std::slice::from_ref(&observation));
+        let closest = groups
+            .iter_mut()
+            .filter(|g| g.iter().all(|o| o.sensor != observation.sensor))
+            .filter_map(|g| {
+                let distance = Contact::fuse(0, time, g).distance(&alone)?;
+                Some((g, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match closest {
+            Some((group, _)) => group.push(observation),
+            None => groups.push(vec![observation]),
+        }
+    }
+    groups
+}
+
+/// The contacts of one ship
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ContactTable {
+    pub contacts: Vec<Contact>,
+    pub tracker: Tracker,
+    /// How much of the bearing histories is kept
+    pub retention: Retention,
+    pub aging: Aging,
+    /// Contacts gained, lost and so on, oldest first; consumers keep their
+    /// own cursor
+    pub changes: Vec<ContactUpdate>,
+    next_number: u32,
+    /// Seconds into the scenario of the last update
+    time: f32,
+    /// Draws the errors of the observations
+    rng: Rng,
+}
+
+impl ContactTable {
+    /// Takes in what the sensors of `observer` hold this tick
+    pub fn update(&mut self, world: &World, observer: EntityId) {
+        let observer = match world.entity(observer) {
+            Some(observer) if !observer.is_destroyed() => observer,
+            _ => return,
+        };
+        let dt = world.time - self.time;
+        self.time = world.time;
+        let mut updated = Vec::new();
+        let observations = observe(world, observer, &mut self.rng);
+        for group in associate(observations, world.time) {
+            let fused = Contact::fuse(0, world.time, &group);
+            let held = self
+                .contacts
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !updated.contains(i))
+                .filter_map(|(i, c)| Some((i, c.distance(&fused)?)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let at = &observer.position;
+            let track = match (self.tracker, held) {
+                (Tracker::LeastSquares, _) => None,
+                (Tracker::Kalman, Some((i, _))) if self.contacts[i].track.is_some() => {
+                    let mut track = self.contacts[i].track.clone().unwrap();
+                    track.predict(world.time);
+                    for o in &group {
+                        track.bearing(at, o.bearing, o.bearing_error);
+                        if let Some((range, error)) = o.range {
+                            track.range(at, range, error);
+                        }
+                    }
+                    Some(track)
+                }
+                (Tracker::Kalman, _) => Some(Track::new(
+                    at,
+                    fused.bearing,
+                    fused.bearing_error,
+                    fused.range,
+                    world.time,
+                )),
+            };
+            let mut bearings = match held {
+                Some((i, _)) => std::mem::take(&mut self.contacts[i].bearings),
+                None => History::bearings(&self.retention),
+            };
+            let positions = match held {
+                Some((i, _)) => std::mem::take(&mut self.contacts[i].positions),
+                None => History::positions(&self.retention),
+            };
+            let mut bearing = game_to_user_angle(fused.bearing);
+            if let Some(last) = bearings.last() {
+                bearing = last.y + normalize_angle((bearing - last.y).to_radians()).to_degrees();
+            }
+            bearings.push(Point {
+                x: world.time,
+                y: bearing,
+            });
+            match held {
+                Some((i, _)) => {
+                    let held = &self.contacts[i];
+                    let resensed = held.sensors.len() != fused.sensors.len()
+                        || fused.sensors.iter().any(|s| !held.sensors.contains(s));
+                    if held.lost || resensed {
+                        self.log(world.time, held.number, ContactChange::Updated);
+                    }
+                    self.contacts[i] = Contact {
+                        number: self.contacts[i].number,
+                        track,
+                        bearings,
+                        positions,
+                        ..fused
+                    };
+                    self.contacts[i].place(at);
+                    updated.push(i);
+                }
+                None => {
+                    self.next_number += 1;
+                    self.log(world.time, self.next_number, ContactChange::Gained);
+                    let mut contact = Contact {
+                        number: self.next_number,
+                        track,
+                        bearings,
+                        positions,
+                        ..fused
+                    };
+                    contact.place(at);
+                    self.contacts.push(contact);
+                    updated.push(self.contacts.len() - 1);
+                }
+            }
+        }
+        let mut changes = Vec::new();
+        for (i, contact) in self.contacts.iter_mut().enumerate() {
+            if updated.contains(&i) {
+                continue;
+            }
+            contact.grow_stale(dt);
+            let age = world.time - contact.last_seen;
+            if age > self.aging.archive {
+                changes.push((contact.number, ContactChange::Archived));
+            } else if !contact.lost && age > self.aging.lost {
+                contact.lost = true;
+                changes.push((contact.number, ContactChange::Lost));
+            }
+        }
+        for (number, change) in changes {
+            self.log(world.time, number, change);
+        }
+        let archive = self.aging.archive;
+        self.contacts
+            .retain(|c| world.time - c.last_seen <= archive);
+    }
+
+    fn log(&mut self, time: f32, number: u32, change: ContactChange) {
+        self.changes.push(ContactUpdate {
+            time,
+            number,
+            change,
+        });
+    }
+
+    /// Switches trackers; tracks start afresh with the next observations
+    pub fn select(&mut self, tracker: Tracker) {
+        self.tracker = tracker;
+        for contact in self.contacts.iter_mut() {
+            contact.track = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::sensors::Sensor;
+    use crate::world::EntityKind;
+
+    fn waters() -> World {
+        let mut world = World::new();
+        let mut boat = Entity::new("Dallas", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = PERISCOPE_DEPTH;
+        boat.mast_raised = true;
+        for kind in [
+            SensorKind::HullSonar,
+            SensorKind::TowedArray,
+            SensorKind::Periscope,
+        ] {
+            boat.sensors.push(Sensor::new(kind));
+        }
+        world.spawn(boat);
+        let mut merchant = Entity::new(
+            "Empire Toucan",
+            EntityKind::Merchant,
+            Point { x: 3000.0, y: 0.0 },
+        );
+        merchant.speed = 5.0;
+        world.spawn(merchant);
+        world
+    }
+
+    #[test]
+    fn one_ship_on_many_sensors_is_one_contact() {
+        let world = waters();
+        let mut rng = Rng::default();
+        let observations = observe(&world, world.entity(1).unwrap(), &mut rng);
+        assert_eq!(observations.len(), 3);
+        let best = observations
+            .iter()
+            .map(|o| o.bearing_error)
+            .fold(f32::INFINITY, f32::min);
+        let mut table = ContactTable::default();
+        table.update(&world, 1);
+        assert_eq!(table.contacts.len(), 1);
+        let contact = &table.contacts[0];
+        assert_eq!(contact.sensors.len(), 3);
+        assert!(contact.bearing_error < best);
+        assert!(contact.bearing.abs() < 3.0 * contact.bearing_error);
+        let (range, error) = contact.range.unwrap();
+        assert!((range - 3000.0).abs() < 3.0 * error, "{}", range);
+        assert!(contact
+            .to_string()
+            .ends_with("m (periscope, towed array, hull sonar)"));
+    }
+
+    #[test]
+    fn reports_scatter_by_their_error() {
+        let mut world = waters();
+        let mut rng = Rng::default();
+        let mut spread = 0.0;
+        for _ in 0..1000 {
+            let observations = observe(&world, world.entity(1).unwrap(), &mut rng);
+            let hull = observations
+                .iter()
+                .find(|o| o.sensor == SensorKind::HullSonar)
+                .unwrap();
+            assert_eq!(hull.range, None);
+            spread += (hull.bearing / hull.bearing_error).powi(2);
+        }
+        let spread = (spread / 1000.0_f32).sqrt();
+        assert!((0.9..1.1).contains(&spread), "{}", spread);
+
+        // the echo of a pulse gives the range
+        world.ping(1);
+        let observations = observe(&world, world.entity(1).unwrap(), &mut rng);
+        let hull = observations
+            .iter()
+            .find(|o| o.sensor == SensorKind::HullSonar)
+            .unwrap();
+        let (range, error) = hull.range.unwrap();
+        assert!(error <= 30.0 * 10_f32.sqrt(), "{}", error);
+        assert!((range - 3000.0).abs() < 3.0 * error);
+    }
+
+    #[test]
+    fn ships_within_a_beam_are_one() {
+        let mut world = waters();
+        world.spawn(Entity::new(
+            "Empire Heron",
+            EntityKind::Merchant,
+            Point {
+                x: 3000.0,
+                y: 260.0,
+            },
+        ));
+        let observations = observe(&world, world.entity(1).unwrap(), &mut Rng::default());
+        let on = |sensor| observations.iter().filter(|o| o.sensor == sensor).count();
+        assert_eq!(on(SensorKind::HullSonar), 1);
+        assert_eq!(on(SensorKind::TowedArray), 2);
+        assert_eq!(on(SensorKind::Periscope), 2);
+
+        let heard = |bearing: f32, excess| Detection {
+            sensor: SensorKind::HullSonar,
+            bearing: bearing.to_radians(),
+            range: 3000.0,
+            excess,
+            ranged: None,
+            resolution: 0.0,
+        };
+        let blur = resolve(vec![heard(4.0, 10.0), heard(0.0, 20.0)]);
+        assert_eq!(blur.len(), 1);
+        let bearing = blur[0].bearing.to_degrees();
+        assert!(bearing > 0.0 && bearing < 1.0, "{}", bearing);
+        assert!((blur[0].excess - 20.4).abs() < 0.1, "{}", blur[0].excess);
+        assert_eq!(resolve(vec![heard(9.0, 10.0), heard(0.0, 20.0)]).len(), 2);
+
+        // a long pulse knows the range of its echo less well
+        let mut echo = heard(0.0, 20.0);
+        echo.ranged = Some(20.0);
+        let sharp = Observation::new(&echo, &Tuning::default(), &mut Rng::default())
+            .range
+            .unwrap()
+            .1;
+        echo.resolution = 300.0;
+        let long = Observation::new(&echo, &Tuning::default(), &mut Rng::default())
+            .range
+            .unwrap()
+            .1;
+        assert_eq!(sharp, 30.0);
+        assert!(long > 150.0);
+    }
+
+    #[test]
+    fn ships_apart_are_kept_apart() {
+        let mut world = waters();
+        world.spawn(Entity::new(
+            "Empire Heron",
+            EntityKind::Merchant,
+            Point { x: 0.0, y: 3000.0 },
+        ));
+        let mut table = ContactTable::default();
+        table.update(&world, 1);
+        assert_eq!(table.contacts.len(), 2);
+        let numbers: Vec<u32> = table.contacts.iter().map(|c| c.number).collect();
+        for _ in 0..10 {
+            world.step(1.0);
+            table.update(&world, 1);
+        }
+        let kept: Vec<u32> = table.contacts.iter().map(|c| c.number).collect();
+        assert_eq!(kept, numbers);
+        assert_eq!(table.contacts[0].bearings.len(), 11);
+
+        // lost, then dropped
+        world.entity_mut(2).unwrap().position = Point {
+            x: 90_000.0,
+            y: 0.0,
+        };
+        world.entity_mut(3).unwrap().position = Point {
+            x: 0.0,
+            y: 90_000.0,
+        };
+        world.step(1.0);
+        table.update(&world, 1);
+        assert_eq!(table.contacts.len(), 2);
+        world.time += ARCHIVE;
+        table.update(&world, 1);
+        assert!(table.contacts.is_empty());
+    }
+
+    #[test]
+    fn stale_contacts_are_lost_then_dropped() {
+        let mut world = waters();
+        let config = Config::parse("[contacts]\nlost = 30\narchive = 90").unwrap();
+        let mut table = ContactTable {
+            aging: Aging::read(&config).unwrap(),
+            ..ContactTable::default()
+        };
+        table.update(&world, 1);
+        let held = table.contacts[0].clone();
+        let gone = Point {
+            x: 90_000.0,
+            y: 0.0,
+        };
+        let back = std::mem::replace(&mut world.entity_mut(2).unwrap().position, gone.clone());
+        for _ in 0..31 {
+            world.step(1.0);
+            table.update(&world, 1);
+        }
+        let stale = &table.contacts[0];
+        assert!(stale.lost && stale.to_string().ends_with(" lost"));
+        assert!(stale.bearing_error > held.bearing_error);
+        assert!(stale.range.unwrap().1 > held.range.unwrap().1 + 100.0);
+
+        // held again, then lost for good
+        world.entity_mut(2).unwrap().position = back;
+        world.step(1.0);
+        table.update(&world, 1);
+        assert!(!table.contacts[0].lost);
+        world.entity_mut(2).unwrap().position = gone;
+        for _ in 0..91 {
+            world.step(1.0);
+            table.update(&world, 1);
+        }
+        assert!(table.contacts.is_empty());
+        let changes: Vec<ContactChange> = table.changes.iter().map(|c| c.change).collect();
+        assert_eq!(
+            changes,
+            vec![
+                ContactChange::Gained,
+                ContactChange::Lost,
+                ContactChange::Updated,
+                ContactChange::Lost,
+                ContactChange::Archived,
+            ]
+        );
+        assert!(table.changes.iter().all(|c| c.number == held.number));
+    }
+
+    #[test]
+    fn kalman_tracks_follow_the_contact() {
+        let mut world = waters();
+        let mut table = ContactTable::default();
+        table.update(&world, 1);
+        assert_eq!(table.contacts[0].track, None);
+        table.select(Tracker::Kalman);
+        for _ in 0..120 {
+            world.step(1.0);
+            table.update(&world, 1);
+        }
+        let track = table.contacts[0].track.as_ref().unwrap();
+        let merchant = &world.entity(2).unwrap().position;
+        let miss = track.position().distance_to(merchant);
+        assert!(miss < 3.0 * track.position_error(), "{}", miss);
+        assert!(track.position_error() < 100.0, "{}", track.position_error());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::environment::Environment;
+use crate::noise;
+use crate::physics::{Point, KNOT};
+use crate::sensors::excess_at;
+use crate::world::{Entity, EntityKind};
+
+// #############################
+// #      SENSOR COVERAGE      #
+// #############################
+
+// How far the passive sonars of a vessel reach right now against a
+// reference target: the "how far can I hear a merchant" ring of the plot.
+// The range is worked out bearing by bearing from the same signal excess
+// the sonars detect by (see sensors.rs), so it shrinks as the sea gets up
+// or the boat speeds up, and jumps when the boat and the target are on
+// opposite sides of the layer. The AI uses it too, to know how close it
+// must get to where it is looking before slowing down to listen.
+//
+// The range is the farthest one the sonars hear the target at, on the
+// assumption that a target closer in is heard all the better.
+
+/// Meters out to which coverage is worked out
+const MAX_RANGE: f32 = 100_000.0;
+/// Meters to which a range is worked out
+const RESOLUTION: f32 = 10.0;
+
+/// What a reference target sounds like: the noise of a vessel of a kind
+/// making a speed at a depth
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Signature {
+    pub kind: EntityKind,
+    /// Meters per second
+    pub speed: f32,
+    pub depth: f32,
+}
+
+impl Signature {
+    /// A merchant making 10 knots
+    pub const MERCHANT: Signature = Signature {
+        kind: EntityKind::Merchant,
+        speed: 10.0 * KNOT,
+        depth: 0.0,
+    };
+    /// A submarine creeping at 5 knots, deep
+    pub const SUBMARINE: Signature = Signature {
+        kind: EntityKind::Submarine,
+        speed: 5.0 * KNOT,
+        depth: 100.0,
+    };
+
+    /// dB the target radiates
+    pub fn level(&self) -> f32 {
+        let mut target = Entity::new("reference", self.kind, Point { x: 0.0, y: 0.0 });
+        target.speed = self.speed;
+        target.depth = self.depth;
+        noise::radiated_level(&target)
+    }
+}
+
+/// Meters out to which `listener` hears `target` along `bearing` (game
+/// angle), 0 when it would not hear it even alongside
+pub fn hearing_range(
+    environment: &Environment,
+    listener: &Entity,
+    target: &Signature,
+    bearing: f32,
+) -> f32 {
+    let level = target.level();
+    let heard = |range: f32| {
+        let at = Point {
+            x: listener.position.x + range * bearing.cos(),
+            y: listener.position.y + range * bearing.sin(),
+        };
+        excess_at(environment, listener, &at, target.depth, level).is_some_and(|e| e > 0.0)
+    };
+    if !heard(RESOLUTION) {
+        return 0.0;
+    }
+    if heard(MAX_RANGE) {
+        return MAX_RANGE;
+    }
+    let (mut near, mut far) = (RESOLUTION, MAX_RANGE);
+    while far - near > RESOLUTION {
+        let middle = (near + far) / 2.0;
+        if heard(middle) {
+            near = middle;
+        } else {
+            far = middle;
+        }
+    }
+    near
+}
+
+/// Outline of how far `listener` hears `target` all around, at `points`
+/// bearings evenly spaced; empty when it hears it on none
+pub fn contour(
+    environment: &Environment,
+    listener: &Entity,
+    target: &Signature,
+    points: usize,
+) -> Vec<Point> {
+    let outline: Vec<(f32, f32)> = (0..points)
+        .map(|i| {
+            let bearing = i as f32 * std::f32::consts::TAU / points as f32;
+            (
+                bearing,
+                hearing_range(environment, listener, target, bearing),
+            )
+        })
+        .collect();
+    if outline.iter().all(|(_, range)| *range <= 0.0) {
+        return Vec::new();
+    }
+    outline
+        .into_iter()
+        .map(|(bearing, range)| Point {
+            x: listener.position.x + range * bearing.cos(),
+            y: listener.position.y + range * bearing.sin(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::{passive_excess, Sensor, SensorKind};
+
+    fn boat(speed_knots: f32) -> Entity {
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        boat.speed = speed_knots * KNOT;
+        boat.depth = 20.0;
+        boat
+    }
+
+    #[test]
+    fn heard_out_to_the_range_and_no_farther() {
+        let environment = Environment::default();
+        let listener = boat(3.0);
+        let range = hearing_range(&environment, &listener, &Signature::MERCHANT, 0.0);
+        assert!(range > 1_000.0 && range < MAX_RANGE, "{}", range);
+        let mut merchant = Entity::new(
+            "SS Test",
+            EntityKind::Merchant,
+            Point {
+                x: range - 50.0,
+                y: 0.0,
+            },
+        );
+        merchant.speed = Signature::MERCHANT.speed;
+        assert!(passive_excess(&environment, &listener, &merchant).unwrap() > 0.0);
+        merchant.position.x = range + 50.0;
+        assert!(passive_excess(&environment, &listener, &merchant).unwrap() < 0.0);
+    }
+
+    #[test]
+    fn shrinks_with_speed_and_sea() {
+        let calm = Environment::default();
+        let slow = hearing_range(&calm, &boat(3.0), &Signature::MERCHANT, 0.0);
+        let fast = hearing_range(&calm, &boat(15.0), &Signature::MERCHANT, 0.0);
+        assert!(fast < slow);
+        let gale = Environment {
+            sea_state: 7,
+            ..Environment::default()
+        };
+        assert!(hearing_range(&gale, &boat(3.0), &Signature::MERCHANT, 0.0) < slow);
+        let ring = contour(&calm, &boat(3.0), &Signature::MERCHANT, 12);
+        assert_eq!(ring.len(), 12);
+        assert!((ring[3].distance_to(&Point { x: 0.0, y: 0.0 }) - slow).abs() < RESOLUTION);
+        let deaf = Entity::new("SS Deaf", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        assert!(contour(&calm, &deaf, &Signature::MERCHANT, 12).is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+
+// #############################
+// #       CREW QUALITY        #
+// #############################
+
+// The same boat is a different opponent with a different crew: a green
+// crew notices late, hears less, shoots wide and panics under attack.
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, PartialOrd)]
+pub enum CrewQuality {
+    Green,
+    #[default]
+    Trained,
+    Veteran,
+    Elite,
+}
+
+impl CrewQuality {
+    /// Seconds between hearing something and acting on it
+    pub fn reaction_time(&self) -> f32 {
+        match self {
+            CrewQuality::Green => 30.0,
+            CrewQuality::Trained => 15.0,
+            CrewQuality::Veteran => 8.0,
+            CrewQuality::Elite => 4.0,
+        }
+    }
+
+    /// dB the sonar operators gain (or lose) over the detection threshold
+    pub fn detection_bonus(&self) -> f32 {
+        match self {
+            CrewQuality::Green => -3.0,
+            CrewQuality::Trained => 0.0,
+            CrewQuality::Veteran => 2.0,
+            CrewQuality::Elite => 4.0,
+        }
+    }
+
+    /// dB the sonar operators discount a decoy by when telling it from the
+    /// boat it mimics, see decoy.rs
+    pub fn decoy_discount(&self) -> f32 {
+        match self {
+            CrewQuality::Green => 0.0,
+            CrewQuality::Trained => 3.0,
+            CrewQuality::Veteran => 6.0,
+            CrewQuality::Elite => 10.0,
+        }
+    }
+
+    /// Standard deviation in radians of the error of a firing bearing
+    pub fn aim_error(&self) -> f32 {
+        let degrees: f32 = match self {
+            CrewQuality::Green => 8.0,
+            CrewQuality::Trained => 4.0,
+            CrewQuality::Veteran => 2.0,
+            CrewQuality::Elite => 1.0,
+        };
+        degrees.to_radians()
+    }
+
+    /// Seconds the radio room takes to break an encrypted signal, see
+    /// signals.rs
+    pub fn decryption_time(&self) -> f32 {
+        match self {
+            CrewQuality::Green => 1_800.0,
+            CrewQuality::Trained => 1_200.0,
+            CrewQuality::Veteran => 900.0,
+            CrewQuality::Elite => 600.0,
+        }
+    }
+
+    /// Factor on how far off a navigational fix is, see navigation.rs
+    pub fn navigation_error(&self) -> f32 {
+        match self {
+            CrewQuality::Green => 2.0,
+            CrewQuality::Trained => 1.0,
+            CrewQuality::Veteran => 0.7,
+            CrewQuality::Elite => 0.5,
+        }
+    }
+
+    /// Whether the crew thinks of using the layer when evading, rather than
+    /// just running
+    pub fn uses_layer(&self) -> bool {
+        *self >= CrewQuality::Trained
+    }
+}
+
+impl FromStr for CrewQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "green" => Ok(CrewQuality::Green),
+            "trained" => Ok(CrewQuality::Trained),
+            "veteran" => Ok(CrewQuality::Veteran),
+            "elite" => Ok(CrewQuality::Elite),
+            _ => Err(format!("unknown crew quality '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for CrewQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CrewQuality::Green => "green",
+            CrewQuality::Trained => "trained",
+            CrewQuality::Veteran => "veteran",
+            CrewQuality::Elite => "elite",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How hard the computer-controlled crews are
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    /// Crew quality given to computer-controlled vessels that do not
+    /// name their own
+    pub fn crew(&self) -> CrewQuality {
+        match self {
+            Difficulty::Easy => CrewQuality::Green,
+            Difficulty::Normal => CrewQuality::Trained,
+            Difficulty::Hard => CrewQuality::Veteran,
+            Difficulty::Expert => CrewQuality::Elite,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(format!("unknown difficulty '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordering() {
+        assert!(CrewQuality::Elite > CrewQuality::Green);
+        assert!(CrewQuality::Elite.reaction_time() < CrewQuality::Green.reaction_time());
+        assert!(!CrewQuality::Green.uses_layer());
+        assert_eq!("Veteran".parse(), Ok(CrewQuality::Veteran));
+        assert_eq!(Difficulty::Hard.crew(), CrewQuality::Veteran);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::physics::Point;
+
+// #############################
+// #    LOST CONTACT SEARCH    #
+// #############################
+
+// A contact lost is not gone: it was last held at a datum, and since then
+// it can have gone no further than its speed allows. The furthest-on
+// circle, centered on the datum and growing by that speed every second,
+// bounds where it can be; where it most likely is, is where its course and
+// speed at the datum would have taken it. A search looks there first, then
+// sweeps either side of that course across the circle, further out on each
+// leg as the circle grows, until the contact is heard again or the search
+// is given up. The AI prosecutes its lost contacts this way, see ai.rs.
+
+/// Degrees between two legs of the sweep, either side of the course
+const SWEEP_STEP: f32 = 45.0;
+/// Fraction of the radius of the circle the sweep searches at
+const SWEEP_DEPTH: f32 = 0.6;
+
+/// Where and when a contact was lost
+#[derive(Debug, PartialEq, Clone)]
+pub struct Datum {
+    pub position: Point,
+    /// Meters per second, as last known
+    pub velocity: Point,
+    /// Seconds into the scenario it was lost at
+    pub time: f32,
+    /// Meters per second it is taken to be able to make at most
+    pub speed: f32,
+}
+
+impl Datum {
+    /// Radius of the furthest-on circle at `time`
+    pub fn radius(&self, time: f32) -> f32 {
+        self.speed * (time - self.time).max(0.0)
+    }
+
+    /// Whether the contact may be at `point` at `time`
+    pub fn contains(&self, point: &Point, time: f32) -> bool {
+        point.distance_to(&self.position) <= self.radius(time)
+    }
+
+    /// Where the contact is at `time` if it kept its course and speed,
+    /// inside the circle
+    pub fn dead_reckoned(&self, time: f32) -> Point {
+        let elapsed = (time - self.time).max(0.0);
+        let moved = Point {
+            x: self.velocity.x * elapsed,
+            y: self.velocity.y * elapsed,
+        };
+        let radius = self.radius(time);
+        if moved.abs() <= radius {
+            return self.position.add(&moved);
+        }
+        let unit = moved.unit();
+        Point {
+            x: self.position.x + unit.x * radius,
+            y: self.position.y + unit.y * radius,
+        }
+    }
+
+    /// Point to search on leg `leg` of the search, at `time`: the dead
+    /// reckoned position first, then alternately either side of the course
+    /// and further off it at each pair of legs
+    pub fn search_point(&self, leg: usize, time: f32) -> Point {
+        if leg == 0 {
+            return self.dead_reckoned(time);
+        }
+        let course = if self.velocity.abs() > 0.0 {
+            self.velocity.angle()
+        } else {
+            0.0
+        };
+        let side = if leg % 2 == 1 { 1.0 } else { -1.0 };
+        let off = side * SWEEP_STEP.to_radians() * leg.div_ceil(2) as f32;
+        let range = self.radius(time) * SWEEP_DEPTH;
+        Point {
+            x: self.position.x + range * (course + off).cos(),
+            y: self.position.y + range * (course + off).sin(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datum() -> Datum {
+        // lost at the origin making 4 m/s north, taken to make 8 at most
+        Datum {
+            position: Point { x: 0.0, y: 0.0 },
+            velocity: Point { x: 0.0, y: 4.0 },
+            time: 100.0,
+            speed: 8.0,
+        }
+    }
+
+    #[test]
+    fn circle_grows_with_time() {
+        let datum = datum();
+        assert_eq!(datum.radius(50.0), 0.0);
+        assert_eq!(datum.radius(200.0), 800.0);
+        assert!(datum.contains(&Point { x: 0.0, y: 700.0 }, 200.0));
+        assert!(!datum.contains(&Point { x: 0.0, y: 900.0 }, 200.0));
+        assert_eq!(datum.dead_reckoned(200.0), Point { x: 0.0, y: 400.0 });
+
+        // a contact faster than it was thought is kept inside the circle
+        let fast = Datum {
+            speed: 2.0,
+            ..datum
+        };
+        assert_eq!(fast.dead_reckoned(200.0), Point { x: 0.0, y: 200.0 });
+    }
+
+    #[test]
+    fn sweeps_either_side_of_the_course() {
+        let datum = datum();
+        let first = datum.search_point(1, 200.0);
+        let second = datum.search_point(2, 200.0);
+        let third = datum.search_point(3, 200.0);
+        // 45 degrees left of north, then right, then 90 left
+        assert!(first.x < 0.0 && first.y > 0.0, "{:?}", first);
+        assert!(second.x > 0.0 && second.y > 0.0, "{:?}", second);
+        assert!(third.x < 0.0 && third.y.abs() < 1.0, "{:?}", third);
+        for leg in 0..8 {
+            assert!(datum.contains(&datum.search_point(leg, 200.0), 200.0));
+        }
+    }
+}
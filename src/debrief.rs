@@ -0,0 +1,679 @@
+use std::fmt;
+
+use crate::environment::Environment;
+use crate::events::{Event, TimedEvent};
+use crate::gunnery;
+use crate::messages::Catalog;
+use crate::noise::{self, NoiseSource};
+use crate::preferences::Preferences;
+use crate::preview::Preview;
+use crate::reliability::Failure;
+use crate::seakeeping;
+use crate::sensors::passive_excess;
+use crate::simulation::Simulation;
+use crate::units::Meters;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #   POST-MISSION ANALYSIS   #
+// #############################
+
+// While the mission runs, a recorder keeps what the events alone do not
+// tell: when the own ship gained and lost each contact and when each other
+// vessel gained and lost the own ship, how loud the own ship was, and how
+// close each of its torpedoes came. After the mission the debrief puts it
+// together with the events, with hints on what gave the boat away, and is
+// written out one record per line for the UI to read:
+//
+// duration 3600.0
+// detection 133.0 3 1 sonar gained
+// noise 60.0 131.2 180.0 8.2 cavitation
+// shot 400.0 9 missed 2 340.0
+// preview 400.0 9 0.85 12.0 95.0
+// hint 133.0 3 sonar 180.0 cavitation
+//
+// Detection lines are time, observer, target, sensor and whether the
+// contact was gained or lost; noise lines are time, level in dB, depth,
+// speed in m/s and the loudest contributor; shot lines are launch time,
+// torpedo and outcome: the target hit, the target and failure, or the
+// closest vessel and range in meters when it missed; preview lines are
+// launch time, torpedo, and the chance to hit, miss in meters and run time
+// in seconds the fire control gave the shot (see preview.rs), so a miss
+// tells whether the solution or the luck was bad. Free text comes last.
+
+/// Seconds between two samples of the own ship's noise
+const NOISE_INTERVAL: f32 = 60.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FailureReport {
+    pub time: f32,
+    pub shooter: EntityId,
+    pub target: Option<EntityId>,
+    pub failure: Failure,
+}
+
+impl FailureReport {
+    /// The report as written for the player
+    pub fn describe(
+        &self,
+        messages: &Catalog,
+        preferences: &Preferences,
+        environment: &Environment,
+    ) -> String {
+        let time = preferences.time(environment, self.time);
+        let failure = messages.get(self.failure.message());
+        match self.target {
+            Some(target) => messages.format(
+                "failure",
+                &[("time", &time), ("target", &target), ("failure", &failure)],
+            ),
+            None => messages.format(
+                "failure-untargeted",
+                &[("time", &time), ("failure", &failure)],
+            ),
+        }
+    }
+}
+
+impl fmt::Display for FailureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.describe(
+            &Catalog::default(),
+            &Preferences::default(),
+            &Environment::default(),
+        );
+        write!(f, "{}", text)
+    }
+}
+
+/// Every torpedo that failed during the mission, in order
+pub fn torpedo_failures(events: &[TimedEvent]) -> Vec<FailureReport> {
+    events
+        .iter()
+        .filter_map(|e| match e.event {
+            Event::TorpedoFailed {
+                shooter,
+                target,
+                failure,
+            } => Some(FailureReport {
+                time: e.time,
+                shooter,
+                target,
+                failure,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DetectionKind {
+    Sonar,
+    Radar,
+    Sight,
+}
+
+impl fmt::Display for DetectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DetectionKind::Sonar => "sonar",
+            DetectionKind::Radar => "radar",
+            DetectionKind::Sight => "sight",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A contact gained or lost
+#[derive(Debug, PartialEq, Clone)]
+pub struct Detection {
+    pub time: f32,
+    pub observer: EntityId,
+    pub target: EntityId,
+    pub kind: DetectionKind,
+    pub gained: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NoiseSample {
+    pub time: f32,
+    /// Radiated level in dB
+    pub level: f32,
+    pub depth: f32,
+    /// Meters per second
+    pub speed: f32,
+    pub loudest: Option<NoiseSource>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ShotOutcome {
+    Running,
+    Hit(EntityId),
+    Failed {
+        target: EntityId,
+        failure: Failure,
+    },
+    /// Ran out without hitting; the closest vessel and how close it came,
+    /// None when it came near nothing
+    Missed(Option<(EntityId, f32)>),
+}
+
+/// A torpedo of the own ship and what became of it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Shot {
+    pub time: f32,
+    pub torpedo: EntityId,
+    pub outcome: ShotOutcome,
+    /// Closest vessel so far and the range in meters
+    pub closest: Option<(EntityId, f32)>,
+    /// The shot run on paper as it was fired
+    pub preview: Option<Preview>,
+}
+
+impl Shot {
+    /// Why the shot did what it did, as written for the player
+    pub fn describe(
+        &self,
+        messages: &Catalog,
+        preferences: &Preferences,
+        environment: &Environment,
+    ) -> String {
+        let time = preferences.time(environment, self.time);
+        let outcome = self.outcome(messages, preferences, &time);
+        match (&self.outcome, &self.preview) {
+            (ShotOutcome::Missed(_), Some(preview)) => {
+                let chance = format!("{:.0}", preview.hit_probability * 100.0);
+                let why = if preview.hit_probability >= 0.5 {
+                    messages.format("shot-expected-hit", &[("chance", &chance)])
+                } else {
+                    let miss = preferences.units.range(Meters(preview.miss_distance));
+                    messages.format(
+                        "shot-expected-miss",
+                        &[("miss", &miss), ("chance", &chance)],
+                    )
+                };
+                format!("{}; {}", outcome, why)
+            }
+            _ => outcome,
+        }
+    }
+
+    fn outcome(&self, messages: &Catalog, preferences: &Preferences, time: &str) -> String {
+        match &self.outcome {
+            ShotOutcome::Running => messages.format("shot-running", &[("time", &time)]),
+            ShotOutcome::Hit(target) => {
+                messages.format("shot-hit", &[("time", &time), ("target", target)])
+            }
+            ShotOutcome::Failed { target, failure } => {
+                let failure = messages.get(failure.message());
+                messages.format(
+                    "shot-failed",
+                    &[("time", &time), ("target", target), ("failure", &failure)],
+                )
+            }
+            ShotOutcome::Missed(Some((target, range))) => {
+                let range = preferences.units.range(Meters(*range));
+                messages.format(
+                    "shot-missed",
+                    &[("time", &time), ("target", target), ("range", &range)],
+                )
+            }
+            ShotOutcome::Missed(None) => messages.format("shot-wild", &[("time", &time)]),
+        }
+    }
+}
+
+/// What gave the own ship away to a vessel that gained it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hint {
+    pub time: f32,
+    pub observer: EntityId,
+    pub kind: DetectionKind,
+    pub depth: f32,
+    /// Loudest noise of the own ship, for sonar detections
+    pub source: Option<NoiseSource>,
+}
+
+impl Hint {
+    /// The hint as written for the player
+    pub fn describe(
+        &self,
+        messages: &Catalog,
+        preferences: &Preferences,
+        environment: &Environment,
+    ) -> String {
+        let time = preferences.time(environment, self.time);
+        let depth = preferences.units.depth(Meters(self.depth));
+        match (self.kind, self.source) {
+            (DetectionKind::Sonar, Some(source)) => messages.format(
+                "hint-sonar",
+                &[("time", &time), ("source", &source), ("depth", &depth)],
+            ),
+            (DetectionKind::Radar, _) => {
+                messages.format("hint-radar", &[("time", &time), ("depth", &depth)])
+            }
+            _ => messages.format("hint-sighted", &[("time", &time), ("depth", &depth)]),
+        }
+    }
+}
+
+/// How `observer` holds `target` right now, the surest way first
+fn detects(world: &World, observer: &Entity, target: &Entity) -> Option<DetectionKind> {
+    let range = observer.position.distance_to(&target.position);
+    if gunnery::exposure(observer) > 0.0
+        && gunnery::exposure(target) > 0.0
+        && range <= seakeeping::sighting_range(observer, &world.environment)
+    {
+        Some(DetectionKind::Sight)
+    } else if observer.radar_contacts.contains(&target.id) {
+        Some(DetectionKind::Radar)
+    } else if passive_excess(&world.environment, observer, target).is_some_and(|e| e > 0.0) {
+        Some(DetectionKind::Sonar)
+    } else {
+        None
+    }
+}
+
+/// Keeps what the debrief needs as the mission runs
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Recorder {
+    /// Contacts held at the last record, (observer, target, how)
+    held: Vec<(EntityId, EntityId, DetectionKind)>,
+    pub detections: Vec<Detection>,
+    pub noise: Vec<NoiseSample>,
+    pub shots: Vec<Shot>,
+    pub hints: Vec<Hint>,
+    /// Shots run on paper as they were fired, by torpedo, until recorded
+    pub previews: Vec<(EntityId, Preview)>,
+    /// Events already gone through
+    events_read: usize,
+}
+
+impl Recorder {
+    /// Records the state of `world` around the own ship `own`
+    pub fn record(&mut self, world: &World, own: EntityId) {
+        let ship = match world.entity(own) {
+            Some(ship) if !ship.is_destroyed() => ship,
+            _ => return,
+        };
+        self.record_detections(world, ship);
+        let due = self
+            .noise
+            .last()
+            .is_none_or(|s| world.time - s.time >= NOISE_INTERVAL);
+        if due {
+            self.noise.push(NoiseSample {
+                time: world.time,
+                level: noise::radiated_level(ship),
+                depth: ship.depth,
+                speed: ship.speed,
+                loudest: noise::contributors(ship).first().map(|c| c.source),
+            });
+        }
+        self.record_shots(world, own);
+    }
+
+    fn record_detections(&mut self, world: &World, ship: &Entity) {
+        let mut held = Vec::new();
+        for other in world.entities.iter() {
+            if other.id == ship.id || other.kind == EntityKind::Torpedo || other.is_destroyed() {
+                continue;
+            }
+            for (observer, target) in [(ship, other), (other, ship)] {
+                if let Some(kind) = detects(world, observer, target) {
+                    held.push((observer.id, target.id, kind));
+                }
+            }
+        }
+        for &(observer, target, kind) in &held {
+            if self.held.iter().any(|h| h.0 == observer && h.1 == target) {
+                continue;
+            }
+            self.detections.push(Detection {
+                time: world.time,
+                observer,
+                target,
+                kind,
+                gained: true,
+            });
+            if target == ship.id {
+                self.hints.push(Hint {
+                    time: world.time,
+                    observer,
+                    kind,
+                    depth: ship.depth,
+                    source: noise::contributors(ship).first().map(|c| c.source),
+                });
+            }
+        }
+        for &(observer, target, kind) in &self.held {
+            if !held.iter().any(|h| h.0 == observer && h.1 == target) {
+                self.detections.push(Detection {
+                    time: world.time,
+                    observer,
+                    target,
+                    kind,
+                    gained: false,
+                });
+            }
+        }
+        self.held = held;
+    }
+
+    fn record_shots(&mut self, world: &World, own: EntityId) {
+        for timed in &world.events[self.events_read..] {
+            match timed.event {
+                Event::TorpedoFired { shooter, torpedo } if shooter == own => {
+                    let preview = self
+                        .previews
+                        .iter()
+                        .position(|p| p.0 == torpedo)
+                        .map(|i| self.previews.remove(i).1);
+                    self.shots.push(Shot {
+                        time: timed.time,
+                        torpedo,
+                        outcome: ShotOutcome::Running,
+                        closest: None,
+                        preview,
+                    });
+                }
+                Event::TorpedoHit {
+                    torpedo, target, ..
+                } => {
+                    if let Some(shot) = self.running(torpedo) {
+                        shot.outcome = ShotOutcome::Hit(target);
+                    }
+                }
+                Event::TorpedoRanOut { torpedo } => {
+                    if let Some(shot) = self.running(torpedo) {
+                        shot.outcome = ShotOutcome::Missed(shot.closest);
+                    }
+                }
+                Event::TorpedoFailed {
+                    shooter,
+                    target: Some(target),
+                    failure,
+                } if shooter == own && failure != Failure::RanDeep => {
+                    // the torpedo is gone: the running one closest to the target
+                    let shot = self
+                        .shots
+                        .iter_mut()
+                        .filter(|s| s.outcome == ShotOutcome::Running)
+                        .filter(|s| world.entity(s.torpedo).is_none())
+                        .find(|s| s.closest.is_some_and(|c| c.0 == target));
+                    if let Some(shot) = shot {
+                        shot.outcome = ShotOutcome::Failed { target, failure };
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.events_read = world.events.len();
+        for shot in &mut self.shots {
+            if shot.outcome != ShotOutcome::Running {
+                continue;
+            }
+            let torpedo = match world.entity(shot.torpedo) {
+                Some(torpedo) => torpedo,
+                None => continue,
+            };
+            let nearest = world
+                .entities
+                .iter()
+                .filter(|e| e.id != own && e.kind != EntityKind::Torpedo && !e.is_destroyed())
+                .map(|e| (e.id, e.position.distance_to(&torpedo.position)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some(nearest) = nearest {
+                if shot.closest.is_none_or(|c| nearest.1 < c.1) {
+                    shot.closest = Some(nearest);
+                }
+            }
+        }
+    }
+
+    fn running(&mut self, torpedo: EntityId) -> Option<&mut Shot> {
+        self.shots
+            .iter_mut()
+            .find(|s| s.torpedo == torpedo && s.outcome == ShotOutcome::Running)
+    }
+}
+
+/// Everything the player is told after the mission
+#[derive(Debug, PartialEq, Clone)]
+pub struct Debrief {
+    /// Seconds the mission lasted
+    pub duration: f32,
+    pub detections: Vec<Detection>,
+    pub noise: Vec<NoiseSample>,
+    pub shots: Vec<Shot>,
+    pub failures: Vec<FailureReport>,
+    pub hints: Vec<Hint>,
+}
+
+impl Debrief {
+    pub fn compile(sim: &Simulation) -> Debrief {
+        let recorder = &sim.recorder;
+        Debrief {
+            duration: sim.world.time,
+            detections: recorder.detections.clone(),
+            noise: recorder.noise.clone(),
+            shots: recorder.shots.clone(),
+            failures: torpedo_failures(&sim.world.events),
+            hints: recorder.hints.clone(),
+        }
+    }
+
+    /// The debrief as written for the player: every shot, then the hints
+    pub fn describe(
+        &self,
+        messages: &Catalog,
+        preferences: &Preferences,
+        environment: &Environment,
+    ) -> Vec<String> {
+        let shots = self
+            .shots
+            .iter()
+            .map(|s| s.describe(messages, preferences, environment));
+        let hints = self
+            .hints
+            .iter()
+            .map(|h| h.describe(messages, preferences, environment));
+        shots.chain(hints).collect()
+    }
+}
+
+/// Writes the debrief one record per line, see the top of this file
+impl fmt::Display for Debrief {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "duration {:.1}", self.duration)?;
+        for d in &self.detections {
+            let change = if d.gained { "gained" } else { "lost" };
+            writeln!(
+                f,
+                "detection {:.1} {} {} {} {}",
+                d.time, d.observer, d.target, d.kind, change
+            )?;
+        }
+        for s in &self.noise {
+            write!(
+                f,
+                "noise {:.1} {:.1} {:.1} {:.1}",
+                s.time, s.level, s.depth, s.speed
+            )?;
+            match s.loudest {
+                Some(source) => writeln!(f, " {}", source)?,
+                None => writeln!(f)?,
+            }
+        }
+        for s in &self.shots {
+            write!(f, "shot {:.1} {} ", s.time, s.torpedo)?;
+            match &s.outcome {
+                ShotOutcome::Running => writeln!(f, "running")?,
+                ShotOutcome::Hit(target) => writeln!(f, "hit {}", target)?,
+                ShotOutcome::Failed { target, failure } => {
+                    writeln!(f, "failed {} {}", target, failure)?
+                }
+                ShotOutcome::Missed(Some((target, range))) => {
+                    writeln!(f, "missed {} {:.1}", target, range)?
+                }
+                ShotOutcome::Missed(None) => writeln!(f, "missed")?,
+            }
+        }
+        for s in &self.shots {
+            if let Some(p) = &s.preview {
+                writeln!(
+                    f,
+                    "preview {:.1} {} {:.2} {:.1} {:.1}",
+                    s.time, s.torpedo, p.hit_probability, p.miss_distance, p.run_time
+                )?;
+            }
+        }
+        for h in &self.hints {
+            write!(
+                f,
+                "hint {:.1} {} {} {:.1}",
+                h.time, h.observer, h.kind, h.depth
+            )?;
+            match h.source {
+                Some(source) => writeln!(f, " {}", source)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+    use crate::physics::{Point, KNOT};
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::tracking::Tracker;
+    use crate::units::UnitSystem;
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+    use std::f32::consts::FRAC_PI_2;
+
+    fn waters() -> Simulation {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-47", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 40.0;
+        boat.heading = FRAC_PI_2;
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        boat.weapons = Some(WeaponsStation::new(2, PresetLibrary::new()));
+        let player = world.spawn(boat);
+        let mut escort = Entity::new("Vanoc", EntityKind::Warship, Point { x: 2000.0, y: 0.0 });
+        escort.heading = FRAC_PI_2;
+        escort.sensors.push(Sensor::new(SensorKind::HullSonar));
+        world.spawn(escort);
+        Simulation::new(world, player)
+    }
+
+    #[test]
+    fn cavitating_gives_the_boat_away() {
+        let mut sim = waters();
+        for _ in 0..5 {
+            sim.step(1.0);
+        }
+        assert!(!sim.recorder.hints.iter().any(|h| h.observer == 2));
+        sim.own_ship_mut().unwrap().speed = 15.0 * KNOT;
+        for _ in 0..5 {
+            sim.step(1.0);
+        }
+        let debrief = Debrief::compile(&sim);
+        let hint = debrief.hints.iter().find(|h| h.observer == 2).unwrap();
+        assert_eq!(hint.kind, DetectionKind::Sonar);
+        assert_eq!(hint.source, Some(NoiseSource::Cavitation));
+        let text = hint.describe(
+            &Catalog::default(),
+            &Preferences::default(),
+            &sim.world.environment,
+        );
+        assert_eq!(text, "you were detected at 00:00 due to cavitation at 40 m");
+        let imperial = Preferences {
+            units: UnitSystem::Imperial,
+            ..Preferences::default()
+        };
+        let text = hint.describe(&Catalog::default(), &imperial, &sim.world.environment);
+        assert!(text.ends_with(" at 131 ft"), "{}", text);
+        let text = debrief.to_string();
+        assert!(
+            text.contains("detection 6.0 2 1 sonar gained\n"),
+            "{}",
+            text
+        );
+
+        sim.own_ship_mut().unwrap().speed = 0.0;
+        sim.step(1.0);
+        let lost = sim.recorder.detections.last().unwrap();
+        assert_eq!((lost.observer, lost.gained), (2, false));
+        assert_eq!(sim.recorder.noise.len(), 1);
+    }
+
+    #[test]
+    fn misses_are_measured() {
+        let mut sim = waters();
+        // straight up north, away from the escort
+        sim.execute(&Command::parse("fire 1 0").unwrap()).unwrap();
+        for _ in 0..1500 {
+            sim.step(1.0);
+        }
+        let debrief = Debrief::compile(&sim);
+        let shot = &debrief.shots[0];
+        match shot.outcome {
+            ShotOutcome::Missed(Some((2, range))) => assert!(range > 1500.0, "{}", range),
+            ref other => panic!("{:?}", other),
+        }
+        assert!(debrief.to_string().contains("shot 0.0 3 missed 2 "));
+        assert_eq!(debrief.noise.len(), 25);
+    }
+
+    #[test]
+    fn misses_are_explained_by_the_preview() {
+        let mut sim = waters();
+        sim.contacts.select(Tracker::Kalman);
+        for _ in 0..120 {
+            sim.step(1.0);
+        }
+        // tracked on bearings alone, the escort to the east
+        assert!(sim.preview(1, 90.0).is_some());
+        assert_eq!(sim.preview(3, 90.0), None);
+        sim.execute(&Command::parse("fire 1 0").unwrap()).unwrap();
+        for _ in 0..1500 {
+            sim.step(1.0);
+        }
+        let debrief = Debrief::compile(&sim);
+        let shot = &debrief.shots[0];
+        let preview = shot.preview.as_ref().unwrap();
+        assert!(preview.miss_distance > 1_500.0);
+        let text = shot.describe(
+            &Catalog::default(),
+            &Preferences::default(),
+            &sim.world.environment,
+        );
+        assert!(text.contains("; the solution had it passing "), "{}", text);
+        assert!(text.contains(" m off, "), "{}", text);
+        assert!(debrief.to_string().contains("preview 120.0 3 0.00 "));
+    }
+
+    #[test]
+    fn failures() {
+        let events = vec![
+            TimedEvent {
+                time: 10.0,
+                event: Event::Destroyed { entity: 3 },
+            },
+            TimedEvent {
+                time: 3720.0,
+                event: Event::TorpedoFailed {
+                    shooter: 1,
+                    target: Some(2),
+                    failure: Failure::Dud,
+                },
+            },
+        ];
+        let reports = torpedo_failures(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].to_string(), "01:02 torpedo against #2: dud");
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+
+use crate::command::DecoyCommand;
+use crate::messages::Catalog;
+use crate::noise;
+use crate::physics::{user_to_game_angle, Point, KNOT};
+use crate::seeker::{AcousticSource, SourceKind};
+use crate::sensors::excess_at;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #      ACOUSTIC DECOYS      #
+// #############################
+
+// A boat hunted by acoustic torpedoes or listening enemies can put false
+// targets in the water. A towed decoy is streamed astern on a cable and
+// plays back the boat's own signature, so it sounds just like the boat a
+// little way behind it; it is recovered when no longer needed. A mobile
+// decoy swims off on its own along a programmed course, making the noise
+// of a submarine until its battery is flat. Both come out of a limited
+// stock set by the class:
+//
+// [class.los_angeles]
+// towed_decoys = 1
+// decoys = 6              # mobile decoys
+//
+// Torpedo seekers discount decoys by their generation (see seeker.rs);
+// crews discount them by their quality (see crew.rs), so a green crew
+// stalks the decoy while an elite one keeps to the boat.
+
+/// Meters of cable between a towing boat and its decoy
+const TOW_CABLE: f32 = 300.0;
+/// dB a towed decoy plays back over the signature of its boat
+const TOWED_GAIN: f32 = 3.0;
+/// Source level in dB of a mobile decoy, that of a submarine making way
+const MOBILE_LEVEL: f32 = 125.0;
+const MOBILE_SPEED: f32 = 10.0 * KNOT;
+/// Seconds a mobile decoy runs before its battery is flat
+const MOBILE_ENDURANCE: f32 = 900.0;
+/// Seconds a mobile decoy holds each leg of its program
+pub const LEG_TIME: f32 = 120.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DecoyKind {
+    Towed,
+    Mobile,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Decoy {
+    /// Boat that put the decoy in the water
+    pub owner: EntityId,
+    pub kind: DecoyKind,
+    pub position: Point,
+    pub depth: f32,
+    /// Game angles of the legs a mobile decoy runs, in order
+    pub program: Vec<f32>,
+    /// Seconds since the decoy was put in the water
+    pub age: f32,
+    /// Source level in dB at 1 m
+    pub level: f32,
+}
+
+impl Decoy {
+    /// Heading of the leg the program is on
+    fn heading(&self) -> f32 {
+        let leg = (self.age / LEG_TIME) as usize;
+        self.program[leg.min(self.program.len() - 1)]
+    }
+
+    /// The decoy as heard by a torpedo seeker
+    pub fn source(&self) -> AcousticSource {
+        AcousticSource {
+            id: self.owner,
+            kind: SourceKind::Decoy,
+            position: self.position.clone(),
+            depth: self.depth,
+            level: self.level,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecoyError {
+    NoDecoys,
+    AlreadyStreamed,
+    NotStreamed,
+}
+
+impl DecoyError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        let id = match self {
+            DecoyError::NoDecoys => "error-no-decoys",
+            DecoyError::AlreadyStreamed => "error-decoy-streamed",
+            DecoyError::NotStreamed => "error-no-decoy-streamed",
+        };
+        messages.get(id).to_string()
+    }
+}
+
+impl fmt::Display for DecoyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for DecoyError {}
+
+/// Where the towed decoy of `boat` rides, at the end of its cable
+fn astern(boat: &Entity) -> Point {
+    Point {
+        x: boat.position.x - TOW_CABLE * boat.heading.cos(),
+        y: boat.position.y - TOW_CABLE * boat.heading.sin(),
+    }
+}
+
+fn towing(world: &World, boat: EntityId) -> Option<usize> {
+    world
+        .decoys
+        .iter()
+        .position(|d| d.owner == boat && d.kind == DecoyKind::Towed)
+}
+
+/// Applies a player decoy order to `boat`; the legs of a mobile decoy are
+/// user angles in degrees
+pub fn execute(
+    world: &mut World,
+    boat: EntityId,
+    command: &DecoyCommand,
+) -> Result<(), DecoyError> {
+    let towed = towing(world, boat);
+    let entity = world.entity_mut(boat).ok_or(DecoyError::NoDecoys)?;
+    let decoy = match command {
+        DecoyCommand::Stream => {
+            if towed.is_some() {
+                return Err(DecoyError::AlreadyStreamed);
+            }
+            if entity.towed_decoys == 0 {
+                return Err(DecoyError::NoDecoys);
+            }
+            entity.towed_decoys -= 1;
+            Decoy {
+                owner: boat,
+                kind: DecoyKind::Towed,
+                position: astern(entity),
+                depth: entity.depth,
+                program: Vec::new(),
+                age: 0.0,
+                level: noise::radiated_level(entity) + TOWED_GAIN,
+            }
+        }
+        DecoyCommand::Recover => {
+            let index = towed.ok_or(DecoyError::NotStreamed)?;
+            entity.towed_decoys += 1;
+            world.decoys.remove(index);
+            return Ok(());
+        }
+        DecoyCommand::Launch(bearings) => {
+            if entity.decoys == 0 {
+                return Err(DecoyError::NoDecoys);
+            }
+            entity.decoys -= 1;
+            Decoy {
+                owner: boat,
+                kind: DecoyKind::Mobile,
+                position: entity.position.clone(),
+                depth: entity.depth,
+                program: bearings.iter().map(|b| user_to_game_angle(*b)).collect(),
+                age: 0.0,
+                level: MOBILE_LEVEL,
+            }
+        }
+    };
+    world.decoys.push(decoy);
+    Ok(())
+}
+
+/// Tows, runs and spends every decoy in the water by `dt` seconds
+pub fn update(world: &mut World, dt: f32) {
+    let entities = &world.entities;
+    world.decoys.retain_mut(|decoy| {
+        let alive = match decoy.kind {
+            DecoyKind::Towed => match entities.get(decoy.owner) {
+                Some(boat) if !boat.is_destroyed() => {
+                    decoy.position = astern(boat);
+                    decoy.depth = boat.depth;
+                    decoy.level = noise::radiated_level(boat) + TOWED_GAIN;
+                    true
+                }
+                _ => false,
+            },
+            DecoyKind::Mobile => {
+                let heading = decoy.heading();
+                decoy.position.x += heading.cos() * MOBILE_SPEED * dt;
+                decoy.position.y += heading.sin() * MOBILE_SPEED * dt;
+                decoy.age + dt < MOBILE_ENDURANCE
+            }
+        };
+        decoy.age += dt;
+        alive
+    });
+}
+
+/// Signal excess in dB `listener` hears `decoy` at as the boat it mimics,
+/// less what its crew discounts a decoy by; None when not heard at all
+pub fn apparent_excess(world: &World, listener: &Entity, decoy: &Decoy) -> Option<f32> {
+    let heard = excess_at(
+        &world.environment,
+        listener,
+        &decoy.position,
+        decoy.depth,
+        decoy.level,
+    )?;
+    Some(heard - listener.crew.decoy_discount())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crew::CrewQuality;
+    use crate::sensors::{passive_excess, Sensor, SensorKind};
+    use crate::world::EntityKind;
+
+    fn waters() -> World {
+        let mut world = World::new();
+        let mut boat = Entity::new("Dallas", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 100.0;
+        boat.speed = 10.0 * KNOT;
+        boat.towed_decoys = 1;
+        boat.decoys = 2;
+        world.spawn(boat);
+        let mut hunter = Entity::new(
+            "hunter",
+            EntityKind::Submarine,
+            Point { x: -2500.0, y: 0.0 },
+        );
+        hunter.depth = 100.0;
+        hunter.sensors.push(Sensor::new(SensorKind::HullSonar));
+        world.spawn(hunter);
+        world
+    }
+
+    #[test]
+    fn stock_and_streaming() {
+        let mut world = waters();
+        execute(&mut world, 1, &DecoyCommand::Stream).unwrap();
+        assert_eq!(
+            execute(&mut world, 1, &DecoyCommand::Stream),
+            Err(DecoyError::AlreadyStreamed)
+        );
+        world.step(10.0);
+        let decoy = &world.decoys[0];
+        assert!((decoy.position.x + TOW_CABLE - 10.0 * KNOT * 10.0).abs() < 1.0);
+        execute(&mut world, 1, &DecoyCommand::Recover).unwrap();
+        assert_eq!(world.entity(1).unwrap().towed_decoys, 1);
+        assert_eq!(
+            execute(&mut world, 1, &DecoyCommand::Recover),
+            Err(DecoyError::NotStreamed)
+        );
+
+        let start = world.entity(1).unwrap().position.clone();
+        let program = DecoyCommand::Launch(vec![90.0, 0.0]);
+        execute(&mut world, 1, &program).unwrap();
+        execute(&mut world, 1, &program).unwrap();
+        assert_eq!(execute(&mut world, 1, &program), Err(DecoyError::NoDecoys));
+        world.step(LEG_TIME);
+        world.step(LEG_TIME);
+        let decoy = &world.decoys[0];
+        let leg = MOBILE_SPEED * LEG_TIME;
+        let run = decoy.position.sub(&start);
+        assert!((run.x - leg).abs() < 1.0 && (run.y - leg).abs() < 1.0);
+        world.step(MOBILE_ENDURANCE);
+        assert!(world.decoys.is_empty());
+    }
+
+    #[test]
+    fn green_crews_are_seduced() {
+        let mut world = waters();
+        // the hunter is astern, the towed decoy between it and the boat
+        execute(&mut world, 1, &DecoyCommand::Stream).unwrap();
+        let boat = world.entity(1).unwrap();
+        let mut hunter = world.entity(2).unwrap().clone();
+        hunter.crew = CrewQuality::Green;
+        let excess = passive_excess(&world.environment, &hunter, boat).unwrap();
+        assert!(excess > 0.0);
+        let decoy = &world.decoys[0];
+        assert!(apparent_excess(&world, &hunter, decoy).unwrap() > excess);
+        hunter.crew = CrewQuality::Elite;
+        let excess = passive_excess(&world.environment, &hunter, boat).unwrap();
+        assert!(apparent_excess(&world, &hunter, decoy).unwrap() < excess);
+    }
+}
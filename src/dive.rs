@@ -0,0 +1,280 @@
+use std::fmt;
+
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::messages::Catalog;
+use crate::transient::{self, TransientKind};
+use crate::world::{EntityId, EntityKind, World};
+
+// #############################
+// #    DIVING AND SURFACING   #
+// #############################
+
+// A submarine does not simply change depth between the surface and below.
+// To dive, the bridge watch has to get down the hatch and the hatches be
+// shut before the vents open and the boat floods down to periscope depth,
+// in the dive time of its class. A crash dive clears the bridge and floods
+// faster, with all vents roaring open at once; a gun crew on deck has to
+// secure the gun first, or in a crash dive leave it and scramble below
+// with the boat already going under. Surfacing blows the ballast tanks,
+// loudly, until the boat rides on the surface again.
+
+/// Seconds to send the bridge watch below for a normal dive
+const CLEAR_BRIDGE_TIME: f32 = 20.0;
+/// Seconds to send the bridge watch below for a crash dive
+const CRASH_CLEAR_BRIDGE_TIME: f32 = 8.0;
+/// Seconds for a gun crew to secure the deck gun and get below
+const SECURE_GUN_TIME: f32 = 15.0;
+/// Seconds a gun crew scrambling below adds to a crash dive
+const SCRAMBLE_TIME: f32 = 5.0;
+/// Fraction of the hull lost to the sea coming in over a crash dive with
+/// the gun crew still on deck
+const SCRAMBLE_DAMAGE: f32 = 0.02;
+/// Fraction of the dive time a crash dive floods down in
+const CRASH_FLOOD_FRACTION: f32 = 0.5;
+/// Meters per second a boat rises at while blowing ballast
+const SURFACE_RATE: f32 = 1.0;
+/// Seconds to reach periscope depth from the surface when the class does
+/// not say
+pub const DEFAULT_DIVE_TIME: f32 = 40.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DiveKind {
+    Normal,
+    Crash,
+}
+
+impl fmt::Display for DiveKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DiveKind::Normal => "normal",
+            DiveKind::Crash => "crash",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A submarine on its way between the surface and periscope depth
+#[derive(Debug, PartialEq, Clone)]
+pub enum Transition {
+    /// Clearing the bridge for `clear_time` seconds, then flooding down
+    /// for `flood_time` seconds
+    Diving {
+        kind: DiveKind,
+        elapsed: f32,
+        clear_time: f32,
+        flood_time: f32,
+    },
+    /// Blowing ballast
+    Surfacing,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DiveError {
+    CannotDive,
+    AlreadySubmerged,
+    AlreadySurfaced,
+    /// Still diving or surfacing
+    Underway,
+}
+
+impl DiveError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        let id = match self {
+            DiveError::CannotDive => "error-cannot-dive",
+            DiveError::AlreadySubmerged => "error-already-submerged",
+            DiveError::AlreadySurfaced => "error-already-surfaced",
+            DiveError::Underway => "error-dive-underway",
+        };
+        messages.get(id).to_string()
+    }
+}
+
+impl fmt::Display for DiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for DiveError {}
+
+/// Orders `boat` to dive, flooding down in `dive_time` seconds (the time of
+/// its class) once the bridge is clear
+pub fn dive(
+    world: &mut World,
+    boat: EntityId,
+    kind: DiveKind,
+    dive_time: f32,
+) -> Result<(), DiveError> {
+    let entity = world.entity_mut(boat).ok_or(DiveError::CannotDive)?;
+    if entity.kind != EntityKind::Submarine {
+        return Err(DiveError::CannotDive);
+    }
+    if entity.transition.is_some() {
+        return Err(DiveError::Underway);
+    }
+    if !entity.is_surfaced() {
+        return Err(DiveError::AlreadySubmerged);
+    }
+    let gun_manned = entity.gun.as_ref().is_some_and(|g| g.manned);
+    let (mut clear_time, flood_time) = match kind {
+        DiveKind::Normal => (CLEAR_BRIDGE_TIME, dive_time),
+        DiveKind::Crash => (CRASH_CLEAR_BRIDGE_TIME, dive_time * CRASH_FLOOD_FRACTION),
+    };
+    if gun_manned {
+        clear_time += match kind {
+            DiveKind::Normal => SECURE_GUN_TIME,
+            DiveKind::Crash => SCRAMBLE_TIME,
+        };
+    }
+    if let Some(gun) = entity.gun.as_mut() {
+        gun.manned = false;
+        gun.target = None;
+    }
+    entity.transition = Some(Transition::Diving {
+        kind,
+        elapsed: 0.0,
+        clear_time,
+        flood_time,
+    });
+    if kind == DiveKind::Crash {
+        if gun_manned {
+            entity.hull = (entity.hull - SCRAMBLE_DAMAGE).max(0.0);
+        }
+        transient::make(world, boat, TransientKind::Ballast);
+    }
+    Ok(())
+}
+
+/// Orders `boat` to blow ballast and surface
+pub fn surface(world: &mut World, boat: EntityId) -> Result<(), DiveError> {
+    let entity = world.entity_mut(boat).ok_or(DiveError::CannotDive)?;
+    if entity.kind != EntityKind::Submarine {
+        return Err(DiveError::CannotDive);
+    }
+    if entity.transition.is_some() {
+        return Err(DiveError::Underway);
+    }
+    if entity.is_surfaced() {
+        return Err(DiveError::AlreadySurfaced);
+    }
+    entity.transition = Some(Transition::Surfacing);
+    transient::make(world, boat, TransientKind::Ballast);
+    Ok(())
+}
+
+/// Carries every dive and surfacing on by `dt` seconds
+pub fn update(world: &mut World, dt: f32) {
+    for entity in world.entities.iter_mut() {
+        let done = match entity.transition.as_mut() {
+            Some(Transition::Diving {
+                elapsed,
+                clear_time,
+                flood_time,
+                ..
+            }) => {
+                *elapsed += dt;
+                let flooded = ((*elapsed - *clear_time) / *flood_time).clamp(0.0, 1.0);
+                entity.depth = entity.depth.max(PERISCOPE_DEPTH * flooded);
+                flooded >= 1.0
+            }
+            Some(Transition::Surfacing) => {
+                entity.depth = (entity.depth - SURFACE_RATE * dt).max(0.0);
+                entity.depth <= 0.0
+            }
+            None => false,
+        };
+        if done {
+            entity.transition = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use crate::gunnery::Gun;
+    use crate::physics::Point;
+    use crate::world::Entity;
+
+    fn boat() -> World {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.gun = Some(Gun::deck_gun());
+        world.spawn(boat);
+        world
+    }
+
+    /// Seconds until the boat reaches periscope depth
+    fn time_to_dive(world: &mut World) -> u32 {
+        let mut seconds = 0;
+        while world.entity(1).unwrap().transition.is_some() {
+            world.step(1.0);
+            seconds += 1;
+        }
+        assert_eq!(world.entity(1).unwrap().depth, PERISCOPE_DEPTH);
+        seconds
+    }
+
+    fn ballast_blown(world: &World) -> bool {
+        world.events.iter().any(|e| {
+            e.event
+                == Event::Transient {
+                    entity: 1,
+                    kind: TransientKind::Ballast,
+                }
+        })
+    }
+
+    #[test]
+    fn crash_dives_are_faster_and_louder() {
+        let mut normal = boat();
+        dive(&mut normal, 1, DiveKind::Normal, 40.0).unwrap();
+        normal.step(1.0);
+        assert_eq!(normal.entity(1).unwrap().depth, 0.0);
+        assert_eq!(time_to_dive(&mut normal), 59);
+        assert!(!ballast_blown(&normal));
+
+        let mut crash = boat();
+        dive(&mut crash, 1, DiveKind::Crash, 40.0).unwrap();
+        assert_eq!(time_to_dive(&mut crash), 28);
+        assert!(ballast_blown(&crash));
+        assert_eq!(
+            dive(&mut crash, 1, DiveKind::Normal, 40.0),
+            Err(DiveError::AlreadySubmerged)
+        );
+    }
+
+    #[test]
+    fn gun_crew_on_deck() {
+        let mut normal = boat();
+        normal.entity_mut(1).unwrap().gun.as_mut().unwrap().manned = true;
+        dive(&mut normal, 1, DiveKind::Normal, 40.0).unwrap();
+        assert_eq!(time_to_dive(&mut normal), 75);
+        assert_eq!(normal.entity(1).unwrap().hull, 1.0);
+
+        let mut crash = boat();
+        crash.entity_mut(1).unwrap().gun.as_mut().unwrap().manned = true;
+        dive(&mut crash, 1, DiveKind::Crash, 40.0).unwrap();
+        assert_eq!(time_to_dive(&mut crash), 33);
+        let boat = crash.entity(1).unwrap();
+        assert!(boat.hull < 1.0);
+        assert!(!boat.gun.as_ref().unwrap().manned);
+    }
+
+    #[test]
+    fn surfacing() {
+        let mut world = boat();
+        world.entity_mut(1).unwrap().depth = 30.0;
+        surface(&mut world, 1).unwrap();
+        assert_eq!(surface(&mut world, 1), Err(DiveError::Underway));
+        for _ in 0..30 {
+            world.step(1.0);
+        }
+        assert!(world.entity(1).unwrap().is_surfaced());
+        assert_eq!(world.entity(1).unwrap().transition, None);
+        assert!(ballast_blown(&world));
+        assert_eq!(surface(&mut world, 1), Err(DiveError::AlreadySurfaced));
+    }
+}
@@ -0,0 +1,670 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::balance::{self, Sweep};
+use crate::config::{Config, ConfigError};
+use crate::debrief::Debrief;
+use crate::forces::Library;
+use crate::generator;
+use crate::geo::LatLon;
+use crate::physics::{user_to_game_angle, Point};
+use crate::random::Rng;
+use crate::savefile::{self, SaveError};
+use crate::scenario::{Scenario, ScenarioIssue};
+use crate::simulation::Simulation;
+use crate::tournament::Tournament;
+use crate::tuning::{Registry, Tuning};
+
+// #############################
+// #      MISSION EDITOR       #
+// #############################
+
+// Builds and changes scenario files (see scenario.rs) through the same
+// config sections the game reads, so anything the editor writes loads back
+// as is. Driven from the command line:
+//
+// subsim validate <file>
+// subsim edit <file> new <scenario name>
+// subsim edit <file> set <key> <value ...>               [scenario] key
+// subsim edit <file> place <entity> <class> xy <x> <y>
+// subsim edit <file> place <entity> <class> latlon <lat> <lon>
+// subsim edit <file> place <entity> <class> from <ref> <range> <bearing>
+// subsim edit <file> entity <entity> <key> <value ...>
+// subsim edit <file> remove <entity>
+// subsim edit <file> force <template> <seed> <course> <position ...>
+//                                         see forces.rs
+// subsim generate <file> <difficulty> <seed>            random skirmish
+// subsim debrief <file> <seconds>         run, then write the debrief
+// subsim save <file> <seconds> <save>     run, then save the game
+// subsim tournament <file>                see tournament.rs
+// subsim sweep <file> <seconds> <runs> <section.key> <values> [summary]
+//                                         see balance.rs
+// subsim constants [file]                 the tuning constants in force
+//
+// Ranges are in meters, bearings in degrees; the reference of "from" is an
+// entity already placed or "origin", and the position of a force, given
+// as for "place", is where its main body is. An entity name of several
+// words is given in double quotes: place "HMS Hood" hood xy 0 0. A saved game (see savefile.rs) is run,
+// debriefed or validated as a scenario file is.
+
+#[derive(Debug)]
+pub enum EditError {
+    Config(ConfigError),
+    Save(SaveError),
+    UnknownEntity(String),
+    UnknownForce(String),
+    Usage(String),
+    /// The scenario does not validate
+    Invalid(Vec<ScenarioIssue>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Config(e) => write!(f, "{}", e),
+            EditError::Save(e) => write!(f, "{}", e),
+            EditError::UnknownEntity(name) => write!(f, "no entity '{}' in the scenario", name),
+            EditError::UnknownForce(name) => write!(f, "no force template '{}'", name),
+            EditError::Usage(message) => write!(f, "{}", message),
+            EditError::Invalid(issues) => {
+                let lines: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+impl From<ConfigError> for EditError {
+    fn from(e: ConfigError) -> Self {
+        EditError::Config(e)
+    }
+}
+
+impl From<SaveError> for EditError {
+    fn from(e: SaveError) -> Self {
+        EditError::Save(e)
+    }
+}
+
+/// Reads a scenario file, or a saved game
+fn open(path: &str) -> Result<Config, EditError> {
+    let data = fs::read(path).map_err(ConfigError::from)?;
+    if savefile::is_save(&data) {
+        return Ok(savefile::load(path)?);
+    }
+    Ok(Config::load(path)?)
+}
+
+/// Runs the scenario of `config` for `seconds`
+fn play(config: &Config, seconds: &str) -> Result<Simulation, EditError> {
+    let seconds: u32 = seconds
+        .parse()
+        .map_err(|_| EditError::Usage(format!("expected seconds, found '{}'", seconds)))?;
+    let mut sim = Scenario::from_config(config)?
+        .build()
+        .map_err(EditError::Invalid)?;
+    for _ in 0..seconds {
+        sim.step(1.0);
+    }
+    Ok(sim)
+}
+
+/// Where to put an entity
+#[derive(Debug, PartialEq, Clone)]
+pub enum Position {
+    /// Meters east and north of the scenario origin
+    Local(Point),
+    LatLon(LatLon),
+    /// Range in meters and bearing (user angle, degrees) from an entity, or
+    /// from the origin
+    From {
+        reference: String,
+        range: f32,
+        bearing: f32,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EditCommand {
+    New {
+        name: String,
+    },
+    Set {
+        key: String,
+        value: String,
+    },
+    Place {
+        entity: String,
+        class: String,
+        position: Position,
+    },
+    Entity {
+        entity: String,
+        key: String,
+        value: String,
+    },
+    Remove {
+        entity: String,
+    },
+    /// Puts a force of a template to sea, see forces.rs
+    Force {
+        template: String,
+        seed: u64,
+        /// User angle, degrees
+        course: f32,
+        position: Position,
+    },
+}
+
+fn word<'a>(words: &[&'a str], index: usize, what: &str) -> Result<&'a str, EditError> {
+    words
+        .get(index)
+        .copied()
+        .ok_or_else(|| EditError::Usage(format!("missing {}", what)))
+}
+
+fn number(words: &[&str], index: usize, what: &str) -> Result<f32, EditError> {
+    let text = word(words, index, what)?;
+    match text.parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(EditError::Usage(format!(
+            "expected {}, found '{}'",
+            what, text
+        ))),
+    }
+}
+
+/// Reads an entity name at `index`, running on to the closing quote when
+/// it opens with one, with the index of the word after it
+fn name(words: &[&str], index: usize, what: &str) -> Result<(String, usize), EditError> {
+    let first = word(words, index, what)?;
+    if !first.starts_with('"') {
+        return Ok((first.to_string(), index + 1));
+    }
+    for end in index..words.len() {
+        let last = words[end];
+        if last.ends_with('"') && (end > index || last.len() > 1) {
+            let joined = words[index..=end].join(" ");
+            return Ok((joined[1..joined.len() - 1].to_string(), end + 1));
+        }
+    }
+    Err(EditError::Usage(format!("unclosed quote in {}", what)))
+}
+
+/// Joins the words from `index` on into one value, so names may have spaces
+fn rest(words: &[&str], index: usize, what: &str) -> Result<String, EditError> {
+    word(words, index, what)?;
+    Ok(words[index..].join(" "))
+}
+
+/// Reads a position given from `index` on as "xy", "latlon" or "from"
+fn position(words: &[&str], index: usize) -> Result<Position, EditError> {
+    match word(words, index, "xy, latlon or from")? {
+        "xy" => Ok(Position::Local(Point {
+            x: number(words, index + 1, "x")?,
+            y: number(words, index + 2, "y")?,
+        })),
+        "latlon" => {
+            let text = format!(
+                "{}, {}",
+                word(words, index + 1, "lat")?,
+                word(words, index + 2, "lon")?
+            );
+            Ok(Position::LatLon(text.parse().map_err(EditError::Usage)?))
+        }
+        "from" => {
+            let (reference, next) = name(words, index + 1, "reference")?;
+            Ok(Position::From {
+                reference,
+                range: number(words, next, "range")?,
+                bearing: number(words, next + 1, "bearing")?,
+            })
+        }
+        other => Err(EditError::Usage(format!("unknown position '{}'", other))),
+    }
+}
+
+impl EditCommand {
+    pub fn parse(words: &[&str]) -> Result<EditCommand, EditError> {
+        match word(words, 0, "edit action")? {
+            "new" => Ok(EditCommand::New {
+                name: rest(words, 1, "scenario name")?,
+            }),
+            "set" => Ok(EditCommand::Set {
+                key: word(words, 1, "key")?.to_string(),
+                value: rest(words, 2, "value")?,
+            }),
+            "place" => {
+                let (entity, next) = name(words, 1, "entity name")?;
+                let class = word(words, next, "class")?.to_string();
+                Ok(EditCommand::Place {
+                    entity,
+                    class,
+                    position: position(words, next + 1)?,
+                })
+            }
+            "entity" => {
+                let (entity, next) = name(words, 1, "entity name")?;
+                Ok(EditCommand::Entity {
+                    entity,
+                    key: word(words, next, "key")?.to_string(),
+                    value: rest(words, next + 1, "value")?,
+                })
+            }
+            "remove" => {
+                let (entity, next) = name(words, 1, "entity name")?;
+                match words.get(next) {
+                    None => Ok(EditCommand::Remove { entity }),
+                    Some(_) => Err(EditError::Usage(format!(
+                        "unexpected '{}'",
+                        words[next..].join(" ")
+                    ))),
+                }
+            }
+            "force" => {
+                let template = word(words, 1, "force template")?.to_string();
+                let seed = word(words, 2, "seed")?;
+                Ok(EditCommand::Force {
+                    template,
+                    seed: seed.parse().map_err(|_| {
+                        EditError::Usage(format!("expected a seed, found '{}'", seed))
+                    })?,
+                    course: number(words, 3, "course")?,
+                    position: position(words, 4)?,
+                })
+            }
+            other => Err(EditError::Usage(format!("unknown edit action '{}'", other))),
+        }
+    }
+}
+
+/// A scenario file being built or changed
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScenarioEditor {
+    pub config: Config,
+}
+
+impl ScenarioEditor {
+    pub fn new(name: &str) -> ScenarioEditor {
+        let mut config = Config::new();
+        config.section_mut("scenario").set("name", name);
+        ScenarioEditor { config }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ScenarioEditor, ConfigError> {
+        Ok(ScenarioEditor {
+            config: Config::load(path)?,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        self.config.save(path)
+    }
+
+    /// Sets a key of the "[scenario]" section
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.config.section_mut("scenario").set(key, value);
+    }
+
+    pub fn origin(&self) -> Result<LatLon, ConfigError> {
+        match self.config.section("scenario") {
+            Some(header) => header.parse_or("origin", LatLon::default()),
+            None => Ok(LatLon::default()),
+        }
+    }
+
+    fn section_name(entity: &str) -> String {
+        format!("entity.{}", entity)
+    }
+
+    pub fn has_entity(&self, entity: &str) -> bool {
+        self.config
+            .section(&ScenarioEditor::section_name(entity))
+            .is_some()
+    }
+
+    pub fn position_of(&self, entity: &str) -> Result<Point, EditError> {
+        let section = self
+            .config
+            .section(&ScenarioEditor::section_name(entity))
+            .ok_or_else(|| EditError::UnknownEntity(entity.to_string()))?;
+        Ok(Point {
+            x: section.parse("x")?,
+            y: section.parse("y")?,
+        })
+    }
+
+    /// Turns `position` into meters east and north of the origin
+    pub fn resolve(&self, position: &Position) -> Result<Point, EditError> {
+        match position {
+            Position::Local(point) => Ok(point.clone()),
+            Position::LatLon(latlon) => Ok(latlon.to_local(&self.origin()?)),
+            Position::From {
+                reference,
+                range,
+                bearing,
+            } => {
+                let from = match reference.as_str() {
+                    "origin" => Point { x: 0.0, y: 0.0 },
+                    entity => self.position_of(entity)?,
+                };
+                let angle = user_to_game_angle(*bearing);
+                Ok(Point {
+                    x: from.x + range * angle.cos(),
+                    y: from.y + range * angle.sin(),
+                })
+            }
+        }
+    }
+
+    /// Adds an entity, or moves it and changes its class if already there
+    pub fn place(
+        &mut self,
+        entity: &str,
+        class: &str,
+        position: &Position,
+    ) -> Result<(), EditError> {
+        let point = self.resolve(position)?;
+        let section = self
+            .config
+            .section_mut(&ScenarioEditor::section_name(entity));
+        section.set("class", class);
+        section.set("x", point.x.round());
+        section.set("y", point.y.round());
+        Ok(())
+    }
+
+    /// Sets a key of an entity already placed
+    pub fn set_entity(&mut self, entity: &str, key: &str, value: &str) -> Result<(), EditError> {
+        let name = ScenarioEditor::section_name(entity);
+        if self.config.section(&name).is_none() {
+            return Err(EditError::UnknownEntity(entity.to_string()));
+        }
+        self.config.section_mut(&name).set(key, value);
+        Ok(())
+    }
+
+    /// Puts a force of `template` to sea, the templates of the scenario
+    /// file itself winning over those shipped with the game
+    pub fn force(
+        &mut self,
+        template: &str,
+        seed: u64,
+        course: f32,
+        position: &Position,
+    ) -> Result<Vec<String>, EditError> {
+        let mut library = Library::builtin();
+        library.merge(Library::read(&self.config)?);
+        let force = library
+            .get(template)
+            .ok_or_else(|| EditError::UnknownForce(template.to_string()))?;
+        let at = self.resolve(position)?;
+        let composition = force.compose(&mut Rng::new(seed));
+        if let (Some(era), None) = (
+            force.era,
+            self.config.section("scenario").and_then(|s| s.get("era")),
+        ) {
+            self.set("era", &era.to_string());
+        }
+        library.instantiate(self, force, &composition, at, course)
+    }
+
+    pub fn remove(&mut self, entity: &str) -> Result<(), EditError> {
+        self.config
+            .remove_section(&ScenarioEditor::section_name(entity))
+            .map(|_| ())
+            .ok_or_else(|| EditError::UnknownEntity(entity.to_string()))
+    }
+
+    pub fn execute(&mut self, command: &EditCommand) -> Result<(), EditError> {
+        match command {
+            EditCommand::New { name } => *self = ScenarioEditor::new(name),
+            EditCommand::Set { key, value } => self.set(key, value),
+            EditCommand::Place {
+                entity,
+                class,
+                position,
+            } => self.place(entity, class, position)?,
+            EditCommand::Entity { entity, key, value } => self.set_entity(entity, key, value)?,
+            EditCommand::Remove { entity } => self.remove(entity)?,
+            EditCommand::Force {
+                template,
+                seed,
+                course,
+                position,
+            } => {
+                self.force(template, *seed, *course, position)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Everything that keeps the scenario from being played
+    pub fn validate(&self) -> Result<Vec<ScenarioIssue>, ConfigError> {
+        Ok(Scenario::from_config(&self.config)?.validate())
+    }
+}
+
+/// Runs the "subsim validate", "subsim edit", "subsim generate", "subsim
+/// run", "subsim debrief", "subsim save", "subsim tournament", "subsim
+/// sweep" and "subsim constants" subcommands, returning what to print
+pub fn run(args: &[&str]) -> Result<String, EditError> {
+    match args {
+        ["validate", path] => {
+            let issues = Scenario::from_config(&open(path)?)?.validate();
+            if issues.is_empty() {
+                Ok(format!("{}: ok", path))
+            } else {
+                Err(EditError::Invalid(issues))
+            }
+        }
+        ["edit", path, words @ ..] => {
+            let command = EditCommand::parse(words)?;
+            let mut editor = match command {
+                EditCommand::New { .. } => ScenarioEditor::new(""),
+                _ => ScenarioEditor::load(path)?,
+            };
+            editor.execute(&command)?;
+            editor.save(path)?;
+            Ok(String::new())
+        }
+        ["generate", path, difficulty, seed] => {
+            let difficulty = difficulty.parse().map_err(EditError::Usage)?;
+            let seed = seed
+                .parse()
+                .map_err(|_| EditError::Usage(format!("expected a seed, found '{}'", seed)))?;
+            generator::generate(difficulty, seed).save(path)?;
+            Ok(String::new())
+        }
+        [action @ ("run" | "debrief"), path, seconds] => {
+            let sim = play(&open(path)?, seconds)?;
+            if *action == "debrief" {
+                return Ok(Debrief::compile(&sim).to_string());
+            }
+            Ok(format!(
+                "{}: ran {} s, {} events",
+                path,
+                seconds,
+                sim.world.events.len()
+            ))
+        }
+        ["save", path, seconds, save] => {
+            let config = open(path)?;
+            let sim = play(&config, seconds)?;
+            savefile::save(save, &savefile::capture(&config, &sim))?;
+            Ok(String::new())
+        }
+        ["sweep", path, seconds, runs, parameter, values, rest @ ..] => {
+            let number = |word: &str| {
+                word.parse::<u32>()
+                    .map_err(|_| EditError::Usage(format!("expected a number, found '{}'", word)))
+            };
+            let parameter = parameter.parse().map_err(EditError::Usage)?;
+            let values = balance::values(values).map_err(EditError::Usage)?;
+            let sweep = Sweep::run(
+                &open(path)?,
+                number(seconds)?,
+                number(runs)? as u64,
+                parameter,
+                &values,
+            )?;
+            match rest {
+                [] => Ok(sweep.csv()),
+                ["summary"] => Ok(sweep.summary()),
+                _ => Err(EditError::Usage(format!("unexpected '{}'", rest.join(" ")))),
+            }
+        }
+        ["constants"] => Ok(Registry(&Tuning::default()).to_string()),
+        ["constants", path] => {
+            let tuning = Tuning::read(&open(path)?)?;
+            Ok(Registry(&tuning).to_string())
+        }
+        ["tournament", path] => Ok(Tournament::load(path)?.run()?.to_string()),
+        _ => Err(EditError::Usage(
+            "usage: subsim validate <file> | subsim edit <file> <action> ... \
+             | subsim generate <file> <difficulty> <seed> | subsim run <file> <seconds> \
+             | subsim debrief <file> <seconds> | subsim save <file> <seconds> <save> \
+             | subsim tournament <file> \
+             | subsim sweep <file> <seconds> <runs> <section.key> <values> [summary] \
+             | subsim constants [file]"
+                .to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> EditCommand {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        EditCommand::parse(&words).unwrap()
+    }
+
+    fn convoy() -> ScenarioEditor {
+        let mut editor = ScenarioEditor::new("Edited");
+        for line in [
+            "set era 1944",
+            "set player U-99",
+            "set origin 56.0, -18.5",
+            "place U-99 type_viic xy 0 -5000",
+            "place Liberty liberty from U-99 5000 90",
+        ] {
+            editor.execute(&parse(line)).unwrap();
+        }
+        editor
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            parse("set name Convoy HX 72"),
+            EditCommand::Set {
+                key: "name".to_string(),
+                value: "Convoy HX 72".to_string()
+            }
+        );
+        assert_eq!(
+            parse("place U-99 type_viic latlon 56.1 -18.5"),
+            EditCommand::Place {
+                entity: "U-99".to_string(),
+                class: "type_viic".to_string(),
+                position: Position::LatLon(LatLon {
+                    lat: 56.1,
+                    lon: -18.5
+                }),
+            }
+        );
+        assert!(EditCommand::parse(&["place", "U-99", "type_viic", "here"]).is_err());
+        assert_eq!(
+            parse("place \"HMS Hood\" hood from \"Prince of Wales\" 2000 90"),
+            EditCommand::Place {
+                entity: "HMS Hood".to_string(),
+                class: "hood".to_string(),
+                position: Position::From {
+                    reference: "Prince of Wales".to_string(),
+                    range: 2000.0,
+                    bearing: 90.0,
+                },
+            }
+        );
+        // a shell hands a quoted name over as one word
+        assert_eq!(
+            EditCommand::parse(&["remove", "HMS Hood"]).unwrap(),
+            EditCommand::Remove {
+                entity: "HMS Hood".to_string()
+            }
+        );
+        for bad in [
+            "place \"HMS Hood hood xy 0 0",
+            "place U-99 type_viic latlon NaN -18.5",
+            "place U-99 type_viic latlon 56.1 inf",
+            "place U-99 type_viic xy NaN 0",
+            "remove HMS Hood",
+        ] {
+            let words: Vec<&str> = bad.split_whitespace().collect();
+            assert!(EditCommand::parse(&words).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn place_relative() {
+        let editor = convoy();
+        assert_eq!(
+            editor.position_of("Liberty").unwrap(),
+            Point {
+                x: 5000.0,
+                y: -5000.0
+            }
+        );
+        let mut editor = editor;
+        let north = Position::LatLon(LatLon {
+            lat: 56.1,
+            lon: -18.5,
+        });
+        editor.place("Escort", "flower", &north).unwrap();
+        assert!((editor.position_of("Escort").unwrap().y - 11_119.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn validate_and_remove() {
+        let mut editor = convoy();
+        let issues = editor.validate().unwrap();
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(issues[0], ScenarioIssue::UnknownClass { .. }));
+        editor.execute(&parse("remove U-99")).unwrap();
+        assert!(editor
+            .validate()
+            .unwrap()
+            .contains(&ScenarioIssue::UnknownPlayer("U-99".to_string())));
+        assert!(editor.remove("U-99").is_err());
+        assert!(editor.set_entity("U-99", "depth", "20").is_err());
+    }
+
+    #[test]
+    fn force_from_a_template() {
+        let mut editor = ScenarioEditor::new("Edited");
+        editor.execute(&parse("set player U-99")).unwrap();
+        editor
+            .execute(&parse("place U-99 type_viic xy 0 -20000"))
+            .unwrap();
+        editor
+            .execute(&parse("force atlantic_convoy_1943 7 90 from U-99 20000 0"))
+            .unwrap();
+        assert_eq!(
+            editor.config.section("scenario").unwrap().get("era"),
+            Some("1943")
+        );
+        // the main body is centred where the force was put
+        let merchant = editor.position_of("Merchant 1").unwrap();
+        assert!(merchant.x.abs() < 3_000.0 && merchant.y.abs() < 3_000.0);
+        assert!(editor.config.section("class.liberty").is_some());
+        assert!(matches!(
+            editor.execute(&parse("force hx_72 7 90 xy 0 0")),
+            Err(EditError::UnknownForce(_))
+        ));
+    }
+}
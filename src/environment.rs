@@ -0,0 +1,403 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::propagation::LossCache;
+use crate::seafloor::Seafloor;
+use crate::tuning::Tuning;
+
+/// Speed of sound against depth, as (depth in meters, speed in m/s) pairs
+/// sorted by depth
+#[derive(Debug, PartialEq, Clone)]
+pub struct SoundSpeedProfile {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Default for SoundSpeedProfile {
+    /// A typical mid-latitude profile with a 60 m surface layer
+    fn default() -> Self {
+        SoundSpeedProfile {
+            points: vec![
+                (0.0, 1500.0),
+                (60.0, 1502.0),
+                (100.0, 1495.0),
+                (500.0, 1485.0),
+                (1000.0, 1482.0),
+                (2000.0, 1490.0),
+                (4000.0, 1518.0),
+            ],
+        }
+    }
+}
+
+impl SoundSpeedProfile {
+    /// Sound speed at `depth`, interpolated between the profile points
+    pub fn speed_at(&self, depth: f32) -> f32 {
+        let points = &self.points;
+        if points.is_empty() {
+            return 1500.0;
+        }
+        if depth <= points[0].0 {
+            return points[0].1;
+        }
+        for pair in points.windows(2) {
+            let (d0, s0) = pair[0];
+            let (d1, s1) = pair[1];
+            if depth <= d1 {
+                return s0 + (s1 - s0) * (depth - d0) / (d1 - d0);
+            }
+        }
+        points[points.len() - 1].1
+    }
+
+    /// Bottom of the surface layer: the depth where sound speed stops
+    /// increasing. None when it decreases right from the surface
+    pub fn layer_depth(&self) -> Option<f32> {
+        let mut layer = None;
+        for pair in self.points.windows(2) {
+            if pair[1].1 > pair[0].1 {
+                layer = Some(pair[1].0);
+            } else {
+                break;
+            }
+        }
+        layer
+    }
+
+    /// Sound speed gradient (m/s per meter) just below `depth`
+    pub fn gradient_below(&self, depth: f32) -> f32 {
+        (self.speed_at(depth + 50.0) - self.speed_at(depth)) / 50.0
+    }
+
+    /// The profile a fraction `t` of the way from this one to `other`, at
+    /// the depths of both
+    pub fn blend(&self, other: &SoundSpeedProfile, t: f32) -> SoundSpeedProfile {
+        let mut depths: Vec<f32> = self
+            .points
+            .iter()
+            .chain(other.points.iter())
+            .map(|(depth, _)| *depth)
+            .collect();
+        depths.sort_by(|a, b| a.total_cmp(b));
+        depths.dedup();
+        let points = depths
+            .into_iter()
+            .map(|depth| {
+                let (from, to) = (self.speed_at(depth), other.speed_at(depth));
+                (depth, from + (to - from) * t)
+            })
+            .collect();
+        SoundSpeedProfile { points }
+    }
+}
+
+/// Seconds in a day
+pub const SECONDS_PER_DAY: f32 = 86_400.0;
+
+/// Calendar date, proleptic Gregorian
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Date {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u32,
+    /// 1 to 31
+    pub day: u32,
+}
+
+impl Default for Date {
+    fn default() -> Self {
+        Date {
+            year: 1942,
+            month: 1,
+            day: 1,
+        }
+    }
+}
+
+fn is_leap(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    pub fn add_days(&self, days: u32) -> Date {
+        let mut date = *self;
+        for _ in 0..days {
+            date.day += 1;
+            if date.day > days_in_month(date.year, date.month) {
+                date.day = 1;
+                date.month += 1;
+                if date.month > 12 {
+                    date.month = 1;
+                    date.year += 1;
+                }
+            }
+        }
+        date
+    }
+}
+
+/// Writes the date as "1941-05-20"
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Reads "1941-05-20"
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Date, String> {
+        let error = || format!("expected a date like 1941-05-20, found '{}'", s);
+        let fields: Vec<&str> = s.trim().split('-').collect();
+        let (year, month, day) = match fields.as_slice() {
+            [year, month, day] => (
+                year.parse().map_err(|_| error())?,
+                month.parse().map_err(|_| error())?,
+                day.parse().map_err(|_| error())?,
+            ),
+            _ => return Err(error()),
+        };
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(error());
+        }
+        Ok(Date { year, month, day })
+    }
+}
+
+/// Local times the sun rises and sets at, seconds after midnight, and how
+/// long the twilight lasts either side
+const SUNRISE: f32 = 6.0 * 3600.0;
+const SUNSET: f32 = 18.0 * 3600.0;
+const TWILIGHT: f32 = 3600.0;
+/// Fraction of the daylight visibility left on a dark night
+pub const NIGHT_LIGHT: f32 = 0.1;
+
+/// Seconds after midnight, read from "HH:MM"
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TimeOfDay(pub f32);
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TimeOfDay, String> {
+        let error = || format!("expected a time like 06:30, found '{}'", s);
+        let (hours, minutes) = s.trim().split_once(':').ok_or_else(error)?;
+        let hours: u32 = hours.parse().map_err(|_| error())?;
+        let minutes: u32 = minutes.parse().map_err(|_| error())?;
+        if hours > 23 || minutes > 59 {
+            return Err(error());
+        }
+        Ok(TimeOfDay((hours * 3600 + minutes * 60) as f32))
+    }
+}
+
+/// Seconds between two high waters of the semidiurnal tide
+pub const TIDAL_PERIOD: f32 = 44_712.0;
+
+/// Height of the tide above chart datum (low water), a plain sine between
+/// 0 and `range`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tide {
+    /// Meters between low and high water, 0 where there is no tide
+    pub range: f32,
+    /// Scenario time in seconds of a high water
+    pub high_water: f32,
+}
+
+impl Default for Tide {
+    fn default() -> Self {
+        Tide {
+            range: 0.0,
+            high_water: 0.0,
+        }
+    }
+}
+
+impl Tide {
+    /// Phase of the tide in radians at `time`, 0 at high water
+    fn phase(&self, time: f32) -> f32 {
+        2.0 * std::f32::consts::PI * (time - self.high_water) / TIDAL_PERIOD
+    }
+
+    /// Meters above chart datum at `time`
+    pub fn height(&self, time: f32) -> f32 {
+        self.range / 2.0 * (1.0 + self.phase(time).cos())
+    }
+
+    /// First time from `time` on the tide is at least `height` meters, None
+    /// when it never gets that high
+    pub fn next_above(&self, time: f32, height: f32) -> Option<f32> {
+        if height <= 0.0 {
+            return Some(time);
+        }
+        if height > self.range {
+            return None;
+        }
+        let tau = 2.0 * std::f32::consts::PI;
+        // above the height while the phase is within `half` of high water
+        let half = (2.0 * height / self.range - 1.0).acos();
+        let phase = self.phase(time).rem_euclid(tau);
+        if phase <= half || phase >= tau - half {
+            return Some(time);
+        }
+        Some(time + (tau - half - phase) / tau * TIDAL_PERIOD)
+    }
+}
+
+/// Weather and sea conditions shared by the whole scenario
+#[derive(Debug, PartialEq, Clone)]
+pub struct Environment {
+    /// Douglas sea state, 0 (calm) to 9 (phenomenal)
+    pub sea_state: u8,
+    /// Game angle the waves come from, see seakeeping.rs
+    pub wave_from: f32,
+    /// Meteorological visibility in meters
+    pub visibility: f32,
+    /// Fraction of the sea surface covered by ice, 0 to 1
+    pub ice_cover: f32,
+    pub sound_speed: SoundSpeedProfile,
+    pub tide: Tide,
+    /// Local date the scenario starts on
+    pub date: Date,
+    /// Seconds after local midnight the scenario starts at
+    pub start_time: f32,
+    /// What the bottom is made of and how deep, see seafloor.rs
+    pub seafloor: Seafloor,
+    /// Losses of sound worked out lately, see propagation.rs
+    pub losses: LossCache,
+    /// The constants changed by the scenario, see tuning.rs
+    pub tuning: Tuning,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            sea_state: 2,
+            wave_from: PI,
+            visibility: 20_000.0,
+            ice_cover: 0.0,
+            sound_speed: SoundSpeedProfile::default(),
+            tide: Tide::default(),
+            date: Date::default(),
+            start_time: 0.0,
+            seafloor: Seafloor::default(),
+            losses: LossCache::default(),
+            tuning: Tuning::default(),
+        }
+    }
+}
+
+impl Environment {
+    /// Local date and seconds after midnight at `time` into the scenario
+    pub fn local_time(&self, time: f32) -> (Date, f32) {
+        let seconds = self.start_time + time.max(0.0);
+        let days = (seconds / SECONDS_PER_DAY).floor();
+        (
+            self.date.add_days(days as u32),
+            seconds - days * SECONDS_PER_DAY,
+        )
+    }
+
+    /// How light it is at `time` into the scenario, 1 by day down to
+    /// NIGHT_LIGHT at night, through an hour of twilight at dawn and dusk
+    pub fn daylight(&self, time: f32) -> f32 {
+        let (_, clock) = self.local_time(time);
+        let sun = ((clock - SUNRISE + TWILIGHT / 2.0) / TWILIGHT)
+            .min((SUNSET + TWILIGHT / 2.0 - clock) / TWILIGHT)
+            .clamp(0.0, 1.0);
+        NIGHT_LIGHT + (1.0 - NIGHT_LIGHT) * sun
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_at() {
+        let profile = SoundSpeedProfile::default();
+        assert_eq!(profile.speed_at(0.0), 1500.0);
+        assert_eq!(profile.speed_at(30.0), 1501.0);
+        assert_eq!(profile.speed_at(10_000.0), 1518.0);
+    }
+
+    #[test]
+    fn layer_depth() {
+        let profile = SoundSpeedProfile::default();
+        assert_eq!(profile.layer_depth(), Some(60.0));
+        assert!(profile.gradient_below(60.0) < 0.0);
+        let no_layer = SoundSpeedProfile {
+            points: vec![(0.0, 1510.0), (200.0, 1490.0)],
+        };
+        assert_eq!(no_layer.layer_depth(), None);
+    }
+
+    #[test]
+    fn blend() {
+        let from = SoundSpeedProfile::default();
+        let to = SoundSpeedProfile {
+            points: vec![(0.0, 1510.0), (200.0, 1490.0)],
+        };
+        let half = from.blend(&to, 0.5);
+        assert_eq!(half.speed_at(0.0), 1505.0);
+        assert!(half.points.windows(2).all(|w| w[0].0 < w[1].0));
+        // a depth gone wrong sorts, rather than panics
+        let broken = SoundSpeedProfile {
+            points: vec![(f32::NAN, 1500.0), (100.0, 1490.0)],
+        };
+        assert!(from.blend(&broken, 0.5).points.len() > from.points.len());
+    }
+
+    #[test]
+    fn daylight() {
+        let environment = Environment {
+            start_time: 12.0 * 3600.0,
+            ..Environment::default()
+        };
+        assert_eq!(environment.daylight(0.0), 1.0);
+        assert_eq!(environment.daylight(12.0 * 3600.0), NIGHT_LIGHT);
+        let dusk = environment.daylight(6.0 * 3600.0);
+        assert!(dusk > NIGHT_LIGHT && dusk < 1.0, "{}", dusk);
+    }
+
+    #[test]
+    fn tide() {
+        let tide = Tide {
+            range: 4.0,
+            high_water: 0.0,
+        };
+        assert_eq!(tide.height(0.0), 4.0);
+        assert!(tide.height(TIDAL_PERIOD / 2.0).abs() < 0.001);
+        assert_eq!(tide.next_above(0.0, 3.0), Some(0.0));
+        // just after low water, 3 m comes back a sixth of a period before
+        // the next high water
+        let next = tide.next_above(TIDAL_PERIOD / 2.0, 3.0).unwrap();
+        assert!((next - TIDAL_PERIOD * 5.0 / 6.0).abs() < 1.0);
+        assert!((tide.height(next) - 3.0).abs() < 0.01);
+        assert_eq!(tide.next_above(0.0, 5.0), None);
+    }
+
+    #[test]
+    fn calendar() {
+        let environment = Environment {
+            date: "1944-02-28".parse().unwrap(),
+            start_time: "22:30".parse::<TimeOfDay>().unwrap().0,
+            ..Environment::default()
+        };
+        let (date, time) = environment.local_time(2.0 * SECONDS_PER_DAY);
+        assert_eq!(date.to_string(), "1944-03-01");
+        assert_eq!(time, 81_000.0);
+        assert!("1943-02-29".parse::<Date>().is_err());
+        assert!("25:00".parse::<TimeOfDay>().is_err());
+    }
+}
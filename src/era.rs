@@ -0,0 +1,133 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::seeker::SeekerGeneration;
+
+// #############################
+// #      TECHNOLOGY ERAS      #
+// #############################
+
+// An era is the year the scenario is set in. Every optional subsystem has
+// a year of introduction (and some a year of retirement), so one engine
+// plays 1942 convoys and 1985 SSN duels without mixing the two.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Subsystem {
+    DeckGun,
+    TowedArray,
+    Radar,
+    InterceptReceiver,
+    HomingTorpedo(SeekerGeneration),
+    WakeHomingTorpedo,
+    Snorkel,
+    TowedDecoy,
+    MobileDecoy,
+    VariableDepthSonar,
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subsystem::DeckGun => write!(f, "submarine deck gun"),
+            Subsystem::TowedArray => write!(f, "towed array"),
+            Subsystem::Radar => write!(f, "radar"),
+            Subsystem::InterceptReceiver => write!(f, "intercept receiver"),
+            Subsystem::HomingTorpedo(generation) => write!(f, "{:?} homing torpedo", generation),
+            Subsystem::WakeHomingTorpedo => write!(f, "wake-homing torpedo"),
+            Subsystem::Snorkel => write!(f, "snorkel"),
+            Subsystem::TowedDecoy => write!(f, "towed decoy"),
+            Subsystem::MobileDecoy => write!(f, "mobile decoy"),
+            Subsystem::VariableDepthSonar => write!(f, "variable depth sonar"),
+        }
+    }
+}
+
+impl Subsystem {
+    /// First and (if any) last year the subsystem is in service
+    pub fn service_years(&self) -> (u16, Option<u16>) {
+        match self {
+            Subsystem::DeckGun => (1914, Some(1955)),
+            Subsystem::TowedArray => (1970, None),
+            Subsystem::Radar => (1941, None),
+            Subsystem::InterceptReceiver => (1955, None),
+            Subsystem::HomingTorpedo(SeekerGeneration::EarlyPassive) => (1943, None),
+            Subsystem::HomingTorpedo(SeekerGeneration::ActivePassive) => (1960, None),
+            Subsystem::HomingTorpedo(SeekerGeneration::Modern) => (1980, None),
+            Subsystem::WakeHomingTorpedo => (1970, None),
+            Subsystem::Snorkel => (1944, None),
+            Subsystem::TowedDecoy => (1943, None),
+            Subsystem::MobileDecoy => (1960, None),
+            Subsystem::VariableDepthSonar => (1960, None),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Era {
+    pub year: u16,
+}
+
+impl Era {
+    pub const WORLD_WAR_TWO: Era = Era { year: 1942 };
+    pub const COLD_WAR: Era = Era { year: 1975 };
+    pub const MODERN: Era = Era { year: 2000 };
+
+    pub fn allows(&self, subsystem: Subsystem) -> bool {
+        let (first, last) = subsystem.service_years();
+        self.year >= first && last.is_none_or(|last| self.year <= last)
+    }
+}
+
+impl Default for Era {
+    fn default() -> Self {
+        Era::MODERN
+    }
+}
+
+impl fmt::Display for Era {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.year)
+    }
+}
+
+impl FromStr for Era {
+    type Err = String;
+
+    /// Accepts a year or one of "wwii", "cold_war" and "modern"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wwii" => Ok(Era::WORLD_WAR_TWO),
+            "cold_war" => Ok(Era::COLD_WAR),
+            "modern" => Ok(Era::MODERN),
+            other => other
+                .parse()
+                .map(|year| Era { year })
+                .map_err(|_| format!("unknown era '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("wwii".parse::<Era>(), Ok(Era::WORLD_WAR_TWO));
+        assert_eq!("1944".parse::<Era>(), Ok(Era { year: 1944 }));
+        assert!("steampunk".parse::<Era>().is_err());
+    }
+
+    #[test]
+    fn allows() {
+        let early_war = Era { year: 1942 };
+        let late_war = Era { year: 1944 };
+        let homing = Subsystem::HomingTorpedo(SeekerGeneration::EarlyPassive);
+        assert!(!early_war.allows(homing));
+        assert!(late_war.allows(homing));
+        assert!(!late_war.allows(Subsystem::TowedArray));
+        assert!(late_war.allows(Subsystem::DeckGun));
+        assert!(!Era::MODERN.allows(Subsystem::DeckGun));
+        assert!(Era::MODERN.allows(Subsystem::TowedArray));
+    }
+}
@@ -0,0 +1,414 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::asw::{self, MAX_SETTING, MIN_SETTING};
+use crate::command::{AswCommand, HeloCommand};
+use crate::faction::Stance;
+use crate::helicopter::{self, Task};
+use crate::intercept::EmissionKind;
+use crate::physics::{turn_towards, Point, KNOT};
+use crate::pulse::Pulse;
+use crate::sensors::{echo_excess, passive_excess};
+use crate::tuning::Tunable;
+use crate::units::Meters;
+use crate::vds;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #         ESCORT AI         #
+// #############################
+
+// The escorts the computer commands hunt the boats they hold, whether
+// heard on the hull sonar or sent to a datum by the lookouts or the
+// direction finders (see lookouts.rs and hfdf.rs). A hunting escort pings,
+// with the pulse its crew thinks best for the water (see pulse.rs), closes
+// a fresh datum at a speed its sonar still hears at and runs in the last
+// of the way to drop a pattern over it, and circles the datum while the
+// boat is lost, giving up after HUNT_TIME without it.
+//
+// Whether the boat went under the layer is weighed all along. Every pulse
+// that should have brought back an echo from a boat above the layer at
+// the datum, and brought back none, makes it likelier that it is under;
+// holding it on the hull sonar again puts it back above. A boat thought to
+// be under the layer has the charges set to go off below it, and the
+// helicopter, if the escort carries one, dips its sonar under the layer
+// to find it; a boat thought to be above has them set shallow. An escort
+// towing a variable depth sonar streams it for the hunt, lowered under
+// the layer or kept above it the same way (see vds.rs), and heaves it in
+// when the hunt is given up; with the body under the layer, a pulse that
+// should have found a boat there and did not puts it back above.
+
+/// Seconds without holding the boat before the hunt is given up
+const HUNT_TIME: f32 = 1_800.0;
+/// Seconds a datum is run in on, rather than searched around
+const FRESH: f32 = 60.0;
+/// Meters per second the escort runs in at, deaf, and hunts at, slow
+/// enough for its sonar
+const ATTACK_SPEED: f32 = 18.0 * KNOT;
+const SEARCH_SPEED: f32 = 8.0 * KNOT;
+/// Meters from a fresh datum within which the escort runs in
+const RUN_IN: f32 = 1_000.0;
+/// Meters from the datum at which the escort searches around it
+const SEARCH_RADIUS: f32 = 1_500.0;
+/// Meters from the datum within which a pattern is dropped
+const DROP_RANGE: f32 = 60.0;
+/// How likely a boat lost is under the layer, before any pulse
+const PRIOR_BELOW: f32 = 0.5;
+/// How likely a boat held on the hull sonar is under the layer
+const HELD_BELOW: f32 = 0.1;
+/// Chance a pulse misses a boat it should have brought back an echo from
+const MISS_CHANCE: f32 = 0.3;
+/// How likely the boat must be under the layer for the helicopter to dip
+/// there
+const DIP_BELOW: f32 = 0.6;
+/// Meters under the layer the boat is looked for, and over it
+const LAYER_MARGIN: f32 = 30.0;
+/// Depth the boat is looked for without a layer
+const DEFAULT_DEPTH: f32 = 60.0;
+
+/// What an escort commanded by the computer makes of the boat it hunts
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct EscortAi {
+    /// Where the boat was last held, and when
+    pub datum: Option<(Point, f32)>,
+    /// How likely the boat is under the layer, 0 to 1
+    pub below: f32,
+    /// Whether the escort pings for the hunt
+    pinging: bool,
+}
+
+impl EscortAi {
+    /// Sends the escort to hunt at `datum` at `time`, unless hunting
+    /// already
+    pub fn alert(&mut self, datum: Point, time: f32) {
+        if self.datum.is_none() {
+            self.datum = Some((datum, time));
+            self.below = PRIOR_BELOW;
+        }
+    }
+
+    /// Holds the boat at `position` at `time`, on the hull sonar
+    fn hold(&mut self, position: Point, time: f32) {
+        self.datum = Some((position, time));
+        self.below = HELD_BELOW;
+    }
+
+    /// Weighs a pulse that brought back no echo where a boat above the
+    /// layer would have
+    fn missed(&mut self) {
+        self.below /= self.below + (1.0 - self.below) * MISS_CHANCE;
+    }
+
+    /// Weighs a pulse that brought back no echo where a boat under the
+    /// layer would have
+    fn cleared(&mut self) {
+        self.below = self.below * MISS_CHANCE / (self.below * MISS_CHANCE + 1.0 - self.below);
+    }
+
+    /// Depth the boat is taken to be at, under a layer at `layer`
+    pub fn search_depth(&self, layer: Option<f32>) -> f32 {
+        let depth = match layer {
+            None => DEFAULT_DEPTH,
+            Some(layer) if self.below >= 0.5 => layer + LAYER_MARGIN,
+            Some(layer) => layer - LAYER_MARGIN,
+        };
+        depth.clamp(MIN_SETTING, MAX_SETTING)
+    }
+}
+
+/// Whether the pulse of `escort` would bring back an echo from a boat at
+/// `at` and `depth`, on its hull sonar or its towed body
+fn would_echo(world: &World, escort: &Entity, at: &Point, depth: f32, pulse: &Pulse) -> bool {
+    let mut boat = Entity::new("boat", EntityKind::Submarine, at.clone());
+    boat.depth = depth;
+    let level = EmissionKind::ActiveSonar.source_level();
+    let body = vds::body(escort);
+    std::iter::once(escort)
+        .chain(body.as_ref())
+        .any(|listener| {
+            echo_excess(&world.environment, listener, &boat, level, pulse).is_some_and(|e| e > 0.0)
+        })
+}
+
+/// The closest hostile boat `escort` holds this tick, on the echoes of
+/// `pulse` if it pinged or on its hull sonar or towed body
+fn held(world: &World, escort: &Entity, pulse: Option<&Pulse>) -> Option<Point> {
+    let level = EmissionKind::ActiveSonar.source_level();
+    let body = vds::body(escort);
+    let listeners: Vec<&Entity> = std::iter::once(escort).chain(body.as_ref()).collect();
+    world
+        .entities
+        .iter()
+        .filter(|e| e.kind == EntityKind::Submarine && !e.is_destroyed())
+        .filter(|e| world.diplomacy.stance(escort, e) == Stance::Hostile)
+        .filter(|e| {
+            listeners.iter().any(|listener| {
+                let echo =
+                    pulse.and_then(|p| echo_excess(&world.environment, listener, e, level, p));
+                let heard = passive_excess(&world.environment, listener, e);
+                echo.is_some_and(|x| x > 0.0) || heard.is_some_and(|x| x > 0.0)
+            })
+        })
+        .map(|e| e.position.clone())
+        .min_by(|a, b| {
+            let range = |p: &Point| escort.position.distance_to(p);
+            range(a).total_cmp(&range(b))
+        })
+}
+
+/// Follows the boat `id` hunts, weighing whether it is under the layer
+fn perceive(world: &mut World, id: EntityId, ai: &mut EscortAi) {
+    let time = world.time;
+    let layer = world.environment.sound_speed.layer_depth();
+    let escort = world.entity(id).unwrap();
+    let pulse = world
+        .emissions
+        .iter()
+        .find(|e| e.source == id && e.kind == EmissionKind::ActiveSonar)
+        .map(|e| e.pulse);
+    match held(world, escort, pulse.as_ref()) {
+        Some(position) => ai.hold(position, time),
+        None => {
+            if let (Some(pulse), Some(layer), Some((datum, _))) = (pulse, layer, &ai.datum) {
+                let above = would_echo(world, escort, datum, layer - LAYER_MARGIN, &pulse);
+                let under = would_echo(world, escort, datum, layer + LAYER_MARGIN, &pulse);
+                if above && !under {
+                    ai.missed();
+                } else if under && !above {
+                    ai.cleared();
+                }
+            }
+        }
+    }
+    // a boat the helicopter placed, dipped under the layer
+    let placed = world
+        .entity_mut(id)
+        .and_then(|e| e.asw.as_mut())
+        .and_then(|s| s.helicopter.as_mut())
+        .and_then(|h| h.datum.take());
+    if let Some((position, depth)) = placed {
+        ai.datum = Some((position, time));
+        ai.below = if layer.is_some_and(|l| depth > l) {
+            1.0 - HELD_BELOW
+        } else {
+            HELD_BELOW
+        };
+    }
+}
+
+/// Pings, steers and attacks for the hunt of `id` over `dt` seconds
+fn act(world: &mut World, id: EntityId, ai: &mut EscortAi, dt: f32) {
+    let time = world.time;
+    let layer = world.environment.sound_speed.layer_depth();
+    let (datum, since) = match &ai.datum {
+        Some((datum, since)) if time - since <= HUNT_TIME => (datum.clone(), *since),
+        _ => {
+            ai.datum = None;
+            if ai.pinging {
+                ai.pinging = false;
+                if let Some(station) = world.entity_mut(id).and_then(|e| e.asw.as_mut()) {
+                    station.pinging = false;
+                }
+                let _ = asw::execute(world, id, &AswCommand::Vds(None));
+            }
+            return;
+        }
+    };
+    let pulse = Pulse::suited(&world.environment, &world.entity(id).unwrap().position);
+    let turn = Tunable::TurnRate.get(&world.environment.tuning) * dt;
+    let escort = world.entity_mut(id).unwrap();
+    let station = escort.asw.as_mut().unwrap();
+    station.pinging = true;
+    station.pulse = pulse;
+    ai.pinging = true;
+    let range = escort.position.distance_to(&datum);
+    let fresh = time - since <= FRESH;
+    let (desired, speed) = if fresh && range < RUN_IN {
+        (escort.position.angle_to(&datum), ATTACK_SPEED)
+    } else if fresh || range > SEARCH_RADIUS {
+        (escort.position.angle_to(&datum), SEARCH_SPEED)
+    } else {
+        (datum.angle_to(&escort.position) + FRAC_PI_2, SEARCH_SPEED)
+    };
+    escort.heading = turn_towards(escort.heading, desired, turn);
+    escort.speed = speed;
+    let helicopter_idle = station.helicopter.as_ref().is_some_and(|h| {
+        h.dip.is_none()
+            && match h.task {
+                Task::OnDeck => h.fuel >= h.endurance,
+                Task::Hover(_) => true,
+                _ => false,
+            }
+    });
+    if fresh && range < DROP_RANGE {
+        let setting = Meters(ai.search_depth(layer));
+        // a refused pattern is simply tried again on the next run in
+        let _ = asw::execute(world, id, &AswCommand::Charges(setting));
+    }
+    if let Some(layer) = layer.filter(|_| helicopter_idle && ai.below >= DIP_BELOW) {
+        let dip = HeloCommand::Dip {
+            x: Meters(datum.x),
+            y: Meters(datum.y),
+            depth: Some(Meters(layer + LAYER_MARGIN)),
+        };
+        let _ = helicopter::execute(world, id, &dip);
+    }
+    let towing = world
+        .entity(id)
+        .and_then(|e| e.asw.as_ref())
+        .and_then(|s| s.vds.as_ref())
+        .map(|v| v.set);
+    if let (Some(set), Some(layer)) = (towing, layer) {
+        let depth = if ai.below >= DIP_BELOW {
+            layer + LAYER_MARGIN
+        } else {
+            (layer - LAYER_MARGIN).max(vds::MIN_DEPTH)
+        };
+        if set != Some(depth) {
+            // refused while running in too fast, streamed once slowed
+            let _ = asw::execute(world, id, &AswCommand::Vds(Some(Meters(depth))));
+        }
+    }
+}
+
+/// Runs the hunts of the escorts the computer commands
+pub fn update(world: &mut World, dt: f32) {
+    let escorts: Vec<EntityId> = world
+        .entities
+        .iter()
+        .filter(|e| e.escort_ai.is_some() && e.asw.is_some() && !e.is_destroyed())
+        .map(|e| e.id)
+        .collect();
+    for id in escorts {
+        let mut ai = world.entity(id).unwrap().escort_ai.clone().unwrap();
+        perceive(world, id, &mut ai);
+        if ai.datum.is_some() {
+            act(world, id, &mut ai, dt);
+        }
+        world.entity_mut(id).unwrap().escort_ai = Some(ai);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asw::AswStation;
+    use crate::helicopter::Helicopter;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::vds::Vds;
+
+    fn escort() -> Entity {
+        let mut escort = Entity::new("Flower", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.sensors.push(Sensor::new(SensorKind::HullSonar));
+        escort.asw = Some(AswStation::new(70, 0));
+        escort.escort_ai = Some(EscortAi::default());
+        escort
+    }
+
+    #[test]
+    fn charges_set_by_the_layer() {
+        let mut ai = EscortAi::default();
+        ai.alert(Point { x: 0.0, y: 0.0 }, 0.0);
+        assert_eq!(ai.below, PRIOR_BELOW);
+        assert_eq!(ai.search_depth(None), DEFAULT_DEPTH);
+        assert_eq!(ai.search_depth(Some(80.0)), 110.0);
+        ai.missed();
+        ai.missed();
+        assert!(ai.below > 0.9, "{}", ai.below);
+        ai.hold(Point { x: 100.0, y: 0.0 }, 10.0);
+        assert_eq!(ai.search_depth(Some(80.0)), 50.0);
+        // a second alert does not move the hunt
+        ai.alert(Point { x: 9000.0, y: 0.0 }, 20.0);
+        assert_eq!(ai.datum, Some((Point { x: 100.0, y: 0.0 }, 10.0)));
+    }
+
+    #[test]
+    fn silence_under_a_layer_is_read() {
+        // the default profile has a layer at 60 m
+        let mut world = World::new();
+        let id = world.spawn(escort());
+        world
+            .entity_mut(id)
+            .unwrap()
+            .escort_ai
+            .as_mut()
+            .unwrap()
+            .alert(Point { x: 4000.0, y: 0.0 }, 0.0);
+        // pulses that would have found a boat above the layer there
+        for _ in 0..3 {
+            world.emissions.clear();
+            world.time += 1.0;
+            world.ping(id);
+            update(&mut world, 1.0);
+        }
+        let escort = world.entity(id).unwrap();
+        let ai = escort.escort_ai.as_ref().unwrap();
+        assert!(ai.below > 0.9, "{}", ai.below);
+        assert_eq!(ai.search_depth(Some(60.0)), 90.0);
+        assert!(escort.asw.as_ref().unwrap().pinging);
+        assert_eq!(escort.speed, SEARCH_SPEED);
+
+        // the boat found above the layer close by
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 1000.0, y: 0.0 });
+        boat.depth = 30.0;
+        world.spawn(boat);
+        world.emissions.clear();
+        world.ping(id);
+        update(&mut world, 1.0);
+        let ai = world.entity(id).unwrap().escort_ai.as_ref().unwrap();
+        assert_eq!(ai.below, HELD_BELOW);
+        assert_eq!(ai.datum.as_ref().unwrap().0, Point { x: 1000.0, y: 0.0 });
+    }
+
+    #[test]
+    fn vds_lowered_under_the_layer() {
+        let mut world = World::new();
+        let mut towing = escort();
+        towing.asw.as_mut().unwrap().vds = Some(Vds::default());
+        towing.escort_ai = Some(EscortAi {
+            datum: Some((Point { x: 4000.0, y: 0.0 }, 0.0)),
+            below: 0.8,
+            pinging: false,
+        });
+        let id = world.spawn(towing);
+        let set = |world: &World| {
+            let station = world.entity(id).unwrap().asw.as_ref().unwrap();
+            station.vds.as_ref().unwrap().set
+        };
+        update(&mut world, 1.0);
+        assert_eq!(set(&world), Some(90.0));
+        let mut ai = EscortAi::default();
+        ai.alert(Point { x: 4000.0, y: 0.0 }, 0.0);
+        ai.cleared();
+        assert!(ai.below < 0.5, "{}", ai.below);
+        world.entity_mut(id).unwrap().escort_ai = Some(ai);
+        update(&mut world, 1.0);
+        assert_eq!(set(&world), Some(30.0));
+        // heaved in when the hunt is given up
+        world.time = HUNT_TIME + 10.0;
+        update(&mut world, 1.0);
+        assert_eq!(set(&world), None);
+    }
+
+    #[test]
+    fn helicopter_dips_under_the_layer() {
+        let mut world = World::new();
+        let mut carrier = escort();
+        let station = carrier.asw.as_mut().unwrap();
+        station.helicopter = Some(Helicopter::new(Point { x: 0.0, y: 0.0 }, 7200.0, 2));
+        carrier.escort_ai = Some(EscortAi {
+            datum: Some((Point { x: 4000.0, y: 0.0 }, 0.0)),
+            below: 0.8,
+            pinging: false,
+        });
+        let id = world.spawn(carrier);
+        update(&mut world, 1.0);
+        let station = world.entity(id).unwrap().asw.as_ref().unwrap();
+        assert_eq!(
+            station.helicopter.as_ref().unwrap().task,
+            Task::Dip {
+                at: Point { x: 4000.0, y: 0.0 },
+                depth: 90.0
+            }
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use crate::hazards::HazardKind;
+use crate::lookouts::SightingKind;
+use crate::physics::Point;
+use crate::reliability::Failure;
+use crate::transient::TransientKind;
+use crate::world::EntityId;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    GunFired {
+        shooter: EntityId,
+        target: EntityId,
+    },
+    ShellHit {
+        shooter: EntityId,
+        target: EntityId,
+        damage: f32,
+    },
+    Destroyed {
+        entity: EntityId,
+    },
+    CrewKilled {
+        entity: EntityId,
+        killed: u32,
+    },
+    TorpedoFired {
+        shooter: EntityId,
+        torpedo: EntityId,
+    },
+    TorpedoHit {
+        torpedo: EntityId,
+        shooter: EntityId,
+        target: EntityId,
+    },
+    TorpedoRanOut {
+        torpedo: EntityId,
+    },
+    /// `torpedo` passed `target` without going off, `distance` meters off
+    /// at the closest, near enough to be heard, see torpedo.rs
+    TorpedoPassed {
+        torpedo: EntityId,
+        target: EntityId,
+        distance: f32,
+    },
+    TorpedoFailed {
+        shooter: EntityId,
+        target: Option<EntityId>,
+        failure: Failure,
+    },
+    Transient {
+        entity: EntityId,
+        kind: TransientKind,
+    },
+    /// The conning tower of a submarine broke the surface
+    Broached {
+        entity: EntityId,
+    },
+    RanAground {
+        entity: EntityId,
+    },
+    TouchedBottom {
+        entity: EntityId,
+    },
+    /// `observer` painted `target` on its radar, see radar.rs
+    RadarContact {
+        observer: EntityId,
+        target: EntityId,
+    },
+    EnteredZone {
+        entity: EntityId,
+        zone: String,
+    },
+    /// A ship of a scheduled sailing left port, see traffic.rs
+    Departed {
+        entity: EntityId,
+    },
+    /// A ship of a scheduled sailing made port and left the world
+    Arrived {
+        entity: EntityId,
+    },
+    /// `entity` ran onto a drifting mine or wreckage, see hazards.rs
+    HazardStruck {
+        entity: EntityId,
+        kind: HazardKind,
+    },
+    /// The lookouts of `observer` sighted `target`, see lookouts.rs
+    Sighted {
+        observer: EntityId,
+        target: EntityId,
+        kind: SightingKind,
+    },
+    /// A torpedo left `entity` afloat but stopped, see cargo.rs
+    Crippled {
+        entity: EntityId,
+    },
+    /// `entity` was on the air for `seconds`, see hfdf.rs
+    Transmitted {
+        entity: EntityId,
+        seconds: f32,
+    },
+    /// `entity` stopped a transfer at a rendezvous before it was done, see
+    /// rendezvous.rs
+    TransferInterrupted {
+        entity: EntityId,
+        name: String,
+    },
+    /// `entity` made the transfer of a rendezvous in its window
+    MissionCompleted {
+        entity: EntityId,
+        name: String,
+    },
+    /// The window of a rendezvous closed before anyone made its transfer
+    MissionFailed {
+        name: String,
+    },
+    /// `entity` took on fuel and torpedoes at a resupply rendezvous
+    Resupplied {
+        entity: EntityId,
+        name: String,
+    },
+    /// `entity` trailed the target of a shadowing mission long enough, see
+    /// shadowing.rs
+    TrailCompleted {
+        entity: EntityId,
+        name: String,
+    },
+    /// `entity` lost the target of a shadowing mission for too long
+    TrailBroken {
+        entity: EntityId,
+        name: String,
+    },
+    /// `entity` was heard by the target of a shadowing mission or its side
+    TrailDetected {
+        entity: EntityId,
+        name: String,
+    },
+    /// A shadowing mission ran out of time, or its target or boat was lost
+    TrailFailed {
+        name: String,
+    },
+    /// The enemy read the signal giving the point of a rendezvous
+    RendezvousCompromised {
+        name: String,
+    },
+    /// A depth charge or mortar bomb of `owner` went off, see asw.rs
+    ChargeExploded {
+        owner: EntityId,
+        position: Point,
+        depth: f32,
+    },
+    /// The helicopter of `owner` raised its sonar, having placed a boat at
+    /// `datum` or not, see helicopter.rs
+    HelicopterDipped {
+        owner: EntityId,
+        datum: Option<Point>,
+    },
+    /// The helicopter of `owner` broke off its task short of fuel
+    HelicopterBingo {
+        owner: EntityId,
+    },
+    HelicopterLanded {
+        owner: EntityId,
+    },
+    /// The helicopter of `owner` ran out of fuel and went into the sea
+    HelicopterDitched {
+        owner: EntityId,
+    },
+    /// `from` radioed a contact report to its pack, see wolfpack.rs
+    PackReported {
+        from: EntityId,
+    },
+    /// Enough reports agreed for the pack to be ordered in on `position`
+    PackConverging {
+        position: Point,
+    },
+}
+
+/// An event together with the scenario time (seconds) it happened at
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimedEvent {
+    pub time: f32,
+    pub event: Event,
+}
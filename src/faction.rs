@@ -0,0 +1,325 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError, Section};
+use crate::events::Event;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #    SIDES AND DIPLOMACY    #
+// #############################
+
+// Ships fight on a side (see registry.rs), and how two sides stand to each
+// other is set per scenario. Ships of the same side are friends; sides the
+// scenario says nothing about are at war, as is any ship without a side,
+// so scenarios without sides play as before:
+//
+// [relations]
+// allied/axis = hostile
+// axis/usa = neutral
+//
+// The AI only goes after hostile ships, and the player's guns refuse to
+// fire on anyone else (the rules of engagement). Who hit whom, and how they
+// stood at the time, is scored after the mission. Relations change during
+// the mission by declarations, at a time or once one side attacks the
+// other:
+//
+// [declaration.greer]
+// sides = axis/usa
+// stance = hostile
+// attacked = true         # or: time = 7200, seconds into the scenario
+//
+// The player does not know how an unidentified contact stands; see
+// Simulation::stance_of.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Stance {
+    Friendly,
+    Neutral,
+    Hostile,
+    /// Not identified yet, for the player
+    Unknown,
+}
+
+impl FromStr for Stance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "friendly" => Ok(Stance::Friendly),
+            "neutral" => Ok(Stance::Neutral),
+            "hostile" => Ok(Stance::Hostile),
+            _ => Err(format!("unknown stance '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Stance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stance::Friendly => "friendly",
+            Stance::Neutral => "neutral",
+            Stance::Hostile => "hostile",
+            Stance::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A change of relations during the mission
+#[derive(Debug, PartialEq, Clone)]
+pub struct Declaration {
+    pub sides: (String, String),
+    pub stance: Stance,
+    /// Seconds into the scenario it takes effect at
+    pub time: Option<f32>,
+    /// Takes effect once either side hits the other
+    pub attacked: bool,
+    pub done: bool,
+}
+
+/// A hit scored, with how the sides stood when it was
+#[derive(Debug, PartialEq, Clone)]
+pub struct Incident {
+    pub shooter: EntityId,
+    pub target: EntityId,
+    pub stance: Stance,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Diplomacy {
+    /// How pairs of sides stand, in either order
+    pub relations: Vec<(String, String, Stance)>,
+    pub declarations: Vec<Declaration>,
+    /// Every hit scored, oldest first
+    pub incidents: Vec<Incident>,
+}
+
+/// Reads "a/b" into the two sides
+fn read_sides(section: &Section, key: &str, text: &str) -> Result<(String, String), ConfigError> {
+    match text.split_once('/') {
+        Some((a, b)) => Ok((a.trim().to_string(), b.trim().to_string())),
+        None => Err(ConfigError::Invalid {
+            section: section.name.clone(),
+            key: key.to_string(),
+            value: text.to_string(),
+        }),
+    }
+}
+
+impl Diplomacy {
+    /// Reads the "[relations]" and "[declaration.<name>]" sections
+    pub fn read(config: &Config) -> Result<Diplomacy, ConfigError> {
+        let mut diplomacy = Diplomacy::default();
+        if let Some(section) = config.section("relations") {
+            for (key, _) in section.entries() {
+                let (a, b) = read_sides(section, key, key)?;
+                diplomacy.set(&a, &b, section.parse(key)?);
+            }
+        }
+        for (_, section) in config.sections_with_prefix("declaration") {
+            let sides: String = section.parse("sides")?;
+            diplomacy.declarations.push(Declaration {
+                sides: read_sides(section, "sides", &sides)?,
+                stance: section.parse("stance")?,
+                time: section.parse_optional("time")?,
+                attacked: section.parse_or("attacked", false)?,
+                done: false,
+            });
+        }
+        Ok(diplomacy)
+    }
+
+    /// How side `a` stands to side `b`
+    pub fn between(&self, a: Option<&str>, b: Option<&str>) -> Stance {
+        let (a, b) = match (a, b) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Stance::Hostile,
+        };
+        if a == b {
+            return Stance::Friendly;
+        }
+        self.relations
+            .iter()
+            .find(|(x, y, _)| (x == a && y == b) || (x == b && y == a))
+            .map_or(Stance::Hostile, |(_, _, stance)| *stance)
+    }
+
+    /// How `a` stands to `b`, by their sides
+    pub fn stance(&self, a: &Entity, b: &Entity) -> Stance {
+        self.between(a.side.as_deref(), b.side.as_deref())
+    }
+
+    pub fn set(&mut self, a: &str, b: &str, stance: Stance) {
+        self.relations
+            .retain(|(x, y, _)| !((x == a && y == b) || (x == b && y == a)));
+        self.relations.push((a.to_string(), b.to_string(), stance));
+    }
+
+    fn declare(&mut self, index: usize) {
+        let declaration = &mut self.declarations[index];
+        declaration.done = true;
+        let (a, b) = declaration.sides.clone();
+        let stance = declaration.stance;
+        self.set(&a, &b, stance);
+    }
+}
+
+/// Records the hits of `event`, and makes the declarations an attack
+/// sets off
+pub fn react(world: &mut World, event: &Event) {
+    let (shooter, target) = match *event {
+        Event::TorpedoHit {
+            shooter, target, ..
+        }
+        | Event::ShellHit {
+            shooter, target, ..
+        } => (shooter, target),
+        _ => return,
+    };
+    let sides = match (world.entity(shooter), world.entity(target)) {
+        (Some(s), Some(t)) => (s.side.clone(), t.side.clone()),
+        _ => return,
+    };
+    let diplomacy = &mut world.diplomacy;
+    let stance = diplomacy.between(sides.0.as_deref(), sides.1.as_deref());
+    diplomacy.incidents.push(Incident {
+        shooter,
+        target,
+        stance,
+    });
+    if let (Some(a), Some(b)) = sides {
+        for i in 0..diplomacy.declarations.len() {
+            let d = &diplomacy.declarations[i];
+            let (x, y) = (&d.sides.0, &d.sides.1);
+            let between = (*x == a && *y == b) || (*x == b && *y == a);
+            if d.attacked && !d.done && between {
+                diplomacy.declare(i);
+            }
+        }
+    }
+}
+
+/// Makes the declarations whose time has come
+pub fn update(world: &mut World) {
+    let time = world.time;
+    let diplomacy = &mut world.diplomacy;
+    for i in 0..diplomacy.declarations.len() {
+        let d = &diplomacy.declarations[i];
+        if !d.done && d.time.is_some_and(|t| time >= t) {
+            diplomacy.declare(i);
+        }
+    }
+}
+
+/// Hits scored by the ships of a side, by how the target stood
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Score {
+    pub hostile: u32,
+    pub neutral: u32,
+    pub friendly: u32,
+}
+
+impl Score {
+    /// Hits on the enemy, less twice those on neutrals and three times
+    /// those on friends
+    pub fn points(&self) -> i32 {
+        self.hostile as i32 - 2 * self.neutral as i32 - 3 * self.friendly as i32
+    }
+}
+
+/// Hits scored by `shooter` and the ships of its side
+pub fn score(world: &World, shooter: &Entity) -> Score {
+    let mut score = Score::default();
+    let own = |id: EntityId| {
+        id == shooter.id
+            || shooter.side.is_some() && world.entity(id).is_some_and(|e| e.side == shooter.side)
+    };
+    for incident in world.diplomacy.incidents.iter().filter(|i| own(i.shooter)) {
+        match incident.stance {
+            Stance::Hostile | Stance::Unknown => score.hostile += 1,
+            Stance::Neutral => score.neutral += 1,
+            Stance::Friendly => score.friendly += 1,
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::world::EntityKind;
+
+    const RELATIONS: &str = "
+[relations]
+allied/axis = hostile
+axis/usa = neutral
+
+[declaration.greer]
+sides = usa/axis
+stance = hostile
+attacked = true
+
+[declaration.armistice]
+sides = allied / axis
+stance = neutral
+time = 3600
+";
+
+    fn ship(world: &mut World, name: &str, side: Option<&str>) -> EntityId {
+        let mut ship = Entity::new(name, EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        ship.side = side.map(|s| s.to_string());
+        world.spawn(ship)
+    }
+
+    fn hit(world: &mut World, shooter: EntityId, target: EntityId) {
+        world.emit(Event::ShellHit {
+            shooter,
+            target,
+            damage: 0.0,
+        });
+    }
+
+    #[test]
+    fn relations_between_sides() {
+        let diplomacy = Diplomacy::read(&Config::parse(RELATIONS).unwrap()).unwrap();
+        assert_eq!(
+            diplomacy.between(Some("axis"), Some("allied")),
+            Stance::Hostile
+        );
+        assert_eq!(
+            diplomacy.between(Some("usa"), Some("axis")),
+            Stance::Neutral
+        );
+        assert_eq!(
+            diplomacy.between(Some("usa"), Some("usa")),
+            Stance::Friendly
+        );
+        assert_eq!(diplomacy.between(Some("usa"), None), Stance::Hostile);
+        assert_eq!(diplomacy.declarations[1].sides.1, "axis");
+        assert_eq!(diplomacy.declarations[1].time, Some(3600.0));
+    }
+
+    #[test]
+    fn neutrals_turn_hostile_when_attacked() {
+        let mut world = World::new();
+        world.diplomacy = Diplomacy::read(&Config::parse(RELATIONS).unwrap()).unwrap();
+        let u552 = ship(&mut world, "U-552", Some("axis"));
+        let u568 = ship(&mut world, "U-568", Some("axis"));
+        let kearny = ship(&mut world, "Kearny", Some("usa"));
+        let walker = ship(&mut world, "Walker", Some("allied"));
+        hit(&mut world, u568, kearny);
+        hit(&mut world, u552, kearny);
+        hit(&mut world, u552, walker);
+        let (a, b) = (world.entity(u552).unwrap(), world.entity(kearny).unwrap());
+        assert_eq!(world.diplomacy.stance(a, b), Stance::Hostile);
+        let score = score(&world, world.entity(u552).unwrap());
+        assert_eq!((score.hostile, score.neutral), (2, 1));
+        assert_eq!(score.points(), 0);
+
+        world.step(3600.0);
+        let (a, b) = (world.entity(u552).unwrap(), world.entity(walker).unwrap());
+        assert_eq!(world.diplomacy.stance(a, b), Stance::Neutral);
+    }
+}
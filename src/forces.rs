@@ -0,0 +1,379 @@
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError, Section};
+use crate::editor::{EditError, Position, ScenarioEditor};
+use crate::era::Era;
+use crate::physics::Point;
+use crate::random::Rng;
+
+// #############################
+// #      ORDER OF BATTLE      #
+// #############################
+
+// Forces the scenario generator and the mission editor put to sea whole,
+// from templates of "[force.<name>]" sections:
+//
+// [force.atlantic_convoy_1943]
+// title = 1943 Atlantic convoy
+// era = 1943              # optional, the year the force is of
+// side = allied           # optional, see registry.rs
+// speed = 7-10            # knots, a number or a range to pick from
+// main = liberty 4-12     # classes of the main body, sailing in columns,
+//                         # and how many of each, a number or a range
+// main_name = Merchant    # optional, its ships are "Merchant 1" and on
+// spacing = 900           # optional, meters between two ships of it
+// screen = flower 1-3, river 0-1   # optional, the escorts, one ahead and
+//                         # the others on the flanks of the main body
+// screen_name = Escort    # optional
+// screen_distance = 2500  # optional, meters from the main body
+//
+// Each time a force is put to sea, how many ships of each class and its
+// speed are picked anew from the ranges of its template, so no two convoys
+// are quite the same. The templates shipped with the game are in
+// data/forces.cfg, with the "[class.<name>]" sections of their ships (see
+// vessel.rs); a scenario file may add its own the same way. The classes
+// a force needs are copied into the scenario unless it has them already,
+// and its ships are numbered on from those of the same name placed before.
+
+/// The templates shipped with the game
+const LIBRARY: &str = include_str!("../data/forces.cfg");
+
+/// Meters between two ships of the main body, unless set
+const SPACING: f32 = 900.0;
+/// Meters from the main body to its screen, unless set
+const SCREEN_DISTANCE: f32 = 2_500.0;
+
+/// How many of something, picked at random between two bounds
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Count {
+    pub low: usize,
+    pub high: usize,
+}
+
+impl Count {
+    pub fn pick(&self, rng: &mut Rng) -> usize {
+        self.low + rng.index(self.high - self.low + 1)
+    }
+}
+
+impl FromStr for Count {
+    type Err = String;
+
+    /// Accepts "3" or "1-3"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |text: &str| {
+            text.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("expected a count, found '{}'", s))
+        };
+        let (low, high) = match s.split_once('-') {
+            Some((low, high)) => (number(low)?, number(high)?),
+            None => (number(s)?, number(s)?),
+        };
+        if low > high {
+            return Err(format!("empty range '{}'", s));
+        }
+        Ok(Count { low, high })
+    }
+}
+
+/// Classes of ships and how many of each, as "liberty 4-12, flower 1"
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Ships(pub Vec<(String, Count)>);
+
+impl FromStr for Ships {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ships = Vec::new();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (class, count) = item.split_once(char::is_whitespace).unwrap_or((item, "1"));
+            ships.push((class.to_string(), count.parse()?));
+        }
+        Ok(Ships(ships))
+    }
+}
+
+impl Ships {
+    /// The class of each ship, as many as picked
+    fn pick(&self, rng: &mut Rng) -> Vec<String> {
+        let mut classes = Vec::new();
+        for (class, count) in &self.0 {
+            for _ in 0..count.pick(rng) {
+                classes.push(class.clone());
+            }
+        }
+        classes
+    }
+}
+
+/// A template of a force
+#[derive(Debug, PartialEq, Clone)]
+pub struct Force {
+    pub name: String,
+    pub title: String,
+    pub era: Option<Era>,
+    pub side: Option<String>,
+    /// Knots
+    pub speed: Count,
+    pub main: Ships,
+    pub main_name: String,
+    pub spacing: f32,
+    pub screen: Ships,
+    pub screen_name: String,
+    pub screen_distance: f32,
+}
+
+/// The ships of a force, by class, and the knots it makes, as picked from
+/// its template
+#[derive(Debug, PartialEq, Clone)]
+pub struct Composition {
+    pub main: Vec<String>,
+    pub screen: Vec<String>,
+    pub speed: f32,
+}
+
+impl Force {
+    /// Reads a "[force.<name>]" section
+    pub fn read(name: &str, section: &Section) -> Result<Force, ConfigError> {
+        Ok(Force {
+            name: name.to_string(),
+            title: section.parse_or("title", name.to_string())?,
+            era: section.parse_optional("era")?,
+            side: section.get("side").map(|s| s.to_string()),
+            speed: section.parse("speed")?,
+            main: section.parse("main")?,
+            main_name: section.parse_or("main_name", "Ship".to_string())?,
+            spacing: section.parse_or("spacing", SPACING)?,
+            screen: section.parse_or("screen", Ships::default())?,
+            screen_name: section.parse_or("screen_name", "Escort".to_string())?,
+            screen_distance: section.parse_or("screen_distance", SCREEN_DISTANCE)?,
+        })
+    }
+
+    /// Picks the ships and the speed of one force of the template
+    pub fn compose(&self, rng: &mut Rng) -> Composition {
+        Composition {
+            main: self.main.pick(rng),
+            screen: self.screen.pick(rng),
+            speed: self.speed.pick(rng) as f32,
+        }
+    }
+
+    /// `escorts` ships of the classes of the screen, taken in turn, for a
+    /// screen sized otherwise than by the template
+    pub fn screen_of(&self, escorts: usize) -> Vec<String> {
+        self.screen
+            .0
+            .iter()
+            .map(|(class, _)| class.clone())
+            .cycle()
+            .take(escorts)
+            .collect()
+    }
+}
+
+/// Offsets a position by `right` meters to starboard and `ahead` meters
+/// along `course` (user angle, degrees)
+pub fn offset(course: f32, right: f32, ahead: f32) -> Point {
+    let c = course.to_radians();
+    Point {
+        x: ahead * c.sin() + right * c.cos(),
+        y: ahead * c.cos() - right * c.sin(),
+    }
+}
+
+/// Templates of forces and the classes of their ships
+#[derive(Debug, PartialEq, Clone)]
+pub struct Library {
+    pub forces: Vec<Force>,
+    classes: Config,
+}
+
+impl Library {
+    /// The templates shipped with the game
+    pub fn builtin() -> Library {
+        Library::read(&Config::parse(LIBRARY).unwrap()).unwrap()
+    }
+
+    /// Reads the "[force.<name>]" and "[class.<name>]" sections of `config`
+    pub fn read(config: &Config) -> Result<Library, ConfigError> {
+        let mut forces = Vec::new();
+        for (name, section) in config.sections_with_prefix("force") {
+            forces.push(Force::read(name, section)?);
+        }
+        let mut classes = Config::new();
+        for (name, section) in config.sections_with_prefix("class") {
+            let copy = classes.section_mut(&format!("class.{}", name));
+            for (key, value) in section.entries() {
+                copy.set(key, value);
+            }
+        }
+        Ok(Library { forces, classes })
+    }
+
+    /// Adds the templates and classes of `other`, which win over those of
+    /// the same name
+    pub fn merge(&mut self, other: Library) {
+        for force in other.forces {
+            self.forces.retain(|f| f.name != force.name);
+            self.forces.push(force);
+        }
+        for section in other.classes.sections() {
+            let copy = self.classes.section_mut(&section.name);
+            for (key, value) in section.entries() {
+                copy.set(key, value);
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Force> {
+        self.forces.iter().find(|f| f.name == name)
+    }
+
+    /// Places the ships of `composition` in the formation of `force`, the
+    /// main body centred on `at` and all of them on `course` (user angle,
+    /// degrees), returning their names
+    pub fn instantiate(
+        &self,
+        editor: &mut ScenarioEditor,
+        force: &Force,
+        composition: &Composition,
+        at: Point,
+        course: f32,
+    ) -> Result<Vec<String>, EditError> {
+        for class in composition.main.iter().chain(&composition.screen) {
+            let name = format!("class.{}", class);
+            if let (Some(section), None) =
+                (self.classes.section(&name), editor.config.section(&name))
+            {
+                let copy = editor.config.section_mut(&name);
+                for (key, value) in section.entries() {
+                    copy.set(key, value);
+                }
+            }
+        }
+
+        let mut placed = Vec::new();
+        let mut place = |editor: &mut ScenarioEditor, base: &str, class: &str, relative: Point| {
+            let name = (1..)
+                .map(|n| format!("{} {}", base, n))
+                .find(|name| !editor.has_entity(name))
+                .unwrap();
+            let position = Position::Local(Point {
+                x: at.x + relative.x,
+                y: at.y + relative.y,
+            });
+            editor.place(&name, class, &position)?;
+            editor.set_entity(&name, "heading", &course.to_string())?;
+            editor.set_entity(&name, "speed", &composition.speed.to_string())?;
+            if let Some(side) = &force.side {
+                editor.set_entity(&name, "side", side)?;
+            }
+            placed.push(name);
+            Ok::<(), EditError>(())
+        };
+
+        let spacing = force.spacing;
+        let columns = (composition.main.len() as f32).sqrt().ceil().max(1.0) as usize;
+        for (i, class) in composition.main.iter().enumerate() {
+            let column = (i % columns) as f32 - (columns - 1) as f32 / 2.0;
+            let row = (i / columns) as f32;
+            let relative = offset(course, column * spacing, -row * spacing);
+            place(editor, &force.main_name, class, relative)?;
+        }
+        for (i, class) in composition.screen.iter().enumerate() {
+            // the first escort leads, the others alternate on the flanks
+            let relative = if i == 0 {
+                offset(course, 0.0, force.screen_distance)
+            } else {
+                let flank = if i % 2 == 1 { 1.0 } else { -1.0 };
+                let width = columns as f32 * spacing / 2.0 + force.screen_distance;
+                offset(
+                    course,
+                    flank * width,
+                    -(((i - 1) / 2) as f32) * 2.0 * spacing,
+                )
+            };
+            place(editor, &force.screen_name, class, relative)?;
+        }
+        Ok(placed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_ships() {
+        assert_eq!("3".parse(), Ok(Count { low: 3, high: 3 }));
+        assert_eq!("1-3".parse(), Ok(Count { low: 1, high: 3 }));
+        assert!("3-1".parse::<Count>().is_err());
+        assert_eq!(
+            "liberty 4-12, flower".parse(),
+            Ok(Ships(vec![
+                ("liberty".to_string(), Count { low: 4, high: 12 }),
+                ("flower".to_string(), Count { low: 1, high: 1 }),
+            ]))
+        );
+        assert!("liberty many".parse::<Ships>().is_err());
+    }
+
+    #[test]
+    fn builtin_templates_compose_within_their_ranges() {
+        let library = Library::builtin();
+        let convoy = library.get("atlantic_convoy_1943").unwrap();
+        assert_eq!(convoy.title, "1943 Atlantic convoy");
+        let sag = library.get("soviet_sag_1984").unwrap();
+        assert_eq!(sag.era, Some(Era { year: 1984 }));
+        let mut rng = Rng::new(3);
+        for _ in 0..20 {
+            let composition = sag.compose(&mut rng);
+            assert!((1..=2).contains(&composition.main.len()));
+            assert!((2..=6).contains(&composition.screen.len()));
+            assert!((14.0..=18.0).contains(&composition.speed));
+        }
+        assert_eq!(convoy.screen_of(3), vec!["flower", "river", "flower"]);
+    }
+
+    #[test]
+    fn instantiated_forces_play() {
+        let library = Library::builtin();
+        let force = library.get("soviet_sag_1984").unwrap();
+        let mut editor = ScenarioEditor::new("Test");
+        editor.set("era", "1984");
+        editor.set("player", "Cruiser 1");
+        let mut rng = Rng::new(1);
+        let first = force.compose(&mut rng);
+        let names = library
+            .instantiate(&mut editor, force, &first, Point { x: 0.0, y: 0.0 }, 90.0)
+            .unwrap();
+        assert_eq!(names[0], "Cruiser 1");
+        let second = force.compose(&mut rng);
+        let more = library
+            .instantiate(
+                &mut editor,
+                force,
+                &second,
+                Point {
+                    x: 0.0,
+                    y: 50_000.0,
+                },
+                270.0,
+            )
+            .unwrap();
+        // numbered on from the first force
+        assert_eq!(more[0], format!("Cruiser {}", first.main.len() + 1));
+        assert!(editor.validate().unwrap().is_empty());
+        let escort = &more[second.main.len()];
+        assert_eq!(
+            editor
+                .config
+                .section(&format!("entity.{}", escort))
+                .unwrap()
+                .get("side"),
+            Some("soviet")
+        );
+    }
+}
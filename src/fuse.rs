@@ -0,0 +1,146 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::random::Rng;
+use crate::reliability::{Failure, Reliability};
+
+// #############################
+// #   PISTOLS AND WARHEADS    #
+// #############################
+
+// What sets a warhead off is its pistol, wired in with the other settings
+// of the torpedo (see weapons.rs), "pistol = magnetic" in a preset:
+//
+// contact         fires on striking the hull, so the torpedo must run
+//                 shallower than the keel; a square hit may crush it
+// magnetic        fires on the disturbance of the earth's field under the
+//                 hull, up to a few meters below the keel, and still on
+//                 contact when it runs shallower
+//
+// A warhead going off against the side holes the hull. One going off
+// under the keel lifts the ship on its gas bubble, which then collapses
+// and breaks her back: the closer under the keel, the worse, up to twice
+// the damage of a hit on the side. A torpedo that passes without going off
+// is heard by the ship it missed if it passed close enough (see
+// torpedo.rs).
+
+/// Meters below the keel down to which a magnetic pistol fires
+pub const INFLUENCE_REACH: f32 = 6.0;
+/// Damage of a detonation right under the keel, against one on the side
+const UNDER_KEEL_FACTOR: f32 = 2.0;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Pistol {
+    #[default]
+    Contact,
+    Magnetic,
+}
+
+impl fmt::Display for Pistol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Pistol::Contact => "contact",
+            Pistol::Magnetic => "magnetic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Pistol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "contact" => Ok(Pistol::Contact),
+            "magnetic" => Ok(Pistol::Magnetic),
+            _ => Err(format!("unknown pistol '{}'", s)),
+        }
+    }
+}
+
+/// How a warhead went off
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Detonation {
+    /// Against the side of the hull
+    Contact,
+    /// Under the keel, `standoff` meters below it
+    UnderKeel { standoff: f32 },
+}
+
+impl Detonation {
+    /// Damage done, against that of a hit on the side
+    pub fn damage_factor(&self) -> f32 {
+        match self {
+            Detonation::Contact => 1.0,
+            Detonation::UnderKeel { standoff } => {
+                let closeness = 1.0 - (standoff / INFLUENCE_REACH).clamp(0.0, 1.0);
+                1.0 + (UNDER_KEEL_FACTOR - 1.0) * closeness
+            }
+        }
+    }
+}
+
+/// How a torpedo with `pistol` running at `running_depth` fares passing a
+/// hull of keel depth `keel`, `impact_angle` radians across it
+pub fn detonate(
+    reliability: &Reliability,
+    rng: &mut Rng,
+    pistol: Pistol,
+    running_depth: f32,
+    keel: f32,
+    impact_angle: f32,
+) -> Result<Detonation, Failure> {
+    if pistol == Pistol::Contact || running_depth <= keel {
+        return reliability
+            .resolve(rng, running_depth, keel, impact_angle)
+            .map(|()| Detonation::Contact);
+    }
+    let standoff = running_depth - keel;
+    if standoff > INFLUENCE_REACH {
+        return Err(Failure::RanDeep);
+    }
+    // no square hit to crush the pistol under the keel
+    if rng.chance(reliability.dud) {
+        return Err(Failure::Dud);
+    }
+    Ok(Detonation::UnderKeel { standoff })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn magnetic_pistols_fire_under_the_keel() {
+        let mut rng = Rng::new(3);
+        let perfect = Reliability::perfect();
+        let keel = 8.0;
+        assert_eq!(
+            detonate(&perfect, &mut rng, Pistol::Contact, 10.0, keel, FRAC_PI_2),
+            Err(Failure::RanDeep)
+        );
+        assert_eq!(
+            detonate(&perfect, &mut rng, Pistol::Magnetic, 10.0, keel, FRAC_PI_2),
+            Ok(Detonation::UnderKeel { standoff: 2.0 })
+        );
+        assert_eq!(
+            detonate(&perfect, &mut rng, Pistol::Magnetic, 15.0, keel, FRAC_PI_2),
+            Err(Failure::RanDeep)
+        );
+        assert_eq!(
+            detonate(&perfect, &mut rng, Pistol::Magnetic, 4.0, keel, FRAC_PI_2),
+            Ok(Detonation::Contact)
+        );
+        assert_eq!("Magnetic".parse(), Ok(Pistol::Magnetic));
+        assert!("proximity".parse::<Pistol>().is_err());
+    }
+
+    #[test]
+    fn closer_under_the_keel_is_worse() {
+        let close = Detonation::UnderKeel { standoff: 0.5 }.damage_factor();
+        let deep = Detonation::UnderKeel { standoff: 5.0 }.damage_factor();
+        assert!(close > deep && deep > Detonation::Contact.damage_factor());
+        assert!(close <= UNDER_KEEL_FACTOR);
+    }
+}
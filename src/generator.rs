@@ -0,0 +1,157 @@
+use crate::config::Config;
+use crate::crew::Difficulty;
+use crate::editor::{Position, ScenarioEditor};
+use crate::forces::{offset, Library};
+use crate::physics::Point;
+use crate::random::Rng;
+
+// #############################
+// #    SKIRMISH GENERATOR     #
+// #############################
+
+// Random convoy battles: a convoy of the "1943 Atlantic convoy" template
+// (see forces.rs), its merchants in columns and its escort screen ahead
+// and on the flanks, and the player's boat somewhere around. The
+// difficulty knob adds escorts, roughens the sea and moves the boat from
+// a textbook position ahead of the convoy to a stern chase; the seed picks
+// everything else, so the same pair always gives the same battle.
+
+/// The class of the player's boat
+const CLASSES: &str = "
+[class.type_viic]
+kind = submarine
+max_speed = 17.7
+tubes = 5
+deck_gun = true
+";
+
+/// The force template of the convoy, see forces.rs
+const CONVOY: &str = "atlantic_convoy_1943";
+const PLAYER: &str = "U-boat";
+
+/// Escorts, extra escorts the seed may add, and the worst sea state
+fn knobs(difficulty: Difficulty) -> (usize, usize, u8) {
+    match difficulty {
+        Difficulty::Easy => (1, 1, 3),
+        Difficulty::Normal => (2, 1, 4),
+        Difficulty::Hard => (3, 2, 5),
+        Difficulty::Expert => (4, 2, 6),
+    }
+}
+
+/// Range in meters from the convoy the boat starts at, and its relative
+/// bearing in degrees off the convoy's course
+fn start(difficulty: Difficulty, rng: &mut Rng) -> (f32, f32) {
+    match difficulty {
+        Difficulty::Easy => (8_000.0, rng.range(-20.0, 20.0)),
+        Difficulty::Normal => (10_000.0, rng.range(-60.0, 60.0)),
+        Difficulty::Hard => (12_000.0, rng.range(60.0, 120.0) * side(rng)),
+        Difficulty::Expert => (15_000.0, rng.range(120.0, 180.0) * side(rng)),
+    }
+}
+
+fn side(rng: &mut Rng) -> f32 {
+    if rng.chance(0.5) {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+pub fn generate(difficulty: Difficulty, seed: u64) -> ScenarioEditor {
+    let mut rng = Rng::new(seed);
+    let mut editor = ScenarioEditor::new(&format!("Skirmish {}", seed));
+    let classes = Config::parse(CLASSES).unwrap();
+    for section in classes.sections() {
+        let copy = editor.config.section_mut(&section.name);
+        for (key, value) in section.entries() {
+            copy.set(key, value);
+        }
+    }
+
+    let library = Library::builtin();
+    let convoy = library.get(CONVOY).unwrap();
+    let (escorts, extra, worst_sea) = knobs(difficulty);
+    let escorts = escorts + rng.index(extra + 1);
+    let mut composition = convoy.compose(&mut rng);
+    composition.screen = convoy.screen_of(escorts);
+    let sea_state = 1 + rng.index(worst_sea as usize) as u8;
+    editor.set("era", &convoy.era.unwrap_or_default().to_string());
+    editor.set("player", PLAYER);
+    editor.set("difficulty", &difficulty.to_string());
+    editor.set("sea_state", &sea_state.to_string());
+    editor.set(
+        "visibility",
+        &(rng.range(4.0, 20.0).round() * 1000.0).to_string(),
+    );
+
+    let course = (rng.range(0.0, 36.0).floor() * 10.0) % 360.0;
+    let origin = Point { x: 0.0, y: 0.0 };
+    library
+        .instantiate(&mut editor, convoy, &composition, origin, course)
+        .unwrap();
+
+    let (range, relative) = start(difficulty, &mut rng);
+    let at = offset(course + relative, 0.0, range);
+    editor
+        .place(PLAYER, "type_viic", &Position::Local(at))
+        .unwrap();
+    editor
+        .set_entity(PLAYER, "heading", &course.to_string())
+        .unwrap();
+    editor.set_entity(PLAYER, "speed", "5").unwrap();
+    editor.set_entity(PLAYER, "depth", "15").unwrap();
+    editor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use crate::world::EntityKind;
+
+    fn count(editor: &ScenarioEditor, class: &str) -> usize {
+        editor
+            .config
+            .sections_with_prefix("entity")
+            .filter(|(_, s)| s.get("class") == Some(class))
+            .count()
+    }
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(
+            generate(Difficulty::Normal, 7),
+            generate(Difficulty::Normal, 7)
+        );
+        assert_ne!(
+            generate(Difficulty::Normal, 7),
+            generate(Difficulty::Normal, 8)
+        );
+    }
+
+    #[test]
+    fn playable() {
+        for seed in 0..20 {
+            let editor = generate(Difficulty::Hard, seed);
+            assert!(editor.validate().unwrap().is_empty());
+            let sim = Scenario::from_config(&editor.config)
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(sim.own_ship().unwrap().kind, EntityKind::Submarine);
+        }
+    }
+
+    #[test]
+    fn difficulty_adds_escorts() {
+        for seed in 0..20 {
+            let easy = generate(Difficulty::Easy, seed);
+            let expert = generate(Difficulty::Expert, seed);
+            let escorts = |editor| count(editor, "flower") + count(editor, "river");
+            assert!(escorts(&easy) <= 2);
+            assert!(escorts(&expert) >= 4);
+            assert!((4..=12).contains(&count(&easy, "liberty")));
+        }
+    }
+}
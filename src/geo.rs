@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::physics::Point;
+
+// #############################
+// #    GEOGRAPHIC POSITIONS   #
+// #############################
+
+// The simulation works on a flat plane in meters. Latitudes and longitudes
+// are mapped onto it around an origin with an equirectangular projection,
+// plenty accurate over the few hundred kilometers a scenario spans.
+
+/// Mean Earth radius in meters
+pub const EARTH_RADIUS: f32 = 6_371_000.0;
+
+/// A position in decimal degrees, north and east positive
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct LatLon {
+    pub lat: f32,
+    pub lon: f32,
+}
+
+impl LatLon {
+    /// Position in meters east and north of `origin`
+    pub fn to_local(&self, origin: &LatLon) -> Point {
+        let k = EARTH_RADIUS * std::f32::consts::PI / 180.0;
+        Point {
+            x: (self.lon - origin.lon) * origin.lat.to_radians().cos() * k,
+            y: (self.lat - origin.lat) * k,
+        }
+    }
+
+    /// Inverse of `to_local`
+    pub fn from_local(point: &Point, origin: &LatLon) -> LatLon {
+        let k = EARTH_RADIUS * std::f32::consts::PI / 180.0;
+        LatLon {
+            lat: origin.lat + point.y / k,
+            lon: origin.lon + point.x / (k * origin.lat.to_radians().cos()),
+        }
+    }
+}
+
+impl fmt::Display for LatLon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4}, {:.4}", self.lat, self.lon)
+    }
+}
+
+/// Reads "lat, lon" in decimal degrees
+impl FromStr for LatLon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("expected 'lat, lon', found '{}'", s);
+        let (lat, lon) = s.split_once(',').ok_or_else(error)?;
+        let lat: f32 = lat.trim().parse().map_err(|_| error())?;
+        let lon: f32 = lon.trim().parse().map_err(|_| error())?;
+        // NaN compares false with anything, so is refused on its own
+        if !lat.is_finite() || !lon.is_finite() || lat.abs() > 90.0 || lon.abs() > 180.0 {
+            return Err(error());
+        }
+        Ok(LatLon { lat, lon })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_round_trip() {
+        let origin = LatLon {
+            lat: 60.0,
+            lon: -20.0,
+        };
+        let one_degree_east = LatLon {
+            lat: 60.0,
+            lon: -19.0,
+        };
+        let local = one_degree_east.to_local(&origin);
+        assert!((local.x - 55_597.0).abs() < 10.0);
+        assert!(local.y.abs() < 0.01);
+        let back = LatLon::from_local(&local, &origin);
+        assert!((back.lon + 19.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "50.5, -4.25".parse(),
+            Ok(LatLon {
+                lat: 50.5,
+                lon: -4.25
+            })
+        );
+        assert!("91, 0".parse::<LatLon>().is_err());
+        assert!("50.5".parse::<LatLon>().is_err());
+        assert!("NaN, 0".parse::<LatLon>().is_err());
+        assert!("0, NaN".parse::<LatLon>().is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError};
+use crate::contacts::ContactTable;
+use crate::intercept::Alert;
+use crate::sensors::passive_excess;
+use crate::world::{EntityId, EntityKind, World};
+
+// #############################
+// #     TIME COMPRESSION      #
+// #############################
+
+// A long transit is run compressed, "compress 32" stepping the world 32
+// times for every tick of the front end (see Simulation::run). So that it
+// does not end with the boat dead before the player could look up, the
+// governor drops back to 1x the moment something needs the captain:
+//
+// torpedo         a torpedo heard in the water
+// contact         a new contact
+// ping            a sonar or seeker pulse on the intercept receiver
+//
+// How easily each of them does is set in the player's profile:
+//
+// [governor]
+// torpedo = high          # off, low or high
+// contact = low
+// ping = high
+//
+// High drops for any of them; low only for a torpedo or a contact closer
+// than a few kilometers and a pulse strong enough to be classified.
+
+/// Steps per tick the player may ask for
+pub const MAX_COMPRESSION: u32 = 128;
+/// Meters within which a torpedo or a contact drops compression at low
+/// sensitivity
+const LOW_TORPEDO_RANGE: f32 = 5_000.0;
+const LOW_CONTACT_RANGE: f32 = 8_000.0;
+
+/// What drops the compression
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Trigger {
+    Torpedo,
+    Contact,
+    Ping,
+}
+
+impl Trigger {
+    pub const ALL: [Trigger; 3] = [Trigger::Torpedo, Trigger::Contact, Trigger::Ping];
+
+    /// Id of the reason in the message catalog
+    pub fn message(&self) -> &'static str {
+        match self {
+            Trigger::Torpedo => "governor-torpedo",
+            Trigger::Contact => "governor-contact",
+            Trigger::Ping => "governor-ping",
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Trigger::Torpedo => "torpedo",
+            Trigger::Contact => "contact",
+            Trigger::Ping => "ping",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Sensitivity {
+    Off,
+    Low,
+    High,
+}
+
+impl FromStr for Sensitivity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Sensitivity, String> {
+        match s {
+            "off" => Ok(Sensitivity::Off),
+            "low" => Ok(Sensitivity::Low),
+            "high" => Ok(Sensitivity::High),
+            _ => Err(format!("unknown sensitivity '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Governor {
+    /// World steps per tick of the front end
+    pub compression: u32,
+    pub torpedo: Sensitivity,
+    pub contact: Sensitivity,
+    pub ping: Sensitivity,
+    /// Torpedoes already heard, contacts and alerts already seen
+    torpedoes: Vec<EntityId>,
+    last_contact: u32,
+    alerts_seen: usize,
+}
+
+impl Default for Governor {
+    fn default() -> Governor {
+        Governor {
+            compression: 1,
+            torpedo: Sensitivity::High,
+            contact: Sensitivity::High,
+            ping: Sensitivity::High,
+            torpedoes: Vec::new(),
+            last_contact: 0,
+            alerts_seen: 0,
+        }
+    }
+}
+
+impl Governor {
+    /// Reads the sensitivities of a profile
+    pub fn read(config: &Config) -> Result<Governor, ConfigError> {
+        let mut governor = Governor::default();
+        if let Some(section) = config.section("governor") {
+            governor.torpedo = section.parse_or("torpedo", governor.torpedo)?;
+            governor.contact = section.parse_or("contact", governor.contact)?;
+            governor.ping = section.parse_or("ping", governor.ping)?;
+        }
+        Ok(governor)
+    }
+
+    fn sensitivity(&self, trigger: Trigger) -> Sensitivity {
+        match trigger {
+            Trigger::Torpedo => self.torpedo,
+            Trigger::Contact => self.contact,
+            Trigger::Ping => self.ping,
+        }
+    }
+
+    /// Sets the compression, within 1 and MAX_COMPRESSION
+    pub fn compress(&mut self, steps: u32) {
+        self.compression = steps.clamp(1, MAX_COMPRESSION);
+    }
+
+    /// Looks for what happened to `own` since the last check, dropping to
+    /// 1x and returning why when it needs the captain
+    pub fn check(
+        &mut self,
+        world: &World,
+        own: EntityId,
+        contacts: &ContactTable,
+        alerts: &[Alert],
+    ) -> Option<Trigger> {
+        let own = world.entity(own)?;
+        let mut triggered = Vec::new();
+        for torpedo in world.entities.iter() {
+            let theirs = torpedo
+                .torpedo
+                .as_ref()
+                .is_some_and(|t| t.shooter != own.id);
+            if torpedo.kind != EntityKind::Torpedo
+                || !theirs
+                || torpedo.is_destroyed()
+                || self.torpedoes.contains(&torpedo.id)
+                || !passive_excess(&world.environment, own, torpedo).is_some_and(|e| e > 0.0)
+            {
+                continue;
+            }
+            self.torpedoes.push(torpedo.id);
+            let close = own.position.distance_to(&torpedo.position) <= LOW_TORPEDO_RANGE;
+            triggered.push((Trigger::Torpedo, close));
+        }
+        for contact in contacts.contacts.iter() {
+            if contact.number <= self.last_contact {
+                continue;
+            }
+            self.last_contact = contact.number;
+            let close = contact
+                .range
+                .is_some_and(|(range, _)| range <= LOW_CONTACT_RANGE);
+            triggered.push((Trigger::Contact, close));
+        }
+        for alert in &alerts[self.alerts_seen.min(alerts.len())..] {
+            triggered.push((Trigger::Ping, alert.intercept.kind.is_some()));
+        }
+        self.alerts_seen = alerts.len();
+
+        if self.compression == 1 {
+            return None;
+        }
+        let trigger = Trigger::ALL.iter().copied().find(|trigger| {
+            triggered.iter().any(|(t, strong)| {
+                t == trigger
+                    && match self.sensitivity(*trigger) {
+                        Sensitivity::Off => false,
+                        Sensitivity::Low => *strong,
+                        Sensitivity::High => true,
+                    }
+            })
+        })?;
+        self.compression = 1;
+        Some(trigger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::torpedo;
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+    use crate::world::Entity;
+
+    /// The boat, and a torpedo fired at it from `range` meters east
+    fn waters(range: f32) -> (World, EntityId) {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        let boat = world.spawn(boat);
+        let mut enemy = Entity::new("S-1", EntityKind::Submarine, Point { x: range, y: 0.0 });
+        enemy.weapons = Some(WeaponsStation::new(1, PresetLibrary::new()));
+        let enemy = world.spawn(enemy);
+        torpedo::fire(&mut world, enemy, 1, std::f32::consts::PI).unwrap();
+        (world, boat)
+    }
+
+    #[test]
+    fn a_torpedo_drops_to_real_time() {
+        let (world, boat) = waters(3_000.0);
+        let contacts = ContactTable::default();
+        let mut governor = Governor::default();
+        governor.compress(1_000);
+        assert_eq!(governor.compression, MAX_COMPRESSION);
+        assert_eq!(
+            governor.check(&world, boat, &contacts, &[]),
+            Some(Trigger::Torpedo)
+        );
+        assert_eq!(governor.compression, 1);
+        // once only
+        governor.compress(32);
+        assert_eq!(governor.check(&world, boat, &contacts, &[]), None);
+        assert_eq!(governor.compression, 32);
+    }
+
+    #[test]
+    fn sensitivities_from_the_profile() {
+        let config = Config::parse("[governor]\ntorpedo = low\nping = off").unwrap();
+        let mut governor = Governor::read(&config).unwrap();
+        assert_eq!(governor.torpedo, Sensitivity::Low);
+        assert_eq!(governor.contact, Sensitivity::High);
+        assert_eq!(governor.ping, Sensitivity::Off);
+        // far off, low sensitivity lets it run
+        let (world, boat) = waters(7_000.0);
+        governor.compress(16);
+        assert_eq!(
+            governor.check(&world, boat, &ContactTable::default(), &[]),
+            None
+        );
+        assert_eq!(governor.compression, 16);
+        let mut high = Governor::default();
+        high.compress(16);
+        assert_eq!(
+            high.check(&world, boat, &ContactTable::default(), &[]),
+            Some(Trigger::Torpedo)
+        );
+
+        let bad = Config::parse("[governor]\ncontact = maybe").unwrap();
+        assert!(Governor::read(&bad).is_err());
+    }
+}
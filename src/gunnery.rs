@@ -0,0 +1,486 @@
+use std::fmt;
+
+use crate::atmosphere;
+use crate::command::GunCommand;
+use crate::events::Event;
+use crate::faction::Stance;
+use crate::identification::apparent_stance;
+use crate::messages::Catalog;
+use crate::seakeeping;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+/// Deepest keel depth, in meters, at which a raised periscope shows
+pub const PERISCOPE_DEPTH: f32 = 18.0;
+/// Roughest sea a submarine deck gun crew can work in
+pub const DECK_GUN_MAX_SEA_STATE: u8 = 3;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum GunError {
+    NoGun,
+    NotSurfaced,
+    SeaTooRough,
+    NotManned,
+    NoSuchTarget(EntityId),
+    NoTargetInSight,
+    /// The rules of engagement forbid firing on a ship that is not hostile
+    NotHostile(EntityId),
+}
+
+impl GunError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        let id = match self {
+            GunError::NoGun => "error-no-gun",
+            GunError::NotSurfaced => "error-not-surfaced",
+            GunError::SeaTooRough => "error-sea-too-rough",
+            GunError::NotManned => "error-not-manned",
+            GunError::NoSuchTarget(id) => {
+                return messages.format("error-no-such-target", &[("target", id)])
+            }
+            GunError::NoTargetInSight => "error-no-target-in-sight",
+            GunError::NotHostile(id) => {
+                return messages.format("error-not-hostile", &[("target", id)])
+            }
+        };
+        messages.get(id).to_string()
+    }
+}
+
+impl fmt::Display for GunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for GunError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Gun {
+    pub max_range: f32,
+    /// Seconds between rounds
+    pub reload_time: f32,
+    pub ammo: u32,
+    /// Fraction of the target hull destroyed by one hit
+    pub damage: f32,
+    /// Seconds until the next round can be fired
+    pub cooldown: f32,
+    /// A crew is on the gun
+    pub manned: bool,
+    /// Chosen target; escorts pick the nearest submarine when unset
+    pub target: Option<EntityId>,
+}
+
+impl Gun {
+    /// A typical escort main gun
+    pub fn escort() -> Gun {
+        Gun {
+            max_range: 8000.0,
+            reload_time: 6.0,
+            ammo: 200,
+            damage: 0.15,
+            cooldown: 0.0,
+            manned: true,
+            target: None,
+        }
+    }
+
+    /// A WWII submarine deck gun, secured until the crew is sent up
+    pub fn deck_gun() -> Gun {
+        Gun {
+            max_range: 6000.0,
+            reload_time: 4.0,
+            ammo: 220,
+            damage: 0.04,
+            cooldown: 0.0,
+            manned: false,
+            target: None,
+        }
+    }
+
+    pub fn can_fire(&self) -> bool {
+        self.manned && self.ammo > 0 && self.cooldown <= 0.0
+    }
+}
+
+/// How much of the target shows above the water, 0 to 1
+pub fn exposure(target: &Entity) -> f32 {
+    if target.is_surfaced() {
+        1.0
+    } else if seakeeping::broached(target) {
+        0.5
+    } else if target.depth <= PERISCOPE_DEPTH
+        && (target.mast_raised || atmosphere::snorkeling(target))
+    {
+        0.05
+    } else {
+        0.0
+    }
+}
+
+/// Chance of one round hitting; falls off with range and a rolling gun
+/// platform (steadiness 1 when steady, see seakeeping.rs)
+pub fn hit_probability(range: f32, max_range: f32, steadiness: f32, exposure: f32) -> f32 {
+    if range > max_range {
+        return 0.0;
+    }
+    let range_factor = 1.0 - 0.9 * range / max_range;
+    0.4 * range_factor * steadiness * exposure
+}
+
+/// How far the gun of `shooter` can engage: its range, or as far as the
+/// lookouts see
+fn gun_reach(world: &World, shooter: &Entity, gun: &Gun) -> f32 {
+    gun.max_range
+        .min(seakeeping::sighting_range(shooter, &world.environment))
+}
+
+/// Whether a submarine can work its deck gun right now
+fn check_deck_gun(world: &World, boat: &Entity) -> Result<(), GunError> {
+    if !boat.is_surfaced() || boat.transition.is_some() {
+        return Err(GunError::NotSurfaced);
+    }
+    if world.environment.sea_state > DECK_GUN_MAX_SEA_STATE {
+        return Err(GunError::SeaTooRough);
+    }
+    Ok(())
+}
+
+/// Returns (id, range, exposure) of the closest entity matching `wanted`
+/// that `shooter` can see and reach
+fn nearest_target<F>(
+    world: &World,
+    shooter: &Entity,
+    reach: f32,
+    wanted: F,
+) -> Option<(EntityId, f32, f32)>
+where
+    F: Fn(&Entity) -> bool,
+{
+    world
+        .entities
+        .in_area(&shooter.position, reach)
+        .filter(|e| e.id != shooter.id && !e.is_destroyed() && wanted(e))
+        .map(|e| (e.id, shooter.position.distance_to(&e.position), exposure(e)))
+        .filter(|&(_, _, exposure)| exposure > 0.0)
+        .fold(
+            None,
+            |best: Option<(EntityId, f32, f32)>, candidate| match best {
+                Some(b) if b.1 <= candidate.1 => best,
+                _ => Some(candidate),
+            },
+        )
+}
+
+fn is_surface_ship(entity: &Entity) -> bool {
+    matches!(entity.kind, EntityKind::Warship | EntityKind::Merchant)
+}
+
+/// Applies a player gun order to the deck gun of `boat`
+pub fn execute(world: &mut World, boat: EntityId, command: &GunCommand) -> Result<(), GunError> {
+    let entity = world.entity(boat).ok_or(GunError::NoGun)?;
+    let gun = entity.gun.as_ref().ok_or(GunError::NoGun)?;
+    if matches!(command, GunCommand::Target(_)) && !gun.manned {
+        return Err(GunError::NotManned);
+    }
+    let target = match command {
+        GunCommand::Man => {
+            check_deck_gun(world, entity)?;
+            None
+        }
+        GunCommand::Secure => None,
+        GunCommand::Target(Some(id)) => {
+            let target = world
+                .entity(*id)
+                .filter(|t| t.id != boat && !t.is_destroyed())
+                .ok_or(GunError::NoSuchTarget(*id))?;
+            if apparent_stance(world, entity, target) != Stance::Hostile {
+                return Err(GunError::NotHostile(*id));
+            }
+            let range = entity.position.distance_to(&target.position);
+            // nothing to lay the gun on while the target is under
+            if range > gun_reach(world, entity, gun) || exposure(target) <= 0.0 {
+                return Err(GunError::NoTargetInSight);
+            }
+            Some(*id)
+        }
+        GunCommand::Target(None) => {
+            let reach = gun_reach(world, entity, gun);
+            let wanted = |e: &Entity| {
+                is_surface_ship(e) && apparent_stance(world, entity, e) == Stance::Hostile
+            };
+            let (id, _, _) =
+                nearest_target(world, entity, reach, wanted).ok_or(GunError::NoTargetInSight)?;
+            Some(id)
+        }
+    };
+    let gun = world
+        .entity_mut(boat)
+        .and_then(|e| e.gun.as_mut())
+        .ok_or(GunError::NoGun)?;
+    match command {
+        GunCommand::Man => gun.manned = true,
+        GunCommand::Secure => gun.manned = false,
+        GunCommand::Target(_) => gun.target = target,
+    }
+    Ok(())
+}
+
+/// Chooses what `shooter` fires at this tick, as (target, range, exposure)
+fn choose_target(world: &World, shooter: &Entity, gun: &Gun) -> Option<(EntityId, f32, f32)> {
+    let reach = gun_reach(world, shooter, gun);
+    match shooter.kind {
+        // boats held on radar are engaged as far as the gun reaches
+        EntityKind::Warship => nearest_target(world, shooter, gun.max_range, |e| {
+            let seen = shooter.position.distance_to(&e.position) <= reach
+                || shooter.radar_contacts.contains(&e.id);
+            e.kind == EntityKind::Submarine
+                && seen
+                && apparent_stance(world, shooter, e) == Stance::Hostile
+        }),
+        EntityKind::Submarine => {
+            check_deck_gun(world, shooter).ok()?;
+            let target = world.entity(gun.target?)?;
+            let range = shooter.position.distance_to(&target.position);
+            if target.is_destroyed() || range > reach {
+                return None;
+            }
+            Some((target.id, range, exposure(target)))
+        }
+        _ => None,
+    }
+}
+
+/// Works every manned gun: escorts engage the nearest submarine in sight
+/// or on radar,
+/// submarines fire at the target the player picked
+pub fn update(world: &mut World, dt: f32) {
+    let mut shots = Vec::new();
+    let mut secured = Vec::new();
+    for shooter in world.entities.iter_mut() {
+        if let Some(gun) = shooter.gun.as_mut() {
+            gun.cooldown = (gun.cooldown - dt).max(0.0);
+        }
+    }
+    for shooter in world.entities.iter() {
+        if shooter.is_destroyed() {
+            continue;
+        }
+        let gun = match &shooter.gun {
+            Some(gun) => gun,
+            None => continue,
+        };
+        if shooter.kind == EntityKind::Submarine
+            && gun.manned
+            && check_deck_gun(world, shooter).is_err()
+        {
+            secured.push(shooter.id);
+            continue;
+        }
+        if !gun.can_fire() {
+            continue;
+        }
+        if let Some((target, range, exposure)) = choose_target(world, shooter, gun) {
+            let steadiness = seakeeping::motion(shooter, &world.environment).steadiness();
+            let p = hit_probability(range, gun.max_range, steadiness, exposure);
+            shots.push((shooter.id, target, p, gun.damage));
+        }
+    }
+    for id in secured {
+        if let Some(gun) = world.entity_mut(id).and_then(|e| e.gun.as_mut()) {
+            gun.manned = false;
+            gun.target = None;
+        }
+    }
+    for (shooter, target, p, damage) in shots {
+        if let Some(gun) = world.entity_mut(shooter).and_then(|e| e.gun.as_mut()) {
+            gun.ammo -= 1;
+            gun.cooldown = gun.reload_time;
+        }
+        world.emit(Event::GunFired { shooter, target });
+        if world.rng.chance(p) {
+            world.emit(Event::ShellHit {
+                shooter,
+                target,
+                damage,
+            });
+            world.apply_damage(target, damage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+
+    fn duel(sub_depth: f32, mast_raised: bool, range: f32) -> World {
+        let mut world = World::new();
+        let mut escort = Entity::new("escort", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.gun = Some(Gun::escort());
+        world.spawn(escort);
+        let mut sub = Entity::new("sub", EntityKind::Submarine, Point { x: range, y: 0.0 });
+        sub.depth = sub_depth;
+        sub.mast_raised = mast_raised;
+        world.spawn(sub);
+        world
+    }
+
+    fn shots_fired(world: &World) -> usize {
+        world
+            .events
+            .iter()
+            .filter(|e| matches!(e.event, Event::GunFired { .. }))
+            .count()
+    }
+
+    #[test]
+    fn hit_probability1() {
+        let close = hit_probability(500.0, 8000.0, 1.0, 1.0);
+        let far = hit_probability(7000.0, 8000.0, 1.0, 1.0);
+        let rough = hit_probability(500.0, 8000.0, 0.4, 1.0);
+        assert!(close > far);
+        assert!(close > rough);
+        assert_eq!(hit_probability(9000.0, 8000.0, 1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn engages_surfaced_sub() {
+        let mut world = duel(0.0, false, 1500.0);
+        for _ in 0..120 {
+            world.step(1.0);
+        }
+        assert!(shots_fired(&world) > 1);
+        assert!(world.entities[1].hull < 1.0);
+    }
+
+    #[test]
+    fn ignores_submerged_sub() {
+        let mut world = duel(40.0, true, 1500.0);
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        assert_eq!(shots_fired(&world), 0);
+    }
+
+    #[test]
+    fn engages_periscope() {
+        let mut world = duel(PERISCOPE_DEPTH, true, 1500.0);
+        world.step(1.0);
+        assert_eq!(shots_fired(&world), 1);
+    }
+
+    fn gun_attack(sea_state: u8) -> (World, EntityId, EntityId) {
+        let mut world = World::new();
+        world.environment.sea_state = sea_state;
+        let mut sub = Entity::new("sub", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        sub.gun = Some(Gun::deck_gun());
+        let sub = world.spawn(sub);
+        let merchant = Entity::new(
+            "merchant",
+            EntityKind::Merchant,
+            Point { x: 1000.0, y: 0.0 },
+        );
+        let merchant = world.spawn(merchant);
+        (world, sub, merchant)
+    }
+
+    #[test]
+    fn deck_gun_attack() {
+        let (mut world, sub, merchant) = gun_attack(1);
+        world.step(1.0);
+        assert_eq!(shots_fired(&world), 0);
+        execute(&mut world, sub, &GunCommand::Man).unwrap();
+        execute(&mut world, sub, &GunCommand::Target(None)).unwrap();
+        assert_eq!(
+            world.entity(sub).unwrap().gun.as_ref().unwrap().target,
+            Some(merchant)
+        );
+        for _ in 0..40 {
+            world.step(1.0);
+        }
+        assert_eq!(shots_fired(&world), 10);
+        assert_eq!(world.entity(sub).unwrap().gun.as_ref().unwrap().ammo, 210);
+        assert!(world.entity(merchant).unwrap().hull < 1.0);
+    }
+
+    #[test]
+    fn holds_fire_on_neutrals() {
+        let (mut world, sub, merchant) = gun_attack(1);
+        world.entity_mut(sub).unwrap().side = Some("axis".to_string());
+        world.entity_mut(merchant).unwrap().side = Some("usa".to_string());
+        world.diplomacy.set("axis", "usa", Stance::Neutral);
+        execute(&mut world, sub, &GunCommand::Man).unwrap();
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(None)),
+            Err(GunError::NoTargetInSight)
+        );
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(Some(merchant))),
+            Err(GunError::NotHostile(merchant))
+        );
+        world.diplomacy.set("axis", "usa", Stance::Hostile);
+        execute(&mut world, sub, &GunCommand::Target(Some(merchant))).unwrap();
+    }
+
+    #[test]
+    fn deck_gun_restrictions() {
+        let (mut world, sub, merchant) = gun_attack(5);
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Man),
+            Err(GunError::SeaTooRough)
+        );
+        world.environment.sea_state = 2;
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(None)),
+            Err(GunError::NotManned)
+        );
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(Some(merchant))),
+            Err(GunError::NotManned)
+        );
+        execute(&mut world, sub, &GunCommand::Man).unwrap();
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(Some(99))),
+            Err(GunError::NoSuchTarget(99))
+        );
+        // nor can the gun be laid on the boat it stands on
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(Some(sub))),
+            Err(GunError::NoSuchTarget(sub))
+        );
+        // nor on a boat under the water, however close
+        let mut submerged = Entity::new("u", EntityKind::Submarine, Point { x: 500.0, y: 0.0 });
+        submerged.depth = 50.0;
+        let submerged = world.spawn(submerged);
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Target(Some(submerged))),
+            Err(GunError::NoTargetInSight)
+        );
+        world.entity_mut(submerged).unwrap().depth = 0.0;
+        execute(&mut world, sub, &GunCommand::Target(Some(submerged))).unwrap();
+        world.entity_mut(sub).unwrap().depth = 30.0;
+        assert_eq!(
+            execute(&mut world, sub, &GunCommand::Man),
+            Err(GunError::NotSurfaced)
+        );
+    }
+
+    #[test]
+    fn deck_gun_secured_when_diving() {
+        let (mut world, sub, _) = gun_attack(1);
+        execute(&mut world, sub, &GunCommand::Man).unwrap();
+        execute(&mut world, sub, &GunCommand::Target(None)).unwrap();
+        world.entity_mut(sub).unwrap().depth = 15.0;
+        world.step(1.0);
+        assert_eq!(shots_fired(&world), 0);
+        assert!(!world.entity(sub).unwrap().gun.as_ref().unwrap().manned);
+    }
+
+    #[test]
+    fn limited_by_visibility() {
+        let mut world = duel(0.0, false, 5000.0);
+        world.environment.visibility = 2000.0;
+        world.step(1.0);
+        assert_eq!(shots_fired(&world), 0);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::config::{Config, ConfigError};
+use crate::events::Event;
+use crate::gunnery::{self, PERISCOPE_DEPTH};
+use crate::physics::{user_to_game_angle, Point};
+use crate::random::Rng;
+use crate::seakeeping;
+use crate::sensors::SensorKind;
+use crate::world::{Entity, EntityKind, World};
+
+// #############################
+// #     DRIFTING HAZARDS      #
+// #############################
+
+// Waters fought over are littered: mines torn from their moorings and the
+// wreckage of sunk ships drift on the surface, set along by the current. A
+// scenario scatters a few of them over a box of the map:
+//
+// [hazards]
+// mines = 3               # drifting mines
+// debris = 12             # pieces of floating wreckage
+// area = -20000 -20000 20000 20000   # west south east north, meters
+// current = 0.3           # m/s
+// current_set = 45        # degrees, where the current sets towards
+//
+// A ship on the surface running onto one is holed, badly by a mine, a
+// little by wreckage; a boat at periscope depth with a mast raised has it
+// struck instead. Only the lookouts and the periscope find them, at short
+// range, so a surface transit in a war zone is best made with them up.
+//
+// What drifts onto a shoal grounds there: mostly buried for good in mud,
+// sometimes in sand, and left lying where it stranded on rock, where it
+// is still struck (see seafloor.rs).
+
+/// Meters of water in which a hazard drifting in grounds
+const GROUNDING: f32 = 2.0;
+/// Meters a ship must come within to strike a hazard
+const STRIKE_RANGE: f32 = 15.0;
+/// Hull a mine and wreckage take off a ship on the surface
+const MINE_DAMAGE: f32 = 0.6;
+const DEBRIS_DAMAGE: f32 = 0.05;
+/// Health they take off a raised mast struck
+const MINE_MAST_DAMAGE: f32 = 1.0;
+const DEBRIS_MAST_DAMAGE: f32 = 0.5;
+/// Meters at which a mine and wreckage are sighted in clear weather
+const MINE_SIGHTING: f32 = 600.0;
+const DEBRIS_SIGHTING: f32 = 400.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HazardKind {
+    Mine,
+    Debris,
+}
+
+impl HazardKind {
+    /// Id of its name in the messages
+    pub fn message(&self) -> &'static str {
+        match self {
+            HazardKind::Mine => "hazard-mine",
+            HazardKind::Debris => "hazard-debris",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hazard {
+    /// Unique within the world, for those keeping track of the sighted
+    pub id: u32,
+    pub kind: HazardKind,
+    pub position: Point,
+    /// Stranded on a shoal, no longer drifting
+    pub grounded: bool,
+}
+
+/// How many hazards a scenario scatters, where and how they drift
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Hazards {
+    pub mines: u32,
+    pub debris: u32,
+    /// West, south, east and north edges of where they are scattered
+    pub area: [f32; 4],
+    /// Meters per second the current sets them along by
+    pub current: Point,
+}
+
+impl Hazards {
+    /// Reads the "[hazards]" section, none without one
+    pub fn read(config: &Config) -> Result<Hazards, ConfigError> {
+        let section = match config.section("hazards") {
+            Some(section) => section,
+            None => return Ok(Hazards::default()),
+        };
+        let value: String = section.parse("area")?;
+        let edges: Vec<f32> = value
+            .split_whitespace()
+            .filter_map(|w| w.parse().ok())
+            .collect();
+        let area = match edges.as_slice() {
+            [west, south, east, north] if west < east && south < north => {
+                [*west, *south, *east, *north]
+            }
+            _ => {
+                return Err(ConfigError::Invalid {
+                    section: "hazards".to_string(),
+                    key: "area".to_string(),
+                    value,
+                })
+            }
+        };
+        let speed: f32 = section.parse_or("current", 0.0)?;
+        let set = user_to_game_angle(section.parse_or("current_set", 0.0)?);
+        Ok(Hazards {
+            mines: section.parse_or("mines", 0)?,
+            debris: section.parse_or("debris", 0)?,
+            area,
+            current: Point {
+                x: speed * set.cos(),
+                y: speed * set.sin(),
+            },
+        })
+    }
+
+    /// Puts the hazards in the water at random over the area
+    pub fn scatter(&self, rng: &mut Rng) -> Vec<Hazard> {
+        let kinds = (0..self.mines)
+            .map(|_| HazardKind::Mine)
+            .chain((0..self.debris).map(|_| HazardKind::Debris));
+        kinds
+            .enumerate()
+            .map(|(i, kind)| Hazard {
+                id: i as u32,
+                kind,
+                position: Point {
+                    x: rng.range(self.area[0], self.area[2]),
+                    y: rng.range(self.area[1], self.area[3]),
+                },
+                grounded: false,
+            })
+            .collect()
+    }
+}
+
+/// Whether `entity` at its depth runs onto what floats, hull or mast
+fn exposed(entity: &Entity) -> bool {
+    entity.kind != EntityKind::Torpedo
+        && (entity.is_surfaced() || (entity.depth <= PERISCOPE_DEPTH && entity.mast_raised))
+}
+
+/// Meters at which the lookouts or the periscope of `entity` sight a
+/// hazard, 0 when neither is up
+pub fn sighting_range(world: &World, entity: &Entity, kind: HazardKind) -> f32 {
+    if gunnery::exposure(entity) <= 0.0 {
+        return 0.0;
+    }
+    let clear = match kind {
+        HazardKind::Mine => MINE_SIGHTING,
+        HazardKind::Debris => DEBRIS_SIGHTING,
+    };
+    clear.min(seakeeping::sighting_range(entity, &world.environment))
+}
+
+/// Strands the hazards drifted onto a shoal, where soft bottom may bury
+/// them for good
+fn ground(world: &mut World) {
+    let mut hazards = std::mem::take(&mut world.hazards);
+    hazards.retain_mut(|hazard| {
+        let shoal = world
+            .water_depth(&hazard.position)
+            .is_some_and(|water| water < GROUNDING);
+        if hazard.grounded || !shoal {
+            return true;
+        }
+        hazard.grounded = true;
+        let bottom = world.environment.seafloor.at(&hazard.position).bottom;
+        !world.rng.chance(bottom.burial())
+    });
+    world.hazards = hazards;
+}
+
+/// Drifts the hazards on the current and sets off those run onto
+pub fn update(world: &mut World, dt: f32) {
+    let current = world.current.clone();
+    let mut struck = Vec::new();
+    for hazard in world.hazards.iter_mut().filter(|h| !h.grounded) {
+        hazard.position.x += current.x * dt;
+        hazard.position.y += current.y * dt;
+    }
+    ground(world);
+    let entities = &world.entities;
+    world.hazards.retain(|hazard| {
+        let victim = entities.iter().find(|e| {
+            exposed(e)
+                && !e.is_destroyed()
+                && e.position.distance_to(&hazard.position) < STRIKE_RANGE + e.length() / 2.0
+        });
+        match victim {
+            Some(victim) => {
+                struck.push((victim.id, victim.is_surfaced(), hazard.kind));
+                false
+            }
+            None => true,
+        }
+    });
+    for (id, surfaced, kind) in struck {
+        world.emit(Event::HazardStruck { entity: id, kind });
+        if surfaced {
+            let damage = match kind {
+                HazardKind::Mine => MINE_DAMAGE,
+                HazardKind::Debris => DEBRIS_DAMAGE,
+            };
+            world.apply_damage(id, damage);
+        } else if let Some(boat) = world.entity_mut(id) {
+            let damage = match kind {
+                HazardKind::Mine => MINE_MAST_DAMAGE,
+                HazardKind::Debris => DEBRIS_MAST_DAMAGE,
+            };
+            for sensor in boat.sensors.iter_mut() {
+                if sensor.kind == SensorKind::Periscope {
+                    sensor.health = (sensor.health - damage).max(0.0);
+                }
+            }
+            boat.mast_raised = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seafloor::{Bottom, Seafloor};
+    use crate::sensors::Sensor;
+    use crate::zone::{Zone, ZoneKind};
+
+    fn world(kind: HazardKind, at: Point) -> World {
+        let mut world = World::new();
+        let config =
+            Config::parse("[hazards]\narea = 0 0 1 1\ncurrent = 1\ncurrent_set = 90").unwrap();
+        world.current = Hazards::read(&config).unwrap().current;
+        world.hazards.push(Hazard {
+            id: 0,
+            kind,
+            position: at,
+            grounded: false,
+        });
+        world
+    }
+
+    #[test]
+    fn hazards_drift_and_scatter() {
+        let mut world = world(HazardKind::Debris, Point { x: 0.0, y: 0.0 });
+        for _ in 0..10 {
+            world.step(1.0);
+        }
+        let drifted = &world.hazards[0].position;
+        assert!((drifted.x - 10.0).abs() < 0.01 && drifted.y.abs() < 0.01);
+
+        let config = Config::parse("[hazards]\nmines = 2\ndebris = 3\narea = 0 0 100 50").unwrap();
+        let hazards = Hazards::read(&config).unwrap();
+        let scattered = hazards.scatter(&mut Rng::new(7));
+        assert_eq!(scattered.len(), 5);
+        assert_eq!(scattered[1].kind, HazardKind::Mine);
+        assert!(scattered.iter().all(|h| h.position.y <= 50.0));
+        let bad = Config::parse("[hazards]\narea = 100 0 0 50").unwrap();
+        assert!(Hazards::read(&bad).is_err());
+    }
+
+    #[test]
+    fn ships_on_the_surface_strike_them() {
+        let mut world = world(HazardKind::Mine, Point { x: 100.0, y: 0.0 });
+        let mut ship = Entity::new("SS Test", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        ship.speed = 5.0;
+        let ship = world.spawn(ship);
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 500.0 });
+        boat.depth = PERISCOPE_DEPTH;
+        boat.mast_raised = true;
+        boat.sensors.push(Sensor::new(SensorKind::Periscope));
+        let boat = world.spawn(boat);
+        assert!(sighting_range(&world, world.entity(boat).unwrap(), HazardKind::Mine) > 0.0);
+        for _ in 0..30 {
+            world.step(1.0);
+        }
+        assert!(world.hazards.is_empty());
+        assert_eq!(world.entity(ship).unwrap().hull, 1.0 - MINE_DAMAGE);
+
+        world.hazards.push(Hazard {
+            id: 1,
+            kind: HazardKind::Debris,
+            position: Point { x: 0.0, y: 500.0 },
+            grounded: false,
+        });
+        world.step(1.0);
+        let boat = world.entity(boat).unwrap();
+        assert_eq!(boat.hull, 1.0);
+        assert_eq!(boat.sensors[0].health, 0.5);
+        assert!(!boat.mast_raised);
+    }
+
+    #[test]
+    fn stranded_on_shoals() {
+        let strand = |bottom: Bottom| {
+            let mut world = world(HazardKind::Mine, Point { x: 0.0, y: 0.0 });
+            for id in 1..20 {
+                world.hazards.push(Hazard {
+                    id,
+                    kind: HazardKind::Mine,
+                    position: Point {
+                        x: 0.0,
+                        y: id as f32 * 10.0,
+                    },
+                    grounded: false,
+                });
+            }
+            world.zones.push(Zone {
+                name: "shoal".to_string(),
+                kind: ZoneKind::Shallow,
+                points: vec![
+                    Point { x: 5.0, y: -100.0 },
+                    Point {
+                        x: 1000.0,
+                        y: -100.0,
+                    },
+                    Point {
+                        x: 1000.0,
+                        y: 400.0,
+                    },
+                    Point { x: 5.0, y: 400.0 },
+                ],
+                depth: Some(1.0),
+                bottom: Some(bottom),
+            });
+            world.environment.seafloor = Seafloor::chart(&world.zones, Bottom::Sand);
+            for _ in 0..10 {
+                world.step(1.0);
+            }
+            world.hazards
+        };
+        // rock leaves them all lying where they grounded
+        let rock = strand(Bottom::Rock);
+        assert_eq!(rock.len(), 20);
+        assert!(rock.iter().all(|h| h.grounded && h.position.x < 10.0));
+        let mud = strand(Bottom::Mud);
+        assert!(mud.len() < 10, "{}", mud.len());
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt;
+
+use crate::asw::PING_INTERVAL;
+use crate::command::HeloCommand;
+use crate::events::Event;
+use crate::intercept::{Emission, EmissionKind};
+use crate::messages::Catalog;
+use crate::physics::{Point, KNOT};
+use crate::pulse::Pulse;
+use crate::reliability::Reliability;
+use crate::seeker::SeekerGeneration;
+use crate::sensors::{echo_excess, Sensor, SensorKind};
+use crate::torpedo;
+use crate::weapons::{Guidance, SearchPattern, SpeedSetting, TorpedoSettings};
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #        HELICOPTERS        #
+// #############################
+
+// An escort may carry a helicopter, hunting ahead of it with a sonar it
+// lowers into the sea on a cable and with light homing torpedoes:
+//
+// [class.leander]
+// kind = warship
+// helicopter = true
+// helicopter_endurance = 120   # optional, minutes of flight on full tanks
+// helicopter_torpedoes = 2     # optional
+//
+// The player of the escort tasks it with
+//
+// helo goto <x> <y>            # fly to a point and hover there
+// helo dip <x> <y> [depth]     # dip the sonar there, to 90 m unless set
+// helo attack [<x> <y>]        # drop a torpedo on the datum of the last
+//                              # dip, or on a point
+// helo recover                 # fly back and land on the escort
+//
+// A dip takes a minute to lower the sonar, three of pinging and listening
+// and one more to raise it, hovering all the while; the crew sends short
+// pulses in shallow water, long ones in deep water (see pulse.rs). Under the
+// layer, the sonar finds a boat the hull sonar of the escort cannot; the
+// boat hears its pulses too and runs (see ai.rs). The dip reports the
+// boat it placed closest, which becomes the datum an attack drops on. The
+// tanks run down while airborne and are filled again on deck. Down to
+// what it takes to fly home with a margin, the helicopter breaks off its
+// task and returns by itself; out of fuel, it ditches and is lost.
+
+/// Meters per second in forward flight
+const SPEED: f32 = 90.0 * KNOT;
+/// Minutes of flight on full tanks, unless set
+pub const ENDURANCE: f32 = 120.0;
+/// Torpedoes carried, unless set
+pub const TORPEDOES: u32 = 2;
+/// Seconds on deck to fill empty tanks
+const REFUEL_TIME: f32 = 900.0;
+/// Seconds of flight kept in hand over the flight home
+const RESERVE: f32 = 300.0;
+/// Meters from where it flies to at which it is there
+const ARRIVED: f32 = 50.0;
+/// Depth the sonar is lowered to, unless set
+pub const DIP_DEPTH: f32 = 90.0;
+/// Seconds to lower the sonar, the end of listening, and to raise it again
+const LOWERED: f32 = 60.0;
+const LISTENED: f32 = 240.0;
+const RAISED: f32 = 300.0;
+/// Meters the torpedo dropped runs before its seeker is enabled
+const ENABLE_RUN: f32 = 100.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HeloError {
+    NoHelicopter,
+    /// No dip has placed a boat to attack
+    NoDatum,
+    NoTorpedoes,
+    OnDeck,
+}
+
+impl HeloError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        let id = match self {
+            HeloError::NoHelicopter => "error-no-helicopter",
+            HeloError::NoDatum => "error-no-datum",
+            HeloError::NoTorpedoes => "error-helo-no-torpedoes",
+            HeloError::OnDeck => "error-helo-on-deck",
+        };
+        messages.get(id).to_string()
+    }
+}
+
+impl fmt::Display for HeloError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for HeloError {}
+
+/// What the helicopter is about
+#[derive(Debug, PartialEq, Clone)]
+pub enum Task {
+    /// On deck, filling its tanks
+    OnDeck,
+    Hover(Point),
+    /// Dip the sonar at a point, lowered to a depth
+    Dip {
+        at: Point,
+        depth: f32,
+    },
+    /// Drop a torpedo on a point, running at a depth
+    Attack {
+        at: Point,
+        depth: f32,
+    },
+    /// Fly back and land on the escort
+    Recover,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Helicopter {
+    pub position: Point,
+    /// Game angle it last flew on
+    pub heading: f32,
+    pub task: Task,
+    /// Seconds of flight left in the tanks
+    pub fuel: f32,
+    /// Seconds of flight on full tanks
+    pub endurance: f32,
+    pub torpedoes: u32,
+    /// Seconds into the dip under way
+    pub dip: Option<f32>,
+    /// Boat placed closest by the dip under way, with its signal excess
+    pub placed: Option<(Point, f32)>,
+    /// Where and at what depth the last dip placed a boat
+    pub datum: Option<(Point, f32)>,
+}
+
+impl Helicopter {
+    /// A helicopter on deck at `position`, tanks full
+    pub fn new(position: Point, endurance: f32, torpedoes: u32) -> Helicopter {
+        Helicopter {
+            position,
+            heading: 0.0,
+            task: Task::OnDeck,
+            fuel: endurance,
+            endurance,
+            torpedoes,
+            dip: None,
+            placed: None,
+            datum: None,
+        }
+    }
+
+    pub fn is_airborne(&self) -> bool {
+        self.task != Task::OnDeck
+    }
+
+    /// Whether it has only enough fuel left to fly `home` meters back
+    fn bingo(&self, home: f32) -> bool {
+        self.fuel <= home / SPEED + RESERVE
+    }
+
+    /// Flies towards `to` for `dt` seconds, returning whether it is there
+    fn fly(&mut self, to: &Point, dt: f32) -> bool {
+        let distance = self.position.distance_to(to);
+        if distance <= ARRIVED {
+            return true;
+        }
+        self.heading = self.position.angle_to(to);
+        let step = (SPEED * dt).min(distance);
+        self.position.x += step * self.heading.cos();
+        self.position.y += step * self.heading.sin();
+        distance - step <= ARRIVED
+    }
+}
+
+/// Tasks the helicopter of `escort`
+pub fn execute(
+    world: &mut World,
+    escort: EntityId,
+    command: &HeloCommand,
+) -> Result<(), HeloError> {
+    let helicopter = world
+        .entity_mut(escort)
+        .and_then(|e| e.asw.as_mut())
+        .and_then(|s| s.helicopter.as_mut())
+        .ok_or(HeloError::NoHelicopter)?;
+    let task = match command {
+        HeloCommand::GoTo { x, y } => Task::Hover(Point { x: x.0, y: y.0 }),
+        HeloCommand::Dip { x, y, depth } => Task::Dip {
+            at: Point { x: x.0, y: y.0 },
+            depth: depth.map_or(DIP_DEPTH, |d| d.0),
+        },
+        HeloCommand::Attack(at) => {
+            if helicopter.torpedoes == 0 {
+                return Err(HeloError::NoTorpedoes);
+            }
+            let (at, depth) = match at {
+                Some((x, y)) => (Point { x: x.0, y: y.0 }, DIP_DEPTH),
+                None => helicopter.datum.clone().ok_or(HeloError::NoDatum)?,
+            };
+            Task::Attack { at, depth }
+        }
+        HeloCommand::Recover if !helicopter.is_airborne() => return Err(HeloError::OnDeck),
+        HeloCommand::Recover => Task::Recover,
+    };
+    helicopter.task = task;
+    helicopter.dip = None;
+    helicopter.placed = None;
+    Ok(())
+}
+
+/// The sonar lowered at `at` to `depth`, as the sensors see it
+fn dipping_sonar(at: &Point, depth: f32) -> Entity {
+    let mut sonar = Entity::new("dipping sonar", EntityKind::Submarine, at.clone());
+    sonar.depth = depth;
+    sonar.sensors.push(Sensor::new(SensorKind::HullSonar));
+    sonar
+}
+
+/// The boat the echoes of `pulse` sent at `at` and `depth` place best,
+/// with the signal excess of its echo
+fn listen(world: &World, at: &Point, depth: f32, pulse: &Pulse) -> Option<(Point, f32)> {
+    let sonar = dipping_sonar(at, depth);
+    let level = EmissionKind::DippingSonar.source_level();
+    world
+        .entities
+        .iter()
+        .filter(|e| e.kind == EntityKind::Submarine && !e.is_destroyed())
+        .filter_map(|e| {
+            let excess = echo_excess(&world.environment, &sonar, e, level, pulse)?;
+            (excess > 0.0).then(|| (e.position.clone(), excess))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Flies one helicopter of `owner` at `ship` for `dt` seconds
+fn update_one(world: &mut World, owner: EntityId, ship: &Point, helo: &mut Helicopter, dt: f32) {
+    if !helo.is_airborne() {
+        helo.position = ship.clone();
+        helo.fuel = (helo.fuel + helo.endurance / REFUEL_TIME * dt).min(helo.endurance);
+        return;
+    }
+    helo.fuel -= dt;
+    let home = helo.position.distance_to(ship);
+    if helo.task != Task::Recover && helo.bingo(home) {
+        helo.task = Task::Recover;
+        helo.dip = None;
+        world.emit(Event::HelicopterBingo { owner });
+    }
+    match helo.task.clone() {
+        Task::OnDeck => {}
+        Task::Hover(at) => {
+            helo.fly(&at, dt);
+        }
+        Task::Dip { at, depth } => {
+            if helo.dip.is_none() && !helo.fly(&at, dt) {
+                return;
+            }
+            let before = helo.dip.unwrap_or(0.0);
+            let t = before + dt;
+            helo.dip = Some(t);
+            let pulses = |s: f32| ((s - LOWERED) / PING_INTERVAL).floor();
+            if t > LOWERED && before < LISTENED && pulses(t) > pulses(before) {
+                let pulse = Pulse::suited(&world.environment, &helo.position);
+                world.emissions.push(Emission {
+                    source: owner,
+                    kind: EmissionKind::DippingSonar,
+                    position: helo.position.clone(),
+                    depth,
+                    heading: 0.0,
+                    pulse,
+                });
+                if let Some((position, excess)) = listen(world, &helo.position, depth, &pulse) {
+                    if helo.placed.as_ref().is_none_or(|p| excess > p.1) {
+                        helo.placed = Some((position, excess));
+                    }
+                }
+            }
+            if before < LISTENED && t >= LISTENED {
+                let datum = helo.placed.take().map(|(position, _)| position);
+                helo.datum = datum.clone().map(|p| (p, depth));
+                world.emit(Event::HelicopterDipped { owner, datum });
+            }
+            if t >= RAISED {
+                helo.dip = None;
+                helo.task = Task::Hover(at);
+            }
+        }
+        Task::Attack { at, depth } => {
+            if !helo.fly(&at, dt) {
+                return;
+            }
+            helo.task = Task::Hover(at);
+            if helo.torpedoes == 0 {
+                return;
+            }
+            helo.torpedoes -= 1;
+            let settings = TorpedoSettings {
+                depth,
+                speed: SpeedSetting::Medium,
+                enable_run: ENABLE_RUN,
+                search: SearchPattern::Circle,
+                ..TorpedoSettings::default()
+            };
+            let guidance = Guidance::Acoustic(SeekerGeneration::ActivePassive);
+            let torpedo = torpedo::launch(
+                world,
+                owner,
+                helo.position.clone(),
+                helo.heading,
+                guidance,
+                settings,
+                Reliability::perfect(),
+            );
+            world.emit(Event::TorpedoFired {
+                shooter: owner,
+                torpedo,
+            });
+        }
+        Task::Recover => {
+            if helo.fly(ship, dt) {
+                helo.task = Task::OnDeck;
+                world.emit(Event::HelicopterLanded { owner });
+            }
+        }
+    }
+}
+
+/// Flies every helicopter, refuels those on deck and ditches those out of
+/// fuel
+pub fn update(world: &mut World, dt: f32) {
+    let carriers: Vec<(EntityId, Point)> = world
+        .entities
+        .iter()
+        .filter(|e| !e.is_destroyed())
+        .filter(|e| e.asw.as_ref().is_some_and(|s| s.helicopter.is_some()))
+        .map(|e| (e.id, e.position.clone()))
+        .collect();
+    for (owner, ship) in carriers {
+        let station = world.entity_mut(owner).unwrap().asw.as_mut().unwrap();
+        let mut helo = station.helicopter.take().unwrap();
+        update_one(world, owner, &ship, &mut helo, dt);
+        let ditched = helo.fuel <= 0.0;
+        if ditched {
+            world.emit(Event::HelicopterDitched { owner });
+        }
+        let station = world.entity_mut(owner).unwrap().asw.as_mut().unwrap();
+        station.helicopter = (!ditched).then_some(helo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asw::AswStation;
+    use crate::units::Meters;
+
+    fn carrier(world: &mut World) -> EntityId {
+        let mut escort = Entity::new("escort", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.asw = Some(AswStation {
+            helicopter: Some(Helicopter::new(Point { x: 0.0, y: 0.0 }, 3_600.0, 1)),
+            ..AswStation::default()
+        });
+        world.spawn(escort)
+    }
+
+    fn helo(world: &World, escort: EntityId) -> &Helicopter {
+        let station = world.entity(escort).unwrap().asw.as_ref().unwrap();
+        station.helicopter.as_ref().unwrap()
+    }
+
+    #[test]
+    fn dips_under_the_layer_and_attacks_the_datum() {
+        let mut world = World::new();
+        let escort = carrier(&mut world);
+        let mut boat = Entity::new("boat", EntityKind::Submarine, Point { x: 6_000.0, y: 0.0 });
+        boat.depth = 120.0;
+        world.spawn(boat);
+        assert_eq!(
+            execute(&mut world, escort, &HeloCommand::Attack(None)),
+            Err(HeloError::NoDatum)
+        );
+        let dip = HeloCommand::Dip {
+            x: Meters(5_000.0),
+            y: Meters(0.0),
+            depth: None,
+        };
+        execute(&mut world, escort, &dip).unwrap();
+        for _ in 0..400 {
+            world.step(1.0);
+        }
+        let dipped = world
+            .events
+            .iter()
+            .any(|t| matches!(t.event, Event::HelicopterDipped { datum: Some(_), .. }));
+        assert!(dipped);
+        let (datum, depth) = helo(&world, escort).datum.clone().unwrap();
+        assert!(datum.distance_to(&Point { x: 6_000.0, y: 0.0 }) < 1.0);
+        assert_eq!(depth, DIP_DEPTH);
+        execute(&mut world, escort, &HeloCommand::Attack(None)).unwrap();
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        assert_eq!(helo(&world, escort).torpedoes, 0);
+        let fired = world.events.iter().any(|t| {
+            t.event
+                == (Event::TorpedoFired {
+                    shooter: escort,
+                    torpedo: 3,
+                })
+        });
+        assert!(fired);
+    }
+
+    #[test]
+    fn comes_home_before_the_tanks_run_dry() {
+        let mut world = World::new();
+        let escort = carrier(&mut world);
+        let far = HeloCommand::GoTo {
+            x: Meters(0.0),
+            y: Meters(100_000.0),
+        };
+        execute(&mut world, escort, &far).unwrap();
+        for _ in 0..4_000 {
+            world.step(1.0);
+        }
+        let bingo = world
+            .events
+            .iter()
+            .position(|t| t.event == Event::HelicopterBingo { owner: escort });
+        let landed = world
+            .events
+            .iter()
+            .position(|t| t.event == Event::HelicopterLanded { owner: escort });
+        assert!(bingo.unwrap() < landed.unwrap());
+        let helo = helo(&world, escort);
+        assert!(!helo.is_airborne());
+        assert!(helo.fuel > 0.0);
+        assert_eq!(
+            execute(&mut world, escort, &HeloCommand::Recover),
+            Err(HeloError::OnDeck)
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::mem;
+
+use crate::autopilot::Autopilot;
+use crate::baffles::BaffleWatch;
+use crate::contacts::ContactTable;
+use crate::intercept::{Alert, EmissionKind};
+use crate::messages::Catalog;
+use crate::physics::Point;
+use crate::simulation::Simulation;
+use crate::world::EntityId;
+
+// #############################
+// #           HELMS           #
+// #############################
+
+// A scenario may give the player more than one vessel to command, for a
+// hot-seat game or to hand the player another boat during the mission:
+//
+// [scenario]
+// player = U-99           # at the helm at the start
+// helms = U-552, U-96     # optional, the others the player may command
+//
+// and the player moves between them with
+//
+// helm <vessel>           # where vessel is its name in the scenario
+//
+// The simulation keeps what the own ship holds and follows for the vessel
+// at the helm: its contacts, route, autopilot, intercept alerts and the
+// watch on its baffles. Those
+// of the others are kept on their helm, where they go on sensing and
+// steering along their route and autopilot while the player is away, and
+// are swapped back in when the player takes it. The reckoning, the chart
+// and the reports are the player's and stay with the simulation. Bot
+// captains are seated the same way, see captain.rs.
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HelmError {
+    NoSuchVessel(String),
+    /// Placed, but not one the player may command
+    NotControllable(String),
+    Lost(String),
+}
+
+impl HelmError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            HelmError::NoSuchVessel(name) => {
+                messages.format("error-no-such-vessel", &[("name", name)])
+            }
+            HelmError::NotControllable(name) => {
+                messages.format("error-not-controllable", &[("name", name)])
+            }
+            HelmError::Lost(name) => messages.format("error-vessel-lost", &[("name", name)]),
+        }
+    }
+}
+
+impl fmt::Display for HelmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for HelmError {}
+
+/// What the simulation keeps for a vessel commanded, while it is not the
+/// one at the helm
+#[derive(Debug, PartialEq, Clone)]
+pub struct Helm {
+    pub entity: EntityId,
+    pub contacts: ContactTable,
+    pub route: Vec<Point>,
+    pub autopilot: Autopilot,
+    pub alerts: Vec<Alert>,
+    pub alerted: Vec<(EntityId, Option<EmissionKind>)>,
+    pub baffles: BaffleWatch,
+}
+
+impl Helm {
+    /// A helm for `entity`, its contacts kept as `template` keeps them
+    pub fn new(entity: EntityId, template: &ContactTable) -> Helm {
+        let mut contacts = ContactTable::default();
+        contacts.retention = template.retention;
+        contacts.aging = template.aging;
+        Helm {
+            entity,
+            contacts,
+            route: Vec::new(),
+            autopilot: Autopilot::default(),
+            alerts: Vec::new(),
+            alerted: Vec::new(),
+            baffles: BaffleWatch::default(),
+        }
+    }
+
+    /// Trades what the helm keeps with what the simulation keeps for the
+    /// vessel at the helm
+    pub fn swap(&mut self, sim: &mut Simulation) {
+        mem::swap(&mut self.entity, &mut sim.player);
+        mem::swap(&mut self.contacts, &mut sim.contacts);
+        mem::swap(&mut self.route, &mut sim.route);
+        mem::swap(&mut self.autopilot, &mut sim.autopilot);
+        mem::swap(&mut self.alerts, &mut sim.alerts);
+        mem::swap(&mut self.alerted, &mut sim.alerted);
+        mem::swap(&mut self.baffles, &mut sim.baffles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Entity, EntityKind, World};
+
+    #[test]
+    fn swapping_twice_changes_nothing() {
+        let mut world = World::new();
+        let first = world.spawn(Entity::new(
+            "U-99",
+            EntityKind::Submarine,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        let second = world.spawn(Entity::new(
+            "U-552",
+            EntityKind::Submarine,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        let mut sim = Simulation::new(world, first);
+        sim.route.push(Point { x: 10.0, y: 0.0 });
+        let mut helm = Helm::new(second, &sim.contacts);
+        helm.swap(&mut sim);
+        assert_eq!((sim.player, helm.entity), (second, first));
+        assert!(sim.route.is_empty());
+        helm.swap(&mut sim);
+        assert_eq!(sim.player, first);
+        assert_eq!(sim.route.len(), 1);
+        assert_eq!(
+            HelmError::Lost("U-552".to_string()).to_string(),
+            "U-552 is lost"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt::Write;
+
+use crate::command::{HelpTopic, REFERENCE};
+use crate::seeker::Seeker;
+use crate::simulation::{CommandError, Simulation};
+use crate::torpedo::max_run;
+use crate::units::{Meters, MetersPerSecond, UnitSystem};
+use crate::weapons::{Guidance, SpeedSetting};
+use crate::world::Entity;
+
+// #############################
+// #      IN-SIM HELP          #
+// #############################
+
+// Help pages are written from what is loaded, never from canned text: the
+// command reference from command::REFERENCE, the boat and weapon pages from
+// the own ship's class and components.
+
+const INDEX: &[(&str, &str)] = &[
+    ("help commands", "every command"),
+    ("help boat", "what the boat carries"),
+    ("help weapons", "weapon envelopes"),
+    ("help <command>", "the commands starting with a word"),
+];
+
+fn table(rows: &[(&str, &str)]) -> String {
+    let width = rows.iter().map(|(a, _)| a.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(a, b)| format!("{:<width$}  {}", a, b, width = width))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn commands(word: Option<&str>) -> String {
+    let rows: Vec<(&str, &str)> = REFERENCE
+        .iter()
+        .filter(|(syntax, _)| word.is_none_or(|w| syntax.split_whitespace().next() == Some(w)))
+        .copied()
+        .collect();
+    if rows.is_empty() {
+        return format!("no command '{}'", word.unwrap_or_default());
+    }
+    table(&rows)
+}
+
+fn boat(sim: &Simulation, ship: &Entity) -> String {
+    let mut rows: Vec<(String, String)> = Vec::new();
+    if let Some(class) = sim.own_class() {
+        rows.push((
+            "class".to_string(),
+            format!("{} ({})", class.name, class.kind),
+        ));
+        rows.push((
+            "max speed".to_string(),
+            sim.preferences
+                .units
+                .speed(MetersPerSecond(class.max_speed)),
+        ));
+    } else {
+        rows.push(("kind".to_string(), ship.kind.to_string()));
+    }
+    if let Some(station) = &ship.weapons {
+        rows.push((
+            "torpedo tubes".to_string(),
+            format!("{} ({})", station.tubes.tubes.len(), station.guidance),
+        ));
+    }
+    let gun = if ship.gun.is_some() { "yes" } else { "no" };
+    rows.push(("deck gun".to_string(), gun.to_string()));
+    let sensors: Vec<String> = ship.sensors.iter().map(|s| s.kind.to_string()).collect();
+    if !sensors.is_empty() {
+        rows.push(("sensors".to_string(), sensors.join(", ")));
+    }
+    if ship.xbts > 0 {
+        rows.push(("bathythermographs".to_string(), ship.xbts.to_string()));
+    }
+    if ship.towed_decoys + ship.decoys > 0 {
+        let decoys = format!("{} towed, {} mobile", ship.towed_decoys, ship.decoys);
+        rows.push(("decoys".to_string(), decoys));
+    }
+    let rows: Vec<(&str, &str)> = rows.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect();
+    format!("{}\n{}", ship.name, table(&rows))
+}
+
+fn weapons(ship: &Entity, units: UnitSystem) -> String {
+    let mut text = String::new();
+    if let Some(station) = &ship.weapons {
+        writeln!(text, "torpedoes ({})", station.guidance).unwrap();
+        for speed in [SpeedSetting::Slow, SpeedSetting::Medium, SpeedSetting::Fast] {
+            writeln!(
+                text,
+                "  {:<7} {:>7} {:>8}",
+                speed.to_string(),
+                units.speed(MetersPerSecond(speed.meters_per_second())),
+                units.range(Meters(max_run(speed)))
+            )
+            .unwrap();
+        }
+        match station.guidance {
+            Guidance::Unguided => {}
+            Guidance::Acoustic(generation) => {
+                let seeker = Seeker::new(generation);
+                writeln!(
+                    text,
+                    "  seeker  {}, {:.0} degrees either side",
+                    units.range(Meters(seeker.max_range)),
+                    seeker.half_cone.to_degrees()
+                )
+                .unwrap();
+            }
+            Guidance::WakeHoming => writeln!(text, "  follows ship wakes").unwrap(),
+        }
+    }
+    if let Some(gun) = &ship.gun {
+        writeln!(
+            text,
+            "deck gun  {}, {} rounds",
+            units.range(Meters(gun.max_range)),
+            gun.ammo
+        )
+        .unwrap();
+    }
+    if text.is_empty() {
+        return "no weapons".to_string();
+    }
+    text.trim_end().to_string()
+}
+
+/// Writes the help page on `topic`
+pub fn page(sim: &Simulation, topic: &HelpTopic) -> Result<String, CommandError> {
+    Ok(match topic {
+        HelpTopic::Index => table(INDEX),
+        HelpTopic::Commands(word) => commands(word.as_deref()),
+        HelpTopic::Boat => boat(sim, sim.own_ship().ok_or(CommandError::NoOwnShip)?),
+        HelpTopic::Weapons => weapons(
+            sim.own_ship().ok_or(CommandError::NoOwnShip)?,
+            sim.preferences.units,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::scenario::Scenario;
+
+    const PATROL: &str = "
+[scenario]
+name = Patrol
+era = 1944
+player = U-99
+
+[class.type_viic]
+kind = submarine
+max_speed = 17.7
+tubes = 5
+torpedo = early_passive
+deck_gun = true
+
+[entity.U-99]
+class = type_viic
+x = 0
+y = 0
+";
+
+    fn patrol() -> Simulation {
+        Scenario::from_config(&Config::parse(PATROL).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn command_reference() {
+        let sim = patrol();
+        let gun = page(&sim, &HelpTopic::Commands(Some("gun".to_string()))).unwrap();
+        assert_eq!(gun.lines().count(), 3);
+        assert!(gun.contains("man the deck gun"));
+        let all = page(&sim, &HelpTopic::Commands(None)).unwrap();
+        assert_eq!(all.lines().count(), REFERENCE.len());
+    }
+
+    #[test]
+    fn from_class_data() {
+        let sim = patrol();
+        let boat = page(&sim, &HelpTopic::Boat).unwrap();
+        assert!(boat.contains("type_viic (submarine)"));
+        assert!(boat.contains("17.7 kn"));
+        assert!(boat.contains("5 (early_passive)"));
+        let weapons = page(&sim, &HelpTopic::Weapons).unwrap();
+        assert!(weapons.contains("600 m, 30 degrees"));
+        assert!(weapons.contains("deck gun  6000 m"));
+    }
+
+    #[test]
+    fn imperial_units() {
+        let mut sim = patrol();
+        sim.execute(&crate::command::Command::parse("set units imperial").unwrap())
+            .unwrap();
+        let weapons = page(&sim, &HelpTopic::Weapons).unwrap();
+        assert!(weapons.contains("656 yd, 30 degrees"));
+        assert!(weapons.contains("deck gun  6562 yd"));
+    }
+
+    #[test]
+    fn help_command() {
+        let mut sim = patrol();
+        sim.execute(&crate::command::Command::parse("help").unwrap())
+            .unwrap();
+        assert!(sim.reports[0].contains("help weapons"));
+    }
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+
+use crate::config::{Config, ConfigError};
+use crate::era::Era;
+use crate::events::Event;
+use crate::faction::Stance;
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::messages::Catalog;
+use crate::physics::{turn_towards, Point, KNOT};
+use crate::tuning::Tunable;
+use crate::world::{EntityId, EntityKind, World};
+
+// #############################
+// #  RADIO DIRECTION FINDING  #
+// #############################
+
+// A boat that radios a contact report gives itself away: shore stations
+// listening on HF take a bearing on it, and crossed, the bearings fix it.
+// Each is off by an error that shrinks the longer the boat stays on the
+// air, and the fix is the better the more squarely they cross. After the
+// delay their staff takes to act on it, the escorts of the side the
+// stations belong to nearest the fix are sent there to hunt (see
+// escort.rs):
+//
+// [hfdf]
+// stations = 0, -200000; 150000, -300000   # x, y in meters, ';' between
+// side = allies           # optional, whose stations: escorts hostile to
+//                         # the boat when not given
+// delay = 1800            # optional, seconds, by the era when not given
+// bearing_error = 2       # optional, degrees for a 30 second message
+//
+// Without stations, transmissions go unheard.
+
+/// Seconds a contact report takes to send, and more per contact in it
+const REPORT_LENGTH: f32 = 20.0;
+const CONTACT_LENGTH: f32 = 10.0;
+/// Seconds on the air the bearing error is given for
+const REFERENCE_LENGTH: f32 = 30.0;
+/// Degrees of bearing error over the reference length, unless set
+const BEARING_ERROR: f32 = 2.0;
+/// Escorts sent to each fix
+const RESPONDERS: usize = 2;
+/// Meters per second they make there
+const RESPONSE_SPEED: f32 = 18.0 * KNOT;
+/// Meters from the fix at which they are there
+const ON_STATION: f32 = 500.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RadioError {
+    /// The aerial is under water
+    TooDeep,
+}
+
+impl RadioError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            RadioError::TooDeep => messages.get("error-radio-too-deep").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RadioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for RadioError {}
+
+/// Seconds the staff of an era takes from a fix to sending escorts
+pub fn doctrinal_delay(era: Era) -> f32 {
+    match era.year {
+        0..=1945 => 3_600.0,
+        1946..=1989 => 1_200.0,
+        _ => 300.0,
+    }
+}
+
+/// Seconds on the air of a contact report of `contacts` contacts
+pub fn report_length(contacts: usize) -> f32 {
+    REPORT_LENGTH + CONTACT_LENGTH * contacts as f32
+}
+
+/// Where a transmission was fixed
+#[derive(Debug, PartialEq, Clone)]
+pub struct Fix {
+    pub position: Point,
+    /// Meters the fix is likely off by
+    pub error: f32,
+}
+
+/// Least squares crossing of the bearing lines from `stations`, game
+/// angles; None when they do not cross
+pub fn cross_bearings(stations: &[Point], bearings: &[f32], error: f32) -> Option<Fix> {
+    // sum over the lines of the projections across them, a 2x2 system
+    let (mut a, mut b, mut c, mut u, mut v) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (station, bearing) in stations.iter().zip(bearings) {
+        let (dx, dy) = (bearing.cos(), bearing.sin());
+        let (pxx, pxy, pyy) = (1.0 - dx * dx, -dx * dy, 1.0 - dy * dy);
+        a += pxx;
+        b += pxy;
+        c += pyy;
+        u += pxx * station.x + pxy * station.y;
+        v += pxy * station.x + pyy * station.y;
+    }
+    let determinant = a * c - b * b;
+    if determinant.abs() < 1e-3 {
+        return None;
+    }
+    let position = Point {
+        x: (c * u - b * v) / determinant,
+        y: (a * v - b * u) / determinant,
+    };
+    // the widest cut between two bearings
+    let mut cut: f32 = 0.0;
+    for (i, first) in bearings.iter().enumerate() {
+        for second in &bearings[i + 1..] {
+            cut = cut.max((first - second).sin().abs());
+        }
+    }
+    let range = stations
+        .iter()
+        .map(|s| s.distance_to(&position))
+        .sum::<f32>()
+        / stations.len() as f32;
+    Some(Fix {
+        error: range * error / cut.max(0.05),
+        position,
+    })
+}
+
+/// Escorts sent to a fix once the staff has acted on it
+#[derive(Debug, PartialEq, Clone)]
+struct Response {
+    /// Seconds into the scenario they are sent at
+    due: f32,
+    fix: Fix,
+    /// Who transmitted, for the escorts hostile to it
+    transmitter: EntityId,
+}
+
+/// The direction finding network of a scenario, and what it has fixed
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DirectionFinding {
+    pub stations: Vec<Point>,
+    pub side: Option<String>,
+    /// Seconds from a fix to sending escorts
+    pub delay: f32,
+    /// Radians of bearing error over the reference length
+    pub bearing_error: f32,
+    /// Every fix taken, for the debrief
+    pub fixes: Vec<(f32, Fix)>,
+    pending: Vec<Response>,
+    /// Escorts on their way, and where to
+    sent: Vec<(EntityId, Point)>,
+}
+
+impl DirectionFinding {
+    /// Reads the "[hfdf]" section, no stations without one
+    pub fn read(config: &Config, era: Era) -> Result<DirectionFinding, ConfigError> {
+        let mut network = DirectionFinding {
+            delay: doctrinal_delay(era),
+            bearing_error: BEARING_ERROR.to_radians(),
+            ..DirectionFinding::default()
+        };
+        let section = match config.section("hfdf") {
+            Some(section) => section,
+            None => return Ok(network),
+        };
+        let value: String = section.parse("stations")?;
+        for station in value.split(';') {
+            let point = station.split_once(',').and_then(|(x, y)| {
+                Some(Point {
+                    x: x.trim().parse().ok()?,
+                    y: y.trim().parse().ok()?,
+                })
+            });
+            network
+                .stations
+                .push(point.ok_or_else(|| ConfigError::Invalid {
+                    section: "hfdf".to_string(),
+                    key: "stations".to_string(),
+                    value: value.clone(),
+                })?);
+        }
+        network.side = section.get("side").map(|s| s.to_string());
+        network.delay = section.parse_or("delay", network.delay)?;
+        network.bearing_error = section
+            .parse_or("bearing_error", BEARING_ERROR)?
+            .to_radians();
+        Ok(network)
+    }
+}
+
+/// `transmitter` sends `seconds` of radio traffic: the stations take their
+/// bearings on it and, if they fix it, the escorts will be sent
+pub fn transmit(world: &mut World, transmitter: EntityId, seconds: f32) -> Result<(), RadioError> {
+    let position = match world.entity(transmitter) {
+        Some(boat) if boat.depth <= PERISCOPE_DEPTH => boat.position.clone(),
+        _ => return Err(RadioError::TooDeep),
+    };
+    world.emit(Event::Transmitted {
+        entity: transmitter,
+        seconds,
+    });
+    let network = &world.hfdf;
+    let error = network.bearing_error * (REFERENCE_LENGTH / seconds.max(1.0)).sqrt();
+    let stations = network.stations.clone();
+    let bearings: Vec<f32> = stations
+        .iter()
+        .map(|s| world.rng.gaussian(s.angle_to(&position), error))
+        .collect();
+    if let Some(fix) = cross_bearings(&stations, &bearings, error) {
+        world.hfdf.fixes.push((world.time, fix.clone()));
+        alert(world, fix, transmitter);
+    }
+    Ok(())
+}
+
+/// Has the escorts of the network sent to `fix` once the staff has acted
+/// on it, as if `against` had been fixed there
+pub fn alert(world: &mut World, fix: Fix, against: EntityId) {
+    let due = world.time + world.hfdf.delay;
+    world.hfdf.pending.push(Response {
+        due,
+        fix,
+        transmitter: against,
+    });
+}
+
+/// Sends the escorts to the fixes due, and steers those on their way
+pub fn update(world: &mut World, dt: f32) {
+    let time = world.time;
+    let (due, pending): (Vec<Response>, Vec<Response>) =
+        world.hfdf.pending.drain(..).partition(|r| r.due <= time);
+    world.hfdf.pending = pending;
+    for response in due {
+        let transmitter = match world.entity(response.transmitter) {
+            Some(transmitter) => transmitter,
+            None => continue,
+        };
+        let side = world.hfdf.side.as_ref();
+        let mut escorts: Vec<(EntityId, f32)> = world
+            .entities
+            .iter()
+            .filter(|e| e.kind == EntityKind::Warship && !e.is_destroyed())
+            .filter(|e| match side {
+                Some(side) => e.side.as_ref() == Some(side),
+                None => world.diplomacy.stance(e, transmitter) == Stance::Hostile,
+            })
+            .filter(|e| !world.hfdf.sent.iter().any(|(id, _)| *id == e.id))
+            .map(|e| (e.id, e.position.distance_to(&response.fix.position)))
+            .collect();
+        escorts.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for (id, _) in escorts.into_iter().take(RESPONDERS) {
+            world.hfdf.sent.push((id, response.fix.position.clone()));
+        }
+    }
+    let turn = Tunable::TurnRate.get(&world.environment.tuning) * dt;
+    let mut sent = std::mem::take(&mut world.hfdf.sent);
+    sent.retain(|(id, to)| {
+        let escort = match world.entities.get_mut(*id) {
+            Some(escort) if !escort.is_destroyed() => escort,
+            _ => return false,
+        };
+        if escort.position.distance_to(to) < ON_STATION {
+            if let Some(ai) = escort.escort_ai.as_mut() {
+                ai.alert(to.clone(), time);
+            }
+            return false;
+        }
+        let desired = escort.position.angle_to(to);
+        escort.heading = turn_towards(escort.heading, desired, turn);
+        escort.speed = RESPONSE_SPEED;
+        true
+    });
+    world.hfdf.sent = sent;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Entity;
+
+    #[test]
+    fn longer_and_squarer_fixes_are_better() {
+        let stations = [
+            Point {
+                x: 0.0,
+                y: -100_000.0,
+            },
+            Point {
+                x: 100_000.0,
+                y: 0.0,
+            },
+        ];
+        let boat = Point { x: 0.0, y: 0.0 };
+        let bearings: Vec<f32> = stations.iter().map(|s| s.angle_to(&boat)).collect();
+        let fix = cross_bearings(&stations, &bearings, 0.01).unwrap();
+        assert!(fix.position.distance_to(&boat) < 1.0, "{:?}", fix);
+        assert!((fix.error - 1_000.0).abs() < 1.0);
+
+        // nearly in line with the boat, the bearings barely cut
+        let line = [
+            Point {
+                x: 0.0,
+                y: -100_000.0,
+            },
+            Point {
+                x: 0.0,
+                y: -200_000.0,
+            },
+        ];
+        let bearings: Vec<f32> = line.iter().map(|s| s.angle_to(&boat)).collect();
+        assert_eq!(cross_bearings(&line, &bearings, 0.01), None);
+        assert_eq!(cross_bearings(&stations[..1], &bearings[..1], 0.01), None);
+        assert!(report_length(3) > report_length(0));
+        assert!(doctrinal_delay(Era::WORLD_WAR_TWO) > doctrinal_delay(Era::MODERN));
+    }
+
+    #[test]
+    fn escorts_are_sent_to_the_fix() {
+        let mut world = World::new();
+        let config =
+            Config::parse("[hfdf]\nstations = 0, -100000; 100000, 0\nside = allies\ndelay = 60")
+                .unwrap();
+        world.hfdf = DirectionFinding::read(&config, Era::WORLD_WAR_TWO).unwrap();
+        let boat = world.spawn(Entity::new(
+            "U-99",
+            EntityKind::Submarine,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        let mut escort = Entity::new(
+            "Vanoc",
+            EntityKind::Warship,
+            Point {
+                x: -10_000.0,
+                y: 0.0,
+            },
+        );
+        escort.side = Some("allies".to_string());
+        let escort = world.spawn(escort);
+        world.entity_mut(boat).unwrap().depth = 100.0;
+        assert_eq!(transmit(&mut world, boat, 60.0), Err(RadioError::TooDeep));
+        world.entity_mut(boat).unwrap().depth = 0.0;
+        transmit(&mut world, boat, 60.0).unwrap();
+        assert_eq!(world.hfdf.fixes.len(), 1);
+        let fix = world.hfdf.fixes[0].1.clone();
+        assert!(fix.position.distance_to(&Point { x: 0.0, y: 0.0 }) < 10.0 * fix.error);
+        for _ in 0..59 {
+            world.step(1.0);
+        }
+        assert_eq!(world.entity(escort).unwrap().speed, 0.0);
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        let escort = world.entity(escort).unwrap();
+        assert_eq!(escort.speed, RESPONSE_SPEED);
+        assert!(escort.velocity().x > 0.0);
+    }
+}
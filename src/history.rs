@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+
+use crate::config::{Config, ConfigError};
+use crate::physics::Point;
+
+// #############################
+// #      TRACK HISTORIES      #
+// #############################
+
+// The own ship's track and the bearings of every contact are recorded for
+// as long as the patrol lasts, which over days of game time would grow
+// without end. A history keeps its latest points as they were recorded and
+// thins out the older ones (Douglas-Peucker): a point is dropped when the
+// line without it strays less than a tolerance from it, so long straight
+// legs shrink to their ends while turns are kept. Whenever a history
+// outgrows its limit the older part is thinned again, the tolerance
+// doubling until it fits in half the room left, so memory stays bounded
+// and the simplification is not redone every tick. The retention is set by
+// an optional section of the scenario:
+//
+// [history]
+// recent = 1000            # points kept as recorded
+// limit = 5000             # points kept at most
+// tolerance = 50           # meters the own track may be simplified by
+// bearing_tolerance = 0.5  # degrees the bearings may be simplified by
+
+/// How much of each history is kept
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Retention {
+    /// Latest points kept as recorded
+    pub recent: usize,
+    /// Points kept at most
+    pub limit: usize,
+    /// Meters the own track may stray from what was recorded, at first
+    pub tolerance: f32,
+    /// The same in degrees for bearing histories
+    pub bearing_tolerance: f32,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            recent: 1000,
+            limit: 5000,
+            tolerance: 50.0,
+            bearing_tolerance: 0.5,
+        }
+    }
+}
+
+impl Retention {
+    /// Reads the "[history]" section, the defaults without one
+    pub fn read(config: &Config) -> Result<Retention, ConfigError> {
+        let defaults = Retention::default();
+        let section = match config.section("history") {
+            Some(section) => section,
+            None => return Ok(defaults),
+        };
+        Ok(Retention {
+            recent: section.parse_or("recent", defaults.recent)?,
+            limit: section.parse_or("limit", defaults.limit)?,
+            tolerance: section.parse_or("tolerance", defaults.tolerance)?,
+            bearing_tolerance: section.parse_or("bearing_tolerance", defaults.bearing_tolerance)?,
+        })
+    }
+}
+
+/// Distance from `p` to the segment from `a` to `b`
+fn off_segment(p: &Point, a: &Point, b: &Point) -> f32 {
+    p.distance_to(&p.closest_on_segment(a, b))
+}
+
+/// The points of `line` the Douglas-Peucker algorithm keeps at `tolerance`
+pub fn simplify(line: &[Point], tolerance: f32) -> Vec<Point> {
+    if line.len() <= 2 {
+        return line.to_vec();
+    }
+    let mut keep = vec![false; line.len()];
+    keep[0] = true;
+    keep[line.len() - 1] = true;
+    let mut spans = vec![(0, line.len() - 1)];
+    while let Some((first, last)) = spans.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, off_segment(&line[i], &line[first], &line[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                spans.push((first, i));
+                spans.push((i, last));
+            }
+        }
+    }
+    line.iter()
+        .zip(keep)
+        .filter(|(_, kept)| *kept)
+        .map(|(p, _)| p.clone())
+        .collect()
+}
+
+/// A line recorded point by point, its older part thinned out
+#[derive(Debug, PartialEq, Clone)]
+pub struct History {
+    /// Latest points kept as recorded
+    recent: usize,
+    limit: usize,
+    tolerance: f32,
+    /// Oldest first
+    points: VecDeque<Point>,
+}
+
+impl History {
+    pub fn new(recent: usize, limit: usize, tolerance: f32) -> History {
+        History {
+            recent: recent.min(limit / 2),
+            limit: limit.max(4),
+            tolerance,
+            points: VecDeque::new(),
+        }
+    }
+
+    /// A history of positions kept as `retention` says
+    pub fn positions(retention: &Retention) -> History {
+        History::new(retention.recent, retention.limit, retention.tolerance)
+    }
+
+    /// A history of bearings, as seconds and degrees, kept as `retention`
+    /// says
+    pub fn bearings(retention: &Retention) -> History {
+        History::new(
+            retention.recent,
+            retention.limit,
+            retention.bearing_tolerance,
+        )
+    }
+
+    pub fn push(&mut self, point: Point) {
+        self.points.push_back(point);
+        if self.points.len() > self.limit {
+            self.compact();
+        }
+    }
+
+    /// Thins out all but the recent points, coarser and coarser until they
+    /// fit in half the room they have
+    fn compact(&mut self) {
+        let older = self.points.len() - self.recent;
+        let line: Vec<Point> = self.points.drain(..older).collect();
+        let room = ((self.limit - self.recent) / 2).max(2);
+        let mut tolerance = self.tolerance;
+        let mut kept = simplify(&line, tolerance);
+        while kept.len() > room && tolerance > 0.0 {
+            tolerance *= 2.0;
+            kept = simplify(&kept, tolerance);
+        }
+        for point in kept.into_iter().rev() {
+            self.points.push_front(point);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&Point> {
+        self.points.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Point> {
+        self.points.iter()
+    }
+
+    pub fn to_vec(&self) -> Vec<Point> {
+        self.points.iter().cloned().collect()
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::positions(&Retention::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_turns() {
+        let mut line: Vec<Point> = (0..=10)
+            .map(|i| Point {
+                x: i as f32 * 100.0,
+                y: 0.0,
+            })
+            .collect();
+        line.extend((1..=10).map(|i| Point {
+            x: 1000.0,
+            y: i as f32 * 100.0,
+        }));
+        line[5].y = 10.0;
+        let kept = simplify(&line, 20.0);
+        assert_eq!(
+            kept,
+            vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 1000.0, y: 0.0 },
+                Point {
+                    x: 1000.0,
+                    y: 1000.0
+                },
+            ]
+        );
+        assert!(simplify(&line, 5.0).contains(&line[5]));
+    }
+
+    #[test]
+    fn stays_within_its_limit() {
+        let mut history = History::new(100, 400, 10.0);
+        // days of zigzags, a leg every 50 points
+        for i in 0..20_000 {
+            let leg = (i / 50) % 2;
+            history.push(Point {
+                x: i as f32 * 10.0,
+                y: if leg == 0 {
+                    (i % 50) as f32 * 10.0
+                } else {
+                    500.0 - (i % 50) as f32 * 10.0
+                },
+            });
+            assert!(history.len() <= 400);
+        }
+        let points = history.to_vec();
+        assert_eq!(points[0], Point { x: 0.0, y: 0.0 });
+        assert_eq!(points.last().unwrap().x, 199_990.0);
+        // the recent points are as recorded
+        let recent = &points[points.len() - 100..];
+        assert!(recent.windows(2).all(|w| w[1].x - w[0].x == 10.0));
+        assert!(points.windows(2).all(|w| w[1].x > w[0].x));
+    }
+
+    #[test]
+    fn reads_the_retention() {
+        let config = Config::parse("[history]\nlimit = 800\ntolerance = 25").unwrap();
+        let retention = Retention::read(&config).unwrap();
+        assert_eq!(retention.limit, 800);
+        assert_eq!(retention.tolerance, 25.0);
+        assert_eq!(retention.recent, 1000);
+        assert_eq!(
+            Retention::read(&Config::new()).unwrap(),
+            Retention::default()
+        );
+    }
+}
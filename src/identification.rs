@@ -0,0 +1,276 @@
+use std::fmt;
+
+use crate::config::{Config, ConfigError, Section};
+use crate::faction::Stance;
+use crate::gunnery;
+use crate::messages::Catalog;
+use crate::random::Rng;
+use crate::seakeeping;
+use crate::sensors::passive_excess;
+use crate::world::{Entity, World};
+
+// #############################
+// #      IDENTIFICATION       #
+// #############################
+
+// Telling what a contact is, and so whose side it is on, can go wrong.
+// Classes that look or sound alike are listed per scenario with how often
+// one is taken for the other on a poor contact:
+//
+// [confusion]
+// trawler = patrol_boat 0.3, merchant 0.1
+// type_viic = type_ix 0.4
+//
+// The clearer the contact (close in sight, or loud over the noise) and the
+// better the crew works, the less likely the mistake. Each observer reads
+// each contact its own way, the same way every time: a mistake persists
+// until the contact gets clearer, instead of flickering tick by tick. A
+// contact taken for another class is taken to be on the side that class
+// sails for, so in scenarios where both sides sail similar ships the AI
+// may shoot at its own.
+
+/// Signal excess in dB at which a sound contact is as clear as it gets
+const CLEAR_EXCESS: f32 = 20.0;
+/// Clarity a sound contact reaches at best; the eye does better
+const ACOUSTIC_CLARITY: f32 = 0.6;
+
+/// Pairs of classes taken for one another
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Confusion {
+    /// (class, class it is taken for, chance on the poorest contact)
+    pub entries: Vec<(String, String, f32)>,
+}
+
+impl Confusion {
+    /// Reads the "[confusion]" section
+    pub fn read(config: &Config) -> Result<Confusion, ConfigError> {
+        let mut confusion = Confusion::default();
+        let section = match config.section("confusion") {
+            Some(section) => section,
+            None => return Ok(confusion),
+        };
+        for (class, value) in section.entries() {
+            for alike in value.split(',') {
+                let (taken_for, chance) = read_alike(section, class, alike)?;
+                confusion
+                    .entries
+                    .push((class.to_string(), taken_for, chance));
+            }
+        }
+        Ok(confusion)
+    }
+
+    /// Classes `class` is taken for on the poorest contact, with the chance
+    fn alike<'a>(&'a self, class: &'a str) -> impl Iterator<Item = (&'a str, f32)> {
+        self.entries
+            .iter()
+            .filter(move |(c, _, _)| c == class)
+            .map(|(_, taken_for, chance)| (taken_for.as_str(), *chance))
+    }
+}
+
+/// Reads "<class> <chance>"
+fn read_alike(section: &Section, key: &str, text: &str) -> Result<(String, f32), ConfigError> {
+    let invalid = || ConfigError::Invalid {
+        section: section.name.clone(),
+        key: key.to_string(),
+        value: text.to_string(),
+    };
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        [class, chance] => Ok((class.to_string(), chance.parse().map_err(|_| invalid())?)),
+        _ => Err(invalid()),
+    }
+}
+
+/// What an observer makes of a contact
+#[derive(Debug, PartialEq, Clone)]
+pub struct Identification {
+    /// Class it is taken for, None when the contact has no class
+    pub class: Option<String>,
+    /// How sure the observer is, 0 to 1
+    pub confidence: f32,
+    /// Whether the class is wrong; the observer does not know
+    pub mistaken: bool,
+}
+
+impl Identification {
+    /// The identification as written for the player
+    pub fn describe(&self, messages: &Catalog, target: usize) -> String {
+        let class = self.class.as_deref().unwrap_or("unknown");
+        let confidence = format!("{:.0}", self.confidence * 100.0);
+        messages.format(
+            "identified",
+            &[
+                ("target", &target),
+                ("class", &class),
+                ("confidence", &confidence),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for Identification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = self.class.as_deref().unwrap_or("unknown");
+        write!(f, "{} ({:.0}%)", class, self.confidence * 100.0)
+    }
+}
+
+/// How clearly `observer` makes out `target`, 0 to 1
+pub fn clarity(world: &World, observer: &Entity, target: &Entity) -> f32 {
+    let range = observer.position.distance_to(&target.position);
+    let sighting = seakeeping::sighting_range(observer, &world.environment);
+    let visual = if gunnery::exposure(observer) > 0.0 && range <= sighting {
+        gunnery::exposure(target).sqrt() * (1.0 - range / sighting)
+    } else {
+        0.0
+    };
+    let acoustic = passive_excess(&world.environment, observer, target).map_or(0.0, |excess| {
+        (excess / CLEAR_EXCESS).clamp(0.0, 1.0) * ACOUSTIC_CLARITY
+    });
+    visual.max(acoustic) * observer.crew_performance()
+}
+
+/// What `observer` takes `target` for
+pub fn identify(world: &World, observer: &Entity, target: &Entity) -> Identification {
+    let clarity = clarity(world, observer, target);
+    let class = match &target.class {
+        Some(class) => class,
+        None => {
+            return Identification {
+                class: None,
+                confidence: clarity,
+                mistaken: false,
+            }
+        }
+    };
+    // the same draw for the pair every time
+    let mut rng = Rng::new(((observer.id as u64) << 32) ^ target.id as u64);
+    let draw = rng.next_f32();
+    let mut cumulative = 0.0;
+    for (taken_for, chance) in world.confusion.alike(class) {
+        cumulative += chance * (1.0 - clarity);
+        if draw < cumulative {
+            return Identification {
+                class: Some(taken_for.to_string()),
+                confidence: clarity,
+                mistaken: true,
+            };
+        }
+    }
+    Identification {
+        class: Some(class.clone()),
+        confidence: clarity,
+        mistaken: false,
+    }
+}
+
+/// How `target` seems to stand to `observer`: by its own side when taken
+/// for what it is, by the side its apparent class sails for when not
+pub fn apparent_stance(world: &World, observer: &Entity, target: &Entity) -> Stance {
+    let identification = identify(world, observer, target);
+    if !identification.mistaken {
+        return world.diplomacy.stance(observer, target);
+    }
+    let class = identification.class.unwrap();
+    let side = world
+        .entities
+        .by_class(&class)
+        .find_map(|e| e.side.as_deref());
+    match side {
+        Some(side) => world
+            .diplomacy
+            .between(observer.side.as_deref(), Some(side)),
+        None => Stance::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::world::{EntityId, EntityKind};
+
+    const CONFUSION: &str = "
+[confusion]
+trawler = patrol_boat 0.6, merchant 0.3
+";
+
+    fn waters() -> (World, EntityId) {
+        let mut world = World::new();
+        world.confusion = Confusion::read(&Config::parse(CONFUSION).unwrap()).unwrap();
+        let mut escort = Entity::new("Vanoc", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.side = Some("allied".to_string());
+        let escort = world.spawn(escort);
+        let mut patrol = Entity::new(
+            "V-1",
+            EntityKind::Warship,
+            Point {
+                x: 0.0,
+                y: 50_000.0,
+            },
+        );
+        patrol.class = Some("patrol_boat".to_string());
+        patrol.side = Some("axis".to_string());
+        world.spawn(patrol);
+        (world, escort)
+    }
+
+    fn trawler(world: &mut World, x: f32, y: f32) -> EntityId {
+        let mut trawler = Entity::new("trawler", EntityKind::Merchant, Point { x, y });
+        trawler.class = Some("trawler".to_string());
+        trawler.side = Some("allied".to_string());
+        world.spawn(trawler)
+    }
+
+    #[test]
+    fn reads_confusion() {
+        let confusion = Confusion::read(&Config::parse(CONFUSION).unwrap()).unwrap();
+        assert_eq!(confusion.entries.len(), 2);
+        assert_eq!(
+            confusion.alike("trawler").collect::<Vec<_>>(),
+            vec![("patrol_boat", 0.6), ("merchant", 0.3)]
+        );
+        let bad = Config::parse("[confusion]\ntrawler = patrol_boat").unwrap();
+        assert!(Confusion::read(&bad).is_err());
+    }
+
+    #[test]
+    fn poor_contacts_are_mistaken() {
+        let (mut world, escort) = waters();
+        let trawlers: Vec<EntityId> = (0..20)
+            .map(|i| trawler(&mut world, 18_000.0, i as f32 * 100.0))
+            .collect();
+        let observer = world.entity(escort).unwrap();
+        let mut mistaken = 0;
+        let mut blue_on_blue = 0;
+        for id in &trawlers {
+            let target = world.entity(*id).unwrap();
+            let identification = identify(&world, observer, target);
+            assert!(identification.confidence < 0.2);
+            if identification.mistaken {
+                mistaken += 1;
+            }
+            if apparent_stance(&world, observer, target) == Stance::Hostile {
+                blue_on_blue += 1;
+            }
+            // the same contact is read the same way
+            assert_eq!(identify(&world, observer, target), identification);
+        }
+        assert!(mistaken > 10, "{}", mistaken);
+        assert!(
+            blue_on_blue > 5 && blue_on_blue < mistaken,
+            "{}",
+            blue_on_blue
+        );
+
+        // close in, in plain sight, there is no mistaking it
+        let close = trawler(&mut world, 100.0, 0.0);
+        let observer = world.entity(escort).unwrap();
+        let close = world.entity(close).unwrap();
+        let identification = identify(&world, observer, close);
+        assert!(identification.confidence > 0.9);
+        assert_eq!(apparent_stance(&world, observer, close), Stance::Friendly);
+    }
+}
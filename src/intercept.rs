@@ -0,0 +1,343 @@
+use std::fmt;
+
+use crate::acoustics::{ambient_noise, db_sum, spreading_loss};
+use crate::environment::Environment;
+use crate::messages::Catalog;
+use crate::noise;
+use crate::physics::Point;
+use crate::preferences::Preferences;
+use crate::pulse::Pulse;
+use crate::sensors::{SensorContext, SensorKind};
+use crate::tuning::Tunable;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #   INTERCEPT RECEIVER      #
+// #############################
+
+// Active sonars and active torpedo seekers announce themselves: their
+// pulses only have to travel one way to an intercept receiver, against two
+// ways back to the pinger, so the target usually hears them first. Each
+// tick the emissions of that tick are kept in World::emissions; a receiver
+// gets the bearing of everything above its threshold, and tells what it is
+// when the signal is strong enough to classify.
+
+/// Signal excess in dB above which an intercept is classified
+const CLASSIFY_MARGIN: f32 = 10.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EmissionKind {
+    ActiveSonar,
+    /// Lowered from a helicopter, see helicopter.rs
+    DippingSonar,
+    TorpedoSeeker,
+}
+
+impl EmissionKind {
+    /// Source level in dB at 1 m
+    pub fn source_level(&self) -> f32 {
+        match self {
+            EmissionKind::ActiveSonar => 220.0,
+            EmissionKind::DippingSonar => 215.0,
+            EmissionKind::TorpedoSeeker => 190.0,
+        }
+    }
+
+    /// dB per meter absorbed; seekers ping at much higher frequencies
+    fn absorption(&self) -> f32 {
+        match self {
+            EmissionKind::ActiveSonar => 0.0003,
+            EmissionKind::DippingSonar => 0.001,
+            EmissionKind::TorpedoSeeker => 0.008,
+        }
+    }
+}
+
+impl EmissionKind {
+    /// Id of the name in the message catalog
+    pub fn message(&self) -> &'static str {
+        match self {
+            EmissionKind::ActiveSonar => "emission-active-sonar",
+            EmissionKind::DippingSonar => "emission-dipping-sonar",
+            EmissionKind::TorpedoSeeker => "emission-torpedo-seeker",
+        }
+    }
+}
+
+impl fmt::Display for EmissionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Catalog::default().get(self.message()))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Emission {
+    pub source: EntityId,
+    pub kind: EmissionKind,
+    pub position: Point,
+    pub depth: f32,
+    /// Game angle the source was heading, for a pulse sent in a sector
+    pub heading: f32,
+    pub pulse: Pulse,
+}
+
+/// An emission heard by an intercept receiver
+#[derive(Debug, PartialEq, Clone)]
+pub struct Intercept {
+    pub source: EntityId,
+    /// Game angle, radians
+    pub bearing: f32,
+    /// Signal excess in dB
+    pub excess: f32,
+    /// None while too weak to classify
+    pub kind: Option<EmissionKind>,
+}
+
+impl Intercept {
+    /// Torpedo seekers first, then the strongest
+    fn priority(&self) -> (bool, f32) {
+        (self.kind == Some(EmissionKind::TorpedoSeeker), self.excess)
+    }
+}
+
+impl Intercept {
+    /// The intercept as written for the player, on a ship on `heading`
+    pub fn describe(&self, messages: &Catalog, preferences: &Preferences, heading: f32) -> String {
+        let bearing = preferences.bearing(self.bearing, heading);
+        match self.kind {
+            Some(kind) => messages.format(
+                "intercept",
+                &[
+                    ("kind", &messages.get(kind.message())),
+                    ("bearing", &bearing),
+                ],
+            ),
+            None => messages.format("intercept-unknown", &[("bearing", &bearing)]),
+        }
+    }
+}
+
+impl fmt::Display for Intercept {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.describe(&Catalog::default(), &Preferences::default(), 0.0);
+        write!(f, "{}", text)
+    }
+}
+
+/// Intercept alert for the player
+#[derive(Debug, PartialEq, Clone)]
+pub struct Alert {
+    /// Seconds into the scenario
+    pub time: f32,
+    /// Game angle the own ship was heading on, for relative bearings
+    pub heading: f32,
+    pub intercept: Intercept,
+}
+
+impl Alert {
+    /// The alert as written for the player
+    pub fn describe(
+        &self,
+        messages: &Catalog,
+        preferences: &Preferences,
+        environment: &Environment,
+    ) -> String {
+        messages.format(
+            "alert",
+            &[
+                ("time", &preferences.time(environment, self.time)),
+                (
+                    "intercept",
+                    &self.intercept.describe(messages, preferences, self.heading),
+                ),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.describe(
+            &Catalog::default(),
+            &Preferences::default(),
+            &Environment::default(),
+        );
+        write!(f, "{}", text)
+    }
+}
+
+/// What the intercept receiver of `listener` picks up this tick, highest
+/// priority first
+pub fn intercepts(world: &World, listener: &Entity) -> Vec<Intercept> {
+    let environment = &world.environment;
+    let context = SensorContext::new(listener, environment);
+    let receiver = match listener
+        .sensors
+        .iter()
+        .find(|s| s.kind == SensorKind::InterceptReceiver && s.is_operational(&context))
+    {
+        Some(receiver) => receiver,
+        None => return Vec::new(),
+    };
+    let background = db_sum(&[
+        ambient_noise(environment.sea_state),
+        noise::radiated_level(listener) - Tunable::SelfNoiseIsolation.get(&environment.tuning),
+    ]);
+    let layer = environment.sound_speed.layer_depth();
+    let mut heard: Vec<Intercept> = world
+        .emissions
+        .iter()
+        .filter(|e| e.source != listener.id)
+        .filter_map(|emission| {
+            let range = listener.position.distance_to(&emission.position).max(1.0);
+            let pulse = &emission.pulse;
+            let mut received = emission.kind.source_level()
+                + pulse.energy()
+                + pulse.gain(&emission.position, emission.heading, &listener.position)
+                - spreading_loss(range)
+                - emission.kind.absorption() * pulse.band.absorption() * range;
+            if layer.is_some_and(|l| (listener.depth < l) != (emission.depth < l)) {
+                received -= Tunable::LayerLoss.get(&environment.tuning);
+            }
+            let excess = receiver.signal_excess(received, background, &context);
+            if excess <= 0.0 {
+                return None;
+            }
+            Some(Intercept {
+                source: emission.source,
+                bearing: listener.position.angle_to(&emission.position),
+                excess,
+                kind: if excess >= CLASSIFY_MARGIN {
+                    Some(emission.kind)
+                } else {
+                    None
+                },
+            })
+        })
+        .collect();
+    heard.sort_by(|a, b| {
+        let (a, b) = (a.priority(), b.priority());
+        b.0.cmp(&a.0).then(b.1.total_cmp(&a.1))
+    });
+    heard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::user_to_game_angle;
+    use crate::preferences::{BearingMode, Clock};
+    use crate::sensors::Sensor;
+    use crate::world::EntityKind;
+
+    fn listener(world: &mut World) -> EntityId {
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 30.0;
+        boat.sensors
+            .push(Sensor::new(SensorKind::InterceptReceiver));
+        world.spawn(boat)
+    }
+
+    fn emission(kind: EmissionKind, x: f32, y: f32) -> Emission {
+        Emission {
+            source: 7,
+            kind,
+            position: Point { x, y },
+            depth: 30.0,
+            heading: 0.0,
+            pulse: Pulse::default(),
+        }
+    }
+
+    #[test]
+    fn classify_by_strength() {
+        let mut world = World::new();
+        let id = listener(&mut world);
+        world
+            .emissions
+            .push(emission(EmissionKind::ActiveSonar, 0.0, 20_000.0));
+        let heard = intercepts(&world, world.entity(id).unwrap());
+        assert_eq!(heard.len(), 1);
+        assert_eq!(heard[0].kind, Some(EmissionKind::ActiveSonar));
+        assert_eq!(heard[0].to_string(), "active sonar bearing 000");
+        // seekers are high frequency and die away within a few kilometers
+        world.emissions = vec![emission(EmissionKind::TorpedoSeeker, 9_000.0, 0.0)];
+        assert!(intercepts(&world, world.entity(id).unwrap()).is_empty());
+        world.emissions = vec![emission(EmissionKind::TorpedoSeeker, 8_000.0, 0.0)];
+        let heard = intercepts(&world, world.entity(id).unwrap());
+        assert_eq!(heard[0].kind, None);
+    }
+
+    #[test]
+    fn seekers_first() {
+        let mut world = World::new();
+        let id = listener(&mut world);
+        world.emissions = vec![
+            emission(EmissionKind::ActiveSonar, 0.0, 2_000.0),
+            emission(EmissionKind::TorpedoSeeker, -2_000.0, 0.0),
+        ];
+        let heard = intercepts(&world, world.entity(id).unwrap());
+        assert_eq!(heard[0].kind, Some(EmissionKind::TorpedoSeeker));
+        assert_eq!(heard[0].to_string(), "torpedo seeker bearing 270");
+        assert!(heard[1].excess > heard[0].excess);
+    }
+
+    #[test]
+    fn sector_pulses_are_faint_abeam() {
+        let mut world = World::new();
+        let id = listener(&mut world);
+        let heard = |world: &World, pulse: &str| {
+            let mut emission = emission(EmissionKind::ActiveSonar, 0.0, 20_000.0);
+            emission.pulse = pulse.parse().unwrap();
+            let mut world = world.clone();
+            world.emissions = vec![emission];
+            intercepts(&world, world.entity(id).unwrap())
+                .first()
+                .map(|i| i.excess)
+        };
+        let omni = heard(&world, "medium medium omni").unwrap();
+        // sent east, the sector points away from the boat to the south
+        assert!(heard(&world, "medium medium sector").is_none_or(|e| e < omni - 15.0));
+        assert!(heard(&world, "long low omni").unwrap() > omni);
+    }
+
+    #[test]
+    fn alert_as_preferred() {
+        let alert = Alert {
+            time: 600.0,
+            heading: user_to_game_angle(90.0),
+            intercept: Intercept {
+                source: 7,
+                bearing: user_to_game_angle(45.0),
+                excess: 20.0,
+                kind: Some(EmissionKind::ActiveSonar),
+            },
+        };
+        assert_eq!(
+            alert.to_string(),
+            "00:10 INTERCEPT active sonar bearing 045"
+        );
+        let environment = Environment {
+            start_time: 13.0 * 3600.0,
+            ..Environment::default()
+        };
+        let preferences = Preferences {
+            bearings: BearingMode::Relative,
+            clock: Clock::TwelveHour,
+            ..Preferences::default()
+        };
+        assert_eq!(
+            alert.describe(&Catalog::default(), &preferences, &environment),
+            "1:10 pm INTERCEPT active sonar bearing 315 rel"
+        );
+    }
+
+    #[test]
+    fn needs_a_receiver() {
+        let mut world = World::new();
+        let boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        let id = world.spawn(boat);
+        world.emissions = vec![emission(EmissionKind::ActiveSonar, 0.0, 500.0)];
+        assert!(intercepts(&world, world.entity(id).unwrap()).is_empty());
+    }
+}
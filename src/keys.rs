@@ -0,0 +1,354 @@
+use std::fmt;
+use std::str::FromStr;
+
+use termion::event::Key;
+
+use crate::config::Config;
+
+// #############################
+// #       KEY BINDINGS        #
+// #############################
+
+// The player may put any command line, alias or macro (see console.rs) on
+// a key, at every station or only at one of them, so the same key does
+// what that station needs most. They are typed at the console:
+//
+// bind ctrl+d dive crash
+// bind sonar space mark datum bearing 10 20 45
+// unbind sonar space
+//
+// and kept in the player's profile with the aliases, the bindings of every
+// station in "[keys]" and those of one station, which come first there, in
+// "[keys.<station>]":
+//
+// [keys]
+// ctrl+d = dive crash
+// f5 = timers
+//
+// [keys.sonar]
+// space = mark datum bearing 10 20 45
+//
+// A key is a character, f1 to f12 or one of space, enter, tab, backtab,
+// esc, backspace, up, down, left, right, home, end, pageup, pagedown,
+// insert and delete, after any of the modifiers "ctrl+", "alt+" and
+// "shift+". "#" and "=" cannot be bound, being the comments and separators
+// of the profile.
+
+/// Keys with a name
+const NAMED_KEYS: &[&str] = &[
+    "space",
+    "enter",
+    "tab",
+    "backtab",
+    "esc",
+    "backspace",
+    "up",
+    "down",
+    "left",
+    "right",
+    "home",
+    "end",
+    "pageup",
+    "pagedown",
+    "insert",
+    "delete",
+];
+
+/// Where the player is, which decides the bindings that come first
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Station {
+    Conn,
+    Sonar,
+    Weapons,
+    Navigation,
+    Radio,
+}
+
+impl Station {
+    pub const ALL: [Station; 5] = [
+        Station::Conn,
+        Station::Sonar,
+        Station::Weapons,
+        Station::Navigation,
+        Station::Radio,
+    ];
+}
+
+impl fmt::Display for Station {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Station::Conn => "conn",
+            Station::Sonar => "sonar",
+            Station::Weapons => "weapons",
+            Station::Navigation => "navigation",
+            Station::Radio => "radio",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Station {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Station, String> {
+        Station::ALL
+            .iter()
+            .find(|station| station.to_string() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown station '{}'", s))
+    }
+}
+
+/// A key with the modifiers held down with it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// A character, lowercase, "f1" to "f12" or one of NAMED_KEYS
+    pub key: String,
+}
+
+impl Chord {
+    fn plain(key: &str) -> Chord {
+        Chord {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            key: key.to_string(),
+        }
+    }
+
+    /// The chord a terminal key press is, None for those that cannot be
+    /// bound
+    pub fn from_key(key: Key) -> Option<Chord> {
+        let chord = match key {
+            Key::Char(' ') => Chord::plain("space"),
+            Key::Char('\n') => Chord::plain("enter"),
+            Key::Char('\t') => Chord::plain("tab"),
+            Key::Char(c) => Chord::from_str(&c.to_string()).ok()?,
+            Key::Alt(c) => Chord {
+                alt: true,
+                ..Chord::from_key(Key::Char(c))?
+            },
+            Key::Ctrl(c) => Chord {
+                ctrl: true,
+                ..Chord::from_key(Key::Char(c))?
+            },
+            Key::F(n) => Chord::plain(&format!("f{}", n)),
+            Key::Backspace => Chord::plain("backspace"),
+            Key::Left => Chord::plain("left"),
+            Key::Right => Chord::plain("right"),
+            Key::Up => Chord::plain("up"),
+            Key::Down => Chord::plain("down"),
+            Key::Home => Chord::plain("home"),
+            Key::End => Chord::plain("end"),
+            Key::PageUp => Chord::plain("pageup"),
+            Key::PageDown => Chord::plain("pagedown"),
+            Key::BackTab => Chord::plain("backtab"),
+            Key::Delete => Chord::plain("delete"),
+            Key::Insert => Chord::plain("insert"),
+            Key::Esc => Chord::plain("esc"),
+            _ => return None,
+        };
+        Some(chord)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "ctrl+"),
+            (self.alt, "alt+"),
+            (self.shift, "shift+"),
+        ] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Chord, String> {
+        let mut chord = Chord::plain("");
+        let mut rest = s;
+        loop {
+            let lower = rest.to_lowercase();
+            if lower.starts_with("ctrl+") && rest.len() > 5 {
+                chord.ctrl = true;
+            } else if lower.starts_with("alt+") && rest.len() > 4 {
+                chord.alt = true;
+            } else if lower.starts_with("shift+") && rest.len() > 6 {
+                chord.shift = true;
+            } else {
+                break;
+            }
+            rest = &rest[rest.find('+').unwrap_or(0) + 1..];
+        }
+        let mut chars = rest.chars();
+        chord.key = match (chars.next(), chars.next()) {
+            (Some('#' | '='), None) => return Err(format!("'{}' cannot be bound", rest)),
+            (Some(c), None) if c.is_uppercase() => {
+                chord.shift = true;
+                c.to_lowercase().to_string()
+            }
+            (Some(c), None) if !c.is_whitespace() => c.to_string(),
+            _ => {
+                let name = rest.to_lowercase();
+                let function = name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .is_some_and(|n| (1..=12).contains(&n));
+                if !function && !NAMED_KEYS.contains(&name.as_str()) {
+                    return Err(format!("unknown key '{}'", s));
+                }
+                name
+            }
+        };
+        Ok(chord)
+    }
+}
+
+/// A command line on a chord, at one station or at all
+#[derive(Debug, PartialEq, Clone)]
+pub struct Binding {
+    pub station: Option<Station>,
+    pub chord: Chord,
+    pub line: String,
+}
+
+/// The bindings of a player
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Keymap {
+    pub bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// Reads the bindings of a profile, skipping the keys it cannot read
+    pub fn read(config: &Config) -> Keymap {
+        let mut keymap = Keymap::default();
+        let sections = config.section("keys").map(|s| (None, s)).into_iter().chain(
+            config
+                .sections_with_prefix("keys")
+                .filter_map(|(name, s)| Some((Some(name.parse().ok()?), s))),
+        );
+        for (station, section) in sections {
+            for (key, line) in section.entries() {
+                if let Ok(chord) = key.parse() {
+                    keymap.bind(station, chord, line);
+                }
+            }
+        }
+        keymap
+    }
+
+    pub fn write(&self, config: &mut Config) {
+        config.remove_section("keys");
+        for station in Station::ALL {
+            config.remove_section(&format!("keys.{}", station));
+        }
+        for binding in &self.bindings {
+            let section = match binding.station {
+                Some(station) => format!("keys.{}", station),
+                None => "keys".to_string(),
+            };
+            config
+                .section_mut(&section)
+                .set(&binding.chord.to_string(), &binding.line);
+        }
+    }
+
+    /// Puts `line` on `chord`, in place of what was on it there
+    pub fn bind(&mut self, station: Option<Station>, chord: Chord, line: &str) {
+        let binding = Binding {
+            station,
+            chord,
+            line: line.to_string(),
+        };
+        let same = |b: &Binding| b.station == binding.station && b.chord == binding.chord;
+        match self.bindings.iter_mut().find(|b| same(b)) {
+            Some(old) => *old = binding,
+            None => self.bindings.push(binding),
+        }
+    }
+
+    /// Takes what is on `chord` off, returning whether there was anything
+    pub fn unbind(&mut self, station: Option<Station>, chord: &Chord) -> bool {
+        let count = self.bindings.len();
+        self.bindings
+            .retain(|b| !(b.station == station && b.chord == *chord));
+        self.bindings.len() < count
+    }
+
+    /// The line `chord` runs at `station`: its own binding, or the one for
+    /// every station
+    pub fn lookup(&self, station: Station, chord: &Chord) -> Option<&str> {
+        let find = |at: Option<Station>| {
+            self.bindings
+                .iter()
+                .find(|b| b.station == at && b.chord == *chord)
+                .map(|b| b.line.as_str())
+        };
+        find(Some(station)).or_else(|| find(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> Chord {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reads_and_writes_chords() {
+        assert_eq!(chord("Ctrl+Alt+d").to_string(), "ctrl+alt+d");
+        assert_eq!(chord("D"), chord("shift+d"));
+        assert_eq!(chord("F5").to_string(), "f5");
+        assert_eq!(chord("ctrl+pageup").to_string(), "ctrl+pageup");
+        assert_eq!(chord("+").key, "+");
+        assert!("f13".parse::<Chord>().is_err());
+        assert!("ctrl+#".parse::<Chord>().is_err());
+        assert!("hyper+x".parse::<Chord>().is_err());
+
+        assert_eq!(Chord::from_key(Key::Ctrl('d')), Some(chord("ctrl+d")));
+        assert_eq!(Chord::from_key(Key::Char('D')), Some(chord("shift+d")));
+        assert_eq!(Chord::from_key(Key::Char(' ')), Some(chord("space")));
+        assert_eq!(Chord::from_key(Key::F(5)), Some(chord("f5")));
+        assert_eq!(Chord::from_key(Key::Null), None);
+    }
+
+    #[test]
+    fn stations_come_before_every_station() {
+        let config = Config::parse(
+            "[keys]\nctrl+d = dive crash\nspace = continue\n\n\
+             [keys.sonar]\nspace = mark datum bearing 10 20 45\n\n[keys.galley]\nx = y",
+        )
+        .unwrap();
+        let mut keymap = Keymap::read(&config);
+        assert_eq!(keymap.bindings.len(), 3);
+        let space = chord("space");
+        assert_eq!(
+            keymap.lookup(Station::Sonar, &space),
+            Some("mark datum bearing 10 20 45")
+        );
+        assert_eq!(keymap.lookup(Station::Conn, &space), Some("continue"));
+        assert_eq!(
+            keymap.lookup(Station::Sonar, &chord("ctrl+d")),
+            Some("dive crash")
+        );
+        assert!(keymap.unbind(Some(Station::Sonar), &space));
+        assert!(!keymap.unbind(Some(Station::Sonar), &space));
+        assert_eq!(keymap.lookup(Station::Sonar, &space), Some("continue"));
+
+        keymap.bind(Some(Station::Weapons), chord("f1"), "door open 1");
+        let mut written = Config::new();
+        keymap.write(&mut written);
+        assert_eq!(Keymap::read(&written), keymap);
+    }
+}
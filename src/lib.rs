@@ -0,0 +1,100 @@
+pub mod acoustics;
+pub mod ai;
+pub mod approach;
+pub mod asw;
+pub mod atmosphere;
+pub mod autopilot;
+pub mod baffles;
+pub mod balance;
+pub mod camera;
+pub mod captain;
+pub mod cargo;
+pub mod casualties;
+pub mod chart;
+pub mod coastline;
+pub mod command;
+pub mod config;
+pub mod console;
+pub mod contacts;
+pub mod coverage;
+pub mod crew;
+pub mod datum;
+pub mod debrief;
+pub mod decoy;
+pub mod dive;
+pub mod editor;
+pub mod environment;
+pub mod era;
+pub mod escort;
+pub mod events;
+pub mod faction;
+pub mod forces;
+pub mod fuse;
+pub mod generator;
+pub mod geo;
+pub mod governor;
+pub mod gunnery;
+pub mod hazards;
+pub mod helicopter;
+pub mod helm;
+pub mod help;
+pub mod hfdf;
+pub mod history;
+pub mod identification;
+pub mod intercept;
+pub mod keys;
+pub mod logbook;
+pub mod lookouts;
+pub mod machinery;
+pub mod messages;
+pub mod moon;
+pub mod morale;
+pub mod narration;
+pub mod navigation;
+pub mod noise;
+pub mod physics;
+pub mod plot;
+pub mod preferences;
+pub mod preview;
+pub mod propagation;
+pub mod pulse;
+pub mod radar;
+pub mod random;
+pub mod registry;
+pub mod reliability;
+pub mod rendezvous;
+pub mod reverberation;
+pub mod route;
+pub mod savefile;
+pub mod scenario;
+pub mod seafloor;
+pub mod seakeeping;
+pub mod seeker;
+pub mod sensors;
+pub mod shadowing;
+pub mod signals;
+pub mod simulation;
+pub mod snapshot;
+pub mod sound;
+pub mod stopwatch;
+pub mod stores;
+pub mod theater;
+pub mod theme;
+pub mod torpedo;
+pub mod tournament;
+pub mod trace;
+pub mod tracking;
+pub mod traffic;
+pub mod transient;
+pub mod tuning;
+pub mod tutorial;
+pub mod units;
+pub mod vds;
+pub mod vessel;
+pub mod wake;
+pub mod weapons;
+pub mod weather;
+pub mod wolfpack;
+pub mod world;
+pub mod xbt;
+pub mod zone;
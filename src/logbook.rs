@@ -0,0 +1,369 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::environment::Environment;
+use crate::events::Event;
+use crate::messages::Catalog;
+use crate::navigation::Navigation;
+use crate::physics::Point;
+use crate::preferences::Preferences;
+use crate::world::{EntityId, World};
+
+// #############################
+// #        PATROL LOG         #
+// #############################
+
+// The captain keeps a log of the patrol, and the game keeps it for them:
+// every event that matters to the own ship goes in as it happens, with the
+// time, the position the boat reckoned it was at (see navigation.rs) and a
+// line of text. Torpedoes fired, hits, failures, ships crippled and sunk,
+// damage taken, broaching, running aground, zones entered, transmissions
+// and rendezvous; not contacts, which the debrief has (see debrief.rs).
+//
+// "log save <file>" writes it out at the end of the mission, as a JSON
+// document when the file name ends in ".json" and as a markdown table
+// otherwise:
+//
+// | Date | Time | Position | Entry |
+// | --- | --- | --- | --- |
+// | 1941-05-20 | 06:45 | 1200, -3400 | torpedo hit SS Test |
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LogError {
+    /// The log could not be written, with why
+    File(String),
+}
+
+impl LogError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            LogError::File(error) => messages.format("error-log-file", &[("error", error)]),
+        }
+    }
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for LogError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LogEntry {
+    /// Seconds into the scenario
+    pub time: f32,
+    /// Where the boat reckoned it was
+    pub position: Point,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PatrolLog {
+    pub entries: Vec<LogEntry>,
+    /// Ships sunk and their gross register tons, 0 for those without a hold
+    pub sunk: Vec<(EntityId, u32)>,
+    /// Ships the own ship hit, whose sinking is its own
+    hit: Vec<EntityId>,
+    /// Index into the world events of the first not yet logged
+    read: usize,
+}
+
+impl PatrolLog {
+    /// Logs the events of `world` since the last call that matter to `own`
+    pub fn record(
+        &mut self,
+        world: &World,
+        own: EntityId,
+        navigation: &Navigation,
+        messages: &Catalog,
+    ) {
+        let position = match world.entity(own) {
+            Some(boat) => navigation.position(&boat.position),
+            None => return,
+        };
+        let name = |id: EntityId| world.entity(id).map_or(id.to_string(), |e| e.name.clone());
+        let events = &world.events[self.read.min(world.events.len())..];
+        self.read = world.events.len();
+        for timed in events {
+            let text = match &timed.event {
+                Event::TorpedoFired { shooter, .. } if *shooter == own => {
+                    messages.get("log-torpedo-fired").to_string()
+                }
+                Event::TorpedoHit {
+                    shooter, target, ..
+                } if *shooter == own => {
+                    self.hit.push(*target);
+                    messages.format("log-torpedo-hit", &[("target", &name(*target))])
+                }
+                Event::TorpedoHit { target, .. } if *target == own => {
+                    messages.get("log-torpedoed").to_string()
+                }
+                Event::TorpedoFailed {
+                    shooter, failure, ..
+                } if *shooter == own => messages.format(
+                    "log-torpedo-failed",
+                    &[("failure", &messages.get(failure.message()))],
+                ),
+                Event::ShellHit {
+                    shooter, target, ..
+                } if *shooter == own => {
+                    if !self.hit.contains(target) {
+                        self.hit.push(*target);
+                    }
+                    continue;
+                }
+                Event::ShellHit {
+                    shooter, target, ..
+                } if *target == own => {
+                    messages.format("log-shelled", &[("shooter", &name(*shooter))])
+                }
+                Event::Destroyed { entity } if *entity == own => {
+                    messages.get("log-lost").to_string()
+                }
+                Event::Destroyed { entity } if self.hit.contains(entity) => {
+                    let tonnage = world
+                        .entity(*entity)
+                        .and_then(|e| e.hold.as_ref())
+                        .map_or(0, |hold| hold.tonnage);
+                    self.sunk.push((*entity, tonnage));
+                    if tonnage > 0 {
+                        messages.format(
+                            "log-sunk-tonnage",
+                            &[("target", &name(*entity)), ("tonnage", &tonnage)],
+                        )
+                    } else {
+                        messages.format("log-sunk", &[("target", &name(*entity))])
+                    }
+                }
+                Event::Crippled { entity } if self.hit.contains(entity) => {
+                    messages.format("log-crippled", &[("target", &name(*entity))])
+                }
+                Event::Broached { entity } if *entity == own => {
+                    messages.get("log-broached").to_string()
+                }
+                Event::RanAground { entity } if *entity == own => {
+                    messages.get("log-aground").to_string()
+                }
+                Event::TouchedBottom { entity } if *entity == own => {
+                    messages.get("log-bottom").to_string()
+                }
+                Event::EnteredZone { entity, zone } if *entity == own => {
+                    messages.format("log-zone", &[("zone", zone)])
+                }
+                Event::HazardStruck { entity, kind } if *entity == own => {
+                    messages.format("log-hazard", &[("kind", &messages.get(kind.message()))])
+                }
+                Event::Transmitted { entity, seconds } if *entity == own => {
+                    messages.format("log-transmitted", &[("seconds", &(*seconds as u32))])
+                }
+                Event::TransferInterrupted { entity, name } if *entity == own => {
+                    messages.format("rendezvous-interrupted", &[("name", name)])
+                }
+                Event::MissionCompleted { entity, name } if *entity == own => {
+                    messages.format("rendezvous-completed", &[("name", name)])
+                }
+                Event::MissionFailed { name } => {
+                    messages.format("rendezvous-failed", &[("name", name)])
+                }
+                Event::Resupplied { entity, name } if *entity == own => {
+                    messages.format("rendezvous-resupplied", &[("name", name)])
+                }
+                Event::TrailCompleted { entity, name } if *entity == own => {
+                    messages.format("trail-completed", &[("name", name)])
+                }
+                Event::TrailDetected { entity, name } if *entity == own => {
+                    messages.format("trail-detected", &[("name", name)])
+                }
+                Event::TrailFailed { name } => messages.format("trail-failed", &[("name", name)]),
+                _ => continue,
+            };
+            self.entries.push(LogEntry {
+                time: timed.time,
+                position: position.clone(),
+                text,
+            });
+        }
+    }
+
+    /// Gross register tons sunk
+    pub fn tonnage(&self) -> u32 {
+        self.sunk.iter().map(|(_, tonnage)| tonnage).sum()
+    }
+
+    /// The log as a markdown document, headed with the name of the boat
+    pub fn to_markdown(
+        &self,
+        boat: &str,
+        environment: &Environment,
+        preferences: &Preferences,
+    ) -> String {
+        let mut text = format!("# Patrol log, {}\n\n", boat);
+        text.push_str("| Date | Time | Position | Entry |\n| --- | --- | --- | --- |\n");
+        for entry in &self.entries {
+            text.push_str(&format!(
+                "| {} | {} | {:.0}, {:.0} | {} |\n",
+                preferences.date(environment, entry.time),
+                preferences.time(environment, entry.time),
+                entry.position.x,
+                entry.position.y,
+                entry.text.replace('|', "\\|"),
+            ));
+        }
+        text.push_str(&format!(
+            "\nSunk: {} ships, {} GRT\n",
+            self.sunk.len(),
+            self.tonnage()
+        ));
+        text
+    }
+
+    /// The log as a JSON document
+    pub fn to_json(
+        &self,
+        boat: &str,
+        environment: &Environment,
+        preferences: &Preferences,
+    ) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{{\"time\": {}, \"date\": {}, \"clock\": {}, \"x\": {:.0}, \"y\": {:.0}, \
+                     \"text\": {}}}",
+                    entry.time,
+                    json_string(&preferences.date(environment, entry.time)),
+                    json_string(&preferences.time(environment, entry.time)),
+                    entry.position.x,
+                    entry.position.y,
+                    json_string(&entry.text),
+                )
+            })
+            .collect();
+        format!(
+            "{{\"boat\": {}, \"entries\": [{}], \"sunk\": {}, \"tonnage\": {}}}\n",
+            json_string(boat),
+            entries.join(", "),
+            self.sunk.len(),
+            self.tonnage()
+        )
+    }
+
+    /// Writes the log to `path`, as JSON for a ".json" file and markdown
+    /// otherwise
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        boat: &str,
+        environment: &Environment,
+        preferences: &Preferences,
+    ) -> Result<(), LogError> {
+        let path = path.as_ref();
+        let text = if path.extension().is_some_and(|e| e == "json") {
+            self.to_json(boat, environment, preferences)
+        } else {
+            self.to_markdown(boat, environment, preferences)
+        };
+        std::fs::write(path, text).map_err(|e| LogError::File(e.to_string()))
+    }
+}
+
+/// `s` as a quoted JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::{CargoKind, Hold};
+    use crate::world::{Entity, EntityKind};
+
+    fn patrol() -> (World, PatrolLog) {
+        let mut world = World::new();
+        let boat = world.spawn(Entity::new(
+            "U-99",
+            EntityKind::Submarine,
+            Point {
+                x: 1200.0,
+                y: -3400.0,
+            },
+        ));
+        let mut merchant = Entity::new("SS Test", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        merchant.hold = Some(Hold::new(7176, CargoKind::Ore, 5));
+        let merchant = world.spawn(merchant);
+        world.emit(Event::TorpedoFired {
+            shooter: boat,
+            torpedo: 9,
+        });
+        world.emit(Event::TorpedoHit {
+            torpedo: 9,
+            shooter: boat,
+            target: merchant,
+        });
+        world.emit(Event::Destroyed { entity: merchant });
+        // not the boat's doing
+        world.emit(Event::Destroyed { entity: 7 });
+        let mut log = PatrolLog::default();
+        log.record(&world, boat, &Navigation::default(), &Catalog::default());
+        (world, log)
+    }
+
+    #[test]
+    fn logs_what_matters_to_the_boat() {
+        let (world, mut log) = patrol();
+        let texts: Vec<&str> = log.entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "fired a torpedo",
+                "torpedo hit SS Test",
+                "SS Test sunk, 7176 GRT"
+            ]
+        );
+        assert_eq!(
+            log.entries[0].position,
+            Point {
+                x: 1200.0,
+                y: -3400.0
+            }
+        );
+        assert_eq!(log.tonnage(), 7176);
+        // nothing logged twice
+        log.record(&world, 1, &Navigation::default(), &Catalog::default());
+        assert_eq!(log.entries.len(), 3);
+    }
+
+    #[test]
+    fn exports_markdown_and_json() {
+        let (world, log) = patrol();
+        let preferences = Preferences::default();
+        let markdown = log.to_markdown("U-99", &world.environment, &preferences);
+        assert!(markdown.starts_with("# Patrol log, U-99\n"));
+        assert!(markdown.contains("| 1200, -3400 | torpedo hit SS Test |\n"));
+        assert!(markdown.ends_with("Sunk: 1 ships, 7176 GRT\n"));
+
+        let json = log.to_json("U-99 \"Snowman\"", &world.environment, &preferences);
+        assert!(
+            json.starts_with("{\"boat\": \"U-99 \\\"Snowman\\\"\", \"entries\": [{\"time\": 0,")
+        );
+        assert!(json.contains("\"x\": 1200, \"y\": -3400, \"text\": \"fired a torpedo\"}"));
+        assert!(json.ends_with("\"sunk\": 1, \"tonnage\": 7176}\n"));
+        assert_eq!(json_string("a\nb\u{1}"), "\"a\\nb\\u0001\"");
+    }
+}
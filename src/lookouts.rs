@@ -0,0 +1,312 @@
+use crate::events::Event;
+use crate::faction::Stance;
+use crate::gunnery;
+use crate::moon;
+use crate::physics::{turn_towards, Point, KNOT};
+use crate::seakeeping;
+use crate::tuning::Tunable;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #     SURFACE LOOKOUTS      #
+// #############################
+
+// The bridges of surface ships are manned day and night, and their
+// lookouts sight what sonar may miss: the feather of a periscope, a boat
+// on the surface, the track of a torpedo coming in. How far they see is the
+// visibility, shortened by a lively deck (see seakeeping.rs) and by the
+// dark, less under a bright moon and for a target silhouetted against it
+// (see moon.rs); a periscope cutting the water at speed throws up a
+// feather seen much further off than one barely moving.
+//
+// A sighting is an Event::Sighted, and the ships of the sighting side act
+// on it: the merchants nearby make an emergency turn away from it, and
+// the nearest escorts run in to attack the datum, the boat itself or, for
+// a torpedo track, back along it where it came from, and hunt there (see
+// escort.rs).
+
+/// Meters at which a periscope is sighted in clear weather by day, still
+/// and throwing up a feather
+const PERISCOPE_SIGHTING: f32 = 1_500.0;
+const FEATHER_SIGHTING: f32 = 4_000.0;
+/// Meters per second over which a raised periscope throws up a feather
+const FEATHER_SPEED: f32 = 3.0 * KNOT;
+/// Meters at which a boat on the surface and a torpedo track are sighted
+const SURFACED_SIGHTING: f32 = 8_000.0;
+const TRACK_SIGHTING: f32 = 2_500.0;
+/// Chance a second of the lookouts sighting what is in range
+const SCAN_CHANCE: f32 = 0.2;
+/// Meters back along a torpedo track the escorts look for the boat
+const TRACK_BACK: f32 = 3_000.0;
+/// Meters from the sighting ship within which merchants turn away and
+/// escorts are sent
+const CONVOY_RADIUS: f32 = 10_000.0;
+const ESCORT_RADIUS: f32 = 20_000.0;
+/// Seconds merchants hold their emergency turn
+const TURN_TIME: f32 = 900.0;
+/// Escorts sent to each datum, the speed they run in at and the meters
+/// from it at which they are there
+const RESPONDERS: usize = 2;
+const ATTACK_SPEED: f32 = 20.0 * KNOT;
+const ON_DATUM: f32 = 300.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SightingKind {
+    Periscope,
+    Surfaced,
+    TorpedoTrack,
+}
+
+/// A merchant turned away from a sighting
+#[derive(Debug, PartialEq, Clone)]
+struct Turn {
+    ship: EntityId,
+    /// Game angle it holds
+    heading: f32,
+    until: f32,
+}
+
+/// What the lookouts hold, and what the ships are doing about it
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Lookouts {
+    /// Observer and target pairs in sight, reported once until lost
+    held: Vec<(EntityId, EntityId)>,
+    turns: Vec<Turn>,
+    /// Escorts running in, and the datum they run to
+    attacks: Vec<(EntityId, Point)>,
+}
+
+/// What of `target` shows to a lookout, and how far off it can be sighted
+/// in clear weather by day
+fn visible(world: &World, observer: &Entity, target: &Entity) -> Option<(SightingKind, f32)> {
+    match target.kind {
+        EntityKind::Submarine => {
+            if world.diplomacy.stance(observer, target) != Stance::Hostile {
+                return None;
+            }
+            let exposure = gunnery::exposure(target);
+            if exposure >= 0.5 {
+                Some((SightingKind::Surfaced, SURFACED_SIGHTING))
+            } else if exposure > 0.0 && target.speed > FEATHER_SPEED {
+                Some((SightingKind::Periscope, FEATHER_SIGHTING))
+            } else if exposure > 0.0 {
+                Some((SightingKind::Periscope, PERISCOPE_SIGHTING))
+            } else {
+                None
+            }
+        }
+        EntityKind::Torpedo => {
+            let shooter = world.entity(target.torpedo.as_ref()?.shooter)?;
+            if world.diplomacy.stance(observer, shooter) != Stance::Hostile {
+                return None;
+            }
+            Some((SightingKind::TorpedoTrack, TRACK_SIGHTING))
+        }
+        _ => None,
+    }
+}
+
+/// Meters at which the lookouts of `observer` see what shows `clear`
+/// meters off in clear weather by day, on `bearing` (a game angle)
+pub fn sighting_range(world: &World, observer: &Entity, clear: f32, bearing: f32) -> f32 {
+    let environment = &world.environment;
+    let light =
+        moon::light(environment, world.time) * moon::silhouette(environment, world.time, bearing);
+    clear.min(seakeeping::sighting_range(observer, environment)) * light
+}
+
+/// Where the escorts look for the boat behind a sighting
+fn datum(target: &Entity, kind: SightingKind) -> Point {
+    if kind != SightingKind::TorpedoTrack {
+        return target.position.clone();
+    }
+    let back = target.heading + std::f32::consts::PI;
+    Point {
+        x: target.position.x + TRACK_BACK * back.cos(),
+        y: target.position.y + TRACK_BACK * back.sin(),
+    }
+}
+
+/// Sends the merchants near `observer` away from `datum` and the nearest
+/// of its escorts to it
+fn respond(world: &mut World, observer: EntityId, datum: &Point) {
+    let observer = match world.entity(observer) {
+        Some(observer) => observer.clone(),
+        None => return,
+    };
+    let time = world.time;
+    let mut escorts = Vec::new();
+    for ship in world.entities.iter() {
+        if ship.is_destroyed() || ship.side != observer.side {
+            continue;
+        }
+        let range = ship.position.distance_to(&observer.position);
+        match ship.kind {
+            EntityKind::Merchant if range <= CONVOY_RADIUS => {
+                world.lookouts.turns.retain(|t| t.ship != ship.id);
+                world.lookouts.turns.push(Turn {
+                    ship: ship.id,
+                    heading: datum.angle_to(&ship.position),
+                    until: time + TURN_TIME,
+                });
+            }
+            EntityKind::Warship
+                if range <= ESCORT_RADIUS
+                    && !world.lookouts.attacks.iter().any(|(id, _)| *id == ship.id) =>
+            {
+                escorts.push((ship.id, ship.position.distance_to(datum)));
+            }
+            _ => {}
+        }
+    }
+    escorts.sort_by(|a, b| a.1.total_cmp(&b.1));
+    for (id, _) in escorts.into_iter().take(RESPONDERS) {
+        world.lookouts.attacks.push((id, datum.clone()));
+    }
+}
+
+/// Has the lookouts of every surface ship scan, and steers the merchants
+/// turning away and the escorts running in
+pub fn update(world: &mut World, dt: f32) {
+    let mut in_sight = Vec::new();
+    for observer in world.entities.iter() {
+        if !matches!(observer.kind, EntityKind::Warship | EntityKind::Merchant)
+            || observer.is_destroyed()
+        {
+            continue;
+        }
+        for target in world.entities.iter() {
+            if let Some((kind, clear)) = visible(world, observer, target) {
+                let range = observer.position.distance_to(&target.position);
+                let bearing = observer.position.angle_to(&target.position);
+                if range <= sighting_range(world, observer, clear, bearing) {
+                    in_sight.push((observer.id, target.id, kind, datum(target, kind)));
+                }
+            }
+        }
+    }
+    let held = std::mem::take(&mut world.lookouts.held);
+    for (observer, target, kind, datum) in in_sight {
+        if held.contains(&(observer, target)) {
+            world.lookouts.held.push((observer, target));
+        } else if world.rng.chance(SCAN_CHANCE * dt) {
+            world.lookouts.held.push((observer, target));
+            world.emit(Event::Sighted {
+                observer,
+                target,
+                kind,
+            });
+            respond(world, observer, &datum);
+        }
+    }
+
+    let time = world.time;
+    let turn_rate = Tunable::TurnRate.get(&world.environment.tuning) * dt;
+    let mut turns = std::mem::take(&mut world.lookouts.turns);
+    turns.retain(|turn| match world.entities.get_mut(turn.ship) {
+        Some(ship) if !ship.is_destroyed() && turn.until > time => {
+            ship.heading = turn_towards(ship.heading, turn.heading, turn_rate);
+            true
+        }
+        _ => false,
+    });
+    world.lookouts.turns = turns;
+    let mut attacks = std::mem::take(&mut world.lookouts.attacks);
+    attacks.retain(|(id, to)| {
+        let escort = match world.entities.get_mut(*id) {
+            Some(escort) if !escort.is_destroyed() => escort,
+            _ => return false,
+        };
+        if escort.position.distance_to(to) < ON_DATUM {
+            if let Some(ai) = escort.escort_ai.as_mut() {
+                ai.alert(to.clone(), time);
+            }
+            return false;
+        }
+        let desired = escort.position.angle_to(to);
+        escort.heading = turn_towards(escort.heading, desired, turn_rate);
+        escort.speed = ATTACK_SPEED;
+        true
+    });
+    world.lookouts.attacks = attacks;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::NIGHT_LIGHT;
+    use crate::gunnery::PERISCOPE_DEPTH;
+
+    /// An escort and a merchant at the origin, a boat at periscope depth
+    /// `range` meters east
+    fn waters(range: f32, speed: f32) -> World {
+        let mut world = World::new();
+        world.environment.start_time = 12.0 * 3600.0;
+        let origin = Point { x: 0.0, y: 0.0 };
+        world.spawn(Entity::new("escort", EntityKind::Warship, origin.clone()));
+        world.spawn(Entity::new("merchant", EntityKind::Merchant, origin));
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: range, y: 0.0 });
+        boat.depth = PERISCOPE_DEPTH;
+        boat.mast_raised = true;
+        boat.speed = speed;
+        boat.side = Some("axis".to_string());
+        world.spawn(boat);
+        world
+    }
+
+    fn sightings(world: &World) -> Vec<&Event> {
+        world
+            .events
+            .iter()
+            .map(|e| &e.event)
+            .filter(|e| matches!(e, Event::Sighted { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn feathers_are_seen_further_and_darkness_hides() {
+        let mut slow = waters(3_000.0, 0.0);
+        let mut fast = waters(3_000.0, 4.0);
+        for _ in 0..60 {
+            slow.step(1.0);
+            fast.step(1.0);
+        }
+        assert!(sightings(&slow).is_empty());
+        let seen = sightings(&fast);
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|e| matches!(
+            e,
+            Event::Sighted {
+                target: 3,
+                kind: SightingKind::Periscope,
+                ..
+            }
+        )));
+
+        // noon, then the midnight of a new moon
+        let mut night = waters(0.0, 0.0);
+        night.environment.date = "1941-05-25".parse().unwrap();
+        let escort = night.entity(1).unwrap();
+        let range = sighting_range(&night, escort, SURFACED_SIGHTING, 0.0);
+        assert_eq!(range, SURFACED_SIGHTING);
+        night.time = 12.0 * 3600.0;
+        let range = sighting_range(&night, night.entity(1).unwrap(), SURFACED_SIGHTING, 0.0);
+        assert!((range - SURFACED_SIGHTING * NIGHT_LIGHT).abs() < 0.1);
+    }
+
+    #[test]
+    fn merchants_turn_away_and_escorts_run_in() {
+        let mut world = waters(1_000.0, 0.0);
+        for _ in 0..120 {
+            world.step(1.0);
+        }
+        // by both the escort and the merchant
+        assert_eq!(sightings(&world).len(), 2);
+        let merchant = world.entity(2).unwrap();
+        // turning west, away from the boat to the east
+        assert!(merchant.heading.cos() < 0.0, "{}", merchant.heading);
+        let escort = world.entity(1).unwrap();
+        assert_eq!(escort.speed, ATTACK_SPEED);
+        assert!(escort.position.x > 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::noise::{NoiseContributor, NoiseSource};
+use crate::world::{Entity, World};
+
+// #############################
+// #      MACHINERY WEAR       #
+// #############################
+
+// A boat is at its quietest fresh out of the dockyard. Weeks of running
+// wear the bearings, put the shafts out of true and settle the mounts, and
+// the machinery radiates more broadband the longer it runs; faster still
+// once the spare parts are used up (see stores.rs). The shock of depth
+// charges and shells knocks the mounts loose, and a loose mount sings at
+// the shaft rate: a tonal, which a sonar picks out of the noise far off.
+// Neither is made good at sea: a tender brings stores but no dockyard, and
+// only a refit in port overhauls the machinery. Wear is tracked wherever
+// stores are, is kept by saves, and may be given for a placement:
+//
+// [entity.U-99]
+// running_hours = 400     # optional, hours since the last overhaul
+// shock = 0.2             # optional, shock taken since, as hull lost
+//
+// Both show in the noise report, so that a captain heard ever farther off
+// as a patrol goes on can tell why.
+
+/// Running hours before the wear of the machinery is heard
+pub const WORN_HOURS: f32 = 200.0;
+/// How much faster the machinery wears without spare parts
+const NO_SPARES_WEAR: f32 = 2.0;
+
+/// Wear and shock the machinery took since its last overhaul
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Machinery {
+    /// Running hours, counting double without spare parts
+    pub running_hours: f32,
+    /// Shock taken, as the hull integrity lost to it
+    pub shock: f32,
+}
+
+impl Machinery {
+    /// Whether the wear is heard
+    pub fn is_worn(&self) -> bool {
+        self.running_hours > WORN_HOURS
+    }
+
+    /// What the wear and the shock add to the noise radiated
+    pub fn contributors(&self) -> Vec<NoiseContributor> {
+        let mut noise = Vec::new();
+        if self.is_worn() {
+            noise.push(NoiseContributor {
+                source: NoiseSource::WornMachinery,
+                level: 104.0 + 10.0 * (self.running_hours / WORN_HOURS).log10(),
+            });
+        }
+        if self.shock > 0.0 {
+            noise.push(NoiseContributor {
+                source: NoiseSource::LooseMounts,
+                level: 106.0 + 20.0 * self.shock.min(1.0),
+            });
+        }
+        noise
+    }
+
+    /// Runs the machinery for `dt` seconds
+    fn run(&mut self, dt: f32, spares: bool) {
+        let rate = if spares { 1.0 } else { NO_SPARES_WEAR };
+        self.running_hours += rate * dt / 3600.0;
+    }
+}
+
+/// Wears the machinery of the ships making way over `dt` seconds
+pub fn update(world: &mut World, dt: f32) {
+    for entity in world.entities.iter_mut() {
+        if entity.speed <= 0.0 {
+            continue;
+        }
+        let spares = entity
+            .stores
+            .as_ref()
+            .is_none_or(|s| s.capacity.spares <= 0.0 || s.remaining.spares > 0.0);
+        if let Some(machinery) = entity.machinery.as_mut() {
+            machinery.run(dt, spares);
+        }
+    }
+}
+
+/// Records the shock of `amount` of hull lost by `entity`
+pub fn shake(entity: &mut Entity, amount: f32) {
+    if let Some(machinery) = entity.machinery.as_mut() {
+        machinery.shock += amount;
+    }
+}
+
+/// Overhauls the machinery of `entity`, refitting in a port
+pub fn overhaul(entity: &mut Entity) {
+    if let Some(machinery) = entity.machinery.as_mut() {
+        *machinery = Machinery::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise;
+    use crate::physics::Point;
+    use crate::world::EntityKind;
+
+    fn boat() -> Entity {
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.machinery = Some(Machinery::default());
+        boat.speed = 4.0;
+        boat.depth = 50.0;
+        boat
+    }
+
+    #[test]
+    fn wears_with_running_hours() {
+        let mut world = World::new();
+        world.spawn(boat());
+        let mut stopped = boat();
+        stopped.speed = 0.0;
+        world.spawn(stopped);
+        let fresh = noise::radiated_level(world.entity(1).unwrap());
+        update(&mut world, 100.0 * 3600.0);
+        assert!(!world
+            .entity(1)
+            .unwrap()
+            .machinery
+            .as_ref()
+            .unwrap()
+            .is_worn());
+        update(&mut world, 300.0 * 3600.0);
+        let boat = world.entity(1).unwrap();
+        let machinery = boat.machinery.as_ref().unwrap();
+        assert_eq!(machinery.running_hours, 400.0);
+        assert!(noise::contributors(boat)
+            .iter()
+            .any(|c| c.source == NoiseSource::WornMachinery));
+        assert!(noise::radiated_level(boat) > fresh);
+        let idle = world.entity(2).unwrap().machinery.as_ref().unwrap();
+        assert_eq!(idle.running_hours, 0.0);
+    }
+
+    #[test]
+    fn shock_knocks_the_mounts_loose() {
+        let mut world = World::new();
+        let id = world.spawn(boat());
+        world.apply_damage(id, 0.3);
+        let boat = world.entity(id).unwrap();
+        let mounts = Machinery {
+            running_hours: 0.0,
+            shock: 0.3,
+        };
+        assert_eq!(boat.machinery, Some(mounts));
+        let tonal = &boat.machinery.as_ref().unwrap().contributors()[0];
+        assert_eq!(tonal.source, NoiseSource::LooseMounts);
+        assert_eq!(tonal.level, 112.0);
+    }
+}
@@ -1,15 +1,62 @@
-mod physics;
-use physics::Point;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
 
+use subsim::editor;
+use subsim::trace::{self, Filter, Tracer};
 
-fn main() {
-    println!("Hello, world!");
-    let a = String::from("teste");
-    let b = a;
-
-    println!("{}",b);
+/// Installs a tracer when SUBSIM_LOG (a filter such as "info,ai=debug") or
+/// SUBSIM_PROFILE (a file for folded stacks) is set
+fn start_tracing() -> Result<(), String> {
+    let log = env::var("SUBSIM_LOG").ok();
+    let profile = env::var_os("SUBSIM_PROFILE").is_some();
+    if log.is_none() && !profile {
+        return Ok(());
+    }
+    let filter = match log {
+        Some(spec) => spec.parse()?,
+        None => "off".parse::<Filter>()?,
+    };
+    let mut tracer = Tracer::new(filter).writing_to(Box::new(io::stderr()));
+    if profile {
+        tracer = tracer.profiling();
+    }
+    trace::install(tracer);
+    Ok(())
+}
 
-    let p = Point { x:10.12345, y:12.0 };
-    println!("{}", p);
+fn finish_tracing() -> io::Result<()> {
+    let tracer = match trace::uninstall() {
+        Some(tracer) => tracer,
+        None => return Ok(()),
+    };
+    match env::var_os("SUBSIM_PROFILE") {
+        Some(path) => fs::write(path, tracer.folded()),
+        None => Ok(()),
+    }
+}
 
+fn main() {
+    if let Err(e) = start_tracing() {
+        eprintln!("SUBSIM_LOG: {}", e);
+        process::exit(1);
+    }
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let result = editor::run(&args);
+    if let Err(e) = finish_tracing() {
+        eprintln!("SUBSIM_PROFILE: {}", e);
+    }
+    match result {
+        Ok(output) => {
+            if !output.is_empty() {
+                println!("{}", output);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
 }
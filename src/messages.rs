@@ -0,0 +1,448 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{Config, ConfigError, Section};
+
+// #############################
+// #      MESSAGE CATALOG      #
+// #############################
+
+// Text shown to the player is looked up by message id, gettext style, so it
+// can be translated without touching the simulation. ENGLISH holds every
+// message; a catalog file replaces any of them:
+//
+// [messages]
+// transient = Transiente, Peilung {bearing}
+// error-no-route = kein Weg dorthin
+//
+// "{name}" is replaced by the argument of that name. Scenarios may add
+// their own messages in the same section, and refer to them from mission
+// text as "@id" (see Catalog::text).
+
+/// The messages in English, by id
+pub const ENGLISH: &[(&str, &str)] = &[
+    ("error-no-own-ship", "own ship is gone"),
+    ("error-no-weapons", "no weapons station"),
+    ("error-no-route", "no way there"),
+    ("error-no-such-tube", "no tube {tube}"),
+    ("error-no-such-preset", "no preset named '{name}'"),
+    ("error-tube-empty", "tube {tube} is empty"),
+    ("error-ultra-quiet", "rigged for ultra quiet"),
+    ("error-no-gun", "no deck gun fitted"),
+    (
+        "error-not-surfaced",
+        "the gun can only be manned on the surface",
+    ),
+    ("error-sea-too-rough", "sea too rough to man the gun"),
+    ("error-not-manned", "the gun is not manned"),
+    ("error-no-such-target", "no target {target}"),
+    ("error-no-such-contact", "no contact {target}"),
+    (
+        "error-no-position",
+        "contact {number} is held on a bearing only, no position to report",
+    ),
+    ("error-no-such-mark", "no mark named '{name}'"),
+    ("error-no-such-vessel", "no vessel named '{name}'"),
+    ("error-not-controllable", "{name} is not yours to command"),
+    ("error-vessel-lost", "{name} is lost"),
+    ("helm-taken", "you have the helm of {name}"),
+    ("error-chart-file", "chart file: {error}"),
+    ("error-log-file", "patrol log file: {error}"),
+    ("error-no-such-timer", "no timer {name}"),
+    ("error-not-a-save", "not a saved game"),
+    (
+        "error-save-version",
+        "saved by version {version} of the game, this is {engine}",
+    ),
+    ("error-save-truncated", "the save is cut short"),
+    ("error-save-corrupted", "the save is damaged"),
+    ("error-save-file", "save file: {error}"),
+    ("error-no-target-in-sight", "no target in sight"),
+    ("error-not-hostile", "{target} is not hostile, holding fire"),
+    ("error-no-xbts", "no bathythermographs left"),
+    ("error-no-active-sonar", "no active sonar fitted"),
+    ("error-no-charges", "no depth charges left for a pattern"),
+    ("error-no-mortar", "no mortar salvos left"),
+    ("error-reloading", "still reloading, {seconds}s to go"),
+    (
+        "error-charge-setting",
+        "charges can be set from {min} to {max}",
+    ),
+    ("error-no-vds", "no variable depth sonar fitted"),
+    (
+        "error-vds-too-fast",
+        "slow to {speed} to stream or recover the body",
+    ),
+    ("error-vds-depth", "the body can be set from {min} to {max}"),
+    ("pattern-dropped", "pattern dropped, set to {depth}"),
+    ("mortar-fired", "mortar salvo away"),
+    ("sonar-pinging", "sonar pinging"),
+    ("sonar-passive", "sonar listening only"),
+    ("sonar-pulse", "sonar pulses {pulse}"),
+    ("vds-streamed", "variable depth sonar set to {depth}"),
+    ("vds-recovered", "variable depth sonar heaving in"),
+    ("error-no-helicopter", "no helicopter on board"),
+    ("error-no-datum", "no datum to attack, dip first"),
+    (
+        "error-helo-no-torpedoes",
+        "the helicopter has no torpedoes left",
+    ),
+    ("error-helo-on-deck", "the helicopter is on deck"),
+    ("helo-tasked", "helicopter tasked: {task}"),
+    (
+        "helo-contact",
+        "helicopter dip: contact bearing {bearing}, {range}",
+    ),
+    ("helo-clear", "helicopter dip: no contact"),
+    ("helo-bingo", "helicopter short of fuel, returning"),
+    ("helo-landed", "helicopter on deck"),
+    ("helo-ditched", "helicopter out of fuel, ditched"),
+    (
+        "error-radio-too-deep",
+        "too deep to transmit, come to periscope depth",
+    ),
+    (
+        "report-sent",
+        "contact report sent, {contacts} contacts, {seconds}s on the air",
+    ),
+    ("torpedo-passed", "torpedo passed close aboard, {range} off"),
+    (
+        "shadow-sent",
+        "shadowing report on contact {number} sent to the pack",
+    ),
+    (
+        "pack-report",
+        "{boat} reports a contact bearing {bearing}, {range}",
+    ),
+    (
+        "pack-converging",
+        "the pack is ordered in, bearing {bearing}",
+    ),
+    (
+        "error-fix-too-deep",
+        "no sights from down here, surface or raise the periscope",
+    ),
+    ("error-fix-overcast", "no sights, the sky is overcast"),
+    ("contact-gained", "new contact S{number}"),
+    ("contact-updated", "contact S{number} held on {sensors}"),
+    ("contact-lost", "contact S{number} lost"),
+    (
+        "baffles-overdue",
+        "baffles not cleared for {minutes} minutes: turn to listen astern",
+    ),
+    (
+        "baffles-trailer",
+        "contact S{number} gained in the baffles just cleared: may be trailing",
+    ),
+    (
+        "contact-archived",
+        "contact S{number} dropped from the plot",
+    ),
+    (
+        "error-fix-no-horizon",
+        "no sights, the horizon cannot be seen",
+    ),
+    ("fix-taken", "fix taken, the reckoning was {off} out"),
+    ("error-no-decoys", "no decoys left"),
+    (
+        "error-decoy-streamed",
+        "the towed decoy is already streamed",
+    ),
+    ("error-no-decoy-streamed", "no towed decoy streamed"),
+    ("error-cannot-dive", "this ship cannot dive"),
+    ("error-already-submerged", "already submerged"),
+    ("error-already-surfaced", "already on the surface"),
+    ("error-dive-underway", "still diving or surfacing"),
+    ("error-no-stores", "stores are not kept"),
+    ("error-no-supplies", "no port or tender to refit from"),
+    ("error-not-stopped", "stop and surface to take on stores"),
+    ("profile-off", "not profiling, start with 'profile on'"),
+    (
+        "signal-copied",
+        "signal {name} received, encrypted: decrypting",
+    ),
+    (
+        "signal-decrypting",
+        "signal {name}: decrypting, {seconds}s to go",
+    ),
+    ("signal-read", "signal {name}: {text}"),
+    ("no-signals", "no signals received"),
+    ("no-timers", "no timers"),
+    ("timer-running", "{name} {elapsed}"),
+    ("timer-stopped", "{name} {elapsed}, stopped"),
+    (
+        "say-status",
+        "{time}: depth {depth}, heading {heading}, speed {speed}",
+    ),
+    ("say-hull", "hull at {hull}%"),
+    ("governor", "back to real time: {reason}"),
+    ("governor-torpedo", "torpedo in the water"),
+    ("governor-contact", "new contact"),
+    ("governor-ping", "ping on the intercept receiver"),
+    ("say-no-contacts", "no contacts"),
+    ("say-contacts", "{count} contacts"),
+    (
+        "say-contact",
+        "S{number} bearing {bearing}, {range}, on {sensors}",
+    ),
+    (
+        "say-contact-bearing",
+        "S{number} bearing {bearing}, range unknown, on {sensors}",
+    ),
+    (
+        "say-reckoning",
+        "reckoned position {x}, {y}, good to {uncertainty}",
+    ),
+    (
+        "say-waypoint",
+        "next waypoint bearing {bearing}, {range}, {more} more after it",
+    ),
+    ("say-zone", "inside {zone}"),
+    ("say-mark", "mark {name} bearing {bearing}, {range}"),
+    ("say-land", "land bearing {bearing}, {range}"),
+    (
+        "say-torpedo",
+        "torpedo bearing {bearing}, {range}, heading {heading}",
+    ),
+    (
+        "say-own-torpedo",
+        "our torpedo bearing {bearing}, {range}, heading {heading}",
+    ),
+    (
+        "air-foul",
+        "air is going foul, {co2}% CO2: snorkel or surface",
+    ),
+    (
+        "machinery-worn",
+        "machinery worn after {hours} running hours: the boat is getting louder until overhauled in port",
+    ),
+    (
+        "identified",
+        "contact {target}: {class}, {confidence}% sure",
+    ),
+    (
+        "broached",
+        "broached! the conning tower is out of the water",
+    ),
+    ("damage-hull", "hull {hull}%"),
+    (
+        "damage-crew",
+        "{fit} fit, {wounded} wounded, {killed} killed",
+    ),
+    ("damage-sensor", "{sensor} at {health}%"),
+    ("hazard-mine", "drifting mine"),
+    ("hazard-debris", "floating wreckage"),
+    (
+        "hazard-sighted",
+        "{kind} sighted bearing {bearing}, {range}",
+    ),
+    (
+        "rendezvous-interrupted",
+        "transfer at {name} interrupted, it must start over",
+    ),
+    ("rendezvous-completed", "transfer at {name} complete"),
+    ("rendezvous-failed", "missed the rendezvous at {name}"),
+    (
+        "rendezvous-resupplied",
+        "resupplied at {name}: fuel and torpedoes taken on",
+    ),
+    ("rendezvous-signal", "rendezvous {name} at {x}, {y}"),
+    (
+        "trail-completed",
+        "{name} trailed long enough: shadowing complete",
+    ),
+    (
+        "trail-broken",
+        "lost the trail of {name}: the count starts over",
+    ),
+    (
+        "trail-detected",
+        "counter-detected shadowing {name}: mission failed",
+    ),
+    ("trail-failed", "failed to shadow {name}"),
+    ("log-torpedo-fired", "fired a torpedo"),
+    ("log-torpedo-hit", "torpedo hit {target}"),
+    ("log-torpedo-failed", "torpedo failed: {failure}"),
+    ("log-torpedoed", "hit by a torpedo"),
+    ("log-shelled", "shelled by {shooter}"),
+    ("log-sunk", "{target} sunk"),
+    ("log-sunk-tonnage", "{target} sunk, {tonnage} GRT"),
+    ("log-crippled", "{target} crippled"),
+    ("log-lost", "boat lost"),
+    ("log-broached", "broached"),
+    ("log-aground", "ran aground"),
+    ("log-bottom", "touched bottom"),
+    ("log-zone", "entered {zone}"),
+    ("log-hazard", "struck a {kind}"),
+    ("log-transmitted", "on the air for {seconds} s"),
+    ("transient", "transient bearing {bearing}"),
+    (
+        "transient-classified",
+        "transient bearing {bearing}, {kind}",
+    ),
+    ("transient-torpedo-launch", "torpedo launch"),
+    ("transient-tube-flooding", "tube flooding"),
+    ("transient-hatch-slam", "hatch slam"),
+    ("transient-dropped-tool", "dropped tool"),
+    ("transient-hull-popping", "hull popping"),
+    ("transient-ballast", "ballast tanks"),
+    ("transient-secondary-explosion", "secondary explosion"),
+    ("transient-breaking-up", "breaking-up noises"),
+    ("intercept", "{kind} bearing {bearing}"),
+    ("intercept-unknown", "unknown pulse bearing {bearing}"),
+    ("emission-active-sonar", "active sonar"),
+    ("emission-dipping-sonar", "dipping sonar"),
+    ("emission-torpedo-seeker", "torpedo seeker"),
+    ("alert", "{time} INTERCEPT {intercept}"),
+    ("failure", "{time} torpedo against #{target}: {failure}"),
+    ("failure-untargeted", "{time} torpedo: {failure}"),
+    ("shot-running", "{time} torpedo still running"),
+    ("shot-hit", "{time} torpedo hit #{target}"),
+    ("shot-failed", "{time} torpedo reached #{target}: {failure}"),
+    (
+        "shot-missed",
+        "{time} torpedo ran out, passing #{target} at {range}",
+    ),
+    ("shot-wild", "{time} torpedo ran out far from anything"),
+    (
+        "shot-expected-miss",
+        "the solution had it passing {miss} off, {chance}% to hit",
+    ),
+    (
+        "shot-expected-hit",
+        "the solution gave it {chance}% to hit: the target was not where it was plotted",
+    ),
+    (
+        "hint-sonar",
+        "you were detected at {time} due to {source} at {depth}",
+    ),
+    (
+        "hint-radar",
+        "you were painted on radar at {time} at {depth}",
+    ),
+    ("hint-sighted", "you were sighted at {time} at {depth}"),
+    ("failure-premature", "premature detonation"),
+    ("failure-dud", "dud"),
+    ("failure-ran-deep", "ran under the keel"),
+];
+
+/// Named values put into a message
+pub type Args<'a> = &'a [(&'a str, &'a dyn fmt::Display)];
+
+/// Messages replacing or adding to ENGLISH
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Catalog {
+    entries: Vec<(String, String)>,
+}
+
+impl Catalog {
+    pub fn read(section: &Section) -> Catalog {
+        Catalog {
+            entries: section
+                .entries()
+                .map(|(id, text)| (id.to_string(), text.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Reads the "[messages]" section of a catalog file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Catalog, ConfigError> {
+        Ok(Config::load(path)?
+            .section("messages")
+            .map(Catalog::read)
+            .unwrap_or_default())
+    }
+
+    /// Adds the messages of `other`, which win over those already there
+    pub fn extend(&mut self, other: &Catalog) {
+        for (id, text) in &other.entries {
+            self.entries.retain(|(i, _)| i != id);
+            self.entries.push((id.clone(), text.clone()));
+        }
+    }
+
+    /// The message `id`, or the id itself when no catalog has it
+    pub fn get<'a>(&'a self, id: &'a str) -> &'a str {
+        self.entries
+            .iter()
+            .find(|(i, _)| i == id)
+            .map(|(_, text)| text.as_str())
+            .or_else(|| ENGLISH.iter().find(|(i, _)| *i == id).map(|(_, t)| *t))
+            .unwrap_or(id)
+    }
+
+    /// The message `id` with its "{name}" placeholders filled from `args`
+    pub fn format(&self, id: &str, args: Args) -> String {
+        let mut text = String::new();
+        let mut rest = self.get(id);
+        while let Some(open) = rest.find('{') {
+            text.push_str(&rest[..open]);
+            let after = &rest[open + 1..];
+            let argument = after.find('}').and_then(|close| {
+                let name = &after[..close];
+                args.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, value)| (value, close))
+            });
+            match argument {
+                Some((value, close)) => {
+                    text.push_str(&value.to_string());
+                    rest = &after[close + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+
+    /// Mission text as shown: "@id" is looked up, anything else is shown
+    /// as written
+    pub fn text<'a>(&'a self, text: &'a str) -> &'a str {
+        match text.strip_prefix('@') {
+            Some(id) => self.get(id),
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_and_format() {
+        let catalog = Catalog::default();
+        assert_eq!(catalog.get("error-no-route"), "no way there");
+        assert_eq!(catalog.get("no-such-message"), "no-such-message");
+        assert_eq!(
+            catalog.format(
+                "transient-classified",
+                &[("bearing", &"090"), ("kind", &"hatch slam")]
+            ),
+            "transient bearing 090, hatch slam"
+        );
+        // unknown placeholders are left alone
+        assert_eq!(catalog.format("error-no-such-tube", &[]), "no tube {tube}");
+    }
+
+    #[test]
+    fn translated() {
+        let config = Config::parse(
+            "[messages]\nerror-no-such-tube = Rohr {tube} gibt es nicht\nbriefing = Auslaufen!",
+        )
+        .unwrap();
+        let mut catalog = Catalog::default();
+        catalog.extend(&Catalog::read(config.section("messages").unwrap()));
+        assert_eq!(
+            catalog.format("error-no-such-tube", &[("tube", &7)]),
+            "Rohr 7 gibt es nicht"
+        );
+        assert_eq!(catalog.get("error-no-route"), "no way there");
+        assert_eq!(catalog.text("@briefing"), "Auslaufen!");
+        assert_eq!(catalog.text("Welcome aboard."), "Welcome aboard.");
+    }
+}
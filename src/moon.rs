@@ -0,0 +1,143 @@
+use std::f32::consts::PI;
+
+use crate::environment::{Date, Environment, NIGHT_LIGHT, SECONDS_PER_DAY};
+use crate::physics::user_to_game_angle;
+
+// #############################
+// #   MOONLIGHT AND SHADOW    #
+// #############################
+
+// At night the moon decides what a lookout sees. Its phase follows the
+// date of the scenario, new to full and back every 29.5 days, and it
+// crosses the sky like the sun: rising in the east, highest in the south,
+// setting in the west, about 50 minutes later each day. A full moon high
+// up lights the sea almost like a dull day.
+//
+// Where it stands matters as much as how bright it is: a ship between the
+// lookout and the moon stands out black against the bright path it
+// throws on the water, while one on the dark side, with the moon behind
+// the lookout, melts into the night. A boat attacking on the surface at
+// night does so from the dark side (see lookouts.rs).
+
+/// Days in a lunation, new moon to new moon
+const SYNODIC_MONTH: f32 = 29.530_588;
+/// A new moon, in days after 2000-01-01 00:00
+const NEW_MOON: f32 = 5.76;
+/// Highest the moon climbs at transit, mid-latitudes
+const MAX_ALTITUDE: f32 = 60.0;
+/// Light a full moon at its highest adds to a dark night, as a fraction of
+/// daylight
+const MOONLIGHT: f32 = 0.3;
+/// How much further a silhouette against the moon is seen, and how much
+/// closer one on the dark side, at full moon
+const SILHOUETTE: f32 = 0.5;
+
+/// Days from 2000-01-01 to `date`
+fn day_number(date: &Date) -> i64 {
+    // days from civil, counting years from March so leap days come last
+    let year = if date.month <= 2 {
+        date.year - 1
+    } else {
+        date.year
+    } as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = (date.month as i64 + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + date.day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 730_425
+}
+
+/// The moon as a lookout sees it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Moon {
+    /// Fraction of the disc lit, 0 at new moon to 1 at full
+    pub illumination: f32,
+    /// Game angle it bears
+    pub azimuth: f32,
+    /// Degrees above the horizon, negative when it has set
+    pub altitude: f32,
+}
+
+impl Moon {
+    /// The moon at `time` into the scenario
+    pub fn at(environment: &Environment, time: f32) -> Moon {
+        let (date, clock) = environment.local_time(time);
+        let days = day_number(&date) as f32 + clock / SECONDS_PER_DAY - NEW_MOON;
+        let phase = days.rem_euclid(SYNODIC_MONTH) / SYNODIC_MONTH;
+        // transits at noon new, at midnight full
+        let transit = (0.5 + phase) * SECONDS_PER_DAY;
+        let hours = ((clock - transit + SECONDS_PER_DAY / 2.0).rem_euclid(SECONDS_PER_DAY)
+            - SECONDS_PER_DAY / 2.0)
+            / 3600.0;
+        Moon {
+            illumination: (1.0 - (2.0 * PI * phase).cos()) / 2.0,
+            azimuth: user_to_game_angle(180.0 + 15.0 * hours),
+            altitude: MAX_ALTITUDE * (2.0 * PI * hours / 24.0).cos(),
+        }
+    }
+
+    /// How much it lights the sea, 0 to 1
+    pub fn brightness(&self) -> f32 {
+        self.illumination * self.altitude.max(0.0).to_radians().sin()
+            / MAX_ALTITUDE.to_radians().sin()
+    }
+}
+
+/// How light it is at `time`, the daylight or the moonlight on the night,
+/// whichever is more
+pub fn light(environment: &Environment, time: f32) -> f32 {
+    let moon = Moon::at(environment, time);
+    environment
+        .daylight(time)
+        .max(NIGHT_LIGHT + MOONLIGHT * moon.brightness())
+}
+
+/// Factor on the range a ship on `bearing` (a game angle) from the lookout
+/// is sighted at for where the moon stands: above 1 silhouetted against
+/// it, below on the dark side; 1 by day and with no moon up
+pub fn silhouette(environment: &Environment, time: f32, bearing: f32) -> f32 {
+    let moon = Moon::at(environment, time);
+    let night = (1.0 - environment.daylight(time)) / (1.0 - NIGHT_LIGHT);
+    1.0 + SILHOUETTE * night * moon.brightness() * (bearing - moon.azimuth).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn night(date: &str) -> Environment {
+        Environment {
+            date: date.parse().unwrap(),
+            ..Environment::default()
+        }
+    }
+
+    #[test]
+    fn phases_follow_the_date() {
+        assert_eq!(day_number(&"2000-01-01".parse().unwrap()), 0);
+        assert_eq!(day_number(&"1941-05-20".parse().unwrap()), -21_410);
+        // new on 1941-05-26, full on 1941-05-11
+        let new = Moon::at(&night("1941-05-26"), 0.0);
+        let full = Moon::at(&night("1941-05-11"), 0.0);
+        assert!(new.illumination < 0.05, "{:?}", new);
+        assert!(full.illumination > 0.95, "{:?}", full);
+        // the full moon is high in the south at midnight, the new one down
+        assert!(full.altitude > 55.0 && (full.azimuth + PI / 2.0).abs() < 0.2);
+        assert!(new.altitude < 0.0);
+        assert!(light(&night("1941-05-11"), 0.0) > light(&night("1941-05-26"), 0.0));
+        assert_eq!(light(&night("1941-05-26"), 0.0), NIGHT_LIGHT);
+    }
+
+    #[test]
+    fn silhouettes_against_the_moon() {
+        let environment = night("1941-05-11");
+        let south = user_to_game_angle(180.0);
+        let north = user_to_game_angle(0.0);
+        assert!(silhouette(&environment, 0.0, south) > 1.4);
+        assert!(silhouette(&environment, 0.0, north) < 0.6);
+        // no moon, or by day, no difference
+        assert_eq!(silhouette(&night("1941-05-26"), 0.0, south), 1.0);
+        assert_eq!(silhouette(&environment, 12.0 * 3600.0, south), 1.0);
+    }
+}
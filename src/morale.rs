@@ -0,0 +1,175 @@
+use crate::events::Event;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #          MORALE           #
+// #############################
+
+// How a crew feels about its war, from 0 (broken) to 1 (could not be
+// better). Hits scored lift it, and being hit, a torpedo that nearly got
+// the boat and every man lost bring it down; a long patrol wears it away
+// day by day until a run ashore in port restores it. A crew in low spirits
+// works worse, makes more mishaps and its wounded take longer to get back
+// on their feet. Scenarios set the morale of a crew with the placement key
+// "morale", and may read or change it through level and adjust.
+
+/// Morale of a crew fresh out of port
+pub const DEFAULT_MORALE: f32 = 0.7;
+/// Morale lost every day at sea
+const PATROL_WEAR: f32 = 0.01;
+/// Morale gained by a torpedo hit scored
+const TORPEDO_SUCCESS: f32 = 0.1;
+/// Morale gained by a shell hit scored
+const SHELL_SUCCESS: f32 = 0.02;
+/// Morale lost to a torpedo hit taken
+const TORPEDO_HIT: f32 = 0.15;
+/// Morale lost to a shell hit taken
+const SHELL_HIT: f32 = 0.05;
+/// Morale lost to a torpedo aimed at the boat that failed to go off
+const NEAR_MISS: f32 = 0.05;
+/// Morale lost to a torpedo of one's own that failed
+const FAILED_SHOT: f32 = 0.03;
+/// Morale lost for every man killed
+const MAN_LOST: f32 = 0.02;
+/// Morale below which the crew starts to work worse
+const LOW_MORALE: f32 = 0.5;
+/// Least performance of a broken crew
+const MIN_PERFORMANCE: f32 = 0.6;
+/// How much more often a broken crew makes mishaps than one at
+/// DEFAULT_MORALE, and how much less often a crew in top spirits does
+const MISHAP_SPREAD: f32 = 1.5;
+
+/// 1 while morale holds, down to 0.6 for a broken crew
+pub fn performance(entity: &Entity) -> f32 {
+    let low = (LOW_MORALE - entity.morale).max(0.0) / LOW_MORALE;
+    1.0 - low * (1.0 - MIN_PERFORMANCE)
+}
+
+/// Factor on the mishaps of the crew of `entity`, 1 at DEFAULT_MORALE
+pub fn mishaps(entity: &Entity) -> f32 {
+    1.0 + MISHAP_SPREAD * (DEFAULT_MORALE - entity.morale)
+}
+
+/// Factor on how fast the wounded of `entity` recover, 1 at DEFAULT_MORALE
+pub fn recovery(entity: &Entity) -> f32 {
+    1.0 + entity.morale - DEFAULT_MORALE
+}
+
+/// Morale of the crew of `id`
+pub fn level(world: &World, id: EntityId) -> Option<f32> {
+    world.entity(id).map(|e| e.morale)
+}
+
+/// Raises (or with a negative `amount` lowers) the morale of `id`
+pub fn adjust(world: &mut World, id: EntityId, amount: f32) {
+    if let Some(entity) = world.entity_mut(id) {
+        entity.morale = (entity.morale + amount).clamp(0.0, 1.0);
+    }
+}
+
+/// Changes the morale of the crews `event` matters to
+pub fn react(world: &mut World, event: &Event) {
+    match *event {
+        Event::TorpedoHit {
+            shooter, target, ..
+        } => {
+            adjust(world, shooter, TORPEDO_SUCCESS);
+            adjust(world, target, -TORPEDO_HIT);
+        }
+        Event::ShellHit {
+            shooter, target, ..
+        } => {
+            adjust(world, shooter, SHELL_SUCCESS);
+            adjust(world, target, -SHELL_HIT);
+        }
+        Event::TorpedoFailed {
+            shooter, target, ..
+        } => {
+            adjust(world, shooter, -FAILED_SHOT);
+            if let Some(target) = target {
+                adjust(world, target, -NEAR_MISS);
+            }
+        }
+        Event::CrewKilled { entity, killed } => {
+            adjust(world, entity, -MAN_LOST * killed as f32);
+        }
+        _ => {}
+    }
+}
+
+/// Wears the morale of every crew down over `dt` seconds at sea
+pub fn update(world: &mut World, dt: f32) {
+    let wear = PATROL_WEAR * dt / 86_400.0;
+    for entity in world.entities.iter_mut() {
+        entity.morale = (entity.morale - wear).max(0.0);
+    }
+}
+
+/// A run ashore: the crew of `entity` comes back in good spirits
+pub fn shore_leave(entity: &mut Entity) {
+    entity.morale = entity.morale.max(DEFAULT_MORALE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::reliability::Failure;
+    use crate::world::EntityKind;
+
+    fn duel() -> World {
+        let mut world = World::new();
+        world.spawn(Entity::new(
+            "U-99",
+            EntityKind::Submarine,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        world.spawn(Entity::new(
+            "Walker",
+            EntityKind::Warship,
+            Point { x: 3000.0, y: 0.0 },
+        ));
+        world
+    }
+
+    #[test]
+    fn hits_and_losses() {
+        let mut world = duel();
+        world.emit(Event::TorpedoHit {
+            torpedo: 3,
+            shooter: 1,
+            target: 2,
+        });
+        assert!(level(&world, 1).unwrap() > DEFAULT_MORALE);
+        assert!(level(&world, 2).unwrap() < DEFAULT_MORALE);
+        world.emit(Event::TorpedoFailed {
+            shooter: 2,
+            target: Some(1),
+            failure: Failure::Dud,
+        });
+        world.emit(Event::CrewKilled {
+            entity: 1,
+            killed: 10,
+        });
+        let boat = world.entity(1).unwrap();
+        assert!(boat.morale < DEFAULT_MORALE);
+        assert!(mishaps(boat) > 1.0);
+        assert_eq!(performance(boat), 1.0);
+        adjust(&mut world, 1, -1.0);
+        assert_eq!(level(&world, 1), Some(0.0));
+        assert_eq!(performance(world.entity(1).unwrap()), MIN_PERFORMANCE);
+    }
+
+    #[test]
+    fn long_patrols_wear_it_down() {
+        let mut world = duel();
+        for _ in 0..30 {
+            world.step(86_400.0);
+        }
+        let boat = world.entity_mut(1).unwrap();
+        assert!((boat.morale - (DEFAULT_MORALE - 0.3)).abs() < 0.001);
+        assert!(recovery(boat) < 1.0);
+        shore_leave(boat);
+        assert_eq!(boat.morale, DEFAULT_MORALE);
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::physics::{game_to_user_angle, Point};
+use crate::preferences::Preferences;
+use crate::sensors::passive_excess;
+use crate::simulation::Simulation;
+use crate::units::{Meters, MetersPerSecond};
+use crate::world::{Entity, EntityKind};
+
+// #############################
+// #     SPOKEN READOUTS       #
+// #############################
+
+// Every display can be had as a few short sentences instead, one per line,
+// for a screen reader to speak, or for a headless run to print:
+//
+// describe status         # time, depth, heading, speed and hull
+// describe sonar          # the contacts held, by number
+// describe plot           # reckoning, route, zones, marks, land, torpedoes
+//
+// They are written through the preferences and the message catalog like
+// every other report, bearings and ranges included, and always in the same
+// order, so a player learns where to listen for what.
+
+/// Meters within which land is worth mentioning
+const LAND_RANGE: f32 = 20_000.0;
+
+/// A display to describe
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Readout {
+    Status,
+    Sonar,
+    Plot,
+}
+
+impl FromStr for Readout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "status" => Ok(Readout::Status),
+            "sonar" => Ok(Readout::Sonar),
+            "plot" => Ok(Readout::Plot),
+            _ => Err(format!("unknown display '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Readout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Readout::Status => "status",
+            Readout::Sonar => "sonar",
+            Readout::Plot => "plot",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Bearing and range of `to` from `own`, as written for the player
+fn bearing_range(preferences: &Preferences, own: &Entity, to: &Point) -> (String, String) {
+    (
+        preferences.bearing(own.position.angle_to(to), own.heading),
+        preferences
+            .units
+            .range(Meters(own.position.distance_to(to))),
+    )
+}
+
+fn status(sim: &Simulation, own: &Entity) -> Vec<String> {
+    let messages = &sim.messages;
+    let preferences = &sim.preferences;
+    let mut lines = vec![messages.format(
+        "say-status",
+        &[
+            (
+                "time",
+                &preferences.time(&sim.world.environment, sim.world.time),
+            ),
+            ("depth", &preferences.units.depth(Meters(own.depth))),
+            (
+                "heading",
+                &format!("{:03.0}", game_to_user_angle(own.heading)),
+            ),
+            (
+                "speed",
+                &preferences.units.speed(MetersPerSecond(own.speed)),
+            ),
+        ],
+    )];
+    if own.hull < 1.0 {
+        let hull = format!("{:.0}", own.hull * 100.0);
+        lines.push(messages.format("say-hull", &[("hull", &hull)]));
+    }
+    lines
+}
+
+fn sonar(sim: &Simulation, own: &Entity) -> Vec<String> {
+    let messages = &sim.messages;
+    let contacts = &sim.contacts.contacts;
+    if contacts.is_empty() {
+        return vec![messages.get("say-no-contacts").to_string()];
+    }
+    let mut lines = vec![messages.format("say-contacts", &[("count", &contacts.len())])];
+    for contact in contacts {
+        let bearing = sim.preferences.bearing(contact.bearing, own.heading);
+        let sensors: Vec<String> = contact.sensors.iter().map(|s| s.to_string()).collect();
+        let sensors = sensors.join(", ");
+        lines.push(match contact.range {
+            Some((range, _)) => messages.format(
+                "say-contact",
+                &[
+                    ("number", &contact.number),
+                    ("bearing", &bearing),
+                    ("range", &sim.preferences.units.range(Meters(range))),
+                    ("sensors", &sensors),
+                ],
+            ),
+            None => messages.format(
+                "say-contact-bearing",
+                &[
+                    ("number", &contact.number),
+                    ("bearing", &bearing),
+                    ("sensors", &sensors),
+                ],
+            ),
+        });
+    }
+    lines
+}
+
+fn plot(sim: &Simulation, own: &Entity) -> Vec<String> {
+    let messages = &sim.messages;
+    let preferences = &sim.preferences;
+    let world = &sim.world;
+    let range = |meters: f32| preferences.units.range(Meters(meters));
+    let reckoned = sim.navigation.position(&own.position);
+    let mut lines = vec![messages.format(
+        "say-reckoning",
+        &[
+            ("x", &range(reckoned.x)),
+            ("y", &range(reckoned.y)),
+            (
+                "uncertainty",
+                &range(sim.navigation.uncertainty(world.time)),
+            ),
+        ],
+    )];
+    if let Some(next) = sim.route.first() {
+        let (bearing, range) = bearing_range(preferences, own, next);
+        lines.push(messages.format(
+            "say-waypoint",
+            &[
+                ("bearing", &bearing),
+                ("range", &range),
+                ("more", &(sim.route.len() - 1)),
+            ],
+        ));
+    }
+    for zone in world.zones_at(&own.position) {
+        lines.push(messages.format("say-zone", &[("zone", &zone.name)]));
+    }
+    for mark in &sim.chart.marks {
+        let (bearing, range) = bearing_range(preferences, own, &mark.anchor());
+        lines.push(messages.format(
+            "say-mark",
+            &[
+                ("name", &mark.name),
+                ("bearing", &bearing),
+                ("range", &range),
+            ],
+        ));
+    }
+    let land = world
+        .coastline
+        .shores
+        .iter()
+        .flat_map(|shore| shore.points.iter())
+        .min_by(|a, b| {
+            let (a, b) = (own.position.distance_to(a), own.position.distance_to(b));
+            a.total_cmp(&b)
+        })
+        .filter(|p| own.position.distance_to(p) <= LAND_RANGE);
+    if let Some(land) = land {
+        let (bearing, range) = bearing_range(preferences, own, land);
+        lines.push(messages.format("say-land", &[("bearing", &bearing), ("range", &range)]));
+    }
+    // the torpedoes the plot draws a danger zone for, see plot.rs
+    for torpedo in world.entities.iter() {
+        if torpedo.kind != EntityKind::Torpedo || torpedo.is_destroyed() {
+            continue;
+        }
+        let ours = torpedo
+            .torpedo
+            .as_ref()
+            .is_some_and(|t| t.shooter == own.id);
+        let heard = passive_excess(&world.environment, own, torpedo).is_some_and(|e| e > 0.0);
+        if !(ours || heard) {
+            continue;
+        }
+        let (bearing, range) = bearing_range(preferences, own, &torpedo.position);
+        let id = if ours {
+            "say-own-torpedo"
+        } else {
+            "say-torpedo"
+        };
+        lines.push(messages.format(
+            id,
+            &[
+                ("bearing", &bearing),
+                ("range", &range),
+                (
+                    "heading",
+                    &format!("{:03.0}", game_to_user_angle(torpedo.heading)),
+                ),
+            ],
+        ));
+    }
+    lines
+}
+
+/// `readout` of `sim` as short sentences, one per line, empty without an
+/// own ship
+pub fn describe(sim: &Simulation, readout: Readout) -> Vec<String> {
+    let own = match sim.own_ship() {
+        Some(own) => own,
+        None => return Vec::new(),
+    };
+    match readout {
+        Readout::Status => status(sim, own),
+        Readout::Sonar => sonar(sim, own),
+        Readout::Plot => plot(sim, own),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chart::{Mark, MarkShape};
+    use crate::world::World;
+    use crate::zone::{Zone, ZoneKind};
+
+    fn boat() -> Simulation {
+        let mut world = World::new();
+        let mut sub = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        sub.depth = 50.0;
+        sub.speed = 2.0;
+        sub.hull = 0.75;
+        let player = world.spawn(sub);
+        Simulation::new(world, player)
+    }
+
+    #[test]
+    fn status_and_sonar_in_words() {
+        let sim = boat();
+        assert_eq!(
+            describe(&sim, Readout::Status),
+            vec![
+                "00:00: depth 50 m, heading 090, speed 3.9 kn",
+                "hull at 75%"
+            ]
+        );
+        assert_eq!(describe(&sim, Readout::Sonar), vec!["no contacts"]);
+        assert_eq!("plot".parse(), Ok(Readout::Plot));
+        assert!("periscope".parse::<Readout>().is_err());
+    }
+
+    #[test]
+    fn the_plot_in_words() {
+        let mut sim = boat();
+        sim.route = vec![Point { x: 0.0, y: 4000.0 }, Point { x: 0.0, y: 8000.0 }];
+        sim.chart.place(Mark {
+            name: "datum".to_string(),
+            shape: MarkShape::Point(Point { x: 3000.0, y: 0.0 }),
+        });
+        sim.world.zones.push(Zone {
+            name: "the Minches".to_string(),
+            kind: ZoneKind::Patrol,
+            points: vec![
+                Point {
+                    x: -100.0,
+                    y: -100.0,
+                },
+                Point {
+                    x: 100.0,
+                    y: -100.0,
+                },
+                Point { x: 0.0, y: 100.0 },
+            ],
+            depth: None,
+            bottom: None,
+        });
+        assert_eq!(
+            describe(&sim, Readout::Plot),
+            vec![
+                "reckoned position 0 m, 0 m, good to 0 m",
+                "next waypoint bearing 000, 4000 m, 1 more after it",
+                "inside the Minches",
+                "mark datum bearing 090, 3000 m",
+            ]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::messages::Catalog;
+use crate::moon;
+use crate::physics::Point;
+use crate::random::Rng;
+use crate::world::{EntityId, World};
+
+// #############################
+// #   CELESTIAL NAVIGATION    #
+// #############################
+
+// Out of sight of land the boat knows where it is only by dead reckoning:
+// courses and speeds run since the last fix, which say nothing of the set
+// of the current and the leeway. The reckoning drifts off the true
+// position by a little every hour, in a direction nobody aboard knows, and
+// the navigator allows for it with a circle of uncertainty that grows from
+// the last fix.
+//
+// A fix from the sun by day, the stars at twilight, or the horizon under a
+// bright moon puts the reckoning right, surfaced or, less exactly, through
+// the periscope sextant at periscope depth; not in thick weather. How
+// close it comes depends on the sea running and the skill of the crew.
+//
+// The reckoning only changes what the player is shown, so it draws on a
+// generator of its own, a stream split off that of the world: taking a
+// fix, or not, leaves everything else in the scenario to happen the same
+// way, and the errors of the fixes owe nothing to the draws of the world.
+
+/// Stream of the generator of the world the reckoning draws on
+pub const STREAM: u64 = 0x4E41_5649;
+/// Meters per second the unknown set and leeway carry the boat off its
+/// reckoning, one standard deviation
+const DRIFT: f32 = 0.15;
+/// Meters per second the navigator allows the uncertainty to grow by
+const DRIFT_ALLOWANCE: f32 = 0.2;
+/// Meters a sight taken by a trained crew in a calm sea is off by, one
+/// standard deviation
+const SIGHT_ERROR: f32 = 1_000.0;
+/// Worse for every sea state, and through the periscope
+const SEA_ERROR: f32 = 0.3;
+const PERISCOPE_ERROR: f32 = 2.0;
+/// Meters of visibility under which the sky and horizon are hidden
+const MIN_VISIBILITY: f32 = 3_000.0;
+/// Light needed to see the horizon, see moon.rs
+const HORIZON_LIGHT: f32 = 0.2;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum NavigationError {
+    /// Neither surfaced nor at periscope depth with the mast up
+    TooDeep,
+    Overcast,
+    /// Too dark to see the horizon
+    NoHorizon,
+}
+
+impl NavigationError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            NavigationError::TooDeep => messages.get("error-fix-too-deep").to_string(),
+            NavigationError::Overcast => messages.get("error-fix-overcast").to_string(),
+            NavigationError::NoHorizon => messages.get("error-fix-no-horizon").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for NavigationError {}
+
+/// Where the own ship reckons it is
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Navigation {
+    /// Meters the reckoned position is off the true one
+    pub error: Point,
+    /// Meters per second the reckoning drifts off by
+    drift: Point,
+    /// Seconds into the scenario of the last fix
+    pub last_fix: f32,
+    /// Meters the last fix was reckoned good to
+    pub accuracy: f32,
+    /// Draws the drift and the error of the fixes
+    rng: Rng,
+}
+
+fn scatter(rng: &mut Rng, sigma: f32) -> Point {
+    Point {
+        x: rng.gaussian(0.0, sigma),
+        y: rng.gaussian(0.0, sigma),
+    }
+}
+
+impl Navigation {
+    /// A reckoning starting from a known position, drifting off it from
+    /// now on as drawn by `rng`
+    pub fn new(mut rng: Rng) -> Navigation {
+        Navigation {
+            drift: scatter(&mut rng, DRIFT),
+            rng,
+            ..Navigation::default()
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.error.x += self.drift.x * dt;
+        self.error.y += self.drift.y * dt;
+    }
+
+    /// The reckoned position of a ship truly at `position`
+    pub fn position(&self, position: &Point) -> Point {
+        position.add(&self.error)
+    }
+
+    /// Radius of the circle of uncertainty at `time`
+    pub fn uncertainty(&self, time: f32) -> f32 {
+        self.accuracy + DRIFT_ALLOWANCE * (time - self.last_fix).max(0.0)
+    }
+
+    /// Takes a fix from `boat`, returning how far off the reckoning was
+    pub fn fix(&mut self, world: &World, boat: EntityId) -> Result<f32, NavigationError> {
+        let ship = match world.entity(boat) {
+            Some(ship) => ship,
+            None => return Err(NavigationError::TooDeep),
+        };
+        let periscope = !ship.is_surfaced();
+        if periscope && !(ship.depth <= PERISCOPE_DEPTH && ship.mast_raised) {
+            return Err(NavigationError::TooDeep);
+        }
+        let environment = &world.environment;
+        if environment.visibility < MIN_VISIBILITY {
+            return Err(NavigationError::Overcast);
+        }
+        if moon::light(environment, world.time) < HORIZON_LIGHT {
+            return Err(NavigationError::NoHorizon);
+        }
+        let mut sigma = SIGHT_ERROR
+            * (1.0 + SEA_ERROR * environment.sea_state as f32)
+            * ship.crew.navigation_error()
+            / ship.crew_performance();
+        if periscope {
+            sigma *= PERISCOPE_ERROR;
+        }
+        let off = self.error.abs();
+        self.error = scatter(&mut self.rng, sigma);
+        self.drift = scatter(&mut self.rng, DRIFT);
+        self.last_fix = world.time;
+        self.accuracy = sigma;
+        Ok(off)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crew::CrewQuality;
+    use crate::world::{Entity, EntityKind};
+
+    fn at_sea(crew: CrewQuality, sea_state: u8) -> (World, EntityId) {
+        let mut world = World::new();
+        world.environment.start_time = 12.0 * 3600.0;
+        world.environment.sea_state = sea_state;
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.crew = crew;
+        let boat = world.spawn(boat);
+        (world, boat)
+    }
+
+    #[test]
+    fn reckoning_drifts_until_a_fix() {
+        let (mut world, boat) = at_sea(CrewQuality::Trained, 2);
+        let mut navigation = Navigation::new(Rng::new(3));
+        for _ in 0..3600 {
+            navigation.update(1.0);
+        }
+        let drifted = navigation.error.abs();
+        assert!(drifted > 50.0, "{}", drifted);
+        assert_eq!(navigation.uncertainty(3600.0), 720.0);
+        world.time = 3600.0;
+        assert_eq!(navigation.fix(&world, boat), Ok(drifted));
+        assert_eq!(navigation.last_fix, 3600.0);
+        assert_eq!(navigation.uncertainty(3600.0), navigation.accuracy);
+
+        world.entity_mut(boat).unwrap().depth = 50.0;
+        assert_eq!(navigation.fix(&world, boat), Err(NavigationError::TooDeep));
+        world.entity_mut(boat).unwrap().depth = 0.0;
+        world.environment.visibility = 1_000.0;
+        assert_eq!(navigation.fix(&world, boat), Err(NavigationError::Overcast));
+    }
+
+    #[test]
+    fn rough_seas_and_green_crews_fix_worse() {
+        let accuracy = |crew, sea_state| {
+            let (world, boat) = at_sea(crew, sea_state);
+            let mut navigation = Navigation::default();
+            navigation.fix(&world, boat).unwrap();
+            navigation.accuracy
+        };
+        assert!(accuracy(CrewQuality::Green, 2) > accuracy(CrewQuality::Elite, 2));
+        assert!(accuracy(CrewQuality::Trained, 6) > accuracy(CrewQuality::Trained, 1));
+    }
+}
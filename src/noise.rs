@@ -0,0 +1,237 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::acoustics::{db_sum, propulsion_level};
+use crate::physics::KNOT;
+use crate::world::{Entity, EntityKind};
+
+// #############################
+// #      OWN-SHIP NOISE       #
+// #############################
+
+// The radiated noise of a hull is the sum of independent contributors. The
+// noise monitoring station lists them so the crew can hunt them down one
+// by one: slow down below cavitation speed, shut the outer doors, rig for
+// quiet...
+//
+// A torpedo radiates its propulsion, louder the faster it runs, and the
+// pings of its seeker if active, the louder once it homes (see torpedo.rs):
+// this is what the ship it is running at hears it coming by.
+
+/// Highest speed, in meters per second, allowed while rigged for ultra quiet
+pub const ULTRA_QUIET_MAX_SPEED: f32 = 5.0 * KNOT;
+/// Meters of heave past which the automatic depth keeping runs the trim
+/// pumps
+const TRIM_PUMPS_HEAVE: f32 = 0.5;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Rig {
+    Normal,
+    /// Non-essential machinery slowed down
+    Quiet,
+    /// Pumps stopped, speed limited, no tube or weapon handling
+    UltraQuiet,
+}
+
+impl FromStr for Rig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "normal" => Ok(Rig::Normal),
+            "quiet" => Ok(Rig::Quiet),
+            "ultra" | "ultra_quiet" => Ok(Rig::UltraQuiet),
+            _ => Err(format!("unknown rig '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Rig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rig::Normal => "normal",
+            Rig::Quiet => "quiet",
+            Rig::UltraQuiet => "ultra",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NoiseSource {
+    Propulsion,
+    Cavitation,
+    Pumps,
+    OpenDoors,
+    /// Trim pumps of the automatic depth keeping at work
+    TrimPumps,
+    DamagedMachinery,
+    /// Machinery run long since its last overhaul, see machinery.rs
+    WornMachinery,
+    /// Mounts knocked loose by shock, singing at the shaft rate
+    LooseMounts,
+    Transient,
+    /// Pings of the active seeker of a torpedo
+    SeekerPings,
+}
+
+impl fmt::Display for NoiseSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NoiseSource::Propulsion => "propulsion",
+            NoiseSource::Cavitation => "cavitation",
+            NoiseSource::Pumps => "pumps",
+            NoiseSource::OpenDoors => "open outer doors",
+            NoiseSource::TrimPumps => "trim pumps",
+            NoiseSource::DamagedMachinery => "damaged machinery",
+            NoiseSource::WornMachinery => "worn machinery",
+            NoiseSource::LooseMounts => "loose mounts (tonal)",
+            NoiseSource::Transient => "transient",
+            NoiseSource::SeekerPings => "seeker pings",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NoiseContributor {
+    pub source: NoiseSource,
+    /// Source level in dB
+    pub level: f32,
+}
+
+/// Speed in meters per second at which the screw starts to cavitate;
+/// pressure at depth pushes it up
+pub fn cavitation_speed(depth: f32) -> f32 {
+    (4.0 + depth.max(0.0) * 0.08) * KNOT
+}
+
+/// Everything `entity` is radiating, loudest first
+pub fn contributors(entity: &Entity) -> Vec<NoiseContributor> {
+    let mut noise = Vec::new();
+    let quieting = match entity.rig {
+        Rig::Normal => 0.0,
+        Rig::Quiet => 3.0,
+        Rig::UltraQuiet => 6.0,
+    };
+    let propulsion = propulsion_level(entity.kind, entity.speed) - quieting;
+    noise.push(NoiseContributor {
+        source: NoiseSource::Propulsion,
+        level: propulsion,
+    });
+    if entity.kind == EntityKind::Torpedo {
+        if let Some(level) = entity.torpedo.as_ref().and_then(|t| t.ping_level()) {
+            noise.push(NoiseContributor {
+                source: NoiseSource::SeekerPings,
+                level,
+            });
+        }
+        noise.sort_by(|a, b| b.level.total_cmp(&a.level));
+        return noise;
+    }
+    let onset = cavitation_speed(entity.depth);
+    if entity.speed > onset {
+        noise.push(NoiseContributor {
+            source: NoiseSource::Cavitation,
+            level: propulsion + 10.0 + 2.0 * (entity.speed - onset) / KNOT,
+        });
+    }
+    if matches!(entity.kind, EntityKind::Submarine | EntityKind::Warship) {
+        let pumps = match entity.rig {
+            Rig::Normal => Some(112.0),
+            Rig::Quiet => Some(104.0),
+            Rig::UltraQuiet => None,
+        };
+        if let Some(level) = pumps {
+            noise.push(NoiseContributor {
+                source: NoiseSource::Pumps,
+                level,
+            });
+        }
+    }
+    let doors = entity.weapons.as_ref().map_or(0, |w| w.tubes.open_doors());
+    if doors > 0 {
+        noise.push(NoiseContributor {
+            source: NoiseSource::OpenDoors,
+            level: 100.0 + 2.0 * entity.speed + 10.0 * (doors as f32).log10(),
+        });
+    }
+    if entity.depth_assist && entity.heave.abs() > TRIM_PUMPS_HEAVE {
+        noise.push(NoiseContributor {
+            source: NoiseSource::TrimPumps,
+            level: 108.0 + 2.0 * entity.heave.abs(),
+        });
+    }
+    if entity.hull < 1.0 {
+        noise.push(NoiseContributor {
+            source: NoiseSource::DamagedMachinery,
+            level: 110.0 + 30.0 * (1.0 - entity.hull),
+        });
+    }
+    if let Some(machinery) = entity.machinery.as_ref() {
+        noise.extend(machinery.contributors());
+    }
+    if entity.transient > 0.0 {
+        noise.push(NoiseContributor {
+            source: NoiseSource::Transient,
+            level: entity.transient,
+        });
+    }
+    noise.sort_by(|a, b| b.level.total_cmp(&a.level));
+    noise
+}
+
+/// Total broadband level radiated by `entity`
+pub fn radiated_level(entity: &Entity) -> f32 {
+    let levels: Vec<f32> = contributors(entity).iter().map(|c| c.level).collect();
+    db_sum(&levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+
+    fn boat(speed_knots: f32, depth: f32) -> Entity {
+        let mut boat = Entity::new("boat", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.speed = speed_knots * KNOT;
+        boat.depth = depth;
+        boat.weapons = Some(WeaponsStation::new(4, PresetLibrary::new()));
+        boat
+    }
+
+    fn has(entity: &Entity, source: NoiseSource) -> bool {
+        contributors(entity).iter().any(|c| c.source == source)
+    }
+
+    #[test]
+    fn cavitation() {
+        assert!(has(&boat(12.0, 20.0), NoiseSource::Cavitation));
+        assert!(!has(&boat(12.0, 150.0), NoiseSource::Cavitation));
+        assert!(!has(&boat(4.0, 20.0), NoiseSource::Cavitation));
+    }
+
+    #[test]
+    fn loudest_first() {
+        let mut noisy = boat(15.0, 20.0);
+        noisy.hull = 0.5;
+        noisy.weapons.as_mut().unwrap().tubes.tubes[0].door_open = true;
+        let list = contributors(&noisy);
+        assert_eq!(list.len(), 5);
+        assert!(list.windows(2).all(|w| w[0].level >= w[1].level));
+        assert_eq!(list[0].source, NoiseSource::Cavitation);
+        // a level gone wrong sorts, rather than panics
+        noisy.speed = f32::NAN;
+        assert!(has(&noisy, NoiseSource::Propulsion));
+    }
+
+    #[test]
+    fn ultra_quiet() {
+        let mut quiet = boat(3.0, 100.0);
+        let normal = radiated_level(&quiet);
+        quiet.rig = Rig::UltraQuiet;
+        assert!(!has(&quiet, NoiseSource::Pumps));
+        assert!(radiated_level(&quiet) < normal - 6.0);
+    }
+}
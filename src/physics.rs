@@ -1,3 +1,4 @@
+use std::f32::consts::PI;
 use std::fmt;
 
 // #############################
@@ -24,7 +25,39 @@ use std::fmt;
 // 270   o    90
 // 225  180   135
 
-#[derive(Debug, PartialEq, Clone)]
+/// One knot in meters per second
+pub const KNOT: f32 = 0.514_444;
+
+/// Converts a "user angle" in degrees into a "game angle" in radians
+pub fn user_to_game_angle(degrees: f32) -> f32 {
+    normalize_angle((90.0 - degrees).to_radians())
+}
+
+/// Converts a "game angle" in radians into a "user angle" in degrees,
+/// in [0, 360)
+pub fn game_to_user_angle(radians: f32) -> f32 {
+    (90.0 - radians.to_degrees()).rem_euclid(360.0)
+}
+
+/// Wraps a "game angle" into the range (-PI, PI]
+pub fn normalize_angle(radians: f32) -> f32 {
+    let mut angle = radians % (2.0 * PI);
+    if angle > PI {
+        angle -= 2.0 * PI;
+    }
+    if angle <= -PI {
+        angle += 2.0 * PI;
+    }
+    angle
+}
+
+/// Turns from `current` towards `desired` by at most `max_turn` radians
+pub fn turn_towards(current: f32, desired: f32, max_turn: f32) -> f32 {
+    let delta = normalize_angle(desired - current);
+    normalize_angle(current + delta.clamp(-max_turn, max_turn))
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -79,13 +112,13 @@ impl Point {
 
     /// Returns the "game angles" in radians between two point
     pub fn angle_to(&self, other: &Point) -> f32 {
-        let diff = other.sub(&self);
+        let diff = other.sub(self);
         diff.y.atan2(diff.x)
     }
 
     /// Returns the Point that "moves" in the diretion on destination with unitary length
     pub fn movement_to(&self, other: &Point) -> Point {
-        let angle = self.angle_to(&other);
+        let angle = self.angle_to(other);
         Point {
             x: angle.cos(),
             y: angle.sin(),
@@ -100,6 +133,21 @@ impl Point {
         Point { x, y }
     }
 
+    /// Returns the point of the segment a-b closest to this point
+    pub fn closest_on_segment(&self, a: &Point, b: &Point) -> Point {
+        let ab = b.sub(a);
+        let length = ab.squared();
+        if length == 0.0 {
+            return a.clone();
+        }
+        let ap = self.sub(a);
+        let t = ((ap.x * ab.x + ap.y * ab.y) / length).clamp(0.0, 1.0);
+        Point {
+            x: a.x + ab.x * t,
+            y: a.y + ab.y * t,
+        }
+    }
+
     pub fn angle(&self) -> f32 {
         self.y.atan2(self.x)
     }
@@ -107,14 +155,14 @@ impl Point {
     /// Return the angle in User Angle
     pub fn user_angle(&self) -> f32 {
         if self.x == 0.0 && self.y == 0.0 {
-            return 0.0
+            return 0.0;
         }
         let mut angle = 90.0 - self.angle().to_degrees();
         while angle >= 360.0 {
-            angle = angle - 360.0
+            angle -= 360.0
         }
         while angle < 0.0 {
-            angle = angle + 360.0
+            angle += 360.0
         }
         angle
     }
@@ -123,6 +171,7 @@ impl Point {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::f32::consts::FRAC_PI_2;
 
     fn similar_points(a: Point, b: Point) -> bool {
         let e1 = (a.x - b.x).abs();
@@ -215,7 +264,7 @@ mod tests {
     fn angle_to3() {
         let x = Point { x: -1.0, y: 1.0 };
         let y = Point { x: -1.0, y: -1.0 };
-        assert_eq!(x.angle_to(&y), -1.0 * std::f32::consts::FRAC_PI_2);
+        assert_eq!(x.angle_to(&y), -std::f32::consts::FRAC_PI_2);
     }
 
     #[test]
@@ -319,6 +368,17 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn closest_on_segment1() {
+        let a = Point { x: 0.0, y: 0.0 };
+        let b = Point { x: 10.0, y: 0.0 };
+        let p = Point { x: 4.0, y: 3.0 };
+        assert_eq!(p.closest_on_segment(&a, &b), Point { x: 4.0, y: 0.0 });
+        let p = Point { x: -4.0, y: 3.0 };
+        assert_eq!(p.closest_on_segment(&a, &b), a);
+        assert_eq!(p.closest_on_segment(&a, &a), a);
+    }
+
     #[test]
     fn user_angle0() {
         let x = Point { x: 0.0, y: 0.0 };
@@ -349,6 +409,32 @@ mod tests {
         assert_eq!(x.user_angle(), 0.0);
     }
 
+    #[test]
+    fn normalize_angle1() {
+        assert!((normalize_angle(3.0 * PI) - PI).abs() < 0.0001);
+        assert!((normalize_angle(-PI) - PI).abs() < 0.0001);
+        assert!((normalize_angle(-0.5) + 0.5).abs() < 0.0001);
+        assert!((normalize_angle(2.0 * PI + 0.25) - 0.25).abs() < 0.0001);
+    }
+
+    #[test]
+    fn turn_towards1() {
+        assert!((turn_towards(0.0, 1.0, 0.25) - 0.25).abs() < 0.0001);
+        assert!((turn_towards(0.0, -1.0, 0.25) + 0.25).abs() < 0.0001);
+        assert!((turn_towards(0.0, 0.1, 0.25) - 0.1).abs() < 0.0001);
+        assert!((turn_towards(3.0, -3.0, 0.25) - normalize_angle(3.25)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn user_to_game_angle1() {
+        assert!((user_to_game_angle(0.0) - FRAC_PI_2).abs() < 0.0001);
+        assert!(user_to_game_angle(90.0).abs() < 0.0001);
+        assert!((user_to_game_angle(180.0) + FRAC_PI_2).abs() < 0.0001);
+        let p = Point { x: -1.0, y: -1.0 };
+        assert!((p.user_angle() - 225.0).abs() < 0.001);
+        assert!((user_to_game_angle(225.0) - p.angle()).abs() < 0.0001);
+    }
+
     #[test]
     fn user_angle5() {
         let x = Point { x: 0.0, y: -1.0 };
@@ -0,0 +1,432 @@
+use crate::approach::{self, Solution, Weapon};
+use crate::coverage::{self, Signature};
+use crate::physics::Point;
+use crate::preview::Preview;
+use crate::seeker::Seeker;
+use crate::sensors::passive_excess;
+use crate::simulation::Simulation;
+use crate::torpedo::{max_run, HIT_RADIUS};
+use crate::weapons::Guidance;
+use crate::world::{Entity, EntityKind};
+use crate::zone::ZoneKind;
+
+// #############################
+// #        NAV PLOT           #
+// #############################
+
+// The plot is plain geometry in world meters, tagged with what it shows.
+// Front ends (terminal or graphical) only scale and draw it, so they all
+// share the same plotting logic.
+
+/// Half width in meters of the lane a wake-homing torpedo may wander into
+const WAKE_HOMING_REACH: f32 = 300.0;
+/// Bearings the coverage ring is worked out on
+const COVERAGE_POINTS: usize = 36;
+
+/// What a shape shows, for the front end to pick colors and line styles
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Layer {
+    OwnTrack,
+    ContactTrack,
+    /// Area a contact is probably in
+    Uncertainty,
+    BearingLine,
+    RangeRing,
+    DangerZone,
+    /// Where a torpedo reaches a tracked contact from, see approach.rs
+    Envelope,
+    /// Limiting lines of approach on a tracked contact
+    ApproachLimit,
+    /// A shot run on paper before firing, see preview.rs
+    AttackPreview,
+    /// Waypoints the own ship is steering along
+    Route,
+    /// Where the own ship reckons it is, see navigation.rs
+    Reckoning,
+    /// How far the own sonars hear a merchant, see coverage.rs
+    Coverage,
+    /// Area of the map, see zone.rs
+    Zone(ZoneKind),
+    /// Shoreline, see coastline.rs
+    Land,
+    /// Plotted by hand, see chart.rs
+    Mark,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Shape {
+    Polyline(Vec<Point>),
+    /// Closed outline
+    Polygon(Vec<Point>),
+    Circle {
+        center: Point,
+        radius: f32,
+    },
+    /// Text written at a point
+    Label {
+        at: Point,
+        text: String,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Item {
+    pub layer: Layer,
+    pub shape: Shape,
+}
+
+/// Point `distance` meters from `from` along `heading` (game angle)
+fn ahead(from: &Point, heading: f32, distance: f32) -> Point {
+    Point {
+        x: from.x + distance * heading.cos(),
+        y: from.y + distance * heading.sin(),
+    }
+}
+
+pub fn range_rings(center: &Point, spacing: f32, count: usize) -> Vec<Item> {
+    (1..=count)
+        .map(|i| Item {
+            layer: Layer::RangeRing,
+            shape: Shape::Circle {
+                center: center.clone(),
+                radius: spacing * i as f32,
+            },
+        })
+        .collect()
+}
+
+/// Line from `origin` along `bearing` (game angle)
+pub fn bearing_line(origin: &Point, bearing: f32, length: f32) -> Item {
+    Item {
+        layer: Layer::BearingLine,
+        shape: Shape::Polyline(vec![origin.clone(), ahead(origin, bearing, length)]),
+    }
+}
+
+/// Track of a contact, ending in a circle of `uncertainty` meters around
+/// its latest estimated position
+pub fn contact_track(history: &[Point], uncertainty: f32) -> Vec<Item> {
+    let mut items = vec![Item {
+        layer: Layer::ContactTrack,
+        shape: Shape::Polyline(history.to_vec()),
+    }];
+    if let Some(last) = history.last() {
+        items.push(Item {
+            layer: Layer::Uncertainty,
+            shape: Shape::Circle {
+                center: last.clone(),
+                radius: uncertainty,
+            },
+        });
+    }
+    items
+}
+
+/// Meters either side of its track a torpedo of `guidance` reaches a
+/// target from
+pub fn reach(guidance: Guidance) -> f32 {
+    match guidance {
+        Guidance::Unguided => HIT_RADIUS,
+        Guidance::Acoustic(generation) => {
+            let seeker = Seeker::new(generation);
+            seeker.max_range * seeker.half_cone.sin()
+        }
+        Guidance::WakeHoming => WAKE_HOMING_REACH,
+    }
+}
+
+/// Lane the torpedo may still reach: the rest of its run, widened by what
+/// its guidance can turn it towards
+pub fn danger_zone(torpedo: &Entity) -> Option<Item> {
+    let state = torpedo.torpedo.as_ref()?;
+    let remaining = (max_run(state.settings.speed) - state.run).max(0.0);
+    let reach = reach(state.guidance);
+    let start = &torpedo.position;
+    let end = ahead(start, torpedo.heading, remaining);
+    let side = torpedo.heading + std::f32::consts::FRAC_PI_2;
+    Some(Item {
+        layer: Layer::DangerZone,
+        shape: Shape::Polygon(vec![
+            ahead(start, side, reach),
+            ahead(&end, side, reach),
+            ahead(&end, side, -reach),
+            ahead(start, side, -reach),
+        ]),
+    })
+}
+
+/// Danger zone of a torpedo set to `weapon` against `target`, and the
+/// limiting lines of approach, `length` meters long, of a boat making
+/// `speed` on it
+pub fn envelope(target: &Solution, weapon: Option<&Weapon>, speed: f32, length: f32) -> Vec<Item> {
+    let mut items: Vec<Item> = weapon
+        .map(|weapon| Item {
+            layer: Layer::Envelope,
+            shape: Shape::Polygon(approach::danger_zone(target, weapon)),
+        })
+        .into_iter()
+        .collect();
+    for end in approach::limiting_lines(target, speed, length)
+        .into_iter()
+        .flatten()
+    {
+        items.push(Item {
+            layer: Layer::ApproachLimit,
+            shape: Shape::Polyline(vec![target.position.clone(), end]),
+        });
+    }
+    items
+}
+
+/// The tracks of the torpedo and the target of a shot previewed, the
+/// point its seeker is enabled and its chance to hit where they meet
+pub fn attack_preview(preview: &Preview) -> Vec<Item> {
+    let mut items = vec![
+        Item {
+            layer: Layer::AttackPreview,
+            shape: Shape::Polyline(preview.torpedo_track.clone()),
+        },
+        Item {
+            layer: Layer::AttackPreview,
+            shape: Shape::Polyline(preview.target_track.clone()),
+        },
+    ];
+    if let Some(enable) = &preview.enable_point {
+        items.push(Item {
+            layer: Layer::AttackPreview,
+            shape: Shape::Circle {
+                center: enable.clone(),
+                radius: HIT_RADIUS,
+            },
+        });
+    }
+    if let Some(end) = preview.torpedo_track.last() {
+        items.push(Item {
+            layer: Layer::AttackPreview,
+            shape: Shape::Label {
+                at: end.clone(),
+                text: format!("{:.0}%", preview.hit_probability * 100.0),
+            },
+        });
+    }
+    items
+}
+
+/// The nav plot of the own ship: its track, range rings, how far it hears
+/// a merchant, bearing lines to what it hears, danger zones of the
+/// torpedoes it knows about, envelopes of the contacts it tracks, and the
+/// marks of the player
+pub fn plot(sim: &Simulation, ring_spacing: f32, rings: usize) -> Vec<Item> {
+    let own = match sim.own_ship() {
+        Some(own) => own,
+        None => return Vec::new(),
+    };
+    let mut items: Vec<Item> = sim
+        .world
+        .zones
+        .iter()
+        .map(|z| Item {
+            layer: Layer::Zone(z.kind),
+            shape: Shape::Polygon(z.points.clone()),
+        })
+        .collect();
+    items.extend(sim.world.coastline.shores.iter().map(|s| Item {
+        layer: Layer::Land,
+        shape: Shape::Polygon(s.points.clone()),
+    }));
+    let mut track = sim.track.to_vec();
+    track.push(own.position.clone());
+    items.push(Item {
+        layer: Layer::OwnTrack,
+        shape: Shape::Polyline(track),
+    });
+    if !sim.route.is_empty() {
+        let mut route = vec![own.position.clone()];
+        route.extend(sim.route.iter().cloned());
+        items.push(Item {
+            layer: Layer::Route,
+            shape: Shape::Polyline(route),
+        });
+    }
+    items.extend(range_rings(&own.position, ring_spacing, rings));
+    let ring = coverage::contour(
+        &sim.world.environment,
+        own,
+        &Signature::MERCHANT,
+        COVERAGE_POINTS,
+    );
+    if !ring.is_empty() {
+        items.push(Item {
+            layer: Layer::Coverage,
+            shape: Shape::Polygon(ring),
+        });
+    }
+    items.push(Item {
+        layer: Layer::Reckoning,
+        shape: Shape::Circle {
+            center: sim.navigation.position(&own.position),
+            radius: sim.navigation.uncertainty(sim.world.time),
+        },
+    });
+    items.extend(sim.chart.items(ring_spacing * rings as f32));
+    let world = &sim.world;
+    for other in world.entities.iter().filter(|e| e.id != own.id) {
+        let heard = passive_excess(&world.environment, own, other).is_some_and(|e| e > 0.0);
+        let ours = other.torpedo.as_ref().is_some_and(|t| t.shooter == own.id);
+        if other.kind == EntityKind::Torpedo && (heard || ours) {
+            items.extend(danger_zone(other));
+        }
+    }
+    let weapon = own
+        .weapons
+        .as_ref()
+        .and_then(|station| station.tubes.tubes.iter().find(|t| t.loaded))
+        .map(|tube| Weapon::torpedo(tube.settings.speed));
+    let speed = sim.own_class().map_or(own.speed, |c| c.max_speed);
+    // what the sensors hold, errors and all, never where the ships are
+    for contact in &sim.contacts.contacts {
+        if !contact.lost {
+            items.push(bearing_line(
+                &own.position,
+                contact.bearing,
+                ring_spacing * rings as f32,
+            ));
+        }
+        match contact.estimate(&own.position) {
+            Some((_, uncertainty)) if !contact.positions.is_empty() => {
+                items.extend(contact_track(&contact.positions.to_vec(), uncertainty));
+            }
+            _ => {}
+        }
+        if let Some(track) = &contact.track {
+            let target = Solution {
+                position: track.projected(world.time - track.time),
+                velocity: track.velocity(),
+            };
+            items.extend(envelope(
+                &target,
+                weapon.as_ref(),
+                speed,
+                ring_spacing * rings as f32,
+            ));
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::torpedo;
+    use crate::tracking::Tracker;
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+    use crate::world::World;
+
+    fn count(items: &[Item], layer: Layer) -> usize {
+        items.iter().filter(|i| i.layer == layer).count()
+    }
+
+    #[test]
+    fn contact_track1() {
+        let history = vec![Point { x: 0.0, y: 0.0 }, Point { x: 100.0, y: 0.0 }];
+        let items = contact_track(&history, 500.0);
+        assert_eq!(
+            items[1].shape,
+            Shape::Circle {
+                center: Point { x: 100.0, y: 0.0 },
+                radius: 500.0
+            }
+        );
+    }
+
+    #[test]
+    fn own_plot() {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        boat.weapons = Some(WeaponsStation::new(2, PresetLibrary::new()));
+        boat.speed = 2.0;
+        let player = world.spawn(boat);
+        let mut merchant = Entity::new("SS Far", EntityKind::Merchant, Point { x: 0.0, y: 9000.0 });
+        merchant.speed = 5.0;
+        world.spawn(merchant);
+        torpedo::fire(&mut world, player, 1, 0.0).unwrap();
+        let mut sim = Simulation::new(world, player);
+        for _ in 0..120 {
+            sim.step(1.0);
+        }
+        let items = plot(&sim, 1000.0, 5);
+        assert_eq!(count(&items, Layer::RangeRing), 5);
+        assert_eq!(count(&items, Layer::Coverage), 1);
+        assert_eq!(count(&items, Layer::DangerZone), 1);
+        // one bearing line a contact held, drawn on its bearing as heard
+        let held: Vec<f32> = sim
+            .contacts
+            .contacts
+            .iter()
+            .filter(|c| !c.lost)
+            .map(|c| c.bearing)
+            .collect();
+        assert!(!held.is_empty());
+        assert_eq!(count(&items, Layer::BearingLine), held.len());
+        let drawn = bearing_line(&sim.own_ship().unwrap().position, held[0], 5000.0);
+        assert!(items.contains(&drawn));
+        // unranged and untracked, nowhere to draw a track
+        assert_eq!(count(&items, Layer::ContactTrack), 0);
+        sim.contacts.select(Tracker::Kalman);
+        for _ in 0..60 {
+            sim.step(1.0);
+        }
+        let items = plot(&sim, 1000.0, 5);
+        assert_eq!(count(&items, Layer::ContactTrack), held.len());
+        match &items
+            .iter()
+            .find(|i| i.layer == Layer::OwnTrack)
+            .unwrap()
+            .shape
+        {
+            Shape::Polyline(track) => assert!(track.len() > 2),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn preview_tracks_and_chance() {
+        let preview = Preview {
+            bearing: 0.0,
+            torpedo_track: vec![Point { x: 0.0, y: 0.0 }, Point { x: 500.0, y: 0.0 }],
+            target_track: vec![Point { x: 500.0, y: 300.0 }, Point { x: 500.0, y: 0.0 }],
+            enable_point: Some(Point { x: 300.0, y: 0.0 }),
+            run_time: 30.0,
+            miss_distance: 0.0,
+            hit_probability: 0.8,
+        };
+        let items = attack_preview(&preview);
+        assert_eq!(count(&items, Layer::AttackPreview), 4);
+        assert_eq!(
+            items[3].shape,
+            Shape::Label {
+                at: Point { x: 500.0, y: 0.0 },
+                text: "80%".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn straight_runner_lane() {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.weapons = Some(WeaponsStation::new(1, PresetLibrary::new()));
+        let player = world.spawn(boat);
+        let id = torpedo::fire(&mut world, player, 1, 0.0).unwrap();
+        let zone = danger_zone(world.entity(id).unwrap()).unwrap();
+        match zone.shape {
+            Shape::Polygon(corners) => {
+                assert!((corners[1].x - 7500.0).abs() < 1.0);
+                assert!((corners[1].y - HIT_RADIUS).abs() < 0.01);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError, Section};
+use crate::environment::Environment;
+use crate::physics::game_to_user_angle;
+use crate::theme::Palette;
+use crate::units::UnitSystem;
+
+// #############################
+// #       PREFERENCES         #
+// #############################
+
+// How the player likes numbers written: units, bearings, clock and dates.
+// Every string meant for the player goes through these, so switching a
+// preference changes all reports at once. They are kept in the
+// "[preferences]" section of a small file:
+//
+// [preferences]
+// units = imperial        # metric or imperial
+// bearings = relative     # true (from north) or relative (from the bow)
+// clock = 12              # 24 or 12 hour clock
+// dates = dmy             # iso (1941-05-20), dmy (20/05/1941) or mdy
+// palette = deuteranopia  # colors of the displays, see theme.rs
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum BearingMode {
+    /// Clockwise from north
+    #[default]
+    True,
+    /// Clockwise from the own ship's bow
+    Relative,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Clock {
+    #[default]
+    TwentyFourHour,
+    TwelveHour,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum DateFormat {
+    /// 1941-05-20
+    #[default]
+    Iso,
+    /// 20/05/1941
+    DayMonthYear,
+    /// 05/20/1941
+    MonthDayYear,
+}
+
+impl fmt::Display for BearingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BearingMode::True => "true",
+            BearingMode::Relative => "relative",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BearingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BearingMode, String> {
+        match s {
+            "true" => Ok(BearingMode::True),
+            "relative" => Ok(BearingMode::Relative),
+            _ => Err(format!("unknown bearing mode '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Clock::TwentyFourHour => "24",
+            Clock::TwelveHour => "12",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Clock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Clock, String> {
+        match s {
+            "24" => Ok(Clock::TwentyFourHour),
+            "12" => Ok(Clock::TwelveHour),
+            _ => Err(format!("unknown clock '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for DateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DateFormat::Iso => "iso",
+            DateFormat::DayMonthYear => "dmy",
+            DateFormat::MonthDayYear => "mdy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DateFormat, String> {
+        match s {
+            "iso" => Ok(DateFormat::Iso),
+            "dmy" => Ok(DateFormat::DayMonthYear),
+            "mdy" => Ok(DateFormat::MonthDayYear),
+            _ => Err(format!("unknown date format '{}'", s)),
+        }
+    }
+}
+
+/// One preference set by the player
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Setting {
+    Units(UnitSystem),
+    Bearings(BearingMode),
+    Clock(Clock),
+    Dates(DateFormat),
+    Palette(Palette),
+}
+
+impl Setting {
+    /// Reads a setting from its key and value, as in the preferences file
+    pub fn parse(key: &str, value: &str) -> Result<Setting, String> {
+        match key {
+            "units" => value.parse().map(Setting::Units),
+            "bearings" => value.parse().map(Setting::Bearings),
+            "clock" => value.parse().map(Setting::Clock),
+            "dates" => value.parse().map(Setting::Dates),
+            "palette" => value.parse().map(Setting::Palette),
+            _ => Err(format!("unknown preference '{}'", key)),
+        }
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Setting::Units(units) => write!(f, "units {}", units),
+            Setting::Bearings(mode) => write!(f, "bearings {}", mode),
+            Setting::Clock(clock) => write!(f, "clock {}", clock),
+            Setting::Dates(format) => write!(f, "dates {}", format),
+            Setting::Palette(palette) => write!(f, "palette {}", palette),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Preferences {
+    pub units: UnitSystem,
+    pub bearings: BearingMode,
+    pub clock: Clock,
+    pub dates: DateFormat,
+    pub palette: Palette,
+}
+
+impl Preferences {
+    pub fn read(section: &Section) -> Result<Preferences, ConfigError> {
+        let defaults = Preferences::default();
+        Ok(Preferences {
+            units: section.parse_or("units", defaults.units)?,
+            bearings: section.parse_or("bearings", defaults.bearings)?,
+            clock: section.parse_or("clock", defaults.clock)?,
+            dates: section.parse_or("dates", defaults.dates)?,
+            palette: section.parse_or("palette", defaults.palette)?,
+        })
+    }
+
+    pub fn write(&self, section: &mut Section) {
+        section.set("units", self.units);
+        section.set("bearings", self.bearings);
+        section.set("clock", self.clock);
+        section.set("dates", self.dates);
+        section.set("palette", self.palette);
+    }
+
+    /// Reads the preferences file, the defaults when it has no preferences
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Preferences, ConfigError> {
+        match Config::load(path)?.section("preferences") {
+            Some(section) => Preferences::read(section),
+            None => Ok(Preferences::default()),
+        }
+    }
+
+    /// Writes the preferences, keeping what else the file holds (see
+    /// console.rs)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let mut config = if path.as_ref().exists() {
+            Config::load(&path)?
+        } else {
+            Config::new()
+        };
+        config.remove_section("preferences");
+        self.write(config.section_mut("preferences"));
+        config.save(path)
+    }
+
+    pub fn set(&mut self, setting: Setting) {
+        match setting {
+            Setting::Units(units) => self.units = units,
+            Setting::Bearings(mode) => self.bearings = mode,
+            Setting::Clock(clock) => self.clock = clock,
+            Setting::Dates(format) => self.dates = format,
+            Setting::Palette(palette) => self.palette = palette,
+        }
+    }
+
+    /// A bearing (game angle) as heard from a ship on `heading` (game
+    /// angle), "045" or "045 rel"
+    pub fn bearing(&self, bearing: f32, heading: f32) -> String {
+        match self.bearings {
+            BearingMode::True => format!("{:03.0}", game_to_user_angle(bearing)),
+            BearingMode::Relative => {
+                let relative = game_to_user_angle(bearing) - game_to_user_angle(heading);
+                format!("{:03.0} rel", relative.rem_euclid(360.0))
+            }
+        }
+    }
+
+    /// Local time of day `time` seconds into the scenario, "14:05" or
+    /// "2:05 pm"
+    pub fn time(&self, environment: &Environment, time: f32) -> String {
+        let (_, seconds) = environment.local_time(time);
+        let minutes = (seconds / 60.0) as u32;
+        let (hours, minutes) = (minutes / 60, minutes % 60);
+        match self.clock {
+            Clock::TwentyFourHour => format!("{:02}:{:02}", hours, minutes),
+            Clock::TwelveHour => {
+                let half = if hours < 12 { "am" } else { "pm" };
+                let hours = match hours % 12 {
+                    0 => 12,
+                    hours => hours,
+                };
+                format!("{}:{:02} {}", hours, minutes, half)
+            }
+        }
+    }
+
+    /// Local date `time` seconds into the scenario
+    pub fn date(&self, environment: &Environment, time: f32) -> String {
+        let (date, _) = environment.local_time(time);
+        match self.dates {
+            DateFormat::Iso => date.to_string(),
+            DateFormat::DayMonthYear => {
+                format!("{:02}/{:02}/{:04}", date.day, date.month, date.year)
+            }
+            DateFormat::MonthDayYear => {
+                format!("{:02}/{:02}/{:04}", date.month, date.day, date.year)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Date;
+    use crate::physics::user_to_game_angle;
+
+    #[test]
+    fn bearings() {
+        let mut preferences = Preferences::default();
+        let east = user_to_game_angle(90.0);
+        let heading = user_to_game_angle(135.0);
+        assert_eq!(preferences.bearing(east, heading), "090");
+        preferences.set(Setting::Bearings(BearingMode::Relative));
+        assert_eq!(preferences.bearing(east, heading), "315 rel");
+    }
+
+    #[test]
+    fn clock_and_dates() {
+        let environment = Environment {
+            date: Date {
+                year: 1941,
+                month: 5,
+                day: 20,
+            },
+            start_time: 23.0 * 3600.0,
+            ..Environment::default()
+        };
+        let mut preferences = Preferences::default();
+        assert_eq!(preferences.time(&environment, 3900.0), "00:05");
+        assert_eq!(preferences.date(&environment, 3900.0), "1941-05-21");
+        preferences.set(Setting::parse("clock", "12").unwrap());
+        preferences.set(Setting::parse("dates", "mdy").unwrap());
+        assert_eq!(preferences.time(&environment, 3900.0), "12:05 am");
+        assert_eq!(preferences.time(&environment, 0.0), "11:00 pm");
+        assert_eq!(preferences.date(&environment, 0.0), "05/20/1941");
+        assert!(Setting::parse("clock", "36").is_err());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let preferences = Preferences {
+            units: UnitSystem::Imperial,
+            bearings: BearingMode::Relative,
+            clock: Clock::TwelveHour,
+            dates: DateFormat::DayMonthYear,
+            palette: Palette::Protanopia,
+        };
+        let mut config = Config::new();
+        preferences.write(config.section_mut("preferences"));
+        let text = config.to_string();
+        let config = Config::parse(&text).unwrap();
+        let read = Preferences::read(config.section("preferences").unwrap()).unwrap();
+        assert_eq!(read, preferences);
+    }
+}
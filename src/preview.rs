@@ -0,0 +1,267 @@
+use crate::approach::Solution;
+use crate::physics::Point;
+use crate::plot;
+use crate::reliability::Reliability;
+use crate::torpedo::{max_run, HIT_RADIUS};
+use crate::tracking::Track;
+use crate::weapons::{Guidance, TorpedoSettings};
+
+// #############################
+// #      ATTACK PREVIEW       #
+// #############################
+
+// Before a shot, the fire control runs it on paper: the torpedo straight
+// out on its bearing at its set speed, the target along the solution, and
+// where the two come closest before the torpedo runs out. The preview
+// gives the tracks of both for the plot to draw, where the seeker is
+// enabled, how long the run takes and by how much the torpedo passes the
+// target according to the solution.
+//
+// How likely it is to hit comes from how far off the solution may be by
+// then: the error of the position grows with the error of the speed over
+// the run, and the target is taken to be anywhere within it, normally
+// distributed across the track. A homing torpedo hits what passes within
+// reach of its seeker once enabled, a wake homer what it crosses the wake
+// of, a straight runner only what it runs into; and the pistol must still
+// work (see reliability.rs). The debrief compares the preview of each shot
+// with what became of it, to tell a bad solution from bad luck.
+
+/// Seconds between two points of the tracks drawn
+const STEP: f32 = 10.0;
+
+/// What the fire control knows of the target: where it is and how it
+/// moves, and how far off that may be
+#[derive(Debug, PartialEq, Clone)]
+pub struct Estimate {
+    pub solution: Solution,
+    /// Meters of standard deviation of the position
+    pub position_error: f32,
+    /// Meters per second of standard deviation of the velocity
+    pub velocity_error: f32,
+}
+
+impl Estimate {
+    /// The estimate of a Kalman track at `time`
+    pub fn from_track(track: &Track, time: f32) -> Estimate {
+        let mut track = track.clone();
+        track.predict(time);
+        Estimate {
+            solution: Solution {
+                position: track.position(),
+                velocity: track.velocity(),
+            },
+            position_error: track.position_error(),
+            velocity_error: (track.covariance[2][2] + track.covariance[3][3]).sqrt(),
+        }
+    }
+
+    /// Meters of standard deviation of where the target is `seconds` on
+    fn error_at(&self, seconds: f32) -> f32 {
+        self.position_error.hypot(self.velocity_error * seconds)
+    }
+}
+
+/// A shot run on paper
+#[derive(Debug, PartialEq, Clone)]
+pub struct Preview {
+    /// Game angle the torpedo runs on
+    pub bearing: f32,
+    /// Where the torpedo is every few seconds of its run, up to where it
+    /// comes closest to the target
+    pub torpedo_track: Vec<Point>,
+    /// Where the target is at the same times
+    pub target_track: Vec<Point>,
+    /// Where the seeker is enabled, None when the torpedo has none or does
+    /// not run that far
+    pub enable_point: Option<Point>,
+    /// Seconds from launch to where the torpedo comes closest
+    pub run_time: f32,
+    /// Meters the torpedo passes the target by according to the solution
+    pub miss_distance: f32,
+    /// 0 to 1
+    pub hit_probability: f32,
+}
+
+impl Preview {
+    /// Whether the solution has the torpedo run into the target
+    pub fn on_target(&self) -> bool {
+        self.miss_distance <= HIT_RADIUS
+    }
+}
+
+/// Seconds from `from` to `to`, within them, at which two points moving
+/// `apart` from `offset` come closest, and how close
+fn closest(offset: &Point, apart: &Point, from: f32, to: f32) -> (f32, f32) {
+    let speed = apart.x * apart.x + apart.y * apart.y;
+    let t = if speed > 0.0 {
+        (-(offset.x * apart.x + offset.y * apart.y) / speed).clamp(from, to)
+    } else {
+        from
+    };
+    let distance = (offset.x + apart.x * t).hypot(offset.y + apart.y * t);
+    (t, distance)
+}
+
+/// Chance a normally distributed miss of `sigma` meters around `miss`
+/// falls within `reach` of the target
+fn within(miss: f32, reach: f32, sigma: f32) -> f32 {
+    if sigma <= 0.0 {
+        return if miss <= reach { 1.0 } else { 0.0 };
+    }
+    let scale = sigma * std::f32::consts::SQRT_2;
+    ((erf((reach - miss) / scale) - erf((-reach - miss) / scale)) / 2.0).clamp(0.0, 1.0)
+}
+
+/// The error function, to 1.5e-7 (Abramowitz and Stegun 7.1.26)
+fn erf(x: f32) -> f32 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_74 + t * (1.421_413_7 + t * (-1.453_152_1 + t * 1.061_405_4))));
+    let y = 1.0 - poly * (-x * x).exp();
+    y.copysign(x)
+}
+
+/// Runs a torpedo `settings` and `guidance` set, fired from `launcher` on
+/// `bearing` (game angle), against the `target` estimated
+pub fn predict(
+    launcher: &Point,
+    bearing: f32,
+    target: &Estimate,
+    settings: &TorpedoSettings,
+    guidance: Guidance,
+    reliability: &Reliability,
+) -> Preview {
+    let speed = settings.speed.meters_per_second();
+    let endurance = max_run(settings.speed) / speed;
+    let heading = Point {
+        x: bearing.cos(),
+        y: bearing.sin(),
+    };
+    let solution = &target.solution;
+    let offset = solution.position.sub(launcher);
+    let apart = Point {
+        x: solution.velocity.x - speed * heading.x,
+        y: solution.velocity.y - speed * heading.y,
+    };
+    let (run_time, miss_distance) = closest(&offset, &apart, 0.0, endurance);
+    let torpedo_at = |t: f32| Point {
+        x: launcher.x + speed * heading.x * t,
+        y: launcher.y + speed * heading.y * t,
+    };
+
+    let enabled = settings.enable_run / speed;
+    let homing = match guidance {
+        Guidance::Unguided => None,
+        guided => Some(plot::reach(guided)),
+    }
+    .filter(|_| enabled < endurance);
+    let mut reaching = within(miss_distance, HIT_RADIUS, target.error_at(run_time));
+    if let Some(reach) = homing {
+        let (t, miss) = closest(&offset, &apart, enabled, endurance);
+        reaching = reaching.max(within(miss, reach, target.error_at(t)));
+    }
+    let course = solution.velocity.y.atan2(solution.velocity.x);
+    let squareness = (bearing - course).sin().powi(2);
+    let works = (1.0 - reliability.premature)
+        * (1.0 - reliability.dud - reliability.dud_square * squareness).max(0.0);
+
+    let steps = (run_time / STEP).ceil() as usize;
+    let times = (0..=steps).map(|i| (i as f32 * STEP).min(run_time));
+    Preview {
+        bearing,
+        torpedo_track: times.clone().map(torpedo_at).collect(),
+        target_track: times.map(|t| solution.at(t)).collect(),
+        enable_point: homing.map(|_| torpedo_at(enabled)),
+        run_time,
+        miss_distance,
+        hit_probability: reaching * works,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approach::intercept_time;
+    use crate::weapons::SpeedSetting;
+
+    const ORIGIN: Point = Point { x: 0.0, y: 0.0 };
+
+    /// A merchant 3 km north making 5 m/s east
+    fn merchant(error: f32) -> Estimate {
+        Estimate {
+            solution: Solution {
+                position: Point { x: 0.0, y: 3000.0 },
+                velocity: Point { x: 5.0, y: 0.0 },
+            },
+            position_error: error,
+            velocity_error: 0.0,
+        }
+    }
+
+    /// Game angle of the intercept course on `target` at medium speed
+    fn lead(target: &Estimate) -> f32 {
+        let speed = SpeedSetting::Medium.meters_per_second();
+        let t = intercept_time(&ORIGIN, &target.solution, speed).unwrap();
+        ORIGIN.angle_to(&target.solution.at(t))
+    }
+
+    #[test]
+    fn a_good_solution_on_the_lead_hits() {
+        let target = merchant(0.0);
+        let settings = TorpedoSettings::default();
+        let preview = predict(
+            &ORIGIN,
+            lead(&target),
+            &target,
+            &settings,
+            Guidance::Unguided,
+            &Reliability::perfect(),
+        );
+        assert!(preview.on_target(), "{}", preview.miss_distance);
+        assert_eq!(preview.hit_probability, 1.0);
+        assert_eq!(preview.enable_point, None);
+        let speed = settings.speed.meters_per_second();
+        let meeting = intercept_time(&ORIGIN, &target.solution, speed).unwrap();
+        assert!((preview.run_time - meeting).abs() < 1.0);
+        let end = preview.torpedo_track.last().unwrap();
+        assert!(end.distance_to(preview.target_track.last().unwrap()) < HIT_RADIUS);
+
+        // straight at where it is now, the torpedo passes astern
+        let behind = predict(
+            &ORIGIN,
+            ORIGIN.angle_to(&target.solution.position),
+            &target,
+            &settings,
+            Guidance::Unguided,
+            &Reliability::perfect(),
+        );
+        assert!(behind.miss_distance > 100.0);
+        assert!(behind.hit_probability < 0.01);
+    }
+
+    #[test]
+    fn uncertainty_seekers_and_pistols() {
+        let settings = TorpedoSettings::default();
+        let sure = merchant(0.0);
+        let vague = merchant(300.0);
+        let bearing = lead(&sure);
+        let shoot = |target: &Estimate, guidance: Guidance, reliability: &Reliability| {
+            predict(&ORIGIN, bearing, target, &settings, guidance, reliability).hit_probability
+        };
+        let perfect = Reliability::perfect();
+        let straight = shoot(&vague, Guidance::Unguided, &perfect);
+        assert!(straight > 0.0 && straight < 0.1, "{}", straight);
+        let homing = Guidance::WakeHoming;
+        let wake = shoot(&vague, homing, &perfect);
+        assert!(wake > 0.6, "{}", wake);
+        let duds = Reliability {
+            dud: 0.5,
+            ..Reliability::perfect()
+        };
+        assert!((shoot(&sure, Guidance::Unguided, &duds) - 0.5).abs() < 0.01);
+        let preview = predict(&ORIGIN, bearing, &sure, &settings, homing, &perfect);
+        let enable = preview.enable_point.unwrap();
+        assert!((enable.distance_to(&ORIGIN) - settings.enable_run).abs() < 1.0);
+    }
+}
@@ -0,0 +1,241 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::physics::Point;
+use crate::tuning::{Tunable, Tuning};
+
+// #############################
+// #     PROPAGATION CACHE     #
+// #############################
+
+// Every tick every sonar works out the loss of sound to everything it may
+// hear, and in charted water that means looking up the bottom cell by cell
+// along the path (see seafloor.rs). The loss changes far more slowly than
+// the ticks go by, so the environment keeps what it worked out for a
+// while, keyed on the cells of QUANTUM meters the two ends of the path lie
+// in and on their depths to within DEPTH_QUANTUM.
+//
+// What it keeps is taken again for at most TTL seconds, and only while the
+// range has not changed by more than RANGE_TOLERANCE of itself, neither
+// end has moved up or down by more than DEPTH_TOLERANCE nor across the
+// layer, and the layer and the tunings the loss is worked out with are
+// the same. Otherwise it is worked out anew. The world advances the clock
+// of the cache every step, which forgets what has grown stale.
+
+/// Seconds a loss worked out is taken again for
+pub const TTL: f32 = 10.0;
+/// Meters on a side of the cells the ends of a path are keyed on
+const QUANTUM: f32 = 100.0;
+/// Meters of depth the ends of a path are keyed on
+const DEPTH_QUANTUM: f32 = 10.0;
+/// Fraction of the range it may change by before the loss is worked out
+/// anew
+const RANGE_TOLERANCE: f32 = 0.02;
+/// Meters either end may move up or down before the loss is worked out
+/// anew
+const DEPTH_TOLERANCE: f32 = 5.0;
+
+type Key = [i32; 6];
+
+/// One end of a path: where it lies and how deep
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct End {
+    x: f32,
+    y: f32,
+    depth: f32,
+}
+
+/// The layer and the tunings a loss was worked out with
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Conditions {
+    layer: Option<f32>,
+    absorption: f32,
+    layer_loss: f32,
+}
+
+impl Conditions {
+    fn now(layer: Option<f32>, tuning: &Tuning) -> Conditions {
+        Conditions {
+            layer,
+            absorption: Tunable::Absorption.get(tuning),
+            layer_loss: Tunable::LayerLoss.get(tuning),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    loss: f32,
+    from: End,
+    to: End,
+    conditions: Conditions,
+    /// Seconds into the scenario it was worked out at
+    at: f32,
+}
+
+impl Entry {
+    /// Whether it may be taken again for a path from `from` to `to`
+    fn fits(&self, from: &End, to: &End, conditions: &Conditions) -> bool {
+        let range = |a: &End, b: &End| (a.x - b.x).hypot(a.y - b.y);
+        let was = range(&self.from, &self.to);
+        let side = |depth: f32| conditions.layer.map(|layer| depth < layer);
+        self.conditions == *conditions
+            && (range(from, to) - was).abs() <= RANGE_TOLERANCE * was
+            && (from.depth - self.from.depth).abs() <= DEPTH_TOLERANCE
+            && (to.depth - self.to.depth).abs() <= DEPTH_TOLERANCE
+            && side(from.depth) == side(self.from.depth)
+            && side(to.depth) == side(self.to.depth)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Losses {
+    /// Seconds into the scenario
+    now: f32,
+    entries: HashMap<Key, Entry>,
+    hits: u64,
+    misses: u64,
+}
+
+/// The losses of sound an environment worked out lately
+#[derive(Debug, Default)]
+pub struct LossCache {
+    losses: RefCell<Losses>,
+}
+
+/// A copy of an environment works its losses out anew
+impl Clone for LossCache {
+    fn clone(&self) -> Self {
+        LossCache::default()
+    }
+}
+
+/// What an environment worked out is no part of what it is
+impl PartialEq for LossCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+fn key(from: &End, to: &End) -> Key {
+    let cell = |v: f32| (v / QUANTUM).floor() as i32;
+    let level = |d: f32| (d / DEPTH_QUANTUM).floor() as i32;
+    [
+        cell(from.x),
+        cell(from.y),
+        level(from.depth),
+        cell(to.x),
+        cell(to.y),
+        level(to.depth),
+    ]
+}
+
+impl LossCache {
+    /// Sets the clock to `time`, forgetting the losses older than TTL
+    pub fn advance(&self, time: f32) {
+        let mut losses = self.losses.borrow_mut();
+        losses.now = time;
+        losses.entries.retain(|_, e| time - e.at <= TTL);
+    }
+
+    /// dB lost between `from` at `from_depth` and `to` at `to_depth` under
+    /// a layer at `layer` and `tuning`, as worked out lately or by
+    /// `work_out`
+    pub fn loss(
+        &self,
+        (from, from_depth): (&Point, f32),
+        (to, to_depth): (&Point, f32),
+        (layer, tuning): (Option<f32>, &Tuning),
+        work_out: impl FnOnce() -> f32,
+    ) -> f32 {
+        let from = End {
+            x: from.x,
+            y: from.y,
+            depth: from_depth,
+        };
+        let to = End {
+            x: to.x,
+            y: to.y,
+            depth: to_depth,
+        };
+        let conditions = Conditions::now(layer, tuning);
+        let key = key(&from, &to);
+        {
+            let mut losses = self.losses.borrow_mut();
+            let now = losses.now;
+            let kept = losses
+                .entries
+                .get(&key)
+                .filter(|e| now - e.at <= TTL && e.fits(&from, &to, &conditions))
+                .map(|e| e.loss);
+            if let Some(loss) = kept {
+                losses.hits += 1;
+                return loss;
+            }
+            losses.misses += 1;
+        }
+        let loss = work_out();
+        let mut losses = self.losses.borrow_mut();
+        let at = losses.now;
+        losses.entries.insert(
+            key,
+            Entry {
+                loss,
+                from,
+                to,
+                conditions,
+                at,
+            },
+        );
+        loss
+    }
+
+    /// Losses taken again and worked out, since the start
+    pub fn hits_and_misses(&self) -> (u64, u64) {
+        let losses = self.losses.borrow();
+        (losses.hits, losses.misses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loss(cache: &LossCache, to_x: f32, to_depth: f32, layer: Option<f32>) -> f32 {
+        let from = Point { x: 0.0, y: 0.0 };
+        let to = Point { x: to_x, y: 0.0 };
+        let tuning = Tuning::default();
+        cache.loss((&from, 30.0), (&to, to_depth), (layer, &tuning), || {
+            to_x / 100.0
+        })
+    }
+
+    #[test]
+    fn taken_again_while_fresh_and_close() {
+        let cache = LossCache::default();
+        assert_eq!(loss(&cache, 5000.0, 50.0, None), 50.0);
+        // a few meters on, the same loss
+        assert_eq!(loss(&cache, 5020.0, 52.0, None), 50.0);
+        assert_eq!(cache.hits_and_misses(), (1, 1));
+        cache.advance(TTL + 1.0);
+        assert_eq!(loss(&cache, 5020.0, 52.0, None), 50.2);
+        assert_eq!(cache.hits_and_misses(), (1, 2));
+    }
+
+    #[test]
+    fn worked_out_anew_across_the_layer_or_the_tolerances() {
+        let cache = LossCache::default();
+        assert_eq!(loss(&cache, 1000.0, 61.0, Some(65.0)), 10.0);
+        assert_eq!(loss(&cache, 1000.0, 64.0, Some(65.0)), 10.0);
+        // under the layer now, though within the tolerance
+        loss(&cache, 1000.0, 66.0, Some(65.0));
+        assert_eq!(cache.hits_and_misses(), (1, 2));
+        // the layer moved
+        loss(&cache, 1000.0, 66.0, Some(80.0));
+        assert_eq!(cache.hits_and_misses(), (1, 3));
+        // farther off than the tolerance, within the same cell
+        assert_eq!(loss(&cache, 1050.0, 66.0, Some(80.0)), 10.5);
+        assert_eq!(cache.hits_and_misses(), (1, 4));
+        assert!(LossCache::default() == cache.clone());
+    }
+}
@@ -0,0 +1,247 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::environment::Environment;
+use crate::physics::{normalize_angle, Point};
+use crate::reverberation::{PULSE_LENGTH, SOUND_SPEED};
+use crate::tuning::{Tunable, Tuning};
+
+// #############################
+// #       SONAR PULSES        #
+// #############################
+
+// What an active sonar sends out is set by its operator, the player of an
+// escort with
+//
+// pulse <short | medium | long> <low | medium | high> [omni | sector]
+//
+// A longer pulse puts more energy into the water, and its echo stands out
+// of the noise farther off; but the clutter it lights up grows with it
+// (see reverberation.rs), and the range of an echo is only known to
+// within half its length in water. A lower band is absorbed less on the
+// way, and reaches farther, in a wider beam that gathers more clutter; a
+// higher band the other way round. A pulse sent all around is heard all
+// around; one sent in a sector ahead is louder within it and faint
+// outside it, for the intercept receivers of the boats abeam and astern
+// as much as for the echoes.
+//
+// The crews of the helicopters choose for themselves, see `Pulse::suited`.
+
+/// Half the width of the sector ahead a pulse may be sent in
+const SECTOR: f32 = PI / 4.0;
+/// dB a pulse sent in a sector is louder within it than all around
+const SECTOR_GAIN: f32 = 6.0;
+/// dB it is fainter outside it
+const BACK_LOBE: f32 = 20.0;
+/// Meters of charted water within which the crews send short, high
+/// pulses, reverberation rather than noise limiting them
+const SHALLOW_WATER: f32 = 200.0;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Length {
+    Short,
+    #[default]
+    Medium,
+    Long,
+}
+
+impl Length {
+    pub fn seconds(&self) -> f32 {
+        match self {
+            Length::Short => PULSE_LENGTH / 4.0,
+            Length::Medium => PULSE_LENGTH,
+            Length::Long => 4.0 * PULSE_LENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Band {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Band {
+    /// How much it is absorbed, against the medium band
+    pub fn absorption(&self) -> f32 {
+        match self {
+            Band::Low => 0.4,
+            Band::Medium => 1.0,
+            Band::High => 2.5,
+        }
+    }
+
+    /// How wide its beam is, against the medium band
+    pub fn beam(&self) -> f32 {
+        match self {
+            Band::Low => 1.5,
+            Band::Medium => 1.0,
+            Band::High => 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Transmission {
+    #[default]
+    Omni,
+    /// Within SECTOR of the heading
+    Sector,
+}
+
+/// The pulse an active sonar sends; the default is what a hull sonar sent
+/// before it could be chosen
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Pulse {
+    pub length: Length,
+    pub band: Band,
+    pub transmission: Transmission,
+}
+
+impl Pulse {
+    /// dB it puts into the water over a pulse of the medium length
+    pub fn energy(&self) -> f32 {
+        10.0 * (self.length.seconds() / PULSE_LENGTH).log10()
+    }
+
+    /// dB it is louder towards `to` from `from`, sent on `heading`
+    pub fn gain(&self, from: &Point, heading: f32, to: &Point) -> f32 {
+        match self.transmission {
+            Transmission::Omni => 0.0,
+            Transmission::Sector => {
+                if normalize_angle(from.angle_to(to) - heading).abs() <= SECTOR {
+                    SECTOR_GAIN
+                } else {
+                    -BACK_LOBE
+                }
+            }
+        }
+    }
+
+    /// dB more it loses over `range` meters than a pulse of the medium
+    /// band, on the way out
+    pub fn extra_absorption(&self, range: f32, tuning: &Tuning) -> f32 {
+        (self.band.absorption() - 1.0) * Tunable::Absorption.get(tuning) * range
+    }
+
+    /// Meters within which the range of an echo is known
+    pub fn range_resolution(&self) -> f32 {
+        SOUND_SPEED * self.length.seconds() / 2.0
+    }
+
+    /// What a crew sends at `at`: short high pulses in shallow water, where
+    /// the bottom returns blind a sonar, long low ones in deep water, for
+    /// the range
+    pub fn suited(environment: &Environment, at: &Point) -> Pulse {
+        let shallow = environment
+            .seafloor
+            .at(at)
+            .depth
+            .is_some_and(|water| water < SHALLOW_WATER);
+        let (length, band) = if shallow {
+            (Length::Short, Band::High)
+        } else {
+            (Length::Long, Band::Low)
+        };
+        Pulse {
+            length,
+            band,
+            transmission: Transmission::Omni,
+        }
+    }
+}
+
+impl FromStr for Pulse {
+    type Err = String;
+
+    /// Reads "<length> <band> [omni | sector]"
+    fn from_str(s: &str) -> Result<Pulse, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (length, band, transmission) = match words.as_slice() {
+            [length, band] => (length, band, &"omni"),
+            [length, band, transmission] => (length, band, transmission),
+            _ => return Err(format!("expected a length and a band, found '{}'", s)),
+        };
+        Ok(Pulse {
+            length: match *length {
+                "short" => Length::Short,
+                "medium" => Length::Medium,
+                "long" => Length::Long,
+                other => return Err(format!("unknown pulse length '{}'", other)),
+            },
+            band: match *band {
+                "low" => Band::Low,
+                "medium" => Band::Medium,
+                "high" => Band::High,
+                other => return Err(format!("unknown band '{}'", other)),
+            },
+            transmission: match *transmission {
+                "omni" => Transmission::Omni,
+                "sector" => Transmission::Sector,
+                other => return Err(format!("unknown transmission '{}'", other)),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Pulse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let length = match self.length {
+            Length::Short => "short",
+            Length::Medium => "medium",
+            Length::Long => "long",
+        };
+        let band = match self.band {
+            Band::Low => "low",
+            Band::Medium => "medium",
+            Band::High => "high",
+        };
+        let transmission = match self.transmission {
+            Transmission::Omni => "omni",
+            Transmission::Sector => "sector",
+        };
+        write!(f, "{} {} {}", length, band, transmission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trades_energy_for_resolution() {
+        let standard = Pulse::default();
+        assert_eq!(standard.energy(), 0.0);
+        assert_eq!(standard.extra_absorption(10_000.0, &Tuning::default()), 0.0);
+        assert_eq!(standard.range_resolution(), 75.0);
+        let long: Pulse = "long low".parse().unwrap();
+        assert!((long.energy() - 6.02).abs() < 0.01);
+        assert_eq!(long.range_resolution(), 300.0);
+        assert!(long.extra_absorption(10_000.0, &Tuning::default()) < 0.0);
+        let short: Pulse = "short high".parse().unwrap();
+        assert!(short.energy() < 0.0);
+        assert!(short.extra_absorption(10_000.0, &Tuning::default()) > 0.0);
+        assert_eq!(short.to_string(), "short high omni");
+        assert!("long".parse::<Pulse>().is_err());
+        assert!("long loud".parse::<Pulse>().is_err());
+    }
+
+    #[test]
+    fn sector_ahead_only() {
+        let sector: Pulse = "medium medium sector".parse().unwrap();
+        let from = Point { x: 0.0, y: 0.0 };
+        let ahead = Point {
+            x: 5000.0,
+            y: 500.0,
+        };
+        let astern = Point { x: -5000.0, y: 0.0 };
+        assert_eq!(sector.gain(&from, 0.0, &ahead), SECTOR_GAIN);
+        assert_eq!(sector.gain(&from, 0.0, &astern), -BACK_LOBE);
+        assert_eq!(Pulse::default().gain(&from, 0.0, &astern), 0.0);
+        let deep = Environment::default();
+        assert_eq!(Pulse::suited(&deep, &from).length, Length::Long);
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::atmosphere;
+use crate::era::Era;
+use crate::events::Event;
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::seakeeping;
+use crate::sensors::{SensorContext, SensorKind};
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #       SURFACE RADAR       #
+// #############################
+
+// Escorts with a radar sweep the sea around them for anything sticking out
+// of it: a surfaced boat, a snorkel head or a bare periscope. How far a
+// radar sees a contact grows with how high it stands out of the water and
+// with the generation of the set; the waves return echoes of their own
+// (sea clutter) which small contacts drown in as the sea gets up. Every
+// sweep of the antenna is another chance, so the longer a mast stays up the
+// likelier it is seen. The generation follows the era of the scenario
+// unless the class says otherwise:
+//
+// [class.flower]
+// radar = true
+// radar_generation = metric    # metric, centimetric or modern
+//
+// Early metric sets barely see a periscope at all; from the centimetric
+// sets of 1943 on, and far more so with modern ones, snorkeling near an
+// escort is a gamble. A contact held on radar is one the escort's gun can
+// engage beyond what its lookouts see.
+
+/// Seconds for one sweep of the antenna
+const SCAN_PERIOD: f32 = 10.0;
+/// Meters above the water of the radar antenna of an escort
+const ANTENNA_HEIGHT: f32 = 20.0;
+/// Meters the conning tower of a surfaced boat stands out of the water
+const CONNING_TOWER: f32 = 5.0;
+/// Meters of a snorkel head, with its exhaust, above the water
+const SNORKEL_HEAD: f32 = 1.0;
+/// Meters of a raised periscope above the water
+const PERISCOPE_MAST: f32 = 0.5;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum RadarGeneration {
+    /// Meter-wave sets of the early war
+    Metric,
+    /// Centimetric sets, from 1943
+    #[default]
+    Centimetric,
+    /// Coherent sets with clutter processing, from 1960
+    Modern,
+}
+
+impl RadarGeneration {
+    pub fn for_era(era: Era) -> RadarGeneration {
+        match era.year {
+            year if year < 1943 => RadarGeneration::Metric,
+            year if year < 1960 => RadarGeneration::Centimetric,
+            _ => RadarGeneration::Modern,
+        }
+    }
+
+    /// Meters at which the set sees a surfaced boat in a calm sea
+    fn range(&self) -> f32 {
+        match self {
+            RadarGeneration::Metric => 5_000.0,
+            RadarGeneration::Centimetric => 12_000.0,
+            RadarGeneration::Modern => 25_000.0,
+        }
+    }
+
+    /// Meters of contact height the clutter of each sea state hides
+    fn clutter(&self) -> f32 {
+        match self {
+            RadarGeneration::Metric => 0.3,
+            RadarGeneration::Centimetric => 0.12,
+            RadarGeneration::Modern => 0.04,
+        }
+    }
+}
+
+impl FromStr for RadarGeneration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "metric" => Ok(RadarGeneration::Metric),
+            "centimetric" => Ok(RadarGeneration::Centimetric),
+            "modern" => Ok(RadarGeneration::Modern),
+            _ => Err(format!("unknown radar generation '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for RadarGeneration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RadarGeneration::Metric => "metric",
+            RadarGeneration::Centimetric => "centimetric",
+            RadarGeneration::Modern => "modern",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Meters `target` stands out of the water, None when nothing does
+pub fn exposed_height(target: &Entity) -> Option<f32> {
+    if target.is_surfaced() || seakeeping::broached(target) {
+        Some(CONNING_TOWER)
+    } else if atmosphere::snorkeling(target) {
+        Some(SNORKEL_HEAD)
+    } else if target.depth <= PERISCOPE_DEPTH && target.mast_raised {
+        Some(PERISCOPE_MAST)
+    } else {
+        None
+    }
+}
+
+/// Meters at which `radar` sees a contact `height` meters tall, limited by
+/// the horizon
+fn detection_range(radar: RadarGeneration, height: f32) -> f32 {
+    let horizon = 4_120.0 * (ANTENNA_HEIGHT.sqrt() + height.sqrt());
+    (radar.range() * (height / CONNING_TOWER).sqrt()).min(horizon)
+}
+
+/// Chance one sweep of `observer`'s radar paints `target`, 0 when the
+/// radar is out or nothing of the target is above water
+pub fn sweep_probability(world: &World, observer: &Entity, target: &Entity) -> f32 {
+    let context = SensorContext::new(observer, &world.environment);
+    let health = match observer
+        .sensors
+        .iter()
+        .find(|s| s.kind == SensorKind::Radar && s.is_operational(&context))
+    {
+        Some(sensor) => sensor.health,
+        None => return 0.0,
+    };
+    let height = match exposed_height(target) {
+        Some(height) => height,
+        None => return 0.0,
+    };
+    let radar = observer.radar_generation;
+    let range = observer.position.distance_to(&target.position);
+    let reach = detection_range(radar, height) * health;
+    let signal = (1.0 - (range / reach).powi(4)).max(0.0);
+    let clutter = world.environment.sea_state as f32 * radar.clutter() / height;
+    signal * (1.0 - clutter).clamp(0.0, 1.0) * observer.crew_performance()
+}
+
+/// Sweeps every radar on by `dt` seconds: submarines painted are held
+/// until they slip under or out of range, new contacts are reported
+pub fn update(world: &mut World, dt: f32) {
+    let mut sweeps = Vec::new();
+    for observer in world.entities.iter() {
+        if observer.is_destroyed() || !observer.sensors.iter().any(|s| s.kind == SensorKind::Radar)
+        {
+            continue;
+        }
+        for target in world.entities.iter() {
+            if target.id == observer.id
+                || target.kind != EntityKind::Submarine
+                || target.is_destroyed()
+            {
+                continue;
+            }
+            let p = sweep_probability(world, observer, target);
+            if p > 0.0 {
+                let chance = 1.0 - (1.0 - p).powf(dt / SCAN_PERIOD);
+                let held = observer.radar_contacts.contains(&target.id);
+                sweeps.push((observer.id, target.id, chance, held));
+            }
+        }
+    }
+    let mut contacts: Vec<(EntityId, Vec<EntityId>)> = Vec::new();
+    for observer in world.entities.iter() {
+        if !observer.radar_contacts.is_empty() {
+            contacts.push((observer.id, Vec::new()));
+        }
+    }
+    let mut events = Vec::new();
+    for (observer, target, chance, held) in sweeps {
+        if !held && !world.rng.chance(chance) {
+            continue;
+        }
+        if !held {
+            events.push(Event::RadarContact { observer, target });
+        }
+        match contacts.iter_mut().find(|(id, _)| *id == observer) {
+            Some((_, held)) => held.push(target),
+            None => contacts.push((observer, vec![target])),
+        }
+    }
+    for (observer, held) in contacts {
+        world.entity_mut(observer).unwrap().radar_contacts = held;
+    }
+    for event in events {
+        world.emit(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atmosphere::Atmosphere;
+    use crate::physics::Point;
+    use crate::sensors::Sensor;
+
+    /// An escort with a radar of `generation`, and a snorkeling boat
+    /// `range` meters off
+    fn snorkeling(generation: RadarGeneration, range: f32) -> World {
+        let mut world = World::new();
+        world.environment.sea_state = 3;
+        let mut escort = Entity::new("Vanoc", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.sensors.push(Sensor::new(SensorKind::Radar));
+        escort.radar_generation = generation;
+        world.spawn(escort);
+        let mut boat = Entity::new("U-100", EntityKind::Submarine, Point { x: range, y: 0.0 });
+        boat.depth = PERISCOPE_DEPTH;
+        boat.atmosphere = Some(Atmosphere::new(72.0, 0, true));
+        world.spawn(boat);
+        world
+    }
+
+    fn probability(world: &World) -> f32 {
+        sweep_probability(world, world.entity(1).unwrap(), world.entity(2).unwrap())
+    }
+
+    #[test]
+    fn later_sets_see_snorkels_further() {
+        assert_eq!(
+            probability(&snorkeling(RadarGeneration::Metric, 3000.0)),
+            0.0
+        );
+        let centimetric = probability(&snorkeling(RadarGeneration::Centimetric, 3000.0));
+        let modern = probability(&snorkeling(RadarGeneration::Modern, 3000.0));
+        assert!((0.4..0.7).contains(&centimetric), "{}", centimetric);
+        assert!(modern > 0.8, "{}", modern);
+
+        // a bare periscope is lost in the clutter of a rough sea
+        let mut world = snorkeling(RadarGeneration::Centimetric, 3000.0);
+        let boat = world.entity_mut(2).unwrap();
+        boat.atmosphere = None;
+        boat.mast_raised = true;
+        let calm = probability(&world);
+        world.environment.sea_state = 4;
+        assert!(probability(&world) < calm / 5.0);
+        world.entity_mut(2).unwrap().mast_raised = false;
+        assert_eq!(probability(&world), 0.0);
+    }
+
+    #[test]
+    fn contact_held_until_the_boat_goes_deep() {
+        let mut world = snorkeling(RadarGeneration::Centimetric, 2000.0);
+        for _ in 0..30 {
+            world.step(1.0);
+        }
+        assert_eq!(world.entity(1).unwrap().radar_contacts, vec![2]);
+        let contacts = world
+            .events
+            .iter()
+            .filter(|e| matches!(e.event, Event::RadarContact { .. }))
+            .count();
+        assert_eq!(contacts, 1);
+
+        world.entity_mut(2).unwrap().depth = 50.0;
+        world.step(1.0);
+        assert!(world.entity(1).unwrap().radar_contacts.is_empty());
+    }
+}
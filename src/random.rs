@@ -0,0 +1,117 @@
+// Small deterministic generator (xorshift64*) so a scenario replays the same
+// way from the same seed on every platform.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(0x5EED)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng {
+            state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform value in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform value in [low, high)
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+
+    /// Returns true with the given probability
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Normally distributed value (Box-Muller)
+    pub fn gaussian(&mut self, mean: f32, sigma: f32) -> f32 {
+        let u1 = self.next_f32().max(f32::MIN_POSITIVE);
+        let u2 = self.next_f32();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
+        mean + sigma * z
+    }
+
+    /// Uniform index in [0, n)
+    pub fn index(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    /// A generator for a part of the simulation that must not share the
+    /// draws of this one, numbered `stream`, without drawing on this one
+    pub fn stream(&self, stream: u64) -> Rng {
+        // splitmix64 finalizer, so that neighbouring states and streams
+        // seed generators far apart
+        let mut z = self.state ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng::new(z ^ (z >> 31))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn streams_apart() {
+        let world = Rng::new(42);
+        let mut own = world.stream(1);
+        assert_eq!(own, Rng::new(42).stream(1));
+        assert_eq!(world, Rng::new(42));
+        let mut other = world.stream(2);
+        let mut same = world.clone();
+        for _ in 0..10 {
+            let draw = own.next_u64();
+            assert_ne!(draw, other.next_u64());
+            assert_ne!(draw, same.next_u64());
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.next_f32();
+            assert!((0.0..1.0).contains(&x));
+            let y = rng.range(-3.0, 5.0);
+            assert!((-3.0..5.0).contains(&y));
+            assert!(rng.index(3) < 3);
+        }
+    }
+
+    #[test]
+    fn gaussian_mean() {
+        let mut rng = Rng::new(3);
+        let n = 5000;
+        let mean: f32 = (0..n).map(|_| rng.gaussian(10.0, 2.0)).sum::<f32>() / n as f32;
+        assert!((mean - 10.0).abs() < 0.2);
+    }
+}
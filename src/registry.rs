@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use crate::physics::Point;
+use crate::world::{Entity, EntityId};
+use crate::zone::Zone;
+
+// #############################
+// #      ENTITY REGISTRY      #
+// #############################
+
+// Every entity of the world, in the order it was spawned, with an index by
+// id so that looking one up does not scan them all. Besides the id, an
+// entity can be found by its name, and groups of them by class, side, tag
+// or where they are. Sides and tags come from the scenario:
+//
+// [entity.Walker]
+// side = allied
+// tags = escort, B3       # any words, separated by commas
+//
+// The registry derefs to the slice of entities for reading them, and hands
+// them out for changing one at a time, by id or in turn, never as a slice
+// that could be reordered under the index. Adding and removing entities
+// goes through World::spawn and World::remove so the index stays right.
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct EntityRegistry {
+    entities: Vec<Entity>,
+    /// Position of each entity in `entities`, by id
+    index: HashMap<EntityId, usize>,
+}
+
+impl EntityRegistry {
+    pub fn new() -> EntityRegistry {
+        EntityRegistry::default()
+    }
+
+    /// Adds `entity`, which must already have its id
+    pub fn insert(&mut self, entity: Entity) {
+        self.index.insert(entity.id, self.entities.len());
+        self.entities.push(entity);
+    }
+
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        let index = self.index.remove(&id)?;
+        let entity = self.entities.remove(index);
+        for e in &self.entities[index..] {
+            *self.index.get_mut(&e.id).unwrap() -= 1;
+        }
+        Some(entity)
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.index.get(&id).map(|&i| &self.entities[i])
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        match self.index.get(&id) {
+            Some(&i) => Some(&mut self.entities[i]),
+            None => None,
+        }
+    }
+
+    /// Every entity in turn, for changing them in place
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Entity> {
+        self.entities.iter_mut()
+    }
+
+    /// The first entity named `name`
+    pub fn by_name(&self, name: &str) -> Option<&Entity> {
+        self.entities.iter().find(|e| e.name == name)
+    }
+
+    pub fn by_class<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.class.as_deref() == Some(class))
+    }
+
+    pub fn by_side<'a>(&'a self, side: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.side.as_deref() == Some(side))
+    }
+
+    pub fn by_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.tags.iter().any(|t| t == tag))
+    }
+
+    /// Entities within `radius` meters of `center`
+    pub fn in_area<'a>(
+        &'a self,
+        center: &'a Point,
+        radius: f32,
+    ) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.position.distance_to(center) <= radius)
+    }
+
+    /// Entities inside `zone`
+    pub fn in_zone<'a>(&'a self, zone: &'a Zone) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| zone.contains(&e.position))
+    }
+}
+
+impl Deref for EntityRegistry {
+    type Target = [Entity];
+
+    fn deref(&self) -> &[Entity] {
+        &self.entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{EntityKind, World};
+    use crate::zone::ZoneKind;
+
+    fn convoy() -> World {
+        let mut world = World::new();
+        for (i, name) in ["Walker", "Vanoc", "SS Empire", "SS Clan"]
+            .iter()
+            .enumerate()
+        {
+            let kind = if i < 2 {
+                EntityKind::Warship
+            } else {
+                EntityKind::Merchant
+            };
+            let x = 1000.0 * i as f32;
+            let mut ship = Entity::new(name, kind, Point { x, y: 0.0 });
+            ship.side = Some("allied".to_string());
+            if i < 2 {
+                ship.tags = vec!["escort".to_string(), "B3".to_string()];
+                ship.class = Some("destroyer".to_string());
+            }
+            world.spawn(ship);
+        }
+        world
+    }
+
+    fn names<'a>(entities: impl Iterator<Item = &'a Entity>) -> Vec<&'a str> {
+        entities.map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn lookups() {
+        let world = convoy();
+        let registry = &world.entities;
+        assert_eq!(registry.get(3).unwrap().name, "SS Empire");
+        assert_eq!(registry.by_name("Vanoc").unwrap().id, 2);
+        assert_eq!(names(registry.by_tag("escort")), vec!["Walker", "Vanoc"]);
+        assert_eq!(names(registry.by_class("destroyer")).len(), 2);
+        assert_eq!(names(registry.by_side("allied")).len(), 4);
+        assert_eq!(names(registry.by_side("axis")).len(), 0);
+        let center = Point { x: 2500.0, y: 0.0 };
+        assert_eq!(
+            names(registry.in_area(&center, 600.0)),
+            vec!["SS Empire", "SS Clan"]
+        );
+        let zone = Zone {
+            name: "box".to_string(),
+            kind: ZoneKind::Patrol,
+            points: vec![
+                Point { x: -10.0, y: -10.0 },
+                Point {
+                    x: 1010.0,
+                    y: -10.0,
+                },
+                Point { x: 1010.0, y: 10.0 },
+                Point { x: -10.0, y: 10.0 },
+            ],
+            depth: None,
+            bottom: None,
+        };
+        assert_eq!(names(registry.in_zone(&zone)), vec!["Walker", "Vanoc"]);
+    }
+
+    #[test]
+    fn index_survives_removal() {
+        let mut world = convoy();
+        assert_eq!(world.remove(2).unwrap().name, "Vanoc");
+        assert!(world.entity(2).is_none());
+        assert_eq!(world.entity(4).unwrap().name, "SS Clan");
+        world.entity_mut(3).unwrap().speed = 5.0;
+        assert_eq!(world.entities[1].speed, 5.0);
+        for entity in world.entities.iter_mut() {
+            entity.depth = 10.0;
+        }
+        assert_eq!(world.entities.get(4).unwrap().depth, 10.0);
+        let id = world.spawn(Entity::new(
+            "Broke",
+            EntityKind::Warship,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        assert_eq!(world.entity(id).unwrap().name, "Broke");
+    }
+}
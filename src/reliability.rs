@@ -0,0 +1,237 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{ConfigError, Section};
+use crate::era::Era;
+use crate::messages::Catalog;
+use crate::random::Rng;
+
+// #############################
+// #    TORPEDO RELIABILITY    #
+// #############################
+
+// Early-war torpedoes were notoriously unreliable: magnetic pistols fired
+// early, contact pistols crushed on square hits and depth engines ran deep.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Failure {
+    /// The pistol fired before reaching the target
+    Premature,
+    /// Hit the target without exploding
+    Dud,
+    /// Ran too deep and passed under the keel
+    RanDeep,
+}
+
+impl Failure {
+    /// Id of the description in the message catalog
+    pub fn message(&self) -> &'static str {
+        match self {
+            Failure::Premature => "failure-premature",
+            Failure::Dud => "failure-dud",
+            Failure::RanDeep => "failure-ran-deep",
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Catalog::default().get(self.message()))
+    }
+}
+
+/// How much of the historical unreliability is simulated
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Realism {
+    /// Torpedoes always work
+    Perfect,
+    /// Half the historical failure rates
+    Reduced,
+    Historical,
+}
+
+impl Realism {
+    fn factor(&self) -> f32 {
+        match self {
+            Realism::Perfect => 0.0,
+            Realism::Reduced => 0.5,
+            Realism::Historical => 1.0,
+        }
+    }
+}
+
+impl FromStr for Realism {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "perfect" => Ok(Realism::Perfect),
+            "reduced" => Ok(Realism::Reduced),
+            "historical" => Ok(Realism::Historical),
+            _ => Err(format!("unknown realism '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reliability {
+    /// Chance per shot of the pistol firing early
+    pub premature: f32,
+    /// Chance of a dud on any hit
+    pub dud: f32,
+    /// Extra chance of a dud on a square (90 degree) hit
+    pub dud_square: f32,
+    /// Meters the torpedo runs deeper than set, on average
+    pub depth_bias: f32,
+    /// Spread in meters of the running depth
+    pub depth_sigma: f32,
+}
+
+impl Reliability {
+    pub fn perfect() -> Reliability {
+        Reliability {
+            premature: 0.0,
+            dud: 0.0,
+            dud_square: 0.0,
+            depth_bias: 0.0,
+            depth_sigma: 0.0,
+        }
+    }
+
+    pub fn for_era(era: Era) -> Reliability {
+        if era.year < 1943 {
+            Reliability {
+                premature: 0.1,
+                dud: 0.1,
+                dud_square: 0.5,
+                depth_bias: 3.0,
+                depth_sigma: 1.5,
+            }
+        } else if era.year < 1960 {
+            Reliability {
+                premature: 0.03,
+                dud: 0.05,
+                dud_square: 0.1,
+                depth_bias: 0.5,
+                depth_sigma: 1.0,
+            }
+        } else {
+            Reliability {
+                premature: 0.005,
+                dud: 0.01,
+                dud_square: 0.0,
+                depth_bias: 0.0,
+                depth_sigma: 0.3,
+            }
+        }
+    }
+
+    pub fn with_realism(&self, realism: Realism) -> Reliability {
+        let k = realism.factor();
+        Reliability {
+            premature: self.premature * k,
+            dud: self.dud * k,
+            dud_square: self.dud_square * k,
+            depth_bias: self.depth_bias * k,
+            depth_sigma: self.depth_sigma * k,
+        }
+    }
+
+    /// Reads overrides from a "[reliability]" section on top of `self`
+    pub fn read(&self, section: &Section) -> Result<Reliability, ConfigError> {
+        Ok(Reliability {
+            premature: section.parse_or("premature", self.premature)?,
+            dud: section.parse_or("dud", self.dud)?,
+            dud_square: section.parse_or("dud_square", self.dud_square)?,
+            depth_bias: section.parse_or("depth_bias", self.depth_bias)?,
+            depth_sigma: section.parse_or("depth_sigma", self.depth_sigma)?,
+        })
+    }
+
+    /// Depth the torpedo actually runs at when set to `set_depth`
+    pub fn running_depth(&self, rng: &mut Rng, set_depth: f32) -> f32 {
+        (set_depth + rng.gaussian(self.depth_bias, self.depth_sigma)).max(0.0)
+    }
+
+    /// Whether the pistol goes off as it arms, short of any target
+    pub fn fires_early(&self, rng: &mut Rng) -> bool {
+        rng.chance(self.premature)
+    }
+
+    /// Decides how a torpedo fares against a target of keel depth `draft`.
+    /// `impact_angle` is the angle between the torpedo track and the target
+    /// hull in radians (PI/2 is a square hit)
+    pub fn resolve(
+        &self,
+        rng: &mut Rng,
+        running_depth: f32,
+        draft: f32,
+        impact_angle: f32,
+    ) -> Result<(), Failure> {
+        if running_depth > draft {
+            return Err(Failure::RanDeep);
+        }
+        let squareness = impact_angle.sin().abs().powi(2);
+        if rng.chance(self.dud + self.dud_square * squareness) {
+            return Err(Failure::Dud);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn failures(reliability: &Reliability, set_depth: f32, angle: f32) -> (usize, usize, usize) {
+        let mut rng = Rng::new(11);
+        let mut counts = (0, 0, 0);
+        for _ in 0..2000 {
+            if reliability.fires_early(&mut rng) {
+                counts.0 += 1;
+                continue;
+            }
+            let depth = reliability.running_depth(&mut rng, set_depth);
+            match reliability.resolve(&mut rng, depth, 8.0, angle) {
+                Err(Failure::Dud) => counts.1 += 1,
+                Err(Failure::RanDeep) => counts.2 += 1,
+                Err(Failure::Premature) | Ok(()) => {}
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn perfect_never_fails() {
+        assert_eq!(failures(&Reliability::perfect(), 5.0, FRAC_PI_2), (0, 0, 0));
+        let none = Reliability::for_era(Era::WORLD_WAR_TWO).with_realism(Realism::Perfect);
+        assert_eq!(failures(&none, 5.0, FRAC_PI_2), (0, 0, 0));
+    }
+
+    #[test]
+    fn early_war_square_hits_dud() {
+        let early = Reliability::for_era(Era::WORLD_WAR_TWO);
+        let square = failures(&early, 2.0, FRAC_PI_2);
+        let glancing = failures(&early, 2.0, 0.5);
+        assert!(square.1 > glancing.1 * 2);
+        assert!(square.0 > 100);
+    }
+
+    #[test]
+    fn early_war_runs_deep() {
+        let early = Reliability::for_era(Era::WORLD_WAR_TWO);
+        let modern = Reliability::for_era(Era::MODERN);
+        assert!(failures(&early, 6.0, 0.5).2 > 1000);
+        assert_eq!(failures(&modern, 6.0, 0.5).2, 0);
+    }
+
+    #[test]
+    fn read_overrides() {
+        let config = crate::config::Config::parse("[reliability]\ndud = 0.5\n").unwrap();
+        let base = Reliability::perfect();
+        let read = base.read(config.section("reliability").unwrap()).unwrap();
+        assert_eq!(read.dud, 0.5);
+        assert_eq!(read.premature, 0.0);
+    }
+}
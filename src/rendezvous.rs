@@ -0,0 +1,420 @@
+use crate::config::{Config, ConfigError};
+use crate::events::Event;
+use crate::hfdf::{self, Fix};
+use crate::physics::Point;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #   RESCUE AND RENDEZVOUS   #
+// #############################
+
+// Some missions are not about sinking anything: a downed pilot is picked
+// up from a dinghy, a party of commandos put ashore in folding boats, fuel
+// and torpedoes taken over from a supply ship far out at sea. Each is a
+// "[rendezvous.<name>]" section:
+//
+// [rendezvous.Pilot]
+// kind = pickup           # pickup, insertion or resupply
+// x = 12000               # meters east
+// y = 4000                # meters north
+// radius = 400            # optional, meters from the point to lie within
+// opens = 3600            # seconds into the scenario the window opens...
+// closes = 10800          # ...and closes at
+// transfer = 900          # seconds it takes to get everyone across
+// signal = 1800           # optional, seconds into the scenario the point
+//                         # is radioed to the boat, see signals.rs
+//
+// A resupply also needs the supply ship, a friendly entity of the
+// scenario, and weather calm enough to pass hoses and torpedoes across:
+//
+// tender = U-459          # the supply ship, within the radius too
+// sea_state = 4           # optional, the roughest sea it can be done in
+// compromise = 0.2        # optional, chance the enemy reads the signal
+//
+// A signal the enemy reads is acted on like a radio fix of the supply ship
+// (see hfdf.rs): their nearest escorts are sent to the point. Once done,
+// the boat has full stores (see stores.rs) and every tube loaded.
+//
+// A submarine makes the transfer lying stopped on the surface within the
+// radius while the window is open. Diving, getting under way or drifting
+// off interrupts it, and it has to start over; if it is not done by the
+// time the window closes, the mission has failed, except for a missed
+// resupply, which the boat simply goes without. Either way the outcome is
+// an event, which is what objectives check.
+
+/// Meters from the point a boat must lie within, unless set otherwise
+const RADIUS: f32 = 500.0;
+/// Meters per second under which a boat counts as stopped
+const STOPPED: f32 = 0.5;
+/// Roughest sea a resupply can be made in, unless set otherwise
+const RESUPPLY_SEA_STATE: u8 = 4;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RendezvousKind {
+    /// Taking aboard a downed pilot or a party waiting at sea
+    Pickup,
+    /// Putting a party across
+    Insertion,
+    /// Taking on fuel and torpedoes from a supply ship
+    Resupply,
+}
+
+impl std::str::FromStr for RendezvousKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RendezvousKind, String> {
+        match s {
+            "pickup" => Ok(RendezvousKind::Pickup),
+            "insertion" => Ok(RendezvousKind::Insertion),
+            "resupply" => Ok(RendezvousKind::Resupply),
+            other => Err(format!("unknown rendezvous kind '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RendezvousState {
+    /// Nobody alongside yet
+    Waiting,
+    /// `entity` has been transferring for `done` seconds
+    Transferring {
+        entity: EntityId,
+        done: f32,
+    },
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rendezvous {
+    pub name: String,
+    pub kind: RendezvousKind,
+    pub position: Point,
+    pub radius: f32,
+    /// Seconds into the scenario the window opens and closes at
+    pub opens: f32,
+    pub closes: f32,
+    /// Seconds of uninterrupted transfer needed
+    pub transfer: f32,
+    /// Seconds into the scenario the point is radioed at
+    pub signal: Option<f32>,
+    /// Name of the supply ship of a resupply
+    pub tender: Option<String>,
+    /// Roughest sea the transfer can be made in
+    pub sea_state: u8,
+    /// Chance the enemy reads the signal
+    pub compromise: f32,
+    /// Whether the enemy has read it
+    pub compromised: bool,
+    /// Whether it has been sent
+    radioed: bool,
+    pub state: RendezvousState,
+}
+
+impl Rendezvous {
+    /// Reads every "[rendezvous.<name>]" section
+    pub fn read_all(config: &Config) -> Result<Vec<Rendezvous>, ConfigError> {
+        let mut all = Vec::new();
+        for (name, section) in config.sections_with_prefix("rendezvous") {
+            let opens: f32 = section.parse("opens")?;
+            let closes: f32 = section.parse("closes")?;
+            if closes <= opens {
+                return Err(ConfigError::Invalid {
+                    section: section.name.clone(),
+                    key: "closes".to_string(),
+                    value: closes.to_string(),
+                });
+            }
+            let kind = section.parse("kind")?;
+            let tender = section.get("tender").map(|t| t.to_string());
+            if kind == RendezvousKind::Resupply && tender.is_none() {
+                return Err(ConfigError::Missing {
+                    section: section.name.clone(),
+                    key: "tender".to_string(),
+                });
+            }
+            all.push(Rendezvous {
+                name: name.to_string(),
+                kind,
+                position: Point {
+                    x: section.parse("x")?,
+                    y: section.parse("y")?,
+                },
+                radius: section.parse_or("radius", RADIUS)?,
+                opens,
+                closes,
+                transfer: section.parse("transfer")?,
+                signal: section.parse_optional("signal")?,
+                tender,
+                sea_state: section.parse_or("sea_state", RESUPPLY_SEA_STATE)?,
+                compromise: section.parse_or("compromise", 0.0)?,
+                compromised: false,
+                radioed: false,
+                state: RendezvousState::Waiting,
+            });
+        }
+        Ok(all)
+    }
+
+    pub fn is_open(&self, time: f32) -> bool {
+        self.opens <= time && time < self.closes
+    }
+
+    /// Whether `entity` of `world` is where and how it can make the
+    /// transfer
+    fn holds(&self, world: &World, entity: EntityId) -> bool {
+        let there = world.entity(entity).is_some_and(|e| {
+            e.kind == EntityKind::Submarine
+                && !e.is_destroyed()
+                && e.is_surfaced()
+                && e.speed < STOPPED
+                && e.position.distance_to(&self.position) <= self.radius
+        });
+        if self.kind != RendezvousKind::Resupply {
+            return there;
+        }
+        there
+            && world.environment.sea_state <= self.sea_state
+            && self.supply_ship(world).is_some_and(|tender| {
+                tender.id != entity && tender.position.distance_to(&self.position) <= self.radius
+            })
+    }
+
+    /// The supply ship of a resupply, while afloat
+    fn supply_ship<'a>(&self, world: &'a World) -> Option<&'a Entity> {
+        let name = self.tender.as_ref()?;
+        world
+            .entities
+            .iter()
+            .find(|e| &e.name == name && !e.is_destroyed())
+    }
+}
+
+/// Fills up the stores of `entity` and loads its tubes
+fn resupply(world: &mut World, entity: EntityId) {
+    let boat = match world.entity_mut(entity) {
+        Some(boat) => boat,
+        None => return,
+    };
+    if let Some(stores) = boat.stores.as_mut() {
+        stores.remaining = stores.capacity;
+    }
+    if let Some(weapons) = boat.weapons.as_mut() {
+        for tube in weapons.tubes.tubes.iter_mut() {
+            tube.loaded = true;
+        }
+    }
+}
+
+/// Rolls whether the enemy reads the signal of `r`, and if they do sends
+/// their escorts as for a radio fix of the supply ship
+fn radio(world: &mut World, r: &mut Rendezvous) {
+    let tender = match r.supply_ship(world) {
+        Some(tender) => tender.id,
+        None => return,
+    };
+    if !world.rng.chance(r.compromise) {
+        return;
+    }
+    r.compromised = true;
+    world.emit(Event::RendezvousCompromised {
+        name: r.name.clone(),
+    });
+    let fix = Fix {
+        position: r.position.clone(),
+        error: r.radius,
+    };
+    hfdf::alert(world, fix, tender);
+}
+
+/// Moves the transfers of the rendezvous on, starting, interrupting,
+/// completing and failing them
+pub fn update(world: &mut World, dt: f32) {
+    let time = world.time;
+    let mut rendezvous = std::mem::take(&mut world.rendezvous);
+    for r in rendezvous.iter_mut() {
+        if !r.radioed && r.signal.is_some_and(|at| at <= time) {
+            r.radioed = true;
+            radio(world, r);
+        }
+        match r.state {
+            RendezvousState::Completed | RendezvousState::Failed => continue,
+            _ if time >= r.closes => {
+                r.state = RendezvousState::Failed;
+                if r.kind != RendezvousKind::Resupply {
+                    world.emit(Event::MissionFailed {
+                        name: r.name.clone(),
+                    });
+                }
+                continue;
+            }
+            _ if !r.is_open(time) => continue,
+            RendezvousState::Waiting => {
+                let alongside = world
+                    .entities
+                    .iter()
+                    .map(|e| e.id)
+                    .find(|id| r.holds(world, *id));
+                if let Some(entity) = alongside {
+                    r.state = RendezvousState::Transferring { entity, done: 0.0 };
+                }
+            }
+            RendezvousState::Transferring { entity, done } => {
+                if !r.holds(world, entity) {
+                    r.state = RendezvousState::Waiting;
+                    world.emit(Event::TransferInterrupted {
+                        entity,
+                        name: r.name.clone(),
+                    });
+                } else if done + dt >= r.transfer {
+                    r.state = RendezvousState::Completed;
+                    let name = r.name.clone();
+                    if r.kind == RendezvousKind::Resupply {
+                        resupply(world, entity);
+                        world.emit(Event::Resupplied { entity, name });
+                    } else {
+                        world.emit(Event::MissionCompleted { entity, name });
+                    }
+                } else {
+                    r.state = RendezvousState::Transferring {
+                        entity,
+                        done: done + dt,
+                    };
+                }
+            }
+        }
+    }
+    world.rendezvous = rendezvous;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+
+    fn world() -> (World, EntityId) {
+        let config = Config::parse(
+            "[rendezvous.Pilot]\nkind = pickup\nx = 0\ny = 0\nopens = 10\ncloses = 100\n\
+             transfer = 30",
+        )
+        .unwrap();
+        let mut world = World::new();
+        world.rendezvous = Rendezvous::read_all(&config).unwrap();
+        let boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 100.0, y: 0.0 });
+        let boat = world.spawn(boat);
+        (world, boat)
+    }
+
+    /// The events of the rendezvous, without the noises the boat made
+    fn outcomes(world: &World) -> Vec<&Event> {
+        world
+            .events
+            .iter()
+            .map(|e| &e.event)
+            .filter(|e| !matches!(e, Event::Transient { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn transfer_completes_in_the_window() {
+        let (mut world, boat) = world();
+        for _ in 0..9 {
+            world.step(1.0);
+        }
+        assert_eq!(world.rendezvous[0].state, RendezvousState::Waiting);
+        for _ in 0..40 {
+            world.step(1.0);
+        }
+        assert_eq!(world.rendezvous[0].state, RendezvousState::Completed);
+        assert_eq!(
+            outcomes(&world),
+            vec![&Event::MissionCompleted {
+                entity: boat,
+                name: "Pilot".to_string()
+            }]
+        );
+
+        let bad = Config::parse(
+            "[rendezvous.X]\nkind = drop\nx = 0\ny = 0\nopens = 0\ncloses = 1\ntransfer = 1",
+        )
+        .unwrap();
+        assert!(Rendezvous::read_all(&bad).is_err());
+    }
+
+    #[test]
+    fn resupply_in_a_calm_sea() {
+        let config = Config::parse(
+            "[rendezvous.Milch]\nkind = resupply\nx = 0\ny = 0\nopens = 0\ncloses = 1000\n\
+             transfer = 60\ntender = U-459\nsea_state = 3\nsignal = 0\ncompromise = 1",
+        )
+        .unwrap();
+        let mut world = World::new();
+        world.rendezvous = Rendezvous::read_all(&config).unwrap();
+        world.environment.sea_state = 5;
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 100.0, y: 0.0 });
+        let mut weapons = WeaponsStation::new(2, PresetLibrary::new());
+        weapons.tubes.tube_mut(1).unwrap().loaded = false;
+        boat.weapons = Some(weapons);
+        let boat = world.spawn(boat);
+        let tender = Entity::new("U-459", EntityKind::Submarine, Point { x: -100.0, y: 0.0 });
+        world.spawn(tender);
+        for _ in 0..100 {
+            world.step(1.0);
+        }
+        // too rough to pass anything across
+        assert_eq!(world.rendezvous[0].state, RendezvousState::Waiting);
+        assert!(world.rendezvous[0].compromised);
+        world.environment.sea_state = 3;
+        for _ in 0..100 {
+            world.step(1.0);
+        }
+        assert_eq!(world.rendezvous[0].state, RendezvousState::Completed);
+        let weapons = world.entity(boat).unwrap().weapons.as_ref().unwrap();
+        assert!(weapons.tubes.tubes.iter().all(|t| t.loaded));
+        assert_eq!(
+            outcomes(&world),
+            vec![
+                &Event::RendezvousCompromised {
+                    name: "Milch".to_string()
+                },
+                &Event::Resupplied {
+                    entity: boat,
+                    name: "Milch".to_string()
+                }
+            ]
+        );
+
+        let bad = Config::parse(
+            "[rendezvous.X]\nkind = resupply\nx = 0\ny = 0\nopens = 0\ncloses = 1\ntransfer = 1",
+        )
+        .unwrap();
+        assert!(Rendezvous::read_all(&bad).is_err());
+    }
+
+    #[test]
+    fn diving_interrupts_the_transfer() {
+        let (mut world, boat) = world();
+        for _ in 0..20 {
+            world.step(1.0);
+        }
+        world.entity_mut(boat).unwrap().depth = 50.0;
+        world.step(1.0);
+        assert_eq!(world.rendezvous[0].state, RendezvousState::Waiting);
+        for _ in 0..100 {
+            world.step(1.0);
+        }
+        assert_eq!(world.rendezvous[0].state, RendezvousState::Failed);
+        assert_eq!(
+            outcomes(&world),
+            vec![
+                &Event::TransferInterrupted {
+                    entity: boat,
+                    name: "Pilot".to_string()
+                },
+                &Event::MissionFailed {
+                    name: "Pilot".to_string()
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::environment::Environment;
+use crate::world::Entity;
+
+// #############################
+// #       REVERBERATION       #
+// #############################
+
+// A pulse does not come back from the target alone: the sea surface, and
+// in charted water the bottom, scatter it back all along its way, and the
+// echo of a boat must stand out of what comes back with it from the same
+// range. The surface scatters the more the sea is up; the bottom scatters
+// little off mud, which swallows the pulse, and much off rock (see
+// seafloor.rs). The patch that sends back its clutter along with the
+// echo grows with the range, the length of the pulse and the width of the
+// beam, while the echo does not: in shallow water reverberation rather
+// than noise blinds a sonar past some range, however loud its pulse.
+//
+// A boat lying on the bottom, or creeping along it, is lost in the bottom
+// returns at its own range, and hard to find with a pulse at all.
+
+/// Meters per second of the speed of sound the patch is worked out with
+pub const SOUND_SPEED: f32 = 1500.0;
+/// Seconds of the pulse of a hull sonar, unless set otherwise (see
+/// pulse.rs)
+pub const PULSE_LENGTH: f32 = 0.1;
+/// Meters of water above the bottom within which a boat is lost in its
+/// returns
+const BOTTOM_BAND: f32 = 5.0;
+/// dB the bottom returns around a boat lying on it add
+const BOTTOMED_CLUTTER: f32 = 12.0;
+/// Meters of water beyond which the bottom scatters less and less
+const SHALLOW: f32 = 100.0;
+
+/// dB per square meter the sea surface scatters back in `sea_state`
+fn surface_scattering(sea_state: u8) -> f32 {
+    -64.0 + 3.0 * sea_state as f32
+}
+
+/// Whether `entity` is within the bottom returns, in charted water
+pub fn on_the_bottom(environment: &Environment, entity: &Entity) -> bool {
+    environment
+        .seafloor
+        .at(&entity.position)
+        .depth
+        .is_some_and(|water| {
+            entity.depth > 0.0 && entity.depth + entity.draft() + BOTTOM_BAND >= water
+        })
+}
+
+/// dB of the clutter coming back with the echo of `target` at `range`,
+/// from a pulse reaching it at `incident` dB (what it sends back before its
+/// target strength), `pulse_length` seconds long in a beam `beam_width`
+/// radians wide
+pub fn level(
+    environment: &Environment,
+    target: &Entity,
+    range: f32,
+    incident: f32,
+    pulse_length: f32,
+    beam_width: f32,
+) -> f32 {
+    let patch = SOUND_SPEED * pulse_length / 2.0 * range.max(1.0) * beam_width;
+    let mut scattering = 10f32.powf(surface_scattering(environment.sea_state) / 10.0);
+    let cell = environment.seafloor.at(&target.position);
+    if let Some(water) = cell.depth {
+        let mut bottom = cell.bottom.scattering() - 10.0 * (water / SHALLOW).max(1.0).log10();
+        if on_the_bottom(environment, target) {
+            bottom += BOTTOMED_CLUTTER;
+        }
+        scattering += 10f32.powf(bottom / 10.0);
+    }
+    incident + 10.0 * (scattering * patch).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::seafloor::{Bottom, Seafloor};
+    use crate::world::EntityKind;
+    use crate::zone::{Zone, ZoneKind};
+
+    fn shallows(bottom: Bottom) -> Environment {
+        let zone = Zone {
+            name: "shallows".to_string(),
+            kind: ZoneKind::Shallow,
+            points: vec![
+                Point {
+                    x: -20_000.0,
+                    y: -20_000.0,
+                },
+                Point {
+                    x: 20_000.0,
+                    y: -20_000.0,
+                },
+                Point {
+                    x: 20_000.0,
+                    y: 20_000.0,
+                },
+                Point {
+                    x: -20_000.0,
+                    y: 20_000.0,
+                },
+            ],
+            depth: Some(60.0),
+            bottom: Some(bottom),
+        };
+        Environment {
+            seafloor: Seafloor::chart(&[zone], Bottom::Sand),
+            ..Environment::default()
+        }
+    }
+
+    fn boat(depth: f32) -> Entity {
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 3000.0, y: 0.0 });
+        boat.depth = depth;
+        boat
+    }
+
+    #[test]
+    fn clutter_grows_with_range_sea_and_bottom() {
+        let at = |environment: &Environment, range: f32| {
+            level(environment, &boat(30.0), range, 0.0, PULSE_LENGTH, 0.14)
+        };
+        let deep = Environment::default();
+        assert!(at(&deep, 6000.0) > at(&deep, 3000.0));
+        let gale = Environment {
+            sea_state: 7,
+            ..Environment::default()
+        };
+        assert!(at(&gale, 3000.0) > at(&deep, 3000.0));
+        let mud = at(&shallows(Bottom::Mud), 3000.0);
+        let rock = at(&shallows(Bottom::Rock), 3000.0);
+        assert!(mud > at(&deep, 3000.0));
+        assert!(rock > mud + 10.0);
+        let longer = level(&deep, &boat(30.0), 3000.0, 0.0, 4.0 * PULSE_LENGTH, 0.14);
+        assert!((longer - at(&deep, 3000.0) - 6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn lost_on_the_bottom() {
+        let sand = shallows(Bottom::Sand);
+        assert!(!on_the_bottom(&sand, &boat(30.0)));
+        assert!(on_the_bottom(&sand, &boat(50.0)));
+        assert!(!on_the_bottom(&Environment::default(), &boat(50.0)));
+        let hovering = level(&sand, &boat(30.0), 3000.0, 0.0, PULSE_LENGTH, 0.14);
+        let bottomed = level(&sand, &boat(50.0), 3000.0, 0.0, PULSE_LENGTH, 0.14);
+        assert!(bottomed > hovering + 10.0);
+    }
+}
@@ -0,0 +1,268 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::physics::Point;
+
+// #############################
+// #      ROUTE PLANNING       #
+// #############################
+
+// Theta* over a grid of cells laid on the area between the two ends: like
+// A*, but a cell keeps the parent of its neighbor whenever it can see it,
+// so routes come out as a few straight legs instead of grid staircases.
+// What a vessel may not cross is given as a predicate on positions (zones
+// it must keep out of, see World::forbidden).
+
+/// Most cells along one side of the grid; longer routes use larger cells
+const MAX_CELLS: f32 = 200.0;
+/// Cells of sea around the two ends, to go around obstacles
+const MARGIN_CELLS: f32 = 20.0;
+
+/// Whether the straight leg from `a` to `b` crosses nothing blocked,
+/// sampled every `step` meters
+pub fn is_clear<F: Fn(&Point) -> bool>(a: &Point, b: &Point, step: f32, blocked: &F) -> bool {
+    let samples = (a.distance_to(b) / step).ceil().max(1.0) as usize;
+    (0..=samples).all(|i| {
+        let t = i as f32 / samples as f32;
+        !blocked(&Point {
+            x: a.x + t * (b.x - a.x),
+            y: a.y + t * (b.y - a.y),
+        })
+    })
+}
+
+/// Entry of the open list, cheapest estimate first
+struct Open {
+    estimate: f32,
+    node: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+struct Grid {
+    min: Point,
+    cell: f32,
+    columns: usize,
+    rows: usize,
+}
+
+impl Grid {
+    fn around(from: &Point, to: &Point, cell: f32) -> Grid {
+        let span = (from.x - to.x).abs().max((from.y - to.y).abs());
+        let cell = cell.max(span / (MAX_CELLS - 2.0 * MARGIN_CELLS));
+        let margin = MARGIN_CELLS * cell;
+        let min = Point {
+            x: from.x.min(to.x) - margin,
+            y: from.y.min(to.y) - margin,
+        };
+        let columns = (((from.x - to.x).abs() + 2.0 * margin) / cell).ceil() as usize + 1;
+        let rows = (((from.y - to.y).abs() + 2.0 * margin) / cell).ceil() as usize + 1;
+        Grid {
+            min,
+            cell,
+            columns,
+            rows,
+        }
+    }
+
+    fn node(&self, p: &Point) -> usize {
+        let column = ((p.x - self.min.x) / self.cell) as usize;
+        let row = ((p.y - self.min.y) / self.cell) as usize;
+        row.min(self.rows - 1) * self.columns + column.min(self.columns - 1)
+    }
+
+    fn center(&self, node: usize) -> Point {
+        Point {
+            x: self.min.x + ((node % self.columns) as f32 + 0.5) * self.cell,
+            y: self.min.y + ((node / self.columns) as f32 + 0.5) * self.cell,
+        }
+    }
+
+    fn neighbors(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        let column = (node % self.columns) as isize;
+        let row = (node / self.columns) as isize;
+        (-1..=1)
+            .flat_map(move |dr| (-1..=1).map(move |dc| (row + dr, column + dc)))
+            .filter(move |&(r, c)| {
+                (r, c) != (row, column)
+                    && r >= 0
+                    && c >= 0
+                    && (r as usize) < self.rows
+                    && (c as usize) < self.columns
+            })
+            .map(move |(r, c)| r as usize * self.columns + c as usize)
+    }
+}
+
+/// Waypoints from `from` to `to` (excluding `from`) that keep out of
+/// what is `blocked`, planned on cells of at least `cell` meters. None when
+/// `to` cannot be reached
+pub fn plan<F: Fn(&Point) -> bool>(
+    from: &Point,
+    to: &Point,
+    cell: f32,
+    blocked: F,
+) -> Option<Vec<Point>> {
+    if blocked(to) {
+        return None;
+    }
+    let step = cell / 4.0;
+    if is_clear(from, to, step, &blocked) {
+        return Some(vec![to.clone()]);
+    }
+    let grid = Grid::around(from, to, cell);
+    let step = grid.cell / 4.0;
+    let start = grid.node(from);
+    let goal = grid.node(to);
+    let position = |node: usize| {
+        if node == start {
+            from.clone()
+        } else if node == goal {
+            to.clone()
+        } else {
+            grid.center(node)
+        }
+    };
+
+    let size = grid.columns * grid.rows;
+    let mut cost = vec![f32::INFINITY; size];
+    let mut parent = vec![usize::MAX; size];
+    let mut closed = vec![false; size];
+    let mut open = BinaryHeap::new();
+    cost[start] = 0.0;
+    parent[start] = start;
+    open.push(Open {
+        estimate: from.distance_to(to),
+        node: start,
+    });
+    while let Some(Open { node, .. }) = open.pop() {
+        if node == goal {
+            break;
+        }
+        if closed[node] {
+            continue;
+        }
+        closed[node] = true;
+        let here = position(node);
+        let up = parent[node];
+        let above = position(up);
+        for next in grid.neighbors(node) {
+            if closed[next] {
+                continue;
+            }
+            let there = position(next);
+            if next != goal && blocked(&there) {
+                continue;
+            }
+            // any-angle shortcut through the parent, else a plain grid step
+            let (from_node, from_position) = if is_clear(&above, &there, step, &blocked) {
+                (up, &above)
+            } else if is_clear(&here, &there, step, &blocked) {
+                (node, &here)
+            } else {
+                continue;
+            };
+            let candidate = cost[from_node] + from_position.distance_to(&there);
+            if candidate < cost[next] {
+                cost[next] = candidate;
+                parent[next] = from_node;
+                open.push(Open {
+                    estimate: candidate + there.distance_to(to),
+                    node: next,
+                });
+            }
+        }
+    }
+    if parent[goal] == usize::MAX {
+        return None;
+    }
+    let mut route = Vec::new();
+    let mut node = goal;
+    while node != start {
+        route.push(position(node));
+        node = parent[node];
+    }
+    route.reverse();
+    Some(route)
+}
+
+/// Length in meters of a route starting at `from`
+pub fn length(from: &Point, route: &[Point]) -> f32 {
+    let mut previous = from;
+    let mut total = 0.0;
+    for point in route {
+        total += previous.distance_to(point);
+        previous = point;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A wall along x = 0 from y = -2000 to y = 2000
+    fn wall(p: &Point) -> bool {
+        p.x.abs() < 100.0 && p.y.abs() < 2000.0
+    }
+
+    #[test]
+    fn straight_when_clear() {
+        let from = Point {
+            x: -5000.0,
+            y: 3000.0,
+        };
+        let to = Point {
+            x: 5000.0,
+            y: 3000.0,
+        };
+        assert_eq!(plan(&from, &to, 100.0, wall), Some(vec![to]));
+    }
+
+    #[test]
+    fn around_a_wall() {
+        let from = Point { x: -3000.0, y: 0.0 };
+        let to = Point { x: 3000.0, y: 0.0 };
+        let route = plan(&from, &to, 100.0, wall).unwrap();
+        assert!(route.len() >= 2);
+        assert_eq!(route.last(), Some(&to));
+        let mut previous = &from;
+        for point in &route {
+            assert!(is_clear(previous, point, 25.0, &wall));
+            previous = point;
+        }
+        // going round the end of the wall, not a grid staircase
+        let shortest = 2.0 * (3000.0f32.powi(2) + 2100.0f32.powi(2)).sqrt();
+        assert!(length(&from, &route) < shortest * 1.1);
+    }
+
+    #[test]
+    fn unreachable() {
+        let from = Point { x: 0.0, y: 0.0 };
+        let to = Point { x: 50.0, y: 500.0 };
+        assert_eq!(plan(&from, &to, 100.0, wall), None);
+        let ring = |p: &Point| {
+            let r = p.distance_to(&Point { x: 0.0, y: 0.0 });
+            r > 400.0 && r < 600.0
+        };
+        assert_eq!(plan(&from, &Point { x: 0.0, y: 1000.0 }, 100.0, ring), None);
+    }
+}
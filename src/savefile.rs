@@ -0,0 +1,359 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{Config, ConfigError};
+use crate::messages::Catalog;
+use crate::physics::game_to_user_angle;
+use crate::simulation::Simulation;
+use crate::units::{Knots, MetersPerSecond};
+
+// #############################
+// #        SAVE GAMES         #
+// #############################
+
+// A save is the scenario as it stands at the moment it is taken: the
+// scenario file the game started from, each entity moved to where it is
+// now with its course, speed, hull and the wear of its machinery, the sunk
+// ones left out, and the chart as plotted. Torpedoes in the water and the
+// contacts held are not kept. It is written packed, unlike a scenario:
+//
+// magic     "SUBSAVE"
+// format    one byte, FORMAT
+// engine    one byte of length, then the version of the game that wrote it
+// length    four bytes, of the text unpacked
+// checksum  four bytes, CRC-32 of the text unpacked
+// body      the text, LZSS compressed
+//
+// so that a save written by another version of the game, cut short or
+// damaged is refused with an error saying so, rather than read as a
+// scenario that is not the one saved. Compression is done here, without
+// a dependency on zstd. From the command line:
+//
+// subsim save <scenario> <seconds> <save>     run, then save
+// subsim run <save> <seconds>                 go on from a save
+
+const MAGIC: &[u8] = b"SUBSAVE";
+/// Layout of the packed file, raised whenever it changes
+const FORMAT: u8 = 1;
+/// Version of the game writing and reading saves
+pub const ENGINE: &str = env!("CARGO_PKG_VERSION");
+
+/// Bytes back a match may start at
+const WINDOW: usize = 4096;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 15;
+/// Earlier positions looked at for a match
+const MAX_CHAIN: usize = 64;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SaveError {
+    /// Not a save at all
+    NotASave,
+    /// Written in another layout, or by another version of the game
+    Incompatible { version: String },
+    /// Ends before all of it was read
+    Truncated,
+    /// Does not unpack to what was saved
+    Corrupted,
+    /// The save could not be read or written, with why
+    File(String),
+}
+
+impl SaveError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            SaveError::NotASave => messages.get("error-not-a-save").to_string(),
+            SaveError::Incompatible { version } => messages.format(
+                "error-save-version",
+                &[("version", version), ("engine", &ENGINE)],
+            ),
+            SaveError::Truncated => messages.get("error-save-truncated").to_string(),
+            SaveError::Corrupted => messages.get("error-save-corrupted").to_string(),
+            SaveError::File(error) => messages.format("error-save-file", &[("error", error)]),
+        }
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<ConfigError> for SaveError {
+    fn from(e: ConfigError) -> Self {
+        SaveError::File(e.to_string())
+    }
+}
+
+/// CRC-32 (IEEE), bit by bit
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn hash(data: &[u8], at: usize) -> usize {
+    let key = (data[at] as usize) << 16 | (data[at + 1] as usize) << 8 | data[at + 2] as usize;
+    key.wrapping_mul(2_654_435_761) >> 20 & 0xFFF
+}
+
+/// LZSS: a flag byte before every eight items, a set bit for a literal
+/// byte and a clear one for a match of two bytes, 12 bits of distance back
+/// and 4 of length
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2);
+    let mut head = vec![usize::MAX; 4096];
+    let mut previous = vec![usize::MAX; data.len()];
+    let mut flags_at = 0;
+    let mut items = 8;
+    let mut i = 0;
+    while i < data.len() {
+        if items == 8 {
+            flags_at = out.len();
+            out.push(0);
+            items = 0;
+        }
+        let mut best = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(data, i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let longest = (data.len() - i).min(MAX_MATCH);
+                let length = (0..longest)
+                    .take_while(|k| data[candidate + k] == data[i + k])
+                    .count();
+                if length > best.1 {
+                    best = (i - candidate, length);
+                }
+                candidate = previous[candidate];
+                chain += 1;
+            }
+        }
+        let step = if best.1 >= MIN_MATCH {
+            let code = ((best.0 - 1) << 4 | (best.1 - MIN_MATCH)) as u16;
+            out.extend_from_slice(&code.to_le_bytes());
+            best.1
+        } else {
+            out[flags_at] |= 1 << items;
+            out.push(data[i]);
+            1
+        };
+        for (at, link) in previous.iter_mut().enumerate().skip(i).take(step) {
+            if at + MIN_MATCH <= data.len() {
+                let h = hash(data, at);
+                *link = head[h];
+                head[h] = at;
+            }
+        }
+        i += step;
+        items += 1;
+    }
+    out
+}
+
+fn decompress(data: &[u8], length: usize) -> Result<Vec<u8>, SaveError> {
+    // no more than a longest match for every two bytes, whatever the header
+    // claims
+    if length > data.len() / 2 * MAX_MATCH {
+        return Err(SaveError::Corrupted);
+    }
+    let mut out = Vec::with_capacity(length);
+    let mut bytes = data.iter();
+    while out.len() < length {
+        let flags = *bytes.next().ok_or(SaveError::Truncated)?;
+        for item in 0..8 {
+            if out.len() >= length {
+                break;
+            }
+            let byte = *bytes.next().ok_or(SaveError::Truncated)?;
+            if flags & 1 << item != 0 {
+                out.push(byte);
+                continue;
+            }
+            let code = u16::from_le_bytes([byte, *bytes.next().ok_or(SaveError::Truncated)?]);
+            let distance = (code >> 4) as usize + 1;
+            let run = (code & 0xF) as usize + MIN_MATCH;
+            if distance > out.len() {
+                return Err(SaveError::Corrupted);
+            }
+            for _ in 0..run {
+                out.push(out[out.len() - distance]);
+            }
+        }
+    }
+    if bytes.next().is_some() || out.len() != length {
+        return Err(SaveError::Corrupted);
+    }
+    Ok(out)
+}
+
+/// Whether `data` starts as a save does
+pub fn is_save(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Packs `text` as a save of this version of the game
+pub fn pack(text: &str) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(FORMAT);
+    out.push(ENGINE.len() as u8);
+    out.extend_from_slice(ENGINE.as_bytes());
+    out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc32(text.as_bytes()).to_le_bytes());
+    out.extend(compress(text.as_bytes()));
+    out
+}
+
+/// Reads `count` bytes from `data` at `at`, moving past them
+fn take<'a>(data: &'a [u8], at: &mut usize, count: usize) -> Result<&'a [u8], SaveError> {
+    let bytes = data.get(*at..*at + count).ok_or(SaveError::Truncated)?;
+    *at += count;
+    Ok(bytes)
+}
+
+fn word(data: &[u8], at: &mut usize) -> Result<u32, SaveError> {
+    let bytes = take(data, at, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The text a save was packed from, checked against its version and
+/// checksum
+pub fn unpack(data: &[u8]) -> Result<String, SaveError> {
+    if !is_save(data) {
+        return Err(SaveError::NotASave);
+    }
+    let mut at = MAGIC.len();
+    let format = take(data, &mut at, 1)?[0];
+    let size = take(data, &mut at, 1)?[0] as usize;
+    let version = String::from_utf8_lossy(take(data, &mut at, size)?).into_owned();
+    if format != FORMAT || version != ENGINE {
+        return Err(SaveError::Incompatible { version });
+    }
+    let length = word(data, &mut at)? as usize;
+    let checksum = word(data, &mut at)?;
+    let text = decompress(&data[at..], length)?;
+    if crc32(&text) != checksum {
+        return Err(SaveError::Corrupted);
+    }
+    String::from_utf8(text).map_err(|_| SaveError::Corrupted)
+}
+
+/// The scenario `scenario` as it stands in `sim`
+pub fn capture(scenario: &Config, sim: &Simulation) -> Config {
+    let mut config = scenario.clone();
+    let names: Vec<String> = config
+        .sections_with_prefix("entity")
+        .map(|(name, _)| name.to_string())
+        .collect();
+    for name in names {
+        let section = format!("entity.{}", name);
+        let entity = match sim.world.entities.by_name(&name) {
+            Some(entity) if !entity.is_destroyed() => entity,
+            _ => {
+                config.remove_section(&section);
+                continue;
+            }
+        };
+        let section = config.section_mut(&section);
+        section.set("x", entity.position.x);
+        section.set("y", entity.position.y);
+        section.set("depth", entity.depth);
+        section.set("heading", game_to_user_angle(entity.heading));
+        section.set("speed", Knots::from(MetersPerSecond(entity.speed)).0);
+        section.set("hull", entity.hull);
+        if let Some(machinery) = &entity.machinery {
+            section.set("running_hours", machinery.running_hours);
+            section.set("shock", machinery.shock);
+        }
+    }
+    config.remove_section("chart");
+    sim.chart.write(&mut config);
+    config
+}
+
+pub fn save<P: AsRef<Path>>(path: P, config: &Config) -> Result<(), SaveError> {
+    fs::write(path, pack(&config.to_string())).map_err(|e| SaveError::File(e.to_string()))
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, SaveError> {
+    let data = fs::read(path).map_err(|e| SaveError::File(e.to_string()))?;
+    Ok(Config::parse(&unpack(&data)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "
+[scenario]
+name = Convoy HX 112
+player = U-99
+
+[entity.U-99]
+class = type_viic
+x = 0
+y = 0
+
+[entity.Scottish Maiden]
+class = tanker
+x = 4000
+y = 1200
+";
+
+    #[test]
+    fn packs_and_unpacks() {
+        let text = TEXT.repeat(20);
+        let packed = pack(&text);
+        assert!(is_save(&packed));
+        assert!(packed.len() < text.len() / 4, "{}", packed.len());
+        assert_eq!(unpack(&packed), Ok(text));
+        assert_eq!(unpack(&pack("")), Ok(String::new()));
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn refuses_damaged_saves() {
+        let packed = pack(TEXT);
+        assert_eq!(unpack(TEXT.as_bytes()), Err(SaveError::NotASave));
+        assert_eq!(
+            unpack(&packed[..packed.len() - 5]),
+            Err(SaveError::Truncated)
+        );
+        for at in MAGIC.len() + 2 + ENGINE.len()..packed.len() {
+            let mut damaged = packed.clone();
+            damaged[at] ^= 0x10;
+            assert!(unpack(&damaged).is_err(), "byte {}", at);
+        }
+        // a length no packed text expands to
+        let mut bloated = packed.clone();
+        let length = MAGIC.len() + 2 + ENGINE.len();
+        bloated[length..length + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(unpack(&bloated), Err(SaveError::Corrupted));
+    }
+
+    #[test]
+    fn refuses_other_versions() {
+        let mut packed = pack(TEXT);
+        packed[MAGIC.len() + 2] = b'9';
+        let error = unpack(&packed).unwrap_err();
+        assert!(matches!(error, SaveError::Incompatible { .. }));
+        assert!(error.to_string().contains(ENGINE));
+        packed = pack(TEXT);
+        packed[MAGIC.len()] = FORMAT + 1;
+        assert!(matches!(
+            unpack(&packed),
+            Err(SaveError::Incompatible { .. })
+        ));
+    }
+}
@@ -0,0 +1,909 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::ai::behavior::Behaviors;
+use crate::ai::SubmarineAi;
+use crate::chart::{Chart, MarkShape};
+use crate::coastline::Coastline;
+use crate::config::{Config, ConfigError, Section};
+use crate::contacts::Aging;
+use crate::crew::{CrewQuality, Difficulty};
+use crate::environment::{Environment, SoundSpeedProfile, Tide, TimeOfDay};
+use crate::era::{Era, Subsystem};
+use crate::escort::EscortAi;
+use crate::faction::Diplomacy;
+use crate::geo::LatLon;
+use crate::hazards::Hazards;
+use crate::helm::Helm;
+use crate::hfdf::DirectionFinding;
+use crate::history::{History, Retention};
+use crate::identification::Confusion;
+use crate::messages::Catalog;
+use crate::navigation::{self, Navigation};
+use crate::physics::{user_to_game_angle, Point};
+use crate::propagation::LossCache;
+use crate::radar::RadarGeneration;
+use crate::reliability::{Realism, Reliability};
+use crate::rendezvous::Rendezvous;
+use crate::seafloor::{Bottom, Seafloor};
+use crate::shadowing::Shadow;
+use crate::signals::{Inbox, Signal, SignalState};
+use crate::simulation::Simulation;
+use crate::theater::Theater;
+use crate::tracking::Tracker;
+use crate::traffic::{Departure, Sailing};
+use crate::tuning::Tuning;
+use crate::tutorial::Tutorial;
+use crate::units::Meters;
+use crate::units::{Knots, MetersPerSecond};
+use crate::vessel::VesselClass;
+use crate::weather::{Preset, ProfileKind, WeatherChange};
+use crate::wolfpack::Wolfpack;
+use crate::world::{EntityKind, World};
+use crate::zone::Zone;
+
+// A scenario file holds the vessel classes it uses ("[class.<name>]", see
+// vessel.rs) and one "[entity.<name>]" section per ship:
+//
+// [scenario]
+// name = Convoy HX-72
+// era = wwii              # or a year
+// player = U-99
+// helms = U-552           # optional, other vessels the player may command,
+//                         # see helm.rs
+// weather = calm          # optional preset the keys below override, see
+//                         # weather.rs
+// sea_state = 3
+// wave_from = 270         # degrees, where wind and waves come from
+// visibility = 15000      # meters
+// ice_cover = 0           # fraction of the surface, 0 to 1
+// realism = historical    # torpedo failures: perfect, reduced or historical
+// difficulty = normal     # easy, normal, hard or expert computer crews
+// origin = 56.0, -18.5    # lat, lon of x = 0, y = 0 (see geo.rs)
+// coastline = gshhs_i.b   # optional GSHHG shorelines, see coastline.rs
+// tide_range = 4          # meters between low and high water
+// high_water = 3600       # seconds into the scenario of a high water
+// date = 1941-05-20       # local date and time the scenario starts at
+// start_time = 06:30
+// sound_profile = layer   # optional, see weather.rs
+// bottom = mud            # optional, of the open sea, see seafloor.rs
+//
+// [sound_speed]           # optional, depth (m) = sound speed (m/s)
+// 0 = 1500
+// 80 = 1503
+// 400 = 1485
+//
+// [reliability]           # optional overrides, see reliability.rs
+// dud = 0.2
+//
+// [tuning]                # optional overrides, see tuning.rs
+// absorption = 0.0008
+//
+// [entity.U-99]
+// class = type_viic
+// x = 0                   # meters east
+// y = 0                   # meters north
+// depth = 0               # meters
+// heading = 90            # degrees, user angle
+// speed = 5               # knots
+// crew = veteran          # optional: green, trained, veteran or elite
+// morale = 0.7            # optional, from 0 to 1, see morale.rs
+// side = axis             # optional, see registry.rs
+// tags = wolfpack         # optional, comma separated
+// hull = 0.6              # optional, from 1 (intact) down, see savefile.rs
+// running_hours = 400     # optional, wear and shock of the machinery
+// shock = 0.2             # since its last overhaul, see machinery.rs
+// waypoint_x = 12000      # optional, meters east and north the AI
+// waypoint_y = 4000       # sprints and drifts to, see autopilot.rs
+// tracker = kalman        # optional, how the AI fire control tracks, see
+//                         # tracking.rs
+//
+// Submarines other than the player's that carry torpedoes are driven by the
+// submarine AI (see ai.rs), patrolling along their initial heading and depth.
+// The player may command an escort instead, and hunt them with its sonar
+// and weapons, see asw.rs.
+// "[tree.<role>]" sections replace the behavior tree of a role, see
+// ai/behavior.rs, "[tutorial.<step>]" sections make a training scenario,
+// see tutorial.rs, "[zone.<name>]" sections mark areas of the map, see
+// zone.rs, "[relations]" and "[declaration.<name>]" sections set how the
+// sides stand, see faction.rs, a "[chart]" section holds marks already
+// plotted, see chart.rs, a "[history]" section sets how much of the
+// tracks is kept, see history.rs, a "[contacts]" section how long
+// contacts no longer held are kept, see contacts.rs, and a "[messages]"
+// section holds the mission text, see messages.rs. "[sailing.<name>]" sections schedule
+// ships leaving port during the scenario, see traffic.rs, and an "[hfdf]"
+// section places the shore stations that fix radio traffic, see hfdf.rs,
+// "[signal.<name>]" sections send orders and intelligence during the
+// mission, see signals.rs, and a "[hazards]" section sets mines and
+// wreckage adrift, see hazards.rs. "[rendezvous.<name>]" sections set
+// the pickups and insertions of a special operation and the resupplies
+// at sea, see rendezvous.rs, their points radioed as signals, and
+// "[shadow.<name>]" sections the vessels to trail unseen, see shadowing.rs.
+// A "[theater]" section sends hunter-killer groups where ships are lost,
+// see theater.rs, and a "[wolfpack]" section lists the boats hunting with
+// the player, see wolfpack.rs. "[weather.<name>]" sections script the
+// weather changing during the mission, see weather.rs.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Placement {
+    pub name: String,
+    pub class: String,
+    pub position: Point,
+    pub depth: f32,
+    /// User angle, degrees
+    pub heading: f32,
+    pub speed: Knots,
+    /// None to leave it to the difficulty
+    pub crew: Option<CrewQuality>,
+    pub morale: Option<f32>,
+    pub side: Option<String>,
+    pub tags: Vec<String>,
+    /// Where the AI makes for before patrolling
+    pub waypoint: Option<Point>,
+    /// How the AI fire control tracks its contacts
+    pub tracker: Tracker,
+    /// Hull integrity it starts with, 1 when intact
+    pub hull: f32,
+    /// Hours the machinery has run since its last overhaul
+    pub running_hours: f32,
+    /// Shock the machinery has taken since
+    pub shock: f32,
+}
+
+impl Placement {
+    fn read(name: &str, section: &Section) -> Result<Placement, ConfigError> {
+        Ok(Placement {
+            name: name.to_string(),
+            class: section.parse("class")?,
+            position: Point {
+                x: section.parse("x")?,
+                y: section.parse("y")?,
+            },
+            depth: section.parse_or("depth", 0.0)?,
+            heading: section.parse_or("heading", 0.0)?,
+            speed: Knots(section.parse_or("speed", 0.0)?),
+            crew: section.parse_optional("crew")?,
+            morale: section.parse_optional("morale")?,
+            side: section.get("side").map(|s| s.to_string()),
+            tags: section
+                .get("tags")
+                .map(|t| t.split(',').map(|t| t.trim().to_string()).collect())
+                .unwrap_or_default(),
+            waypoint: match (
+                section.parse_optional("waypoint_x")?,
+                section.parse_optional("waypoint_y")?,
+            ) {
+                (Some(x), Some(y)) => Some(Point { x, y }),
+                _ => None,
+            },
+            tracker: section.parse_or("tracker", Tracker::default())?,
+            hull: section.parse_or("hull", 1.0)?,
+            running_hours: section.parse_or("running_hours", 0.0)?,
+            shock: section.parse_or("shock", 0.0)?,
+        })
+    }
+}
+
+/// Problems that make a scenario unplayable
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScenarioIssue {
+    UnknownClass {
+        entity: String,
+        class: String,
+    },
+    NotInEra {
+        class: String,
+        subsystem: Subsystem,
+    },
+    NoPlayer,
+    UnknownPlayer(String),
+    /// A resupply from a supply ship not placed
+    UnknownTender {
+        rendezvous: String,
+        tender: String,
+    },
+    /// A shadowing mission of a vessel not placed
+    UnknownShadowed {
+        shadow: String,
+        vessel: String,
+    },
+}
+
+impl fmt::Display for ScenarioIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioIssue::UnknownClass { entity, class } => {
+                write!(f, "entity '{}' uses unknown class '{}'", entity, class)
+            }
+            ScenarioIssue::NotInEra { class, subsystem } => {
+                write!(
+                    f,
+                    "class '{}' carries a {} not available in this era",
+                    class, subsystem
+                )
+            }
+            ScenarioIssue::NoPlayer => write!(f, "no player vessel"),
+            ScenarioIssue::UnknownPlayer(name) => write!(f, "player vessel '{}' not placed", name),
+            ScenarioIssue::UnknownTender { rendezvous, tender } => write!(
+                f,
+                "rendezvous '{}' with supply ship '{}' not placed",
+                rendezvous, tender
+            ),
+            ScenarioIssue::UnknownShadowed { shadow, vessel } => write!(
+                f,
+                "shadowing '{}' of vessel '{}' not placed",
+                shadow, vessel
+            ),
+        }
+    }
+}
+
+/// Reads "depth = speed" pairs, in meters and meters per second
+fn read_sound_speed(section: &Section) -> Result<SoundSpeedProfile, ConfigError> {
+    let mut points: Vec<(f32, f32)> = Vec::new();
+    for (depth, _) in section.entries() {
+        let invalid = || ConfigError::Invalid {
+            section: section.name.clone(),
+            key: depth.to_string(),
+            value: depth.to_string(),
+        };
+        let parsed = depth.parse::<f32>().map_err(|_| invalid())?;
+        // each depth once, for the speed between two of them
+        if !parsed.is_finite() || points.iter().any(|(d, _)| *d == parsed) {
+            return Err(invalid());
+        }
+        points.push((parsed, section.parse(depth)?));
+    }
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(SoundSpeedProfile { points })
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub era: Era,
+    pub player: Option<String>,
+    /// The other vessels the player may command
+    pub helms: Vec<String>,
+    pub environment: Environment,
+    /// Torpedo reliability for every boat in the scenario
+    pub reliability: Reliability,
+    /// AI behavior trees by role
+    pub behaviors: Behaviors,
+    pub difficulty: Difficulty,
+    /// Where the local x/y plane is anchored on the globe
+    pub origin: LatLon,
+    pub tutorial: Option<Tutorial>,
+    /// Mission text and translations of the scenario
+    pub messages: Catalog,
+    pub zones: Vec<Zone>,
+    /// How the sides stand to each other
+    pub diplomacy: Diplomacy,
+    /// Classes taken for one another
+    pub confusion: Confusion,
+    /// Marks plotted before the mission starts
+    pub chart: Chart,
+    /// How much of the track and bearing histories is kept
+    pub retention: Retention,
+    /// How long contacts are kept without an observation
+    pub aging: Aging,
+    /// Constants the scenario changes
+    pub tuning: Tuning,
+    pub coastline: Coastline,
+    pub classes: Vec<VesselClass>,
+    pub placements: Vec<Placement>,
+    /// Ships leaving port during the scenario
+    pub sailings: Vec<Sailing>,
+    /// Shore stations fixing radio traffic
+    pub direction_finding: DirectionFinding,
+    /// Signals sent to the player during the mission
+    pub signals: Inbox,
+    /// Mines and wreckage adrift
+    pub hazards: Hazards,
+    /// Pickups and insertions to make
+    pub rendezvous: Vec<Rendezvous>,
+    /// Vessels to trail unseen
+    pub shadows: Vec<Shadow>,
+    /// Hunter-killer groups and air patrols of a side
+    pub theater: Theater,
+    /// The boats hunting with the player
+    pub wolfpack: Wolfpack,
+    /// Scripted changes of the weather
+    pub weather: Vec<WeatherChange>,
+}
+
+impl Scenario {
+    pub fn from_config(config: &Config) -> Result<Scenario, ConfigError> {
+        let header = config
+            .section("scenario")
+            .ok_or_else(|| ConfigError::Missing {
+                section: "scenario".to_string(),
+                key: "name".to_string(),
+            })?;
+        let defaults = match header.parse_optional::<Preset>("weather")? {
+            Some(preset) => preset.environment(),
+            None => Environment::default(),
+        };
+        let era = header.parse_or("era", Era::default())?;
+        let realism = header.parse_or("realism", Realism::Historical)?;
+        let mut reliability = Reliability::for_era(era).with_realism(realism);
+        if let Some(section) = config.section("reliability") {
+            reliability = reliability.read(section)?;
+        }
+        let mut scenario = Scenario {
+            name: header.parse("name")?,
+            era,
+            player: header.get("player").map(|p| p.to_string()),
+            helms: header
+                .get("helms")
+                .map(|h| {
+                    h.split(',')
+                        .map(|h| h.trim().to_string())
+                        .filter(|h| !h.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            environment: Environment {
+                sea_state: header.parse_or("sea_state", defaults.sea_state)?,
+                wave_from: match header.parse_optional::<f32>("wave_from")? {
+                    Some(degrees) => user_to_game_angle(degrees),
+                    None => defaults.wave_from,
+                },
+                visibility: header.parse_or("visibility", defaults.visibility)?,
+                ice_cover: header.parse_or("ice_cover", defaults.ice_cover)?,
+                tide: Tide {
+                    range: header.parse_or("tide_range", defaults.tide.range)?,
+                    high_water: header.parse_or("high_water", defaults.tide.high_water)?,
+                },
+                date: header.parse_or("date", defaults.date)?,
+                start_time: header
+                    .parse_or("start_time", TimeOfDay(defaults.start_time))?
+                    .0,
+                sound_speed: match header.parse_optional::<ProfileKind>("sound_profile")? {
+                    Some(kind) => kind.profile(),
+                    None => defaults.sound_speed,
+                },
+                seafloor: Seafloor::default(),
+                losses: LossCache::default(),
+                tuning: Tuning::default(),
+            },
+            reliability,
+            behaviors: Behaviors::default(),
+            difficulty: header.parse_or("difficulty", Difficulty::default())?,
+            origin: header.parse_or("origin", LatLon::default())?,
+            tutorial: Tutorial::read(config)?,
+            messages: config
+                .section("messages")
+                .map(Catalog::read)
+                .unwrap_or_default(),
+            zones: Vec::new(),
+            diplomacy: Diplomacy::read(config)?,
+            confusion: Confusion::read(config)?,
+            chart: Chart::read(config)?,
+            retention: Retention::read(config)?,
+            aging: Aging::read(config)?,
+            tuning: Tuning::read(config)?,
+            coastline: Coastline::default(),
+            classes: Vec::new(),
+            placements: Vec::new(),
+            sailings: Vec::new(),
+            direction_finding: DirectionFinding::read(config, era)?,
+            signals: Inbox::read(config)?,
+            hazards: Hazards::read(config)?,
+            rendezvous: Rendezvous::read_all(config)?,
+            shadows: Shadow::read_all(config)?,
+            theater: Theater::default(),
+            wolfpack: Wolfpack::read(config)?,
+            weather: Vec::new(),
+        };
+        if let Some(section) = config.section("sound_speed") {
+            scenario.environment.sound_speed = read_sound_speed(section)?;
+        }
+        if let Some(path) = header.get("coastline") {
+            scenario.coastline = Coastline::load(path, &scenario.origin)?;
+        }
+        scenario.behaviors.read(config)?;
+        for (name, section) in config.sections_with_prefix("class") {
+            scenario.classes.push(VesselClass::read(name, section)?);
+        }
+        for (name, section) in config.sections_with_prefix("zone") {
+            scenario.zones.push(Zone::read(name, section)?);
+        }
+        let open = header.parse_or("bottom", Bottom::default())?;
+        scenario.environment.seafloor = Seafloor::chart(&scenario.zones, open);
+        for (name, section) in config.sections_with_prefix("entity") {
+            scenario.placements.push(Placement::read(name, section)?);
+        }
+        scenario.sailings = Sailing::read_all(config, &scenario.zones)?;
+        scenario.theater = Theater::read(config, &scenario.zones)?;
+        scenario.weather = WeatherChange::read_all(config, scenario.environment.start_time)?;
+        Ok(scenario)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scenario, ConfigError> {
+        Scenario::from_config(&Config::load(path)?)
+    }
+
+    pub fn class(&self, name: &str) -> Option<&VesselClass> {
+        self.classes.iter().find(|c| c.name == name)
+    }
+
+    /// Lists everything that keeps the scenario from being played
+    pub fn validate(&self) -> Vec<ScenarioIssue> {
+        let mut issues = Vec::new();
+        for class in &self.classes {
+            for subsystem in class.era_violations(self.era) {
+                issues.push(ScenarioIssue::NotInEra {
+                    class: class.name.clone(),
+                    subsystem,
+                });
+            }
+        }
+        for placement in &self.placements {
+            if self.class(&placement.class).is_none() {
+                issues.push(ScenarioIssue::UnknownClass {
+                    entity: placement.name.clone(),
+                    class: placement.class.clone(),
+                });
+            }
+        }
+        for sailing in &self.sailings {
+            for class in &sailing.classes {
+                if self.class(class).is_none() {
+                    issues.push(ScenarioIssue::UnknownClass {
+                        entity: sailing.name.clone(),
+                        class: class.clone(),
+                    });
+                }
+            }
+        }
+        for class in &self.theater.classes {
+            if self.class(class).is_none() {
+                issues.push(ScenarioIssue::UnknownClass {
+                    entity: "theater".to_string(),
+                    class: class.clone(),
+                });
+            }
+        }
+        for rendezvous in &self.rendezvous {
+            if let Some(tender) = &rendezvous.tender {
+                if !self.placements.iter().any(|p| &p.name == tender) {
+                    issues.push(ScenarioIssue::UnknownTender {
+                        rendezvous: rendezvous.name.clone(),
+                        tender: tender.clone(),
+                    });
+                }
+            }
+        }
+        for shadow in &self.shadows {
+            for vessel in std::iter::once(&shadow.target).chain(&shadow.by) {
+                if !self.placements.iter().any(|p| &p.name == vessel) {
+                    issues.push(ScenarioIssue::UnknownShadowed {
+                        shadow: shadow.name.clone(),
+                        vessel: vessel.clone(),
+                    });
+                }
+            }
+        }
+        match &self.player {
+            None => issues.push(ScenarioIssue::NoPlayer),
+            Some(player) => {
+                if !self.placements.iter().any(|p| &p.name == player) {
+                    issues.push(ScenarioIssue::UnknownPlayer(player.clone()));
+                }
+            }
+        }
+        for helm in &self.helms {
+            if !self.placements.iter().any(|p| &p.name == helm) {
+                issues.push(ScenarioIssue::UnknownPlayer(helm.clone()));
+            }
+        }
+        issues
+    }
+
+    /// Creates the simulation, provided the scenario validates
+    pub fn build(&self) -> Result<Simulation, Vec<ScenarioIssue>> {
+        let issues = self.validate();
+        if !issues.is_empty() {
+            return Err(issues);
+        }
+        let mut world = World::new();
+        world.environment = self.environment.clone();
+        world.environment.tuning = self.tuning.clone();
+        world.behaviors = self.behaviors.clone();
+        world.zones = self.zones.clone();
+        world.diplomacy = self.diplomacy.clone();
+        world.confusion = self.confusion.clone();
+        world.coastline = self.coastline.clone();
+        world.hfdf = self.direction_finding.clone();
+        world.current = self.hazards.current.clone();
+        world.hazards = self.hazards.scatter(&mut world.rng);
+        world.rendezvous = self.rendezvous.clone();
+        world.shadows = self.shadows.clone();
+        for shadow in world.shadows.iter_mut() {
+            if shadow.by.is_none() {
+                shadow.by = self.player.clone();
+            }
+        }
+        world.weather = self.weather.clone();
+        let mut player = None;
+        let mut helms = Vec::new();
+        for placement in &self.placements {
+            let class = self.class(&placement.class).unwrap();
+            let mut entity = class.instantiate(&placement.name, placement.position.clone());
+            if class.radar_generation.is_none() {
+                entity.radar_generation = RadarGeneration::for_era(self.era);
+            }
+            entity.depth = placement.depth;
+            entity.heading = user_to_game_angle(placement.heading);
+            entity.speed = MetersPerSecond::from(placement.speed).0;
+            entity.hull = placement.hull.clamp(0.0, 1.0);
+            if let Some(machinery) = entity.machinery.as_mut() {
+                machinery.running_hours = placement.running_hours.max(0.0);
+                machinery.shock = placement.shock.max(0.0);
+            }
+            if let Some(station) = entity.weapons.as_mut() {
+                station.reliability = self.reliability.clone();
+            }
+            let at_helm = self.player.as_deref() == Some(placement.name.as_str());
+            let is_helm = !at_helm && self.helms.contains(&placement.name);
+            let is_player = at_helm || is_helm;
+            entity.crew = placement.crew.unwrap_or(if is_player {
+                CrewQuality::default()
+            } else {
+                self.difficulty.crew()
+            });
+            entity.side = placement.side.clone();
+            entity.tags = placement.tags.clone();
+            if let Some(morale) = placement.morale {
+                entity.morale = morale.clamp(0.0, 1.0);
+            }
+            if !is_player && entity.kind == EntityKind::Submarine && entity.weapons.is_some() {
+                let mut ai = SubmarineAi::new(class.max_speed, entity.heading, entity.depth);
+                if let Some(waypoint) = &placement.waypoint {
+                    ai.send_to(waypoint.clone());
+                }
+                ai.tracker = placement.tracker;
+                entity.ai = Some(ai);
+            }
+            if !is_player && entity.asw.is_some() {
+                entity.escort_ai = Some(EscortAi::default());
+            }
+            let id = world.spawn(entity);
+            if is_helm {
+                helms.push(id);
+            } else if is_player {
+                player = Some(id);
+            }
+        }
+        for sailing in &self.sailings {
+            let mut ships = Vec::new();
+            for (i, class) in sailing.classes.iter().enumerate() {
+                let class = self.class(class).unwrap();
+                let mut ship = class.instantiate(&sailing.ship_name(i), sailing.station(i));
+                if class.radar_generation.is_none() {
+                    ship.radar_generation = RadarGeneration::for_era(self.era);
+                }
+                ship.heading = sailing.from.angle_to(&sailing.to);
+                ship.speed = MetersPerSecond::from(sailing.speed).0;
+                ship.crew = self.difficulty.crew();
+                ship.side = sailing.side.clone();
+                if ship.asw.is_some() {
+                    ship.escort_ai = Some(EscortAi::default());
+                }
+                ships.push(ship);
+            }
+            world.traffic.departures.push(Departure {
+                time: sailing.departure(self.environment.start_time),
+                ships,
+                to: sailing.to.clone(),
+            });
+        }
+        world.theater = self.theater.clone();
+        world.wolfpack = self.wolfpack.clone();
+        for class in &self.theater.classes {
+            let class = self.class(class).unwrap();
+            let mut ship = class.instantiate(&class.name, self.theater.base.clone());
+            if class.radar_generation.is_none() {
+                ship.radar_generation = RadarGeneration::for_era(self.era);
+            }
+            ship.crew = self.difficulty.crew();
+            if ship.asw.is_some() {
+                ship.escort_ai = Some(EscortAi::default());
+            }
+            world.theater.group.push(ship);
+        }
+        let mut simulation = Simulation::new(world, player.unwrap());
+        simulation.tutorial = self.tutorial.clone();
+        simulation.messages.extend(&self.messages);
+        simulation.classes = self.classes.clone();
+        simulation.chart = self.chart.clone();
+        simulation.signals = self.signals.clone();
+        for rendezvous in &self.rendezvous {
+            let at = match rendezvous.signal {
+                Some(at) => at,
+                None => continue,
+            };
+            let units = simulation.preferences.units;
+            let text = simulation.messages.format(
+                "rendezvous-signal",
+                &[
+                    ("name", &rendezvous.name),
+                    ("x", &units.range(Meters(rendezvous.position.x))),
+                    ("y", &units.range(Meters(rendezvous.position.y))),
+                ],
+            );
+            let signal = Signal {
+                name: rendezvous.name.clone(),
+                at,
+                text,
+                encrypted: true,
+                mark: Some(MarkShape::Point(rendezvous.position.clone())),
+            };
+            simulation
+                .signals
+                .signals
+                .push((signal, SignalState::Waiting));
+        }
+        simulation.navigation = Navigation::new(simulation.world.rng.stream(navigation::STREAM));
+        simulation.track = History::positions(&self.retention);
+        simulation.contacts.retention = self.retention;
+        simulation.contacts.aging = self.aging;
+        for id in helms {
+            let helm = Helm::new(id, &simulation.contacts);
+            simulation.helms.push(helm);
+        }
+        Ok(simulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::KNOT;
+    use crate::tuning::Tunable;
+
+    const CONVOY: &str = "
+[scenario]
+name = Convoy test
+era = 1944
+player = U-99
+sea_state = 3
+
+[class.type_viic]
+kind = submarine
+max_speed = 17.7
+tubes = 5
+torpedo = early_passive
+deck_gun = true
+
+[class.liberty]
+kind = merchant
+max_speed = 11
+
+[entity.U-99]
+class = type_viic
+x = 0
+y = -5000
+heading = 0
+speed = 4
+
+[entity.SS Test]
+class = liberty
+x = 0
+y = 0
+heading = 90
+speed = 9
+";
+
+    #[test]
+    fn build_convoy() {
+        let scenario = Scenario::from_config(&Config::parse(CONVOY).unwrap()).unwrap();
+        assert!(scenario.validate().is_empty());
+        let sim = scenario.build().unwrap();
+        let boat = sim.own_ship().unwrap();
+        assert_eq!(boat.name, "U-99");
+        assert!((boat.velocity().y - 4.0 * KNOT).abs() < 0.001);
+        assert_eq!(sim.world.entities.len(), 2);
+        assert_eq!(sim.world.environment.sea_state, 3);
+        let station = boat.weapons.as_ref().unwrap();
+        assert_eq!(
+            station.reliability,
+            Reliability::for_era(Era { year: 1944 })
+        );
+    }
+
+    #[test]
+    fn sound_speed() {
+        let text = CONVOY.replace(
+            "[class.liberty]",
+            "[sound_speed]\n80 = 1503\n0 = 1500\n400 = 1485\n\n[class.liberty]",
+        );
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        let profile = &scenario.environment.sound_speed;
+        assert_eq!(profile.points[0], (0.0, 1500.0));
+        assert_eq!(profile.layer_depth(), Some(80.0));
+        for bad in ["nan = 1500", "inf = 1500", "80 = 1500\n80.0 = 1490"] {
+            let text = CONVOY.replace(
+                "[class.liberty]",
+                &format!("[sound_speed]\n{}\n\n[class.liberty]", bad),
+            );
+            let config = Config::parse(&text).unwrap();
+            assert!(Scenario::from_config(&config).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn tuned_apart() {
+        let tuned = |text: &str| {
+            let scenario = Scenario::from_config(&Config::parse(text).unwrap()).unwrap();
+            scenario.build().unwrap()
+        };
+        let quick = tuned(&format!("{}\n[tuning]\nturn_rate = 0.1\n", CONVOY));
+        // a second scenario built since leaves the first as tuned
+        let plain = tuned(CONVOY);
+        let turn_rate = |sim: &Simulation| Tunable::TurnRate.get(&sim.world.environment.tuning);
+        assert_eq!(turn_rate(&quick), 0.1);
+        assert_eq!(turn_rate(&plain), 0.05);
+    }
+
+    #[test]
+    fn rendezvous_radioed() {
+        let text = format!(
+            "{}\n[rendezvous.Pilot]\nkind = pickup\nx = 1234.4\ny = -500.6\nopens = 0\n\
+             closes = 1000\ntransfer = 60\nsignal = 0",
+            CONVOY
+        );
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        let sim = scenario.build().unwrap();
+        let (signal, _) = &sim.signals.signals[0];
+        assert_eq!(signal.text, "rendezvous Pilot at 1234 m, -501 m");
+    }
+
+    #[test]
+    fn weather() {
+        let text = CONVOY.replace(
+            "sea_state = 3",
+            "weather = gale\nsea_state = 5\nsound_profile = isovelocity\n\
+             [weather.later]\nat = 600\nover = 60\nsea_state = 2",
+        );
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert_eq!(scenario.environment.sea_state, 5);
+        assert_eq!(scenario.environment.visibility, 4_000.0);
+        assert_eq!(scenario.environment.sound_speed.layer_depth(), None);
+        let mut sim = scenario.build().unwrap();
+        for _ in 0..700 {
+            sim.world.step(1.0);
+        }
+        assert_eq!(sim.world.environment.sea_state, 2);
+    }
+
+    #[test]
+    fn several_vessels_to_command() {
+        use crate::command::Command;
+        use crate::helm::HelmError;
+        use crate::simulation::CommandError;
+        let text = CONVOY.replace("player = U-99", "player = U-99\nhelms = U-552")
+            + "\n[entity.U-552]\nclass = type_viic\nx = 4000\ny = -5000\n";
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert!(scenario.validate().is_empty());
+        let mut sim = scenario.build().unwrap();
+        let second = sim.world.entities.by_name("U-552").unwrap();
+        assert!(second.ai.is_none());
+        let second = second.id;
+        let first = sim.player;
+        sim.execute(&Command::parse("course 0 0").unwrap()).unwrap();
+        sim.execute(&Command::parse("helm U-552").unwrap()).unwrap();
+        assert_eq!(sim.player, second);
+        assert!(sim.route.is_empty());
+        assert_eq!(
+            sim.execute(&Command::parse("helm SS Test").unwrap()),
+            Err(CommandError::Helm(HelmError::NotControllable(
+                "SS Test".to_string()
+            )))
+        );
+        // the boat left behind keeps sensing and steering
+        sim.step(1.0);
+        assert!(!sim.helms[0].contacts.contacts.is_empty());
+        assert!(!sim.helms[0].route.is_empty());
+        sim.execute(&Command::parse("helm U-99").unwrap()).unwrap();
+        assert_eq!(sim.player, first);
+        assert_eq!(sim.helms[0].entity, second);
+
+        let text = text.replace("helms = U-552", "helms = U-553");
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert_eq!(
+            scenario.validate(),
+            vec![ScenarioIssue::UnknownPlayer("U-553".to_string())]
+        );
+    }
+
+    #[test]
+    fn realism() {
+        let text = CONVOY.replace(
+            "sea_state = 3",
+            "realism = perfect\n[reliability]\ndud = 0.3",
+        );
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert_eq!(scenario.reliability.premature, 0.0);
+        assert_eq!(scenario.reliability.dud, 0.3);
+    }
+
+    #[test]
+    fn crews() {
+        let text = CONVOY.replace("sea_state = 3", "difficulty = expert");
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        let sim = scenario.build().unwrap();
+        assert_eq!(sim.own_ship().unwrap().crew, CrewQuality::Trained);
+        let merchant = sim.world.entities.by_name("SS Test");
+        assert_eq!(merchant.unwrap().crew, CrewQuality::Elite);
+        let text = text.replace("speed = 9", "speed = 9\ncrew = green\nmorale = 0.2");
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert_eq!(scenario.placements[1].crew, Some(CrewQuality::Green));
+        assert_eq!(scenario.placements[1].morale, Some(0.2));
+    }
+
+    #[test]
+    fn sides_and_tags() {
+        let text = CONVOY.replace(
+            "speed = 9",
+            "speed = 9\nside = allied\ntags = convoy, HX-72",
+        );
+        let sim = Scenario::from_config(&Config::parse(&text).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let merchant = sim.world.entities.by_tag("HX-72").next().unwrap();
+        assert_eq!(merchant.name, "SS Test");
+        assert_eq!(merchant.side.as_deref(), Some("allied"));
+        assert_eq!(sim.own_ship().unwrap().side, None);
+    }
+
+    #[test]
+    fn behavior_trees() {
+        use crate::ai::behavior::{Leaf, Node};
+        let text = CONVOY.replace(
+            "[class.liberty]",
+            "[tree.submarine]\nroot = stalk\n\n[class.liberty]",
+        );
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        let tree = scenario.behaviors.get("submarine").unwrap();
+        assert_eq!(tree.root, Node::Leaf(Leaf::Stalk));
+        let text = text.replace("root = stalk", "root = sulk");
+        assert!(Scenario::from_config(&Config::parse(&text).unwrap()).is_err());
+    }
+
+    #[test]
+    fn shadowing() {
+        let text = CONVOY.to_string()
+            + "\n[shadow.Test]\ntarget = SS Test\nhours = 2\nmin_range = 2000\nmax_range = 8000\n";
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert!(scenario.validate().is_empty());
+        let sim = scenario.build().unwrap();
+        assert_eq!(sim.world.shadows[0].by.as_deref(), Some("U-99"));
+        assert_eq!(sim.world.shadows[0].required, 7200.0);
+
+        let text = text.replace("target = SS Test", "target = SS Gone");
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert_eq!(
+            scenario.validate(),
+            vec![ScenarioIssue::UnknownShadowed {
+                shadow: "Test".to_string(),
+                vessel: "SS Gone".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn validate_issues() {
+        let text = CONVOY
+            .replace("era = 1944", "era = 1942")
+            .replace("player = U-99", "player = U-100")
+            .replace("class = liberty", "class = hog_islander");
+        let scenario = Scenario::from_config(&Config::parse(&text).unwrap()).unwrap();
+        let issues = scenario.validate();
+        assert_eq!(issues.len(), 3);
+        assert!(issues.contains(&ScenarioIssue::UnknownPlayer("U-100".to_string())));
+        assert!(scenario.build().is_err());
+    }
+}
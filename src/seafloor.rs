@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::physics::Point;
+use crate::zone::Zone;
+
+// #############################
+// #         SEAFLOOR          #
+// #############################
+
+// What the bottom is made of is charted with its depth, on the zones:
+//
+// [zone.Dogger Bank]
+// kind = shallow
+// points = 0 0, 8000 0, 8000 5000, 0 5000
+// depth = 18
+// bottom = sand           # optional: mud, sand or rock
+//
+// and for the open sea with "bottom = mud" in the "[scenario]" section,
+// sand when not given. The scenario lays both out on a grid of cells CELL
+// meters on a side, the shallowest zone over the middle of a cell giving
+// its depth and bottom, for the sonars to look up along a path.
+//
+// In charted water sound reaches a distant listener bouncing off the
+// bottom, and loses some of itself at every bounce: little off rock, which
+// reflects it, much into mud, which soaks it up. The shallower the water,
+// the more bounces over a range. A boat settling slowly onto mud or sand
+// lies there unharmed, where rock holes it (see world.rs); and what
+// drifts onto a shoal is swallowed by soft bottom, or lies stranded on
+// rock (see hazards.rs).
+
+/// Meters on a side of the cells the seafloor is charted on
+pub const CELL: f32 = 500.0;
+/// Meters sound travels between two bounces, per meter of water
+const SKIP: f32 = 20.0;
+/// Most cells looked up along a path
+const MAX_SAMPLES: usize = 16;
+/// Meters per second below which a boat settles onto the bottom
+pub const SETTLE_SPEED: f32 = 1.0;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Bottom {
+    Mud,
+    #[default]
+    Sand,
+    Rock,
+}
+
+impl Bottom {
+    /// dB lost by sound bouncing off it once
+    pub fn bounce_loss(&self) -> f32 {
+        match self {
+            Bottom::Mud => 3.0,
+            Bottom::Sand => 1.0,
+            Bottom::Rock => 0.3,
+        }
+    }
+
+    /// dB per square meter it scatters a pulse back in shallow water, see
+    /// reverberation.rs
+    pub fn scattering(&self) -> f32 {
+        match self {
+            Bottom::Mud => -47.0,
+            Bottom::Sand => -41.0,
+            Bottom::Rock => -35.0,
+        }
+    }
+
+    /// Whether a boat may settle onto it unharmed
+    pub fn holds(&self) -> bool {
+        matches!(self, Bottom::Mud | Bottom::Sand)
+    }
+
+    /// Chance it buries what grounds on it
+    pub fn burial(&self) -> f32 {
+        match self {
+            Bottom::Mud => 0.9,
+            Bottom::Sand => 0.5,
+            Bottom::Rock => 0.0,
+        }
+    }
+}
+
+impl FromStr for Bottom {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Bottom, String> {
+        match s {
+            "mud" => Ok(Bottom::Mud),
+            "sand" => Ok(Bottom::Sand),
+            "rock" => Ok(Bottom::Rock),
+            _ => Err(format!("unknown bottom '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Bottom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Bottom::Mud => "mud",
+            Bottom::Sand => "sand",
+            Bottom::Rock => "rock",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The seafloor under a cell
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Cell {
+    /// Meters of water at low tide, None where not charted
+    pub depth: Option<f32>,
+    pub bottom: Bottom,
+}
+
+/// The bottom and the charted depths, cell by cell
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Seafloor {
+    /// Bottom of the open sea, where no zone charts one
+    pub open: Bottom,
+    cells: HashMap<(i32, i32), Cell>,
+}
+
+fn index(value: f32) -> i32 {
+    (value / CELL).floor() as i32
+}
+
+impl Seafloor {
+    /// Lays out the depths and bottoms of `zones` over an `open` sea
+    pub fn chart(zones: &[Zone], open: Bottom) -> Seafloor {
+        let mut seafloor = Seafloor {
+            open,
+            cells: HashMap::new(),
+        };
+        let mut charted: Vec<&Zone> = zones
+            .iter()
+            .filter(|z| z.depth.is_some() || z.bottom.is_some())
+            .collect();
+        // the deepest first, for the shallowest to be charted over them
+        charted.sort_by(|a, b| {
+            let depth = |z: &Zone| z.depth.unwrap_or(f32::INFINITY);
+            depth(b).total_cmp(&depth(a))
+        });
+        for zone in charted {
+            let xs = zone.points.iter().map(|p| index(p.x));
+            let ys = zone.points.iter().map(|p| index(p.y));
+            let (x0, x1) = (xs.clone().min().unwrap(), xs.max().unwrap());
+            let (y0, y1) = (ys.clone().min().unwrap(), ys.max().unwrap());
+            for i in x0..=x1 {
+                for j in y0..=y1 {
+                    let middle = Point {
+                        x: (i as f32 + 0.5) * CELL,
+                        y: (j as f32 + 0.5) * CELL,
+                    };
+                    if !zone.contains(&middle) {
+                        continue;
+                    }
+                    let cell = seafloor.cells.entry((i, j)).or_insert(Cell {
+                        depth: None,
+                        bottom: open,
+                    });
+                    if zone.depth.is_some() {
+                        cell.depth = zone.depth;
+                    }
+                    if let Some(bottom) = zone.bottom {
+                        cell.bottom = bottom;
+                    }
+                }
+            }
+        }
+        seafloor
+    }
+
+    /// The seafloor at `p`
+    pub fn at(&self, p: &Point) -> Cell {
+        self.cells
+            .get(&(index(p.x), index(p.y)))
+            .copied()
+            .unwrap_or(Cell {
+                depth: None,
+                bottom: self.open,
+            })
+    }
+
+    /// dB sound loses to the bottom on its way from `from` to `to`, 0 over
+    /// uncharted water
+    pub fn bottom_loss(&self, from: &Point, to: &Point) -> f32 {
+        if self.cells.is_empty() {
+            return 0.0;
+        }
+        let range = from.distance_to(to);
+        let samples = ((range / CELL).ceil() as usize).clamp(1, MAX_SAMPLES);
+        let stretch = range / samples as f32;
+        (0..samples)
+            .map(|i| {
+                let t = (i as f32 + 0.5) / samples as f32;
+                let cell = self.at(&Point {
+                    x: from.x + (to.x - from.x) * t,
+                    y: from.y + (to.y - from.y) * t,
+                });
+                match cell.depth {
+                    Some(depth) => stretch / (SKIP * depth.max(1.0)) * cell.bottom.bounce_loss(),
+                    None => 0.0,
+                }
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zone::ZoneKind;
+
+    fn bank(name: &str, side: f32, depth: Option<f32>, bottom: Option<Bottom>) -> Zone {
+        Zone {
+            name: name.to_string(),
+            kind: ZoneKind::Shallow,
+            points: vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: side, y: 0.0 },
+                Point { x: side, y: side },
+                Point { x: 0.0, y: side },
+            ],
+            depth,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn charted_cell_by_cell() {
+        let zones = vec![
+            bank("bank", 2000.0, Some(10.0), None),
+            bank("basin", 10_000.0, Some(80.0), Some(Bottom::Mud)),
+            bank("reef", 1000.0, Some(4.0), Some(Bottom::Rock)),
+        ];
+        let seafloor = Seafloor::chart(&zones, Bottom::Sand);
+        let at = |x: f32, y: f32| seafloor.at(&Point { x, y });
+        assert_eq!(
+            at(200.0, 200.0),
+            Cell {
+                depth: Some(4.0),
+                bottom: Bottom::Rock
+            }
+        );
+        // the bank charts no bottom, the basin under it gives it
+        assert_eq!(at(1500.0, 1500.0).bottom, Bottom::Mud);
+        assert_eq!(at(1500.0, 1500.0).depth, Some(10.0));
+        assert_eq!(at(5000.0, 5000.0).depth, Some(80.0));
+        assert_eq!(
+            at(-5000.0, 0.0),
+            Cell {
+                depth: None,
+                bottom: Bottom::Sand
+            }
+        );
+        assert_eq!("rock".parse(), Ok(Bottom::Rock));
+        assert!("chalk".parse::<Bottom>().is_err());
+    }
+
+    #[test]
+    fn soft_bottom_soaks_up_sound() {
+        let path = |bottom: Bottom| {
+            let seafloor = Seafloor::chart(
+                &[bank("bank", 10_000.0, Some(50.0), Some(bottom))],
+                Bottom::Sand,
+            );
+            seafloor.bottom_loss(
+                &Point { x: 0.0, y: 5000.0 },
+                &Point {
+                    x: 10_000.0,
+                    y: 5000.0,
+                },
+            )
+        };
+        // ten bounces in 50 m of water over 10 km
+        assert!((path(Bottom::Sand) - 10.0).abs() < 0.01);
+        assert!(path(Bottom::Mud) > path(Bottom::Sand));
+        assert!(path(Bottom::Rock) < path(Bottom::Sand));
+        let open = Seafloor::default();
+        assert_eq!(
+            open.bottom_loss(&Point { x: 0.0, y: 0.0 }, &Point { x: 9000.0, y: 0.0 }),
+            0.0
+        );
+    }
+}
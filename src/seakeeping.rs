@@ -0,0 +1,331 @@
+use std::f32::consts::PI;
+
+use crate::environment::Environment;
+use crate::events::Event;
+use crate::physics::{normalize_angle, KNOT};
+use crate::world::{Entity, EntityKind, World};
+
+// #############################
+// #        SEAKEEPING         #
+// #############################
+
+// How a hull rides the waves. The sea state gives the height of the waves
+// and Environment::wave_from where they come from; a hull rolls in beam
+// seas and pitches in head or following seas, the more the shorter it is.
+// The motion fades out below the surface. A moving platform spoils the
+// aim of a gun and the eye of a lookout, and small hulls have to slow
+// down as the sea gets up.
+//
+// Just under the surface the waves also work on a submarine's depth: the
+// boat heaves up and down with them, and is sucked towards the surface.
+// Only the planes hold it down, and they need speed through the water to
+// bite; slow at periscope depth in a heavy sea, a boat swings many meters
+// and may broach, showing its conning tower. With the automatic depth
+// keeping on (the default, "planes auto") the trim pumps help the planes
+// at the cost of their noise; "planes manual" leaves it to the planes.
+
+/// Meters of depth over which the motion of the sea fades away
+const WAVE_DEPTH: f32 = 10.0;
+/// Radians of roll amplitude per unit of wave height over hull length
+const ROLL_RESPONSE: f32 = 7.0;
+/// Radians of pitch amplitude per unit of wave height over hull length
+const PITCH_RESPONSE: f32 = 2.5;
+/// Radians, roll amplitude of a hull on its beam ends
+pub const MAX_ROLL: f32 = 0.6;
+/// Radians, pitch amplitude of a hull burying its bow
+pub const MAX_PITCH: f32 = 0.25;
+/// Meters per second squared
+const GRAVITY: f32 = 9.81;
+/// Froude number of the fastest a hull is driven through calm water
+const HULL_FROUDE: f32 = 0.6;
+/// Fraction of its length a head sea has to reach to stop a hull
+const STOPPING_WAVE: f32 = 0.1;
+/// Least fraction of its speed a hull keeps in any sea
+const MIN_SPEED_FRACTION: f32 = 0.2;
+/// Meters of heave per meter of wave height felt, with the planes at full
+/// authority
+const HEAVE_RESPONSE: f32 = 2.0;
+/// Meters the boat is sucked up per meter of wave height felt
+const SUCTION: f32 = 1.5;
+/// Meters per second through the water at which the planes hold the boat
+const PLANES_SPEED: f32 = 4.0 * KNOT;
+/// Least authority the planes keep, stopped
+const MIN_AUTHORITY: f32 = 0.25;
+/// Factor the trim pumps of the automatic depth keeping add to the planes
+const ASSIST_AUTHORITY: f32 = 2.0;
+/// Meters off its depth the planesmen always catch
+const HOLD_TOLERANCE: f32 = 1.0;
+/// Meters below which the waves no longer move a boat off its depth
+const NEAR_SURFACE: f32 = 40.0;
+/// Depth in meters above which a boat's conning tower breaks the surface
+pub const BROACH_DEPTH: f32 = 8.0;
+
+/// Significant wave height in meters for a Douglas sea state
+pub fn wave_height(sea_state: u8) -> f32 {
+    match sea_state {
+        0 => 0.0,
+        1 => 0.1,
+        2 => 0.5,
+        3 => 1.25,
+        4 => 2.5,
+        5 => 4.0,
+        6 => 6.0,
+        7 => 9.0,
+        _ => 14.0,
+    }
+}
+
+/// Radians between the bow and where the waves come from: 0 in head seas,
+/// PI in following seas
+fn encounter_angle(entity: &Entity, environment: &Environment) -> f32 {
+    normalize_angle(environment.wave_from - entity.heading).abs()
+}
+
+/// Meters of wave height the hull of `entity` feels
+fn felt_wave_height(entity: &Entity, environment: &Environment) -> f32 {
+    if entity.kind == EntityKind::Torpedo {
+        return 0.0;
+    }
+    let fading = if entity.is_surfaced() {
+        1.0
+    } else {
+        (-entity.depth / WAVE_DEPTH).exp()
+    };
+    wave_height(environment.sea_state) * fading
+}
+
+/// Amplitudes of the motion of a hull, in radians
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Motion {
+    pub roll: f32,
+    pub pitch: f32,
+}
+
+impl Motion {
+    /// 1 on a steady platform, down to 0.1 on one thrown about
+    pub fn steadiness(&self) -> f32 {
+        let severity = (self.roll / MAX_ROLL + self.pitch / MAX_PITCH) / 2.0;
+        (1.0 - severity).clamp(0.1, 1.0)
+    }
+}
+
+pub fn motion(entity: &Entity, environment: &Environment) -> Motion {
+    let steepness = felt_wave_height(entity, environment) / entity.length();
+    let angle = encounter_angle(entity, environment);
+    Motion {
+        roll: (ROLL_RESPONSE * steepness * angle.sin()).min(MAX_ROLL),
+        pitch: (PITCH_RESPONSE * steepness * angle.cos().abs()).min(MAX_PITCH),
+    }
+}
+
+/// Pitch and roll of `entity` at `time`, in radians, bow up and
+/// starboard down positive
+pub fn attitude(entity: &Entity, environment: &Environment, time: f32) -> (f32, f32) {
+    let motion = motion(entity, environment);
+    // longer hulls swing slower; each hull keeps its own phase
+    let period = entity.length().sqrt();
+    let phase = entity.id as f32 * 1.7;
+    let pitch = motion.pitch * (2.0 * PI * time / (0.5 * period) + phase).sin();
+    let roll = motion.roll * (2.0 * PI * time / (0.8 * period) + phase).sin();
+    (pitch, roll)
+}
+
+/// Meters at which the lookouts of `entity` sight a ship, the visibility
+/// shortened by spray and a lively deck
+pub fn sighting_range(entity: &Entity, environment: &Environment) -> f32 {
+    let steadiness = motion(entity, environment).steadiness();
+    environment.visibility * (0.5 + 0.5 * steadiness)
+}
+
+/// Meters per second `entity` can make through the sea, None when the sea
+/// does not hold it back (submerged hulls and torpedoes)
+pub fn speed_limit(entity: &Entity, environment: &Environment) -> Option<f32> {
+    if !entity.is_surfaced() || entity.kind == EntityKind::Torpedo {
+        return None;
+    }
+    let hull_speed = HULL_FROUDE * (GRAVITY * entity.length()).sqrt();
+    // head seas slow a hull down twice as much as following ones
+    let head = (1.0 + encounter_angle(entity, environment).cos()) / 2.0;
+    let wave = felt_wave_height(entity, environment) * (0.5 + 0.5 * head);
+    let fraction = 1.0 - wave / (STOPPING_WAVE * entity.length());
+    Some(hull_speed * fraction.max(MIN_SPEED_FRACTION))
+}
+
+/// How well the planes (and trim pumps, when assisting) of `boat` hold
+/// it at depth, from MIN_AUTHORITY stopped up
+fn authority(boat: &Entity) -> f32 {
+    let planes = (boat.speed / PLANES_SPEED).clamp(MIN_AUTHORITY, 1.0);
+    if boat.depth_assist {
+        planes * ASSIST_AUTHORITY
+    } else {
+        planes
+    }
+}
+
+/// Meters `boat`, held at `depth`, is moved off it by the waves at
+/// `time`; negative is up
+pub fn heave(boat: &Entity, depth: f32, environment: &Environment, time: f32) -> f32 {
+    if boat.kind != EntityKind::Submarine || depth > NEAR_SURFACE {
+        return 0.0;
+    }
+    let felt = wave_height(environment.sea_state) * (-depth / WAVE_DEPTH).exp();
+    let authority = authority(boat);
+    let period = 4.0 + 2.0 * wave_height(environment.sea_state).sqrt();
+    let phase = boat.id as f32 * 1.7;
+    let swing = HEAVE_RESPONSE * felt * (2.0 * PI * time / period + phase).sin();
+    let off = (SUCTION * felt + swing) / authority;
+    -off.signum() * (off.abs() - HOLD_TOLERANCE).max(0.0)
+}
+
+/// Whether the conning tower of `boat` is breaking the surface
+pub fn broached(boat: &Entity) -> bool {
+    boat.kind == EntityKind::Submarine && !boat.is_surfaced() && boat.depth < BROACH_DEPTH
+}
+
+/// Moves every submarine near the surface off the depth it is held at by
+/// the waves, reporting those that broach
+pub fn keep_depth(world: &mut World) {
+    let mut broaching = Vec::new();
+    for boat in world.entities.iter_mut() {
+        if boat.kind != EntityKind::Submarine || boat.transition.is_some() {
+            boat.heave = 0.0;
+            continue;
+        }
+        let held = boat.depth - boat.heave;
+        if held < 1.0 {
+            boat.heave = 0.0;
+            continue;
+        }
+        let was_broached = broached(boat);
+        let heave = heave(boat, held, &world.environment, world.time);
+        boat.depth = (held + heave).max(1.0);
+        boat.heave = boat.depth - held;
+        if broached(boat) && !was_broached {
+            broaching.push(boat.id);
+        }
+    }
+    for entity in broaching {
+        world.emit(Event::Broached { entity });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gunnery::PERISCOPE_DEPTH;
+    use crate::noise;
+    use crate::physics::{user_to_game_angle, Point};
+
+    fn sea(sea_state: u8) -> Environment {
+        Environment {
+            sea_state,
+            wave_from: user_to_game_angle(0.0),
+            ..Environment::default()
+        }
+    }
+
+    fn ship(kind: EntityKind, heading: f32) -> Entity {
+        let mut ship = Entity::new("ship", kind, Point { x: 0.0, y: 0.0 });
+        ship.heading = user_to_game_angle(heading);
+        ship
+    }
+
+    #[test]
+    fn rolls_in_beam_seas_and_pitches_in_head_seas() {
+        let environment = sea(5);
+        let beam = motion(&ship(EntityKind::Warship, 90.0), &environment);
+        let head = motion(&ship(EntityKind::Warship, 0.0), &environment);
+        assert!(beam.roll > 0.2 && beam.pitch < 0.001);
+        assert!(head.roll < 0.001 && head.pitch > 0.05);
+        let merchant = motion(&ship(EntityKind::Merchant, 90.0), &environment);
+        assert!(merchant.roll < beam.roll);
+        assert_eq!(
+            motion(&ship(EntityKind::Warship, 90.0), &sea(0)).steadiness(),
+            1.0
+        );
+        assert!(beam.steadiness() < 0.9);
+    }
+
+    #[test]
+    fn calm_below_the_waves() {
+        let environment = sea(6);
+        let mut boat = ship(EntityKind::Submarine, 90.0);
+        let surfaced = motion(&boat, &environment).roll;
+        boat.depth = 60.0;
+        assert!(motion(&boat, &environment).roll < surfaced * 0.01);
+        assert_eq!(speed_limit(&boat, &environment), None);
+    }
+
+    #[test]
+    fn small_hulls_slow_down_first() {
+        let calm = sea(1);
+        let rough = sea(6);
+        let boat = ship(EntityKind::Submarine, 0.0);
+        let merchant = ship(EntityKind::Merchant, 0.0);
+        let limit = |entity, environment| speed_limit(entity, environment).unwrap();
+        assert!(limit(&boat, &calm) > 14.0);
+        let boat_loss = limit(&boat, &rough) / limit(&boat, &calm);
+        let merchant_loss = limit(&merchant, &rough) / limit(&merchant, &calm);
+        assert!(boat_loss < merchant_loss);
+        let following = ship(EntityKind::Submarine, 180.0);
+        assert!(limit(&following, &rough) > limit(&boat, &rough));
+    }
+
+    /// Shallowest and deepest a boat held at periscope depth in a sea
+    /// state 6 gets over a minute, with how often it broached
+    fn hold_periscope_depth(speed: f32, assist: bool) -> (f32, f32, usize) {
+        let mut world = World::new();
+        world.environment = sea(6);
+        let mut boat = ship(EntityKind::Submarine, 0.0);
+        boat.depth = PERISCOPE_DEPTH;
+        boat.speed = speed * KNOT;
+        boat.depth_assist = assist;
+        world.spawn(boat);
+        let (mut shallowest, mut deepest) = (PERISCOPE_DEPTH, PERISCOPE_DEPTH);
+        for _ in 0..60 {
+            world.step(1.0);
+            let depth = world.entity(1).unwrap().depth;
+            shallowest = shallowest.min(depth);
+            deepest = deepest.max(depth);
+        }
+        let broached = world
+            .events
+            .iter()
+            .filter(|e| e.event == Event::Broached { entity: 1 })
+            .count();
+        (shallowest, deepest, broached)
+    }
+
+    #[test]
+    fn slow_boats_broach_in_a_heavy_sea() {
+        let (shallowest, _, broached) = hold_periscope_depth(0.0, false);
+        assert!(shallowest < BROACH_DEPTH);
+        assert!(broached > 1);
+
+        let (shallowest, deepest, broached) = hold_periscope_depth(6.0, false);
+        assert_eq!(broached, 0);
+        assert!(
+            shallowest > 12.0 && deepest < 20.0,
+            "{} {}",
+            shallowest,
+            deepest
+        );
+
+        // the trim pumps hold her even stopped, loudly
+        let (shallowest, _, broached) = hold_periscope_depth(0.0, true);
+        assert_eq!(broached, 0);
+        assert!(shallowest > BROACH_DEPTH);
+        let mut boat = ship(EntityKind::Submarine, 0.0);
+        boat.heave = -2.0;
+        assert!(noise::contributors(&boat)
+            .iter()
+            .any(|c| c.source == noise::NoiseSource::TrimPumps));
+    }
+
+    #[test]
+    fn lookouts_see_less_in_a_seaway() {
+        let escort = ship(EntityKind::Warship, 90.0);
+        assert_eq!(sighting_range(&escort, &sea(0)), 20_000.0);
+        assert!(sighting_range(&escort, &sea(6)) < 16_000.0);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::acoustics::spreading_loss;
+use crate::physics::{normalize_angle, Point};
+
+// #############################
+// #      TORPEDO SEEKERS      #
+// #############################
+
+// Each tick the seeker looks at every acoustic source inside its cone and
+// scores it as "received level minus how suspicious it sounds". The highest
+// score above the detection threshold wins, so a loud decoy beats a quiet
+// target unless the seeker is good enough to discount it.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SourceKind {
+    Target,
+    Decoy,
+    Wake,
+    SurfaceClutter,
+    /// The boat that fired the torpedo
+    Launcher,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AcousticSource {
+    pub id: usize,
+    pub kind: SourceKind,
+    pub position: Point,
+    /// Depth in meters, positive down
+    pub depth: f32,
+    /// Source (or echo) level in dB at 1 m
+    pub level: f32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SeekerGeneration {
+    /// Early passive acoustic homing, easily seduced
+    EarlyPassive,
+    /// Active/passive seeker with basic echo classification
+    ActivePassive,
+    /// Wire-guided, doppler and depth discrimination
+    Modern,
+}
+
+/// How many dB each kind of false source is discounted by
+#[derive(Debug, PartialEq, Clone)]
+pub struct Discrimination {
+    pub decoy: f32,
+    pub wake: f32,
+    pub surface_clutter: f32,
+    /// Anti circular-run logic: how strongly the launcher is ignored
+    pub launcher: f32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Seeker {
+    pub generation: SeekerGeneration,
+    /// Half width of the acoustic cone, in radians
+    pub half_cone: f32,
+    pub max_range: f32,
+    /// Minimum score needed to home on a source
+    pub threshold: f32,
+    pub discrimination: Discrimination,
+}
+
+impl Seeker {
+    pub fn new(generation: SeekerGeneration) -> Seeker {
+        match generation {
+            SeekerGeneration::EarlyPassive => Seeker {
+                generation,
+                half_cone: 30f32.to_radians(),
+                max_range: 600.0,
+                threshold: 70.0,
+                discrimination: Discrimination {
+                    decoy: 0.0,
+                    wake: 0.0,
+                    surface_clutter: 0.0,
+                    launcher: 0.0,
+                },
+            },
+            SeekerGeneration::ActivePassive => Seeker {
+                generation,
+                half_cone: 40f32.to_radians(),
+                max_range: 1500.0,
+                threshold: 60.0,
+                discrimination: Discrimination {
+                    decoy: 6.0,
+                    wake: 8.0,
+                    surface_clutter: 10.0,
+                    launcher: 20.0,
+                },
+            },
+            SeekerGeneration::Modern => Seeker {
+                generation,
+                half_cone: 45f32.to_radians(),
+                max_range: 2500.0,
+                threshold: 50.0,
+                discrimination: Discrimination {
+                    decoy: 15.0,
+                    wake: 20.0,
+                    surface_clutter: 25.0,
+                    launcher: 100.0,
+                },
+            },
+        }
+    }
+
+    fn discount(&self, kind: SourceKind) -> f32 {
+        match kind {
+            SourceKind::Target => 0.0,
+            SourceKind::Decoy => self.discrimination.decoy,
+            SourceKind::Wake => self.discrimination.wake,
+            SourceKind::SurfaceClutter => self.discrimination.surface_clutter,
+            SourceKind::Launcher => self.discrimination.launcher,
+        }
+    }
+
+    /// Returns the score of a source as heard from the torpedo, if it can be heard
+    pub fn score(
+        &self,
+        position: &Point,
+        heading: f32,
+        depth: f32,
+        source: &AcousticSource,
+    ) -> Option<f32> {
+        let horizontal = position.distance_to(&source.position);
+        let vertical = source.depth - depth;
+        let range = (horizontal.powi(2) + vertical.powi(2)).sqrt();
+        if range > self.max_range {
+            return None;
+        }
+        if horizontal > 0.0 {
+            let off_axis = normalize_angle(position.angle_to(&source.position) - heading);
+            if off_axis.abs() > self.half_cone {
+                return None;
+            }
+        }
+        let score = source.level - spreading_loss(range) - self.discount(source.kind);
+        if score < self.threshold {
+            return None;
+        }
+        Some(score)
+    }
+
+    /// Picks the source the torpedo will home on
+    pub fn select<'a>(
+        &self,
+        position: &Point,
+        heading: f32,
+        depth: f32,
+        sources: &'a [AcousticSource],
+    ) -> Option<&'a AcousticSource> {
+        sources
+            .iter()
+            .filter_map(|s| {
+                self.score(position, heading, depth, s)
+                    .map(|score| (score, s))
+            })
+            .fold(
+                None,
+                |best: Option<(f32, &AcousticSource)>, (score, s)| match best {
+                    Some((best_score, _)) if best_score >= score => best,
+                    _ => Some((score, s)),
+                },
+            )
+            .map(|(_, s)| s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: usize, kind: SourceKind, x: f32, y: f32, level: f32) -> AcousticSource {
+        AcousticSource {
+            id,
+            kind,
+            position: Point { x, y },
+            depth: 20.0,
+            level,
+        }
+    }
+
+    #[test]
+    fn select_target() {
+        let seeker = Seeker::new(SeekerGeneration::EarlyPassive);
+        let sources = vec![source(1, SourceKind::Target, 400.0, 10.0, 140.0)];
+        let chosen = seeker.select(&Point { x: 0.0, y: 0.0 }, 0.0, 20.0, &sources);
+        assert_eq!(chosen.unwrap().id, 1);
+    }
+
+    #[test]
+    fn outside_cone() {
+        let seeker = Seeker::new(SeekerGeneration::Modern);
+        let sources = vec![source(1, SourceKind::Target, 0.0, 400.0, 140.0)];
+        assert!(seeker
+            .select(&Point { x: 0.0, y: 0.0 }, 0.0, 20.0, &sources)
+            .is_none());
+    }
+
+    #[test]
+    fn decoy_seduces_old_fish_only() {
+        let sources = vec![
+            source(1, SourceKind::Target, 500.0, 0.0, 135.0),
+            source(2, SourceKind::Decoy, 450.0, 50.0, 145.0),
+        ];
+        let origin = Point { x: 0.0, y: 0.0 };
+        let old = Seeker::new(SeekerGeneration::EarlyPassive);
+        let new = Seeker::new(SeekerGeneration::Modern);
+        assert_eq!(old.select(&origin, 0.0, 20.0, &sources).unwrap().id, 2);
+        assert_eq!(new.select(&origin, 0.0, 20.0, &sources).unwrap().id, 1);
+    }
+
+    #[test]
+    fn circular_run() {
+        let sources = vec![source(7, SourceKind::Launcher, 300.0, 0.0, 130.0)];
+        let origin = Point { x: 0.0, y: 0.0 };
+        let old = Seeker::new(SeekerGeneration::EarlyPassive);
+        let new = Seeker::new(SeekerGeneration::Modern);
+        assert_eq!(old.select(&origin, 0.0, 20.0, &sources).unwrap().id, 7);
+        assert!(new.select(&origin, 0.0, 20.0, &sources).is_none());
+    }
+
+    #[test]
+    fn wake_and_clutter() {
+        let sources = vec![
+            source(1, SourceKind::Target, 800.0, 0.0, 125.0),
+            source(2, SourceKind::Wake, 300.0, 0.0, 125.0),
+            source(3, SourceKind::SurfaceClutter, 200.0, 0.0, 120.0),
+        ];
+        let origin = Point { x: 0.0, y: 0.0 };
+        let mid = Seeker::new(SeekerGeneration::ActivePassive);
+        let new = Seeker::new(SeekerGeneration::Modern);
+        assert_eq!(mid.select(&origin, 0.0, 20.0, &sources).unwrap().id, 2);
+        assert_eq!(new.select(&origin, 0.0, 20.0, &sources).unwrap().id, 1);
+    }
+}
@@ -0,0 +1,566 @@
+use std::fmt;
+
+use crate::acoustics::{ambient_noise, db_sum, transmission_loss};
+use crate::environment::Environment;
+use crate::noise;
+use crate::physics::{normalize_angle, Point, KNOT};
+use crate::pulse::Pulse;
+use crate::reverberation;
+use crate::tuning::{Tunable, Tuning};
+use crate::world::Entity;
+
+// #############################
+// #          SENSORS          #
+// #############################
+
+// A sensor does not simply work or not: every tick its modifier pipeline
+// turns the current conditions (damage, ice, own speed, own noise) into dB
+// of lost performance, which is subtracted from the signal excess of
+// everything it tries to detect.
+//
+// What a sensor reports is never the truth: every bearing it takes is off
+// by an error drawn around its accuracy, and the weaker the signal the
+// worse the accuracy, a contact at the threshold bearing three times worse
+// than a clear one. The periscope stadimeter, the radar and the echoes of
+// an active pulse measure ranges too, with an error growing with the range.
+//
+// Nor does a sensor tell apart everything it holds: ships closer together
+// in bearing than its beam is wide come in as one, on a bearing between
+// them, unless it also measures their ranges. The long towed array
+// separates the ships of a convoy the hull sonar hears as a single blur.
+// And the hull sonar is deaf right astern, where the hull and the screw
+// stand in the way: the baffles, which only a turn clears (see baffles.rs).
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SensorKind {
+    HullSonar,
+    TowedArray,
+    Periscope,
+    Radar,
+    /// Hears active sonar and seeker pulses, see intercept.rs
+    InterceptReceiver,
+}
+
+impl SensorKind {
+    pub fn is_acoustic(&self) -> bool {
+        matches!(
+            self,
+            SensorKind::HullSonar | SensorKind::TowedArray | SensorKind::InterceptReceiver
+        )
+    }
+
+    /// Whether the sensor listens to the noise vessels radiate
+    pub fn is_passive_sonar(&self) -> bool {
+        matches!(self, SensorKind::HullSonar | SensorKind::TowedArray)
+    }
+
+    /// Speed in meters per second above which flow noise starts to wash
+    /// out the sensor, with the dB lost per extra knot
+    fn washout(&self) -> Option<(f32, f32)> {
+        match self {
+            SensorKind::HullSonar => Some((5.0 * KNOT, 1.5)),
+            SensorKind::TowedArray => Some((8.0 * KNOT, 1.0)),
+            SensorKind::InterceptReceiver => Some((10.0 * KNOT, 1.0)),
+            _ => None,
+        }
+    }
+
+    /// Standard deviation in degrees of the bearings taken on a clear
+    /// signal
+    fn bearing_accuracy(&self) -> f32 {
+        match self {
+            SensorKind::HullSonar => 1.5,
+            SensorKind::TowedArray => 0.75,
+            SensorKind::Periscope => 0.5,
+            SensorKind::Radar => 1.0,
+            SensorKind::InterceptReceiver => 3.0,
+        }
+    }
+
+    /// Width in radians of the beam: ships closer together in bearing are
+    /// held as one
+    pub fn beam_width(&self) -> f32 {
+        let degrees = match self {
+            SensorKind::HullSonar => 8.0,
+            SensorKind::TowedArray => 3.0,
+            SensorKind::Periscope => 0.5,
+            SensorKind::Radar => 2.0,
+            SensorKind::InterceptReceiver => 10.0,
+        };
+        f32::to_radians(degrees)
+    }
+
+    /// Half the width in radians of the arc astern the sensor is deaf in,
+    /// None when it hears all around
+    pub fn baffles(&self) -> Option<f32> {
+        match self {
+            SensorKind::HullSonar => Some(30_f32.to_radians()),
+            _ => None,
+        }
+    }
+
+    /// Whether `position` is in the baffles of the sensor on `listener`
+    pub fn is_baffled(&self, listener: &Entity, position: &Point) -> bool {
+        let astern = listener.heading + std::f32::consts::PI;
+        self.baffles().is_some_and(|half| {
+            normalize_angle(listener.position.angle_to(position) - astern).abs() < half
+        })
+    }
+
+    /// How many times worse than on a clear signal the sensor measures at
+    /// `excess` dB of signal excess
+    fn degradation(excess: f32, tuning: &Tuning) -> f32 {
+        10_f32.powf((Tunable::ClearSignal.get(tuning) - excess).max(0.0) / 20.0)
+    }
+
+    /// Standard deviation in radians of a bearing taken at `excess` dB of
+    /// signal excess
+    pub fn bearing_error(&self, excess: f32, tuning: &Tuning) -> f32 {
+        self.bearing_accuracy().to_radians() * SensorKind::degradation(excess, tuning)
+    }
+
+    /// Standard deviation of a range measured at `excess` dB of signal
+    /// excess, as a fraction of the range; None for the sensors measuring
+    /// none. The hull sonar measures the range of the echoes of its pulses.
+    pub fn range_error(&self, excess: f32, tuning: &Tuning) -> Option<f32> {
+        let accuracy = match self {
+            SensorKind::HullSonar => 0.01,
+            SensorKind::Periscope => 0.05,
+            SensorKind::Radar => 0.02,
+            SensorKind::TowedArray | SensorKind::InterceptReceiver => return None,
+        };
+        Some(accuracy * SensorKind::degradation(excess, tuning))
+    }
+}
+
+impl fmt::Display for SensorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SensorKind::HullSonar => "hull sonar",
+            SensorKind::TowedArray => "towed array",
+            SensorKind::Periscope => "periscope",
+            SensorKind::Radar => "radar",
+            SensorKind::InterceptReceiver => "intercept receiver",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One stage of the degradation pipeline
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Modifier {
+    Damage,
+    Ice,
+    /// Flow noise over the array at high own speed
+    SpeedWashout,
+    /// Own transients (launches, machinery) drowning the receiver
+    OwnTransients,
+}
+
+/// Conditions a sensor works in this tick
+#[derive(Debug, PartialEq, Clone)]
+pub struct SensorContext {
+    /// Own speed in meters per second
+    pub speed: f32,
+    /// Fraction of the sea covered by ice, 0 to 1
+    pub ice_cover: f32,
+    /// Level in dB of the own transient currently going on, 0 when quiet
+    pub transient: f32,
+}
+
+impl SensorContext {
+    pub fn new(platform: &Entity, environment: &Environment) -> SensorContext {
+        SensorContext {
+            speed: platform.speed,
+            ice_cover: environment.ice_cover,
+            transient: platform.transient,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sensor {
+    pub kind: SensorKind,
+    /// 1 when intact, 0 when destroyed
+    pub health: f32,
+    /// Signal excess in dB needed for a detection, before modifiers
+    pub detection_threshold: f32,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl Sensor {
+    /// A sensor with the modifiers that make sense for its kind
+    pub fn new(kind: SensorKind) -> Sensor {
+        let mut modifiers = vec![Modifier::Damage, Modifier::Ice];
+        if kind.is_acoustic() {
+            modifiers.push(Modifier::SpeedWashout);
+            modifiers.push(Modifier::OwnTransients);
+        }
+        Sensor {
+            kind,
+            health: 1.0,
+            detection_threshold: match kind {
+                SensorKind::HullSonar => 10.0,
+                SensorKind::TowedArray => 4.0,
+                SensorKind::Periscope => 0.0,
+                SensorKind::Radar => 0.0,
+                SensorKind::InterceptReceiver => 6.0,
+            },
+            modifiers,
+        }
+    }
+
+    fn penalty(&self, modifier: Modifier, context: &SensorContext) -> f32 {
+        match modifier {
+            Modifier::Damage => {
+                if self.health <= 0.0 {
+                    f32::INFINITY
+                } else {
+                    (1.0 - self.health) * 20.0
+                }
+            }
+            Modifier::Ice => {
+                if self.kind.is_acoustic() {
+                    // cracking and grinding ice is loud
+                    context.ice_cover * 10.0
+                } else {
+                    context.ice_cover * 30.0
+                }
+            }
+            Modifier::SpeedWashout => match self.kind.washout() {
+                Some((onset, per_knot)) => ((context.speed - onset) / KNOT).max(0.0) * per_knot,
+                None => 0.0,
+            },
+            Modifier::OwnTransients => ((context.transient - 90.0) / 2.0).max(0.0),
+        }
+    }
+
+    /// dB lost to each stage of the pipeline
+    pub fn penalties(&self, context: &SensorContext) -> Vec<(Modifier, f32)> {
+        self.modifiers
+            .iter()
+            .map(|m| (*m, self.penalty(*m, context)))
+            .collect()
+    }
+
+    pub fn total_penalty(&self, context: &SensorContext) -> f32 {
+        self.penalties(context).iter().map(|(_, p)| p).sum()
+    }
+
+    pub fn is_operational(&self, context: &SensorContext) -> bool {
+        self.total_penalty(context).is_finite()
+    }
+
+    /// Signal excess in dB of a signal received at `received` dB over
+    /// `noise` dB of background; positive means detected
+    pub fn signal_excess(&self, received: f32, noise: f32, context: &SensorContext) -> f32 {
+        received - noise - self.detection_threshold - self.total_penalty(context)
+    }
+}
+
+/// Best signal excess `listener` gets on `target` through its acoustic
+/// sensors and the ears of its crew, None when it has no working sensor
+pub fn passive_excess(
+    environment: &Environment,
+    listener: &Entity,
+    target: &Entity,
+) -> Option<f32> {
+    excess_at(
+        environment,
+        listener,
+        &target.position,
+        target.depth,
+        noise::radiated_level(target),
+    )
+}
+
+/// dB lost by sound between `listener` and `position` at `depth`: the
+/// spreading and absorption, the layer when it lies between them and the
+/// bottom in charted water (see seafloor.rs), as worked out lately when
+/// little has changed (see propagation.rs)
+pub fn propagation_loss(
+    environment: &Environment,
+    listener: &Entity,
+    position: &Point,
+    depth: f32,
+) -> f32 {
+    let layer = environment.sound_speed.layer_depth();
+    environment.losses.loss(
+        (&listener.position, listener.depth),
+        (position, depth),
+        (layer, &environment.tuning),
+        || {
+            let range = listener.position.distance_to(position);
+            let mut loss = transmission_loss(range, &environment.tuning);
+            if let Some(layer) = layer {
+                if (listener.depth < layer) != (depth < layer) {
+                    loss += Tunable::LayerLoss.get(&environment.tuning);
+                }
+            }
+            loss + environment
+                .seafloor
+                .bottom_loss(&listener.position, position)
+        },
+    )
+}
+
+/// Best signal excess `listener` gets on its passive sonars on a sound of
+/// `level` dB made at `position` and `depth`
+pub fn excess_at(
+    environment: &Environment,
+    listener: &Entity,
+    position: &Point,
+    depth: f32,
+    level: f32,
+) -> Option<f32> {
+    excesses_at(environment, listener, position, depth, level)
+        .into_iter()
+        .map(|(_, e)| e)
+        .fold(None, |best: Option<f32>, e| {
+            Some(best.map_or(e, |b| b.max(e)))
+        })
+}
+
+/// Signal excess on each working passive sonar of `listener`, as for
+/// `excess_at`
+pub fn excesses_at(
+    environment: &Environment,
+    listener: &Entity,
+    position: &Point,
+    depth: f32,
+    level: f32,
+) -> Vec<(SensorKind, f32)> {
+    let context = SensorContext::new(listener, environment);
+    let received = level - propagation_loss(environment, listener, position, depth);
+    let background = db_sum(&[
+        ambient_noise(environment.sea_state),
+        noise::radiated_level(listener) - Tunable::SelfNoiseIsolation.get(&environment.tuning),
+    ]);
+    listener
+        .sensors
+        .iter()
+        .filter(|s| s.kind.is_passive_sonar() && s.is_operational(&context))
+        .filter(|s| !s.kind.is_baffled(listener, position))
+        .map(|s| {
+            let excess = s.signal_excess(received, background, &context)
+                + operators(listener, &environment.tuning);
+            (s.kind, excess)
+        })
+        .collect()
+}
+
+/// Signal excess of the echo `target` sends back to the hull sonar of
+/// `listener` of `pulse` sent at `level` dB, over the noise and the
+/// reverberation (see reverberation.rs); None without a working hull sonar
+/// or with the target in its baffles
+pub fn echo_excess(
+    environment: &Environment,
+    listener: &Entity,
+    target: &Entity,
+    level: f32,
+    pulse: &Pulse,
+) -> Option<f32> {
+    let context = SensorContext::new(listener, environment);
+    let sonar = listener
+        .sensors
+        .iter()
+        .find(|s| s.kind == SensorKind::HullSonar && s.is_operational(&context))
+        .filter(|s| !s.kind.is_baffled(listener, &target.position))?;
+    let range = listener.position.distance_to(&target.position);
+    let loss = propagation_loss(environment, listener, &target.position, target.depth)
+        + pulse.extra_absorption(range, &environment.tuning);
+    let incident =
+        level + pulse.energy() + pulse.gain(&listener.position, listener.heading, &target.position)
+            - 2.0 * loss;
+    let received = incident + Tunable::TargetStrength.get(&environment.tuning);
+    let background = db_sum(&[
+        ambient_noise(environment.sea_state),
+        noise::radiated_level(listener) - Tunable::SelfNoiseIsolation.get(&environment.tuning),
+        reverberation::level(
+            environment,
+            target,
+            range,
+            incident,
+            pulse.length.seconds(),
+            sonar.kind.beam_width() * pulse.band.beam(),
+        ),
+    ]);
+    Some(
+        sonar.signal_excess(received, background, &context)
+            + operators(listener, &environment.tuning),
+    )
+}
+
+/// dB the sonar operators of `listener` gain over the detection threshold,
+/// for their quality, the air they breathe and the hands to spare
+fn operators(listener: &Entity, tuning: &Tuning) -> f32 {
+    let dulled = 1.0 - listener.crew_performance();
+    listener.crew.detection_bonus() - Tunable::DetectionLoss.get(tuning) * dulled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calm() -> SensorContext {
+        SensorContext {
+            speed: 3.0 * KNOT,
+            ice_cover: 0.0,
+            transient: 0.0,
+        }
+    }
+
+    #[test]
+    fn pristine() {
+        let sonar = Sensor::new(SensorKind::HullSonar);
+        assert_eq!(sonar.total_penalty(&calm()), 0.0);
+        assert_eq!(sonar.signal_excess(80.0, 60.0, &calm()), 10.0);
+    }
+
+    #[test]
+    fn speed_washout() {
+        let sonar = Sensor::new(SensorKind::HullSonar);
+        let array = Sensor::new(SensorKind::TowedArray);
+        let fast = SensorContext {
+            speed: 15.0 * KNOT,
+            ..calm()
+        };
+        assert!((sonar.total_penalty(&fast) - 15.0).abs() < 0.01);
+        assert!((array.total_penalty(&fast) - 7.0).abs() < 0.01);
+        let periscope = Sensor::new(SensorKind::Periscope);
+        assert_eq!(periscope.total_penalty(&fast), 0.0);
+    }
+
+    #[test]
+    fn damage_degrades_then_kills() {
+        let mut sonar = Sensor::new(SensorKind::HullSonar);
+        sonar.health = 0.5;
+        assert_eq!(sonar.total_penalty(&calm()), 10.0);
+        sonar.health = 0.0;
+        assert!(!sonar.is_operational(&calm()));
+    }
+
+    #[test]
+    fn ice_and_transients() {
+        let sonar = Sensor::new(SensorKind::HullSonar);
+        let context = SensorContext {
+            ice_cover: 0.5,
+            transient: 110.0,
+            ..calm()
+        };
+        let penalties = sonar.penalties(&context);
+        assert!(penalties.contains(&(Modifier::Ice, 5.0)));
+        assert!(penalties.contains(&(Modifier::OwnTransients, 10.0)));
+    }
+
+    #[test]
+    fn passive_excess1() {
+        use crate::physics::Point;
+        use crate::world::EntityKind;
+        let environment = Environment::default();
+        let mut listener = Entity::new("a", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        let mut target = Entity::new("b", EntityKind::Merchant, Point { x: 0.0, y: 5000.0 });
+        target.speed = 5.0;
+        assert_eq!(passive_excess(&environment, &listener, &target), None);
+        listener.sensors.push(Sensor::new(SensorKind::HullSonar));
+        let above = passive_excess(&environment, &listener, &target).unwrap();
+        assert!(above > 0.0);
+        listener.depth = 150.0;
+        let below = passive_excess(&environment, &listener, &target).unwrap();
+        assert!((above - below - Tunable::LayerLoss.get(&Tuning::default())).abs() < 0.01);
+    }
+
+    #[test]
+    fn deaf_astern() {
+        use crate::physics::Point;
+        use crate::world::EntityKind;
+        let environment = Environment::default();
+        let mut listener = Entity::new("a", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        listener.sensors.push(Sensor::new(SensorKind::HullSonar));
+        let mut target = Entity::new("b", EntityKind::Merchant, Point { x: -3000.0, y: 0.0 });
+        target.speed = 5.0;
+        assert_eq!(passive_excess(&environment, &listener, &target), None);
+        assert_eq!(
+            echo_excess(&environment, &listener, &target, 220.0, &Pulse::default()),
+            None
+        );
+        target.position.y = 3000.0;
+        assert!(passive_excess(&environment, &listener, &target).is_some());
+        // the towed array hears astern
+        target.position.y = 0.0;
+        listener.sensors.push(Sensor::new(SensorKind::TowedArray));
+        let heard = excesses_at(&environment, &listener, &target.position, 0.0, 150.0);
+        assert_eq!(heard.len(), 1);
+        assert_eq!(heard[0].0, SensorKind::TowedArray);
+    }
+
+    #[test]
+    fn reverberation_blinds_in_shallow_water() {
+        use crate::physics::Point;
+        use crate::seafloor::{Bottom, Seafloor};
+        use crate::world::EntityKind;
+        use crate::zone::{Zone, ZoneKind};
+        let deep = Environment::default();
+        let mut listener = Entity::new("a", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        listener.sensors.push(Sensor::new(SensorKind::HullSonar));
+        let mut target = Entity::new("b", EntityKind::Submarine, Point { x: 5000.0, y: 0.0 });
+        target.depth = 30.0;
+        let echo = |environment: &Environment, target: &Entity| {
+            echo_excess(environment, &listener, target, 220.0, &Pulse::default()).unwrap()
+        };
+        assert!(echo(&deep, &target) > 0.0);
+        let bank = Zone {
+            name: "bank".to_string(),
+            kind: ZoneKind::Shallow,
+            points: vec![
+                Point {
+                    x: -1000.0,
+                    y: -1000.0,
+                },
+                Point {
+                    x: 9000.0,
+                    y: -1000.0,
+                },
+                Point {
+                    x: 9000.0,
+                    y: 1000.0,
+                },
+                Point {
+                    x: -1000.0,
+                    y: 1000.0,
+                },
+            ],
+            depth: Some(60.0),
+            bottom: Some(Bottom::Sand),
+        };
+        let shallow = Environment {
+            seafloor: Seafloor::chart(&[bank], Bottom::Sand),
+            ..Environment::default()
+        };
+        assert!(echo(&shallow, &target) < 0.0);
+        // close in the echo stands out of the clutter, unless the boat lies
+        // on the bottom
+        target.position.x = 1000.0;
+        assert!(echo(&shallow, &target) > 0.0);
+        // a short pulse in a narrow beam lights up less of the bottom
+        let short: Pulse = "short high".parse().unwrap();
+        let sharper = echo_excess(&shallow, &listener, &target, 220.0, &short).unwrap();
+        assert!(sharper > echo(&shallow, &target) + 5.0);
+        target.depth = 52.0;
+        assert!(echo(&shallow, &target) < 0.0);
+    }
+
+    #[test]
+    fn weak_signals_bear_worse() {
+        let sonar = SensorKind::HullSonar;
+        let tuning = Tuning::default();
+        assert_eq!(sonar.bearing_error(30.0, &tuning), 1.5_f32.to_radians());
+        let clear = Tunable::ClearSignal.get(&tuning);
+        let faint = sonar.bearing_error(0.0, &tuning) / sonar.bearing_error(clear, &tuning);
+        assert!((faint - 10_f32.sqrt()).abs() < 0.001);
+        let array = SensorKind::TowedArray;
+        assert!(array.bearing_error(5.0, &tuning) < sonar.bearing_error(5.0, &tuning));
+        assert_eq!(array.range_error(20.0, &tuning), None);
+        assert_eq!(SensorKind::Radar.range_error(20.0, &tuning), Some(0.02));
+    }
+}
@@ -0,0 +1,302 @@
+use crate::config::{Config, ConfigError};
+use crate::events::Event;
+use crate::sensors::passive_excess;
+use crate::world::{Entity, EntityId, World};
+
+// #############################
+// #         SHADOWING         #
+// #############################
+
+// Some missions are about watching, not sinking: a battle group trailed
+// for hours to report where it goes, without ever letting it know. Each is
+// a "[shadow.<name>]" section:
+//
+// [shadow.Kirov]
+// target = Kirov          # the vessel to trail, by its name in the scenario
+// by = U-99               # optional, the boat trailing it, the player's
+//                         # by default
+// hours = 4               # how long it must be trailed
+// min_range = 3000        # meters it must be kept beyond...
+// max_range = 15000       # ...and within
+// grace = 600             # optional, seconds it may be lost before the
+//                         # count starts over
+// closes = 43200          # optional, seconds into the scenario it must be
+//                         # done by
+//
+// The target counts as trailed while the sonars of the boat hold it and it
+// is within the range band. Losing it or straying out of the band for
+// longer than the grace breaks the trail, and the hours start over; the
+// longest trail is kept for the score. Being heard by the target or any
+// ship of its side fails the mission at once, as does running out of time.
+// Every outcome is an event, which the reports and the log follow.
+
+/// Seconds the trail may be broken for, unless set otherwise
+const GRACE: f32 = 600.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ShadowState {
+    /// `held` seconds trailed so far, for `out` seconds now lost
+    Trailing {
+        held: f32,
+        out: f32,
+    },
+    Completed,
+    /// Heard by the target or its side
+    Detected,
+    /// Out of time, or the target sunk
+    Failed,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Shadow {
+    pub name: String,
+    /// Name of the vessel to trail
+    pub target: String,
+    /// Name of the boat trailing it, None for the player's
+    pub by: Option<String>,
+    /// Seconds it must be trailed
+    pub required: f32,
+    /// Meters it must be kept between
+    pub min_range: f32,
+    pub max_range: f32,
+    /// Seconds the trail may be broken for
+    pub grace: f32,
+    /// Seconds into the scenario it must be done by
+    pub closes: Option<f32>,
+    /// Seconds of the longest trail so far
+    pub longest: f32,
+    pub state: ShadowState,
+}
+
+impl Shadow {
+    /// Reads every "[shadow.<name>]" section
+    pub fn read_all(config: &Config) -> Result<Vec<Shadow>, ConfigError> {
+        let mut all = Vec::new();
+        for (name, section) in config.sections_with_prefix("shadow") {
+            let min_range: f32 = section.parse("min_range")?;
+            let max_range: f32 = section.parse("max_range")?;
+            if max_range <= min_range {
+                return Err(ConfigError::Invalid {
+                    section: section.name.clone(),
+                    key: "max_range".to_string(),
+                    value: max_range.to_string(),
+                });
+            }
+            let hours: f32 = section.parse("hours")?;
+            all.push(Shadow {
+                name: name.to_string(),
+                target: section.parse("target")?,
+                by: section.get("by").map(|b| b.to_string()),
+                required: hours * 3600.0,
+                min_range,
+                max_range,
+                grace: section.parse_or("grace", GRACE)?,
+                closes: section.parse_optional("closes")?,
+                longest: 0.0,
+                state: ShadowState::Trailing {
+                    held: 0.0,
+                    out: 0.0,
+                },
+            });
+        }
+        Ok(all)
+    }
+
+    /// How much of the trail asked for was made, from 0 to 1
+    pub fn score(&self) -> f32 {
+        match self.state {
+            ShadowState::Completed => 1.0,
+            _ => (self.longest / self.required).min(1.0),
+        }
+    }
+
+    /// The target and the boat trailing it, while afloat
+    fn vessels<'a>(&self, world: &'a World) -> Option<(&'a Entity, &'a Entity)> {
+        let afloat = |name: &str| {
+            world
+                .entities
+                .iter()
+                .find(|e| e.name == name && !e.is_destroyed())
+        };
+        Some((afloat(&self.target)?, afloat(self.by.as_ref()?)?))
+    }
+}
+
+/// Whether `boat` holds `target` on its sonars within the range band of
+/// `shadow`
+fn trails(world: &World, shadow: &Shadow, target: &Entity, boat: &Entity) -> bool {
+    let range = boat.position.distance_to(&target.position);
+    shadow.min_range <= range
+        && range <= shadow.max_range
+        && passive_excess(&world.environment, boat, target).is_some_and(|e| e > 0.0)
+}
+
+/// Whether `target` or a ship of its side hears `boat`
+fn counter_detected(world: &World, target: &Entity, boat: &Entity) -> bool {
+    world
+        .entities
+        .iter()
+        .filter(|e| e.id == target.id || (target.side.is_some() && e.side == target.side))
+        .filter(|e| e.id != boat.id && !e.is_destroyed())
+        .any(|e| passive_excess(&world.environment, e, boat).is_some_and(|x| x > 0.0))
+}
+
+/// Moves the trails on, completing, breaking and failing them
+pub fn update(world: &mut World, dt: f32) {
+    let time = world.time;
+    let mut shadows = std::mem::take(&mut world.shadows);
+    for s in shadows.iter_mut() {
+        let (held, out) = match s.state {
+            ShadowState::Trailing { held, out } => (held, out),
+            _ => continue,
+        };
+        let (target, boat) = match s.vessels(world) {
+            Some(vessels) => vessels,
+            None => {
+                s.state = ShadowState::Failed;
+                world.emit(Event::TrailFailed {
+                    name: s.name.clone(),
+                });
+                continue;
+            }
+        };
+        let entity: EntityId = boat.id;
+        if counter_detected(world, target, boat) {
+            s.state = ShadowState::Detected;
+            world.emit(Event::TrailDetected {
+                entity,
+                name: s.name.clone(),
+            });
+        } else if trails(world, s, target, boat) {
+            let held = held + dt;
+            s.longest = s.longest.max(held);
+            if held >= s.required {
+                s.state = ShadowState::Completed;
+                world.emit(Event::TrailCompleted {
+                    entity,
+                    name: s.name.clone(),
+                });
+            } else {
+                s.state = ShadowState::Trailing { held, out: 0.0 };
+            }
+        } else if held > 0.0 && out + dt > s.grace {
+            s.state = ShadowState::Trailing {
+                held: 0.0,
+                out: 0.0,
+            };
+            world.emit(Event::TrailBroken {
+                entity,
+                name: s.name.clone(),
+            });
+        } else {
+            s.state = ShadowState::Trailing {
+                held,
+                out: out + dt,
+            };
+        }
+        if matches!(s.state, ShadowState::Trailing { .. }) && s.closes.is_some_and(|c| time >= c) {
+            s.state = ShadowState::Failed;
+            world.emit(Event::TrailFailed {
+                name: s.name.clone(),
+            });
+        }
+    }
+    world.shadows = shadows;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::world::EntityKind;
+
+    fn world(section: &str) -> World {
+        let config = Config::parse(&format!(
+            "[shadow.Kirov]\ntarget = Kirov\nby = U-99\nmin_range = 3000\n\
+             max_range = 15000\n{}",
+            section
+        ))
+        .unwrap();
+        let mut world = World::new();
+        world.shadows = Shadow::read_all(&config).unwrap();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 100.0;
+        boat.heading = std::f32::consts::FRAC_PI_2;
+        boat.sensors.push(Sensor::new(SensorKind::TowedArray));
+        world.spawn(boat);
+        let mut kirov = Entity::new("Kirov", EntityKind::Warship, Point { x: 0.0, y: 8000.0 });
+        kirov.speed = 8.0;
+        kirov.heading = std::f32::consts::FRAC_PI_2;
+        world.spawn(kirov);
+        world
+    }
+
+    /// Runs the trails for `seconds`, the vessels standing still
+    fn run(world: &mut World, seconds: u32) {
+        for _ in 0..seconds {
+            world.time += 1.0;
+            update(world, 1.0);
+        }
+    }
+
+    fn events(world: &World) -> Vec<&Event> {
+        world.events.iter().map(|e| &e.event).collect()
+    }
+
+    #[test]
+    fn trailed_long_enough() {
+        let mut world = world("hours = 0.5\ngrace = 60");
+        run(&mut world, 1000);
+        assert_eq!((world.shadows[0].score() * 100.0).round(), 56.0);
+        // out of the band for less than the grace, the count goes on
+        world.entity_mut(2).unwrap().position.y = 20_000.0;
+        run(&mut world, 30);
+        world.entity_mut(2).unwrap().position.y = 8000.0;
+        run(&mut world, 800);
+        assert_eq!(world.shadows[0].state, ShadowState::Completed);
+        assert_eq!(
+            events(&world),
+            vec![&Event::TrailCompleted {
+                entity: 1,
+                name: "Kirov".to_string()
+            }]
+        );
+        assert_eq!(world.shadows[0].score(), 1.0);
+    }
+
+    #[test]
+    fn broken_then_detected() {
+        let mut world = world("hours = 1\ngrace = 60\ncloses = 5000");
+        run(&mut world, 100);
+        world.entity_mut(2).unwrap().position.y = 20_000.0;
+        run(&mut world, 61);
+        assert!(matches!(
+            events(&world)[..],
+            [Event::TrailBroken { entity: 1, .. }]
+        ));
+        assert_eq!(world.shadows[0].longest, 100.0);
+        // the target turns its sonar on the boat
+        let kirov = world.entity_mut(2).unwrap();
+        kirov.position.y = 4000.0;
+        kirov.speed = 0.0;
+        kirov.sensors.push(Sensor::new(SensorKind::HullSonar));
+        kirov.heading = 0.0;
+        world.entity_mut(1).unwrap().speed = 10.0;
+        run(&mut world, 1);
+        assert_eq!(world.shadows[0].state, ShadowState::Detected);
+        assert!(matches!(
+            events(&world)[1],
+            Event::TrailDetected { entity: 1, .. }
+        ));
+
+        let mut late = self::world("hours = 4\ncloses = 100");
+        run(&mut late, 100);
+        assert_eq!(late.shadows[0].state, ShadowState::Failed);
+        assert!(Shadow::read_all(
+            &Config::parse("[shadow.x]\ntarget = a\nhours = 1\nmin_range = 5\nmax_range = 1")
+                .unwrap()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::chart::MarkShape;
+use crate::config::{Config, ConfigError};
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::world::Entity;
+
+// #############################
+// #      RADIO SIGNALS        #
+// #############################
+
+// Orders and intelligence reach the boat by radio during the mission, one
+// "[signal.<name>]" section each:
+//
+// [signal.HX-72]
+// at = 3600               # seconds into the scenario it is sent
+// text = HX-72 sails from Halifax 03:00, 9 knots, course 045
+// encrypted = true        # optional, read only once decrypted
+// mark = point 12000 4000 # optional, plotted when read, see chart.rs
+//
+// A signal is copied at periscope depth or shallower, so a boat deep when
+// it is sent gets it on coming up. An encrypted one then takes its crew a
+// while to break, the longer the greener and the more worn out they are,
+// before its text is shown and its mark plotted.
+
+/// A signal as the scenario gives it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Signal {
+    pub name: String,
+    /// Seconds into the scenario
+    pub at: f32,
+    pub text: String,
+    pub encrypted: bool,
+    pub mark: Option<MarkShape>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SignalState {
+    /// Not sent yet, or not copied
+    Waiting,
+    /// Copied, readable at the time given
+    Decrypting(f32),
+    Read,
+}
+
+/// What happened to a signal this tick
+#[derive(Debug, PartialEq, Clone)]
+pub enum Delivery {
+    /// Copied, being decrypted
+    Copied(Signal),
+    Read(Signal),
+}
+
+/// The signals of a scenario, and how far each has got
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Inbox {
+    pub signals: Vec<(Signal, SignalState)>,
+}
+
+impl Inbox {
+    /// Reads every "[signal.<name>]" section
+    pub fn read(config: &Config) -> Result<Inbox, ConfigError> {
+        let mut inbox = Inbox::default();
+        for (name, section) in config.sections_with_prefix("signal") {
+            let mark = match section.get("mark") {
+                Some(mark) => Some(mark.parse().map_err(|_| ConfigError::Invalid {
+                    section: section.name.clone(),
+                    key: "mark".to_string(),
+                    value: mark.to_string(),
+                })?),
+                None => None,
+            };
+            let signal = Signal {
+                name: name.to_string(),
+                at: section.parse("at")?,
+                text: section.parse("text")?,
+                encrypted: section.parse_or("encrypted", false)?,
+                mark,
+            };
+            inbox.signals.push((signal, SignalState::Waiting));
+        }
+        Ok(inbox)
+    }
+
+    /// Copies the signals sent by `time` if `boat` is shallow enough, and
+    /// reads those copied and decrypted
+    pub fn update(&mut self, time: f32, boat: &Entity) -> Vec<Delivery> {
+        let mut deliveries = Vec::new();
+        let copying = boat.depth <= PERISCOPE_DEPTH;
+        for (signal, state) in self.signals.iter_mut() {
+            if *state == SignalState::Waiting && copying && signal.at <= time {
+                if signal.encrypted {
+                    let breaking = boat.crew.decryption_time() / boat.crew_performance();
+                    *state = SignalState::Decrypting(time + breaking);
+                    deliveries.push(Delivery::Copied(signal.clone()));
+                    continue;
+                }
+                *state = SignalState::Read;
+                deliveries.push(Delivery::Read(signal.clone()));
+            }
+            if let SignalState::Decrypting(ready) = *state {
+                if ready <= time {
+                    *state = SignalState::Read;
+                    deliveries.push(Delivery::Read(signal.clone()));
+                }
+            }
+        }
+        deliveries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crew::CrewQuality;
+    use crate::physics::Point;
+    use crate::world::EntityKind;
+
+    const SIGNALS: &str = "
+[signal.orders]
+at = 0
+text = Patrol grid AJ 36
+
+[signal.HX-72]
+at = 100
+text = HX-72 sails 03:00
+encrypted = true
+mark = point 12000 4000
+";
+
+    fn names(deliveries: &[Delivery]) -> Vec<String> {
+        deliveries
+            .iter()
+            .map(|d| match d {
+                Delivery::Copied(s) => format!("copied {}", s.name),
+                Delivery::Read(s) => format!("read {}", s.name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn signals_are_copied_shallow() {
+        let mut inbox = Inbox::read(&Config::parse(SIGNALS).unwrap()).unwrap();
+        assert_eq!(
+            inbox.signals[1].0.mark,
+            Some(MarkShape::Point(Point {
+                x: 12000.0,
+                y: 4000.0
+            }))
+        );
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.depth = 100.0;
+        assert!(inbox.update(200.0, &boat).is_empty());
+        boat.depth = PERISCOPE_DEPTH;
+        assert_eq!(
+            names(&inbox.update(300.0, &boat)),
+            vec!["read orders", "copied HX-72"]
+        );
+        assert!(inbox.update(301.0, &boat).is_empty());
+        let ready = 300.0 + boat.crew.decryption_time();
+        assert_eq!(names(&inbox.update(ready, &boat)), vec!["read HX-72"]);
+        assert_eq!(inbox.signals[1].1, SignalState::Read);
+    }
+
+    #[test]
+    fn green_crews_decrypt_slower() {
+        assert!(CrewQuality::Green.decryption_time() > CrewQuality::Elite.decryption_time());
+        let bad = Config::parse("[signal.x]\nat = 0\ntext = t\nmark = circle").unwrap();
+        assert!(Inbox::read(&bad).is_err());
+    }
+}
@@ -0,0 +1,1583 @@
+use std::fmt;
+
+use crate::asw::{self, AswError};
+use crate::autopilot::{self, Autopilot, SprintDrift};
+use crate::baffles::BaffleWatch;
+use crate::camera::CameraFeed;
+use crate::casualties::DamageReport;
+use crate::chart::{Chart, ChartError, Mark, MarkShape};
+use crate::command::{
+    AswCommand, AutopilotCommand, ChartCommand, Command, ProfileCommand, TimerCommand,
+};
+use crate::contacts::{ContactChange, ContactTable};
+use crate::debrief::Recorder;
+use crate::decoy::{self, DecoyError};
+use crate::dive::{self, DiveError};
+use crate::events::Event;
+use crate::faction::Stance;
+use crate::governor::Governor;
+use crate::gunnery::{self, GunError};
+use crate::hazards;
+use crate::helicopter::{self, HeloError};
+use crate::helm::{Helm, HelmError};
+use crate::help;
+use crate::hfdf::{self, RadioError};
+use crate::history::History;
+use crate::identification;
+use crate::intercept::{self, Alert, EmissionKind};
+use crate::logbook::{LogError, PatrolLog};
+use crate::machinery::Machinery;
+use crate::messages::Catalog;
+use crate::narration;
+use crate::navigation::{Navigation, NavigationError};
+use crate::noise::{self, NoiseContributor, Rig};
+use crate::physics::{normalize_angle, turn_towards, user_to_game_angle, Point};
+use crate::preferences::Preferences;
+use crate::preview::{self, Estimate, Preview};
+use crate::seakeeping;
+use crate::signals::{Delivery, Inbox, SignalState};
+use crate::sound::{self, ContactSound, SoundEvent};
+use crate::stopwatch::{TimerError, Timers, PING_TIMER, TORPEDO_TIMER};
+use crate::stores::{self, Endurance, StoresError};
+use crate::torpedo;
+use crate::trace;
+use crate::transient::{self, TransientKind};
+use crate::tuning::Tunable;
+use crate::tutorial::Tutorial;
+use crate::units::Meters;
+use crate::vessel::VesselClass;
+use crate::weapons::WeaponError;
+use crate::wolfpack::{self, PackError};
+use crate::world::{Entity, EntityId, World};
+use crate::xbt::{self, XbtError, XbtReading};
+
+/// Meters the own ship moves between two points of its recorded track
+const TRACK_SPACING: f32 = 100.0;
+/// Meters from a waypoint at which the helm steers for the next one
+const WAYPOINT_REACHED: f32 = 150.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CommandError {
+    NoOwnShip,
+    NoWeapons,
+    Weapons(WeaponError),
+    Gun(GunError),
+    Asw(AswError),
+    Helo(HeloError),
+    Xbt(XbtError),
+    Radio(RadioError),
+    Navigation(NavigationError),
+    Dive(DiveError),
+    Stores(StoresError),
+    Decoy(DecoyError),
+    Chart(ChartError),
+    Log(LogError),
+    Timer(TimerError),
+    Pack(PackError),
+    Helm(HelmError),
+    NoRoute,
+    NoSuchContact(EntityId),
+}
+
+impl CommandError {
+    /// The error as written for the player, in the units of `preferences`
+    pub fn describe(&self, messages: &Catalog, preferences: &Preferences) -> String {
+        match self {
+            CommandError::NoOwnShip => messages.get("error-no-own-ship").to_string(),
+            CommandError::NoWeapons => messages.get("error-no-weapons").to_string(),
+            CommandError::Weapons(e) => e.describe(messages),
+            CommandError::Gun(e) => e.describe(messages),
+            CommandError::Asw(e) => e.describe(messages, preferences),
+            CommandError::Helo(e) => e.describe(messages),
+            CommandError::Xbt(e) => e.describe(messages),
+            CommandError::Radio(e) => e.describe(messages),
+            CommandError::Navigation(e) => e.describe(messages),
+            CommandError::Dive(e) => e.describe(messages),
+            CommandError::Stores(e) => e.describe(messages),
+            CommandError::Decoy(e) => e.describe(messages),
+            CommandError::Chart(e) => e.describe(messages),
+            CommandError::Log(e) => e.describe(messages),
+            CommandError::Timer(e) => e.describe(messages),
+            CommandError::Pack(e) => e.describe(messages),
+            CommandError::Helm(e) => e.describe(messages),
+            CommandError::NoRoute => messages.get("error-no-route").to_string(),
+            CommandError::NoSuchContact(id) => {
+                messages.format("error-no-such-contact", &[("target", id)])
+            }
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.describe(&Catalog::default(), &Preferences::default())
+        )
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<WeaponError> for CommandError {
+    fn from(e: WeaponError) -> Self {
+        CommandError::Weapons(e)
+    }
+}
+
+impl From<DiveError> for CommandError {
+    fn from(e: DiveError) -> Self {
+        CommandError::Dive(e)
+    }
+}
+
+impl From<StoresError> for CommandError {
+    fn from(e: StoresError) -> Self {
+        CommandError::Stores(e)
+    }
+}
+
+impl From<DecoyError> for CommandError {
+    fn from(e: DecoyError) -> Self {
+        CommandError::Decoy(e)
+    }
+}
+
+impl From<ChartError> for CommandError {
+    fn from(e: ChartError) -> Self {
+        CommandError::Chart(e)
+    }
+}
+
+impl From<GunError> for CommandError {
+    fn from(e: GunError) -> Self {
+        CommandError::Gun(e)
+    }
+}
+
+impl From<AswError> for CommandError {
+    fn from(e: AswError) -> Self {
+        CommandError::Asw(e)
+    }
+}
+
+impl From<HeloError> for CommandError {
+    fn from(e: HeloError) -> Self {
+        CommandError::Helo(e)
+    }
+}
+
+impl From<XbtError> for CommandError {
+    fn from(e: XbtError) -> Self {
+        CommandError::Xbt(e)
+    }
+}
+
+impl From<PackError> for CommandError {
+    fn from(e: PackError) -> Self {
+        CommandError::Pack(e)
+    }
+}
+
+impl From<HelmError> for CommandError {
+    fn from(e: HelmError) -> Self {
+        CommandError::Helm(e)
+    }
+}
+
+impl From<RadioError> for CommandError {
+    fn from(e: RadioError) -> Self {
+        CommandError::Radio(e)
+    }
+}
+
+impl From<TimerError> for CommandError {
+    fn from(e: TimerError) -> Self {
+        CommandError::Timer(e)
+    }
+}
+
+impl From<LogError> for CommandError {
+    fn from(e: LogError) -> Self {
+        CommandError::Log(e)
+    }
+}
+
+impl From<NavigationError> for CommandError {
+    fn from(e: NavigationError) -> Self {
+        CommandError::Navigation(e)
+    }
+}
+
+/// The world as seen from the boat the player commands
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub world: World,
+    pub player: EntityId,
+    /// Sound speed profiles measured so far, oldest first
+    pub xbt_readings: Vec<XbtReading>,
+    /// Training steps, for tutorial scenarios
+    pub tutorial: Option<Tutorial>,
+    /// Vessel classes of the scenario
+    pub classes: Vec<VesselClass>,
+    /// Text answers for the player (help pages...), oldest first;
+    /// consumers keep their own cursor
+    pub reports: Vec<String>,
+    /// Where the own ship has been, oldest first
+    pub track: History,
+    /// Waypoints the helm is steering along, next first
+    pub route: Vec<Point>,
+    /// Standing orders the own ship is under
+    pub autopilot: Autopilot,
+    /// Marks plotted by hand
+    pub chart: Chart,
+    /// What the own ship holds on its sensors, see contacts.rs
+    pub contacts: ContactTable,
+    /// The other vessels the player may command, see helm.rs
+    pub helms: Vec<Helm>,
+    /// Intercept alerts, oldest first; consumers keep their own cursor
+    pub alerts: Vec<Alert>,
+    /// Sources already alerted on, with how far they were classified
+    pub alerted: Vec<(EntityId, Option<EmissionKind>)>,
+    /// When the own ship last cleared its baffles, see baffles.rs
+    pub baffles: BaffleWatch,
+    /// How reports are written
+    pub preferences: Preferences,
+    /// Text of the reports, errors and mission
+    pub messages: Catalog,
+    /// Poses of the last two ticks, for the external view
+    pub camera: CameraFeed,
+    /// Sounds heard on the own ship, oldest first; consumers keep their
+    /// own cursor
+    pub sounds: Vec<SoundEvent>,
+    /// What the debrief is made of, see debrief.rs
+    pub recorder: Recorder,
+    /// Radio signals of the mission, see signals.rs
+    pub signals: Inbox,
+    /// Where the own ship reckons it is, see navigation.rs
+    pub navigation: Navigation,
+    /// The captain's log of the patrol, see logbook.rs
+    pub log: PatrolLog,
+    /// Stopwatches of the attack team, see stopwatch.rs
+    pub timers: Timers,
+    /// Time compression, see governor.rs
+    pub governor: Governor,
+    /// Whether the player was told the air is going foul
+    air_warned: bool,
+    /// Whether the player was told the machinery is worn
+    machinery_warned: bool,
+    /// Hazards the lookouts have reported
+    hazards_sighted: Vec<u32>,
+    /// Events already listened to for transients
+    transients_heard: usize,
+    /// Index into the world events of the first not yet checked for
+    /// rendezvous outcomes
+    rendezvous_followed: usize,
+    /// Events already reported on the helicopter of the own ship
+    helicopter_followed: usize,
+    /// Events already listened to for sounds
+    sounds_heard: usize,
+    /// Events already checked for torpedoes fired, to time their run
+    torpedoes_timed: usize,
+    /// Events already checked for the reports of the pack
+    pack_followed: usize,
+    /// Contact changes already reported
+    contacts_followed: usize,
+}
+
+impl Simulation {
+    pub fn new(world: World, player: EntityId) -> Simulation {
+        Simulation {
+            world,
+            player,
+            xbt_readings: Vec::new(),
+            tutorial: None,
+            classes: Vec::new(),
+            reports: Vec::new(),
+            track: History::default(),
+            route: Vec::new(),
+            autopilot: Autopilot::default(),
+            chart: Chart::default(),
+            contacts: ContactTable::default(),
+            helms: Vec::new(),
+            alerts: Vec::new(),
+            alerted: Vec::new(),
+            baffles: BaffleWatch::default(),
+            preferences: Preferences::default(),
+            messages: Catalog::default(),
+            camera: CameraFeed::default(),
+            sounds: Vec::new(),
+            recorder: Recorder::default(),
+            signals: Inbox::default(),
+            navigation: Navigation::default(),
+            log: PatrolLog::default(),
+            timers: Timers::default(),
+            governor: Governor::default(),
+            air_warned: false,
+            machinery_warned: false,
+            hazards_sighted: Vec::new(),
+            transients_heard: 0,
+            rendezvous_followed: 0,
+            helicopter_followed: 0,
+            sounds_heard: 0,
+            torpedoes_timed: 0,
+            pack_followed: 0,
+            contacts_followed: 0,
+        }
+    }
+
+    pub fn own_ship(&self) -> Option<&Entity> {
+        self.world.entity(self.player)
+    }
+
+    /// Vessel class of the own ship, when the scenario defines it
+    pub fn own_class(&self) -> Option<&VesselClass> {
+        let name = self.own_ship()?.class.as_ref()?;
+        self.classes.iter().find(|c| &c.name == name)
+    }
+
+    pub fn own_ship_mut(&mut self) -> Option<&mut Entity> {
+        self.world.entity_mut(self.player)
+    }
+
+    /// Carries out a player command on the own ship
+    pub fn execute(&mut self, command: &Command) -> Result<(), CommandError> {
+        self.carry_out(command)?;
+        if let Some(tutorial) = self.tutorial.as_mut() {
+            tutorial.observe(command);
+        }
+        Ok(())
+    }
+
+    fn carry_out(&mut self, command: &Command) -> Result<(), CommandError> {
+        match command {
+            Command::Preset(command) => {
+                let ship = self.own_ship_mut().ok_or(CommandError::NoOwnShip)?;
+                let station = ship.weapons.as_mut().ok_or(CommandError::NoWeapons)?;
+                Ok(station.execute(command)?)
+            }
+            Command::Gun(command) => Ok(gunnery::execute(&mut self.world, self.player, command)?),
+            Command::Fire { tube, bearing } => {
+                self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                let preview = self.preview(*tube, *bearing);
+                let bearing = user_to_game_angle(*bearing);
+                let id = torpedo::fire(&mut self.world, self.player, *tube, bearing)?;
+                if let Some(preview) = preview {
+                    self.recorder.previews.push((id, preview));
+                }
+                Ok(())
+            }
+            Command::Asw(order) => {
+                self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                asw::execute(&mut self.world, self.player, order)?;
+                let report = match order {
+                    AswCommand::Ping(None) => None,
+                    AswCommand::Ping(Some(true)) => Some(self.messages.get("sonar-pinging").into()),
+                    AswCommand::Ping(Some(false)) => {
+                        Some(self.messages.get("sonar-passive").into())
+                    }
+                    AswCommand::Pulse(pulse) => Some(
+                        self.messages
+                            .format("sonar-pulse", &[("pulse", &pulse.to_string())]),
+                    ),
+                    AswCommand::Charges(depth) => {
+                        let depth = self.preferences.units.depth(*depth);
+                        Some(
+                            self.messages
+                                .format("pattern-dropped", &[("depth", &depth)]),
+                        )
+                    }
+                    AswCommand::Vds(Some(depth)) => {
+                        let depth = self.preferences.units.depth(*depth);
+                        Some(self.messages.format("vds-streamed", &[("depth", &depth)]))
+                    }
+                    AswCommand::Vds(None) => Some(self.messages.get("vds-recovered").into()),
+                    AswCommand::Mortar => Some(self.messages.get("mortar-fired").into()),
+                };
+                self.reports.extend(report);
+                Ok(())
+            }
+            Command::Helo(task) => {
+                self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                helicopter::execute(&mut self.world, self.player, task)?;
+                let task = command.to_string();
+                let text = self.messages.format("helo-tasked", &[("task", &task)]);
+                self.reports.push(text);
+                Ok(())
+            }
+            Command::LaunchXbt => {
+                self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                let reading = xbt::launch(&mut self.world, self.player)?;
+                self.xbt_readings.push(reading);
+                Ok(())
+            }
+            Command::Report => {
+                self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                let contacts = self.contacts.contacts.len();
+                let seconds = hfdf::report_length(contacts);
+                hfdf::transmit(&mut self.world, self.player, seconds)?;
+                let text = self.messages.format(
+                    "report-sent",
+                    &[("contacts", &contacts), ("seconds", &seconds)],
+                );
+                self.reports.push(text);
+                Ok(())
+            }
+            Command::Shadow(number) => {
+                let own = self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                let reckoned = self.navigation.position(&own.position);
+                let position = wolfpack::position_of(&self.contacts, &reckoned, *number)?;
+                wolfpack::report(&mut self.world, self.player, position)?;
+                let text = self.messages.format("shadow-sent", &[("number", number)]);
+                self.reports.push(text);
+                Ok(())
+            }
+            Command::Helm(name) => {
+                let id = self
+                    .world
+                    .entities
+                    .by_name(name)
+                    .map(|e| e.id)
+                    .ok_or_else(|| HelmError::NoSuchVessel(name.clone()))?;
+                self.take_helm(id)?;
+                let text = self.messages.format("helm-taken", &[("name", name)]);
+                self.reports.push(text);
+                Ok(())
+            }
+            Command::Fix => {
+                let off = self.navigation.fix(&self.world, self.player)?;
+                let off = self.preferences.units.range(Meters(off));
+                let text = self.messages.format("fix-taken", &[("off", &off)]);
+                self.reports.push(text);
+                Ok(())
+            }
+            Command::Decoy(command) => Ok(decoy::execute(&mut self.world, self.player, command)?),
+            Command::Dive(kind) => {
+                let dive_time = self
+                    .own_class()
+                    .map_or(dive::DEFAULT_DIVE_TIME, |c| c.dive_time);
+                Ok(dive::dive(&mut self.world, self.player, *kind, dive_time)?)
+            }
+            Command::Surface => Ok(dive::surface(&mut self.world, self.player)?),
+            Command::Planes(auto) => {
+                self.own_ship_mut()
+                    .ok_or(CommandError::NoOwnShip)?
+                    .depth_assist = *auto;
+                Ok(())
+            }
+            Command::Refit => Ok(stores::refit(&mut self.world, self.player)?),
+            Command::Identify(id) => {
+                let ship = self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                let target = self
+                    .world
+                    .entity(*id)
+                    .filter(|t| t.id != ship.id && !t.is_destroyed())
+                    .ok_or(CommandError::NoSuchContact(*id))?;
+                let identification = identification::identify(&self.world, ship, target);
+                let text = identification.describe(&self.messages, *id);
+                self.reports.push(text);
+                Ok(())
+            }
+            Command::Door { tube, open } => {
+                let ship = self.own_ship_mut().ok_or(CommandError::NoOwnShip)?;
+                if ship.rig == Rig::UltraQuiet {
+                    return Err(WeaponError::RiggedForUltraQuiet.into());
+                }
+                let station = ship.weapons.as_mut().ok_or(CommandError::NoWeapons)?;
+                let tube = station
+                    .tubes
+                    .tube_mut(*tube)
+                    .ok_or(WeaponError::NoSuchTube(*tube))?;
+                let flooding = *open && !tube.door_open;
+                tube.door_open = *open;
+                if flooding {
+                    transient::make(&mut self.world, self.player, TransientKind::TubeFlooding);
+                }
+                Ok(())
+            }
+            Command::Rig(rig) => {
+                self.own_ship_mut().ok_or(CommandError::NoOwnShip)?.rig = *rig;
+                Ok(())
+            }
+            Command::Course { x, y } => {
+                let ship = self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                let to = Point { x: x.0, y: y.0 };
+                self.route = self.world.route(ship, &to).ok_or(CommandError::NoRoute)?;
+                self.autopilot.sprint_drift = None;
+                Ok(())
+            }
+            Command::Autopilot(command) => {
+                self.own_ship().ok_or(CommandError::NoOwnShip)?;
+                match command {
+                    AutopilotCommand::BelowLayer => self.autopilot.below_layer = true,
+                    AutopilotCommand::Sprint { x, y, minutes } => {
+                        let waypoint = Point { x: x.0, y: y.0 };
+                        self.autopilot.sprint_drift =
+                            Some(SprintDrift::new(waypoint, minutes * 60.0));
+                        self.route.clear();
+                    }
+                    AutopilotCommand::Off => self.autopilot = Autopilot::default(),
+                }
+                Ok(())
+            }
+            Command::Mark(mark) => {
+                self.chart.place(mark.clone());
+                Ok(())
+            }
+            Command::Unmark(name) => Ok(self.chart.remove(name)?),
+            Command::Chart(ChartCommand::Save(path)) => {
+                self.chart.save(path).map_err(ChartError::from)?;
+                Ok(())
+            }
+            Command::Chart(ChartCommand::Load(path)) => {
+                let chart = Chart::load(path).map_err(ChartError::from)?;
+                self.chart.merge(chart);
+                Ok(())
+            }
+            Command::SaveLog(path) => {
+                let boat = self.own_ship().map_or("", |s| s.name.as_str());
+                let environment = &self.world.environment;
+                Ok(self.log.save(path, boat, environment, &self.preferences)?)
+            }
+            Command::Tracker(tracker) => {
+                self.contacts.select(*tracker);
+                Ok(())
+            }
+            Command::Profile(ProfileCommand::Start) => {
+                trace::start_profiling();
+                Ok(())
+            }
+            Command::Profile(ProfileCommand::Report) => {
+                let report = trace::profile_report()
+                    .unwrap_or_else(|| self.messages.get("profile-off").to_string());
+                self.reports.push(report);
+                Ok(())
+            }
+            Command::Set(setting) => {
+                self.preferences.set(*setting);
+                Ok(())
+            }
+            Command::Signals => {
+                let mut lines = Vec::new();
+                for (signal, state) in &self.signals.signals {
+                    let line = match state {
+                        SignalState::Waiting => continue,
+                        SignalState::Decrypting(ready) => self.messages.format(
+                            "signal-decrypting",
+                            &[
+                                ("name", &signal.name),
+                                ("seconds", &(ready - self.world.time).max(0.0).ceil()),
+                            ],
+                        ),
+                        SignalState::Read => self.messages.format(
+                            "signal-read",
+                            &[("name", &signal.name), ("text", &signal.text)],
+                        ),
+                    };
+                    lines.push(line);
+                }
+                if lines.is_empty() {
+                    lines.push(self.messages.get("no-signals").to_string());
+                }
+                self.reports.push(lines.join("\n"));
+                Ok(())
+            }
+            Command::Timer(TimerCommand::Start(name)) => {
+                self.timers.start(name, self.world.time);
+                Ok(())
+            }
+            Command::Timer(TimerCommand::Stop(name)) => {
+                self.timers.stop(name, self.world.time)?;
+                Ok(())
+            }
+            Command::Timer(TimerCommand::Clear(name)) => Ok(self.timers.clear(name)?),
+            Command::Timers => {
+                let mut lines = self.timers.describe(&self.messages, self.world.time);
+                if lines.is_empty() {
+                    lines.push(self.messages.get("no-timers").to_string());
+                }
+                self.reports.push(lines.join("\n"));
+                Ok(())
+            }
+            Command::Describe(readout) => {
+                let lines = narration::describe(self, *readout);
+                self.reports.push(lines.join("\n"));
+                Ok(())
+            }
+            Command::Compress(steps) => {
+                self.governor.compress(*steps);
+                Ok(())
+            }
+            Command::Continue => Ok(()),
+            Command::Help(topic) => {
+                let text = help::page(self, topic)?;
+                self.reports.push(text);
+                Ok(())
+            }
+        }
+    }
+
+    /// What the noise monitoring station hears of the own ship, loudest
+    /// first
+    pub fn noise_report(&self) -> Vec<NoiseContributor> {
+        self.own_ship().map(noise::contributors).unwrap_or_default()
+    }
+
+    /// How the contact `id` stands to the own ship, as far as the player
+    /// knows: ships of the own side are known, others only once the
+    /// lookouts or the periscope have them in sight, and then as what they
+    /// are taken for (see identification.rs)
+    pub fn stance_of(&self, id: EntityId) -> Stance {
+        let (own, other) = match (self.own_ship(), self.world.entity(id)) {
+            (Some(own), Some(other)) => (own, other),
+            _ => return Stance::Unknown,
+        };
+        let stance = self.world.diplomacy.stance(own, other);
+        let range = own.position.distance_to(&other.position);
+        let sighted = gunnery::exposure(own) > 0.0
+            && gunnery::exposure(other) > 0.0
+            && range <= seakeeping::sighting_range(own, &self.world.environment);
+        if stance == Stance::Friendly {
+            stance
+        } else if sighted {
+            identification::apparent_stance(&self.world, own, other)
+        } else {
+            Stance::Unknown
+        }
+    }
+
+    /// The shot of the torpedo in `tube` on `bearing` (user angle,
+    /// degrees) run on paper against the tracked contact it passes
+    /// closest, see preview.rs; None without a torpedo in the tube or a
+    /// contact tracked
+    pub fn preview(&self, tube: usize, bearing: f32) -> Option<Preview> {
+        let own = self.own_ship()?;
+        let station = own.weapons.as_ref()?;
+        let tube = station.tubes.tube(tube).filter(|t| t.loaded)?;
+        let bearing = user_to_game_angle(bearing);
+        self.contacts
+            .contacts
+            .iter()
+            .filter(|c| !c.lost)
+            .filter_map(|c| c.track.as_ref())
+            .map(|track| {
+                preview::predict(
+                    &own.position,
+                    bearing,
+                    &Estimate::from_track(track, self.world.time),
+                    &tube.settings,
+                    station.guidance,
+                    &station.reliability,
+                )
+            })
+            .min_by(|a, b| a.miss_distance.total_cmp(&b.miss_distance))
+    }
+
+    /// What the sonar operator hears listening to contact `number` on the
+    /// headphones: the ship on its bearing, see sound.rs; None when the
+    /// contact is not held or nothing is heard there
+    pub fn contact_sound(&self, number: u32) -> Option<ContactSound> {
+        let own = self.own_ship()?;
+        let contact = self
+            .contacts
+            .contacts
+            .iter()
+            .find(|c| c.number == number && !c.lost)?;
+        let off = |e: &Entity| {
+            normalize_angle(own.position.angle_to(&e.position) - contact.bearing).abs()
+        };
+        self.world
+            .entities
+            .iter()
+            .filter(|e| e.id != own.id && !e.is_destroyed())
+            .filter(|e| contact.covers(own.position.angle_to(&e.position)))
+            .filter_map(|e| Some((off(e), sound::contact(&self.world.environment, own, e)?)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, sound)| sound)
+    }
+
+    /// What the damage control station reports of the own ship
+    pub fn damage_report(&self) -> Option<DamageReport> {
+        self.own_ship().map(DamageReport::new)
+    }
+
+    /// How long the own ship's stores last at `speed` (m/s), for planning
+    /// a route; None when stores are not kept
+    pub fn endurance(&self, speed: f32) -> Option<Endurance> {
+        let ship = self.own_ship()?;
+        let stores = ship.stores.as_ref()?;
+        let sea_state = self.world.environment.sea_state;
+        Some(stores.endurance(speed, sea_state, ship.is_surfaced()))
+    }
+
+    /// Wear and shock of the own ship's machinery since its last overhaul;
+    /// None when not tracked
+    pub fn machinery(&self) -> Option<&Machinery> {
+        self.own_ship()?.machinery.as_ref()
+    }
+
+    /// What the tutorial asks the player to do now
+    pub fn instruction(&self) -> Option<&str> {
+        self.tutorial
+            .as_ref()
+            .and_then(|t| t.step())
+            .map(|s| self.messages.text(&s.text))
+    }
+
+    /// Turns the own ship towards the next waypoint of its route, and
+    /// carries out the autopilot orders
+    fn steer(&mut self, dt: f32) {
+        let position = match self.own_ship() {
+            Some(ship) => ship.position.clone(),
+            None => return,
+        };
+        if self.autopilot.is_engaged() {
+            let max_speed = self
+                .own_class()
+                .map_or(self.own_ship().unwrap().speed, |c| c.max_speed);
+            let ship = self.world.entity(self.player).unwrap();
+            let orders = self
+                .autopilot
+                .orders(&self.world.environment, ship, max_speed, dt);
+            let safe_depth = self.world.safe_depth(ship);
+            let tuning = self.world.environment.tuning.clone();
+            autopilot::drive(
+                self.own_ship_mut().unwrap(),
+                &orders,
+                safe_depth,
+                &tuning,
+                dt,
+            );
+        }
+        while self
+            .route
+            .first()
+            .is_some_and(|w| w.distance_to(&position) < WAYPOINT_REACHED)
+        {
+            self.route.remove(0);
+        }
+        if let Some(waypoint) = self.route.first() {
+            let desired = position.angle_to(waypoint);
+            let turn = Tunable::TurnRate.get(&self.world.environment.tuning) * dt;
+            let ship = self.own_ship_mut().unwrap();
+            ship.heading = turn_towards(ship.heading, desired, turn);
+        }
+    }
+
+    /// Raises an alert for every new pulse the intercept receiver picks up,
+    /// and again once it is classified
+    fn listen(&mut self) {
+        let (heard, heading) = match self.own_ship() {
+            Some(ship) => (intercept::intercepts(&self.world, ship), ship.heading),
+            None => return,
+        };
+        for intercept in heard {
+            self.sounds.extend(sound::ping(&self.world, &intercept));
+            if intercept.kind == Some(EmissionKind::ActiveSonar) {
+                self.timers.start(PING_TIMER, self.world.time);
+            }
+            let key = (intercept.source, intercept.kind);
+            if !self.alerted.contains(&key) {
+                self.alerted.push(key);
+                self.alerts.push(Alert {
+                    time: self.world.time,
+                    heading,
+                    intercept,
+                });
+            }
+        }
+    }
+
+    /// Reports the transients the own ship heard since last time, and its
+    /// own broaching
+    fn hear_transients(&mut self) {
+        let events = &self.world.events[self.transients_heard..];
+        self.transients_heard = self.world.events.len();
+        let own = match self.world.entity(self.player) {
+            Some(own) => own,
+            None => return,
+        };
+        for timed in events {
+            if timed.event == (Event::Broached { entity: own.id }) {
+                self.reports.push(self.messages.get("broached").to_string());
+            }
+            if let Event::TorpedoPassed {
+                target, distance, ..
+            } = timed.event
+            {
+                if target == own.id {
+                    let range = self.preferences.units.range(Meters(distance));
+                    let text = self.messages.format("torpedo-passed", &[("range", &range)]);
+                    self.reports.push(text);
+                }
+            }
+            if let Event::Transient { entity, kind } = timed.event {
+                let report = self
+                    .world
+                    .entity(entity)
+                    .and_then(|source| transient::hear(&self.world, own, source, kind));
+                if let Some(report) = report {
+                    let text = report.describe(&self.messages, &self.preferences, own.heading);
+                    self.reports.push(text);
+                }
+            }
+        }
+    }
+
+    /// Collects the sounds the own ship heard since last time
+    fn hear_sounds(&mut self) {
+        let events = &self.world.events[self.sounds_heard..];
+        self.sounds_heard = self.world.events.len();
+        if let Some(own) = self.world.entity(self.player) {
+            for timed in events {
+                self.sounds.extend(sound::cues(&self.world, own, timed));
+            }
+        }
+    }
+
+    /// Warns once the air of the own ship goes foul, and again the next
+    /// time after it was aired out
+    fn check_air(&mut self) {
+        let air = match self.own_ship().and_then(|s| s.atmosphere.as_ref()) {
+            Some(air) => air,
+            None => return,
+        };
+        if !air.is_foul() {
+            self.air_warned = false;
+        } else if !self.air_warned {
+            let co2 = format!("{:.1}", air.co2 * 100.0);
+            let text = self.messages.format("air-foul", &[("co2", &co2)]);
+            self.reports.push(text);
+            self.air_warned = true;
+        }
+    }
+
+    /// Tells the player once the wear of the machinery is heard, until it
+    /// is overhauled
+    fn check_machinery(&mut self) {
+        let machinery = match self.machinery() {
+            Some(machinery) => machinery,
+            None => return,
+        };
+        if !machinery.is_worn() {
+            self.machinery_warned = false;
+        } else if !self.machinery_warned {
+            let hours = format!("{:.0}", machinery.running_hours);
+            let text = self.messages.format("machinery-worn", &[("hours", &hours)]);
+            self.reports.push(text);
+            self.machinery_warned = true;
+        }
+    }
+
+    /// Reports the hazards adrift the lookouts or the periscope sight for
+    /// the first time
+    fn sight_hazards(&mut self) {
+        let own = match self.world.entity(self.player) {
+            Some(own) => own,
+            None => return,
+        };
+        for hazard in &self.world.hazards {
+            let range = own.position.distance_to(&hazard.position);
+            if self.hazards_sighted.contains(&hazard.id)
+                || range > hazards::sighting_range(&self.world, own, hazard.kind)
+            {
+                continue;
+            }
+            self.hazards_sighted.push(hazard.id);
+            let bearing = self
+                .preferences
+                .bearing(own.position.angle_to(&hazard.position), own.heading);
+            let text = self.messages.format(
+                "hazard-sighted",
+                &[
+                    ("kind", &self.messages.get(hazard.kind.message())),
+                    ("bearing", &bearing),
+                    ("range", &self.preferences.units.range(Meters(range))),
+                ],
+            );
+            self.reports.push(text);
+        }
+    }
+
+    /// Reports how the transfers at the rendezvous went, see rendezvous.rs,
+    /// and the trails of the shadowing missions, see shadowing.rs
+    fn follow_rendezvous(&mut self) {
+        let events = &self.world.events[self.rendezvous_followed..];
+        self.rendezvous_followed = self.world.events.len();
+        for timed in events {
+            let (id, name) = match &timed.event {
+                Event::TransferInterrupted { entity, name } if *entity == self.player => {
+                    ("rendezvous-interrupted", name)
+                }
+                Event::MissionCompleted { entity, name } if *entity == self.player => {
+                    ("rendezvous-completed", name)
+                }
+                Event::MissionFailed { name } => ("rendezvous-failed", name),
+                Event::Resupplied { entity, name } if *entity == self.player => {
+                    ("rendezvous-resupplied", name)
+                }
+                Event::TrailCompleted { entity, name } if *entity == self.player => {
+                    ("trail-completed", name)
+                }
+                Event::TrailBroken { entity, name } if *entity == self.player => {
+                    ("trail-broken", name)
+                }
+                Event::TrailDetected { entity, name } if *entity == self.player => {
+                    ("trail-detected", name)
+                }
+                Event::TrailFailed { name } => ("trail-failed", name),
+                _ => continue,
+            };
+            let text = self.messages.format(id, &[("name", name)]);
+            self.reports.push(text);
+        }
+    }
+
+    /// Reports what the helicopter of the own ship found and how it fares,
+    /// see helicopter.rs
+    fn follow_helicopter(&mut self) {
+        let events = &self.world.events[self.helicopter_followed..];
+        self.helicopter_followed = self.world.events.len();
+        let own = match self.world.entity(self.player) {
+            Some(own) => own,
+            None => return,
+        };
+        for timed in events {
+            let text = match &timed.event {
+                Event::HelicopterDipped {
+                    owner,
+                    datum: Some(datum),
+                } if *owner == own.id => {
+                    let bearing = self
+                        .preferences
+                        .bearing(own.position.angle_to(datum), own.heading);
+                    let range = Meters(own.position.distance_to(datum));
+                    let range = self.preferences.units.range(range);
+                    self.messages
+                        .format("helo-contact", &[("bearing", &bearing), ("range", &range)])
+                }
+                Event::HelicopterDipped { owner, datum: None } if *owner == own.id => {
+                    self.messages.get("helo-clear").to_string()
+                }
+                Event::HelicopterBingo { owner } if *owner == own.id => {
+                    self.messages.get("helo-bingo").to_string()
+                }
+                Event::HelicopterLanded { owner } if *owner == own.id => {
+                    self.messages.get("helo-landed").to_string()
+                }
+                Event::HelicopterDitched { owner } if *owner == own.id => {
+                    self.messages.get("helo-ditched").to_string()
+                }
+                _ => continue,
+            };
+            self.reports.push(text);
+        }
+    }
+
+    /// Starts the torpedo timer over for every torpedo the own ship fired,
+    /// and moves the tutorial on when it waits for a timer
+    fn run_timers(&mut self) {
+        let events = &self.world.events[self.torpedoes_timed..];
+        self.torpedoes_timed = self.world.events.len();
+        let fired = events.iter().rev().find(|timed| {
+            matches!(timed.event, Event::TorpedoFired { shooter, .. } if shooter == self.player)
+        });
+        if let Some(timed) = fired {
+            self.timers.start(TORPEDO_TIMER, timed.time);
+        }
+        if let Some(tutorial) = self.tutorial.as_mut() {
+            tutorial.watch(&self.timers, self.world.time);
+        }
+    }
+
+    /// Reports and plots the contacts the rest of the pack radioed, and
+    /// when it is ordered in, see wolfpack.rs
+    fn follow_pack(&mut self) {
+        let events = &self.world.events[self.pack_followed..];
+        self.pack_followed = self.world.events.len();
+        let own = match self.world.entity(self.player) {
+            Some(own) => own,
+            None => return,
+        };
+        for timed in events {
+            let text = match &timed.event {
+                Event::PackReported { from } if *from != self.player => {
+                    let report = self
+                        .world
+                        .wolfpack
+                        .reports
+                        .iter()
+                        .rev()
+                        .find(|r| r.from == *from);
+                    let (report, boat) = match (report, self.world.entity(*from)) {
+                        (Some(report), Some(boat)) => (report, boat),
+                        _ => continue,
+                    };
+                    self.chart.place(Mark {
+                        name: boat.name.replace(' ', "-"),
+                        shape: MarkShape::Point(report.position.clone()),
+                    });
+                    let bearing = self
+                        .preferences
+                        .bearing(own.position.angle_to(&report.position), own.heading);
+                    let range = own.position.distance_to(&report.position);
+                    self.messages.format(
+                        "pack-report",
+                        &[
+                            ("boat", &boat.name),
+                            ("bearing", &bearing),
+                            ("range", &self.preferences.units.range(Meters(range))),
+                        ],
+                    )
+                }
+                Event::PackConverging { position } => {
+                    let bearing = self
+                        .preferences
+                        .bearing(own.position.angle_to(position), own.heading);
+                    self.messages
+                        .format("pack-converging", &[("bearing", &bearing)])
+                }
+                _ => continue,
+            };
+            self.reports.push(text);
+        }
+    }
+
+    /// Advises clearing the baffles when the own ship has gone too long
+    /// without
+    fn watch_baffles(&mut self) {
+        let own = match self.world.entity(self.player) {
+            Some(own) => own,
+            None => return,
+        };
+        if self.baffles.update(own, self.world.time) {
+            let minutes = format!("{:.0}", self.baffles.minutes_since(self.world.time));
+            let text = self
+                .messages
+                .format("baffles-overdue", &[("minutes", &minutes)]);
+            self.reports.push(text);
+        }
+    }
+
+    /// Reports the contacts gained, held anew, lost and dropped, and the
+    /// ones gained in the baffles just cleared
+    fn follow_contacts(&mut self) {
+        let changes =
+            &self.contacts.changes[self.contacts_followed.min(self.contacts.changes.len())..];
+        for update in changes {
+            let number = &update.number;
+            let text = match update.change {
+                ContactChange::Gained => self
+                    .messages
+                    .format("contact-gained", &[("number", number)]),
+                ContactChange::Updated => {
+                    let sensors: Vec<String> = self
+                        .contacts
+                        .contacts
+                        .iter()
+                        .find(|c| c.number == update.number)
+                        .map(|c| c.sensors.iter().map(|s| s.to_string()).collect())
+                        .unwrap_or_default();
+                    self.messages.format(
+                        "contact-updated",
+                        &[("number", number), ("sensors", &sensors.join(", "))],
+                    )
+                }
+                ContactChange::Lost => self.messages.format("contact-lost", &[("number", number)]),
+                ContactChange::Archived => self
+                    .messages
+                    .format("contact-archived", &[("number", number)]),
+            };
+            self.reports.push(text);
+            let trailing = update.change == ContactChange::Gained
+                && self
+                    .contacts
+                    .contacts
+                    .iter()
+                    .find(|c| c.number == update.number)
+                    .is_some_and(|c| self.baffles.trailing(c.bearing, update.time));
+            if trailing {
+                let text = self
+                    .messages
+                    .format("baffles-trailer", &[("number", number)]);
+                self.reports.push(text);
+            }
+        }
+        self.contacts_followed = self.contacts.changes.len();
+    }
+
+    /// Copies and reads the signals due, plotting their marks
+    fn read_signals(&mut self) {
+        let deliveries = match self.world.entity(self.player) {
+            Some(boat) => self.signals.update(self.world.time, boat),
+            None => return,
+        };
+        for delivery in deliveries {
+            let text = match delivery {
+                Delivery::Copied(signal) => self
+                    .messages
+                    .format("signal-copied", &[("name", &signal.name)]),
+                Delivery::Read(signal) => {
+                    if let Some(shape) = signal.mark.clone() {
+                        self.chart.place(Mark {
+                            name: signal.name.clone(),
+                            shape,
+                        });
+                    }
+                    self.messages.format(
+                        "signal-read",
+                        &[("name", &signal.name), ("text", &signal.text)],
+                    )
+                }
+            };
+            self.reports.push(text);
+        }
+    }
+
+    /// Advances the world, unless a tutorial step holds it
+    pub fn step(&mut self, dt: f32) {
+        if self.tutorial.as_ref().is_some_and(|t| t.is_paused()) {
+            return;
+        }
+        let _span = trace::span("tick", &[("time", &self.world.time)]);
+        self.sense(dt);
+        let mut helms = std::mem::take(&mut self.helms);
+        for helm in helms.iter_mut() {
+            helm.swap(self);
+            self.sense(dt);
+            helm.swap(self);
+        }
+        self.helms = helms;
+        self.advance(dt);
+    }
+
+    /// Takes the helm of `entity`, one of the vessels the player may
+    /// command, the vessel left carrying on with its route and autopilot
+    pub fn take_helm(&mut self, entity: EntityId) -> Result<(), HelmError> {
+        let name = match self.world.entity(entity) {
+            Some(vessel) => vessel.name.clone(),
+            None => return Err(HelmError::NoSuchVessel(entity.to_string())),
+        };
+        if entity == self.player {
+            return Ok(());
+        }
+        let index = self
+            .helms
+            .iter()
+            .position(|h| h.entity == entity)
+            .ok_or(HelmError::NotControllable(name.clone()))?;
+        if self.world.entity(entity).unwrap().is_destroyed() {
+            return Err(HelmError::Lost(name));
+        }
+        let mut helm = self.helms.remove(index);
+        helm.swap(self);
+        self.helms.push(helm);
+        Ok(())
+    }
+
+    /// Runs one tick of the front end: as many steps as the compression
+    /// asks for, stopping short when the governor drops it
+    pub fn run(&mut self, dt: f32) {
+        for _ in 0..self.governor.compression {
+            self.step(dt);
+            let dropped =
+                self.governor
+                    .check(&self.world, self.player, &self.contacts, &self.alerts);
+            if let Some(trigger) = dropped {
+                let reason = self.messages.get(trigger.message());
+                let text = self.messages.format("governor", &[("reason", &reason)]);
+                self.reports.push(text);
+                break;
+            }
+        }
+    }
+
+    /// The part of a tick done for each boat commanded: steers it and
+    /// updates what it holds (see captain.rs)
+    pub fn sense(&mut self, dt: f32) {
+        self.steer(dt);
+        let _span = trace::span("sensors", &[]);
+        self.listen();
+        self.contacts.update(&self.world, self.player);
+    }
+
+    /// The rest of a tick, once for the whole world
+    pub fn advance(&mut self, dt: f32) {
+        self.world.step(dt);
+        self.navigation.update(dt);
+        let _events = trace::span("events", &[]);
+        self.hear_transients();
+        self.hear_sounds();
+        self.check_air();
+        self.check_machinery();
+        self.read_signals();
+        self.sight_hazards();
+        self.follow_rendezvous();
+        self.follow_helicopter();
+        self.follow_pack();
+        self.watch_baffles();
+        self.follow_contacts();
+        self.run_timers();
+        self.log
+            .record(&self.world, self.player, &self.navigation, &self.messages);
+        self.recorder.record(&self.world, self.player);
+        self.camera.push(&self.world);
+        if let Some(position) = self.own_ship().map(|s| s.position.clone()) {
+            let moved = self
+                .track
+                .last()
+                .is_none_or(|last| last.distance_to(&position) >= TRACK_SPACING);
+            if moved {
+                self.track.push(position);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atmosphere::Atmosphere;
+    use crate::crew::CrewQuality;
+    use crate::gunnery::Gun;
+    use crate::machinery;
+    use crate::physics::Point;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+    use crate::world::EntityKind;
+    use crate::zone::{Zone, ZoneKind};
+
+    fn boat() -> Simulation {
+        let mut world = World::new();
+        let mut sub = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        sub.gun = Some(Gun::deck_gun());
+        sub.weapons = Some(WeaponsStation::new(4, PresetLibrary::new()));
+        let player = world.spawn(sub);
+        Simulation::new(world, player)
+    }
+
+    #[test]
+    fn execute_commands() {
+        let mut sim = boat();
+        sim.execute(&Command::parse("preset save default 1").unwrap())
+            .unwrap();
+        sim.execute(&Command::parse("gun man").unwrap()).unwrap();
+        assert_eq!(
+            sim.execute(&Command::parse("gun target nearest").unwrap()),
+            Err(CommandError::Gun(GunError::NoTargetInSight))
+        );
+        assert!(sim.own_ship().unwrap().gun.as_ref().unwrap().manned);
+        assert_eq!(
+            sim.execute(&Command::LaunchXbt),
+            Err(CommandError::Xbt(XbtError::NoneLeft))
+        );
+        sim.own_ship_mut().unwrap().xbts = 2;
+        sim.execute(&Command::LaunchXbt).unwrap();
+        assert_eq!(sim.xbt_readings.len(), 1);
+    }
+
+    #[test]
+    fn quiet_the_boat() {
+        let mut sim = boat();
+        sim.own_ship_mut().unwrap().depth = 100.0;
+        sim.execute(&Command::parse("door open 1").unwrap())
+            .unwrap();
+        let loud = noise::radiated_level(sim.own_ship().unwrap());
+        assert!(sim
+            .noise_report()
+            .iter()
+            .any(|c| c.source == noise::NoiseSource::OpenDoors));
+        sim.execute(&Command::parse("door close 1").unwrap())
+            .unwrap();
+        sim.execute(&Command::parse("rig ultra").unwrap()).unwrap();
+        assert!(noise::radiated_level(sim.own_ship().unwrap()) < loud);
+        assert_eq!(
+            sim.execute(&Command::parse("fire 1 90").unwrap()),
+            Err(CommandError::Weapons(WeaponError::RiggedForUltraQuiet))
+        );
+        sim.own_ship_mut().unwrap().speed = 10.0;
+        sim.step(1.0);
+        assert_eq!(sim.own_ship().unwrap().speed, noise::ULTRA_QUIET_MAX_SPEED);
+    }
+
+    #[test]
+    fn worn_machinery_is_reported() {
+        let mut sim = boat();
+        assert_eq!(sim.machinery(), None);
+        let worn = Machinery {
+            running_hours: machinery::WORN_HOURS - 0.1,
+            shock: 0.0,
+        };
+        sim.own_ship_mut().unwrap().machinery = Some(worn);
+        sim.own_ship_mut().unwrap().speed = 4.0;
+        sim.advance(600.0);
+        assert!(sim.machinery().unwrap().is_worn());
+        assert!(sim
+            .noise_report()
+            .iter()
+            .any(|c| c.source == noise::NoiseSource::WornMachinery));
+        let worn = sim.messages.format("machinery-worn", &[("hours", &"200")]);
+        assert_eq!(sim.reports.iter().filter(|r| **r == worn).count(), 1);
+        sim.advance(600.0);
+        assert_eq!(sim.reports.iter().filter(|r| **r == worn).count(), 1);
+    }
+
+    #[test]
+    fn course_around_land() {
+        let mut sim = boat();
+        sim.world.zones.push(Zone {
+            name: "island".to_string(),
+            kind: ZoneKind::Land,
+            points: vec![
+                Point {
+                    x: -500.0,
+                    y: 1000.0,
+                },
+                Point {
+                    x: 500.0,
+                    y: 1000.0,
+                },
+                Point { x: 0.0, y: 2000.0 },
+            ],
+            depth: None,
+            bottom: None,
+        });
+        assert_eq!(
+            sim.execute(&Command::parse("course 0 1500").unwrap()),
+            Err(CommandError::NoRoute)
+        );
+        sim.execute(&Command::parse("course 0 4000").unwrap())
+            .unwrap();
+        assert!(sim.route.len() >= 2);
+        sim.own_ship_mut().unwrap().speed = 8.0;
+        for _ in 0..1200 {
+            sim.step(1.0);
+            let ship = sim.own_ship().unwrap();
+            assert!(sim.world.zones_at(&ship.position).next().is_none());
+        }
+        let ship = sim.own_ship().unwrap();
+        assert!(sim.route.is_empty());
+        assert!(ship.position.y > 4000.0);
+    }
+
+    #[test]
+    fn profile_report() {
+        let mut sim = boat();
+        let report = Command::parse("profile").unwrap();
+        sim.execute(&report).unwrap();
+        assert_eq!(sim.reports, vec!["not profiling, start with 'profile on'"]);
+        sim.execute(&Command::parse("profile on").unwrap()).unwrap();
+        for _ in 0..3 {
+            sim.step(1.0);
+        }
+        sim.execute(&report).unwrap();
+        trace::uninstall();
+        let spans: Vec<&str> = sim.reports[1]
+            .lines()
+            .filter_map(|l| l.split_whitespace().next())
+            .collect();
+        for span in ["tick", "sensors", "movement", "ai", "events"] {
+            assert!(spans.contains(&span), "{}", sim.reports[1]);
+        }
+    }
+
+    #[test]
+    fn intercept_alerts() {
+        let mut sim = boat();
+        sim.own_ship_mut()
+            .unwrap()
+            .sensors
+            .push(Sensor::new(SensorKind::InterceptReceiver));
+        let escort = sim.world.spawn(Entity::new(
+            "escort",
+            EntityKind::Warship,
+            Point { x: 5000.0, y: 0.0 },
+        ));
+        for _ in 0..3 {
+            sim.world.ping(escort);
+            sim.step(1.0);
+        }
+        assert_eq!(sim.alerts.len(), 1);
+        assert_eq!(
+            sim.alerts[0].to_string(),
+            "00:00 INTERCEPT active sonar bearing 090"
+        );
+    }
+
+    #[test]
+    fn transients_heard() {
+        let mut sim = boat();
+        sim.own_ship_mut()
+            .unwrap()
+            .sensors
+            .push(Sensor::new(SensorKind::HullSonar));
+        let mut other = Entity::new("S-13", EntityKind::Submarine, Point { x: 0.0, y: -500.0 });
+        other.crew = CrewQuality::Elite;
+        let other = sim.world.spawn(other);
+        sim.step(1.0);
+        sim.world.entity_mut(other).unwrap().depth = 30.0;
+        sim.step(1.0);
+        let transients = |sim: &Simulation| {
+            sim.reports
+                .iter()
+                .filter(|r| r.starts_with("transient"))
+                .cloned()
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(transients(&sim), vec!["transient bearing 180, hatch slam"]);
+        // our own noises are not reported
+        sim.execute(&Command::parse("door open 1").unwrap())
+            .unwrap();
+        sim.step(1.0);
+        assert_eq!(transients(&sim).len(), 1);
+        assert!(sim.own_ship().unwrap().transient > 0.0);
+    }
+
+    #[test]
+    fn contacts_unknown_until_sighted() {
+        let mut sim = boat();
+        let far = Entity::new(
+            "SS Clan",
+            EntityKind::Merchant,
+            Point {
+                x: 0.0,
+                y: 30_000.0,
+            },
+        );
+        let far = sim.world.spawn(far);
+        assert_eq!(sim.stance_of(far), Stance::Unknown);
+        sim.world.entity_mut(far).unwrap().position.y = 5_000.0;
+        assert_eq!(sim.stance_of(far), Stance::Hostile);
+        sim.own_ship_mut().unwrap().depth = 40.0;
+        assert_eq!(sim.stance_of(far), Stance::Unknown);
+    }
+
+    #[test]
+    fn warned_of_foul_air() {
+        let mut sim = boat();
+        let mut air = Atmosphere::new(0.0, 0, false);
+        air.co2 = 0.0199;
+        let ship = sim.own_ship_mut().unwrap();
+        ship.depth = 60.0;
+        ship.atmosphere = Some(air);
+        for _ in 0..60 {
+            sim.step(60.0);
+        }
+        assert_eq!(
+            sim.reports,
+            vec!["air is going foul, 2.0% CO2: snorkel or surface"]
+        );
+    }
+
+    #[test]
+    fn translated() {
+        let config = crate::config::Config::parse(
+            "[messages]\n\
+             transient-classified = Transiente {bearing}, {kind}\n\
+             transient-hatch-slam = Luke\n\
+             error-no-such-tube = kein Rohr {tube}\n\
+             welcome = Willkommen an Bord\n\
+             [tutorial.1]\ntext = @welcome\n",
+        )
+        .unwrap();
+        let mut sim = boat();
+        sim.messages = Catalog::read(config.section("messages").unwrap());
+        sim.tutorial = Tutorial::read(&config).unwrap();
+        assert_eq!(sim.instruction(), Some("Willkommen an Bord"));
+        let error = sim
+            .execute(&Command::parse("door open 9").unwrap())
+            .unwrap_err();
+        assert_eq!(
+            error.describe(&sim.messages, &sim.preferences),
+            "kein Rohr 9"
+        );
+        let report = transient::TransientReport {
+            source: 2,
+            bearing: user_to_game_angle(90.0),
+            kind: Some(TransientKind::HatchSlam),
+        };
+        assert_eq!(
+            report.describe(&sim.messages, &sim.preferences, 0.0),
+            "Transiente 090, Luke"
+        );
+    }
+
+    #[test]
+    fn sounds_of_a_launch() {
+        let mut sim = boat();
+        sim.execute(&Command::parse("fire 1 90").unwrap()).unwrap();
+        sim.step(1.0);
+        let cues: Vec<sound::Cue> = sim.sounds.iter().map(|s| s.cue).collect();
+        assert_eq!(cues, vec![sound::Cue::TorpedoInWater]);
+        assert!(sim.sounds[0].intensity > 0.3);
+    }
+
+    #[test]
+    fn listen_to_a_contact() {
+        let mut sim = boat();
+        let own = sim.own_ship_mut().unwrap();
+        own.sensors.push(Sensor::new(SensorKind::HullSonar));
+        own.depth = 20.0;
+        let mut ship = Entity::new("Ship", EntityKind::Merchant, Point { x: 0.0, y: 3000.0 });
+        ship.speed = 1.5;
+        let ship = sim.world.spawn(ship);
+        assert_eq!(sim.contact_sound(1), None);
+        sim.step(1.0);
+        let number = sim.contacts.contacts[0].number;
+        let heard = sim.contact_sound(number).unwrap();
+        assert_eq!(heard.source, ship);
+        assert!(heard.blade_rate > 0.0);
+        assert_eq!(sim.contact_sound(number + 1), None);
+    }
+
+    #[test]
+    fn clearing_the_baffles_finds_a_trailer() {
+        let mut sim = boat();
+        let own = sim.own_ship_mut().unwrap();
+        own.sensors.push(Sensor::new(SensorKind::HullSonar));
+        own.depth = 60.0;
+        let mut trailer = Entity::new("U-552", EntityKind::Submarine, Point { x: -2000.0, y: 0.0 });
+        trailer.depth = 60.0;
+        trailer.speed = 4.0;
+        sim.world.spawn(trailer);
+        sim.step(1.0);
+        assert!(sim.contacts.contacts.is_empty());
+        sim.world.time = 900.0;
+        sim.step(1.0);
+        assert!(sim
+            .reports
+            .last()
+            .unwrap()
+            .starts_with("baffles not cleared for 15 minutes"));
+        sim.own_ship_mut().unwrap().heading = 70_f32.to_radians();
+        sim.step(1.0);
+        let number = sim.contacts.contacts[0].number;
+        assert_eq!(
+            sim.reports.last().unwrap(),
+            &format!(
+                "contact S{} gained in the baffles just cleared: may be trailing",
+                number
+            )
+        );
+    }
+
+    #[test]
+    fn tutorial_holds_the_world() {
+        let config =
+            crate::config::Config::parse("[tutorial.1]\ntext = Man the gun.\nwait_for = gun man\n")
+                .unwrap();
+        let mut sim = boat();
+        sim.tutorial = Tutorial::read(&config).unwrap();
+        assert_eq!(sim.instruction(), Some("Man the gun."));
+        sim.step(10.0);
+        assert_eq!(sim.world.time, 0.0);
+        assert!(sim
+            .execute(&Command::parse("gun target 9").unwrap())
+            .is_err());
+        sim.execute(&Command::parse("gun man").unwrap()).unwrap();
+        assert_eq!(sim.instruction(), None);
+        sim.step(10.0);
+        assert_eq!(sim.world.time, 10.0);
+    }
+}
@@ -0,0 +1,353 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::sensors::passive_excess;
+use crate::simulation::Simulation;
+use crate::world::{EntityId, EntityKind};
+
+// #############################
+// #         SNAPSHOTS         #
+// #############################
+
+// The outcome of a run, kept as text so a change of gameplay shows up as a
+// diff: where every entity ended, who hears whom, and the events:
+//
+// time 600
+// entity 1 -450.0 5143.8 0.0 1.00 Merchant 1
+// hears 8 1
+// event 312.0 TorpedoFired { shooter: 8, torpedo: 9 }
+//
+// Entity lines are id, x, y, depth, hull then the name. The scenario tests
+// (tests/scenarios.rs) compare runs against stored snapshots, allowing
+// for the small differences a refactoring may make to the numbers, those
+// written inside events included: an event matches when its words agree
+// and its fractional numbers are within tolerance.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntitySnapshot {
+    pub id: EntityId,
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub depth: f32,
+    pub hull: f32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    /// Seconds into the scenario
+    pub time: f32,
+    pub entities: Vec<EntitySnapshot>,
+    /// (listener, target) pairs, for the targets the listener hears
+    pub detections: Vec<(EntityId, EntityId)>,
+    /// Time and description of each event, oldest first
+    pub events: Vec<(f32, String)>,
+}
+
+/// How far numbers may drift from a snapshot before it counts as changed
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tolerance {
+    /// Meters
+    pub position: f32,
+    /// Meters
+    pub depth: f32,
+    /// Fraction of the hull
+    pub hull: f32,
+    /// Seconds
+    pub time: f32,
+    /// Fraction of a fractional number within an event, of at least 1
+    pub event: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            position: 5.0,
+            depth: 1.0,
+            hull: 0.01,
+            time: 1.0,
+            event: 0.01,
+        }
+    }
+}
+
+/// Whether two event descriptions agree: the same words, whole numbers
+/// (ids, counts) alike, fractional numbers within `tolerance`
+fn same_event(expected: &str, found: &str, tolerance: f32) -> bool {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| c.is_whitespace() || ",:{}()[]".contains(c))
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (expected, found) = (words(expected), words(found));
+    expected.len() == found.len()
+        && expected.iter().zip(&found).all(|(e, f)| {
+            let fractional = |w: &str| w.contains('.').then(|| w.parse::<f32>().ok()).flatten();
+            match (fractional(e), fractional(f)) {
+                (Some(e), Some(f)) => (e - f).abs() <= tolerance * e.abs().max(1.0),
+                _ => e == f,
+            }
+        })
+}
+
+impl Snapshot {
+    pub fn take(sim: &Simulation) -> Snapshot {
+        let world = &sim.world;
+        let entities = world
+            .entities
+            .iter()
+            .map(|e| EntitySnapshot {
+                id: e.id,
+                name: e.name.clone(),
+                x: e.position.x,
+                y: e.position.y,
+                depth: e.depth,
+                hull: e.hull,
+            })
+            .collect();
+        let mut detections = Vec::new();
+        for listener in world.entities.iter().filter(|e| !e.is_destroyed()) {
+            for target in world.entities.iter() {
+                if target.id == listener.id || target.kind == EntityKind::Torpedo {
+                    continue;
+                }
+                let heard = passive_excess(&world.environment, listener, target);
+                if heard.is_some_and(|excess| excess > 0.0) {
+                    detections.push((listener.id, target.id));
+                }
+            }
+        }
+        Snapshot {
+            time: world.time,
+            entities,
+            detections,
+            events: world
+                .events
+                .iter()
+                .map(|e| (e.time, format!("{:?}", e.event)))
+                .collect(),
+        }
+    }
+
+    /// Differences from `self`, the snapshot expected, to `actual`; empty
+    /// when they agree within `tolerance`
+    pub fn diff(&self, actual: &Snapshot, tolerance: &Tolerance) -> Vec<String> {
+        let mut differences = Vec::new();
+        if (self.time - actual.time).abs() > tolerance.time {
+            differences.push(format!("time {} became {}", self.time, actual.time));
+        }
+        for expected in &self.entities {
+            let found = match actual.entities.iter().find(|e| e.id == expected.id) {
+                Some(found) => found,
+                None => {
+                    differences.push(format!("entity {} is missing", expected.name));
+                    continue;
+                }
+            };
+            let moved = ((expected.x - found.x).powi(2) + (expected.y - found.y).powi(2)).sqrt();
+            if moved > tolerance.position {
+                differences.push(format!(
+                    "{} ended at {:.1} {:.1}, not {:.1} {:.1}",
+                    expected.name, found.x, found.y, expected.x, expected.y
+                ));
+            }
+            if (expected.depth - found.depth).abs() > tolerance.depth {
+                differences.push(format!(
+                    "{} ended at depth {:.1}, not {:.1}",
+                    expected.name, found.depth, expected.depth
+                ));
+            }
+            if (expected.hull - found.hull).abs() > tolerance.hull {
+                differences.push(format!(
+                    "{} ended with hull {:.2}, not {:.2}",
+                    expected.name, found.hull, expected.hull
+                ));
+            }
+        }
+        for extra in actual
+            .entities
+            .iter()
+            .filter(|e| !self.entities.iter().any(|x| x.id == e.id))
+        {
+            differences.push(format!("entity {} is new", extra.name));
+        }
+        for pair in &self.detections {
+            if !actual.detections.contains(pair) {
+                differences.push(format!("{} no longer hears {}", pair.0, pair.1));
+            }
+        }
+        for pair in &actual.detections {
+            if !self.detections.contains(pair) {
+                differences.push(format!("{} now hears {}", pair.0, pair.1));
+            }
+        }
+        for (i, (expected, found)) in self.events.iter().zip(&actual.events).enumerate() {
+            if !same_event(&expected.1, &found.1, tolerance.event)
+                || (expected.0 - found.0).abs() > tolerance.time
+            {
+                differences.push(format!(
+                    "event {} is {} at {}, not {} at {}",
+                    i, found.1, found.0, expected.1, expected.0
+                ));
+            }
+        }
+        if self.events.len() != actual.events.len() {
+            differences.push(format!(
+                "{} events, not {}",
+                actual.events.len(),
+                self.events.len()
+            ));
+        }
+        differences
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "time {}", self.time)?;
+        for e in &self.entities {
+            writeln!(
+                f,
+                "entity {} {:.1} {:.1} {:.1} {:.2} {}",
+                e.id, e.x, e.y, e.depth, e.hull, e.name
+            )?;
+        }
+        for (listener, target) in &self.detections {
+            writeln!(f, "hears {} {}", listener, target)?;
+        }
+        for (time, event) in &self.events {
+            writeln!(f, "event {:.1} {}", time, event)?;
+        }
+        Ok(())
+    }
+}
+
+fn number<T: FromStr>(word: Option<&str>, line: &str) -> Result<T, String> {
+    word.and_then(|w| w.parse().ok())
+        .ok_or_else(|| format!("bad snapshot line '{}'", line))
+}
+
+impl FromStr for Snapshot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Snapshot, String> {
+        let mut snapshot = Snapshot {
+            time: 0.0,
+            entities: Vec::new(),
+            detections: Vec::new(),
+            events: Vec::new(),
+        };
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let mut words = line.splitn(2, ' ');
+            let rest = words.next().zip(words.next());
+            match rest {
+                Some(("time", rest)) => snapshot.time = number(Some(rest), line)?,
+                Some(("entity", rest)) => {
+                    let mut words = rest.splitn(6, ' ');
+                    snapshot.entities.push(EntitySnapshot {
+                        id: number(words.next(), line)?,
+                        x: number(words.next(), line)?,
+                        y: number(words.next(), line)?,
+                        depth: number(words.next(), line)?,
+                        hull: number(words.next(), line)?,
+                        name: words.next().unwrap_or_default().to_string(),
+                    });
+                }
+                Some(("hears", rest)) => {
+                    let mut words = rest.split(' ');
+                    let listener = number(words.next(), line)?;
+                    snapshot
+                        .detections
+                        .push((listener, number(words.next(), line)?));
+                }
+                Some(("event", rest)) => {
+                    let (time, event) = rest
+                        .split_once(' ')
+                        .ok_or_else(|| format!("bad snapshot line '{}'", line))?;
+                    snapshot
+                        .events
+                        .push((number(Some(time), line)?, event.to_string()));
+                }
+                _ => return Err(format!("bad snapshot line '{}'", line)),
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use crate::physics::Point;
+    use crate::sensors::{Sensor, SensorKind};
+    use crate::world::{Entity, World};
+
+    fn sim() -> Simulation {
+        let mut world = World::new();
+        let mut boat = Entity::new("U 99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.sensors.push(Sensor::new(SensorKind::HullSonar));
+        // submerged, out of sight of the merchant's lookouts
+        boat.depth = 50.0;
+        let player = world.spawn(boat);
+        let mut merchant = Entity::new(
+            "SS Empire",
+            EntityKind::Merchant,
+            Point { x: 3000.0, y: 0.0 },
+        );
+        merchant.speed = 5.0;
+        world.spawn(merchant);
+        let mut sim = Simulation::new(world, player);
+        for _ in 0..10 {
+            sim.step(1.0);
+        }
+        sim.world.emit(Event::Destroyed { entity: 2 });
+        sim
+    }
+
+    #[test]
+    fn round_trip() {
+        let snapshot = Snapshot::take(&sim());
+        assert_eq!(snapshot.detections, vec![(1, 2)]);
+        let text = snapshot.to_string();
+        assert!(text.contains("entity 2 3050.0 0.0 0.0 1.00 SS Empire\n"));
+        let read: Snapshot = text.parse().unwrap();
+        assert_eq!(read.entities[1].name, "SS Empire");
+        assert!(read.diff(&snapshot, &Tolerance::default()).is_empty());
+        assert!("entity 1 x".parse::<Snapshot>().is_err());
+    }
+
+    #[test]
+    fn differences() {
+        let expected = Snapshot::take(&sim());
+        let mut actual = expected.clone();
+        actual.entities[1].x += 3.0;
+        actual.entities[1].y += 3.0;
+        assert!(expected.diff(&actual, &Tolerance::default()).is_empty());
+        actual.entities[1].y += 10.0;
+        actual.detections.clear();
+        actual.events.clear();
+        assert_eq!(
+            expected.diff(&actual, &Tolerance::default()),
+            vec![
+                "SS Empire ended at 3053.0 13.0, not 3050.0 0.0",
+                "1 no longer hears 2",
+                "0 events, not 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn events_within_tolerance() {
+        let tolerance = Tolerance::default().event;
+        let shot = "Shot { shooter: 1, target: 2, range: 1500.0, bearing: 0.7853 }";
+        let drifted = "Shot { shooter: 1, target: 2, range: 1500.4, bearing: 0.7856 }";
+        assert!(same_event(shot, drifted, tolerance));
+        let far = "Shot { shooter: 1, target: 2, range: 1530.0, bearing: 0.7853 }";
+        assert!(!same_event(shot, far, tolerance));
+        let other = "Shot { shooter: 1, target: 3, range: 1500.0, bearing: 0.7853 }";
+        assert!(!same_event(shot, other, tolerance));
+        assert!(!same_event(shot, "Destroyed { entity: 2 }", tolerance));
+    }
+}
@@ -0,0 +1,425 @@
+use std::fmt;
+
+use crate::acoustics::transmission_loss;
+use crate::environment::Environment;
+use crate::events::{Event, TimedEvent};
+use crate::intercept::Intercept;
+use crate::noise;
+use crate::physics::Point;
+use crate::sensors::passive_excess;
+use crate::transient::TransientKind;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+#[cfg(feature = "audio")]
+pub mod player;
+
+// #############################
+// #       SOUND EVENTS        #
+// #############################
+
+// What the crew of the own ship hears, as cues a frontend can put sounds
+// to: a ping on the hull, a torpedo starting its run, the hull creaking,
+// water coming in, a distant explosion. The simulation only says what was
+// heard, where and how loud; Simulation::sounds collects the cues and the
+// "audio" feature adds a reference player (see sound/player.rs).
+//
+// The sonar operator may also put on the headphones and listen to one
+// contact: the broadband hiss of its screw, beating with the blades as
+// they turn, thrashing once it cavitates, and the clicks of its transients
+// and of damaged machinery knocking once a turn of the shaft. A trained ear
+// tells the slow beat of a merchant from the quick one of a warship and
+// the whine of a torpedo; ContactSound says what there is to hear and the
+// player puts a sound to it.
+
+/// Meters a screw advances by per turn, by the kind of vessel: large slow
+/// screws on merchants, small fast ones on torpedoes
+fn advance(kind: EntityKind) -> f32 {
+    match kind {
+        EntityKind::Merchant => 3.0,
+        EntityKind::Warship => 2.5,
+        EntityKind::Submarine => 2.0,
+        EntityKind::Torpedo => 0.5,
+    }
+}
+
+/// Blades of the screw, by the kind of vessel
+fn blades(kind: EntityKind) -> u32 {
+    match kind {
+        EntityKind::Merchant => 4,
+        EntityKind::Warship => 3,
+        EntityKind::Submarine => 5,
+        EntityKind::Torpedo => 8,
+    }
+}
+
+/// Received level in dB at which a sound is barely heard through the hull
+const FAINTEST: f32 = 60.0;
+/// Received level in dB at which a sound is as loud as it gets
+const LOUDEST: f32 = 160.0;
+/// Signal excess in dB of a contact barely heard over the sea
+const FAINTEST_CONTACT: f32 = -10.0;
+/// Signal excess in dB of a contact as loud as it gets
+const LOUDEST_CONTACT: f32 = 30.0;
+/// How strongly the hiss of a screw beats with its blades, and once it
+/// cavitates
+const BLADE_MODULATION: f32 = 0.3;
+const CAVITATION_MODULATION: f32 = 0.8;
+/// Clicks per second of a vessel making a transient
+const TRANSIENT_CLICKS: f32 = 3.0;
+/// Intercept signal excess in dB of a ping as loud as it gets
+const LOUDEST_PING: f32 = 40.0;
+/// Source level in dB of a warhead going off
+const EXPLOSION_LEVEL: f32 = 220.0;
+/// Level in dB of the sea rushing into a holed hull, heard from inside
+const FLOODING_LEVEL: f32 = 150.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Cue {
+    /// An active pulse on the hull
+    PingReceived,
+    /// A torpedo starting its run
+    TorpedoInWater,
+    HullCreak,
+    Flooding,
+    Explosion,
+}
+
+impl fmt::Display for Cue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Cue::PingReceived => "ping",
+            Cue::TorpedoInWater => "torpedo in water",
+            Cue::HullCreak => "hull creak",
+            Cue::Flooding => "flooding",
+            Cue::Explosion => "explosion",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A sound heard on the own ship
+#[derive(Debug, PartialEq, Clone)]
+pub struct SoundEvent {
+    /// Seconds into the scenario
+    pub time: f32,
+    pub cue: Cue,
+    /// Entity the sound came from
+    pub source: EntityId,
+    pub position: Point,
+    pub depth: f32,
+    /// How loud it is heard, from 0 (barely) to 1
+    pub intensity: f32,
+}
+
+/// What the sonar operator hears of one vessel through the headphones
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContactSound {
+    /// Entity heard
+    pub source: EntityId,
+    /// How loud it is over the hiss of the sea, from 0 (lost in it) to 1
+    pub intensity: f32,
+    /// Blades passing per second, the beat of the hiss; 0 when stopped
+    pub blade_rate: f32,
+    /// How strongly the hiss beats with the blades, from 0 to 1
+    pub modulation: f32,
+    /// Clicks per second of transients and knocking machinery
+    pub clicks: f32,
+}
+
+/// What `listener` hears of `target` on its passive sonars; None when it
+/// has none working or does not hear it at all
+pub fn contact(
+    environment: &Environment,
+    listener: &Entity,
+    target: &Entity,
+) -> Option<ContactSound> {
+    let excess = passive_excess(environment, listener, target)?;
+    let intensity = (excess - FAINTEST_CONTACT) / (LOUDEST_CONTACT - FAINTEST_CONTACT);
+    if intensity <= 0.0 {
+        return None;
+    }
+    let shaft_rate = target.speed.max(0.0) / advance(target.kind);
+    let cavitating =
+        target.kind != EntityKind::Torpedo && target.speed > noise::cavitation_speed(target.depth);
+    let mut clicks = 0.0;
+    if target.transient > 0.0 {
+        clicks += TRANSIENT_CLICKS;
+    }
+    if target.hull < 1.0 {
+        clicks += shaft_rate;
+    }
+    Some(ContactSound {
+        source: target.id,
+        intensity: intensity.min(1.0),
+        blade_rate: shaft_rate * blades(target.kind) as f32,
+        modulation: match (shaft_rate > 0.0, cavitating) {
+            (false, _) => 0.0,
+            (true, false) => BLADE_MODULATION,
+            (true, true) => CAVITATION_MODULATION,
+        },
+        clicks,
+    })
+}
+
+/// How loud a sound of `level` dB made at `position` is to `listener`;
+/// None when it is not heard at all
+fn intensity(world: &World, listener: &Entity, position: &Point, level: f32) -> Option<f32> {
+    let range = listener.position.distance_to(position);
+    let received = level - transmission_loss(range, &world.environment.tuning);
+    let intensity = (received - FAINTEST) / (LOUDEST - FAINTEST);
+    if intensity > 0.0 {
+        Some(intensity.min(1.0))
+    } else {
+        None
+    }
+}
+
+fn heard(
+    world: &World,
+    listener: &Entity,
+    time: f32,
+    cue: Cue,
+    source: EntityId,
+    level: f32,
+) -> Option<SoundEvent> {
+    let entity = world.entity(source)?;
+    Some(SoundEvent {
+        time,
+        cue,
+        source,
+        position: entity.position.clone(),
+        depth: entity.depth,
+        intensity: intensity(world, listener, &entity.position, level)?,
+    })
+}
+
+/// The sounds `listener` hears of an event
+pub fn cues(world: &World, listener: &Entity, timed: &TimedEvent) -> Vec<SoundEvent> {
+    let time = timed.time;
+    let mut sounds = Vec::new();
+    match timed.event {
+        Event::TorpedoFired { torpedo, .. } => {
+            let level = TransientKind::TorpedoLaunch.level();
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::TorpedoInWater,
+                torpedo,
+                level,
+            ));
+        }
+        Event::Transient {
+            entity,
+            kind: kind @ TransientKind::HullPopping,
+        } => {
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::HullCreak,
+                entity,
+                kind.level(),
+            ));
+        }
+        Event::Transient {
+            entity,
+            kind: kind @ TransientKind::TubeFlooding,
+        } => {
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::Flooding,
+                entity,
+                kind.level(),
+            ));
+        }
+        Event::Transient {
+            entity,
+            kind: kind @ TransientKind::SecondaryExplosion,
+        } => {
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::Explosion,
+                entity,
+                kind.level(),
+            ));
+        }
+        Event::Transient {
+            entity,
+            kind: kind @ TransientKind::BreakingUp,
+        } => {
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::HullCreak,
+                entity,
+                kind.level(),
+            ));
+        }
+        Event::TorpedoHit { target, .. } => {
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::Explosion,
+                target,
+                EXPLOSION_LEVEL,
+            ));
+            if target == listener.id {
+                sounds.extend(heard(
+                    world,
+                    listener,
+                    time,
+                    Cue::Flooding,
+                    target,
+                    FLOODING_LEVEL,
+                ));
+            }
+        }
+        Event::ShellHit { target, .. } if target == listener.id => {
+            sounds.extend(heard(
+                world,
+                listener,
+                time,
+                Cue::Flooding,
+                target,
+                FLOODING_LEVEL,
+            ));
+        }
+        _ => {}
+    }
+    sounds
+}
+
+/// The ping heard on the hull for an intercept, as loud as the receiver
+/// hears it
+pub fn ping(world: &World, intercept: &Intercept) -> Option<SoundEvent> {
+    let entity = world.entity(intercept.source)?;
+    Some(SoundEvent {
+        time: world.time,
+        cue: Cue::PingReceived,
+        source: intercept.source,
+        position: entity.position.clone(),
+        depth: entity.depth,
+        intensity: (intercept.excess / LOUDEST_PING).min(1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::{Sensor, SensorKind};
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.spawn(Entity::new(
+            "U-99",
+            EntityKind::Submarine,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        world.spawn(Entity::new(
+            "Ship",
+            EntityKind::Merchant,
+            Point { x: 2000.0, y: 0.0 },
+        ));
+        world.spawn(Entity::new(
+            "Far",
+            EntityKind::Merchant,
+            Point {
+                x: 0.0,
+                y: 90_000.0,
+            },
+        ));
+        world
+    }
+
+    fn event(event: Event) -> TimedEvent {
+        TimedEvent { time: 5.0, event }
+    }
+
+    #[test]
+    fn explosions_carry_further_than_creaks() {
+        let world = world();
+        let own = world.entity(1).unwrap();
+        let hit = event(Event::TorpedoHit {
+            torpedo: 9,
+            shooter: 1,
+            target: 2,
+        });
+        let sounds = cues(&world, own, &hit);
+        assert_eq!(sounds.len(), 1);
+        assert_eq!(sounds[0].cue, Cue::Explosion);
+        assert_eq!(sounds[0].position, Point { x: 2000.0, y: 0.0 });
+        assert!(sounds[0].intensity > 0.5 && sounds[0].intensity < 1.0);
+        let creak = |entity| {
+            event(Event::Transient {
+                entity,
+                kind: TransientKind::HullPopping,
+            })
+        };
+        assert!(cues(&world, own, &creak(2)).is_empty());
+        assert_eq!(cues(&world, own, &creak(1))[0].cue, Cue::HullCreak);
+        let far = event(Event::TorpedoHit {
+            torpedo: 9,
+            shooter: 1,
+            target: 3,
+        });
+        assert!(cues(&world, own, &far)[0].intensity < sounds[0].intensity);
+    }
+
+    #[test]
+    fn hits_flood_the_own_ship() {
+        let world = world();
+        let own = world.entity(1).unwrap();
+        let shell = |target| {
+            event(Event::ShellHit {
+                shooter: 2,
+                target,
+                damage: 0.1,
+            })
+        };
+        let sounds = cues(&world, own, &shell(1));
+        assert_eq!(sounds.len(), 1);
+        assert_eq!(sounds[0].cue, Cue::Flooding);
+        assert!(cues(&world, own, &shell(2)).is_empty());
+    }
+
+    #[test]
+    fn blades_beat_and_cavitation_thrashes() {
+        let environment = Environment::default();
+        let mut own = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        own.sensors.push(Sensor::new(SensorKind::HullSonar));
+        let mut ship = Entity::new("Ship", EntityKind::Merchant, Point { x: 2000.0, y: 0.0 });
+        ship.speed = 1.5;
+        let heard = contact(&environment, &own, &ship).unwrap();
+        assert!(
+            (heard.blade_rate - 2.0).abs() < 0.01,
+            "{}",
+            heard.blade_rate
+        );
+        assert_eq!(heard.modulation, BLADE_MODULATION);
+        assert_eq!(heard.clicks, 0.0);
+
+        ship.hull = 0.5;
+        ship.transient = 150.0;
+        let knocking = contact(&environment, &own, &ship).unwrap();
+        assert!((knocking.clicks - 3.5).abs() < 0.01, "{}", knocking.clicks);
+        ship.speed = 6.0;
+        let fast = contact(&environment, &own, &ship).unwrap();
+        assert_eq!(fast.modulation, CAVITATION_MODULATION);
+        assert!(fast.intensity >= heard.intensity);
+
+        ship.position.x = 90_000.0;
+        ship.speed = 3.0;
+        ship.hull = 1.0;
+        ship.transient = 0.0;
+        assert_eq!(contact(&environment, &own, &ship), None);
+        let deaf = Entity::new("Deaf", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        assert_eq!(contact(&environment, &deaf, &own), None);
+    }
+}
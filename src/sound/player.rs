@@ -0,0 +1,218 @@
+use std::f32::consts::PI;
+use std::io::{self, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::random::Rng;
+use crate::sound::{ContactSound, Cue, SoundEvent};
+
+// Reference player for the sound cues, built with the "audio" feature. It
+// synthesizes a rough sound for each cue and pipes it, as raw 16 bit mono
+// samples, to a command line player such as
+//
+// aplay -q -f S16_LE -r 22050 -c 1
+//
+// A real frontend would play recorded sounds instead, placed by the
+// position of the cue. Listening to a contact is synthesized the same way,
+// a few seconds at a time, for as long as the operator keeps listening.
+//
+// The player is an external command rather than an audio crate such as
+// rodio because the game builds offline with the crates it already depends
+// on, and no audio crate is among them. aplay comes with the ALSA utilities
+// on Linux; elsewhere another command reading the same raw samples from its
+// input must be given. A command that cannot be started, or that stops
+// taking samples, is reported as an error naming it, for the frontend to
+// show the player.
+
+pub const SAMPLE_RATE: u32 = 22_050;
+/// Command the samples are piped to by default
+pub const DEFAULT_COMMAND: &str = "aplay -q -f S16_LE -r 22050 -c 1";
+
+/// Sine of `frequency` Hz at `t` seconds
+fn tone(frequency: f32, t: f32) -> f32 {
+    (2.0 * PI * frequency * t).sin()
+}
+
+/// Samples of a cue heard at `intensity` (0 to 1)
+pub fn synthesize(cue: Cue, intensity: f32, rng: &mut Rng) -> Vec<i16> {
+    let seconds = match cue {
+        Cue::PingReceived => 0.6,
+        Cue::TorpedoInWater => 1.5,
+        Cue::HullCreak => 1.2,
+        Cue::Flooding => 2.0,
+        Cue::Explosion => 2.5,
+    };
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut low_passed = 0.0;
+    (0..count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let noise = rng.range(-1.0, 1.0);
+            let value = match cue {
+                Cue::PingReceived => tone(3_500.0, t) * (-6.0 * t).exp(),
+                Cue::TorpedoInWater => {
+                    0.5 * tone(600.0 + 400.0 * t, t) + 0.5 * noise * (t / seconds)
+                }
+                Cue::HullCreak => tone(70.0 + 15.0 * tone(3.0, t), t) * (PI * t / seconds).sin(),
+                Cue::Flooding => {
+                    low_passed += 0.2 * (noise - low_passed);
+                    low_passed * 2.0
+                }
+                Cue::Explosion => {
+                    low_passed += 0.05 * (noise - low_passed);
+                    low_passed * 6.0 * (-2.0 * t).exp()
+                }
+            };
+            (value.clamp(-1.0, 1.0) * intensity * i16::MAX as f32) as i16
+        })
+        .collect()
+}
+
+/// Level of the hiss of the sea under a contact
+const SEA_LEVEL: f32 = 0.15;
+/// Seconds a click takes to die away
+const CLICK_DECAY: f32 = 0.01;
+
+/// `seconds` of samples of a contact heard through the headphones
+pub fn synthesize_contact(sound: &ContactSound, seconds: f32, rng: &mut Rng) -> Vec<i16> {
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut sea = 0.0;
+    let mut screw = 0.0;
+    let mut click = 0.0;
+    (0..count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            sea += 0.1 * (rng.range(-1.0, 1.0) - sea);
+            screw += 0.3 * (rng.range(-1.0, 1.0) - screw);
+            let beat = 1.0 - sound.modulation * (0.5 + 0.5 * tone(sound.blade_rate, t));
+            if rng.range(0.0, 1.0) < sound.clicks / SAMPLE_RATE as f32 {
+                click = 1.0;
+            } else {
+                click *= (-1.0 / (CLICK_DECAY * SAMPLE_RATE as f32)).exp();
+            }
+            let contact = 2.0 * screw * beat + click * rng.range(-1.0, 1.0);
+            let value = SEA_LEVEL * 3.0 * sea + sound.intensity * contact;
+            (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+        })
+        .collect()
+}
+
+/// Plays cues through a command line player
+pub struct Player {
+    child: Child,
+    stdin: ChildStdin,
+    rng: Rng,
+}
+
+impl Player {
+    /// Starts `command` (see DEFAULT_COMMAND) to play samples
+    pub fn spawn(command: &str) -> io::Result<Player> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no player command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                let message = format!("cannot start the sound player '{}': {}", program, e);
+                io::Error::new(e.kind(), message)
+            })?;
+        let stdin = child.stdin.take().unwrap();
+        Ok(Player {
+            child,
+            stdin,
+            rng: Rng::default(),
+        })
+    }
+
+    pub fn play(&mut self, sound: &SoundEvent) -> io::Result<()> {
+        let samples = synthesize(sound.cue, sound.intensity, &mut self.rng);
+        self.write(&samples)
+    }
+
+    /// Plays `seconds` of a contact heard through the headphones
+    pub fn listen(&mut self, sound: &ContactSound, seconds: f32) -> io::Result<()> {
+        let samples = synthesize_contact(sound, seconds, &mut self.rng);
+        self.write(&samples)
+    }
+
+    /// Pipes `samples` to the command, telling when it has stopped
+    fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.stdin
+            .write_all(&bytes)
+            .map_err(|e| match self.child.try_wait() {
+                Ok(Some(status)) => {
+                    let message = format!("the sound player stopped ({})", status);
+                    io::Error::new(io::ErrorKind::BrokenPipe, message)
+                }
+                _ => e,
+            })
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn louder_cues_are_louder() {
+        let peak = |intensity| {
+            let samples = synthesize(Cue::PingReceived, intensity, &mut Rng::default());
+            samples.iter().map(|s| s.unsigned_abs()).max().unwrap()
+        };
+        assert_eq!(
+            synthesize(Cue::PingReceived, 1.0, &mut Rng::default()).len(),
+            (0.6 * SAMPLE_RATE as f32) as usize
+        );
+        assert!(peak(1.0) > 2 * peak(0.3));
+    }
+
+    #[test]
+    fn missing_player_reported() {
+        let error = Player::spawn("no-such-player-command -q").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("'no-such-player-command'"));
+        assert!(Player::spawn("  ").is_err());
+        // a player that quits at once
+        let mut player = Player::spawn("true").unwrap();
+        let _ = player.child.wait();
+        let error = player.write(&[0; SAMPLE_RATE as usize]).err().unwrap();
+        assert!(error.to_string().contains("stopped"), "{}", error);
+    }
+
+    #[test]
+    fn contacts_beat_with_their_blades() {
+        let sound = ContactSound {
+            source: 2,
+            intensity: 1.0,
+            blade_rate: 5.0,
+            modulation: 0.8,
+            clicks: 0.0,
+        };
+        let samples = synthesize_contact(&sound, 1.0, &mut Rng::default());
+        assert_eq!(samples.len(), SAMPLE_RATE as usize);
+        // loudness over a tenth of the blade period from `from` seconds
+        let loudness = |from: f32| {
+            let start = (from * SAMPLE_RATE as f32) as usize;
+            let window = &samples[start..start + SAMPLE_RATE as usize / 50];
+            window.iter().map(|s| (*s as f32).abs()).sum::<f32>()
+        };
+        // the hiss is quietest a quarter into the period, loudest at three
+        assert!(loudness(0.04) < loudness(0.14) / 2.0);
+        let quiet = ContactSound {
+            intensity: 0.0,
+            ..sound
+        };
+        let sea = synthesize_contact(&quiet, 1.0, &mut Rng::default());
+        let peak = |samples: &[i16]| samples.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        assert!(peak(&sea) < peak(&samples));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use crate::messages::Catalog;
+
+// #############################
+// #        STOPWATCHES        #
+// #############################
+
+// The attack team times everything: how long a fish has been running, how
+// long since the last ping, how long the boat has held a leg. The player
+// keeps named timers with
+//
+// timer start run         # starts the timer "run", or starts it over
+// timer stop run          # stops it, showing the time it stopped at
+// timer clear run         # throws it away
+// timers                  # what every timer shows
+//
+// Two run by themselves: "torpedo" starts over with every torpedo the own
+// ship fires, and "ping" with every sonar pulse the intercept receiver
+// hears. A tutorial step may wait for a timer (see tutorial.rs).
+
+/// Timers started over by the simulation, see above
+pub const TORPEDO_TIMER: &str = "torpedo";
+pub const PING_TIMER: &str = "ping";
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TimerError {
+    NoSuchTimer(String),
+}
+
+impl TimerError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            TimerError::NoSuchTimer(name) => {
+                messages.format("error-no-such-timer", &[("name", name)])
+            }
+        }
+    }
+}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for TimerError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Timer {
+    pub name: String,
+    /// Seconds into the scenario it was started at
+    pub started: f32,
+    /// And stopped at, None while running
+    pub stopped: Option<f32>,
+}
+
+impl Timer {
+    /// Seconds it shows at `time`
+    pub fn elapsed(&self, time: f32) -> f32 {
+        (self.stopped.unwrap_or(time) - self.started).max(0.0)
+    }
+}
+
+/// Seconds as a stopwatch shows them, "m:ss" or "h:mm:ss"
+pub fn clock(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// The timers of the attack team, in the order they were first started
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Timers {
+    pub timers: Vec<Timer>,
+}
+
+impl Timers {
+    pub fn get(&self, name: &str) -> Option<&Timer> {
+        self.timers.iter().find(|t| t.name == name)
+    }
+
+    /// Starts `name` at `time`, over again if it already ran
+    pub fn start(&mut self, name: &str, time: f32) {
+        let timer = Timer {
+            name: name.to_string(),
+            started: time,
+            stopped: None,
+        };
+        match self.timers.iter_mut().find(|t| t.name == name) {
+            Some(old) => *old = timer,
+            None => self.timers.push(timer),
+        }
+    }
+
+    /// Stops `name` at `time`, returning the seconds it shows
+    pub fn stop(&mut self, name: &str, time: f32) -> Result<f32, TimerError> {
+        let timer = self
+            .timers
+            .iter_mut()
+            .find(|t| t.name == name)
+            .ok_or_else(|| TimerError::NoSuchTimer(name.to_string()))?;
+        if timer.stopped.is_none() {
+            timer.stopped = Some(time);
+        }
+        Ok(timer.elapsed(time))
+    }
+
+    pub fn clear(&mut self, name: &str) -> Result<(), TimerError> {
+        let count = self.timers.len();
+        self.timers.retain(|t| t.name != name);
+        if self.timers.len() == count {
+            return Err(TimerError::NoSuchTimer(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Seconds `name` shows at `time`, None if it was never started
+    pub fn elapsed(&self, name: &str, time: f32) -> Option<f32> {
+        self.get(name).map(|t| t.elapsed(time))
+    }
+
+    /// A line for each timer, as written for the player
+    pub fn describe(&self, messages: &Catalog, time: f32) -> Vec<String> {
+        self.timers
+            .iter()
+            .map(|timer| {
+                let id = match timer.stopped {
+                    Some(_) => "timer-stopped",
+                    None => "timer-running",
+                };
+                messages.format(
+                    id,
+                    &[
+                        ("name", &timer.name),
+                        ("elapsed", &clock(timer.elapsed(time))),
+                    ],
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_stop_and_clear() {
+        let mut timers = Timers::default();
+        timers.start("run", 10.0);
+        assert_eq!(timers.elapsed("run", 70.0), Some(60.0));
+        assert_eq!(timers.stop("run", 100.0), Ok(90.0));
+        // stopped, it no longer moves
+        assert_eq!(timers.elapsed("run", 500.0), Some(90.0));
+        assert_eq!(timers.stop("run", 500.0), Ok(90.0));
+        timers.start("run", 500.0);
+        assert_eq!(timers.elapsed("run", 530.0), Some(30.0));
+        assert_eq!(timers.timers.len(), 1);
+
+        assert_eq!(timers.clear("run"), Ok(()));
+        assert_eq!(
+            timers.clear("run"),
+            Err(TimerError::NoSuchTimer("run".to_string()))
+        );
+        assert_eq!(timers.elapsed("run", 600.0), None);
+    }
+
+    #[test]
+    fn shows_minutes_and_hours() {
+        assert_eq!(clock(0.0), "0:00");
+        assert_eq!(clock(95.4), "1:35");
+        assert_eq!(clock(3_725.0), "1:02:05");
+        let mut timers = Timers::default();
+        timers.start("ping", 0.0);
+        timers.start("leg", 0.0);
+        timers.stop("leg", 30.0).unwrap();
+        assert_eq!(
+            timers.describe(&Catalog::default(), 95.0),
+            vec!["ping 1:35", "leg 0:30, stopped"]
+        );
+    }
+}
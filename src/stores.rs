@@ -0,0 +1,372 @@
+use std::fmt;
+
+use crate::machinery;
+use crate::messages::Catalog;
+use crate::morale;
+use crate::physics::KNOT;
+use crate::seakeeping::wave_height;
+use crate::world::{Entity, EntityId, World};
+use crate::zone::ZoneKind;
+
+// #############################
+// #     FUEL AND PROVISIONS   #
+// #############################
+
+// What a ship carries for a long patrol: diesel fuel, provisions and
+// spare parts. Fuel burns with the cube of the speed on top of what the
+// ship uses lying stopped, more in a heavy sea; provisions last so many
+// days; spare parts wear out with the running hours of the machinery. A
+// ship out of fuel can no longer make way. Stores are filled up again by a
+// refit, lying stopped in a port or alongside a tender; in port the
+// machinery is overhauled too (see machinery.rs). Classes give what
+// they carry:
+//
+// [class.type_viic]
+// fuel = 113              # tonnes of diesel
+// fuel_rate = 0.13        # tonnes an hour at 10 knots
+// provisions = 60         # days
+// spares = 20             # spare parts
+// tender = false          # refits other ships alongside
+
+/// Tonnes of fuel an hour at 10 knots when the class does not say
+pub const DEFAULT_FUEL_RATE: f32 = 0.13;
+/// Speed in meters per second `fuel_rate` is given at
+const RATED_SPEED: f32 = 10.0 * KNOT;
+/// Fraction of the rated consumption burnt lying stopped
+const HOTEL_LOAD: f32 = 0.1;
+/// Extra fuel burnt per meter of wave height, as a fraction
+const SEA_RESISTANCE: f32 = 0.05;
+/// Running hours of the machinery that wear out one spare part
+const WEAR_HOURS: f32 = 48.0;
+/// Meters from a tender at which a ship can take on stores
+pub const TENDER_RANGE: f32 = 300.0;
+/// Meters per second below which a ship counts as stopped for a refit
+const STOPPED: f32 = 0.5;
+
+/// Amounts of each consumable
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Consumables {
+    /// Tonnes of fuel
+    pub fuel: f32,
+    /// Days of provisions
+    pub food: f32,
+    /// Spare parts
+    pub spares: f32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Consumable {
+    Fuel,
+    Food,
+    Spares,
+}
+
+impl fmt::Display for Consumable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Consumable::Fuel => "fuel",
+            Consumable::Food => "provisions",
+            Consumable::Spares => "spare parts",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What a ship has aboard, out of what it can carry
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stores {
+    pub remaining: Consumables,
+    pub capacity: Consumables,
+    /// Tonnes of fuel an hour at 10 knots
+    pub fuel_rate: f32,
+}
+
+/// How much longer a ship can keep the sea
+#[derive(Debug, PartialEq, Clone)]
+pub struct Endurance {
+    /// Hours until the first consumable runs out, None when nothing does
+    pub hours: Option<f32>,
+    /// Meters the fuel lasts at the speed asked for, None when fuel is not
+    /// burnt
+    pub range: Option<f32>,
+    /// What runs out first
+    pub limited_by: Option<Consumable>,
+}
+
+impl Endurance {
+    /// Whether the fuel lasts for `distance` meters
+    pub fn reaches(&self, distance: f32) -> bool {
+        self.range.is_none_or(|range| range >= distance)
+    }
+}
+
+impl Stores {
+    /// Stores filled up to `capacity`
+    pub fn full(capacity: Consumables, fuel_rate: f32) -> Stores {
+        Stores {
+            remaining: capacity,
+            capacity,
+            fuel_rate,
+        }
+    }
+
+    /// Tonnes of fuel an hour at `speed` (m/s) in `sea_state`; `surfaced`
+    /// hulls also fight the waves
+    pub fn fuel_per_hour(&self, speed: f32, sea_state: u8, surfaced: bool) -> f32 {
+        let power = HOTEL_LOAD + (1.0 - HOTEL_LOAD) * (speed / RATED_SPEED).powi(3);
+        let sea = if surfaced {
+            1.0 + SEA_RESISTANCE * wave_height(sea_state)
+        } else {
+            1.0
+        };
+        self.fuel_rate * power * sea
+    }
+
+    /// Uses up stores over `dt` seconds of steaming at `speed`
+    fn consume(&mut self, dt: f32, speed: f32, sea_state: u8, surfaced: bool) {
+        let hours = dt / 3600.0;
+        let fuel = self.fuel_per_hour(speed, sea_state, surfaced) * hours;
+        let remaining = &mut self.remaining;
+        if self.capacity.fuel > 0.0 {
+            remaining.fuel = (remaining.fuel - fuel).max(0.0);
+        }
+        remaining.food = (remaining.food - hours / 24.0).max(0.0);
+        if speed > 0.0 {
+            remaining.spares = (remaining.spares - hours / WEAR_HOURS).max(0.0);
+        }
+    }
+
+    /// Whether the ship can still make way
+    pub fn has_fuel(&self) -> bool {
+        self.capacity.fuel <= 0.0 || self.remaining.fuel > 0.0
+    }
+
+    /// Consumables used up, of those carried
+    pub fn shortages(&self) -> Vec<Consumable> {
+        let mut short = Vec::new();
+        let pairs = [
+            (Consumable::Fuel, self.remaining.fuel, self.capacity.fuel),
+            (Consumable::Food, self.remaining.food, self.capacity.food),
+            (
+                Consumable::Spares,
+                self.remaining.spares,
+                self.capacity.spares,
+            ),
+        ];
+        for (consumable, remaining, capacity) in pairs {
+            if capacity > 0.0 && remaining <= 0.0 {
+                short.push(consumable);
+            }
+        }
+        short
+    }
+
+    /// How long the stores last steaming at `speed` (m/s) in `sea_state`
+    pub fn endurance(&self, speed: f32, sea_state: u8, surfaced: bool) -> Endurance {
+        let mut limits = Vec::new();
+        let mut range = None;
+        if self.capacity.fuel > 0.0 {
+            let hours = self.remaining.fuel / self.fuel_per_hour(speed, sea_state, surfaced);
+            limits.push((Consumable::Fuel, hours));
+            range = Some(hours * 3600.0 * speed);
+        }
+        if self.capacity.food > 0.0 {
+            limits.push((Consumable::Food, self.remaining.food * 24.0));
+        }
+        let first = limits.into_iter().min_by(|a, b| a.1.total_cmp(&b.1));
+        Endurance {
+            hours: first.map(|(_, hours)| hours),
+            range,
+            limited_by: first.map(|(consumable, _)| consumable),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StoresError {
+    NoStores,
+    /// Neither in a port nor alongside a tender
+    NoSupplies,
+    NotStopped,
+}
+
+impl StoresError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        let id = match self {
+            StoresError::NoStores => "error-no-stores",
+            StoresError::NoSupplies => "error-no-supplies",
+            StoresError::NotStopped => "error-not-stopped",
+        };
+        messages.get(id).to_string()
+    }
+}
+
+impl fmt::Display for StoresError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for StoresError {}
+
+fn in_port(world: &World, entity: &Entity) -> bool {
+    world
+        .zones_at(&entity.position)
+        .any(|z| z.kind == ZoneKind::Port)
+}
+
+/// Whether `entity` lies where it can take on stores
+fn can_refit(world: &World, entity: &Entity) -> bool {
+    let alongside = world.entities.iter().any(|e| {
+        e.tender
+            && e.id != entity.id
+            && !e.is_destroyed()
+            && e.position.distance_to(&entity.position) <= TENDER_RANGE
+    });
+    in_port(world, entity) || alongside
+}
+
+/// Fills up the stores of `id`, lying stopped in a port or by a tender; in
+/// port the crew also gets a run ashore and the machinery an overhaul
+pub fn refit(world: &mut World, id: EntityId) -> Result<(), StoresError> {
+    let entity = world.entity(id).ok_or(StoresError::NoStores)?;
+    if entity.stores.is_none() {
+        return Err(StoresError::NoStores);
+    }
+    if entity.speed > STOPPED || !entity.is_surfaced() {
+        return Err(StoresError::NotStopped);
+    }
+    if !can_refit(world, entity) {
+        return Err(StoresError::NoSupplies);
+    }
+    let ashore = in_port(world, entity);
+    let entity = world.entity_mut(id).unwrap();
+    let stores = entity.stores.as_mut().unwrap();
+    stores.remaining = stores.capacity;
+    if ashore {
+        morale::shore_leave(entity);
+        machinery::overhaul(entity);
+    }
+    Ok(())
+}
+
+/// Burns fuel and uses up provisions over `dt` seconds; ships out of fuel
+/// come to a stop
+pub fn update(world: &mut World, dt: f32) {
+    let sea_state = world.environment.sea_state;
+    for entity in world.entities.iter_mut() {
+        let surfaced = entity.is_surfaced();
+        if let Some(stores) = entity.stores.as_mut() {
+            stores.consume(dt, entity.speed, sea_state, surfaced);
+            if !stores.has_fuel() {
+                entity.speed = 0.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machinery::Machinery;
+    use crate::physics::Point;
+    use crate::world::EntityKind;
+    use crate::zone::Zone;
+
+    fn type_viic() -> Stores {
+        let capacity = Consumables {
+            fuel: 113.0,
+            food: 60.0,
+            spares: 20.0,
+        };
+        Stores::full(capacity, 0.13)
+    }
+
+    #[test]
+    fn endurance_by_speed() {
+        let stores = type_viic();
+        let economical = stores.endurance(10.0 * KNOT, 0, true);
+        let flank = stores.endurance(17.0 * KNOT, 0, true);
+        // about 8,500 nautical miles at 10 knots
+        let miles = economical.range.unwrap() / 1852.0;
+        assert!(miles > 8000.0 && miles < 9000.0, "{}", miles);
+        assert!(flank.range.unwrap() < economical.range.unwrap() / 2.0);
+        assert_eq!(economical.limited_by, Some(Consumable::Fuel));
+        // lying stopped the provisions run out first
+        let stopped = stores.endurance(0.0, 0, true);
+        assert_eq!(stopped.limited_by, Some(Consumable::Food));
+        assert_eq!(stopped.hours, Some(60.0 * 24.0));
+        let rough = stores.endurance(10.0 * KNOT, 6, true);
+        assert!(rough.range.unwrap() < economical.range.unwrap());
+        assert!(economical.reaches(1_000_000.0));
+    }
+
+    #[test]
+    fn out_of_fuel() {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.speed = 8.0;
+        let mut stores = type_viic();
+        stores.remaining.fuel = 0.001;
+        boat.stores = Some(stores);
+        world.spawn(boat);
+        for _ in 0..60 {
+            world.step(1.0);
+        }
+        let boat = world.entity(1).unwrap();
+        assert_eq!(boat.speed, 0.0);
+        assert_eq!(
+            boat.stores.as_ref().unwrap().shortages(),
+            vec![Consumable::Fuel]
+        );
+    }
+
+    #[test]
+    fn refit_in_port_or_alongside_a_tender() {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        let mut stores = type_viic();
+        stores.remaining.food = 3.0;
+        boat.stores = Some(stores);
+        let worn = Machinery {
+            running_hours: 500.0,
+            shock: 0.2,
+        };
+        boat.machinery = Some(worn.clone());
+        let boat = world.spawn(boat);
+        assert_eq!(refit(&mut world, boat), Err(StoresError::NoSupplies));
+        let mut tender = Entity::new("Nordmark", EntityKind::Merchant, Point { x: 200.0, y: 0.0 });
+        tender.tender = true;
+        world.spawn(tender);
+        world.entity_mut(boat).unwrap().speed = 3.0;
+        assert_eq!(refit(&mut world, boat), Err(StoresError::NotStopped));
+        world.entity_mut(boat).unwrap().speed = 0.0;
+        refit(&mut world, boat).unwrap();
+        assert_eq!(world.entity(boat).unwrap().stores, Some(type_viic()));
+        // a tender brings stores, but only a dockyard overhauls
+        assert_eq!(world.entity(boat).unwrap().machinery, Some(worn));
+
+        world.remove(2);
+        world.zones.push(Zone {
+            name: "Lorient".to_string(),
+            kind: ZoneKind::Port,
+            points: vec![
+                Point {
+                    x: -100.0,
+                    y: -100.0,
+                },
+                Point {
+                    x: 100.0,
+                    y: -100.0,
+                },
+                Point { x: 100.0, y: 100.0 },
+            ],
+            depth: None,
+            bottom: None,
+        });
+        refit(&mut world, boat).unwrap();
+        let overhauled = world.entity(boat).unwrap().machinery.clone();
+        assert_eq!(overhauled, Some(Machinery::default()));
+    }
+}
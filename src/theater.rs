@@ -0,0 +1,371 @@
+use crate::config::{Config, ConfigError};
+use crate::events::Event;
+use crate::gunnery;
+use crate::physics::{turn_towards, Point, KNOT};
+use crate::traffic::read_place;
+use crate::tuning::Tunable;
+use crate::world::{Entity, EntityId, EntityKind, World};
+use crate::zone::Zone;
+
+// #############################
+// #      THEATER COMMAND      #
+// #############################
+
+// Beyond the escorts of its convoys, the command of a side has
+// hunter-killer groups and air patrols to put where the boats are. It
+// tallies the sinkings of its ships by square of a grid over the map, and
+// each time a square has cost it so many ships, forms another group at its
+// base and sends it to sweep there, so that a boat doing well draws more
+// and more against it the longer it works the same waters:
+//
+// [theater]
+// side = allies           # whose command
+// base = Liverpool        # a port zone, or "x, y" in meters
+// hunters = flower, flower, sloop   # a ship of each class in a group
+// square = 100000         # optional, meters on a side of a grid square
+// sinkings = 2            # optional, sinkings in a square per group sent
+// speed = 16              # optional, knots the groups make
+// patrol = 3600           # optional, seconds between air patrols over a
+//                         # square a group was sent to
+//
+// The air patrols are not flown as entities: every patrol interval, a boat
+// of another side surfaced or at periscope depth in the square may be
+// sighted from the air, and the groups of the square steer for where it
+// was seen. Otherwise they sweep it, steering for one point after another
+// at random within it, for as long as the scenario runs.
+
+/// Meters on a side of a grid square, unless set
+const SQUARE: f32 = 100_000.0;
+/// Sinkings in a square per group sent, unless set
+const SINKINGS: u32 = 2;
+/// Knots the groups make, unless set
+const SPEED: f32 = 16.0;
+/// Seconds between air patrols, unless set
+const PATROL: f32 = 3_600.0;
+/// Chance an air patrol sights a boat showing above the water
+const SIGHTING_CHANCE: f32 = 0.5;
+/// Meters between the ships of a group leaving its base
+const SPACING: f32 = 600.0;
+/// Meters from where it steers for at which a hunter picks the next point
+const ON_STATION: f32 = 1_000.0;
+
+/// A square of the grid, by column and row
+pub type Square = (i32, i32);
+
+/// A ship of a group, and where in its square it steers for
+#[derive(Debug, PartialEq, Clone)]
+struct Hunter {
+    id: EntityId,
+    square: Square,
+    to: Point,
+}
+
+/// The command of a side over its theater, and where it has sent its groups
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Theater {
+    /// Whose command, None for none
+    pub side: Option<String>,
+    pub base: Point,
+    /// Classes of the ships of a group
+    pub classes: Vec<String>,
+    /// The ships of a group as they leave the base, by the scenario
+    pub group: Vec<Entity>,
+    pub square: f32,
+    pub sinkings: u32,
+    /// Meters per second
+    pub speed: f32,
+    pub patrol: f32,
+    /// Sinkings reported, by square
+    pub tally: Vec<(Square, u32)>,
+    /// Groups formed so far
+    pub groups: u32,
+    hunters: Vec<Hunter>,
+    /// Squares under air patrol, and when it is next over them
+    patrols: Vec<(Square, f32)>,
+    /// Events already read
+    seen: usize,
+}
+
+impl Theater {
+    /// Reads the "[theater]" section, no command without one
+    pub fn read(config: &Config, zones: &[Zone]) -> Result<Theater, ConfigError> {
+        let section = match config.section("theater") {
+            Some(section) => section,
+            None => return Ok(Theater::default()),
+        };
+        Ok(Theater {
+            side: Some(section.parse("side")?),
+            base: read_place(section, "base", zones)?,
+            classes: section
+                .parse::<String>("hunters")?
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+            square: section.parse_or("square", SQUARE)?,
+            sinkings: section.parse_or("sinkings", SINKINGS)?.max(1),
+            speed: section.parse_or("speed", SPEED)? * KNOT,
+            patrol: section.parse_or("patrol", PATROL)?,
+            ..Theater::default()
+        })
+    }
+
+    /// The square `p` is in
+    pub fn square_of(&self, p: &Point) -> Square {
+        (
+            (p.x / self.square).floor() as i32,
+            (p.y / self.square).floor() as i32,
+        )
+    }
+
+    /// Sinkings reported in `square`
+    pub fn sunk_in(&self, square: Square) -> u32 {
+        self.tally
+            .iter()
+            .find(|(s, _)| *s == square)
+            .map_or(0, |(_, n)| *n)
+    }
+
+    /// Groups working `square`, by the ships in it still afloat
+    pub fn hunters_in(&self, square: Square) -> Vec<EntityId> {
+        self.hunters
+            .iter()
+            .filter(|h| h.square == square)
+            .map(|h| h.id)
+            .collect()
+    }
+
+    /// Counts a sinking in `square`, returning whether it calls for a group
+    fn report(&mut self, square: Square) -> bool {
+        let count = match self.tally.iter_mut().find(|(s, _)| *s == square) {
+            Some((_, n)) => {
+                *n += 1;
+                *n
+            }
+            None => {
+                self.tally.push((square, 1));
+                1
+            }
+        };
+        count % self.sinkings == 0
+    }
+}
+
+/// A point at random within `square`
+fn anywhere_in(world: &mut World, square: Square) -> Point {
+    let size = world.theater.square;
+    Point {
+        x: (square.0 as f32 + world.rng.next_f32()) * size,
+        y: (square.1 as f32 + world.rng.next_f32()) * size,
+    }
+}
+
+/// Forms a group at the base and sends it to `square`
+fn form_group(world: &mut World, square: Square) {
+    world.theater.groups += 1;
+    let number = world.theater.groups;
+    let to = anywhere_in(world, square);
+    let base = world.theater.base.clone();
+    let back = base.angle_to(&to) + std::f32::consts::PI;
+    for (i, ship) in world.theater.group.clone().into_iter().enumerate() {
+        let distance = SPACING * i as f32;
+        let ship = Entity {
+            name: format!("HK-{} {}", number, i + 1),
+            position: Point {
+                x: base.x + distance * back.cos(),
+                y: base.y + distance * back.sin(),
+            },
+            heading: base.angle_to(&to),
+            speed: world.theater.speed,
+            side: world.theater.side.clone(),
+            ..ship
+        };
+        let id = world.spawn(ship);
+        world.emit(Event::Departed { entity: id });
+        world.theater.hunters.push(Hunter {
+            id,
+            square,
+            to: to.clone(),
+        });
+    }
+    if !world.theater.patrols.iter().any(|(s, _)| *s == square) {
+        let next = world.time + world.theater.patrol;
+        world.theater.patrols.push((square, next));
+    }
+}
+
+/// Flies the air patrols due, returning where each sighted a boat
+fn fly_patrols(world: &mut World) -> Vec<(Square, Point)> {
+    let side = world.theater.side.clone();
+    let mut sightings = Vec::new();
+    let mut patrols = std::mem::take(&mut world.theater.patrols);
+    for (square, next) in patrols.iter_mut() {
+        if *next > world.time {
+            continue;
+        }
+        *next = world.time + world.theater.patrol;
+        let showing: Vec<Point> = world
+            .entities
+            .iter()
+            .filter(|e| e.kind == EntityKind::Submarine && !e.is_destroyed())
+            .filter(|e| e.side != side && gunnery::exposure(e) > 0.0)
+            .filter(|e| world.theater.square_of(&e.position) == *square)
+            .map(|e| e.position.clone())
+            .collect();
+        for position in showing {
+            if world.rng.chance(SIGHTING_CHANCE) {
+                sightings.push((*square, position));
+            }
+        }
+    }
+    world.theater.patrols = patrols;
+    sightings
+}
+
+/// Reads the sinkings reported, forms the groups they call for, flies the
+/// air patrols and steers the groups at sea
+pub fn update(world: &mut World, dt: f32) {
+    let side = match &world.theater.side {
+        Some(side) => side.clone(),
+        None => return,
+    };
+    let mut lost = Vec::new();
+    for timed in &world.events[world.theater.seen.min(world.events.len())..] {
+        if let Event::Destroyed { entity } = timed.event {
+            let ship = match world.entity(entity) {
+                Some(ship) => ship,
+                None => continue,
+            };
+            let ours = ship.side.as_ref() == Some(&side);
+            if ours && matches!(ship.kind, EntityKind::Merchant | EntityKind::Warship) {
+                lost.push(world.theater.square_of(&ship.position));
+            }
+        }
+    }
+    world.theater.seen = world.events.len();
+    for square in lost {
+        if world.theater.report(square) {
+            form_group(world, square);
+        }
+    }
+
+    for (square, position) in fly_patrols(world) {
+        for hunter in world.theater.hunters.iter_mut() {
+            if hunter.square == square {
+                hunter.to = position.clone();
+            }
+        }
+    }
+
+    let turn = Tunable::TurnRate.get(&world.environment.tuning) * dt;
+    let mut hunters = std::mem::take(&mut world.theater.hunters);
+    hunters.retain_mut(|hunter| {
+        let position = match world.entity(hunter.id) {
+            Some(ship) if !ship.is_destroyed() => ship.position.clone(),
+            _ => return false,
+        };
+        if position.distance_to(&hunter.to) < ON_STATION {
+            hunter.to = anywhere_in(world, hunter.square);
+        }
+        let speed = world.theater.speed;
+        let ship = world.entities.get_mut(hunter.id).unwrap();
+        let desired = ship.position.angle_to(&hunter.to);
+        ship.heading = turn_towards(ship.heading, desired, turn);
+        ship.speed = speed;
+        true
+    });
+    world.theater.hunters = hunters;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Theater {
+        let config = Config::parse(
+            "[theater]\nside = allies\nbase = 0, 0\nhunters = flower, flower\n\
+             square = 10000\nsinkings = 2\npatrol = 600",
+        )
+        .unwrap();
+        let mut theater = Theater::read(&config, &[]).unwrap();
+        theater.group =
+            vec![Entity::new("Flower", EntityKind::Warship, Point { x: 0.0, y: 0.0 }); 2];
+        theater
+    }
+
+    /// A merchant of the allies at `p`, sunk
+    fn sink(world: &mut World, p: Point) {
+        let mut ship = Entity::new("Empire", EntityKind::Merchant, p);
+        ship.side = Some("allies".to_string());
+        ship.hull = 0.0;
+        let id = world.spawn(ship);
+        world.emit(Event::Destroyed { entity: id });
+    }
+
+    #[test]
+    fn reads_the_command() {
+        let theater = command();
+        assert_eq!(theater.side.as_deref(), Some("allies"));
+        assert_eq!(theater.classes, vec!["flower", "flower"]);
+        assert!((theater.speed - 16.0 * KNOT).abs() < 0.001);
+        assert_eq!(
+            theater.square_of(&Point {
+                x: -1.0,
+                y: 25_000.0
+            }),
+            (-1, 2)
+        );
+        assert_eq!(
+            Theater::read(&Config::new(), &[]).unwrap(),
+            Theater::default()
+        );
+        let bad = Config::parse("[theater]\nside = allies\nbase = nowhere\nhunters = a").unwrap();
+        assert!(Theater::read(&bad, &[]).is_err());
+    }
+
+    #[test]
+    fn sinkings_bring_more_and_more_hunters() {
+        let mut world = World::new();
+        world.theater = command();
+        let square = (5, 0);
+        let there = Point {
+            x: 55_000.0,
+            y: 5_000.0,
+        };
+        sink(&mut world, there.clone());
+        world.step(1.0);
+        assert_eq!(world.theater.sunk_in(square), 1);
+        assert_eq!(world.theater.groups, 0);
+        // the ships of another side are none of its business
+        let mut neutral = Entity::new("Gripsholm", EntityKind::Merchant, there.clone());
+        neutral.hull = 0.0;
+        let id = world.spawn(neutral);
+        world.emit(Event::Destroyed { entity: id });
+        sink(&mut world, there.clone());
+        world.step(1.0);
+        assert_eq!(world.theater.sunk_in(square), 2);
+        assert_eq!(world.theater.groups, 1);
+        assert_eq!(world.theater.hunters_in(square).len(), 2);
+        sink(&mut world, there.clone());
+        sink(&mut world, there.clone());
+        world.step(1.0);
+        assert_eq!(world.theater.groups, 2);
+        assert_eq!(world.theater.hunters_in(square).len(), 4);
+
+        // the groups make for the square and stay there
+        for _ in 0..8_000 {
+            world.step(1.0);
+        }
+        for id in world.theater.hunters_in(square) {
+            let hunter = world.entity(id).unwrap();
+            assert_eq!(hunter.side.as_deref(), Some("allies"));
+            let (x, y) = (hunter.position.x, hunter.position.y);
+            assert!(
+                (45_000.0..65_000.0).contains(&x) && (-5_000.0..15_000.0).contains(&y),
+                "{} at {:?}",
+                hunter.name,
+                hunter.position
+            );
+        }
+    }
+}
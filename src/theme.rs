@@ -0,0 +1,275 @@
+use std::fmt;
+use std::str::FromStr;
+
+use tui::style::Color;
+
+use crate::config::{Config, ConfigError};
+use crate::faction::Stance;
+use crate::plot::Layer;
+use crate::zone::ZoneKind;
+
+// #############################
+// #          THEMES           #
+// #############################
+
+// Front ends never pick a color themselves: they ask the theme for the
+// color of what they draw, by what it means. A contact is hostile,
+// friendly, neutral or unknown (see faction.rs), a plot item shows the own
+// ship, a warning, land (see plot.rs). The palette is a preference
+// ("set palette deuteranopia"):
+//
+// standard        red hostile, green friendly
+// deuteranopia    safe for red-green color blindness, the most common
+// protanopia      likewise, with reds that stay visible when dimmed
+// tritanopia      safe for blue-yellow color blindness
+// monochrome      no hue at all, brightness only
+//
+// The color-blind palettes are drawn from the Okabe-Ito set. Any color can
+// still be changed in the player's profile:
+//
+// [theme]
+// hostile = #ff8000
+// land = #806040
+
+/// What a color means
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Role {
+    Own,
+    Friendly,
+    Neutral,
+    Hostile,
+    Unknown,
+    /// Something needing attention: danger zones, minefields, alerts
+    Warning,
+    Land,
+    /// Rings, tracks and marks
+    Plotting,
+    Text,
+}
+
+impl Role {
+    pub const ALL: [Role; 9] = [
+        Role::Own,
+        Role::Friendly,
+        Role::Neutral,
+        Role::Hostile,
+        Role::Unknown,
+        Role::Warning,
+        Role::Land,
+        Role::Plotting,
+        Role::Text,
+    ];
+
+    pub fn of_stance(stance: Stance) -> Role {
+        match stance {
+            Stance::Friendly => Role::Friendly,
+            Stance::Neutral => Role::Neutral,
+            Stance::Hostile => Role::Hostile,
+            Stance::Unknown => Role::Unknown,
+        }
+    }
+
+    pub fn of_layer(layer: Layer) -> Role {
+        match layer {
+            Layer::OwnTrack | Layer::Route | Layer::Reckoning | Layer::Coverage => Role::Own,
+            Layer::ContactTrack | Layer::Uncertainty | Layer::BearingLine => Role::Unknown,
+            Layer::DangerZone
+            | Layer::Zone(ZoneKind::Exclusion)
+            | Layer::Zone(ZoneKind::Minefield) => Role::Warning,
+            Layer::Land | Layer::Zone(ZoneKind::Land) | Layer::Zone(ZoneKind::Shallow) => {
+                Role::Land
+            }
+            _ => Role::Plotting,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Role::Own => "own",
+            Role::Friendly => "friendly",
+            Role::Neutral => "neutral",
+            Role::Hostile => "hostile",
+            Role::Unknown => "unknown",
+            Role::Warning => "warning",
+            Role::Land => "land",
+            Role::Plotting => "plotting",
+            Role::Text => "text",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Palette {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+    Monochrome,
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Palette::Standard => "standard",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia => "protanopia",
+            Palette::Tritanopia => "tritanopia",
+            Palette::Monochrome => "monochrome",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Palette, String> {
+        match s {
+            "standard" => Ok(Palette::Standard),
+            "deuteranopia" => Ok(Palette::Deuteranopia),
+            "protanopia" => Ok(Palette::Protanopia),
+            "tritanopia" => Ok(Palette::Tritanopia),
+            "monochrome" => Ok(Palette::Monochrome),
+            _ => Err(format!("unknown palette '{}'", s)),
+        }
+    }
+}
+
+// Okabe-Ito
+const ORANGE: Color = Color::Rgb(230, 159, 0);
+const SKY_BLUE: Color = Color::Rgb(86, 180, 233);
+const BLUISH_GREEN: Color = Color::Rgb(0, 158, 115);
+const YELLOW: Color = Color::Rgb(240, 228, 66);
+const BLUE: Color = Color::Rgb(0, 114, 178);
+const VERMILLION: Color = Color::Rgb(213, 94, 0);
+const REDDISH_PURPLE: Color = Color::Rgb(204, 121, 167);
+
+impl Palette {
+    /// The color of `role` in the palette
+    pub fn color(&self, role: Role) -> Color {
+        match (self, role) {
+            (_, Role::Text) => Color::White,
+            (Palette::Monochrome, Role::Hostile | Role::Warning | Role::Own) => Color::White,
+            (Palette::Monochrome, Role::Unknown | Role::Neutral | Role::Friendly) => Color::Gray,
+            (Palette::Monochrome, _) => Color::DarkGray,
+            (_, Role::Land) => Color::Rgb(128, 96, 64),
+            (_, Role::Plotting) => Color::DarkGray,
+            (Palette::Standard, Role::Own) => Color::Cyan,
+            (Palette::Standard, Role::Friendly) => Color::Green,
+            (Palette::Standard, Role::Neutral) => Color::White,
+            (Palette::Standard, Role::Hostile) => Color::Red,
+            (Palette::Standard, Role::Unknown) => Color::Yellow,
+            (Palette::Standard, Role::Warning) => Color::LightMagenta,
+            (Palette::Deuteranopia, Role::Hostile) => ORANGE,
+            (Palette::Protanopia, Role::Hostile) => VERMILLION,
+            (Palette::Deuteranopia | Palette::Protanopia, Role::Own) => SKY_BLUE,
+            (Palette::Deuteranopia | Palette::Protanopia, Role::Friendly) => BLUE,
+            (Palette::Deuteranopia | Palette::Protanopia, Role::Neutral) => Color::White,
+            (Palette::Deuteranopia | Palette::Protanopia, Role::Unknown) => YELLOW,
+            (Palette::Deuteranopia | Palette::Protanopia, Role::Warning) => REDDISH_PURPLE,
+            (Palette::Tritanopia, Role::Own) => SKY_BLUE,
+            (Palette::Tritanopia, Role::Friendly) => BLUISH_GREEN,
+            (Palette::Tritanopia, Role::Neutral) => Color::White,
+            (Palette::Tritanopia, Role::Hostile) => VERMILLION,
+            (Palette::Tritanopia, Role::Unknown) => REDDISH_PURPLE,
+            (Palette::Tritanopia, Role::Warning) => ORANGE,
+        }
+    }
+}
+
+/// Reads "#rrggbb"
+fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#').filter(|h| h.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// The colors a front end draws with
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Theme {
+    pub palette: Palette,
+    /// Colors set in the profile, in place of the palette's
+    pub overrides: Vec<(Role, Color)>,
+}
+
+impl Theme {
+    /// The theme of `palette` with the colors the "[theme]" section of
+    /// `config` sets
+    pub fn read(config: &Config, palette: Palette) -> Result<Theme, ConfigError> {
+        let mut theme = Theme {
+            palette,
+            overrides: Vec::new(),
+        };
+        let section = match config.section("theme") {
+            Some(section) => section,
+            None => return Ok(theme),
+        };
+        for role in Role::ALL {
+            let name = role.to_string();
+            if let Some(value) = section.get(&name) {
+                let color = parse_color(value).ok_or_else(|| ConfigError::Invalid {
+                    section: section.name.clone(),
+                    key: name,
+                    value: value.to_string(),
+                })?;
+                theme.overrides.push((role, color));
+            }
+        }
+        Ok(theme)
+    }
+
+    pub fn color(&self, role: Role) -> Color {
+        match self.overrides.iter().find(|(r, _)| *r == role) {
+            Some((_, color)) => *color,
+            None => self.palette.color(role),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes_tell_the_stances_apart() {
+        let stances = [
+            Stance::Friendly,
+            Stance::Neutral,
+            Stance::Hostile,
+            Stance::Unknown,
+        ];
+        for palette in [
+            Palette::Standard,
+            Palette::Deuteranopia,
+            Palette::Protanopia,
+            Palette::Tritanopia,
+        ] {
+            let colors: Vec<Color> = stances
+                .iter()
+                .map(|s| palette.color(Role::of_stance(*s)))
+                .collect();
+            for (i, color) in colors.iter().enumerate() {
+                assert!(!colors[i + 1..].contains(color), "{} {:?}", palette, colors);
+            }
+        }
+        // no red and green to confuse
+        assert_ne!(Palette::Deuteranopia.color(Role::Hostile), Color::Red);
+        assert_eq!("tritanopia".parse(), Ok(Palette::Tritanopia));
+        assert!("sepia".parse::<Palette>().is_err());
+        assert_eq!(Role::of_layer(Layer::DangerZone), Role::Warning);
+    }
+
+    #[test]
+    fn profile_overrides_the_palette() {
+        let config = Config::parse("[theme]\nhostile = #ff8000").unwrap();
+        let theme = Theme::read(&config, Palette::Deuteranopia).unwrap();
+        assert_eq!(theme.color(Role::Hostile), Color::Rgb(255, 128, 0));
+        assert_eq!(theme.color(Role::Friendly), BLUE);
+        let bad = Config::parse("[theme]\nland = brown").unwrap();
+        assert!(Theme::read(&bad, Palette::Standard).is_err());
+    }
+}
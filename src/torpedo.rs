@@ -0,0 +1,596 @@
+use crate::cargo;
+use crate::events::Event;
+use crate::fuse;
+use crate::intercept::{Emission, EmissionKind};
+use crate::noise::{self, Rig};
+use crate::physics::{normalize_angle, turn_towards, Point};
+use crate::pulse::Pulse;
+use crate::reliability::{Failure, Reliability};
+use crate::seeker::{AcousticSource, Seeker, SeekerGeneration, SourceKind};
+use crate::transient::{self, TransientKind};
+use crate::tuning::Tunable;
+use crate::wake::WakeHomer;
+use crate::weapons::{Guidance, SearchPattern, SpeedSetting, TorpedoSettings, WeaponError};
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+/// Horizontal distance in meters at which a torpedo strikes a hull
+pub const HIT_RADIUS: f32 = 15.0;
+/// Fraction of the hull destroyed by one warhead, in a ship without a hold
+/// (see cargo.rs)
+pub const TORPEDO_DAMAGE: f32 = 0.6;
+/// Distance in meters run before the torpedo can strike its own launcher
+const ARMING_RUN: f32 = 500.0;
+/// Meters within which a ship hears a torpedo pass it by
+const HEARD_RANGE: f32 = 150.0;
+/// Level in dB of the pings of an active seeker searching, averaged over
+/// the silences between them
+const SEARCH_PINGS: f32 = 165.0;
+/// Level of the pings of an active seeker homing, pinging four times as
+/// often
+const HOMING_PINGS: f32 = 171.0;
+
+/// What the seeker of a torpedo is doing
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum SeekerState {
+    /// Running out to the enable run, or without a seeker
+    #[default]
+    Running,
+    /// Enabled and listening or pinging for a target
+    Searching,
+    /// Holding a target and steering for it
+    Homing,
+}
+
+/// State of a torpedo running in the water
+#[derive(Debug, PartialEq, Clone)]
+pub struct TorpedoState {
+    pub shooter: EntityId,
+    pub guidance: Guidance,
+    pub settings: TorpedoSettings,
+    pub reliability: Reliability,
+    /// Meters travelled so far
+    pub run: f32,
+    pub max_run: f32,
+    /// Hulls the torpedo already passed under
+    pub passed: Vec<EntityId>,
+    /// Ships that heard it pass them by
+    pub missed: Vec<EntityId>,
+    pub wake_homer: Option<WakeHomer>,
+    pub seeker: SeekerState,
+}
+
+impl TorpedoState {
+    /// Whether the seeker sends pings of its own
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.guidance,
+            Guidance::Acoustic(SeekerGeneration::ActivePassive | SeekerGeneration::Modern)
+        )
+    }
+
+    /// Level in dB of the pings of the seeker as heard around, None while it
+    /// is silent
+    pub fn ping_level(&self) -> Option<f32> {
+        if !self.is_active() {
+            return None;
+        }
+        match self.seeker {
+            SeekerState::Running => None,
+            SeekerState::Searching => Some(SEARCH_PINGS),
+            SeekerState::Homing => Some(HOMING_PINGS),
+        }
+    }
+}
+
+/// Meters a torpedo runs at `speed` before its fuel is spent
+pub fn max_run(speed: SpeedSetting) -> f32 {
+    match speed {
+        SpeedSetting::Slow => 12_000.0,
+        SpeedSetting::Medium => 7_500.0,
+        SpeedSetting::Fast => 5_000.0,
+    }
+}
+
+/// Launches the torpedo in `tube` of `shooter` on `bearing` (game angle)
+pub fn fire(
+    world: &mut World,
+    shooter: EntityId,
+    tube: usize,
+    bearing: f32,
+) -> Result<EntityId, WeaponError> {
+    let boat = world
+        .entity_mut(shooter)
+        .ok_or(WeaponError::NoSuchTube(tube))?;
+    if boat.rig == Rig::UltraQuiet {
+        return Err(WeaponError::RiggedForUltraQuiet);
+    }
+    let position = boat.position.clone();
+    let station = boat.weapons.as_mut().ok_or(WeaponError::NoSuchTube(tube))?;
+    let guidance = station.guidance;
+    let reliability = station.reliability.clone();
+    let loaded = station
+        .tubes
+        .tube_mut(tube)
+        .ok_or(WeaponError::NoSuchTube(tube))?;
+    if !loaded.loaded {
+        return Err(WeaponError::TubeEmpty(tube));
+    }
+    loaded.loaded = false;
+    loaded.door_open = true;
+    let settings = loaded.settings.clone();
+    let id = launch(
+        world,
+        shooter,
+        position,
+        bearing,
+        guidance,
+        settings,
+        reliability,
+    );
+    transient::make(world, shooter, TransientKind::TorpedoLaunch);
+    world.emit(Event::TorpedoFired {
+        shooter,
+        torpedo: id,
+    });
+    Ok(id)
+}
+
+/// Puts a torpedo of `shooter` in the water at `position`, running on
+/// `bearing` (game angle) as set
+pub fn launch(
+    world: &mut World,
+    shooter: EntityId,
+    position: Point,
+    bearing: f32,
+    guidance: Guidance,
+    settings: TorpedoSettings,
+    reliability: Reliability,
+) -> EntityId {
+    let mut torpedo = Entity::new("torpedo", EntityKind::Torpedo, position);
+    torpedo.heading = normalize_angle(bearing);
+    torpedo.speed = settings.speed.meters_per_second();
+    torpedo.depth = reliability.running_depth(&mut world.rng, settings.depth);
+    torpedo.torpedo = Some(TorpedoState {
+        shooter,
+        guidance,
+        max_run: max_run(settings.speed),
+        settings,
+        reliability,
+        run: 0.0,
+        passed: Vec::new(),
+        missed: Vec::new(),
+        wake_homer: match guidance {
+            Guidance::WakeHoming => Some(WakeHomer::new(0.2, 35f32.to_radians())),
+            _ => None,
+        },
+        seeker: SeekerState::Running,
+    });
+    world.spawn(torpedo)
+}
+
+/// What the seeker of torpedo `id` can hear
+fn acoustic_sources(world: &World, id: EntityId, shooter: EntityId) -> Vec<AcousticSource> {
+    let mut sources: Vec<AcousticSource> = world
+        .entities
+        .iter()
+        .filter(|e| e.id != id && e.kind != EntityKind::Torpedo && !e.is_destroyed())
+        .map(|e| AcousticSource {
+            id: e.id,
+            kind: if e.id == shooter {
+                SourceKind::Launcher
+            } else {
+                SourceKind::Target
+            },
+            position: e.position.clone(),
+            depth: e.depth,
+            level: noise::radiated_level(e),
+        })
+        .collect();
+    sources.extend(world.decoys.iter().map(|d| d.source()));
+    for wake in world.wakes.iter() {
+        if let Some(point) = wake.points.back() {
+            sources.push(AcousticSource {
+                id: wake.owner,
+                kind: SourceKind::Wake,
+                position: point.position.clone(),
+                depth: 0.0,
+                level: 110.0 + 20.0 * point.strength(),
+            });
+        }
+    }
+    sources
+}
+
+/// Heading the search pattern asks for after `run` meters, turning by at
+/// most `turn` radians
+fn search_heading(pattern: SearchPattern, heading: f32, run: f32, turn: f32) -> f32 {
+    match pattern {
+        SearchPattern::Straight => heading,
+        SearchPattern::Circle => heading + turn * 0.5,
+        SearchPattern::Snake | SearchPattern::Ladder => {
+            // swing 30 degrees either side of the base course every 300 m
+            if ((run / 300.0) as u32).is_multiple_of(2) {
+                heading + turn * 0.2
+            } else {
+                heading - turn * 0.2
+            }
+        }
+    }
+}
+
+fn steer(world: &World, torpedo: &Entity, state: &mut TorpedoState, dt: f32) -> f32 {
+    if state.run < state.settings.enable_run {
+        return torpedo.heading;
+    }
+    let turn = Tunable::TorpedoTurnRate.get(&world.environment.tuning) * dt;
+    state.seeker = SeekerState::Searching;
+    match state.guidance {
+        Guidance::Acoustic(generation) => {
+            let sources = acoustic_sources(world, torpedo.id, state.shooter);
+            let seeker = Seeker::new(generation);
+            if let Some(source) =
+                seeker.select(&torpedo.position, torpedo.heading, torpedo.depth, &sources)
+            {
+                state.seeker = SeekerState::Homing;
+                let desired = torpedo.position.angle_to(&source.position);
+                return turn_towards(torpedo.heading, desired, turn);
+            }
+        }
+        Guidance::WakeHoming => {
+            let wake = world.wakes.iter().max_by(|a, b| {
+                a.intensity_at(&torpedo.position)
+                    .partial_cmp(&b.intensity_at(&torpedo.position))
+                    .unwrap()
+            });
+            if let (Some(wake), Some(homer)) = (wake, state.wake_homer.as_mut()) {
+                let desired = homer.steer(&torpedo.position, torpedo.heading, wake);
+                if homer.has_acquired() {
+                    state.seeker = SeekerState::Homing;
+                    return turn_towards(torpedo.heading, desired, turn);
+                }
+            }
+        }
+        Guidance::Unguided => state.seeker = SeekerState::Running,
+    }
+    normalize_angle(search_heading(
+        state.settings.search,
+        torpedo.heading,
+        state.run,
+        turn,
+    ))
+}
+
+/// The hull torpedo `torpedo` is about to strike, if any
+fn struck_hull(world: &World, torpedo: &Entity, state: &TorpedoState) -> Option<EntityId> {
+    world
+        .entities
+        .in_area(&torpedo.position, HIT_RADIUS)
+        .filter(|e| e.kind != EntityKind::Torpedo && !e.is_destroyed())
+        .filter(|e| e.id != state.shooter || state.run > ARMING_RUN)
+        .filter(|e| !state.passed.contains(&e.id))
+        .find(|e| e.kind != EntityKind::Submarine || (e.depth - torpedo.depth).abs() < 10.0)
+        .map(|e| e.id)
+}
+
+fn update_one(world: &mut World, id: EntityId, dt: f32) {
+    let torpedo = match world.entity(id) {
+        Some(torpedo) => torpedo.clone(),
+        None => return,
+    };
+    let mut state = match torpedo.torpedo.clone() {
+        Some(state) => state,
+        None => return,
+    };
+    let before = state.run;
+    state.run += torpedo.speed * dt;
+    if state.run > state.max_run {
+        world.remove(id);
+        world.emit(Event::TorpedoRanOut { torpedo: id });
+        return;
+    }
+    // the pistol arms at the enable run, and may go off there on nothing
+    let enable_run = state.settings.enable_run;
+    if before <= enable_run
+        && state.run > enable_run
+        && state.reliability.fires_early(&mut world.rng)
+    {
+        world.remove(id);
+        world.emit(Event::TorpedoFailed {
+            shooter: state.shooter,
+            target: None,
+            failure: Failure::Premature,
+        });
+        return;
+    }
+    let heading = steer(world, &torpedo, &mut state, dt);
+    if state.ping_level().is_some() {
+        world.emissions.push(Emission {
+            source: id,
+            kind: EmissionKind::TorpedoSeeker,
+            position: torpedo.position.clone(),
+            depth: torpedo.depth,
+            heading: torpedo.heading,
+            pulse: Pulse::default(),
+        });
+    }
+
+    if let Some(target) = struck_hull(world, &torpedo, &state) {
+        let (draft, impact_angle) = {
+            let hull = world.entity(target).unwrap();
+            (
+                hull.depth + hull.draft(),
+                normalize_angle(torpedo.heading - hull.heading),
+            )
+        };
+        let reliability = state.reliability.clone();
+        let pistol = state.settings.pistol;
+        match fuse::detonate(
+            &reliability,
+            &mut world.rng,
+            pistol,
+            torpedo.depth,
+            draft,
+            impact_angle,
+        ) {
+            Ok(detonation) => {
+                world.remove(id);
+                world.emit(Event::TorpedoHit {
+                    torpedo: id,
+                    shooter: state.shooter,
+                    target,
+                });
+                cargo::torpedo_hit(world, target, detonation.damage_factor());
+                return;
+            }
+            Err(failure) => {
+                world.emit(Event::TorpedoFailed {
+                    shooter: state.shooter,
+                    target: Some(target),
+                    failure,
+                });
+                if failure != Failure::RanDeep {
+                    world.remove(id);
+                    return;
+                }
+                state.passed.push(target);
+            }
+        }
+    }
+
+    for (target, distance) in passing(world, &torpedo, &state, dt) {
+        state.missed.push(target);
+        world.emit(Event::TorpedoPassed {
+            torpedo: id,
+            target,
+            distance,
+        });
+    }
+
+    if let Some(torpedo) = world.entity_mut(id) {
+        torpedo.heading = heading;
+        torpedo.torpedo = Some(state);
+    }
+}
+
+/// The ships `torpedo` got past in the last `dt` seconds, within hearing,
+/// and how close it came
+fn passing(world: &World, torpedo: &Entity, state: &TorpedoState, dt: f32) -> Vec<(EntityId, f32)> {
+    let velocity = torpedo.velocity();
+    let before = Point {
+        x: torpedo.position.x - velocity.x * dt,
+        y: torpedo.position.y - velocity.y * dt,
+    };
+    world
+        .entities
+        .in_area(&torpedo.position, HEARD_RANGE)
+        .filter(|e| e.kind != EntityKind::Torpedo && !e.is_destroyed())
+        .filter(|e| e.id != state.shooter && !state.missed.contains(&e.id))
+        .filter_map(|e| {
+            let closest = before.distance_to(&e.position);
+            let opening = torpedo.position.distance_to(&e.position) > closest;
+            (opening && closest <= HEARD_RANGE).then_some((e.id, closest))
+        })
+        .collect()
+}
+
+/// Runs, steers and detonates every torpedo in the water
+pub fn update(world: &mut World, dt: f32) {
+    let ids: Vec<EntityId> = world
+        .entities
+        .iter()
+        .filter(|e| e.torpedo.is_some())
+        .map(|e| e.id)
+        .collect();
+    for id in ids {
+        update_one(world, id, dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeker::SeekerGeneration;
+    use crate::weapons::{PresetLibrary, WeaponsStation};
+    use std::f32::consts::FRAC_PI_2;
+
+    fn setup(guidance: Guidance, reliability: Reliability) -> (World, EntityId, EntityId) {
+        let mut world = World::new();
+        let mut sub = Entity::new("sub", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        sub.depth = 15.0;
+        let mut station = WeaponsStation::new(2, PresetLibrary::new());
+        station.guidance = guidance;
+        station.reliability = reliability;
+        for tube in station.tubes.tubes.iter_mut() {
+            tube.settings.depth = 4.0;
+            tube.settings.enable_run = 300.0;
+        }
+        sub.weapons = Some(station);
+        let sub = world.spawn(sub);
+        let mut merchant = Entity::new(
+            "merchant",
+            EntityKind::Merchant,
+            Point { x: 0.0, y: 1500.0 },
+        );
+        merchant.speed = 4.0;
+        let merchant = world.spawn(merchant);
+        (world, sub, merchant)
+    }
+
+    fn count(world: &World, matches: fn(&Event) -> bool) -> usize {
+        world.events.iter().filter(|e| matches(&e.event)).count()
+    }
+
+    #[test]
+    fn straight_shot_hits() {
+        let (mut world, sub, merchant) = setup(Guidance::Unguided, Reliability::perfect());
+        world.entity_mut(merchant).unwrap().speed = 0.0;
+        fire(&mut world, sub, 1, FRAC_PI_2).unwrap();
+        assert_eq!(
+            fire(&mut world, sub, 1, FRAC_PI_2),
+            Err(WeaponError::TubeEmpty(1))
+        );
+        for _ in 0..200 {
+            world.step(0.5);
+        }
+        assert_eq!(count(&world, |e| matches!(e, Event::TorpedoHit { .. })), 1);
+        assert!(world.entity(merchant).unwrap().hull < 1.0);
+        assert!(world.entities.iter().all(|e| e.kind != EntityKind::Torpedo));
+    }
+
+    #[test]
+    fn homing_corrects_a_bad_bearing() {
+        let (mut world, sub, merchant) = setup(
+            Guidance::Acoustic(SeekerGeneration::EarlyPassive),
+            Reliability::perfect(),
+        );
+        fire(&mut world, sub, 1, FRAC_PI_2 - 0.2).unwrap();
+        for _ in 0..300 {
+            world.step(0.5);
+        }
+        assert!(world.entity(merchant).unwrap().hull < 1.0);
+    }
+
+    #[test]
+    fn seekers_ping_louder_homing() {
+        let (mut world, sub, _) = setup(
+            Guidance::Acoustic(SeekerGeneration::Modern),
+            Reliability::perfect(),
+        );
+        let id = fire(&mut world, sub, 1, FRAC_PI_2).unwrap();
+        let state = |world: &World| world.entity(id).unwrap().torpedo.clone().unwrap();
+        let quiet = noise::radiated_level(world.entity(id).unwrap());
+        assert_eq!(state(&world).seeker, SeekerState::Running);
+        assert_eq!(state(&world).ping_level(), None);
+        while state(&world).run < 300.0 {
+            world.step(0.5);
+        }
+        world.step(0.5);
+        assert_eq!(state(&world).seeker, SeekerState::Homing);
+        assert_eq!(state(&world).ping_level(), Some(HOMING_PINGS));
+        assert!(noise::radiated_level(world.entity(id).unwrap()) > quiet + 5.0);
+    }
+
+    #[test]
+    fn failures_reported() {
+        let mut duds = Reliability::perfect();
+        duds.dud = 1.0;
+        let (mut world, sub, merchant) = setup(Guidance::Unguided, duds);
+        world.entity_mut(merchant).unwrap().speed = 0.0;
+        fire(&mut world, sub, 1, FRAC_PI_2).unwrap();
+        for _ in 0..200 {
+            world.step(0.5);
+        }
+        assert_eq!(world.entity(merchant).unwrap().hull, 1.0);
+        let failures = crate::debrief::torpedo_failures(&world.events);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].failure, Failure::Dud);
+    }
+
+    #[test]
+    fn prematures_go_off_on_the_way() {
+        let mut early = Reliability::perfect();
+        early.premature = 1.0;
+        let (mut world, sub, merchant) = setup(Guidance::Unguided, early);
+        world.entity_mut(merchant).unwrap().speed = 0.0;
+        let torpedo = fire(&mut world, sub, 1, FRAC_PI_2).unwrap();
+        // short of the enable run, still running
+        for _ in 0..10 {
+            world.step(0.5);
+        }
+        assert!(world.entity(torpedo).is_some());
+        for _ in 0..200 {
+            world.step(0.5);
+        }
+        assert_eq!(world.entity(merchant).unwrap().hull, 1.0);
+        let failures = crate::debrief::torpedo_failures(&world.events);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].failure, Failure::Premature);
+        assert_eq!(failures[0].target, None);
+    }
+
+    #[test]
+    fn magnetic_pistols_break_the_back() {
+        let (mut world, sub, merchant) = setup(Guidance::Unguided, Reliability::perfect());
+        world.entity_mut(merchant).unwrap().speed = 0.0;
+        let tubes = &mut world
+            .entity_mut(sub)
+            .unwrap()
+            .weapons
+            .as_mut()
+            .unwrap()
+            .tubes;
+        for tube in tubes.tubes.iter_mut() {
+            tube.settings.depth = 9.0;
+        }
+        tubes.tube_mut(2).unwrap().settings.pistol = fuse::Pistol::Magnetic;
+        // under the keel, a contact pistol passes by...
+        fire(&mut world, sub, 1, FRAC_PI_2).unwrap();
+        for _ in 0..200 {
+            world.step(0.5);
+        }
+        assert_eq!(world.entity(merchant).unwrap().hull, 1.0);
+        // ...where a magnetic one goes off, worse than on the side
+        fire(&mut world, sub, 2, FRAC_PI_2).unwrap();
+        for _ in 0..200 {
+            world.step(0.5);
+        }
+        assert!(world.entity(merchant).unwrap().hull < 1.0 - TORPEDO_DAMAGE);
+    }
+
+    #[test]
+    fn close_misses_are_heard() {
+        let (mut world, sub, merchant) = setup(Guidance::Unguided, Reliability::perfect());
+        world.entity_mut(merchant).unwrap().speed = 0.0;
+        // 60 m ahead of the bow at 1500 m
+        fire(&mut world, sub, 1, FRAC_PI_2 - 0.04).unwrap();
+        for _ in 0..200 {
+            world.step(0.5);
+        }
+        assert_eq!(world.entity(merchant).unwrap().hull, 1.0);
+        let passed: Vec<f32> = world
+            .events
+            .iter()
+            .filter_map(|e| match e.event {
+                Event::TorpedoPassed {
+                    target, distance, ..
+                } if target == merchant => Some(distance),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(passed.len(), 1);
+        assert!(passed[0] > HIT_RADIUS && passed[0] < 80.0, "{:?}", passed);
+    }
+
+    #[test]
+    fn runs_out() {
+        let (mut world, sub, _) = setup(Guidance::Unguided, Reliability::perfect());
+        fire(&mut world, sub, 2, -FRAC_PI_2).unwrap();
+        for _ in 0..1000 {
+            world.step(1.0);
+        }
+        assert_eq!(
+            count(&world, |e| matches!(e, Event::TorpedoRanOut { .. })),
+            1
+        );
+    }
+}
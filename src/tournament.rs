@@ -0,0 +1,426 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::ai::SubmarineAi;
+use crate::captain::{Arena, BotCaptain, CaptainView};
+use crate::command::Command;
+use crate::config::{Config, ConfigError};
+use crate::editor::EditError;
+use crate::navigation::{self, Navigation};
+use crate::random::Rng;
+use crate::scenario::Scenario;
+
+// #############################
+// #        TOURNAMENTS        #
+// #############################
+
+// Runs headless matches between entrants over a pool of scenarios and
+// tallies how each did, to see what a change to the AI does to its
+// results. An entrant is a bot captain (see captain.rs) or the built-in
+// AI, with keys set on the placement of the boat it gets (crew, morale,
+// tracker...). Every entrant meets every other in two seats of each
+// scenario, once in each seat, as many rounds as asked, each round with
+// its own seed. A match is won by the boat with more hull left at the end.
+// Described in a file of its own:
+//
+// [tournament]
+// scenarios = duel.cfg, narrows.cfg
+// seats = U-47, U-99      # the two boats given to the entrants
+// seconds = 3600          # length of a match
+// rounds = 4
+// seed = 1
+//
+// [entrant.veteran]
+// crew = veteran
+// tracker = kalman
+//
+// [entrant.silent]
+// captain = silent        # a registered bot captain
+//
+// and run with "subsim tournament <file>".
+
+/// Hull left between two boats that still makes a draw
+const DRAW_MARGIN: f32 = 0.05;
+
+/// Makes a fresh bot captain for each match
+pub type CaptainFactory = fn() -> Box<dyn BotCaptain>;
+
+/// Rigs for ultra quiet and waits, a yardstick for other captains
+struct Silent;
+
+impl BotCaptain for Silent {
+    fn name(&self) -> &str {
+        "silent"
+    }
+
+    fn orders(&mut self, view: &CaptainView) -> Vec<Command> {
+        if view.time > 0.0 {
+            return Vec::new();
+        }
+        vec![Command::parse("rig ultra").unwrap()]
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entrant {
+    pub name: String,
+    /// Registered bot captain, None for the built-in AI
+    pub captain: Option<String>,
+    /// Keys set on the placement of the boat
+    pub settings: Vec<(String, String)>,
+}
+
+pub struct Tournament {
+    /// Scenario files
+    pub scenarios: Vec<String>,
+    pub seats: [String; 2],
+    pub seconds: u32,
+    pub rounds: u32,
+    pub seed: u64,
+    pub entrants: Vec<Entrant>,
+    captains: Vec<(String, CaptainFactory)>,
+}
+
+/// How one match ended, hull left in each seat
+#[derive(Debug, PartialEq, Clone)]
+pub struct Outcome {
+    pub entrants: [String; 2],
+    pub hulls: [f32; 2],
+}
+
+impl Outcome {
+    /// Seat of the winner, None for a draw
+    pub fn winner(&self) -> Option<usize> {
+        let difference = self.hulls[0] - self.hulls[1];
+        if difference.abs() <= DRAW_MARGIN {
+            None
+        } else if difference > 0.0 {
+            Some(0)
+        } else {
+            Some(1)
+        }
+    }
+}
+
+impl Tournament {
+    pub fn read(config: &Config) -> Result<Tournament, ConfigError> {
+        let header = config
+            .section("tournament")
+            .ok_or_else(|| ConfigError::Missing {
+                section: "tournament".to_string(),
+                key: "seats".to_string(),
+            })?;
+        let list = |value: &str| -> Vec<String> {
+            value
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        let seats = list(header.get("seats").unwrap_or(""));
+        if seats.len() != 2 {
+            return Err(ConfigError::Invalid {
+                section: "tournament".to_string(),
+                key: "seats".to_string(),
+                value: header.get("seats").unwrap_or("").to_string(),
+            });
+        }
+        let mut tournament = Tournament {
+            scenarios: list(header.get("scenarios").unwrap_or("")),
+            seats: [seats[0].clone(), seats[1].clone()],
+            seconds: header.parse_or("seconds", 3600)?,
+            rounds: header.parse_or("rounds", 1)?,
+            seed: header.parse_or("seed", 1)?,
+            entrants: Vec::new(),
+            captains: Vec::new(),
+        };
+        for (name, section) in config.sections_with_prefix("entrant") {
+            tournament.entrants.push(Entrant {
+                name: name.to_string(),
+                captain: section.get("captain").map(|c| c.to_string()),
+                settings: section
+                    .entries()
+                    .filter(|(key, _)| *key != "captain")
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            });
+        }
+        tournament.register("silent", || Box::new(Silent));
+        Ok(tournament)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Tournament, ConfigError> {
+        Tournament::read(&Config::load(path)?)
+    }
+
+    /// Makes a bot captain available to entrants by `name`
+    pub fn register(&mut self, name: &str, factory: CaptainFactory) {
+        self.captains.retain(|(n, _)| n != name);
+        self.captains.push((name.to_string(), factory));
+    }
+
+    /// Runs one match of `entrants` in the seats of `scenario`
+    fn play(
+        &self,
+        scenario: &Config,
+        entrants: [&Entrant; 2],
+        seed: u64,
+    ) -> Result<Outcome, EditError> {
+        let mut config = scenario.clone();
+        for (seat, entrant) in self.seats.iter().zip(entrants.iter()) {
+            let name = format!("entity.{}", seat);
+            if config.section(&name).is_none() {
+                return Err(EditError::UnknownEntity(seat.clone()));
+            }
+            let section = config.section_mut(&name);
+            for (key, value) in &entrant.settings {
+                section.set(key, value);
+            }
+        }
+        let scenario = Scenario::from_config(&config)?;
+        let mut sim = scenario.build().map_err(EditError::Invalid)?;
+        sim.world.rng = Rng::new(seed);
+        sim.navigation = Navigation::new(sim.world.rng.stream(navigation::STREAM));
+        let mut arena = Arena::new(sim);
+        for (seat, entrant) in self.seats.iter().zip(entrants.iter()) {
+            match &entrant.captain {
+                Some(captain) => {
+                    let factory = self
+                        .captains
+                        .iter()
+                        .find(|(n, _)| n == captain)
+                        .map(|(_, f)| f)
+                        .ok_or_else(|| EditError::Usage(format!("no captain '{}'", captain)))?;
+                    arena.seat(seat, factory()).map_err(EditError::Usage)?;
+                }
+                None => {
+                    // the scenario's own player has no AI of its own
+                    let placement = scenario.placements.iter().find(|p| &p.name == seat);
+                    let id = arena.sim.world.entities.by_name(seat).map(|e| e.id);
+                    let boat = id.and_then(|id| arena.sim.world.entity_mut(id));
+                    if let (Some(placement), Some(boat)) = (placement, boat) {
+                        if boat.ai.is_none() && boat.weapons.is_some() {
+                            let max_speed = scenario
+                                .class(&placement.class)
+                                .map_or(boat.speed, |c| c.max_speed);
+                            let mut ai = SubmarineAi::new(max_speed, boat.heading, boat.depth);
+                            ai.tracker = placement.tracker;
+                            boat.ai = Some(ai);
+                        }
+                    }
+                }
+            }
+        }
+        arena.run(self.seconds);
+        let hull = |seat: &String| {
+            arena
+                .sim
+                .world
+                .entities
+                .by_name(seat)
+                .map_or(0.0, |e| e.hull.max(0.0))
+        };
+        Ok(Outcome {
+            entrants: [entrants[0].name.clone(), entrants[1].name.clone()],
+            hulls: [hull(&self.seats[0]), hull(&self.seats[1])],
+        })
+    }
+
+    /// Every match of the tournament on `scenario`
+    pub fn matches(&self, scenario: &Config) -> Result<Vec<Outcome>, EditError> {
+        let mut outcomes = Vec::new();
+        for round in 0..self.rounds {
+            for a in &self.entrants {
+                for b in self.entrants.iter().filter(|b| b.name != a.name) {
+                    let seed = self.seed.wrapping_add(round as u64);
+                    outcomes.push(self.play(scenario, [a, b], seed)?);
+                }
+            }
+        }
+        Ok(outcomes)
+    }
+
+    /// Runs the whole tournament
+    pub fn run(&self) -> Result<Report, EditError> {
+        let mut outcomes = Vec::new();
+        for path in &self.scenarios {
+            outcomes.extend(self.matches(&Config::load(path)?)?);
+        }
+        Ok(Report::tally(&self.entrants, &outcomes))
+    }
+}
+
+/// How one entrant did over the tournament
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Record {
+    pub entrant: String,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Hull left, summed over the matches
+    pub hull: f32,
+}
+
+impl Record {
+    pub fn matches(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn win_rate(&self) -> f32 {
+        self.wins as f32 / self.matches().max(1) as f32
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Report {
+    /// Best win rate first
+    pub records: Vec<Record>,
+}
+
+impl Report {
+    pub fn tally(entrants: &[Entrant], outcomes: &[Outcome]) -> Report {
+        let mut records: Vec<Record> = entrants
+            .iter()
+            .map(|e| Record {
+                entrant: e.name.clone(),
+                ..Record::default()
+            })
+            .collect();
+        for outcome in outcomes {
+            let winner = outcome.winner();
+            for seat in 0..2 {
+                let record = match records
+                    .iter_mut()
+                    .find(|r| r.entrant == outcome.entrants[seat])
+                {
+                    Some(record) => record,
+                    None => continue,
+                };
+                record.hull += outcome.hulls[seat];
+                match winner {
+                    None => record.draws += 1,
+                    Some(w) if w == seat => record.wins += 1,
+                    Some(_) => record.losses += 1,
+                }
+            }
+        }
+        records.sort_by(|a, b| b.win_rate().total_cmp(&a.win_rate()));
+        Report { records }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<16} {:>7} {:>5} {:>5} {:>6} {:>8} {:>9}",
+            "entrant", "matches", "wins", "draws", "losses", "win rate", "hull left"
+        )?;
+        for r in &self.records {
+            write!(
+                f,
+                "\n{:<16} {:>7} {:>5} {:>5} {:>6} {:>7.0}% {:>9.2}",
+                r.entrant,
+                r.matches(),
+                r.wins,
+                r.draws,
+                r.losses,
+                r.win_rate() * 100.0,
+                r.hull / r.matches().max(1) as f32
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = "
+[scenario]
+name = Duel
+player = U-99
+
+[class.type_viic]
+kind = submarine
+max_speed = 17.7
+tubes = 5
+
+[entity.U-47]
+class = type_viic
+x = -1500
+y = 0
+depth = 60
+
+[entity.U-99]
+class = type_viic
+x = 1500
+y = 0
+depth = 40
+";
+
+    const TOURNAMENT: &str = "
+[tournament]
+seats = U-47, U-99
+seconds = 30
+rounds = 2
+
+[entrant.veteran]
+crew = veteran
+tracker = kalman
+
+[entrant.silent]
+captain = silent
+";
+
+    #[test]
+    fn every_entrant_meets_every_other() {
+        let tournament = Tournament::read(&Config::parse(TOURNAMENT).unwrap()).unwrap();
+        assert_eq!(tournament.entrants.len(), 2);
+        assert_eq!(tournament.entrants[0].settings.len(), 2);
+        let outcomes = tournament
+            .matches(&Config::parse(SCENARIO).unwrap())
+            .unwrap();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes[0].entrants, ["veteran", "silent"]);
+        assert_eq!(outcomes[1].entrants, ["silent", "veteran"]);
+        let report = Report::tally(&tournament.entrants, &outcomes);
+        assert!(report.records.iter().all(|r| r.matches() == 4));
+        assert_eq!(report.to_string().lines().count(), 3);
+    }
+
+    #[test]
+    fn tallies_wins_and_draws() {
+        let entrants: Vec<Entrant> = ["a", "b"]
+            .iter()
+            .map(|n| Entrant {
+                name: n.to_string(),
+                captain: None,
+                settings: Vec::new(),
+            })
+            .collect();
+        let outcome = |a: &str, b: &str, hulls| Outcome {
+            entrants: [a.to_string(), b.to_string()],
+            hulls,
+        };
+        let report = Report::tally(
+            &entrants,
+            &[
+                outcome("a", "b", [1.0, 0.0]),
+                outcome("b", "a", [0.2, 0.9]),
+                outcome("a", "b", [0.5, 0.52]),
+            ],
+        );
+        let a = &report.records[0];
+        assert_eq!(
+            (a.entrant.as_str(), a.wins, a.draws, a.losses),
+            ("a", 2, 1, 0)
+        );
+        assert_eq!(report.records[1].losses, 2);
+        assert!((a.win_rate() - 2.0 / 3.0).abs() < 1e-6);
+
+        let bad = Config::parse("[tournament]\nseats = U-47").unwrap();
+        assert!(Tournament::read(&bad).is_err());
+    }
+}
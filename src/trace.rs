@@ -0,0 +1,513 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// #############################
+// #          TRACING          #
+// #############################
+
+// Spans and events in the manner of the tracing crate, without the
+// dependency: the game builds offline with the crates it already depends on
+// (tui, termion and theirs), and tracing with tracing-subscriber is not
+// among them. The names follow the crate's so the calls can be swapped for
+// its macros once it is. A span marks a stretch of work (a tick, a
+// subsystem, one boat's AI) and carries fields such as the entity id;
+// events are log lines written inside the spans that are open. Nothing is
+// recorded unless a Tracer is installed on the thread, so the cost when off
+// is one check.
+//
+// What gets logged is chosen by a filter in the RUST_LOG manner:
+//
+// SUBSIM_LOG=info,ai=trace,torpedo=debug
+//
+// a default level, then levels per target. The filter can be changed while
+// running. When profiling, the time spent in every stack of spans is added
+// up and written as folded stacks ("tick;world;ai 1234", microseconds),
+// which flamegraph.pl and inferno turn into a flame graph. The time of each
+// subsystem (the spans directly inside the outermost one) is also kept
+// tick by tick, for the median, 90th and 99th percentile and worst tick
+// the "profile" command reports: the spikes of a big scenario show there,
+// where totals hide them.
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Level, String> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("unknown log level '{}'", s)),
+        }
+    }
+}
+
+/// Which levels are logged for which targets
+#[derive(Debug, PartialEq, Clone)]
+pub struct Filter {
+    /// Most verbose level logged, None when off
+    pub default: Option<Level>,
+    /// The same for given targets
+    pub targets: Vec<(String, Option<Level>)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            default: Some(Level::Info),
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Reads "level,target=level,..."; "off" logs nothing
+    fn from_str(s: &str) -> Result<Filter, String> {
+        let level = |s: &str| match s {
+            "off" => Ok(None),
+            s => s.parse().map(Some),
+        };
+        let mut filter = Filter {
+            default: None,
+            targets: Vec::new(),
+        };
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, value)) => filter
+                    .targets
+                    .push((target.trim().to_string(), level(value.trim())?)),
+                None => filter.default = level(directive)?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl Filter {
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        let most = self
+            .targets
+            .iter()
+            .find(|(t, _)| t == target)
+            .map_or(self.default, |(_, l)| *l);
+        most.is_some_and(|most| level <= most)
+    }
+}
+
+/// Named values attached to a span or an event
+pub type Fields<'a> = &'a [(&'a str, &'a dyn fmt::Display)];
+
+fn format_fields(fields: Fields) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A span that is open
+struct Frame {
+    name: &'static str,
+    /// "name{field=value ...}", for the log lines
+    label: String,
+    start: Instant,
+    /// Time spent in the spans opened inside this one
+    children: Duration,
+}
+
+pub struct Tracer {
+    pub filter: Filter,
+    /// Where log lines are written; they are kept in `lines` without one
+    out: Option<Box<dyn Write>>,
+    pub lines: Vec<String>,
+    /// Whether to time spans
+    profile: bool,
+    stack: Vec<Frame>,
+    /// Time spent in each stack of spans, not counting the spans inside
+    folded: HashMap<String, Duration>,
+    /// Time spent so far in each subsystem during the outermost span
+    current: HashMap<&'static str, Duration>,
+    /// Time spent in the outermost span and each subsystem, span by span
+    samples: HashMap<&'static str, Vec<Duration>>,
+}
+
+/// How long a subsystem takes per tick
+#[derive(Debug, PartialEq, Clone)]
+pub struct Percentiles {
+    pub name: &'static str,
+    /// Ticks the subsystem ran in
+    pub ticks: usize,
+    pub median: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    fn of(name: &'static str, samples: &[Duration]) -> Percentiles {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let rank = |p: f32| sorted[((p * sorted.len() as f32).ceil() as usize).max(1) - 1];
+        Percentiles {
+            name,
+            ticks: sorted.len(),
+            median: rank(0.5),
+            p90: rank(0.9),
+            p99: rank(0.99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+impl fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<12} {:>6} {:>8} {:>8} {:>8} {:>8}",
+            self.name,
+            self.ticks,
+            self.median.as_micros(),
+            self.p90.as_micros(),
+            self.p99.as_micros(),
+            self.max.as_micros()
+        )
+    }
+}
+
+impl Tracer {
+    pub fn new(filter: Filter) -> Tracer {
+        Tracer {
+            filter,
+            out: None,
+            lines: Vec::new(),
+            profile: false,
+            stack: Vec::new(),
+            folded: HashMap::new(),
+            current: HashMap::new(),
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Writes the log lines to `out` as they come
+    pub fn writing_to(mut self, out: Box<dyn Write>) -> Tracer {
+        self.out = Some(out);
+        self
+    }
+
+    /// Times every span, for `folded`
+    pub fn profiling(mut self) -> Tracer {
+        self.profile = true;
+        self
+    }
+
+    fn log(&mut self, line: String) {
+        match self.out.as_mut() {
+            // a log that cannot be written must not stop the game
+            Some(out) => {
+                let _ = writeln!(out, "{}", line);
+            }
+            None => self.lines.push(line),
+        }
+    }
+
+    fn exit(&mut self) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        if !self.profile {
+            return;
+        }
+        let total = frame.start.elapsed();
+        let names: Vec<&str> = self
+            .stack
+            .iter()
+            .map(|f| f.name)
+            .chain(Some(frame.name))
+            .collect();
+        *self.folded.entry(names.join(";")).or_default() += total.saturating_sub(frame.children);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += total;
+        }
+        match self.stack.len() {
+            0 => {
+                self.samples.entry(frame.name).or_default().push(total);
+                for (name, time) in self.current.drain() {
+                    self.samples.entry(name).or_default().push(time);
+                }
+            }
+            1 => *self.current.entry(frame.name).or_default() += total,
+            _ => {}
+        }
+    }
+
+    /// Time spent in each stack of spans, as "a;b;c <microseconds>" lines
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<String> = self
+            .folded
+            .iter()
+            .map(|(stack, time)| format!("{} {}", stack, time.as_micros()))
+            .collect();
+        stacks.sort();
+        stacks.join("\n")
+    }
+
+    /// Per tick times of the outermost span and of each subsystem, the
+    /// slowest at the 99th percentile first
+    pub fn percentiles(&self) -> Vec<Percentiles> {
+        let mut all: Vec<Percentiles> = self
+            .samples
+            .iter()
+            .map(|(name, samples)| Percentiles::of(name, samples))
+            .collect();
+        all.sort_by(|a, b| b.p99.cmp(&a.p99).then(a.name.cmp(b.name)));
+        all
+    }
+
+    /// The percentiles as a table, in microseconds
+    pub fn report(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<12} {:>6} {:>8} {:>8} {:>8} {:>8}",
+            "span", "ticks", "p50", "p90", "p99", "max"
+        )];
+        lines.extend(self.percentiles().iter().map(|p| p.to_string()));
+        lines.join("\n")
+    }
+}
+
+thread_local! {
+    static TRACER: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+}
+
+/// Makes `tracer` receive the spans and events of this thread
+pub fn install(tracer: Tracer) {
+    TRACER.with(|t| *t.borrow_mut() = Some(tracer));
+}
+
+/// Takes the tracer of this thread back, stopping tracing
+pub fn uninstall() -> Option<Tracer> {
+    TRACER.with(|t| t.borrow_mut().take())
+}
+
+/// Starts timing spans, installing a tracer logging nothing when there is
+/// none
+pub fn start_profiling() {
+    TRACER.with(|t| {
+        let mut tracer = t.borrow_mut();
+        let tracer = tracer.get_or_insert_with(|| {
+            Tracer::new(Filter {
+                default: None,
+                targets: Vec::new(),
+            })
+        });
+        tracer.profile = true;
+    });
+}
+
+/// The per tick report of the installed tracer, None unless profiling
+pub fn profile_report() -> Option<String> {
+    TRACER.with(|t| {
+        t.borrow()
+            .as_ref()
+            .filter(|tracer| tracer.profile)
+            .map(Tracer::report)
+    })
+}
+
+/// Changes what the installed tracer logs
+pub fn set_filter(filter: Filter) {
+    TRACER.with(|t| {
+        if let Some(tracer) = t.borrow_mut().as_mut() {
+            tracer.filter = filter;
+        }
+    });
+}
+
+/// Closes its span when dropped
+#[must_use = "the span closes when this is dropped"]
+pub struct Entered {
+    active: bool,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        if self.active {
+            TRACER.with(|t| {
+                if let Some(tracer) = t.borrow_mut().as_mut() {
+                    tracer.exit();
+                }
+            });
+        }
+    }
+}
+
+/// Opens a span named `name` until the returned guard is dropped
+pub fn span(name: &'static str, fields: Fields) -> Entered {
+    TRACER.with(|t| {
+        let mut tracer = t.borrow_mut();
+        let tracer = match tracer.as_mut() {
+            Some(tracer) => tracer,
+            None => return Entered { active: false },
+        };
+        let label = if fields.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}{{{}}}", name, format_fields(fields))
+        };
+        tracer.stack.push(Frame {
+            name,
+            label,
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+        Entered { active: true }
+    })
+}
+
+/// Logs `message` from `target`, when the filter lets it through
+pub fn event(level: Level, target: &str, message: &str, fields: Fields) {
+    TRACER.with(|t| {
+        let mut tracer = t.borrow_mut();
+        let tracer = match tracer.as_mut() {
+            Some(tracer) if tracer.filter.enabled(target, level) => tracer,
+            _ => return,
+        };
+        let mut line = format!("{:>5} ", level);
+        if !tracer.stack.is_empty() {
+            let spans: Vec<&str> = tracer.stack.iter().map(|f| f.label.as_str()).collect();
+            line.push_str(&spans.join(":"));
+            line.push_str(": ");
+        }
+        line.push_str(target);
+        line.push_str(": ");
+        line.push_str(message);
+        if !fields.is_empty() {
+            line.push(' ');
+            line.push_str(&format_fields(fields));
+        }
+        tracer.log(line);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let filter: Filter = "warn, ai=trace,torpedo=off".parse().unwrap();
+        assert_eq!(filter.targets.len(), 2);
+        assert!("ai=loud".parse::<Filter>().is_err());
+        assert!(filter.enabled("ai", Level::Trace));
+        assert!(!filter.enabled("torpedo", Level::Error));
+        assert!(filter.enabled("world", Level::Error));
+        assert!(!filter.enabled("world", Level::Info));
+        let off: Filter = "off".parse().unwrap();
+        assert!(!off.enabled("world", Level::Error));
+    }
+
+    #[test]
+    fn events_inside_spans() {
+        event(Level::Error, "world", "nobody listening", &[]);
+        install(Tracer::new("info,ai=debug".parse().unwrap()));
+        {
+            let _tick = span("tick", &[("time", &12.0)]);
+            let _ai = span("ai", &[("entity", &3)]);
+            event(Level::Debug, "ai", "new threat", &[("contact", &5)]);
+            event(Level::Debug, "world", "left out", &[]);
+        }
+        event(Level::Info, "world", "done", &[]);
+        let tracer = uninstall().unwrap();
+        assert_eq!(
+            tracer.lines,
+            vec![
+                "DEBUG tick{time=12}:ai{entity=3}: ai: new threat contact=5",
+                " INFO world: done",
+            ]
+        );
+        assert!(tracer.stack.is_empty());
+    }
+
+    #[test]
+    fn folded_stacks() {
+        install(Tracer::new(Filter::default()).profiling());
+        for _ in 0..2 {
+            let _tick = span("tick", &[]);
+            let _world = span("world", &[]);
+            drop(span("ai", &[]));
+            drop(span("torpedo", &[]));
+        }
+        let tracer = uninstall().unwrap();
+        let folded = tracer.folded();
+        let stacks: Vec<&str> = folded
+            .lines()
+            .map(|l| l.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            vec!["tick", "tick;world", "tick;world;ai", "tick;world;torpedo"]
+        );
+    }
+
+    #[test]
+    fn percentiles_per_tick() {
+        assert_eq!(profile_report(), None);
+        start_profiling();
+        for _ in 0..10 {
+            let _tick = span("tick", &[]);
+            let _world = span("world", &[]);
+            drop(span("ai", &[]));
+        }
+        let report = profile_report().unwrap();
+        let tracer = uninstall().unwrap();
+        let percentiles = tracer.percentiles();
+        let names: Vec<&str> = percentiles.iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["tick", "world"]);
+        assert!(percentiles.iter().all(|p| p.ticks == 10));
+        assert!(percentiles[0].median >= percentiles[1].median);
+        assert!(percentiles[0].max >= percentiles[0].p99);
+        assert_eq!(report.lines().count(), 3);
+        assert!(report.starts_with("span"));
+
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+        let p = Percentiles::of("ai", &samples);
+        assert_eq!(
+            (p.median, p.p90, p.p99, p.max),
+            (
+                Duration::from_micros(50),
+                Duration::from_micros(90),
+                Duration::from_micros(99),
+                Duration::from_micros(100)
+            )
+        );
+    }
+}
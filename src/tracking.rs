@@ -0,0 +1,305 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::physics::{normalize_angle, Point};
+
+// #############################
+// #     KALMAN TRACKING       #
+// #############################
+
+// By default a contact is where the observations of the last tick put it,
+// the least-squares fit of their bearings and ranges (see contacts.rs). The
+// Kalman tracker instead follows each contact with an extended Kalman
+// filter on a constant-velocity model: the state is the position and
+// velocity of the target, with their covariance, carried forward between
+// observations and corrected by every bearing and range as it comes in.
+// Bearings only give the range slowly, as the own ship maneuvers, but the
+// filter keeps a course and speed the fire control can lead a shot with.
+// The player picks the tracker:
+//
+// tracker kalman
+// tracker least-squares
+//
+// and an AI boat uses it for its fire control when its placement says so:
+//
+// [entity.u48]
+// tracker = kalman
+
+/// Meters assumed to a target first held on a bearing alone
+const FIRST_RANGE: f32 = 5_000.0;
+/// Standard deviation in meters of that assumed range
+const FIRST_RANGE_ERROR: f32 = 4_000.0;
+/// Standard deviation in meters per second of the speed of a new track
+const FIRST_SPEED_ERROR: f32 = 6.0;
+/// Square meters per second cubed of maneuvering the model allows
+const MANEUVER_NOISE: f32 = 0.001;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Tracker {
+    /// Fits the observations of each tick on their own
+    #[default]
+    LeastSquares,
+    /// Follows each contact with an extended Kalman filter
+    Kalman,
+}
+
+impl FromStr for Tracker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "least-squares" => Ok(Tracker::LeastSquares),
+            "kalman" => Ok(Tracker::Kalman),
+            _ => Err(format!("unknown tracker '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Tracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Tracker::LeastSquares => "least-squares",
+            Tracker::Kalman => "kalman",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+type Matrix = [[f32; 4]; 4];
+
+/// What a Kalman filter knows of one target
+#[derive(Debug, PartialEq, Clone)]
+pub struct Track {
+    /// Meters east and north, then meters per second east and north
+    pub state: [f32; 4],
+    pub covariance: Matrix,
+    /// Seconds into the scenario the state is for
+    pub time: f32,
+}
+
+impl Track {
+    /// A track on a target first held from `observer` on `bearing` (game
+    /// angle) with a `bearing_error`, and at a range with its error when
+    /// one was measured
+    pub fn new(
+        observer: &Point,
+        bearing: f32,
+        bearing_error: f32,
+        range: Option<(f32, f32)>,
+        time: f32,
+    ) -> Track {
+        let (range, range_error) = range.unwrap_or((FIRST_RANGE, FIRST_RANGE_ERROR));
+        let (sin, cos) = bearing.sin_cos();
+        let along = range_error.powi(2);
+        let across = (range * bearing_error).powi(2);
+        let speed = FIRST_SPEED_ERROR.powi(2);
+        let mut covariance = [[0.0; 4]; 4];
+        covariance[0][0] = along * cos * cos + across * sin * sin;
+        covariance[1][1] = along * sin * sin + across * cos * cos;
+        covariance[0][1] = (along - across) * sin * cos;
+        covariance[1][0] = covariance[0][1];
+        covariance[2][2] = speed;
+        covariance[3][3] = speed;
+        Track {
+            state: [observer.x + range * cos, observer.y + range * sin, 0.0, 0.0],
+            covariance,
+            time,
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        Point {
+            x: self.state[0],
+            y: self.state[1],
+        }
+    }
+
+    /// Meters per second east and north
+    pub fn velocity(&self) -> Point {
+        Point {
+            x: self.state[2],
+            y: self.state[3],
+        }
+    }
+
+    /// Where the target will be `seconds` from the time of the track
+    pub fn projected(&self, seconds: f32) -> Point {
+        Point {
+            x: self.state[0] + self.state[2] * seconds,
+            y: self.state[1] + self.state[3] * seconds,
+        }
+    }
+
+    /// Meters of standard deviation of the position
+    pub fn position_error(&self) -> f32 {
+        (self.covariance[0][0] + self.covariance[1][1]).sqrt()
+    }
+
+    /// Carries the track forward to `time`
+    pub fn predict(&mut self, time: f32) {
+        let dt = time - self.time;
+        if dt <= 0.0 {
+            return;
+        }
+        self.time = time;
+        self.state[0] += self.state[2] * dt;
+        self.state[1] += self.state[3] * dt;
+        let mut transition = identity();
+        transition[0][2] = dt;
+        transition[1][3] = dt;
+        let mut covariance = multiply(
+            &multiply(&transition, &self.covariance),
+            &transpose(&transition),
+        );
+        let q = MANEUVER_NOISE;
+        for axis in 0..2 {
+            covariance[axis][axis] += q * dt.powi(3) / 3.0;
+            covariance[axis][axis + 2] += q * dt.powi(2) / 2.0;
+            covariance[axis + 2][axis] += q * dt.powi(2) / 2.0;
+            covariance[axis + 2][axis + 2] += q * dt;
+        }
+        self.covariance = covariance;
+    }
+
+    /// Corrects the track with a bearing (game angle) taken from `observer`
+    pub fn bearing(&mut self, observer: &Point, bearing: f32, error: f32) {
+        let dx = self.state[0] - observer.x;
+        let dy = self.state[1] - observer.y;
+        let squared = (dx * dx + dy * dy).max(1.0);
+        let expected = dy.atan2(dx);
+        let jacobian = [-dy / squared, dx / squared, 0.0, 0.0];
+        self.correct(jacobian, normalize_angle(bearing - expected), error);
+    }
+
+    /// Corrects the track with a range measured from `observer`
+    pub fn range(&mut self, observer: &Point, range: f32, error: f32) {
+        let dx = self.state[0] - observer.x;
+        let dy = self.state[1] - observer.y;
+        let expected = (dx * dx + dy * dy).sqrt().max(1.0);
+        let jacobian = [dx / expected, dy / expected, 0.0, 0.0];
+        self.correct(jacobian, range - expected, error);
+    }
+
+    /// Kalman update with one measurement off by `residual` from what the
+    /// state expects, `jacobian` being how it changes with the state
+    fn correct(&mut self, jacobian: [f32; 4], residual: f32, error: f32) {
+        let p = &self.covariance;
+        let mut ph = [0.0; 4];
+        for (i, row) in p.iter().enumerate() {
+            ph[i] = (0..4).map(|j| row[j] * jacobian[j]).sum();
+        }
+        let innovation: f32 = (0..4).map(|i| jacobian[i] * ph[i]).sum::<f32>() + error * error;
+        let gain: Vec<f32> = ph.iter().map(|v| v / innovation).collect();
+        for (x, k) in self.state.iter_mut().zip(&gain) {
+            *x += k * residual;
+        }
+        // Joseph form, which keeps the covariance positive
+        let mut keep = identity();
+        for i in 0..4 {
+            for j in 0..4 {
+                keep[i][j] -= gain[i] * jacobian[j];
+            }
+        }
+        let mut covariance = multiply(&multiply(&keep, p), &transpose(&keep));
+        for i in 0..4 {
+            for j in 0..4 {
+                covariance[i][j] += gain[i] * gain[j] * error * error;
+            }
+        }
+        self.covariance = covariance;
+    }
+}
+
+fn identity() -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    m
+}
+
+fn transpose(m: &Matrix) -> Matrix {
+    let mut t = [[0.0; 4]; 4];
+    for i in 0..4 {
+        for j in 0..4 {
+            t[j][i] = m[i][j];
+        }
+    }
+    t
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for i in 0..4 {
+        for j in 0..4 {
+            m[i][j] = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn converges_on_a_steady_target() {
+        // a merchant making 5 m/s north from 6 km east, tracked on bearings
+        // from a zigzagging boat, with a periscope range now and then
+        let mut rng = Rng::default();
+        let target = |t: f32| Point {
+            x: 6000.0,
+            y: 5.0 * t,
+        };
+        let own = |t: f32| {
+            // 5 m/s on legs of ten minutes, alternately north east and north west
+            let leg = 3000.0 * (t / 1200.0).fract();
+            Point {
+                x: if leg < 1500.0 { leg } else { 3000.0 - leg },
+                y: t * 3.5,
+            }
+        };
+        let error = 1_f32.to_radians();
+        let bearing = |t: f32, rng: &mut Rng| {
+            let truth = own(t).angle_to(&target(t));
+            rng.gaussian(truth, error)
+        };
+        let first = bearing(0.0, &mut rng);
+        let mut track = Track::new(&own(0.0), first, error, None, 0.0);
+        let initial = track.position_error();
+        for i in 1..=600 {
+            let t = i as f32 * 5.0;
+            track.predict(t);
+            let measured = bearing(t, &mut rng);
+            track.bearing(&own(t), measured, error);
+            if i % 60 == 0 {
+                let range = own(t).distance_to(&target(t));
+                let measured = rng.gaussian(range, range * 0.05);
+                track.range(&own(t), measured, range * 0.05);
+            }
+        }
+        let miss = track.position().distance_to(&target(3000.0));
+        assert!(miss < 600.0, "{}", miss);
+        assert!(track.position_error() < initial / 4.0);
+        assert!(
+            (track.velocity().y - 5.0).abs() < 1.0,
+            "{:?}",
+            track.velocity()
+        );
+    }
+
+    #[test]
+    fn ranges_pin_the_track_down() {
+        let observer = Point { x: 0.0, y: 0.0 };
+        let mut track = Track::new(&observer, 0.0, 0.01, None, 0.0);
+        assert_eq!(track.position(), Point { x: 5000.0, y: 0.0 });
+        track.range(&observer, 3000.0, 30.0);
+        assert!((track.position().x - 3000.0).abs() < 5.0);
+        assert!(track.position_error() < 100.0);
+        track.predict(60.0);
+        assert!(track.position_error() > 100.0);
+        assert_eq!("kalman".parse(), Ok(Tracker::Kalman));
+        assert_eq!(Tracker::LeastSquares.to_string(), "least-squares");
+    }
+}
@@ -0,0 +1,314 @@
+use crate::config::{Config, ConfigError, Section};
+use crate::environment::{TimeOfDay, SECONDS_PER_DAY};
+use crate::events::Event;
+use crate::physics::{turn_towards, Point};
+use crate::tuning::Tunable;
+use crate::units::Knots;
+use crate::world::{Entity, EntityId, World};
+use crate::zone::{Zone, ZoneKind};
+
+// #############################
+// #       PORT TRAFFIC        #
+// #############################
+
+// Ships that are not at sea when the scenario starts but sail on a
+// schedule, one "[sailing.<name>]" section per group leaving together:
+//
+// [sailing.HX-72]
+// from = Halifax          # a port zone, or "x, y" in meters
+// to = Liverpool          # likewise
+// depart = 03:00          # local time, the first after the start...
+// day = 1                 # ...or on this day after the start date
+// ships = liberty, liberty, tanker   # a ship of each class, in column
+// speed = 9               # knots
+// side = allies           # optional, see registry.rs
+// spacing = 800           # optional, meters between ships in column
+//
+// The ships, named after the sailing ("HX-72 1", "HX-72 2", ...), appear
+// at the departure point when the world clock reaches their time, steer
+// around the zones they must keep out of and leave the world on arrival,
+// so that a boat can wait on station for a sailing it knows of.
+
+/// Meters between ships in column, unless set otherwise
+const SPACING: f32 = 600.0;
+/// Meters from its destination at which a ship has arrived
+const ARRIVAL_RANGE: f32 = 1_000.0;
+/// Meters from a waypoint at which a ship steers for the next one
+const WAYPOINT_REACHED: f32 = 300.0;
+
+/// A group of ships leaving port together, as the scenario gives it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sailing {
+    pub name: String,
+    pub from: Point,
+    pub to: Point,
+    /// Local time it leaves at, seconds after midnight
+    pub depart: f32,
+    /// Days after the start date, None for the first `depart` after the
+    /// start
+    pub day: Option<u32>,
+    /// Classes of the ships, in column order
+    pub classes: Vec<String>,
+    pub speed: Knots,
+    pub side: Option<String>,
+    pub spacing: f32,
+}
+
+/// Reads a port zone name, or "x, y"
+pub fn read_place(section: &Section, key: &str, zones: &[Zone]) -> Result<Point, ConfigError> {
+    let value: String = section.parse(key)?;
+    if let Some(zone) = zones
+        .iter()
+        .find(|z| z.name == value && z.kind == ZoneKind::Port)
+    {
+        let n = zone.points.len() as f32;
+        return Ok(Point {
+            x: zone.points.iter().map(|p| p.x).sum::<f32>() / n,
+            y: zone.points.iter().map(|p| p.y).sum::<f32>() / n,
+        });
+    }
+    let invalid = || ConfigError::Invalid {
+        section: section.name.clone(),
+        key: key.to_string(),
+        value: value.clone(),
+    };
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    Ok(Point {
+        x: x.trim().parse().map_err(|_| invalid())?,
+        y: y.trim().parse().map_err(|_| invalid())?,
+    })
+}
+
+impl Sailing {
+    pub fn read(name: &str, section: &Section, zones: &[Zone]) -> Result<Sailing, ConfigError> {
+        Ok(Sailing {
+            name: name.to_string(),
+            from: read_place(section, "from", zones)?,
+            to: read_place(section, "to", zones)?,
+            depart: section.parse::<TimeOfDay>("depart")?.0,
+            day: section.parse_optional("day")?,
+            classes: section
+                .parse::<String>("ships")?
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+            speed: Knots(section.parse("speed")?),
+            side: section.get("side").map(|s| s.to_string()),
+            spacing: section.parse_or("spacing", SPACING)?,
+        })
+    }
+
+    /// Every "[sailing.<name>]" section, places named after the port zones
+    /// of `zones`
+    pub fn read_all(config: &Config, zones: &[Zone]) -> Result<Vec<Sailing>, ConfigError> {
+        config
+            .sections_with_prefix("sailing")
+            .map(|(name, section)| Sailing::read(name, section, zones))
+            .collect()
+    }
+
+    /// Seconds into a scenario starting at `start_time` it leaves at, at
+    /// once for a day already past
+    pub fn departure(&self, start_time: f32) -> f32 {
+        match self.day {
+            Some(day) => (day as f32 * SECONDS_PER_DAY + self.depart - start_time).max(0.0),
+            None if self.depart >= start_time => self.depart - start_time,
+            None => self.depart + SECONDS_PER_DAY - start_time,
+        }
+    }
+
+    /// Name of its `i`th ship
+    pub fn ship_name(&self, i: usize) -> String {
+        format!("{} {}", self.name, i + 1)
+    }
+
+    /// Where its `i`th ship starts, astern of the ones before it
+    pub fn station(&self, i: usize) -> Point {
+        let back = self.from.angle_to(&self.to) + std::f32::consts::PI;
+        let distance = self.spacing * i as f32;
+        Point {
+            x: self.from.x + distance * back.cos(),
+            y: self.from.y + distance * back.sin(),
+        }
+    }
+}
+
+/// Ships waiting to sail at a time
+#[derive(Debug, PartialEq, Clone)]
+pub struct Departure {
+    /// Seconds into the scenario
+    pub time: f32,
+    pub ships: Vec<Entity>,
+    pub to: Point,
+}
+
+/// A ship of a sailing under way
+#[derive(Debug, PartialEq, Clone)]
+struct Underway {
+    id: EntityId,
+    to: Point,
+    /// Waypoints around the zones in the way, next first
+    route: Vec<Point>,
+}
+
+/// The sailings of the world, waiting and under way
+#[derive(Debug, Clone, Default)]
+pub struct Traffic {
+    pub departures: Vec<Departure>,
+    underway: Vec<Underway>,
+}
+
+/// Sails the departures due, steers the ships under way and takes those
+/// arrived out of the world
+pub fn update(world: &mut World, dt: f32) {
+    let time = world.time;
+    let (due, waiting): (Vec<Departure>, Vec<Departure>) = world
+        .traffic
+        .departures
+        .drain(..)
+        .partition(|d| d.time <= time);
+    world.traffic.departures = waiting;
+    for departure in due {
+        for ship in departure.ships {
+            let route = world.route(&ship, &departure.to).unwrap_or_default();
+            let id = world.spawn(ship);
+            world.emit(Event::Departed { entity: id });
+            world.traffic.underway.push(Underway {
+                id,
+                to: departure.to.clone(),
+                route,
+            });
+        }
+    }
+    let mut arrived = Vec::new();
+    let turn = Tunable::TurnRate.get(&world.environment.tuning) * dt;
+    let mut underway = std::mem::take(&mut world.traffic.underway);
+    underway.retain_mut(|u| {
+        let ship = match world.entities.get_mut(u.id) {
+            Some(ship) if !ship.is_destroyed() => ship,
+            _ => return false,
+        };
+        if ship.position.distance_to(&u.to) < ARRIVAL_RANGE {
+            arrived.push(u.id);
+            return false;
+        }
+        while u.route.len() > 1 && u.route[0].distance_to(&ship.position) < WAYPOINT_REACHED {
+            u.route.remove(0);
+        }
+        let next = u.route.first().unwrap_or(&u.to);
+        let desired = ship.position.angle_to(next);
+        ship.heading = turn_towards(ship.heading, desired, turn);
+        true
+    });
+    world.traffic.underway = underway;
+    for id in arrived {
+        world.emit(Event::Arrived { entity: id });
+        world.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::EntityKind;
+
+    fn sailing() -> Sailing {
+        let zones = vec![Zone {
+            name: "Halifax".to_string(),
+            kind: ZoneKind::Port,
+            points: vec![
+                Point {
+                    x: -500.0,
+                    y: -500.0,
+                },
+                Point {
+                    x: 500.0,
+                    y: -500.0,
+                },
+                Point { x: 500.0, y: 500.0 },
+                Point {
+                    x: -500.0,
+                    y: 500.0,
+                },
+            ],
+            depth: None,
+            bottom: None,
+        }];
+        let config = Config::parse(
+            "[sailing.HX-72]\nfrom = Halifax\nto = 0, 8000\ndepart = 03:00\n\
+             ships = liberty, tanker\nspeed = 9",
+        )
+        .unwrap();
+        let mut sailings = Sailing::read_all(&config, &zones).unwrap();
+        sailings.remove(0)
+    }
+
+    #[test]
+    fn reads_schedules() {
+        let sailing = sailing();
+        assert_eq!(sailing.from, Point { x: 0.0, y: 0.0 });
+        assert_eq!(sailing.to, Point { x: 0.0, y: 8000.0 });
+        assert_eq!(sailing.classes, vec!["liberty", "tanker"]);
+        assert!(sailing.station(1).distance_to(&Point { x: 0.0, y: -600.0 }) < 0.01);
+        assert_eq!(sailing.ship_name(1), "HX-72 2");
+        // from midnight, from 06:00 the next night, and on day 1 from 06:00
+        assert_eq!(sailing.departure(0.0), 10_800.0);
+        assert_eq!(sailing.departure(21_600.0), 75_600.0);
+        let later = Sailing {
+            day: Some(1),
+            ..sailing.clone()
+        };
+        assert_eq!(later.departure(21_600.0), 75_600.0);
+        assert_eq!(
+            Sailing {
+                day: Some(0),
+                ..sailing
+            }
+            .departure(21_600.0),
+            0.0
+        );
+
+        let bad = Config::parse(
+            "[sailing.X]\nfrom = Boston\nto = 0, 0\ndepart = 03:00\nships = a\nspeed = 9",
+        )
+        .unwrap();
+        assert!(Sailing::read_all(&bad, &[]).is_err());
+    }
+
+    #[test]
+    fn ships_sail_and_arrive() {
+        let sailing = sailing();
+        let mut world = World::new();
+        let mut ship = Entity::new(
+            &sailing.ship_name(0),
+            EntityKind::Merchant,
+            sailing.station(0),
+        );
+        ship.speed = 10.0;
+        ship.heading = std::f32::consts::FRAC_PI_2;
+        world.traffic.departures.push(Departure {
+            time: 100.0,
+            ships: vec![ship],
+            to: sailing.to.clone(),
+        });
+        for _ in 0..99 {
+            world.step(1.0);
+        }
+        assert!(world.entities.is_empty());
+        world.step(1.0);
+        assert_eq!(world.entities.len(), 1);
+        for _ in 0..800 {
+            world.step(1.0);
+        }
+        assert!(world.entities.is_empty());
+        let kinds: Vec<&Event> = world.events.iter().map(|e| &e.event).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &Event::Departed { entity: 1 },
+                &Event::Arrived { entity: 1 }
+            ]
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+
+use crate::crew::CrewQuality;
+use crate::events::Event;
+use crate::messages::Catalog;
+use crate::morale;
+use crate::noise::Rig;
+use crate::preferences::Preferences;
+use crate::sensors::excess_at;
+use crate::world::{Entity, EntityId, EntityKind, World};
+
+// #############################
+// #        TRANSIENTS         #
+// #############################
+
+// Short, sharp noises a boat makes besides its steady machinery: a torpedo
+// launch, a tube flooding, a hatch slammed when diving, a tool dropped by a
+// careless hand, the hull popping as the pressure on it changes. Each is
+// an Event::Transient and raises the boat's transient level for a moment,
+// so a listener nearby may catch a bearing on it.
+
+/// Meters of depth change after which the hull pops
+const HULL_POP_DEPTH: f32 = 50.0;
+/// Signal excess in dB above which a transient can be told apart
+const CLASSIFY_MARGIN: f32 = 10.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TransientKind {
+    TorpedoLaunch,
+    TubeFlooding,
+    HatchSlam,
+    DroppedTool,
+    HullPopping,
+    /// Vents opening for a crash dive, or ballast blown to surface
+    Ballast,
+    /// Cargo going up aboard a burning ship, see cargo.rs
+    SecondaryExplosion,
+    /// Bulkheads giving way as a ship goes down
+    BreakingUp,
+}
+
+impl TransientKind {
+    /// Source level in dB at 1 m
+    pub fn level(&self) -> f32 {
+        match self {
+            TransientKind::TorpedoLaunch => 125.0,
+            TransientKind::TubeFlooding => 115.0,
+            TransientKind::HatchSlam => 120.0,
+            TransientKind::DroppedTool => 110.0,
+            TransientKind::HullPopping => 112.0,
+            TransientKind::Ballast => 128.0,
+            TransientKind::SecondaryExplosion => 175.0,
+            TransientKind::BreakingUp => 150.0,
+        }
+    }
+}
+
+impl TransientKind {
+    /// Id of the name in the message catalog
+    pub fn message(&self) -> &'static str {
+        match self {
+            TransientKind::TorpedoLaunch => "transient-torpedo-launch",
+            TransientKind::TubeFlooding => "transient-tube-flooding",
+            TransientKind::HatchSlam => "transient-hatch-slam",
+            TransientKind::DroppedTool => "transient-dropped-tool",
+            TransientKind::HullPopping => "transient-hull-popping",
+            TransientKind::Ballast => "transient-ballast",
+            TransientKind::SecondaryExplosion => "transient-secondary-explosion",
+            TransientKind::BreakingUp => "transient-breaking-up",
+        }
+    }
+}
+
+impl fmt::Display for TransientKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Catalog::default().get(self.message()))
+    }
+}
+
+/// Makes `id` produce a transient
+pub fn make(world: &mut World, id: EntityId, kind: TransientKind) {
+    if let Some(entity) = world.entity_mut(id) {
+        entity.transient = entity.transient.max(kind.level());
+        world.emit(Event::Transient { entity: id, kind });
+    }
+}
+
+/// Mishaps an hour for a crew, in its spirits, on the rig it is running
+fn mishaps_per_hour(entity: &Entity) -> f32 {
+    let rate = match entity.crew {
+        CrewQuality::Green => 6.0,
+        CrewQuality::Trained => 2.0,
+        CrewQuality::Veteran => 0.5,
+        CrewQuality::Elite => 0.1,
+    };
+    let rate = rate * morale::mishaps(entity);
+    match entity.rig {
+        Rig::Normal => rate,
+        // the crew moves about carefully, in soft shoes
+        Rig::Quiet => rate / 2.0,
+        Rig::UltraQuiet => rate / 4.0,
+    }
+}
+
+/// What the hull of a submarine does at its depth: a hatch slams when it
+/// leaves the surface, the hull pops every HULL_POP_DEPTH meters
+fn hull(entity: &mut Entity) -> Option<TransientKind> {
+    let settled = match entity.settled_depth {
+        Some(settled) => settled,
+        None => {
+            entity.settled_depth = Some(entity.depth);
+            return None;
+        }
+    };
+    if entity.depth <= 0.0 {
+        entity.settled_depth = Some(0.0);
+        None
+    } else if settled <= 0.0 {
+        entity.settled_depth = Some(entity.depth);
+        Some(TransientKind::HatchSlam)
+    } else if (entity.depth - settled).abs() >= HULL_POP_DEPTH {
+        entity.settled_depth = Some(entity.depth);
+        Some(TransientKind::HullPopping)
+    } else {
+        None
+    }
+}
+
+/// Transients coming from what the crews did this tick: hatches slammed
+/// diving, tools dropped, hulls popping
+pub fn update(world: &mut World, dt: f32) {
+    let mut made = Vec::new();
+    for entity in world.entities.iter_mut() {
+        if entity.kind == EntityKind::Torpedo || entity.is_destroyed() {
+            continue;
+        }
+        if entity.kind == EntityKind::Submarine {
+            if let Some(kind) = hull(entity) {
+                made.push((entity.id, kind));
+            }
+        }
+        if entity.kind != EntityKind::Merchant {
+            made.push((entity.id, TransientKind::DroppedTool));
+        }
+    }
+    for (id, kind) in made {
+        if kind == TransientKind::DroppedTool {
+            let probability = mishaps_per_hour(world.entity(id).unwrap()) * dt / 3600.0;
+            if !world.rng.chance(probability) {
+                continue;
+            }
+        }
+        make(world, id, kind);
+    }
+}
+
+/// A transient heard on passive sonar
+#[derive(Debug, PartialEq, Clone)]
+pub struct TransientReport {
+    pub source: EntityId,
+    /// Game angle, radians
+    pub bearing: f32,
+    /// None while too faint to tell what it was
+    pub kind: Option<TransientKind>,
+}
+
+impl TransientReport {
+    /// The report as written for the player, on a ship on `heading`
+    pub fn describe(&self, messages: &Catalog, preferences: &Preferences, heading: f32) -> String {
+        let bearing = preferences.bearing(self.bearing, heading);
+        match self.kind {
+            Some(kind) => messages.format(
+                "transient-classified",
+                &[
+                    ("bearing", &bearing),
+                    ("kind", &messages.get(kind.message())),
+                ],
+            ),
+            None => messages.format("transient", &[("bearing", &bearing)]),
+        }
+    }
+}
+
+impl fmt::Display for TransientReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.describe(&Catalog::default(), &Preferences::default(), 0.0);
+        write!(f, "{}", text)
+    }
+}
+
+/// What `listener` hears of a transient `kind` made by `source`
+pub fn hear(
+    world: &World,
+    listener: &Entity,
+    source: &Entity,
+    kind: TransientKind,
+) -> Option<TransientReport> {
+    if listener.id == source.id {
+        return None;
+    }
+    let excess = excess_at(
+        &world.environment,
+        listener,
+        &source.position,
+        source.depth,
+        kind.level(),
+    )?;
+    if excess <= 0.0 {
+        return None;
+    }
+    Some(TransientReport {
+        source: source.id,
+        bearing: listener.position.angle_to(&source.position),
+        kind: if excess >= CLASSIFY_MARGIN {
+            Some(kind)
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Point;
+    use crate::sensors::{Sensor, SensorKind};
+
+    fn count(world: &World, kind: TransientKind) -> usize {
+        world
+            .events
+            .iter()
+            .filter(|e| matches!(e.event, Event::Transient { kind: k, .. } if k == kind))
+            .count()
+    }
+
+    #[test]
+    fn diving_and_depth_changes() {
+        let mut world = World::new();
+        let mut boat = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.crew = CrewQuality::Elite;
+        let id = world.spawn(boat);
+        world.step(1.0);
+        world.entity_mut(id).unwrap().depth = 20.0;
+        world.step(1.0);
+        assert_eq!(count(&world, TransientKind::HatchSlam), 1);
+        assert_eq!(count(&world, TransientKind::HullPopping), 0);
+        world.entity_mut(id).unwrap().depth = 80.0;
+        world.step(1.0);
+        world.step(1.0);
+        assert_eq!(count(&world, TransientKind::HullPopping), 1);
+        assert!(world.entity(id).unwrap().transient > 0.0);
+    }
+
+    #[test]
+    fn green_crews_drop_things() {
+        let mut world = World::new();
+        let mut green = Entity::new("a", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        green.crew = CrewQuality::Green;
+        world.spawn(green);
+        for _ in 0..3600 {
+            world.step(1.0);
+        }
+        let dropped = count(&world, TransientKind::DroppedTool);
+        assert!((2..=12).contains(&dropped));
+    }
+
+    #[test]
+    fn bearing_report() {
+        let mut world = World::new();
+        let mut listener = Entity::new("a", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        listener.sensors.push(Sensor::new(SensorKind::HullSonar));
+        let near = Entity::new("b", EntityKind::Submarine, Point { x: 500.0, y: 0.0 });
+        let mut far = near.clone();
+        far.position = Point {
+            x: 0.0,
+            y: -30_000.0,
+        };
+        let id = world.spawn(listener);
+        let listener = world.entity(id).unwrap().clone();
+        let id = world.spawn(near);
+        let near = world.entity(id).unwrap().clone();
+        let id = world.spawn(far);
+        let far = world.entity(id).unwrap().clone();
+        let report = hear(&world, &listener, &near, TransientKind::HatchSlam).unwrap();
+        assert_eq!(report.to_string(), "transient bearing 090, hatch slam");
+        assert_eq!(
+            hear(&world, &listener, &far, TransientKind::DroppedTool),
+            None
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError};
+
+// #############################
+// #     TUNING CONSTANTS      #
+// #############################
+
+// The numbers the physics and the crews are tuned by, each with its name,
+// unit and meaning, in one place: modules read them through Tunable::get
+// rather than keeping constants of their own, so that a scenario, or a
+// sweep of it (see balance.rs), can change them without a rebuild, here
+// the absorption in dB per meter and the turn rate in radians per second:
+//
+// [tuning]
+// absorption = 0.0008
+// turn_rate = 0.04
+//
+// Every constant is a positive number, anything else is refused.
+//
+// The values of a scenario are kept with the environment of the world built
+// from it, everything not given going back to its default, so that worlds
+// tuned apart run side by side; "subsim constants" lists them all.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Tunable {
+    Absorption,
+    LayerLoss,
+    DetectionLoss,
+    ClearSignal,
+    TargetStrength,
+    SelfNoiseIsolation,
+    TurnRate,
+    DepthRate,
+    Acceleration,
+    TorpedoTurnRate,
+    KeelClearance,
+    TransientDecay,
+}
+
+/// One entry of the registry
+#[derive(Debug, PartialEq, Clone)]
+pub struct Constant {
+    pub tunable: Tunable,
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub default: f32,
+    pub doc: &'static str,
+}
+
+/// Every constant, in the order of `Tunable`
+pub const CONSTANTS: &[Constant] = &[
+    Constant {
+        tunable: Tunable::Absorption,
+        name: "absorption",
+        unit: "dB/m",
+        default: 0.0005,
+        doc: "absorption at the low frequencies sonar listens to",
+    },
+    Constant {
+        tunable: Tunable::LayerLoss,
+        name: "layer_loss",
+        unit: "dB",
+        default: 15.0,
+        doc: "lost by a signal crossing the layer",
+    },
+    Constant {
+        tunable: Tunable::DetectionLoss,
+        name: "detection_loss",
+        unit: "dB",
+        default: 6.0,
+        doc: "the sonar operators lose when their crew works worst",
+    },
+    Constant {
+        tunable: Tunable::ClearSignal,
+        name: "clear_signal",
+        unit: "dB",
+        default: 10.0,
+        doc: "signal excess from which a sensor bears as well as it can",
+    },
+    Constant {
+        tunable: Tunable::TargetStrength,
+        name: "target_strength",
+        unit: "dB",
+        default: 15.0,
+        doc: "a ship sends back of an active pulse",
+    },
+    Constant {
+        tunable: Tunable::SelfNoiseIsolation,
+        name: "self_noise_isolation",
+        unit: "dB",
+        default: 85.0,
+        doc: "of its own radiated noise a platform does not hear",
+    },
+    Constant {
+        tunable: Tunable::TurnRate,
+        name: "turn_rate",
+        unit: "rad/s",
+        default: 0.05,
+        doc: "a boat turns by, at the helm, on autopilot or under the AI",
+    },
+    Constant {
+        tunable: Tunable::DepthRate,
+        name: "depth_rate",
+        unit: "m/s",
+        default: 1.0,
+        doc: "a boat changes depth by on autopilot or under the AI",
+    },
+    Constant {
+        tunable: Tunable::Acceleration,
+        name: "acceleration",
+        unit: "m/s2",
+        default: 0.2,
+        doc: "a boat changes speed by on autopilot or under the AI",
+    },
+    Constant {
+        tunable: Tunable::TorpedoTurnRate,
+        name: "torpedo_turn_rate",
+        unit: "rad/s",
+        default: 0.26,
+        doc: "a torpedo turns by when searching or homing",
+    },
+    Constant {
+        tunable: Tunable::KeelClearance,
+        name: "keel_clearance",
+        unit: "m",
+        default: 5.0,
+        doc: "of water kept under the keel to be safe",
+    },
+    Constant {
+        tunable: Tunable::TransientDecay,
+        name: "transient_decay",
+        unit: "dB/s",
+        default: 20.0,
+        doc: "a transient dies away by",
+    },
+];
+
+impl Tunable {
+    pub fn constant(self) -> &'static Constant {
+        &CONSTANTS[self as usize]
+    }
+
+    /// The value in force under `tuning`
+    pub fn get(self, tuning: &Tuning) -> f32 {
+        tuning
+            .overrides
+            .iter()
+            .find(|(t, _)| *t == self)
+            .map_or(self.constant().default, |(_, value)| *value)
+    }
+}
+
+impl FromStr for Tunable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CONSTANTS
+            .iter()
+            .find(|c| c.name == s)
+            .map(|c| c.tunable)
+            .ok_or_else(|| format!("unknown constant '{}'", s))
+    }
+}
+
+/// The constants a scenario changes
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Tuning {
+    pub overrides: Vec<(Tunable, f32)>,
+}
+
+impl Tuning {
+    /// Reads the "[tuning]" section, nothing changed without one
+    pub fn read(config: &Config) -> Result<Tuning, ConfigError> {
+        let mut tuning = Tuning::default();
+        let section = match config.section("tuning") {
+            Some(section) => section,
+            None => return Ok(tuning),
+        };
+        for (name, value) in section.entries() {
+            let invalid = || ConfigError::Invalid {
+                section: "tuning".to_string(),
+                key: name.to_string(),
+                value: value.to_string(),
+            };
+            let tunable = name.parse().map_err(|_| invalid())?;
+            let number: f32 = section.parse(name)?;
+            if !number.is_finite() || number <= 0.0 {
+                return Err(invalid());
+            }
+            tuning.overrides.push((tunable, number));
+        }
+        Ok(tuning)
+    }
+}
+
+/// Every constant with its value in force under a tuning, as listed by
+/// "subsim constants"
+pub struct Registry<'a>(pub &'a Tuning);
+
+impl fmt::Display for Registry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = CONSTANTS
+            .iter()
+            .map(|c| {
+                format!(
+                    "{:<22} {:>8} {:<6} {}",
+                    c.name,
+                    c.tunable.get(self.0),
+                    c.unit,
+                    c.doc
+                )
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_is_in_order() {
+        for (i, constant) in CONSTANTS.iter().enumerate() {
+            assert_eq!(constant.tunable as usize, i, "{}", constant.name);
+            assert_eq!(constant.name.parse(), Ok(constant.tunable));
+        }
+        let lines = Registry(&Tuning::default()).to_string().lines().count();
+        assert_eq!(lines, CONSTANTS.len());
+    }
+
+    #[test]
+    fn scenarios_override_constants() {
+        let config = Config::parse("[tuning]\nturn_rate = 0.1").unwrap();
+        let tuning = Tuning::read(&config).unwrap();
+        assert_eq!(Tunable::TurnRate.get(&Tuning::default()), 0.05);
+        assert_eq!(Tunable::TurnRate.get(&tuning), 0.1);
+        assert_eq!(Tunable::Absorption.get(&tuning), 0.0005);
+        assert!(Registry(&tuning).to_string().contains("0.1"));
+
+        for bad in [
+            "turning = 0.1",
+            "turn_rate = fast",
+            "turn_rate = NaN",
+            "turn_rate = inf",
+            "turn_rate = 0",
+            "turn_rate = -0.1",
+        ] {
+            let config = Config::parse(&format!("[tuning]\n{}", bad)).unwrap();
+            assert!(Tuning::read(&config).is_err(), "{}", bad);
+        }
+    }
+}
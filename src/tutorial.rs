@@ -0,0 +1,257 @@
+use crate::command::{Command, REFERENCE};
+use crate::config::{Config, ConfigError, Section};
+use crate::stopwatch::Timers;
+
+// #############################
+// #         TUTORIALS         #
+// #############################
+
+// A training scenario walks the player through "[tutorial.<step>]"
+// sections, in file order:
+//
+// [tutorial.1]
+// text = Rig the boat for ultra quiet before the escort passes over.
+// wait_for = rig ultra
+// pause = true
+//
+// A step is done when the player successfully gives a command matching
+// "wait_for" (by default "continue"), "*" matching any word; a pattern
+// shorter than the command matches its first words, so "fire" accepts any
+// launch. A pattern no command could match is refused when the scenario
+// loads. "pause" freezes the simulation until the step is done. A step
+// may instead wait for a timer (see stopwatch.rs) to show so many
+// seconds, here a minute after the last torpedo fired:
+//
+// timer = torpedo 60
+//
+// A text written "@<id>" is the message of that id, see messages.rs, so a
+// tutorial can be translated.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Step {
+    pub text: String,
+    pub wait_for: String,
+    pub pause: bool,
+    /// Timer and seconds it must show for the step to be done
+    pub timer: Option<(String, f32)>,
+}
+
+/// Reads the "timer" of a step, a name and seconds
+fn read_timer(section: &Section) -> Result<Option<(String, f32)>, ConfigError> {
+    let value: String = match section.parse_optional("timer")? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let invalid = || ConfigError::Invalid {
+        section: section.name.clone(),
+        key: "timer".to_string(),
+        value: value.clone(),
+    };
+    match value.split_whitespace().collect::<Vec<_>>()[..] {
+        [name, seconds] => match seconds.parse::<f32>() {
+            Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                Ok(Some((name.to_string(), seconds)))
+            }
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+/// Whether some command could match the "wait_for" pattern: its first word
+/// names a command, a whole command reads back as the pattern, and the
+/// words of a partial one are words of its usage, numbers or "*"
+fn reachable(pattern: &str) -> bool {
+    let words: Vec<&str> = pattern.split_whitespace().collect();
+    let usages: Vec<&str> = REFERENCE
+        .iter()
+        .map(|(usage, _)| *usage)
+        .filter(|usage| usage.split_whitespace().next() == words.first().copied())
+        .collect();
+    if usages.is_empty() {
+        return false;
+    }
+    if !words.contains(&"*") {
+        if let Ok(command) = Command::parse(pattern) {
+            let step = Step {
+                text: String::new(),
+                wait_for: pattern.to_string(),
+                pause: false,
+                timer: None,
+            };
+            return step.accepts(&command);
+        }
+    }
+    let vocabulary: Vec<&str> = usages
+        .iter()
+        .flat_map(|usage| usage.split(|c: char| c.is_whitespace() || "<>[]|;".contains(c)))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words
+        .iter()
+        .all(|w| *w == "*" || vocabulary.contains(w) || w.parse::<f32>().is_ok())
+}
+
+impl Step {
+    pub fn accepts(&self, command: &Command) -> bool {
+        let text = command.to_string();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let pattern: Vec<&str> = self.wait_for.split_whitespace().collect();
+        pattern.len() <= words.len()
+            && pattern
+                .iter()
+                .zip(words.iter())
+                .all(|(p, w)| *p == "*" || p == w)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tutorial {
+    pub steps: Vec<Step>,
+    /// Index of the step being taught, `steps.len()` when done
+    pub current: usize,
+}
+
+impl Tutorial {
+    /// Reads the tutorial steps of a scenario, None if it has none
+    pub fn read(config: &Config) -> Result<Option<Tutorial>, ConfigError> {
+        let mut steps = Vec::new();
+        for (_, section) in config.sections_with_prefix("tutorial") {
+            let wait_for: String = section.parse_or("wait_for", "continue".to_string())?;
+            if !reachable(&wait_for) {
+                return Err(ConfigError::Invalid {
+                    section: section.name.clone(),
+                    key: "wait_for".to_string(),
+                    value: wait_for,
+                });
+            }
+            steps.push(Step {
+                text: section.parse("text")?,
+                wait_for,
+                pause: section.parse_or("pause", true)?,
+                timer: read_timer(section)?,
+            });
+        }
+        if steps.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Tutorial { steps, current: 0 }))
+    }
+
+    pub fn step(&self) -> Option<&Step> {
+        self.steps.get(self.current)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.steps.len()
+    }
+
+    /// Whether the simulation is held until the current step is done
+    pub fn is_paused(&self) -> bool {
+        self.step().is_some_and(|s| s.pause)
+    }
+
+    /// Takes note of a command the player gave successfully, returning
+    /// true when it completes the current step
+    pub fn observe(&mut self, command: &Command) -> bool {
+        match self.step() {
+            Some(step) if step.accepts(command) => {
+                self.current += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks the timers at `time`, returning true when they complete the
+    /// current step
+    pub fn watch(&mut self, timers: &Timers, time: f32) -> bool {
+        let done = self.step().is_some_and(|step| match &step.timer {
+            Some((name, seconds)) => timers.elapsed(name, time).is_some_and(|e| e >= *seconds),
+            None => false,
+        });
+        if done {
+            self.current += 1;
+        }
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LESSON: &str = "
+[tutorial.welcome]
+text = Welcome aboard.
+
+[tutorial.quiet]
+text = Rig for ultra quiet.
+wait_for = rig ultra
+
+[tutorial.shoot]
+text = Fire any tube on any bearing.
+wait_for = fire * *
+pause = false
+
+[tutorial.run]
+text = Time the run of the fish.
+timer = torpedo 60
+pause = false
+";
+
+    #[test]
+    fn walk_through() {
+        let mut tutorial = Tutorial::read(&Config::parse(LESSON).unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(tutorial.is_paused());
+        assert!(!tutorial.observe(&Command::LaunchXbt));
+        assert!(tutorial.observe(&Command::Continue));
+        assert!(!tutorial.observe(&Command::parse("rig quiet").unwrap()));
+        assert!(tutorial.observe(&Command::parse("rig ultra").unwrap()));
+        assert!(!tutorial.is_paused());
+        assert!(tutorial.observe(&Command::parse("fire 3 270").unwrap()));
+        let mut timers = Timers::default();
+        assert!(!tutorial.watch(&timers, 100.0));
+        timers.start("torpedo", 50.0);
+        assert!(!tutorial.watch(&timers, 100.0));
+        assert!(tutorial.watch(&timers, 110.0));
+        assert!(tutorial.is_finished());
+
+        for bad in [
+            "timer = 60",
+            "timer = torpedo NaN",
+            "timer = torpedo -5",
+            "wait_for = fyre",
+            "wait_for = rig ultr",
+            "wait_for = rig ultra # once the escort nears",
+        ] {
+            let config = Config::parse(&format!("[tutorial.1]\ntext = Wait.\n{}", bad)).unwrap();
+            assert!(Tutorial::read(&config).is_err(), "{}", bad);
+        }
+        for good in ["fire", "helo goto * *", "gun target nearest", "fire 1"] {
+            let config =
+                Config::parse(&format!("[tutorial.1]\ntext = Wait.\nwait_for = {}", good)).unwrap();
+            assert!(Tutorial::read(&config).is_ok(), "{}", good);
+        }
+    }
+
+    #[test]
+    fn no_tutorial() {
+        let config = Config::parse("[scenario]\nname = Free play").unwrap();
+        assert!(Tutorial::read(&config).unwrap().is_none());
+    }
+
+    #[test]
+    fn prefix_patterns() {
+        let step = Step {
+            text: String::new(),
+            wait_for: "fire".to_string(),
+            pause: true,
+            timer: None,
+        };
+        assert!(step.accepts(&Command::parse("fire 1 90").unwrap()));
+        assert!(!step.accepts(&Command::Continue));
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::physics::KNOT;
+
+// #############################
+// #     UNITS OF MEASURE      #
+// #############################
+
+// The simulation works in meters and meters per second throughout; these
+// wrappers are for what crosses to the player, so a value read or shown in
+// another unit is converted once, by type, and never by hand. Which units
+// the reports use is the player's UnitSystem. Speeds are in knots in both
+// systems, as they are at sea.
+
+/// Meters in a yard
+pub const YARD: f32 = 0.9144;
+/// Meters in a foot
+pub const FOOT: f32 = 0.3048;
+/// Meters in a nautical mile
+pub const NAUTICAL_MILE: f32 = 1852.0;
+
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+pub struct MetersPerSecond(pub f32);
+
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+pub struct Knots(pub f32);
+
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+pub struct Meters(pub f32);
+
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+pub struct Yards(pub f32);
+
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+pub struct Feet(pub f32);
+
+#[derive(Debug, Default, PartialEq, PartialOrd, Clone, Copy)]
+pub struct NauticalMiles(pub f32);
+
+impl From<Knots> for MetersPerSecond {
+    fn from(speed: Knots) -> Self {
+        MetersPerSecond(speed.0 * KNOT)
+    }
+}
+
+impl From<MetersPerSecond> for Knots {
+    fn from(speed: MetersPerSecond) -> Self {
+        Knots(speed.0 / KNOT)
+    }
+}
+
+impl From<Yards> for Meters {
+    fn from(length: Yards) -> Self {
+        Meters(length.0 * YARD)
+    }
+}
+
+impl From<Meters> for Yards {
+    fn from(length: Meters) -> Self {
+        Yards(length.0 / YARD)
+    }
+}
+
+impl From<Feet> for Meters {
+    fn from(length: Feet) -> Self {
+        Meters(length.0 * FOOT)
+    }
+}
+
+impl From<Meters> for Feet {
+    fn from(length: Meters) -> Self {
+        Feet(length.0 / FOOT)
+    }
+}
+
+impl From<NauticalMiles> for Meters {
+    fn from(length: NauticalMiles) -> Self {
+        Meters(length.0 * NAUTICAL_MILE)
+    }
+}
+
+impl From<Meters> for NauticalMiles {
+    fn from(length: Meters) -> Self {
+        NauticalMiles(length.0 / NAUTICAL_MILE)
+    }
+}
+
+impl From<NauticalMiles> for Yards {
+    fn from(length: NauticalMiles) -> Self {
+        Meters::from(length).into()
+    }
+}
+
+impl From<Yards> for NauticalMiles {
+    fn from(length: Yards) -> Self {
+        Meters::from(length).into()
+    }
+}
+
+impl fmt::Display for MetersPerSecond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} m/s", self.0)
+    }
+}
+
+impl fmt::Display for Knots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} kn", self.0)
+    }
+}
+
+impl fmt::Display for Meters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.0} m", self.0)
+    }
+}
+
+impl fmt::Display for Yards {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.0} yd", self.0)
+    }
+}
+
+impl fmt::Display for Feet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.0} ft", self.0)
+    }
+}
+
+impl fmt::Display for NauticalMiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} nm", self.0)
+    }
+}
+
+/// Reads a length such as "1500", "1500m", "1600yd", "4900ft" or "2.5nm";
+/// plain numbers are meters
+impl FromStr for Meters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Meters, String> {
+        let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let value: f32 = number
+            .trim()
+            .parse()
+            .map_err(|_| format!("expected a length, found '{}'", s))?;
+        match unit {
+            "" | "m" => Ok(Meters(value)),
+            "yd" => Ok(Yards(value).into()),
+            "ft" => Ok(Feet(value).into()),
+            "nm" => Ok(NauticalMiles(value).into()),
+            _ => Err(format!("unknown unit '{}'", unit)),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum UnitSystem {
+    /// Meters
+    #[default]
+    Metric,
+    /// Yards for ranges, feet for depths
+    Imperial,
+}
+
+impl fmt::Display for UnitSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UnitSystem::Metric => "metric",
+            UnitSystem::Imperial => "imperial",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for UnitSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<UnitSystem, String> {
+        match s {
+            "metric" => Ok(UnitSystem::Metric),
+            "imperial" => Ok(UnitSystem::Imperial),
+            _ => Err(format!("unknown unit system '{}'", s)),
+        }
+    }
+}
+
+impl UnitSystem {
+    /// A range or distance as the player reads it
+    pub fn range(&self, length: Meters) -> String {
+        match self {
+            UnitSystem::Metric => length.to_string(),
+            UnitSystem::Imperial => Yards::from(length).to_string(),
+        }
+    }
+
+    /// A depth as the player reads it
+    pub fn depth(&self, depth: Meters) -> String {
+        match self {
+            UnitSystem::Metric => depth.to_string(),
+            UnitSystem::Imperial => Feet::from(depth).to_string(),
+        }
+    }
+
+    /// A speed as the player reads it
+    pub fn speed(&self, speed: MetersPerSecond) -> String {
+        Knots::from(speed).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let speed = MetersPerSecond::from(Knots(10.0));
+        assert!((speed.0 - 5.144).abs() < 0.001);
+        assert!((Knots::from(speed).0 - 10.0).abs() < 0.001);
+        assert_eq!(Meters::from(NauticalMiles(1.0)), Meters(1852.0));
+        let yards = Yards::from(NauticalMiles(1.0));
+        assert!((yards.0 - 2025.4).abs() < 0.1);
+        assert_eq!(Feet::from(Meters(30.48)).to_string(), "100 ft");
+    }
+
+    #[test]
+    fn parse_lengths() {
+        assert_eq!("1500".parse(), Ok(Meters(1500.0)));
+        assert_eq!("2nm".parse(), Ok(Meters(3704.0)));
+        assert_eq!("1000yd".parse::<Meters>().unwrap().to_string(), "914 m");
+        assert!("12 parsecs".parse::<Meters>().is_err());
+        assert!("nm".parse::<Meters>().is_err());
+    }
+
+    #[test]
+    fn unit_systems() {
+        let units = UnitSystem::Imperial;
+        assert_eq!(units.range(Meters(914.4)), "1000 yd");
+        assert_eq!(units.depth(Meters(30.48)), "100 ft");
+        assert_eq!(UnitSystem::Metric.range(Meters(914.4)), "914 m");
+        assert_eq!(units.speed(MetersPerSecond(KNOT * 12.0)), "12.0 kn");
+    }
+}
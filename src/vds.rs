@@ -0,0 +1,215 @@
+use crate::asw::{AswError, AswStation};
+use crate::autopilot::approach;
+use crate::physics::{normalize_angle, Point, KNOT};
+use crate::sensors::{Sensor, SensorKind};
+use crate::world::{Entity, EntityKind, World};
+
+// #############################
+// #   VARIABLE DEPTH SONAR    #
+// #############################
+
+// A modern escort may tow a sonar body on a cable, lowered to listen and
+// ping under the layer, where the hull sonar does not reach:
+//
+// [class.udaloy]
+// kind = warship
+// vds = true
+//
+// The player of the escort orders
+//
+// vds <depth>             # stream the body, or move it, to that depth
+// vds recover             # heave it in
+//
+// The body is streamed and recovered only at STREAM_SPEED or slower, and
+// the winch moves it WINCH_RATE meters a second. Towed faster than
+// TOW_SPEED the cable drags it up towards the surface, and in a hard turn
+// it swings out and is deaf until it steadies again. Wherever it is, it
+// hears and pings as a hull sonar would from there, and what it holds
+// comes in with the hull sonar contacts (see contacts.rs). The escorts the
+// computer commands lower it under the layer when they think the boat
+// went there, see escort.rs.
+
+/// Meters per second at or below which the body is streamed and recovered
+pub const STREAM_SPEED: f32 = 12.0 * KNOT;
+/// Meters per second above which the cable drags the body up
+const TOW_SPEED: f32 = 15.0 * KNOT;
+/// Meters per second the winch lowers and raises the body
+const WINCH_RATE: f32 = 2.0;
+/// Shallowest and deepest depths the body is set to, meters
+pub const MIN_DEPTH: f32 = 20.0;
+pub const MAX_DEPTH: f32 = 300.0;
+/// Radians per second of turn over which the body swings out
+const HARD_TURN: f32 = 0.03;
+/// Seconds the body takes to steady after a hard turn
+const STEADYING: f32 = 60.0;
+/// Meters astern of the ship the body is towed
+const TOW_LENGTH: f32 = 250.0;
+
+/// The towed body of a variable depth sonar
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Vds {
+    /// Depth it is set to, None while stowed
+    pub set: Option<f32>,
+    /// Depth it is at
+    pub depth: f32,
+    /// Seconds it swings on after a hard turn
+    pub swinging: f32,
+    /// Heading of the ship on the last tick
+    heading: Option<f32>,
+}
+
+impl Vds {
+    /// Whether it listens: streamed and steady
+    pub fn is_working(&self) -> bool {
+        self.set.is_some() && self.swinging <= 0.0
+    }
+}
+
+/// Streams the body of `station` to `depth`, moves it there, or recovers
+/// it for None, on a ship making `speed`
+pub fn order(station: &mut AswStation, speed: f32, depth: Option<f32>) -> Result<(), AswError> {
+    let vds = station.vds.as_mut().ok_or(AswError::NoVds)?;
+    if let Some(depth) = depth {
+        if !(MIN_DEPTH..=MAX_DEPTH).contains(&depth) {
+            return Err(AswError::BadVdsDepth(depth));
+        }
+    }
+    if vds.set.is_some() != depth.is_some() && speed > STREAM_SPEED {
+        return Err(AswError::TooFastForVds);
+    }
+    vds.set = depth;
+    Ok(())
+}
+
+/// The body towed behind `ship`, as the sensors see it; None unless it
+/// listens
+pub fn body(ship: &Entity) -> Option<Entity> {
+    let vds = ship.asw.as_ref()?.vds.as_ref().filter(|v| v.is_working())?;
+    let (sin, cos) = ship.heading.sin_cos();
+    let at = Point {
+        x: ship.position.x - TOW_LENGTH * cos,
+        y: ship.position.y - TOW_LENGTH * sin,
+    };
+    let mut body = Entity::new("variable depth sonar", EntityKind::Submarine, at);
+    body.depth = vds.depth;
+    body.heading = ship.heading;
+    body.speed = ship.speed;
+    body.crew = ship.crew;
+    body.sensors.push(Sensor::new(SensorKind::HullSonar));
+    Some(body)
+}
+
+/// Winches the bodies towards their depths over `dt` seconds, the cable
+/// dragging them up at speed and hard turns swinging them out
+pub fn update(world: &mut World, dt: f32) {
+    for ship in world.entities.iter_mut() {
+        let (heading, speed) = (ship.heading, ship.speed);
+        let vds = match ship.asw.as_mut().and_then(|s| s.vds.as_mut()) {
+            Some(vds) => vds,
+            None => continue,
+        };
+        let turn = vds
+            .heading
+            .map_or(0.0, |h| normalize_angle(heading - h).abs() / dt.max(1e-3));
+        vds.heading = Some(heading);
+        vds.swinging = if vds.set.is_some() && turn > HARD_TURN {
+            STEADYING
+        } else {
+            (vds.swinging - dt).max(0.0)
+        };
+        let mut target = vds.set.unwrap_or(0.0);
+        if speed > TOW_SPEED {
+            target *= (TOW_SPEED / speed).powi(2);
+        }
+        vds.depth = approach(vds.depth, target, WINCH_RATE * dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escort(speed: f32) -> Entity {
+        let mut escort = Entity::new("Udaloy", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        escort.asw = Some(AswStation {
+            vds: Some(Vds::default()),
+            ..AswStation::default()
+        });
+        escort.speed = speed;
+        escort
+    }
+
+    fn vds(world: &World) -> &Vds {
+        world
+            .entity(1)
+            .unwrap()
+            .asw
+            .as_ref()
+            .unwrap()
+            .vds
+            .as_ref()
+            .unwrap()
+    }
+
+    #[test]
+    fn streamed_slow_and_lowered() {
+        let mut fast = escort(20.0 * KNOT);
+        let station = fast.asw.as_mut().unwrap();
+        assert_eq!(
+            order(station, 20.0 * KNOT, Some(100.0)),
+            Err(AswError::TooFastForVds)
+        );
+        assert_eq!(
+            order(station, 10.0 * KNOT, Some(900.0)),
+            Err(AswError::BadVdsDepth(900.0))
+        );
+        assert_eq!(
+            order(&mut AswStation::default(), 0.0, Some(100.0)),
+            Err(AswError::NoVds)
+        );
+
+        let mut world = World::new();
+        let mut ship = escort(10.0 * KNOT);
+        order(ship.asw.as_mut().unwrap(), ship.speed, Some(100.0)).unwrap();
+        world.spawn(ship);
+        for _ in 0..30 {
+            update(&mut world, 1.0);
+        }
+        assert_eq!(vds(&world).depth, 60.0);
+        for _ in 0..30 {
+            update(&mut world, 1.0);
+        }
+        assert_eq!(vds(&world).depth, 100.0);
+        let body = body(world.entity(1).unwrap()).unwrap();
+        assert_eq!(body.depth, 100.0);
+        assert_eq!(body.position, Point { x: -250.0, y: 0.0 });
+        // once streamed, it is moved at any speed
+        let ship = world.entity_mut(1).unwrap();
+        ship.speed = 20.0 * KNOT;
+        order(ship.asw.as_mut().unwrap(), 20.0 * KNOT, Some(150.0)).unwrap();
+    }
+
+    #[test]
+    fn dragged_up_and_swung_out() {
+        let mut world = World::new();
+        let mut ship = escort(10.0 * KNOT);
+        order(ship.asw.as_mut().unwrap(), ship.speed, Some(100.0)).unwrap();
+        world.spawn(ship);
+        for _ in 0..60 {
+            update(&mut world, 1.0);
+        }
+        world.entity_mut(1).unwrap().speed = 30.0 * KNOT;
+        for _ in 0..60 {
+            update(&mut world, 1.0);
+        }
+        assert_eq!(vds(&world).depth, 25.0);
+        // a hard turn leaves it deaf for a while
+        world.entity_mut(1).unwrap().heading = 0.5;
+        update(&mut world, 1.0);
+        assert!(body(world.entity(1).unwrap()).is_none());
+        for _ in 0..60 {
+            update(&mut world, 1.0);
+        }
+        assert!(body(world.entity(1).unwrap()).is_some());
+    }
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+
+use crate::asw::AswStation;
+use crate::atmosphere::{Atmosphere, DEFAULT_SCRUBBER};
+use crate::cargo::{CargoKind, Hold, DEFAULT_COMPARTMENTS};
+use crate::casualties::{Casualties, Medic};
+use crate::config::{Config, ConfigError, Section};
+use crate::dive::DEFAULT_DIVE_TIME;
+use crate::era::{Era, Subsystem};
+use crate::gunnery::Gun;
+use crate::helicopter::{self, Helicopter};
+use crate::machinery::Machinery;
+use crate::physics::Point;
+use crate::radar::RadarGeneration;
+use crate::sensors::{Sensor, SensorKind};
+use crate::stores::{Consumables, Stores, DEFAULT_FUEL_RATE};
+use crate::units::{Knots, MetersPerSecond};
+use crate::vds::Vds;
+use crate::weapons::{Guidance, PresetLibrary, WeaponsStation};
+use crate::world::{Entity, EntityKind};
+
+// Vessel classes are read from "[class.<name>]" sections:
+//
+// [class.type_viic]
+// kind = submarine
+// max_speed = 17.7        # knots
+// tubes = 5
+// torpedo = unguided      # see weapons::Guidance
+// deck_gun = true
+// towed_array = false
+// radar = false           # and radar_generation, see radar.rs
+// intercept = false       # acoustic intercept receiver
+// xbts = 0                # expendable bathythermographs carried
+// towed_decoys = 0        # and decoys, mobile ones, see decoy.rs
+// dive_time = 40          # seconds to flood down to periscope depth
+// depth_charges = 0       # and mortar salvos of an escort, see asw.rs
+// helicopter = false      # and helicopter_endurance and
+//                         # helicopter_torpedoes, see helicopter.rs
+// vds = false             # variable depth sonar, see vds.rs
+//
+// and optionally what it carries for a patrol (fuel, fuel_rate,
+// provisions, spares and tender, see stores.rs) and for the air of a
+// submarine (scrubber, candles and snorkel, see atmosphere.rs), the men
+// aboard (complement and medic, see casualties.rs) and for a merchant what
+// it carries (tonnage, cargo and compartments, see cargo.rs).
+
+#[derive(Debug)]
+pub enum VesselError {
+    Config(ConfigError),
+    /// The class carries a subsystem its era does not have
+    NotInEra {
+        class: String,
+        subsystem: Subsystem,
+        era: Era,
+    },
+}
+
+impl fmt::Display for VesselError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VesselError::Config(e) => write!(f, "{}", e),
+            VesselError::NotInEra {
+                class,
+                subsystem,
+                era,
+            } => write!(
+                f,
+                "class '{}' carries a {} not available in {}",
+                class, subsystem, era
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VesselError {}
+
+impl From<ConfigError> for VesselError {
+    fn from(e: ConfigError) -> Self {
+        VesselError::Config(e)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VesselClass {
+    pub name: String,
+    pub kind: EntityKind,
+    /// Meters per second
+    pub max_speed: f32,
+    pub tubes: usize,
+    pub torpedo: Guidance,
+    pub deck_gun: bool,
+    pub towed_array: bool,
+    pub radar: bool,
+    /// None to go by the era of the scenario
+    pub radar_generation: Option<RadarGeneration>,
+    pub intercept: bool,
+    pub xbts: u32,
+    pub towed_decoys: u32,
+    /// Mobile decoys carried
+    pub decoys: u32,
+    /// Seconds a submarine takes to flood down to periscope depth
+    pub dive_time: f32,
+    pub depth_charges: u32,
+    /// Salvos of an ahead-throwing mortar
+    pub mortar: u32,
+    pub helicopter: bool,
+    /// Minutes of flight of the helicopter on full tanks
+    pub helicopter_endurance: f32,
+    pub helicopter_torpedoes: u32,
+    /// Tows a variable depth sonar
+    pub vds: bool,
+    /// What the class carries for a patrol, see stores.rs
+    pub stores: Consumables,
+    /// Tonnes of fuel an hour at 10 knots
+    pub fuel_rate: f32,
+    pub tender: bool,
+    /// Hours of CO2 absorbent, see atmosphere.rs
+    pub scrubber: f32,
+    pub candles: u32,
+    pub snorkel: bool,
+    /// Men aboard, see casualties.rs
+    pub complement: u32,
+    pub medic: Medic,
+    /// Gross register tons of a merchant, 0 when not tracked
+    pub tonnage: u32,
+    pub cargo: CargoKind,
+    pub compartments: u32,
+}
+
+impl VesselClass {
+    pub fn read(name: &str, section: &Section) -> Result<VesselClass, ConfigError> {
+        Ok(VesselClass {
+            name: name.to_string(),
+            kind: section.parse("kind")?,
+            max_speed: MetersPerSecond::from(Knots(section.parse("max_speed")?)).0,
+            tubes: section.parse_or("tubes", 0)?,
+            torpedo: section.parse_or("torpedo", Guidance::Unguided)?,
+            deck_gun: section.parse_or("deck_gun", false)?,
+            towed_array: section.parse_or("towed_array", false)?,
+            radar: section.parse_or("radar", false)?,
+            radar_generation: section.parse_optional("radar_generation")?,
+            intercept: section.parse_or("intercept", false)?,
+            xbts: section.parse_or("xbts", 0)?,
+            towed_decoys: section.parse_or("towed_decoys", 0)?,
+            decoys: section.parse_or("decoys", 0)?,
+            dive_time: section.parse_or("dive_time", DEFAULT_DIVE_TIME)?,
+            depth_charges: section.parse_or("depth_charges", 0)?,
+            mortar: section.parse_or("mortar", 0)?,
+            helicopter: section.parse_or("helicopter", false)?,
+            helicopter_endurance: section
+                .parse_or("helicopter_endurance", helicopter::ENDURANCE)?,
+            helicopter_torpedoes: section
+                .parse_or("helicopter_torpedoes", helicopter::TORPEDOES)?,
+            vds: section.parse_or("vds", false)?,
+            stores: Consumables {
+                fuel: section.parse_or("fuel", 0.0)?,
+                food: section.parse_or("provisions", 0.0)?,
+                spares: section.parse_or("spares", 0.0)?,
+            },
+            fuel_rate: section.parse_or("fuel_rate", DEFAULT_FUEL_RATE)?,
+            tender: section.parse_or("tender", false)?,
+            scrubber: section.parse_or("scrubber", DEFAULT_SCRUBBER)?,
+            candles: section.parse_or("candles", 0)?,
+            snorkel: section.parse_or("snorkel", false)?,
+            complement: section.parse_or("complement", 0)?,
+            medic: section.parse_or("medic", Medic::None)?,
+            tonnage: section.parse_or("tonnage", 0)?,
+            cargo: section.parse_or("cargo", CargoKind::General)?,
+            compartments: section.parse_or("compartments", DEFAULT_COMPARTMENTS)?,
+        })
+    }
+
+    /// Optional subsystems fitted to the class
+    pub fn subsystems(&self) -> Vec<Subsystem> {
+        let mut subsystems = Vec::new();
+        if self.deck_gun && self.kind == EntityKind::Submarine {
+            subsystems.push(Subsystem::DeckGun);
+        }
+        if self.towed_array {
+            subsystems.push(Subsystem::TowedArray);
+        }
+        if self.radar {
+            subsystems.push(Subsystem::Radar);
+        }
+        if self.intercept {
+            subsystems.push(Subsystem::InterceptReceiver);
+        }
+        if self.snorkel {
+            subsystems.push(Subsystem::Snorkel);
+        }
+        if self.towed_decoys > 0 {
+            subsystems.push(Subsystem::TowedDecoy);
+        }
+        if self.decoys > 0 {
+            subsystems.push(Subsystem::MobileDecoy);
+        }
+        if self.vds && self.kind == EntityKind::Warship {
+            subsystems.push(Subsystem::VariableDepthSonar);
+        }
+        if self.tubes > 0 {
+            match self.torpedo {
+                Guidance::Unguided => {}
+                Guidance::Acoustic(generation) => {
+                    subsystems.push(Subsystem::HomingTorpedo(generation))
+                }
+                Guidance::WakeHoming => subsystems.push(Subsystem::WakeHomingTorpedo),
+            }
+        }
+        subsystems
+    }
+
+    /// Subsystems fitted to the class that `era` does not have
+    pub fn era_violations(&self, era: Era) -> Vec<Subsystem> {
+        self.subsystems()
+            .into_iter()
+            .filter(|s| !era.allows(*s))
+            .collect()
+    }
+
+    pub fn check_era(&self, era: Era) -> Result<(), VesselError> {
+        match self.era_violations(era).first() {
+            Some(subsystem) => Err(VesselError::NotInEra {
+                class: self.name.clone(),
+                subsystem: *subsystem,
+                era,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn sensor_kinds(&self) -> Vec<SensorKind> {
+        let mut kinds = Vec::new();
+        if matches!(self.kind, EntityKind::Submarine | EntityKind::Warship) {
+            kinds.push(SensorKind::HullSonar);
+        }
+        if self.kind == EntityKind::Submarine {
+            kinds.push(SensorKind::Periscope);
+        }
+        if self.towed_array {
+            kinds.push(SensorKind::TowedArray);
+        }
+        if self.radar {
+            kinds.push(SensorKind::Radar);
+        }
+        if self.intercept {
+            kinds.push(SensorKind::InterceptReceiver);
+        }
+        kinds
+    }
+
+    /// Creates an entity of this class
+    pub fn instantiate(&self, name: &str, position: Point) -> Entity {
+        let mut entity = Entity::new(name, self.kind, position);
+        entity.class = Some(self.name.clone());
+        entity.sensors = self.sensor_kinds().into_iter().map(Sensor::new).collect();
+        entity.radar_generation = self.radar_generation.unwrap_or_default();
+        entity.xbts = self.xbts;
+        entity.towed_decoys = self.towed_decoys;
+        entity.decoys = self.decoys;
+        entity.tender = self.tender;
+        if self.kind == EntityKind::Submarine {
+            entity.atmosphere = Some(Atmosphere::new(self.scrubber, self.candles, self.snorkel));
+        }
+        if self.complement > 0 {
+            entity.casualties = Some(Casualties::new(self.complement, self.medic));
+        }
+        if self.stores != Consumables::default() {
+            entity.stores = Some(Stores::full(self.stores, self.fuel_rate));
+            entity.machinery = Some(Machinery::default());
+        }
+        if self.tonnage > 0 {
+            entity.hold = Some(Hold::new(self.tonnage, self.cargo, self.compartments));
+        }
+        if self.deck_gun {
+            entity.gun = Some(match self.kind {
+                EntityKind::Submarine => Gun::deck_gun(),
+                _ => Gun::escort(),
+            });
+        }
+        // every escort can ping, whether or not it has anything to attack with
+        if self.kind == EntityKind::Warship {
+            let mut station = AswStation::new(self.depth_charges, self.mortar);
+            if self.helicopter {
+                station.helicopter = Some(Helicopter::new(
+                    entity.position.clone(),
+                    self.helicopter_endurance * 60.0,
+                    self.helicopter_torpedoes,
+                ));
+            }
+            if self.vds {
+                station.vds = Some(Vds::default());
+            }
+            entity.asw = Some(station);
+        }
+        if self.tubes > 0 {
+            let mut station = WeaponsStation::new(self.tubes, PresetLibrary::new());
+            station.guidance = self.torpedo;
+            entity.weapons = Some(station);
+        }
+        entity
+    }
+}
+
+/// Reads every "[class.<name>]" section, refusing classes outside of `era`
+pub fn load_classes(config: &Config, era: Era) -> Result<Vec<VesselClass>, VesselError> {
+    let mut classes = Vec::new();
+    for (name, section) in config.sections_with_prefix("class") {
+        let class = VesselClass::read(name, section)?;
+        class.check_era(era)?;
+        classes.push(class);
+    }
+    Ok(classes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeker::SeekerGeneration;
+
+    const CLASSES: &str = "
+[class.type_viic]
+kind = submarine
+max_speed = 17.7
+tubes = 5
+torpedo = early_passive
+deck_gun = true
+
+[class.flower]
+kind = warship
+max_speed = 16
+deck_gun = true
+";
+
+    #[test]
+    fn read_class() {
+        let config = Config::parse(CLASSES).unwrap();
+        let classes = load_classes(&config, Era { year: 1944 }).unwrap();
+        assert_eq!(classes.len(), 2);
+        let viic = &classes[0];
+        assert_eq!(viic.kind, EntityKind::Submarine);
+        assert!((viic.max_speed - 9.105).abs() < 0.01);
+        assert_eq!(
+            viic.torpedo,
+            Guidance::Acoustic(SeekerGeneration::EarlyPassive)
+        );
+        let boat = viic.instantiate("U-96", Point { x: 0.0, y: 0.0 });
+        assert_eq!(boat.weapons.unwrap().tubes.tubes.len(), 5);
+        assert!(!boat.gun.unwrap().manned);
+        assert_eq!(boat.sensors.len(), 2);
+    }
+
+    #[test]
+    fn era_enforced() {
+        let config = Config::parse(CLASSES).unwrap();
+        match load_classes(&config, Era { year: 1942 }) {
+            Err(VesselError::NotInEra { class, .. }) => assert_eq!(class, "type_viic"),
+            other => panic!("{:?}", other),
+        }
+        let classes = load_classes(&Config::parse(CLASSES).unwrap(), Era { year: 1944 }).unwrap();
+        assert_eq!(
+            classes[0].era_violations(Era::MODERN),
+            vec![Subsystem::DeckGun]
+        );
+        assert!(classes[1].era_violations(Era::MODERN).is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+use crate::physics::{normalize_angle, Point};
+use crate::world::EntityId;
+
+// #############################
+// #           WAKES           #
+// #############################
+
+// A wake is a trail of points dropped behind a surface ship. Each point
+// spreads out and fades with age until it is dropped altogether.
+
+/// Distance in meters the ship moves between wake points
+pub const WAKE_SPACING: f32 = 25.0;
+/// Seconds after which a wake point is gone
+pub const WAKE_LIFETIME: f32 = 900.0;
+/// Seconds for a wake point to lose half of its strength
+pub const WAKE_HALF_LIFE: f32 = 300.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WakePoint {
+    pub position: Point,
+    /// Seconds since the point was laid
+    pub age: f32,
+    /// Strength when laid, 0 to 1
+    pub initial: f32,
+}
+
+impl WakePoint {
+    pub fn strength(&self) -> f32 {
+        self.initial * 0.5f32.powf(self.age / WAKE_HALF_LIFE)
+    }
+
+    /// Width of the turbulent water in meters
+    pub fn width(&self) -> f32 {
+        20.0 + self.age * 0.05
+    }
+}
+
+/// Wake trail, oldest point first
+#[derive(Debug, PartialEq, Clone)]
+pub struct Wake {
+    pub owner: EntityId,
+    pub points: VecDeque<WakePoint>,
+}
+
+impl Wake {
+    pub fn new(owner: EntityId) -> Wake {
+        Wake {
+            owner,
+            points: VecDeque::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Lays a new point if the ship has moved far enough since the last one
+    pub fn record(&mut self, position: &Point, speed: f32) {
+        if let Some(last) = self.points.back() {
+            if last.position.distance_to(position) < WAKE_SPACING {
+                return;
+            }
+        }
+        self.points.push_back(WakePoint {
+            position: position.clone(),
+            age: 0.0,
+            initial: (speed / 10.0).min(1.0),
+        });
+    }
+
+    pub fn age(&mut self, dt: f32) {
+        for point in self.points.iter_mut() {
+            point.age += dt;
+        }
+        while self.points.front().is_some_and(|p| p.age > WAKE_LIFETIME) {
+            self.points.pop_front();
+        }
+    }
+
+    /// Returns the closest segment as (older point index, closest point, distance)
+    fn nearest_segment(&self, position: &Point) -> Option<(usize, Point, f32)> {
+        if self.points.len() == 1 {
+            let p = &self.points[0].position;
+            return Some((0, p.clone(), position.distance_to(p)));
+        }
+        (0..self.points.len().saturating_sub(1))
+            .map(|i| {
+                let closest = position
+                    .closest_on_segment(&self.points[i].position, &self.points[i + 1].position);
+                let distance = position.distance_to(&closest);
+                (i, closest, distance)
+            })
+            .fold(
+                None,
+                |best: Option<(usize, Point, f32)>, candidate| match best {
+                    Some(ref b) if b.2 <= candidate.2 => best,
+                    _ => Some(candidate),
+                },
+            )
+    }
+
+    /// How strong the wake is at `position`, 0 when outside of it
+    pub fn intensity_at(&self, position: &Point) -> f32 {
+        let (index, _, distance) = match self.nearest_segment(position) {
+            Some(found) => found,
+            None => return 0.0,
+        };
+        let point = &self.points[index];
+        if distance > point.width() / 2.0 {
+            return 0.0;
+        }
+        point.strength()
+    }
+
+    /// Returns the local wake direction (towards the ship) and which side of
+    /// it `position` lies on: positive on the left, negative on the right
+    pub fn direction_at(&self, position: &Point) -> Option<(f32, f32)> {
+        if self.points.len() < 2 {
+            return None;
+        }
+        let (index, closest, _) = self.nearest_segment(position)?;
+        let a = &self.points[index].position;
+        let b = &self.points[index + 1].position;
+        let along = b.sub(a);
+        let offset = position.sub(&closest);
+        let side = along.x * offset.y - along.y * offset.x;
+        Some((along.angle(), side))
+    }
+}
+
+/// Guidance of a wake-homing torpedo: it runs across the wake and, each time
+/// it comes out the other side, turns back in at `crossing_angle` towards the
+/// younger end, snaking up the wake to the ship that laid it
+#[derive(Debug, PartialEq, Clone)]
+pub struct WakeHomer {
+    /// Weakest wake intensity the sensor can feel
+    pub sensitivity: f32,
+    pub crossing_angle: f32,
+    pub crossings: usize,
+    inside: bool,
+}
+
+impl WakeHomer {
+    pub fn new(sensitivity: f32, crossing_angle: f32) -> WakeHomer {
+        WakeHomer {
+            sensitivity,
+            crossing_angle,
+            crossings: 0,
+            inside: false,
+        }
+    }
+
+    pub fn has_acquired(&self) -> bool {
+        self.crossings > 0
+    }
+
+    /// Returns the heading the torpedo should take
+    pub fn steer(&mut self, position: &Point, heading: f32, wake: &Wake) -> f32 {
+        let inside = wake.intensity_at(position) >= self.sensitivity;
+        let mut new_heading = heading;
+        if inside && !self.inside {
+            self.crossings += 1;
+        }
+        if self.inside && !inside {
+            if let Some((direction, side)) = wake.direction_at(position) {
+                new_heading = direction - side.signum() * self.crossing_angle;
+            }
+        }
+        self.inside = inside;
+        normalize_angle(new_heading)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn straight_wake() -> Wake {
+        let mut wake = Wake::new(1);
+        for i in 0..=40 {
+            wake.record(
+                &Point {
+                    x: i as f32 * 25.0,
+                    y: 0.0,
+                },
+                10.0,
+            );
+        }
+        wake
+    }
+
+    #[test]
+    fn record_spacing() {
+        let mut wake = Wake::new(1);
+        wake.record(&Point { x: 0.0, y: 0.0 }, 10.0);
+        wake.record(&Point { x: 10.0, y: 0.0 }, 10.0);
+        wake.record(&Point { x: 30.0, y: 0.0 }, 10.0);
+        assert_eq!(wake.points.len(), 2);
+    }
+
+    #[test]
+    fn decay() {
+        let mut wake = straight_wake();
+        let p = Point { x: 100.0, y: 5.0 };
+        assert_eq!(wake.intensity_at(&p), 1.0);
+        wake.age(WAKE_HALF_LIFE);
+        assert!((wake.intensity_at(&p) - 0.5).abs() < 0.001);
+        assert_eq!(wake.intensity_at(&Point { x: 100.0, y: 200.0 }), 0.0);
+        wake.age(WAKE_LIFETIME);
+        assert!(wake.is_empty());
+    }
+
+    #[test]
+    fn direction() {
+        let wake = straight_wake();
+        let (direction, side) = wake.direction_at(&Point { x: 300.0, y: 40.0 }).unwrap();
+        assert_eq!(direction, 0.0);
+        assert!(side > 0.0);
+        let (_, side) = wake.direction_at(&Point { x: 300.0, y: -40.0 }).unwrap();
+        assert!(side < 0.0);
+    }
+
+    #[test]
+    fn snakes_up_the_wake() {
+        let wake = straight_wake();
+        let mut homer = WakeHomer::new(0.2, 35f32.to_radians());
+        let mut position = Point {
+            x: 300.0,
+            y: -100.0,
+        };
+        let mut heading = std::f32::consts::FRAC_PI_2;
+        for _ in 0..80 {
+            heading = homer.steer(&position, heading, &wake);
+            position.x += heading.cos() * 10.0;
+            position.y += heading.sin() * 10.0;
+        }
+        assert!(homer.crossings >= 3);
+        assert!(position.x > 600.0);
+        assert!(position.y.abs() < 60.0);
+    }
+
+    #[test]
+    fn faint_wake_not_felt() {
+        let mut wake = straight_wake();
+        wake.age(WAKE_HALF_LIFE * 2.5);
+        let mut homer = WakeHomer::new(0.2, 35f32.to_radians());
+        let mut position = Point {
+            x: 300.0,
+            y: -100.0,
+        };
+        let heading = std::f32::consts::FRAC_PI_2;
+        for _ in 0..30 {
+            assert_eq!(homer.steer(&position, heading, &wake), heading);
+            position.y += 10.0;
+        }
+        assert!(!homer.has_acquired());
+    }
+}
@@ -0,0 +1,469 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::command::PresetCommand;
+use crate::config::{Config, ConfigError, Section};
+use crate::fuse::Pistol;
+use crate::messages::Catalog;
+use crate::reliability::Reliability;
+use crate::seeker::SeekerGeneration;
+use crate::units::{Knots, MetersPerSecond};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpeedSetting {
+    Slow,
+    Medium,
+    Fast,
+}
+
+impl fmt::Display for SpeedSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SpeedSetting::Slow => "slow",
+            SpeedSetting::Medium => "medium",
+            SpeedSetting::Fast => "fast",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl SpeedSetting {
+    /// Running speed in meters per second
+    pub fn meters_per_second(&self) -> f32 {
+        let knots = match self {
+            SpeedSetting::Slow => 15.0,
+            SpeedSetting::Medium => 30.0,
+            SpeedSetting::Fast => 44.0,
+        };
+        MetersPerSecond::from(Knots(knots)).0
+    }
+}
+
+impl FromStr for SpeedSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "slow" => Ok(SpeedSetting::Slow),
+            "medium" => Ok(SpeedSetting::Medium),
+            "fast" => Ok(SpeedSetting::Fast),
+            _ => Err(format!("unknown speed setting '{}'", s)),
+        }
+    }
+}
+
+/// What the torpedo does once the enable run is over
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SearchPattern {
+    Straight,
+    Snake,
+    Circle,
+    Ladder,
+}
+
+impl fmt::Display for SearchPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SearchPattern::Straight => "straight",
+            SearchPattern::Snake => "snake",
+            SearchPattern::Circle => "circle",
+            SearchPattern::Ladder => "ladder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SearchPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "straight" => Ok(SearchPattern::Straight),
+            "snake" => Ok(SearchPattern::Snake),
+            "circle" => Ok(SearchPattern::Circle),
+            "ladder" => Ok(SearchPattern::Ladder),
+            _ => Err(format!("unknown search pattern '{}'", s)),
+        }
+    }
+}
+
+/// How a torpedo finds its target once the enable run is over
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Guidance {
+    /// Runs the search pattern blind
+    Unguided,
+    Acoustic(SeekerGeneration),
+    /// Follows a surface ship wake up to the ship (see `wake::WakeHomer`)
+    WakeHoming,
+}
+
+impl fmt::Display for Guidance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Guidance::Unguided => "unguided",
+            Guidance::Acoustic(SeekerGeneration::EarlyPassive) => "early_passive",
+            Guidance::Acoustic(SeekerGeneration::ActivePassive) => "active_passive",
+            Guidance::Acoustic(SeekerGeneration::Modern) => "modern",
+            Guidance::WakeHoming => "wake_homing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Guidance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unguided" => Ok(Guidance::Unguided),
+            "early_passive" => Ok(Guidance::Acoustic(SeekerGeneration::EarlyPassive)),
+            "active_passive" => Ok(Guidance::Acoustic(SeekerGeneration::ActivePassive)),
+            "modern" => Ok(Guidance::Acoustic(SeekerGeneration::Modern)),
+            "wake_homing" => Ok(Guidance::WakeHoming),
+            _ => Err(format!("unknown torpedo guidance '{}'", s)),
+        }
+    }
+}
+
+/// Settings wired into a torpedo before launch
+#[derive(Debug, PartialEq, Clone)]
+pub struct TorpedoSettings {
+    /// Running depth in meters
+    pub depth: f32,
+    pub speed: SpeedSetting,
+    /// Distance in meters run before the seeker is enabled
+    pub enable_run: f32,
+    pub search: SearchPattern,
+    /// What sets the warhead off, see fuse.rs
+    pub pistol: Pistol,
+}
+
+impl Default for TorpedoSettings {
+    fn default() -> Self {
+        TorpedoSettings {
+            depth: 20.0,
+            speed: SpeedSetting::Medium,
+            enable_run: 1000.0,
+            search: SearchPattern::Straight,
+            pistol: Pistol::Contact,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TorpedoPreset {
+    pub name: String,
+    pub settings: TorpedoSettings,
+}
+
+impl TorpedoPreset {
+    fn read(name: &str, section: &Section) -> Result<TorpedoPreset, ConfigError> {
+        Ok(TorpedoPreset {
+            name: name.to_string(),
+            settings: TorpedoSettings {
+                depth: section.parse("depth")?,
+                speed: section.parse("speed")?,
+                enable_run: section.parse("enable_run")?,
+                search: section.parse("search")?,
+                pistol: section.parse_or("pistol", Pistol::Contact)?,
+            },
+        })
+    }
+
+    fn write(&self, section: &mut Section) {
+        section.set("depth", self.settings.depth);
+        section.set("speed", self.settings.speed);
+        section.set("enable_run", self.settings.enable_run);
+        section.set("search", self.settings.search);
+        section.set("pistol", self.settings.pistol);
+    }
+}
+
+/// Named torpedo presets, persisted as "[preset.<name>]" sections
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PresetLibrary {
+    presets: Vec<TorpedoPreset>,
+}
+
+impl PresetLibrary {
+    pub fn new() -> PresetLibrary {
+        PresetLibrary::default()
+    }
+
+    /// Adds a preset, replacing any preset with the same name
+    pub fn insert(&mut self, preset: TorpedoPreset) {
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TorpedoPreset> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<TorpedoPreset> {
+        let index = self.presets.iter().position(|p| p.name == name)?;
+        Some(self.presets.remove(index))
+    }
+
+    pub fn presets(&self) -> &[TorpedoPreset] {
+        &self.presets
+    }
+
+    pub fn from_config(config: &Config) -> Result<PresetLibrary, ConfigError> {
+        let mut library = PresetLibrary::new();
+        for (name, section) in config.sections_with_prefix("preset") {
+            library.insert(TorpedoPreset::read(name, section)?);
+        }
+        Ok(library)
+    }
+
+    /// Writes every preset into `config`, dropping presets that no longer exist
+    pub fn write_config(&self, config: &mut Config) {
+        let stale: Vec<String> = config
+            .sections_with_prefix("preset")
+            .map(|(_, s)| s.name.clone())
+            .collect();
+        for name in stale {
+            config.remove_section(&name);
+        }
+        for preset in &self.presets {
+            preset.write(config.section_mut(&format!("preset.{}", preset.name)));
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum WeaponError {
+    NoSuchTube(usize),
+    NoSuchPreset(String),
+    TubeEmpty(usize),
+    /// Tube machinery is not worked while rigged for ultra quiet
+    RiggedForUltraQuiet,
+}
+
+impl WeaponError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            WeaponError::NoSuchTube(n) => messages.format("error-no-such-tube", &[("tube", n)]),
+            WeaponError::NoSuchPreset(name) => {
+                messages.format("error-no-such-preset", &[("name", name)])
+            }
+            WeaponError::TubeEmpty(n) => messages.format("error-tube-empty", &[("tube", n)]),
+            WeaponError::RiggedForUltraQuiet => messages.get("error-ultra-quiet").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for WeaponError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for WeaponError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TorpedoTube {
+    pub number: usize,
+    pub loaded: bool,
+    /// Outer door open to the sea
+    pub door_open: bool,
+    pub settings: TorpedoSettings,
+}
+
+impl TorpedoTube {
+    pub fn new(number: usize) -> TorpedoTube {
+        TorpedoTube {
+            number,
+            loaded: true,
+            door_open: false,
+            settings: TorpedoSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TubeBank {
+    pub tubes: Vec<TorpedoTube>,
+}
+
+impl TubeBank {
+    /// Creates `count` tubes numbered from 1
+    pub fn new(count: usize) -> TubeBank {
+        TubeBank {
+            tubes: (1..=count).map(TorpedoTube::new).collect(),
+        }
+    }
+
+    pub fn tube(&self, number: usize) -> Option<&TorpedoTube> {
+        self.tubes.iter().find(|t| t.number == number)
+    }
+
+    pub fn tube_mut(&mut self, number: usize) -> Option<&mut TorpedoTube> {
+        self.tubes.iter_mut().find(|t| t.number == number)
+    }
+
+    pub fn open_doors(&self) -> usize {
+        self.tubes.iter().filter(|t| t.door_open).count()
+    }
+
+    /// Wires the named preset into each listed tube (all tubes when empty)
+    pub fn apply_preset(
+        &mut self,
+        library: &PresetLibrary,
+        name: &str,
+        tubes: &[usize],
+    ) -> Result<(), WeaponError> {
+        let preset = library
+            .get(name)
+            .ok_or_else(|| WeaponError::NoSuchPreset(name.to_string()))?;
+        if let Some(&missing) = tubes.iter().find(|&&n| self.tube(n).is_none()) {
+            return Err(WeaponError::NoSuchTube(missing));
+        }
+        for tube in self.tubes.iter_mut() {
+            if tubes.is_empty() || tubes.contains(&tube.number) {
+                tube.settings = preset.settings.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the settings currently wired into `tube` as a named preset
+    pub fn save_preset(
+        &self,
+        library: &mut PresetLibrary,
+        name: &str,
+        tube: usize,
+    ) -> Result<(), WeaponError> {
+        let tube = self.tube(tube).ok_or(WeaponError::NoSuchTube(tube))?;
+        library.insert(TorpedoPreset {
+            name: name.to_string(),
+            settings: tube.settings.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// The tubes together with the presets the crew can wire into them
+#[derive(Debug, PartialEq, Clone)]
+pub struct WeaponsStation {
+    pub tubes: TubeBank,
+    pub presets: PresetLibrary,
+    /// Type of the torpedoes carried
+    pub guidance: Guidance,
+    /// How trustworthy the torpedoes carried are
+    pub reliability: Reliability,
+}
+
+impl WeaponsStation {
+    pub fn new(tubes: usize, presets: PresetLibrary) -> WeaponsStation {
+        WeaponsStation {
+            tubes: TubeBank::new(tubes),
+            presets,
+            guidance: Guidance::Unguided,
+            reliability: Reliability::perfect(),
+        }
+    }
+
+    pub fn execute(&mut self, command: &PresetCommand) -> Result<(), WeaponError> {
+        match command {
+            PresetCommand::Save { name, tube } => {
+                self.tubes.save_preset(&mut self.presets, name, *tube)
+            }
+            PresetCommand::Apply { name, tubes } => {
+                self.tubes.apply_preset(&self.presets, name, tubes)
+            }
+            PresetCommand::Delete { name } => self
+                .presets
+                .remove(name)
+                .map(|_| ())
+                .ok_or_else(|| WeaponError::NoSuchPreset(name.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+
+    fn deep_snake() -> TorpedoPreset {
+        TorpedoPreset {
+            name: "deep".to_string(),
+            settings: TorpedoSettings {
+                depth: 80.0,
+                speed: SpeedSetting::Slow,
+                enable_run: 2500.0,
+                search: SearchPattern::Snake,
+                pistol: Pistol::Magnetic,
+            },
+        }
+    }
+
+    #[test]
+    fn library_round_trip() {
+        let mut library = PresetLibrary::new();
+        library.insert(deep_snake());
+        let mut config = Config::new();
+        library.write_config(&mut config);
+        let text = config.to_string();
+        let loaded = PresetLibrary::from_config(&Config::parse(&text).unwrap()).unwrap();
+        assert_eq!(loaded, library);
+    }
+
+    #[test]
+    fn library_write_drops_removed() {
+        let mut library = PresetLibrary::new();
+        library.insert(deep_snake());
+        let mut config = Config::new();
+        library.write_config(&mut config);
+        library.remove("deep");
+        library.write_config(&mut config);
+        assert!(config.section("preset.deep").is_none());
+    }
+
+    #[test]
+    fn apply_preset1() {
+        let mut library = PresetLibrary::new();
+        library.insert(deep_snake());
+        let mut bank = TubeBank::new(4);
+        bank.apply_preset(&library, "deep", &[2, 3]).unwrap();
+        assert_eq!(bank.tube(1).unwrap().settings, TorpedoSettings::default());
+        assert_eq!(bank.tube(2).unwrap().settings.depth, 80.0);
+        assert_eq!(bank.tube(3).unwrap().settings.search, SearchPattern::Snake);
+    }
+
+    #[test]
+    fn apply_preset_errors() {
+        let mut library = PresetLibrary::new();
+        library.insert(deep_snake());
+        let mut bank = TubeBank::new(2);
+        assert_eq!(
+            bank.apply_preset(&library, "shallow", &[]),
+            Err(WeaponError::NoSuchPreset("shallow".to_string()))
+        );
+        assert_eq!(
+            bank.apply_preset(&library, "deep", &[1, 5]),
+            Err(WeaponError::NoSuchTube(5))
+        );
+        assert_eq!(bank.tube(1).unwrap().settings, TorpedoSettings::default());
+    }
+
+    #[test]
+    fn station_commands() {
+        let parse = |line: &str| match Command::parse(line) {
+            Ok(Command::Preset(command)) => command,
+            other => panic!("{:?}", other),
+        };
+        let mut station = WeaponsStation::new(4, PresetLibrary::new());
+        station.tubes.tube_mut(1).unwrap().settings = deep_snake().settings;
+        station.execute(&parse("preset save deep 1")).unwrap();
+        station.execute(&parse("preset apply deep")).unwrap();
+        assert!(station.tubes.tubes.iter().all(|t| t.settings.depth == 80.0));
+        station.execute(&parse("preset delete deep")).unwrap();
+        assert!(station.presets.get("deep").is_none());
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, ConfigError, Section};
+use crate::environment::{Environment, SoundSpeedProfile, TimeOfDay, SECONDS_PER_DAY};
+use crate::physics::{normalize_angle, user_to_game_angle};
+use crate::world::World;
+
+// #############################
+// #          WEATHER          #
+// #############################
+
+// A scenario may start from a preset of the conditions at sea instead of
+// setting each of them, and the keys of the "[scenario]" section it does
+// set override the preset:
+//
+// [scenario]
+// weather = fog           # calm, haze, fog or gale
+// sound_profile = negative   # layer, deep_layer, negative or isovelocity
+//
+// The sound profiles: "layer" is a surface layer 60 m deep over the
+// thermocline, "deep_layer" a winter layer mixed down to 150 m, "negative"
+// a summer profile warmest at the surface with no layer at all, and
+// "isovelocity" shallow water mixed from top to bottom. A "[sound_speed]"
+// section still replaces the profile point by point.
+//
+// The weather then changes during the mission as "[weather.<name>]"
+// sections script it:
+//
+// [weather.dawn]
+// at = 06:00              # local time, or seconds into the scenario
+// over = 1800             # optional, seconds the change takes
+// visibility = 15000      # meters
+// sea_state = 2           # optional, as are the keys below
+// wave_from = 300         # degrees
+// ice_cover = 0
+// sound_profile = layer
+//
+// A change eases from the conditions it finds to those it sets over its
+// time, so fog lifts and the sea gets up gradually. The sea state is
+// whole, and goes up or down a step at a time along the way.
+
+/// Seconds a change takes, unless set
+const OVER: f32 = 1_800.0;
+
+/// Shapes of the speed of sound against depth
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ProfileKind {
+    #[default]
+    Layer,
+    DeepLayer,
+    Negative,
+    Isovelocity,
+}
+
+impl ProfileKind {
+    pub fn profile(&self) -> SoundSpeedProfile {
+        let points = match self {
+            ProfileKind::Layer => return SoundSpeedProfile::default(),
+            ProfileKind::DeepLayer => vec![
+                (0.0, 1490.0),
+                (150.0, 1492.5),
+                (300.0, 1486.0),
+                (1000.0, 1482.0),
+                (2000.0, 1490.0),
+                (4000.0, 1518.0),
+            ],
+            ProfileKind::Negative => vec![
+                (0.0, 1515.0),
+                (100.0, 1500.0),
+                (500.0, 1487.0),
+                (1000.0, 1482.0),
+                (2000.0, 1490.0),
+                (4000.0, 1518.0),
+            ],
+            ProfileKind::Isovelocity => vec![(0.0, 1495.0), (4000.0, 1495.0)],
+        };
+        SoundSpeedProfile { points }
+    }
+}
+
+impl fmt::Display for ProfileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProfileKind::Layer => "layer",
+            ProfileKind::DeepLayer => "deep_layer",
+            ProfileKind::Negative => "negative",
+            ProfileKind::Isovelocity => "isovelocity",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ProfileKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "layer" => Ok(ProfileKind::Layer),
+            "deep_layer" => Ok(ProfileKind::DeepLayer),
+            "negative" => Ok(ProfileKind::Negative),
+            "isovelocity" => Ok(ProfileKind::Isovelocity),
+            _ => Err(format!("unknown sound profile '{}'", s)),
+        }
+    }
+}
+
+/// Conditions at sea a scenario may start from
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Preset {
+    Calm,
+    Haze,
+    Fog,
+    Gale,
+}
+
+impl Preset {
+    /// The environment of the preset, otherwise the default one
+    pub fn environment(&self) -> Environment {
+        let (sea_state, visibility, profile) = match self {
+            Preset::Calm => (1, 25_000.0, ProfileKind::Layer),
+            Preset::Haze => (2, 6_000.0, ProfileKind::Layer),
+            Preset::Fog => (1, 400.0, ProfileKind::Negative),
+            Preset::Gale => (7, 4_000.0, ProfileKind::DeepLayer),
+        };
+        Environment {
+            sea_state,
+            visibility,
+            sound_speed: profile.profile(),
+            ..Environment::default()
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "calm" => Ok(Preset::Calm),
+            "haze" => Ok(Preset::Haze),
+            "fog" => Ok(Preset::Fog),
+            "gale" => Ok(Preset::Gale),
+            _ => Err(format!("unknown weather '{}'", s)),
+        }
+    }
+}
+
+/// A scripted change of the weather
+#[derive(Debug, PartialEq, Clone)]
+pub struct WeatherChange {
+    pub name: String,
+    /// Seconds into the scenario it starts at
+    pub start: f32,
+    /// Seconds it takes
+    pub over: f32,
+    pub sea_state: Option<u8>,
+    /// Game angle
+    pub wave_from: Option<f32>,
+    pub visibility: Option<f32>,
+    pub ice_cover: Option<f32>,
+    pub sound_speed: Option<SoundSpeedProfile>,
+    /// The conditions it found when it started
+    from: Option<Environment>,
+    done: bool,
+}
+
+impl WeatherChange {
+    /// Reads a "[weather.<name>]" section, for a scenario starting at
+    /// `start_time` seconds after local midnight
+    pub fn read(
+        name: &str,
+        section: &Section,
+        start_time: f32,
+    ) -> Result<WeatherChange, ConfigError> {
+        let at: String = section.parse("at")?;
+        let start = if at.contains(':') {
+            let clock = section.parse::<TimeOfDay>("at")?.0;
+            (clock - start_time).rem_euclid(SECONDS_PER_DAY)
+        } else {
+            section.parse("at")?
+        };
+        if !start.is_finite() {
+            return Err(ConfigError::Invalid {
+                section: section.name.clone(),
+                key: "at".to_string(),
+                value: at,
+            });
+        }
+        Ok(WeatherChange {
+            name: name.to_string(),
+            start,
+            over: section.parse_or("over", OVER)?.max(0.0),
+            sea_state: section.parse_optional("sea_state")?,
+            wave_from: section
+                .parse_optional::<f32>("wave_from")?
+                .map(user_to_game_angle),
+            visibility: section.parse_optional("visibility")?,
+            ice_cover: section.parse_optional("ice_cover")?,
+            sound_speed: section
+                .parse_optional::<ProfileKind>("sound_profile")?
+                .map(|kind| kind.profile()),
+            from: None,
+            done: false,
+        })
+    }
+
+    /// Reads every "[weather.<name>]" section, in the order they start
+    pub fn read_all(config: &Config, start_time: f32) -> Result<Vec<WeatherChange>, ConfigError> {
+        let mut changes = Vec::new();
+        for (name, section) in config.sections_with_prefix("weather") {
+            changes.push(WeatherChange::read(name, section, start_time)?);
+        }
+        changes.sort_by(|a, b| a.start.total_cmp(&b.start));
+        Ok(changes)
+    }
+
+    /// `from` eased a fraction `t` of the way to the conditions it sets
+    fn apply(&self, from: &Environment, t: f32, to: &mut Environment) {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        if let Some(sea_state) = self.sea_state {
+            to.sea_state = lerp(from.sea_state as f32, sea_state as f32).round() as u8;
+        }
+        if let Some(wave_from) = self.wave_from {
+            let turn = normalize_angle(wave_from - from.wave_from);
+            to.wave_from = normalize_angle(from.wave_from + turn * t);
+        }
+        if let Some(visibility) = self.visibility {
+            to.visibility = lerp(from.visibility, visibility);
+        }
+        if let Some(ice_cover) = self.ice_cover {
+            to.ice_cover = lerp(from.ice_cover, ice_cover);
+        }
+        if let Some(profile) = &self.sound_speed {
+            to.sound_speed = from.sound_speed.blend(profile, t);
+        }
+    }
+}
+
+/// Eases the environment through the weather changes under way
+pub fn update(world: &mut World) {
+    let time = world.time;
+    let mut changes = std::mem::take(&mut world.weather);
+    for change in changes.iter_mut() {
+        if change.done || time < change.start {
+            continue;
+        }
+        let from = change
+            .from
+            .get_or_insert_with(|| world.environment.clone())
+            .clone();
+        let t = if change.over > 0.0 {
+            ((time - change.start) / change.over).min(1.0)
+        } else {
+            1.0
+        };
+        change.apply(&from, t, &mut world.environment);
+        change.done = t >= 1.0;
+    }
+    world.weather = changes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "
+[weather.dawn]
+at = 06:00
+over = 1000
+visibility = 15000
+sea_state = 5
+sound_profile = isovelocity
+";
+
+    #[test]
+    fn presets_and_profiles() {
+        let fog = Preset::Fog.environment();
+        assert_eq!(fog.visibility, 400.0);
+        assert_eq!(fog.sound_speed.layer_depth(), None);
+        assert_eq!("gale".parse(), Ok(Preset::Gale));
+        assert!("drizzle".parse::<Preset>().is_err());
+        assert_eq!(ProfileKind::DeepLayer.profile().layer_depth(), Some(150.0));
+        assert_eq!("Deep_Layer".parse(), Ok(ProfileKind::DeepLayer));
+    }
+
+    #[test]
+    fn fog_lifts_at_dawn() {
+        let config = Config::parse(SCRIPT).unwrap();
+        let start = "05:00".parse::<TimeOfDay>().unwrap().0;
+        let mut world = World::new();
+        world.environment = Preset::Fog.environment();
+        world.weather = WeatherChange::read_all(&config, start).unwrap();
+        assert_eq!(world.weather[0].start, 3_600.0);
+        world.time = 3_000.0;
+        update(&mut world);
+        assert_eq!(world.environment.visibility, 400.0);
+        world.time = 3_600.0;
+        update(&mut world);
+        world.time = 4_100.0;
+        update(&mut world);
+        let halfway = world.environment.clone();
+        assert!((halfway.visibility - 7_700.0).abs() < 1.0);
+        assert_eq!(halfway.sea_state, 3);
+        let speed = halfway.sound_speed.speed_at(0.0);
+        assert!(speed < 1515.0 && speed > 1495.0);
+        world.time = 5_000.0;
+        update(&mut world);
+        assert_eq!(world.environment.visibility, 15_000.0);
+        assert_eq!(world.environment.sea_state, 5);
+        assert_eq!(world.environment.sound_speed.speed_at(300.0), 1495.0);
+        // done, it leaves the weather alone
+        world.environment.visibility = 100.0;
+        update(&mut world);
+        assert_eq!(world.environment.visibility, 100.0);
+
+        for bad in ["nan", "inf"] {
+            let config = Config::parse(&SCRIPT.replace("06:00", bad)).unwrap();
+            assert!(WeatherChange::read_all(&config, start).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt;
+
+use crate::config::{Config, ConfigError};
+use crate::contacts::ContactTable;
+use crate::events::Event;
+use crate::gunnery::PERISCOPE_DEPTH;
+use crate::hfdf::{self, RadioError};
+use crate::messages::Catalog;
+use crate::physics::Point;
+use crate::world::{EntityId, World};
+
+// #############################
+// #         WOLFPACK          #
+// #############################
+
+// Boats of a pack do not attack alone: the first to find a convoy shadows
+// it and radios where it is, and the others close in on the reports. The
+// boats of the scenario that hunt with the player are listed in a
+// "[wolfpack]" section, with the doctrine the pack follows:
+//
+// [wolfpack]
+// boats = U-96, U-552     # AI boats of the pack
+// converge = 2            # optional, reports agreeing on a contact before
+//                         # the pack is ordered in
+// window = 7200           # optional, seconds the reports must fall within
+// range = 150000          # optional, meters within which a boat is ordered in
+// interval = 1800         # optional, seconds between the reports of a boat
+//                         # shadowing
+//
+// A boat of the pack holding a contact reports it from periscope depth at
+// most once an interval; the player reports one with
+//
+// shadow <contact>        # where contact is its number on the sonar display
+//
+// and gets the reports of the others, plotted on the chart. Every report
+// is radio traffic the enemy may fix (see hfdf.rs). Once enough reports
+// agree, within a few kilometers of one another, the boats within range
+// that are not attacking or evading already sprint and drift to the
+// latest of them (see ai.rs).
+
+/// Reports agreeing on a contact before the pack is ordered in, unless set
+const CONVERGE: usize = 2;
+/// Seconds the reports must fall within, unless set
+const WINDOW: f32 = 7_200.0;
+/// Meters within which a boat is ordered in, unless set
+const RANGE: f32 = 150_000.0;
+/// Seconds between two reports of a boat, unless set
+const INTERVAL: f32 = 1_800.0;
+/// Meters within which two reports are of the same contact
+const AGREEMENT: f32 = 10_000.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PackError {
+    NoSuchContact(u32),
+    /// The contact is held on a bearing only
+    NoPosition(u32),
+    Radio(RadioError),
+}
+
+impl PackError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            PackError::NoSuchContact(number) => {
+                messages.format("error-no-such-contact", &[("target", number)])
+            }
+            PackError::NoPosition(number) => {
+                messages.format("error-no-position", &[("number", number)])
+            }
+            PackError::Radio(e) => e.describe(messages),
+        }
+    }
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for PackError {}
+
+impl From<RadioError> for PackError {
+    fn from(e: RadioError) -> Self {
+        PackError::Radio(e)
+    }
+}
+
+/// Where a boat of the pack reported a contact
+#[derive(Debug, PartialEq, Clone)]
+pub struct PackReport {
+    /// Seconds into the scenario
+    pub time: f32,
+    pub from: EntityId,
+    pub position: Point,
+}
+
+/// The pack and its doctrine, and what it has reported
+#[derive(Debug, PartialEq, Clone)]
+pub struct Wolfpack {
+    /// Names of the AI boats of the pack
+    pub boats: Vec<String>,
+    pub converge: usize,
+    pub window: f32,
+    pub range: f32,
+    pub interval: f32,
+    pub reports: Vec<PackReport>,
+    /// Where the pack was ordered in, and when
+    pub orders: Vec<(f32, Point)>,
+    /// When each boat last reported
+    last_report: Vec<(EntityId, f32)>,
+}
+
+impl Default for Wolfpack {
+    fn default() -> Wolfpack {
+        Wolfpack {
+            boats: Vec::new(),
+            converge: CONVERGE,
+            window: WINDOW,
+            range: RANGE,
+            interval: INTERVAL,
+            reports: Vec::new(),
+            orders: Vec::new(),
+            last_report: Vec::new(),
+        }
+    }
+}
+
+impl Wolfpack {
+    /// Reads the "[wolfpack]" section, no pack without one
+    pub fn read(config: &Config) -> Result<Wolfpack, ConfigError> {
+        let mut pack = Wolfpack::default();
+        let section = match config.section("wolfpack") {
+            Some(section) => section,
+            None => return Ok(pack),
+        };
+        pack.boats = section
+            .parse::<String>("boats")?
+            .split(',')
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty())
+            .collect();
+        pack.converge = section.parse_or("converge", CONVERGE)?.max(1);
+        pack.window = section.parse_or("window", WINDOW)?;
+        pack.range = section.parse_or("range", RANGE)?;
+        pack.interval = section.parse_or("interval", INTERVAL)?;
+        Ok(pack)
+    }
+
+    /// Whether the pack has already been ordered in on `position` lately
+    fn ordered_to(&self, time: f32, position: &Point) -> bool {
+        self.orders
+            .iter()
+            .any(|(at, to)| time - at <= self.window && to.distance_to(position) <= AGREEMENT)
+    }
+}
+
+/// `from` radios a report of a contact at `position` to the pack, ordering
+/// the pack in if enough reports now agree
+pub fn report(world: &mut World, from: EntityId, position: Point) -> Result<(), RadioError> {
+    hfdf::transmit(world, from, hfdf::report_length(1))?;
+    let time = world.time;
+    let pack = &mut world.wolfpack;
+    pack.reports.push(PackReport {
+        time,
+        from,
+        position: position.clone(),
+    });
+    match pack.last_report.iter_mut().find(|(id, _)| *id == from) {
+        Some((_, at)) => *at = time,
+        None => pack.last_report.push((from, time)),
+    }
+    let agreeing = pack
+        .reports
+        .iter()
+        .filter(|r| time - r.time <= pack.window && r.position.distance_to(&position) <= AGREEMENT)
+        .count();
+    let converging = agreeing >= pack.converge && !pack.ordered_to(time, &position);
+    if converging {
+        pack.orders.push((time, position.clone()));
+    }
+    let (range, boats) = (pack.range, pack.boats.clone());
+    world.emit(Event::PackReported { from });
+    if !converging {
+        return Ok(());
+    }
+    for boat in world.entities.iter_mut() {
+        if !boats.contains(&boat.name) || boat.is_destroyed() || boat.id == from {
+            continue;
+        }
+        if boat.position.distance_to(&position) > range {
+            continue;
+        }
+        if let Some(ai) = boat.ai.as_mut() {
+            if ai.contact.is_none() && ai.evasion <= 0.0 {
+                ai.send_to(position.clone());
+            }
+        }
+    }
+    world.emit(Event::PackConverging { position });
+    Ok(())
+}
+
+/// Where the player's contact `number` is taken to be: on its track, or at
+/// its range down its bearing
+pub fn position_of(contacts: &ContactTable, own: &Point, number: u32) -> Result<Point, PackError> {
+    let contact = contacts
+        .contacts
+        .iter()
+        .find(|c| c.number == number)
+        .ok_or(PackError::NoSuchContact(number))?;
+    if let Some(track) = &contact.track {
+        return Ok(track.position());
+    }
+    let (range, _) = contact.range.ok_or(PackError::NoPosition(number))?;
+    Ok(Point {
+        x: own.x + range * contact.bearing.cos(),
+        y: own.y + range * contact.bearing.sin(),
+    })
+}
+
+/// Has the boats of the pack shadowing a contact report it when due
+pub fn update(world: &mut World) {
+    let time = world.time;
+    let pack = &world.wolfpack;
+    let due: Vec<(EntityId, Point)> = world
+        .entities
+        .iter()
+        .filter(|b| pack.boats.contains(&b.name) && !b.is_destroyed())
+        .filter(|b| b.depth <= PERISCOPE_DEPTH)
+        .filter(|b| {
+            pack.last_report
+                .iter()
+                .find(|(id, _)| *id == b.id)
+                .is_none_or(|(_, at)| time - at >= pack.interval)
+        })
+        .filter_map(|b| {
+            let contact = b.ai.as_ref()?.contact.as_ref()?;
+            Some((b.id, contact.position.clone()))
+        })
+        .collect();
+    for (boat, position) in due {
+        // only deep boats are refused, and they were left out
+        let _ = report(world, boat, position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{Contact, SubmarineAi};
+    use crate::world::{Entity, EntityKind};
+
+    fn pack() -> (World, EntityId, EntityId) {
+        let config = Config::parse("[wolfpack]\nboats = U-96, U-552\nconverge = 2").unwrap();
+        let mut world = World::new();
+        world.wolfpack = Wolfpack::read(&config).unwrap();
+        let mut player = Entity::new("U-99", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        player.depth = PERISCOPE_DEPTH;
+        let player = world.spawn(player);
+        let mut shadower = Entity::new(
+            "U-96",
+            EntityKind::Submarine,
+            Point {
+                x: 20_000.0,
+                y: 0.0,
+            },
+        );
+        shadower.depth = PERISCOPE_DEPTH;
+        shadower.ai = Some(SubmarineAi::new(8.0, 0.0, PERISCOPE_DEPTH));
+        let shadower = world.spawn(shadower);
+        let mut far = Entity::new(
+            "U-552",
+            EntityKind::Submarine,
+            Point {
+                x: -50_000.0,
+                y: 0.0,
+            },
+        );
+        far.depth = 100.0;
+        far.ai = Some(SubmarineAi::new(8.0, 0.0, 100.0));
+        world.spawn(far);
+        (world, player, shadower)
+    }
+
+    #[test]
+    fn agreeing_reports_bring_the_pack_in() {
+        let (mut world, player, shadower) = pack();
+        let convoy = Point {
+            x: 10_000.0,
+            y: 10_000.0,
+        };
+        report(&mut world, player, convoy.clone()).unwrap();
+        assert!(world.wolfpack.orders.is_empty());
+        let near = Point {
+            x: 12_000.0,
+            y: 9_000.0,
+        };
+        report(&mut world, shadower, near.clone()).unwrap();
+        assert_eq!(world.wolfpack.orders, vec![(0.0, near.clone())]);
+        let far = world.entities.iter().find(|e| e.name == "U-552").unwrap();
+        let sprint = far.ai.as_ref().unwrap().sprint_to.as_ref().unwrap();
+        assert_eq!(sprint.waypoint, near);
+        // ordered in once
+        report(&mut world, player, convoy).unwrap();
+        assert_eq!(world.wolfpack.orders.len(), 1);
+
+        // deep, nothing gets out
+        world.entity_mut(player).unwrap().depth = 50.0;
+        assert_eq!(
+            report(&mut world, player, Point { x: 0.0, y: 0.0 }),
+            Err(RadioError::TooDeep)
+        );
+        assert!(Wolfpack::read(&Config::parse("[wolfpack]\nconverge = 2").unwrap()).is_err());
+    }
+
+    #[test]
+    fn shadowers_report_once_an_interval() {
+        let (mut world, _, shadower) = pack();
+        let mut heard = Entity::new(
+            "HX-72 1",
+            EntityKind::Merchant,
+            Point {
+                x: 25_000.0,
+                y: 0.0,
+            },
+        );
+        heard.speed = 4.0;
+        let target = world.spawn(heard);
+        let ai = world.entity_mut(shadower).unwrap().ai.as_mut().unwrap();
+        ai.contact = Some(Contact {
+            target,
+            position: Point {
+                x: 25_000.0,
+                y: 0.0,
+            },
+            depth: 0.0,
+            velocity: Point { x: 0.0, y: 0.0 },
+            first_heard: 0.0,
+            last_heard: 0.0,
+            track: None,
+        });
+        update(&mut world);
+        update(&mut world);
+        assert_eq!(world.wolfpack.reports.len(), 1);
+        assert_eq!(world.wolfpack.reports[0].from, shadower);
+        world.time = INTERVAL;
+        update(&mut world);
+        assert_eq!(world.wolfpack.reports.len(), 2);
+    }
+}
@@ -0,0 +1,932 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ai::behavior::Behaviors;
+use crate::ai::{self, SubmarineAi};
+use crate::asw::{self, AswStation, Charge};
+use crate::atmosphere::{self, Atmosphere};
+use crate::cargo::{self, Hold};
+use crate::casualties::{self, Casualties};
+use crate::coastline::Coastline;
+use crate::crew::CrewQuality;
+use crate::decoy::{self, Decoy};
+use crate::dive::{self, Transition};
+use crate::environment::Environment;
+use crate::escort::{self, EscortAi};
+use crate::events::{Event, TimedEvent};
+use crate::faction::{self, Diplomacy};
+use crate::gunnery::{self, Gun};
+use crate::hazards::{self, Hazard};
+use crate::helicopter;
+use crate::hfdf::{self, DirectionFinding};
+use crate::identification::Confusion;
+use crate::intercept::{Emission, EmissionKind};
+use crate::lookouts::{self, Lookouts};
+use crate::machinery::{self, Machinery};
+use crate::morale::{self, DEFAULT_MORALE};
+use crate::noise::{Rig, ULTRA_QUIET_MAX_SPEED};
+use crate::physics::Point;
+use crate::radar::{self, RadarGeneration};
+use crate::random::Rng;
+use crate::registry::EntityRegistry;
+use crate::rendezvous::{self, Rendezvous};
+use crate::route;
+use crate::seafloor::SETTLE_SPEED;
+use crate::seakeeping;
+use crate::sensors::Sensor;
+use crate::shadowing::{self, Shadow};
+use crate::stores::{self, Stores};
+use crate::theater::{self, Theater};
+use crate::torpedo::{self, TorpedoState};
+use crate::trace::{self, Level};
+use crate::traffic::{self, Traffic};
+use crate::transient;
+use crate::tuning::Tunable;
+use crate::vds;
+use crate::wake::Wake;
+use crate::weapons::WeaponsStation;
+use crate::weather::{self, WeatherChange};
+use crate::wolfpack::{self, Wolfpack};
+use crate::zone::Zone;
+
+// Positions are in meters, depths in meters (positive down), headings are
+// "game angles" in radians and speeds are in meters per second.
+
+pub type EntityId = usize;
+
+/// Meters on a side of the cells routes are planned on
+const ROUTE_CELL: f32 = 100.0;
+
+/// Hull integrity a submarine loses striking the bottom
+const BOTTOM_DAMAGE: f32 = 0.05;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntityKind {
+    Submarine,
+    Warship,
+    Merchant,
+    Torpedo,
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EntityKind::Submarine => "submarine",
+            EntityKind::Warship => "warship",
+            EntityKind::Merchant => "merchant",
+            EntityKind::Torpedo => "torpedo",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for EntityKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "submarine" => Ok(EntityKind::Submarine),
+            "warship" => Ok(EntityKind::Warship),
+            "merchant" => Ok(EntityKind::Merchant),
+            "torpedo" => Ok(EntityKind::Torpedo),
+            _ => Err(format!("unknown entity kind '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entity {
+    pub id: EntityId,
+    pub name: String,
+    pub kind: EntityKind,
+    /// Vessel class the entity was built from, if any
+    pub class: Option<String>,
+    /// Side it fights on, None when not given
+    pub side: Option<String>,
+    /// Words a scenario groups entities by, see registry.rs
+    pub tags: Vec<String>,
+    pub position: Point,
+    pub depth: f32,
+    /// Meters the waves hold a submarine off its depth, see seakeeping.rs
+    pub heave: f32,
+    /// Automatic depth keeping with the trim pumps
+    pub depth_assist: bool,
+    pub heading: f32,
+    pub speed: f32,
+    /// Periscope or other masts above the water
+    pub mast_raised: bool,
+    /// Remaining hull integrity, 1 (intact) to 0 (destroyed)
+    pub hull: f32,
+    pub gun: Option<Gun>,
+    /// Active sonar and weapons of an escort, see asw.rs
+    pub asw: Option<AswStation>,
+    pub weapons: Option<WeaponsStation>,
+    pub torpedo: Option<TorpedoState>,
+    pub sensors: Vec<Sensor>,
+    /// Generation of the radar among `sensors`, if it has one
+    pub radar_generation: RadarGeneration,
+    /// Submarines held on radar, see radar.rs
+    pub radar_contacts: Vec<EntityId>,
+    /// Level in dB of the transient noise the entity is making, 0 when quiet
+    pub transient: f32,
+    /// Depth the hull last settled at, None until first seen; see
+    /// transient.rs
+    pub settled_depth: Option<f32>,
+    /// Expendable bathythermographs left
+    pub xbts: u32,
+    /// Towed and mobile decoys left, see decoy.rs
+    pub towed_decoys: u32,
+    pub decoys: u32,
+    pub rig: Rig,
+    pub crew: CrewQuality,
+    /// Spirits of the crew, from 0 to 1; see morale.rs
+    pub morale: f32,
+    /// Wounded and killed among the crew, None when not tracked; see
+    /// casualties.rs
+    pub casualties: Option<Casualties>,
+    /// Air in the boat, None when not tracked; see atmosphere.rs
+    pub atmosphere: Option<Atmosphere>,
+    /// Fuel, provisions and spare parts, None when not tracked; see
+    /// stores.rs
+    pub stores: Option<Stores>,
+    /// Wear and shock of the machinery since its last overhaul, None when
+    /// not tracked; see machinery.rs
+    pub machinery: Option<Machinery>,
+    /// Refits other ships alongside
+    pub tender: bool,
+    /// Tonnage, cargo and flooding of a merchant, None when not tracked;
+    /// see cargo.rs
+    pub hold: Option<Hold>,
+    /// Dive or surfacing under way, see dive.rs
+    pub transition: Option<Transition>,
+    /// Computer control, None for the player and ships that just sail on
+    pub ai: Option<SubmarineAi>,
+    /// Computer control of an escort, see escort.rs
+    pub escort_ai: Option<EscortAi>,
+}
+
+impl Entity {
+    pub fn new(name: &str, kind: EntityKind, position: Point) -> Entity {
+        Entity {
+            id: 0,
+            name: name.to_string(),
+            kind,
+            class: None,
+            side: None,
+            tags: Vec::new(),
+            position,
+            depth: 0.0,
+            heave: 0.0,
+            depth_assist: true,
+            heading: 0.0,
+            speed: 0.0,
+            mast_raised: false,
+            hull: 1.0,
+            gun: None,
+            asw: None,
+            weapons: None,
+            torpedo: None,
+            sensors: Vec::new(),
+            radar_generation: RadarGeneration::default(),
+            radar_contacts: Vec::new(),
+            transient: 0.0,
+            settled_depth: None,
+            xbts: 0,
+            towed_decoys: 0,
+            decoys: 0,
+            rig: Rig::Normal,
+            crew: CrewQuality::Trained,
+            stores: None,
+            machinery: None,
+            tender: false,
+            atmosphere: None,
+            morale: DEFAULT_MORALE,
+            casualties: None,
+            hold: None,
+            transition: None,
+            ai: None,
+            escort_ai: None,
+        }
+    }
+
+    /// How well the crew works, for the air it breathes, the hands it has
+    /// and its spirits; 1 at best
+    pub fn crew_performance(&self) -> f32 {
+        atmosphere::performance(self) * casualties::performance(self) * morale::performance(self)
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.hull <= 0.0
+    }
+
+    /// Meters from the waterline, or the depth of a submerged boat, down to
+    /// the keel
+    pub fn draft(&self) -> f32 {
+        match self.kind {
+            EntityKind::Submarine => 5.0,
+            EntityKind::Warship => 4.0,
+            EntityKind::Merchant => 8.0,
+            EntityKind::Torpedo => 0.0,
+        }
+    }
+
+    pub fn velocity(&self) -> Point {
+        Point {
+            x: self.heading.cos() * self.speed,
+            y: self.heading.sin() * self.speed,
+        }
+    }
+
+    pub fn is_surfaced(&self) -> bool {
+        self.depth < 1.0
+    }
+
+    /// Meters from stem to stern
+    pub fn length(&self) -> f32 {
+        match self.kind {
+            EntityKind::Submarine => 67.0,
+            EntityKind::Warship => 80.0,
+            EntityKind::Merchant => 130.0,
+            EntityKind::Torpedo => 7.0,
+        }
+    }
+
+    /// Surfaced hulls moving fast enough churn up a visible wake
+    pub fn leaves_wake(&self) -> bool {
+        self.kind != EntityKind::Torpedo && self.is_surfaced() && self.speed > 2.0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct World {
+    /// Seconds since the start of the scenario
+    pub time: f32,
+    pub entities: EntityRegistry,
+    pub wakes: Vec<Wake>,
+    /// Decoys in the water, see decoy.rs
+    pub decoys: Vec<Decoy>,
+    /// Depth charges and mortar bombs sinking, see asw.rs
+    pub charges: Vec<Charge>,
+    /// Mines and wreckage adrift, see hazards.rs
+    pub hazards: Vec<Hazard>,
+    /// Meters per second the surface current sets along by
+    pub current: Point,
+    pub environment: Environment,
+    /// Everything that happened, in order; consumers keep their own cursor
+    pub events: Vec<TimedEvent>,
+    pub rng: Rng,
+    /// Behavior trees of the AI, by role
+    pub behaviors: Behaviors,
+    pub zones: Vec<Zone>,
+    /// How the sides stand to each other, see faction.rs
+    pub diplomacy: Diplomacy,
+    /// Classes taken for one another, see identification.rs
+    pub confusion: Confusion,
+    /// Land of real-world shorelines, see coastline.rs
+    pub coastline: Coastline,
+    /// Active pulses sent since the start of the current tick
+    pub emissions: Vec<Emission>,
+    /// Scheduled sailings, see traffic.rs
+    pub traffic: Traffic,
+    /// Shore stations listening for radio traffic, see hfdf.rs
+    pub hfdf: DirectionFinding,
+    /// The boats hunting with the player, see wolfpack.rs
+    pub wolfpack: Wolfpack,
+    /// Hunter-killer groups and air patrols of a side, see theater.rs
+    pub theater: Theater,
+    /// What the lookouts of surface ships hold, see lookouts.rs
+    pub lookouts: Lookouts,
+    /// Pickups and insertions of the mission, see rendezvous.rs
+    pub rendezvous: Vec<Rendezvous>,
+    /// Vessels to trail unseen, see shadowing.rs
+    pub shadows: Vec<Shadow>,
+    /// Scripted changes of the weather, see weather.rs
+    pub weather: Vec<WeatherChange>,
+    next_id: EntityId,
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            next_id: 1,
+            ..World::default()
+        }
+    }
+
+    /// Adds an entity, assigning it a fresh id
+    pub fn spawn(&mut self, mut entity: Entity) -> EntityId {
+        entity.id = self.next_id;
+        self.next_id += 1;
+        self.entities.insert(entity);
+        self.next_id - 1
+    }
+
+    pub fn entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(id)
+    }
+
+    pub fn entity_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(id)
+    }
+
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(id)
+    }
+
+    pub fn wake_of(&self, id: EntityId) -> Option<&Wake> {
+        self.wakes.iter().find(|w| w.owner == id)
+    }
+
+    /// Records `event`, and lets the crews it matters to react to it
+    pub fn emit(&mut self, event: Event) {
+        trace::event(Level::Debug, "events", &format!("{:?}", event), &[]);
+        morale::react(self, &event);
+        faction::react(self, &event);
+        self.events.push(TimedEvent {
+            time: self.time,
+            event,
+        });
+    }
+
+    /// Removes `amount` of hull integrity, reporting the loss of the entity
+    pub fn apply_damage(&mut self, id: EntityId, amount: f32) {
+        let entity = match self.entity_mut(id) {
+            Some(entity) if !entity.is_destroyed() => entity,
+            _ => return,
+        };
+        entity.hull = (entity.hull - amount).max(0.0);
+        machinery::shake(entity, amount);
+        // shock knocks out delicate equipment at half the rate of the hull
+        for sensor in entity.sensors.iter_mut() {
+            sensor.health = (sensor.health - amount / 2.0).max(0.0);
+        }
+        let destroyed = entity.is_destroyed();
+        casualties::wound(self, id, amount);
+        if destroyed {
+            self.entity_mut(id).unwrap().speed = 0.0;
+            self.emit(Event::Destroyed { entity: id });
+        }
+    }
+
+    /// Advances the world by `dt` seconds
+    pub fn step(&mut self, dt: f32) {
+        self.time += dt;
+        self.emissions.clear();
+        self.environment.losses.advance(self.time);
+        {
+            let _span = trace::span("weather", &[]);
+            weather::update(self);
+        }
+        let before: Vec<Point> = self.entities.iter().map(|e| e.position.clone()).collect();
+        let movement = trace::span("movement", &[]);
+        let decay = Tunable::TransientDecay.get(&self.environment.tuning) * dt;
+        for entity in self.entities.iter_mut() {
+            if entity.rig == Rig::UltraQuiet {
+                entity.speed = entity.speed.min(ULTRA_QUIET_MAX_SPEED);
+            }
+            if let Some(limit) = seakeeping::speed_limit(entity, &self.environment) {
+                entity.speed = entity.speed.min(limit);
+            }
+            let velocity = entity.velocity();
+            entity.position.x += velocity.x * dt;
+            entity.position.y += velocity.y * dt;
+            entity.transient = (entity.transient - decay).max(0.0);
+        }
+        self.report_zones(&before);
+        self.run_aground(&before);
+        self.touch_bottom();
+        drop(movement);
+        {
+            let _span = trace::span("diplomacy", &[]);
+            faction::update(self);
+        }
+        {
+            let _span = trace::span("wakes", &[]);
+            self.update_wakes(dt);
+        }
+        {
+            let _span = trace::span("decoys", &[]);
+            decoy::update(self, dt);
+        }
+        {
+            let _span = trace::span("hazards", &[]);
+            hazards::update(self, dt);
+        }
+        {
+            let _span = trace::span("torpedo", &[]);
+            torpedo::update(self, dt);
+        }
+        {
+            let _span = trace::span("radar", &[]);
+            radar::update(self, dt);
+        }
+        {
+            let _span = trace::span("gunnery", &[]);
+            gunnery::update(self, dt);
+        }
+        {
+            let _span = trace::span("asw", &[]);
+            asw::update(self, dt);
+        }
+        {
+            let _span = trace::span("helicopters", &[]);
+            helicopter::update(self, dt);
+        }
+        {
+            let _span = trace::span("vds", &[]);
+            vds::update(self, dt);
+        }
+        {
+            let _span = trace::span("ai", &[]);
+            ai::update(self, dt);
+        }
+        {
+            let _span = trace::span("escorts", &[]);
+            escort::update(self, dt);
+        }
+        {
+            let _span = trace::span("dive", &[]);
+            dive::update(self, dt);
+        }
+        {
+            let _span = trace::span("seakeeping", &[]);
+            seakeeping::keep_depth(self);
+        }
+        {
+            let _span = trace::span("cargo", &[]);
+            cargo::update(self, dt);
+        }
+        {
+            let _span = trace::span("stores", &[]);
+            stores::update(self, dt);
+        }
+        {
+            let _span = trace::span("machinery", &[]);
+            machinery::update(self, dt);
+        }
+        {
+            let _span = trace::span("atmosphere", &[]);
+            atmosphere::update(self, dt);
+        }
+        {
+            let _span = trace::span("casualties", &[]);
+            casualties::update(self, dt);
+        }
+        {
+            let _span = trace::span("morale", &[]);
+            morale::update(self, dt);
+        }
+        {
+            let _span = trace::span("traffic", &[]);
+            traffic::update(self, dt);
+        }
+        {
+            let _span = trace::span("hfdf", &[]);
+            hfdf::update(self, dt);
+        }
+        {
+            let _span = trace::span("wolfpack", &[]);
+            wolfpack::update(self);
+        }
+        {
+            let _span = trace::span("theater", &[]);
+            theater::update(self, dt);
+        }
+        {
+            let _span = trace::span("lookouts", &[]);
+            lookouts::update(self, dt);
+        }
+        {
+            let _span = trace::span("rendezvous", &[]);
+            rendezvous::update(self, dt);
+        }
+        {
+            let _span = trace::span("shadowing", &[]);
+            shadowing::update(self, dt);
+        }
+        let _span = trace::span("transient", &[]);
+        transient::update(self, dt);
+    }
+
+    /// Sends an active sonar pulse from `id`
+    pub fn ping(&mut self, id: EntityId) {
+        if let Some(entity) = self.entity(id) {
+            let emission = Emission {
+                source: id,
+                kind: EmissionKind::ActiveSonar,
+                position: entity.position.clone(),
+                depth: entity.depth,
+                heading: entity.heading,
+                pulse: entity.asw.as_ref().map(|a| a.pulse).unwrap_or_default(),
+            };
+            self.emissions.push(emission);
+        }
+    }
+
+    /// Zones containing `p`
+    pub fn zones_at<'a>(&'a self, p: &'a Point) -> impl Iterator<Item = &'a Zone> {
+        self.zones.iter().filter(move |z| z.contains(p))
+    }
+
+    /// Whether `entity` must keep out of `p`; zones it is already in do not
+    /// count, so that it can find its way out
+    pub fn forbidden(&self, entity: &Entity, p: &Point) -> bool {
+        let zoned = self
+            .zones
+            .iter()
+            .any(|z| z.forbids(entity) && z.contains(p) && !z.contains(&entity.position));
+        zoned || (self.coastline.is_land(p) && !self.coastline.is_land(&entity.position))
+    }
+
+    /// Waypoints taking `entity` to `to` around the zones it must keep out
+    /// of, None when there is no way there
+    pub fn route(&self, entity: &Entity, to: &Point) -> Option<Vec<Point>> {
+        route::plan(&entity.position, to, ROUTE_CELL, |p| {
+            self.forbidden(entity, p)
+        })
+    }
+
+    /// Stops the ships that ran onto land since `before` at the shore, and
+    /// the torpedoes there for good
+    fn run_aground(&mut self, before: &[Point]) {
+        if self.coastline.is_empty() {
+            return;
+        }
+        let mut events = Vec::new();
+        for (entity, old) in self.entities.iter_mut().zip(before) {
+            if !self.coastline.is_land(&entity.position) || self.coastline.is_land(old) {
+                continue;
+            }
+            if entity.kind == EntityKind::Torpedo {
+                events.push(Event::TorpedoRanOut { torpedo: entity.id });
+            } else {
+                entity.position = old.clone();
+                entity.speed = 0.0;
+                events.push(Event::RanAground { entity: entity.id });
+            }
+        }
+        for event in events {
+            if let Event::TorpedoRanOut { torpedo } = event {
+                self.remove(torpedo);
+            }
+            self.emit(event);
+        }
+    }
+
+    /// Meters of water at `p` now, None where the charts do not say
+    pub fn water_depth(&self, p: &Point) -> Option<f32> {
+        let charted = self
+            .zones_at(p)
+            .filter_map(|z| z.depth)
+            .min_by(|a, b| a.total_cmp(b))?;
+        Some(charted + self.environment.tide.height(self.time))
+    }
+
+    /// Deepest `entity` can go at its position leaving the keel clearance,
+    /// None where the charts do not limit it
+    pub fn safe_depth(&self, entity: &Entity) -> Option<f32> {
+        let water = self.water_depth(&entity.position)?;
+        Some(
+            (water - entity.draft() - Tunable::KeelClearance.get(&self.environment.tuning))
+                .max(0.0),
+        )
+    }
+
+    /// Scenario time from now on the tide lets `entity` cross `p` on the
+    /// surface with the keel clearance, None when it never does
+    pub fn next_passage(&self, entity: &Entity, p: &Point) -> Option<f32> {
+        let charted = self
+            .zones_at(p)
+            .filter_map(|z| z.depth)
+            .min_by(|a, b| a.total_cmp(b));
+        match charted {
+            Some(charted) => {
+                let needed =
+                    entity.draft() + Tunable::KeelClearance.get(&self.environment.tuning) - charted;
+                self.environment.tide.next_above(self.time, needed)
+            }
+            None => Some(self.time),
+        }
+    }
+
+    /// Stops the ships whose keels reached the bottom in shallow water; a
+    /// submerged submarine is pushed up instead, damaged unless it settles
+    /// slowly onto mud or sand
+    fn touch_bottom(&mut self) {
+        let mut events = Vec::new();
+        let mut damaged = Vec::new();
+        for entity in self.entities.iter() {
+            if entity.kind == EntityKind::Torpedo || entity.is_destroyed() {
+                continue;
+            }
+            let water = match self.water_depth(&entity.position) {
+                Some(water) => water,
+                None => continue,
+            };
+            if entity.depth + entity.draft() <= water {
+                continue;
+            }
+            if entity.depth > 0.0 {
+                let settles = entity.speed <= SETTLE_SPEED
+                    && self
+                        .environment
+                        .seafloor
+                        .at(&entity.position)
+                        .bottom
+                        .holds();
+                let depth = (water - entity.draft()).max(0.0);
+                damaged.push((entity.id, depth, !settles));
+            } else if entity.speed > 0.0 {
+                events.push(Event::RanAground { entity: entity.id });
+            }
+        }
+        for (id, depth, struck) in damaged {
+            let entity = self.entity_mut(id).unwrap();
+            entity.depth = depth;
+            entity.speed = 0.0;
+            self.emit(Event::TouchedBottom { entity: id });
+            if struck {
+                self.apply_damage(id, BOTTOM_DAMAGE);
+            }
+        }
+        for event in events {
+            if let Event::RanAground { entity } = event {
+                self.entity_mut(entity).unwrap().speed = 0.0;
+            }
+            self.emit(event);
+        }
+    }
+
+    /// Reports the ships that moved into a zone since `before`
+    fn report_zones(&mut self, before: &[Point]) {
+        let mut entered = Vec::new();
+        for (entity, old) in self.entities.iter().zip(before) {
+            if entity.kind == EntityKind::Torpedo {
+                continue;
+            }
+            for zone in &self.zones {
+                if zone.contains(&entity.position) && !zone.contains(old) {
+                    entered.push(Event::EnteredZone {
+                        entity: entity.id,
+                        zone: zone.name.clone(),
+                    });
+                }
+            }
+        }
+        for event in entered {
+            self.emit(event);
+        }
+    }
+
+    fn update_wakes(&mut self, dt: f32) {
+        for wake in self.wakes.iter_mut() {
+            wake.age(dt);
+        }
+        for entity in self.entities.iter().filter(|e| e.leaves_wake()) {
+            match self.wakes.iter_mut().find(|w| w.owner == entity.id) {
+                Some(wake) => wake.record(&entity.position, entity.speed),
+                None => {
+                    let mut wake = Wake::new(entity.id);
+                    wake.record(&entity.position, entity.speed);
+                    self.wakes.push(wake);
+                }
+            }
+        }
+        self.wakes.retain(|w| !w.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coastline::Shore;
+    use crate::environment::{Tide, TIDAL_PERIOD};
+    use crate::seafloor::{Bottom, Seafloor};
+    use crate::zone::ZoneKind;
+
+    #[test]
+    fn spawn_ids() {
+        let mut world = World::new();
+        let a = world.spawn(Entity::new(
+            "a",
+            EntityKind::Merchant,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        let b = world.spawn(Entity::new(
+            "b",
+            EntityKind::Merchant,
+            Point { x: 0.0, y: 0.0 },
+        ));
+        assert_ne!(a, b);
+        assert_eq!(world.entity(b).unwrap().name, "b");
+        assert!(world.remove(a).is_some());
+        assert!(world.entity(a).is_none());
+    }
+
+    #[test]
+    fn entering_zones() {
+        let mut world = World::new();
+        world.zones.push(Zone {
+            name: "patrol area".to_string(),
+            kind: crate::zone::ZoneKind::Patrol,
+            points: vec![
+                Point { x: 100.0, y: -50.0 },
+                Point { x: 200.0, y: -50.0 },
+                Point { x: 200.0, y: 50.0 },
+            ],
+            depth: None,
+            bottom: None,
+        });
+        let mut ship = Entity::new("a", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        ship.speed = 10.0;
+        let id = world.spawn(ship);
+        for _ in 0..30 {
+            world.step(1.0);
+        }
+        let entered: Vec<&Event> = world
+            .events
+            .iter()
+            .map(|e| &e.event)
+            .filter(|e| matches!(e, Event::EnteredZone { .. }))
+            .collect();
+        assert_eq!(
+            entered,
+            vec![&Event::EnteredZone {
+                entity: id,
+                zone: "patrol area".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn running_aground() {
+        let mut world = World::new();
+        world.coastline = Coastline::new(vec![Shore::new(vec![
+            Point {
+                x: 100.0,
+                y: -500.0,
+            },
+            Point {
+                x: 500.0,
+                y: -500.0,
+            },
+            Point { x: 500.0, y: 500.0 },
+            Point { x: 100.0, y: 500.0 },
+        ])
+        .unwrap()]);
+        let mut ship = Entity::new("a", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        ship.speed = 10.0;
+        let id = world.spawn(ship);
+        for _ in 0..20 {
+            world.step(1.0);
+        }
+        let ship = world.entity(id).unwrap();
+        assert_eq!(ship.speed, 0.0);
+        assert!(ship.position.x <= 100.0);
+        assert_eq!(
+            world.events.last().map(|e| &e.event),
+            Some(&Event::RanAground { entity: id })
+        );
+        assert!(world.forbidden(ship, &Point { x: 200.0, y: 0.0 }));
+    }
+
+    #[test]
+    fn falling_tide() {
+        let mut world = World::new();
+        world.environment.tide = Tide {
+            range: 4.0,
+            high_water: 0.0,
+        };
+        world.zones.push(Zone {
+            name: "harbor".to_string(),
+            kind: ZoneKind::Shallow,
+            points: vec![
+                Point {
+                    x: -1000.0,
+                    y: -1000.0,
+                },
+                Point {
+                    x: 1000.0,
+                    y: -1000.0,
+                },
+                Point {
+                    x: 1000.0,
+                    y: 1000.0,
+                },
+                Point {
+                    x: -1000.0,
+                    y: 1000.0,
+                },
+            ],
+            bottom: None,
+            depth: Some(6.0),
+        });
+        let mut ship = Entity::new("a", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        ship.speed = 0.02;
+        let id = world.spawn(ship);
+        assert_eq!(world.water_depth(&Point { x: 0.0, y: 0.0 }), Some(10.0));
+        let harbor = Point { x: 0.0, y: 0.0 };
+        assert_eq!(world.next_passage(world.entity(id).unwrap(), &harbor), None);
+        world.step(60.0);
+        assert!(world.entity(id).unwrap().speed > 0.0);
+        // 8 m draft in 6 m of water at low tide
+        for _ in 0..400 {
+            world.step(60.0);
+        }
+        assert_eq!(world.entity(id).unwrap().speed, 0.0);
+        assert!(world
+            .events
+            .iter()
+            .any(|e| e.event == Event::RanAground { entity: id }));
+        // a corvette waits for 3 m of tide
+        let corvette = Entity::new("b", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        let next = world.next_passage(&corvette, &harbor).unwrap();
+        assert!(next > world.time && next < TIDAL_PERIOD);
+        assert!((world.environment.tide.height(next) - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn settling_on_the_bottom() {
+        let settle = |bottom: Bottom, speed: f32| {
+            let mut world = World::new();
+            world.zones.push(Zone {
+                name: "bank".to_string(),
+                kind: ZoneKind::Shallow,
+                points: vec![
+                    Point {
+                        x: -1000.0,
+                        y: -1000.0,
+                    },
+                    Point {
+                        x: 1000.0,
+                        y: -1000.0,
+                    },
+                    Point {
+                        x: 1000.0,
+                        y: 1000.0,
+                    },
+                ],
+                depth: Some(40.0),
+                bottom: Some(bottom),
+            });
+            world.environment.seafloor = Seafloor::chart(&world.zones, Bottom::Sand);
+            let mut boat = Entity::new("a", EntityKind::Submarine, Point { x: 500.0, y: 0.0 });
+            boat.depth = 45.0;
+            boat.speed = speed;
+            let id = world.spawn(boat);
+            world.step(1.0);
+            assert!(world
+                .events
+                .iter()
+                .any(|e| e.event == Event::TouchedBottom { entity: id }));
+            world.entity(id).unwrap().hull
+        };
+        assert_eq!(settle(Bottom::Mud, 0.5), 1.0);
+        assert_eq!(settle(Bottom::Sand, 0.5), 1.0);
+        assert_eq!(settle(Bottom::Rock, 0.5), 1.0 - BOTTOM_DAMAGE);
+        assert_eq!(settle(Bottom::Mud, 4.0), 1.0 - BOTTOM_DAMAGE);
+    }
+
+    #[test]
+    fn step_moves() {
+        let mut world = World::new();
+        let mut ship = Entity::new("a", EntityKind::Merchant, Point { x: 0.0, y: 0.0 });
+        ship.heading = std::f32::consts::FRAC_PI_2;
+        ship.speed = 5.0;
+        let id = world.spawn(ship);
+        world.step(10.0);
+        let position = &world.entity(id).unwrap().position;
+        assert!(position.x.abs() < 0.001);
+        assert!((position.y - 50.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn damage_reaches_sensors() {
+        let mut world = World::new();
+        let mut boat = Entity::new("a", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.sensors
+            .push(Sensor::new(crate::sensors::SensorKind::HullSonar));
+        let id = world.spawn(boat);
+        world.apply_damage(id, 0.4);
+        let boat = world.entity(id).unwrap();
+        assert!((boat.hull - 0.6).abs() < 0.0001);
+        assert!((boat.sensors[0].health - 0.8).abs() < 0.0001);
+    }
+
+    #[test]
+    fn surface_ships_leave_wakes() {
+        let mut world = World::new();
+        let mut ship = Entity::new("a", EntityKind::Warship, Point { x: 0.0, y: 0.0 });
+        ship.speed = 10.0;
+        let ship = world.spawn(ship);
+        let mut sub = Entity::new("b", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        sub.depth = 50.0;
+        sub.speed = 10.0;
+        let sub = world.spawn(sub);
+        for _ in 0..10 {
+            world.step(5.0);
+        }
+        assert!(world.wake_of(ship).is_some());
+        assert!(world.wake_of(sub).is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::messages::Catalog;
+use crate::physics::Point;
+use crate::world::{EntityId, World};
+
+/// Deepest reading of an expendable bathythermograph, in meters
+pub const XBT_MAX_DEPTH: f32 = 760.0;
+/// Meters between two samples of the readout
+const SAMPLE_SPACING: f32 = 10.0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum XbtError {
+    NoneLeft,
+}
+
+impl XbtError {
+    /// The error as written for the player
+    pub fn describe(&self, messages: &Catalog) -> String {
+        match self {
+            XbtError::NoneLeft => messages.get("error-no-xbts").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for XbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(&Catalog::default()))
+    }
+}
+
+impl std::error::Error for XbtError {}
+
+/// Sound speed profile measured by one probe, ready to be charted
+#[derive(Debug, PartialEq, Clone)]
+pub struct XbtReading {
+    pub time: f32,
+    pub position: Point,
+    /// (depth in meters, sound speed in m/s), top down
+    pub samples: Vec<(f32, f32)>,
+    pub layer_depth: Option<f32>,
+    /// Sound speed gradient below the layer (or the surface), m/s per meter
+    pub gradient_below: f32,
+}
+
+/// Drops a probe from `boat`, using up one XBT
+pub fn launch(world: &mut World, boat: EntityId) -> Result<XbtReading, XbtError> {
+    let time = world.time;
+    let profile = world.environment.sound_speed.clone();
+    let entity = world.entity_mut(boat).ok_or(XbtError::NoneLeft)?;
+    if entity.xbts == 0 {
+        return Err(XbtError::NoneLeft);
+    }
+    entity.xbts -= 1;
+    let samples = (0..)
+        .map(|i| i as f32 * SAMPLE_SPACING)
+        .take_while(|d| *d <= XBT_MAX_DEPTH)
+        .map(|d| (d, profile.speed_at(d)))
+        .collect();
+    let layer_depth = profile.layer_depth();
+    Ok(XbtReading {
+        time,
+        position: entity.position.clone(),
+        samples,
+        layer_depth,
+        gradient_below: profile.gradient_below(layer_depth.unwrap_or(0.0)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Entity, EntityKind};
+
+    #[test]
+    fn launch1() {
+        let mut world = World::new();
+        let mut boat = Entity::new("a", EntityKind::Submarine, Point { x: 0.0, y: 0.0 });
+        boat.xbts = 1;
+        let boat = world.spawn(boat);
+        let reading = launch(&mut world, boat).unwrap();
+        assert_eq!(reading.samples.len(), 77);
+        assert_eq!(reading.samples[3], (30.0, 1501.0));
+        assert_eq!(reading.layer_depth, Some(60.0));
+        assert!(reading.gradient_below < 0.0);
+        assert_eq!(launch(&mut world, boat), Err(XbtError::NoneLeft));
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{ConfigError, Section};
+use crate::physics::Point;
+use crate::seafloor::Bottom;
+use crate::world::{Entity, EntityKind};
+
+// #############################
+// #          ZONES            #
+// #############################
+
+// Named areas of the map, given in a scenario as "[zone.<name>]" sections:
+//
+// [zone.Rockall Bank]
+// kind = shallow          # patrol, exclusion, minefield, shallow, land or port
+// points = 0 0, 8000 0, 8000 5000, 0 5000   # x y corners in meters
+// depth = 12              # optional, charted depth at low water, meters
+// bottom = mud            # optional, mud, sand or rock, see seafloor.rs
+//
+// Where a zone has a charted depth, the water is that deep plus the tide
+// (see environment.rs); keels deeper than that touch the bottom.
+//
+// Ships report entering a zone as an event, which is what objectives
+// check; the AI keeps out of the zones that are forbidden to its boat and
+// routes are planned around them (see route.rs).
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ZoneKind {
+    Patrol,
+    Exclusion,
+    Minefield,
+    /// Too shallow for a submarine to pass submerged
+    Shallow,
+    Land,
+    /// Harbour where ships refit, see stores.rs
+    Port,
+}
+
+impl fmt::Display for ZoneKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ZoneKind::Patrol => "patrol",
+            ZoneKind::Exclusion => "exclusion",
+            ZoneKind::Minefield => "minefield",
+            ZoneKind::Shallow => "shallow",
+            ZoneKind::Land => "land",
+            ZoneKind::Port => "port",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ZoneKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ZoneKind, String> {
+        match s {
+            "patrol" => Ok(ZoneKind::Patrol),
+            "exclusion" => Ok(ZoneKind::Exclusion),
+            "minefield" => Ok(ZoneKind::Minefield),
+            "shallow" => Ok(ZoneKind::Shallow),
+            "land" => Ok(ZoneKind::Land),
+            "port" => Ok(ZoneKind::Port),
+            _ => Err(format!("unknown zone kind '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Zone {
+    pub name: String,
+    pub kind: ZoneKind,
+    /// Corners in order, the last one joining back to the first
+    pub points: Vec<Point>,
+    /// Meters of water at low tide
+    pub depth: Option<f32>,
+    /// What the seafloor is made of
+    pub bottom: Option<Bottom>,
+}
+
+/// Whether `p` is inside the polygon with corners `points` (even-odd rule)
+pub fn polygon_contains(points: &[Point], p: &Point) -> bool {
+    let mut inside = false;
+    for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+        if (a.y > p.y) != (b.y > p.y) {
+            let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if p.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Reads "x y, x y, ..." corners, None unless there are at least three
+fn parse_points(value: &str) -> Option<Vec<Point>> {
+    let mut points = Vec::new();
+    for pair in value.split(',') {
+        let numbers: Vec<&str> = pair.split_whitespace().collect();
+        match numbers.as_slice() {
+            [x, y] => points.push(Point {
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+            }),
+            _ => return None,
+        }
+    }
+    if points.len() < 3 {
+        return None;
+    }
+    Some(points)
+}
+
+impl Zone {
+    pub fn read(name: &str, section: &Section) -> Result<Zone, ConfigError> {
+        let value: String = section.parse("points")?;
+        let points = parse_points(&value).ok_or_else(|| ConfigError::Invalid {
+            section: section.name.clone(),
+            key: "points".to_string(),
+            value: value.clone(),
+        })?;
+        Ok(Zone {
+            name: name.to_string(),
+            kind: section.parse("kind")?,
+            points,
+            depth: section.parse_optional("depth")?,
+            bottom: section.parse_optional("bottom")?,
+        })
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (&Point, &Point)> {
+        self.points.iter().zip(self.points.iter().cycle().skip(1))
+    }
+
+    /// Whether `p` is inside the zone
+    pub fn contains(&self, p: &Point) -> bool {
+        polygon_contains(&self.points, p)
+    }
+
+    /// Closest point of the zone's boundary to `p`, and its distance
+    pub fn closest_edge(&self, p: &Point) -> (Point, f32) {
+        self.edges()
+            .map(|(a, b)| {
+                let closest = p.closest_on_segment(a, b);
+                let distance = closest.distance_to(p);
+                (closest, distance)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap()
+    }
+
+    /// Whether `entity` must stay out of the zone
+    pub fn forbids(&self, entity: &Entity) -> bool {
+        match self.kind {
+            ZoneKind::Patrol | ZoneKind::Port => false,
+            ZoneKind::Exclusion | ZoneKind::Minefield | ZoneKind::Land => true,
+            ZoneKind::Shallow => entity.kind == EntityKind::Submarine,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn square() -> Zone {
+        Zone {
+            name: "box".to_string(),
+            kind: ZoneKind::Exclusion,
+            points: vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+                Point { x: 100.0, y: 100.0 },
+                Point { x: 0.0, y: 100.0 },
+            ],
+            depth: None,
+            bottom: None,
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let zone = square();
+        assert!(zone.contains(&Point { x: 50.0, y: 50.0 }));
+        assert!(!zone.contains(&Point { x: 150.0, y: 50.0 }));
+        assert!(!zone.contains(&Point { x: 50.0, y: -1.0 }));
+    }
+
+    #[test]
+    fn closest_edge() {
+        let zone = square();
+        let (point, distance) = zone.closest_edge(&Point { x: 50.0, y: 130.0 });
+        assert_eq!(point, Point { x: 50.0, y: 100.0 });
+        assert_eq!(distance, 30.0);
+        let (_, distance) = zone.closest_edge(&Point { x: 90.0, y: 50.0 });
+        assert_eq!(distance, 10.0);
+    }
+
+    #[test]
+    fn read() {
+        let config =
+            Config::parse("[zone.bank]\nkind = shallow\npoints = 0 0, 8000 0, 8000 5000, 0 5000")
+                .unwrap();
+        let section = config.section("zone.bank").unwrap();
+        let zone = Zone::read("bank", section).unwrap();
+        assert_eq!(zone.kind, ZoneKind::Shallow);
+        assert_eq!(zone.points.len(), 4);
+        let config = Config::parse("[zone.bad]\nkind = patrol\npoints = 0 0, 10").unwrap();
+        assert!(Zone::read("bad", config.section("zone.bad").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+// Runs the canned scenarios of tests/scenarios headlessly and compares how
+// they end with the snapshots stored in tests/snapshots (see snapshot.rs).
+// After a deliberate change of gameplay, store new snapshots with
+//
+// UPDATE_SNAPSHOTS=1 cargo test --test scenarios
+//
+// and review their diff.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use subsim::config::Config;
+use subsim::savefile;
+use subsim::scenario::Scenario;
+use subsim::snapshot::{Snapshot, Tolerance};
+
+fn path(directory: &str, name: &str, extension: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", directory, name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension(extension)
+}
+
+fn check(name: &str, seconds: u32) {
+    let scenario = Scenario::load(path("scenarios", name, "cfg")).unwrap();
+    let mut sim = scenario.build().unwrap();
+    for _ in 0..seconds {
+        sim.step(1.0);
+    }
+    let actual = Snapshot::take(&sim);
+    let stored = path("snapshots", name, "snap");
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&stored, actual.to_string()).unwrap();
+        return;
+    }
+    let expected: Snapshot = fs::read_to_string(&stored)
+        .unwrap_or_else(|e| panic!("{}: {}", stored.display(), e))
+        .parse()
+        .unwrap();
+    let differences = expected.diff(&actual, &Tolerance::default());
+    assert!(
+        differences.is_empty(),
+        "{} changed:\n{}",
+        name,
+        differences.join("\n")
+    );
+}
+
+#[test]
+fn convoy() {
+    check("convoy", 1800);
+}
+
+#[test]
+fn duel() {
+    check("duel", 1800);
+}
+
+#[test]
+fn escort() {
+    check("escort", 1800);
+}
+
+#[test]
+fn save_and_resume() {
+    let config = Config::load(path("scenarios", "convoy", "cfg")).unwrap();
+    let mut sim = Scenario::from_config(&config).unwrap().build().unwrap();
+    for _ in 0..600 {
+        sim.step(1.0);
+    }
+    let packed = savefile::pack(&savefile::capture(&config, &sim).to_string());
+    let saved = Config::parse(&savefile::unpack(&packed).unwrap()).unwrap();
+    let resumed = Scenario::from_config(&saved).unwrap().build().unwrap();
+    for entity in sim.world.entities.iter().filter(|e| e.class.is_some()) {
+        let again = resumed.world.entities.by_name(&entity.name).unwrap();
+        assert!(again.position.distance_to(&entity.position) < 0.01);
+        assert!((again.speed - entity.speed).abs() < 0.01);
+        assert_eq!(again.hull, entity.hull);
+    }
+}